@@ -0,0 +1,159 @@
+//! Per-plant locks against the single fleet lock they replaced, with 500
+//! plants: an update tick while /metrics scrapes run alongside, and one
+//! scrape on its own.
+//!
+//! Both sides run the real update (`AppState::set_data`) and the real
+//! /metrics handler. The fleet lock side wraps them the way the code before
+//! per-plant locks did: an update holds the fleet write lock for the whole
+//! `set_data`, and a scrape clones the fleet under the read lock first.
+//!
+//!     cargo bench --bench plant_state
+
+use std::hint::black_box;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::RwLock;
+
+use axum::extract::State;
+use axum::response::IntoResponse;
+use chrono_tz::Tz;
+use criterion::{criterion_group, criterion_main, Criterion};
+use solar_panel_sim::config::PhaseImbalanceConfig;
+use solar_panel_sim::controllers::power_controller::prometheus_metrics;
+use solar_panel_sim::models::power::{PlantData, SimulationData, WeatherSource};
+use solar_panel_sim::services::pv_string::StringLayout;
+use solar_panel_sim::shared_state::{AppState, PlantRating};
+
+const PLANTS: usize = 500;
+/// Update tasks running at once, as on a multi-core runtime
+const UPDATERS: usize = 4;
+/// Clients scraping while the plants update
+const SCRAPERS: usize = 2;
+
+/// How the fleet's telemetry is guarded around the real code paths.
+enum Locking {
+    /// Each plant behind its own lock, as `AppState` keeps it now.
+    PerPlant,
+    /// Every plant behind one lock, as before.
+    Fleet(RwLock<()>),
+}
+
+struct Fleet {
+    state:   AppState,
+    locking: Locking,
+    ids:     Vec<String>,
+    rating:  PlantRating,
+    sample:  SimulationData,
+}
+
+impl Fleet {
+    fn new(locking: Locking) -> Self {
+        let state = AppState::new(true);
+        let ids: Vec<String> = (0..PLANTS).map(|i| format!("plant_{}", i)).collect();
+        for id in &ids {
+            state.plant_data.insert(id, PlantData::default());
+        }
+        let rating = PlantRating {
+            nominal_power_kw: 100.0, max_kva: 100.0, max_ac_kw: 100.0, timezone: Tz::UTC,
+            grid_voltage_v: 230.0, phase_imbalance: PhaseImbalanceConfig::default(),
+        };
+        let point = StringLayout::sized_for(100.0).operating_point(800.0, 45.0, 1.0);
+        let sample = SimulationData {
+            timestamp:             chrono::Utc::now(),
+            power_kw:              point.power_kw(),
+            temperature_c:         45.0,
+            ambient_temp_c:        25.0,
+            weather_code:          0,
+            is_day:                true,
+            poa_irradiance_w_m2:   800.0,
+            ghi_w_m2:              800.0,
+            dni_w_m2:              0.0,
+            dhi_w_m2:              800.0,
+            rear_irradiance_w_m2:  0.0,
+            cloud_factor:          1.0,
+            solar_elevation_deg:   50.0,
+            solar_azimuth_deg:     180.0,
+            source:                WeatherSource::Model,
+            wind_speed_m_s:        3.0,
+            relative_humidity_pct: 50.0,
+            soiling_factor:        1.0,
+            rain_mm_h:             0.0,
+            snow_cover_factor:     0.0,
+            degradation_factor:    1.0,
+            tracker_stowed:        false,
+            mppt:                  vec![point],
+        };
+        Self { state, locking, ids, rating, sample }
+    }
+
+    /// One plant's update cycle.
+    fn update(&self, plant_id: &str) {
+        let _fleet = match &self.locking {
+            Locking::PerPlant  => None,
+            Locking::Fleet(lock) => Some(lock.write().unwrap()),
+        };
+        self.state.set_data(plant_id, &self.rating, &self.sample, 0.0);
+    }
+
+    /// One /metrics scrape, rendered to the response.
+    fn scrape(&self, runtime: &tokio::runtime::Runtime) -> axum::response::Response {
+        let _fleet = match &self.locking {
+            Locking::PerPlant  => None,
+            Locking::Fleet(lock) => {
+                let fleet = lock.read().unwrap();
+                black_box(self.state.get_all_data());
+                Some(fleet)
+            }
+        };
+        runtime.block_on(prometheus_metrics(State(self.state.clone()))).into_response()
+    }
+
+    /// Update every plant once, split across the updaters.
+    fn tick(&self) {
+        std::thread::scope(|s| {
+            for chunk in self.ids.chunks(self.ids.len().div_ceil(UPDATERS)) {
+                s.spawn(move || chunk.iter().for_each(|id| self.update(id)));
+            }
+        });
+    }
+}
+
+fn runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_current_thread().build().unwrap()
+}
+
+fn update_tick_with_scrapes(c: &mut Criterion) {
+    let mut group = c.benchmark_group("update_tick_500_plants_while_scraping");
+    fn run(b: &mut criterion::Bencher, fleet: &Fleet) {
+        let stop = AtomicBool::new(false);
+        std::thread::scope(|s| {
+            for _ in 0..SCRAPERS {
+                s.spawn(|| {
+                    let runtime = runtime();
+                    while !stop.load(Ordering::Relaxed) { black_box(fleet.scrape(&runtime)); }
+                });
+            }
+            b.iter(|| fleet.tick());
+            stop.store(true, Ordering::Relaxed);
+        });
+    }
+    let global = Fleet::new(Locking::Fleet(RwLock::new(())));
+    let per_plant = Fleet::new(Locking::PerPlant);
+    group.bench_function("global_lock", |b| run(b, &global));
+    group.bench_function("per_plant_locks", |b| run(b, &per_plant));
+    group.finish();
+}
+
+fn scrape(c: &mut Criterion) {
+    let mut group = c.benchmark_group("scrape_500_plants");
+    let runtime = runtime();
+    let global = Fleet::new(Locking::Fleet(RwLock::new(())));
+    let per_plant = Fleet::new(Locking::PerPlant);
+    global.tick();
+    per_plant.tick();
+    group.bench_function("clone_all", |b| b.iter(|| black_box(global.scrape(&runtime))));
+    group.bench_function("projection", |b| b.iter(|| black_box(per_plant.scrape(&runtime))));
+    group.finish();
+}
+
+criterion_group!(benches, update_tick_with_scrapes, scrape);
+criterion_main!(benches);
@@ -0,0 +1,16 @@
+//! Stamps the build with the git commit it was built from (`GIT_HASH`),
+//! reported by `/health`. A `GIT_HASH` set in the environment wins, for
+//! builds without the repository such as the Docker image.
+
+use std::process::Command;
+
+fn main() {
+    let hash = std::env::var("GIT_HASH").ok().filter(|h| !h.is_empty()).or_else(|| {
+        let out = Command::new("git").args(["rev-parse", "--short=12", "HEAD"]).output().ok()?;
+        out.status.success().then(|| String::from_utf8_lossy(&out.stdout).trim().to_string())
+    });
+    println!("cargo:rustc-env=GIT_HASH={}", hash.as_deref().unwrap_or("unknown"));
+    println!("cargo:rerun-if-env-changed=GIT_HASH");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs");
+}
@@ -1,4 +1,5 @@
-use utoipa::OpenApi;
+use utoipa::{Modify, OpenApi};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, Http, HttpAuthScheme, SecurityScheme};
 use crate::controllers::power_controller;
 use crate::models::power;
 use crate::config;
@@ -7,21 +8,111 @@ use crate::config;
 #[openapi(
     paths(
         power_controller::list_plants,
+        power_controller::create_plant,
+        power_controller::update_plant,
+        power_controller::delete_plant,
         power_controller::get_plant_power,
+        power_controller::get_plant_sun,
+        power_controller::get_plant_weather,
+        power_controller::get_expected_energy,
         power_controller::get_global_power,
+        power_controller::get_plant_history,
+        power_controller::get_global_history,
+        power_controller::get_plant_forecast,
+        power_controller::get_fleet_forecast,
+        power_controller::get_plant_report,
+        power_controller::get_fleet_report,
+        power_controller::control_plant,
+        power_controller::get_reactive_power,
+        power_controller::set_reactive_power,
+        power_controller::inject_grid_event,
+        power_controller::inject_isolation_fault,
+        power_controller::inject_fault,
+        power_controller::withdraw_fault,
+        power_controller::get_fault_catalog,
+        power_controller::clean_panels,
+        power_controller::damage_plant,
+        power_controller::repair_plant,
+        power_controller::enable_plant,
+        power_controller::disable_plant,
+        power_controller::list_scenarios,
+        power_controller::create_scenario,
+        power_controller::cancel_scenario,
+        power_controller::get_plant_alarms,
+        power_controller::get_all_alarms,
+        power_controller::acknowledge_alarm,
+        power_controller::get_events,
         power_controller::get_modbus_info,
+        power_controller::get_state_snapshot,
+        power_controller::load_state_snapshot,
         power_controller::get_offline_mode,
-        power_controller::set_offline_mode
+        power_controller::set_offline_mode,
+        power_controller::get_simulation_seed,
+        power_controller::set_simulation_seed,
+        power_controller::get_sim_clock,
+        power_controller::set_sim_clock,
+        power_controller::stream_telemetry
     ),
     components(
         schemas(
             power::PlantData,
+            power::MpptData,
+            power::SunInfo,
+            power::Daylight,
+            power::PlantWeather,
+            power::WeatherSource,
+            power::ExpectedEnergy,
+            power::EnergyPeriod,
+            power::PowerForecast,
+            power::ForecastPoint,
+            power::ForecastSource,
+            power::ProductionReport,
+            power::FleetProductionReport,
+            power::AlarmCounts,
             config::PlantConfig,
-            power::ModbusInfo
+            config::ScenarioConfig,
+            crate::services::scenarios::Scenario,
+            crate::services::history::HistoryPoint,
+            crate::services::history::Resolution,
+            crate::services::history::FleetHistoryPoint,
+            power_controller::GroupBy,
+            power::ModbusInfo,
+            power_controller::ControlBody,
+            power::PlantControlResponse,
+            power::ControlInterface,
+            power::ReactivePowerControl,
+            power::ReactivePowerMode,
+            power_controller::ReactivePowerBody,
+            power_controller::GridEventBody,
+            power_controller::IsolationFaultBody,
+            power_controller::FaultBody,
+            power::FaultCatalogEntry,
+            power::AlarmSeverity,
+            power::Alarm,
+            power_controller::AlarmAckBody,
+            power::Event,
+            power::EventKind,
+            power_controller::DamageBody,
+            power_controller::MaintenanceBody,
+            power_controller::SimulationSeedBody,
+            power_controller::SimClockBody
         )
     ),
+    modifiers(&SecurityAddon),
+    security(("bearer" = []), ("api_key" = [])),
     tags(
         (name = "solar-panel-sim", description = "Solar Panel Simulation API")
     )
 )]
 pub struct ApiDoc;
+
+/// The two ways to present an API key when `auth` is configured.
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme("bearer", SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)));
+        components.add_security_scheme("api_key", SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-API-Key"))));
+    }
+}
@@ -0,0 +1,143 @@
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderMap, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::config::{ApiRole, AuthConfig};
+
+/// Header carrying a key for clients that cannot send `Authorization`
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Why a request was turned away.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Denied {
+    /// No key, or one that is not configured (401)
+    Unauthenticated,
+    /// A read-only key on a route that changes the simulation (403)
+    Forbidden,
+}
+
+/// Whether `method` changes the simulation: every POST, PUT, PATCH and
+/// DELETE route (settings, plant control, faults, snapshots) needs `control`.
+fn mutates(method: &Method) -> bool {
+    !matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+/// Key of the request: a Bearer token, the `X-API-Key` header or, for the
+/// WebSocket and Server-Sent Events streams browsers open without custom
+/// headers, an `api_key` query parameter.
+fn presented_key<'a>(headers: &'a HeaderMap, path: &str, query: Option<&'a str>) -> Option<&'a str> {
+    let bearer = headers.get(header::AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::trim);
+    let header_key = || headers.get(API_KEY_HEADER).and_then(|v| v.to_str().ok()).map(str::trim);
+    let streaming = path.starts_with("/ws/") || path.starts_with("/api/stream/");
+    let query_key = || query.filter(|_| streaming)
+        .and_then(|q| q.split('&').find_map(|pair| pair.strip_prefix("api_key=")));
+    bearer.or_else(header_key).or_else(query_key).filter(|k| !k.is_empty())
+}
+
+/// Compare without an early exit, so timing does not reveal how much of a key matched.
+fn same_key(a: &str, b: &str) -> bool {
+    a.len() == b.len() && a.bytes().zip(b.bytes()).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// The role the request is let through with, or why it is not.
+pub fn authorize(auth: &AuthConfig, method: &Method, path: &str, query: Option<&str>, headers: &HeaderMap) -> Result<Option<ApiRole>, Denied> {
+    if (path == "/health" && auth.public_health) || (path == "/metrics" && auth.public_metrics) {
+        return Ok(None);
+    }
+    let presented = presented_key(headers, path, query).ok_or(Denied::Unauthenticated)?;
+    let key = auth.keys.iter().find(|k| same_key(&k.key, presented)).ok_or(Denied::Unauthenticated)?;
+    if key.role == ApiRole::Read && mutates(method) {
+        return Err(Denied::Forbidden);
+    }
+    Ok(Some(key.role))
+}
+
+/// Middleware in front of /api, /ws, /health and /metrics when `auth` is
+/// configured: 401 without a valid key, 403 for a read key on a mutating route.
+pub async fn require_api_key(State(auth): State<Arc<AuthConfig>>, request: Request, next: Next) -> Response {
+    let uri = request.uri();
+    match authorize(&auth, request.method(), uri.path(), uri.query(), request.headers()) {
+        Ok(_) => next.run(request).await,
+        Err(Denied::Unauthenticated) => (
+            StatusCode::UNAUTHORIZED,
+            [(header::WWW_AUTHENTICATE, "Bearer")],
+            Json(serde_json::json!({ "error": "missing or invalid API key" })),
+        ).into_response(),
+        Err(Denied::Forbidden) => (
+            StatusCode::FORBIDDEN,
+            Json(serde_json::json!({ "error": "this API key is read-only: a control key is needed" })),
+        ).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ApiKeyConfig;
+
+    fn auth(public_health: bool) -> AuthConfig {
+        let key = |key: &str, role| ApiKeyConfig { key: key.to_string(), role, name: None };
+        AuthConfig {
+            keys: vec![key("reader", ApiRole::Read), key("operator", ApiRole::Control)],
+            public_health,
+            public_metrics: false,
+        }
+    }
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    #[test]
+    fn keys_are_checked_against_their_role() {
+        let auth = auth(false);
+        let check = |method: Method, path: &str, h: HeaderMap| authorize(&auth, &method, path, None, &h);
+
+        assert_eq!(check(Method::GET, "/api/plants", headers(&[])), Err(Denied::Unauthenticated));
+        assert_eq!(check(Method::GET, "/api/plants", headers(&[("authorization", "Bearer nope")])), Err(Denied::Unauthenticated));
+        assert_eq!(check(Method::GET, "/api/plants", headers(&[("authorization", "Bearer reader")])), Ok(Some(ApiRole::Read)));
+        assert_eq!(check(Method::GET, "/api/plants", headers(&[("x-api-key", "operator")])), Ok(Some(ApiRole::Control)));
+        assert_eq!(check(Method::POST, "/api/settings/offline-mode", headers(&[("x-api-key", "reader")])), Err(Denied::Forbidden));
+        assert_eq!(check(Method::DELETE, "/api/plants/plant_1", headers(&[("authorization", "Bearer operator")])), Ok(Some(ApiRole::Control)));
+        // A prefix of a key is no key
+        assert_eq!(check(Method::GET, "/api/plants", headers(&[("x-api-key", "read")])), Err(Denied::Unauthenticated));
+    }
+
+    #[test]
+    fn health_may_be_public_and_streams_take_the_key_in_the_query() {
+        let closed = auth(false);
+        let open = auth(true);
+        assert_eq!(authorize(&closed, &Method::GET, "/health", None, &HeaderMap::new()), Err(Denied::Unauthenticated));
+        assert_eq!(authorize(&open, &Method::GET, "/health", None, &HeaderMap::new()), Ok(None));
+        assert_eq!(authorize(&open, &Method::GET, "/metrics", None, &HeaderMap::new()), Err(Denied::Unauthenticated));
+
+        let query = Some("plants=plant_1&api_key=reader");
+        assert_eq!(authorize(&closed, &Method::GET, "/ws/telemetry", query, &HeaderMap::new()), Ok(Some(ApiRole::Read)));
+        assert_eq!(authorize(&closed, &Method::GET, "/api/stream/telemetry", query, &HeaderMap::new()), Ok(Some(ApiRole::Read)));
+        assert_eq!(authorize(&closed, &Method::GET, "/api/plants", query, &HeaderMap::new()), Err(Denied::Unauthenticated));
+    }
+
+    #[test]
+    fn the_openapi_document_declares_both_schemes() {
+        use utoipa::OpenApi;
+        let doc = serde_json::to_value(crate::api_docs::ApiDoc::openapi()).unwrap();
+        let schemes = &doc["components"]["securitySchemes"];
+        assert_eq!(schemes["bearer"]["scheme"], "bearer");
+        assert_eq!(schemes["api_key"]["in"], "header");
+        assert_eq!(schemes["api_key"]["name"], "X-API-Key");
+        assert_eq!(doc["security"].as_array().map(Vec::len), Some(2));
+    }
+}
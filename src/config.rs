@@ -1,10 +1,93 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::ops::Range;
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc, Weekday};
+use chrono_tz::Tz;
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
 fn default_offline_mode() -> bool { false }
+fn default_bind_address() -> String { "0.0.0.0".to_string() }
 fn default_mqtt_topic_prefix() -> String { "solar".to_string() }
 fn default_mqtt_port() -> u16 { 1883 }
 fn default_mqtt_enabled() -> bool { false }
+fn default_modbus_max_connections() -> usize { 64 }
+fn default_modbus_idle_timeout_s() -> u64 { 300 }
+fn default_serial_baud_rate() -> u32 { 9600 }
+fn default_serial_stop_bits() -> u8 { 1 }
+fn default_serial_slave_id() -> u8 { 1 }
+fn default_update_interval_s() -> f64 { 5.0 }
+fn default_plant_enabled() -> bool { true }
+fn default_modbus_tls_port() -> u16 { 802 }
+fn default_tracker_max_elevation_deg() -> f64 { 90.0 }
+fn default_bifaciality() -> f64 { 0.7 }
+fn default_panel_height_m() -> f64 { 1.0 }
+fn default_electrical_shading_factor() -> f64 { 1.0 }
+fn default_degradation_pct_per_year() -> f64 { 0.5 }
+fn default_freq_watt_threshold_hz() -> f64 { 50.2 }
+fn default_freq_watt_droop_pct() -> f64 { 5.0 }
+fn default_volt_var_points() -> Vec<[f64; 2]> {
+    vec![[0.92, 44.0], [0.98, 0.0], [1.02, 0.0], [1.08, -44.0]]
+}
+fn default_grid_nominal_voltage_v() -> f64 { 230.0 }
+fn default_grid_nominal_frequency_hz() -> f64 { 50.0 }
+fn default_grid_network() -> String { "default".to_string() }
+fn default_voltage_imbalance_pct() -> f64 { 1.0 }
+fn default_current_imbalance_pct() -> f64 { 2.0 }
+fn default_undervoltage_v() -> f64 { 207.0 }
+fn default_undervoltage_trip_s() -> f64 { 1.5 }
+fn default_overvoltage_v() -> f64 { 253.0 }
+fn default_overvoltage_trip_s() -> f64 { 3.0 }
+fn default_underfrequency_hz() -> f64 { 49.5 }
+fn default_overfrequency_hz() -> f64 { 50.5 }
+fn default_frequency_trip_s() -> f64 { 0.1 }
+fn default_rocof_hz_s() -> f64 { 1.0 }
+fn default_reconnect_delay_s() -> f64 { 60.0 }
+fn default_aod_scale() -> f64 { 1.0 }
+fn default_iam_b0() -> f64 { 0.05 }
+fn default_voltage_alarm_delay_s() -> f64 { 10.0 }
+fn default_voltage_alarm_hysteresis_v() -> f64 { 2.0 }
+fn default_frequency_alarm_delay_s() -> f64 { 5.0 }
+fn default_frequency_alarm_hysteresis_hz() -> f64 { 0.05 }
+fn default_overtemperature_c() -> f64 { 80.0 }
+fn default_overtemperature_delay_s() -> f64 { 30.0 }
+fn default_overtemperature_hysteresis_c() -> f64 { 5.0 }
+fn default_isolation_mohm() -> f64 { 1.0 }
+fn default_isolation_hysteresis_mohm() -> f64 { 0.5 }
+fn default_mppt_deviation_pct() -> f64 { 25.0 }
+fn default_mppt_deviation_delay_s() -> f64 { 600.0 }
+fn default_mppt_deviation_hysteresis_pct() -> f64 { 5.0 }
+fn default_persistence_interval_s() -> u64 { 60 }
+fn default_history_retention_h() -> f64 { 24.0 }
+fn default_soiling_rate_pct_per_day() -> f64 { 0.3 }
+fn default_cloud_correlation_km() -> f64 { 20.0 }
+fn default_cloud_persistence() -> f64 { 0.7 }
+fn default_simulation_speed() -> f64 { 1.0 }
+fn default_poa_sigma_pct() -> f64 { 2.0 }
+fn default_voltage_sigma_v() -> f64 { 0.3 }
+fn default_frequency_sigma_hz() -> f64 { 0.01 }
+fn default_dropout_probability() -> f64 { 0.001 }
+fn default_dropout_cycles() -> u32 { 12 }
+fn default_poa_drift_pct_per_year() -> f64 { -1.0 }
+fn default_module_voc_v() -> f64 { 49.5 }
+fn default_module_isc_a() -> f64 { 10.4 }
+fn default_module_vmp_v() -> f64 { 41.5 }
+fn default_module_imp_a() -> f64 { 9.64 }
+fn default_voc_temp_coeff_pct_per_c() -> f64 { -0.20 }
+fn default_isc_temp_coeff_pct_per_c() -> f64 { 0.05 }
+fn default_rate_limit_enabled() -> bool { true }
+fn default_rate_limit_window_s() -> u64 { 1 }
+fn default_rate_limit_read() -> u32 { 50 }
+fn default_rate_limit_mutation() -> u32 { 10 }
+fn default_rate_limit_metrics() -> u32 { 5 }
+fn default_cors_methods() -> Vec<String> {
+    ["GET", "POST", "PUT", "PATCH", "DELETE"].map(String::from).to_vec()
+}
+fn default_cors_headers() -> Vec<String> {
+    ["content-type", "authorization", "x-api-key"].map(String::from).to_vec()
+}
+fn default_compression_min_size_bytes() -> u16 { 1024 }
+fn default_compression_enabled() -> bool { true }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
@@ -15,16 +98,313 @@ pub struct Config {
     pub plants: Vec<PlantConfig>,
     #[serde(default)]
     pub mqtt: MqttConfig,
+    #[serde(default)]
+    pub simulation: SimulationConfig,
+    /// Irradiance overrides (eclipses, rehearsals) loaded at startup
+    #[serde(default)]
+    pub scenarios: Vec<ScenarioConfig>,
+    /// State snapshot carried across restarts; unset = every start is fresh
+    #[serde(default)]
+    pub persistence: Option<PersistenceConfig>,
+    #[serde(default)]
+    pub history: HistoryConfig,
+    /// Write plants created, changed or deleted over the API back to config.json
+    #[serde(default)]
+    pub persist_changes: bool,
+    /// API keys required on the HTTP API; unset = open to anyone who can reach it
+    #[serde(default)]
+    pub auth: Option<AuthConfig>,
+}
+
+/// API keys accepted as `Authorization: Bearer …` or `X-API-Key`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct AuthConfig {
+    pub keys: Vec<ApiKeyConfig>,
+    /// Serve /health without a key, e.g. for a load balancer probe
+    #[serde(default)]
+    pub public_health: bool,
+    /// Serve /metrics without a key, e.g. for a Prometheus scraper
+    #[serde(default)]
+    pub public_metrics: bool,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ApiKeyConfig {
+    pub key: String,
+    pub role: ApiRole,
+    /// Who holds the key, for the logs
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// What a key may do: `read` only reads, `control` also changes the simulation.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiRole {
+    Read,
+    Control,
+}
+
+/// In-memory telemetry history behind `/api/plants/{id}/history`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct HistoryConfig {
+    /// Hours of 1-minute points kept per plant (at most a week)
+    #[serde(default = "default_history_retention_h")]
+    pub retention_h: f64,
+}
+
+impl Default for HistoryConfig {
+    fn default() -> Self {
+        Self { retention_h: default_history_retention_h() }
+    }
+}
+
+/// Snapshot of the energy counters, soiling, active alarms and recent events.
+#[derive(Debug, Deserialize, Clone)]
+pub struct PersistenceConfig {
+    /// JSON file, restored at startup and rewritten every `interval_s` and on shutdown
+    pub path: String,
+    #[serde(default = "default_persistence_interval_s")]
+    pub interval_s: u64,
+}
+
+/// Offline weather scenario.
+#[derive(Debug, Deserialize, Clone)]
+pub struct SimulationConfig {
+    /// Mixed into the weather noise (haze, clouds, wind); the same seed and
+    /// timestamps replay the same weather, 0 = the built-in scenario
+    #[serde(default)]
+    pub seed: u64,
+    /// Plants closer than this see nearly the same clouds (km)
+    #[serde(default = "default_cloud_correlation_km")]
+    pub cloud_correlation_km: f64,
+    /// Day-to-day persistence of cloudiness (0 = independent days, max 0.95)
+    #[serde(default = "default_cloud_persistence")]
+    pub cloud_persistence: f64,
+    /// Sensor noise, drift and dropouts on the telemetry (off by default)
+    #[serde(default)]
+    pub measurement_noise: MeasurementNoiseConfig,
+    /// Simulated seconds per wall-clock second (offline mode only)
+    #[serde(default = "default_simulation_speed")]
+    pub speed: f64,
+    /// Simulated time the run starts at; unset = now
+    #[serde(default)]
+    pub start: Option<DateTime<Utc>>,
+}
+
+impl Default for SimulationConfig {
+    fn default() -> Self {
+        Self {
+            seed:                 0,
+            cloud_correlation_km: default_cloud_correlation_km(),
+            cloud_persistence:    default_cloud_persistence(),
+            measurement_noise:    MeasurementNoiseConfig::default(),
+            speed:                default_simulation_speed(),
+            start:                None,
+        }
+    }
+}
+
+/// Error model of the plant instruments, replayed from the simulation seed.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq)]
+pub struct MeasurementNoiseConfig {
+    /// Master switch for every plant
+    #[serde(default)]
+    pub enabled: bool,
+    /// Standard deviation of the irradiance sensor (% of reading)
+    #[serde(default = "default_poa_sigma_pct")]
+    pub poa_sigma_pct: f64,
+    /// Standard deviation of each phase voltage reading (V)
+    #[serde(default = "default_voltage_sigma_v")]
+    pub voltage_sigma_v: f64,
+    /// Standard deviation of the frequency reading (Hz)
+    #[serde(default = "default_frequency_sigma_hz")]
+    pub frequency_sigma_hz: f64,
+    /// Chance per update cycle that the logger drops out
+    #[serde(default = "default_dropout_probability")]
+    pub dropout_probability: f64,
+    /// Update cycles a dropout holds the last readings for
+    #[serde(default = "default_dropout_cycles")]
+    pub dropout_cycles: u32,
+    /// Calibration drift of the irradiance sensor (% of reading per year)
+    #[serde(default = "default_poa_drift_pct_per_year")]
+    pub poa_drift_pct_per_year: f64,
+}
+
+impl Default for MeasurementNoiseConfig {
+    fn default() -> Self {
+        Self {
+            enabled:                false,
+            poa_sigma_pct:          default_poa_sigma_pct(),
+            voltage_sigma_v:        default_voltage_sigma_v(),
+            frequency_sigma_hz:     default_frequency_sigma_hz(),
+            dropout_probability:    default_dropout_probability(),
+            dropout_cycles:         default_dropout_cycles(),
+            poa_drift_pct_per_year: default_poa_drift_pct_per_year(),
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct ServerConfig {
     pub port: u16,
+    /// Interface the HTTP server listens on (IPv4 or IPv6 literal)
+    #[serde(default = "default_bind_address")]
+    pub bind_address: String,
+    /// Request budgets per client IP; unset = no limit
+    #[serde(default)]
+    pub rate_limit: Option<RateLimitConfig>,
+    /// Cross-origin access for browser dashboards; unset = no CORS headers
+    #[serde(default)]
+    pub cors: Option<CorsConfig>,
+    /// gzip/brotli response compression; unset = responses go out as is
+    #[serde(default)]
+    pub compression: Option<CompressionConfig>,
+}
+
+/// Origins, methods and request headers browsers may use cross-origin.
+#[derive(Debug, Deserialize, Clone)]
+pub struct CorsConfig {
+    /// e.g. `["http://localhost:5173"]`, or `["*"]` for any origin
+    pub allowed_origins: Vec<String>,
+    /// `["*"]` = any method
+    #[serde(default = "default_cors_methods")]
+    pub allowed_methods: Vec<String>,
+    /// `["*"]` = any header
+    #[serde(default = "default_cors_headers")]
+    pub allowed_headers: Vec<String>,
+    /// How long browsers may cache a preflight answer; unset = not at all
+    #[serde(default)]
+    pub max_age_s: Option<u64>,
+}
+
+/// Which encodings responses may be compressed with, above what size.
+#[derive(Debug, Deserialize, Clone)]
+pub struct CompressionConfig {
+    /// Smaller bodies go out uncompressed: the saving would not pay for the CPU
+    #[serde(default = "default_compression_min_size_bytes")]
+    pub min_size_bytes: u16,
+    #[serde(default = "default_compression_enabled")]
+    pub gzip: bool,
+    #[serde(default = "default_compression_enabled")]
+    pub brotli: bool,
+}
+
+/// Requests each client IP may send per window, by kind of route; 0 = no
+/// limit on that kind.
+#[derive(Debug, Deserialize, Clone)]
+pub struct RateLimitConfig {
+    /// false lets every request through while keeping the budgets
+    #[serde(default = "default_rate_limit_enabled")]
+    pub enabled: bool,
+    #[serde(default = "default_rate_limit_window_s")]
+    pub window_s: u64,
+    /// GET routes: telemetry, history, lists, streams
+    #[serde(default = "default_rate_limit_read")]
+    pub read: u32,
+    /// POST, PUT and DELETE routes
+    #[serde(default = "default_rate_limit_mutation")]
+    pub mutation: u32,
+    /// /metrics
+    #[serde(default = "default_rate_limit_metrics")]
+    pub metrics: u32,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct ModbusConfig {
     pub port: u16,
+    /// Interface the Modbus TCP server listens on (IPv4 or IPv6 literal)
+    #[serde(default = "default_bind_address")]
+    pub bind_address: String,
+    /// Answer reads outside every plant block with zeros instead of
+    /// IllegalDataAddress (legacy behaviour for lenient clients)
+    #[serde(default)]
+    pub zero_fill_unmapped: bool,
+    /// Register ordering of 32-bit values ("ABCD", "CDAB", "BADC", "DCBA")
+    #[serde(default)]
+    pub word_order: WordOrder,
+    /// Optional Modbus RTU server on a serial line, running next to TCP
+    #[serde(default)]
+    pub serial: Option<SerialConfig>,
+    /// Optional Modbus/TCP Security (TLS) listener serving every plant
+    #[serde(default)]
+    pub tls: Option<ModbusTlsConfig>,
+    /// Simultaneous TCP clients; further connections are closed immediately
+    #[serde(default = "default_modbus_max_connections")]
+    pub max_connections: usize,
+    /// Drop TCP clients that send nothing for this many seconds (0 = never)
+    #[serde(default = "default_modbus_idle_timeout_s")]
+    pub idle_timeout_s: u64,
+}
+
+/// Modbus/TCP over TLS (IEC 62351 style deployments).
+#[derive(Debug, Deserialize, Clone)]
+pub struct ModbusTlsConfig {
+    /// PEM certificate chain presented to clients
+    pub cert_path: String,
+    /// PEM private key (PKCS#8, PKCS#1 or SEC1)
+    pub key_path: String,
+    #[serde(default = "default_modbus_tls_port")]
+    pub port: u16,
+    /// Do not open the plain TCP listeners: Modbus is only reachable over TLS
+    #[serde(default)]
+    pub require: bool,
+}
+
+/// Modbus RTU serial line settings (8 data bits).
+#[derive(Debug, Deserialize, Clone)]
+pub struct SerialConfig {
+    /// Serial device or PTY path, e.g. "/dev/ttyUSB0"
+    pub device: String,
+    #[serde(default = "default_serial_baud_rate")]
+    pub baud_rate: u32,
+    #[serde(default)]
+    pub parity: SerialParity,
+    /// 1 or 2
+    #[serde(default = "default_serial_stop_bits")]
+    pub stop_bits: u8,
+    /// Slave address the simulator answers to on the bus
+    #[serde(default = "default_serial_slave_id")]
+    pub slave_id: u8,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SerialParity {
+    #[default]
+    None,
+    Even,
+    Odd,
+}
+
+/// Byte order of a 32-bit value split over two registers, named after the
+/// position of the big-endian bytes A B C D (A = most significant).
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+pub enum WordOrder {
+    /// Big-endian, high word first (default)
+    #[default]
+    #[serde(rename = "ABCD")]
+    Abcd,
+    /// Word-swapped: low word first
+    #[serde(rename = "CDAB")]
+    Cdab,
+    /// Byte-swapped within each word
+    #[serde(rename = "BADC")]
+    Badc,
+    /// Little-endian
+    #[serde(rename = "DCBA")]
+    Dcba,
+}
+
+impl WordOrder {
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            WordOrder::Abcd => "ABCD",
+            WordOrder::Cdab => "CDAB",
+            WordOrder::Badc => "BADC",
+            WordOrder::Dcba => "DCBA",
+        }
+    }
 }
 
 #[derive(Debug, Deserialize, Clone)]
@@ -70,23 +450,1284 @@ pub struct PlantConfig {
     pub latitude: f64,
     pub longitude: f64,
     pub nominal_power_kw: f64,
-    pub timezone: String,
+    /// Site the plant belongs to, for fleet breakdowns; unset = a site of its own
+    #[serde(default)]
+    pub site: Option<String>,
+    /// Site elevation above sea level (m); unset = sea level
+    #[serde(default)]
+    pub altitude_m: Option<f64>,
+    /// IANA time zone: local midnight and the daily weather cycle follow it
+    #[schema(value_type = String, example = "Europe/Rome")]
+    pub timezone: Tz,
     pub modbus_mapping: ModbusMapping,
+    /// Device identity reported by the SunSpec Common Model
+    /// (defaults: simulator name, plant name, plant id)
+    #[serde(default)]
+    pub manufacturer: Option<String>,
+    #[serde(default)]
+    pub model: Option<String>,
+    #[serde(default)]
+    pub serial_number: Option<String>,
+    #[serde(default)]
+    pub inverter: InverterConfig,
+    #[serde(default)]
+    pub panel: PanelConfig,
+    /// Inverter MPPT inputs, each with its own array orientation and strings;
+    /// empty = one MPPT fed by the whole `panel` array
+    #[serde(default)]
+    pub mppt_inputs: Vec<MpptConfig>,
+    /// Skyline seen from the array; the sun casts no beam while below it
+    #[serde(default)]
+    pub horizon: Vec<HorizonPoint>,
+    /// Date the plant went into service; modules lose capacity from then on.
+    /// Unset = a new plant
+    #[serde(default)]
+    pub commissioning_date: Option<NaiveDate>,
+    #[serde(default)]
+    pub atmosphere: AtmosphereConfig,
+    #[serde(default)]
+    pub grid: GridConfig,
+    #[serde(default)]
+    pub alarms: AlarmConfig,
+    /// Recurring active power limits, e.g. imposed by the DSO, on the plant's
+    /// local clock
+    #[serde(default)]
+    pub curtailment_schedule: Vec<CurtailmentWindow>,
+    /// Weather scenario seed for this plant; unset = `simulation.seed`
+    #[serde(default)]
+    pub seed: Option<u64>,
+    /// Seconds between two telemetry updates of this plant (1–300)
+    #[serde(default = "default_update_interval_s")]
+    pub update_interval_s: f64,
+    /// Seconds without an update before the plant is flagged stale with
+    /// COMMUNICATION_LOSS; unset = three update intervals
+    #[serde(default)]
+    pub stale_after_s: Option<f64>,
+    /// In service at startup; false = starts in maintenance until enabled over the API
+    #[serde(default = "default_plant_enabled")]
+    pub enabled: bool,
+    #[serde(default)]
+    pub meter: MeterConfig,
+}
+
+impl PlantConfig {
+    /// Active power the inverter can deliver (kW); `nominal_power_kw` is the DC rating.
+    pub fn max_ac_kw(&self) -> f64 {
+        self.inverter.max_ac_kw
+            .or(self.inverter.dc_ac_ratio.filter(|r| *r > 0.0).map(|r| self.nominal_power_kw / r))
+            .unwrap_or(self.nominal_power_kw)
+    }
+
+    /// Apparent power the inverter can deliver (kVA).
+    pub fn max_kva(&self) -> f64 {
+        self.inverter.max_kva.unwrap_or_else(|| self.max_ac_kw())
+    }
+}
+
+/// Orientation of a plant's PV array; unset fields keep the simulator defaults.
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct PanelConfig {
+    #[serde(default)]
+    pub mounting: Mounting,
+    /// Tilt from horizontal (°), fixed mounting; unset = |latitude| capped at 60°
+    #[serde(default)]
+    pub tilt_deg: Option<f64>,
+    /// Surface azimuth (° from North, clockwise: 90 = east, 180 = south),
+    /// fixed mounting; unset = facing the equator
+    #[serde(default)]
+    pub azimuth_deg: Option<f64>,
+    /// Ground reflectance (0–1); unset = 0.20
+    #[serde(default)]
+    pub albedo: Option<f64>,
+    /// Lowest elevation (°) the tracker can point its panels at
+    #[serde(default)]
+    pub tracker_min_elevation_deg: f64,
+    /// Highest elevation (°) the tracker can point its panels at
+    #[serde(default = "default_tracker_max_elevation_deg")]
+    pub tracker_max_elevation_deg: f64,
+    /// Wind speed (m/s) above which the tracker stows flat; unset = never
+    #[serde(default)]
+    pub stow_wind_m_s: Option<f64>,
+    /// Bifacial modules: the rear side collects ground-reflected and sky diffuse light
+    #[serde(default)]
+    pub bifacial: bool,
+    /// Rear-to-front efficiency ratio of bifacial modules
+    #[serde(default = "default_bifaciality")]
+    pub bifaciality: f64,
+    /// Clearance (m) between the lower module edge and the ground
+    #[serde(default = "default_panel_height_m")]
+    pub height_m: f64,
+    /// Distance (m) between consecutive rows; unset = a single row
+    #[serde(default)]
+    pub row_pitch_m: Option<f64>,
+    /// Ground coverage ratio (collector width / row pitch); overrides the
+    /// ratio derived from `row_pitch_m`. Unset with no pitch = no row shading
+    #[serde(default)]
+    pub gcr: Option<f64>,
+    /// Number of rows; the front row is never shaded. Unset = a large field
+    #[serde(default)]
+    pub rows: Option<u32>,
+    /// Beam loss per unit of shaded area; above 1 mimics bypass-diode and
+    /// string mismatch losses from partial shading
+    #[serde(default = "default_electrical_shading_factor")]
+    pub electrical_shading_factor: f64,
+    /// Module power loss per year of service (%)
+    #[serde(default = "default_degradation_pct_per_year")]
+    pub degradation_pct_per_year: f64,
+    /// Light-induced loss over the first year (%), replacing the yearly rate
+    /// for that year; unset = the yearly rate from day one
+    #[serde(default)]
+    pub first_year_degradation_pct: Option<f64>,
+    /// Sky diffuse model used to transpose irradiance onto the panel plane
+    #[serde(default)]
+    pub transposition: Transposition,
+    /// Cell technology: temperature coefficient, thermal model and low-light behaviour
+    #[serde(default)]
+    pub technology: Technology,
+    /// Power temperature coefficient (%/°C, negative); overrides the technology preset
+    #[serde(default)]
+    pub temp_coeff_pct_per_c: Option<f64>,
+    /// ASHRAE incidence angle modifier coefficient for the beam reflected off
+    /// the glass at shallow angles; 0 disables the loss
+    #[serde(default = "default_iam_b0")]
+    pub iam_b0: f64,
+    /// Output lost to dust per dry day (%); rain washes it off
+    #[serde(default = "default_soiling_rate_pct_per_day")]
+    pub soiling_rate_pct_per_day: f64,
+    /// Modules in series per string; unset = 14
+    #[serde(default)]
+    pub modules_per_string: Option<u32>,
+    /// Parallel strings; unset = enough strings to reach `nominal_power_kw`.
+    /// With both counts set, DC power follows the module ratings instead of
+    /// the nominal power and the technology preset
+    #[serde(default)]
+    pub strings: Option<u32>,
+    /// Module datasheet ratings at STC
+    #[serde(default)]
+    pub module: ModuleRating,
+}
+
+impl Default for PanelConfig {
+    fn default() -> Self {
+        Self {
+            mounting:                   Mounting::default(),
+            tilt_deg:                   None,
+            azimuth_deg:                None,
+            albedo:                     None,
+            tracker_min_elevation_deg:  0.0,
+            tracker_max_elevation_deg:  default_tracker_max_elevation_deg(),
+            stow_wind_m_s:              None,
+            bifacial:                   false,
+            bifaciality:                default_bifaciality(),
+            height_m:                   default_panel_height_m(),
+            row_pitch_m:                None,
+            gcr:                        None,
+            rows:                       None,
+            electrical_shading_factor:  default_electrical_shading_factor(),
+            degradation_pct_per_year:   default_degradation_pct_per_year(),
+            first_year_degradation_pct: None,
+            transposition:              Transposition::default(),
+            technology:                 Technology::default(),
+            temp_coeff_pct_per_c:       None,
+            iam_b0:                     default_iam_b0(),
+            soiling_rate_pct_per_day:   default_soiling_rate_pct_per_day(),
+            modules_per_string:         None,
+            strings:                    None,
+            module:                     ModuleRating::default(),
+        }
+    }
+}
+
+/// One MPPT input: an array sharing the `panel` modules and mounting, with
+/// its own orientation and strings. Unset fields fall back to `panel`.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, ToSchema)]
+pub struct MpptConfig {
+    /// Tilt from horizontal (°)
+    #[serde(default)]
+    pub tilt_deg: Option<f64>,
+    /// Surface azimuth (° from North, clockwise)
+    #[serde(default)]
+    pub azimuth_deg: Option<f64>,
+    /// Modules in series per string
+    #[serde(default)]
+    pub modules_per_string: Option<u32>,
+    /// Parallel strings; unset = an equal share of `nominal_power_kw`
+    #[serde(default)]
+    pub strings: Option<u32>,
+}
+
+/// Electrical ratings of one module at STC (1000 W/m², 25 °C cell);
+/// the defaults describe a 400 W monocrystalline module.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, ToSchema)]
+pub struct ModuleRating {
+    /// Open-circuit voltage (V)
+    #[serde(default = "default_module_voc_v")]
+    pub voc_v: f64,
+    /// Short-circuit current (A)
+    #[serde(default = "default_module_isc_a")]
+    pub isc_a: f64,
+    /// Maximum power point voltage (V)
+    #[serde(default = "default_module_vmp_v")]
+    pub vmp_v: f64,
+    /// Maximum power point current (A)
+    #[serde(default = "default_module_imp_a")]
+    pub imp_a: f64,
+    /// Voltage temperature coefficient (%/°C, negative)
+    #[serde(default = "default_voc_temp_coeff_pct_per_c")]
+    pub voc_temp_coeff_pct_per_c: f64,
+    /// Current temperature coefficient (%/°C)
+    #[serde(default = "default_isc_temp_coeff_pct_per_c")]
+    pub isc_temp_coeff_pct_per_c: f64,
+}
+
+impl Default for ModuleRating {
+    fn default() -> Self {
+        Self {
+            voc_v:                    default_module_voc_v(),
+            isc_a:                    default_module_isc_a(),
+            vmp_v:                    default_module_vmp_v(),
+            imp_a:                    default_module_imp_a(),
+            voc_temp_coeff_pct_per_c: default_voc_temp_coeff_pct_per_c(),
+            isc_temp_coeff_pct_per_c: default_isc_temp_coeff_pct_per_c(),
+        }
+    }
+}
+
+/// One point of a plant's horizon profile, interpolated linearly in azimuth.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, ToSchema)]
+pub struct HorizonPoint {
+    /// Degrees from North, clockwise
+    pub azimuth_deg: f64,
+    /// Height of the skyline above the horizontal (°)
+    pub elevation_deg: f64,
+}
+
+/// A weekly window during which the plant's output is capped.
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct CurtailmentWindow {
+    /// Days the window starts on ("Mon", "Tuesday", …); empty = every day
+    #[serde(default)]
+    #[schema(value_type = Vec<String>)]
+    pub days: Vec<Weekday>,
+    /// Local start time; an end at or before it runs past midnight
+    #[schema(value_type = String, example = "11:00")]
+    pub start: NaiveTime,
+    #[schema(value_type = String, example = "13:00")]
+    pub end: NaiveTime,
+    /// Active power limit while inside the window (% of nominal)
+    pub limit_pct: f64,
+}
+
+/// Time-bounded multiplier on the irradiance of one plant or the whole
+/// fleet, on top of the weather model (e.g. a solar eclipse).
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct ScenarioConfig {
+    /// Label for the event log
+    pub name: String,
+    /// Plant the override applies to; unset = every plant
+    #[serde(default)]
+    pub plant_id: Option<String>,
+    #[schema(value_type = String, example = "2026-08-12T17:30:00Z")]
+    pub start: DateTime<Utc>,
+    #[schema(value_type = String, example = "2026-08-12T19:30:00Z")]
+    pub end: DateTime<Utc>,
+    /// [share of the window 0..1, irradiance multiplier] points, linearly
+    /// interpolated; [[0, 1], [0.5, 0.1], [1, 1]] dips to 10 % halfway
+    pub profile: Vec<[f64; 2]>,
+}
+
+/// How the PV array is mounted.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+pub enum Mounting {
+    /// Fixed tilt and azimuth
+    #[default]
+    #[serde(rename = "fixed")]
+    Fixed,
+    /// Dual-axis tracker: the panel normal points at the sun within the elevation limits
+    #[serde(rename = "tracker_2axis")]
+    Tracker2Axis,
+}
+
+/// Sky diffuse transposition model.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Transposition {
+    /// Uniform sky radiance
+    #[default]
+    Isotropic,
+    /// Perez 1990: circumsolar and horizon brightening, from sky clearness bins
+    Perez,
+}
+
+/// PV cell technology of a plant's modules.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Technology {
+    /// Monocrystalline silicon
+    #[default]
+    MonoSi,
+    /// Polycrystalline silicon
+    PolySi,
+    /// Cadmium telluride thin film
+    Cdte,
+    /// Copper indium gallium selenide thin film
+    Cigs,
+    /// Silicon heterojunction
+    Hjt,
+}
+
+/// Revenue meter at the grid connection.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, ToSchema)]
+pub struct MeterConfig {
+    /// Accuracy class (%, e.g. 0.5, 1 or 2) of the meter booking the energy
+    /// counters; unset = the counters book the true energy
+    #[serde(default)]
+    pub accuracy_class: Option<f64>,
+}
+
+/// Clear-sky atmosphere of a plant; unset fields keep the climatological model.
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct AtmosphereConfig {
+    /// Linke turbidity replacing the latitude/season heuristic
+    /// (≈2 desert, 3 rural, 5+ urban haze)
+    #[serde(default)]
+    pub linke_turbidity: Option<LinkeTurbidity>,
+    /// Multiplier on the aerosol optical depth (< 1 cleaner air, > 1 hazier)
+    #[serde(default = "default_aod_scale")]
+    pub aod_scale: f64,
+    /// Local humidity bias over the climatological model (RH points),
+    /// e.g. +10 in a river valley prone to morning fog
+    #[serde(default)]
+    pub humidity_offset_pct: f64,
+}
+
+impl Default for AtmosphereConfig {
+    fn default() -> Self {
+        Self { linke_turbidity: None, aod_scale: default_aod_scale(), humidity_offset_pct: 0.0 }
+    }
+}
+
+/// Grid connection of a plant: the export cap at the meter, the site load
+/// behind it, the grid support functions and the interface protection of the
+/// inverter.
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct GridConfig {
+    /// Phase-to-neutral nominal voltage (V): 230 for a 230/400 V network,
+    /// 277 for 277/480 V
+    #[serde(default = "default_grid_nominal_voltage_v")]
+    pub nominal_voltage_v: f64,
+    /// Nominal frequency (Hz): 50 or 60
+    #[serde(default = "default_grid_nominal_frequency_hz")]
+    pub nominal_frequency_hz: f64,
+    /// Synchronous grid the plant feeds: plants on the same network see the
+    /// same frequency and RoCoF
+    #[serde(default = "default_grid_network")]
+    pub network: String,
+    #[serde(default)]
+    pub phase_imbalance: PhaseImbalanceConfig,
+    /// Most power (kW) the site may feed into the grid; 0 = zero export,
+    /// unset = no cap
+    #[serde(default)]
+    pub export_limit_kw: Option<f64>,
+    #[serde(default)]
+    pub load: SiteLoadConfig,
+    /// P(f) droop above an over-frequency threshold; unset = off
+    #[serde(default)]
+    pub freq_watt: Option<FreqWattConfig>,
+    /// Q(U) curve; when set the plant starts in reactive mode `volt_var`
+    #[serde(default)]
+    pub volt_var: Option<VoltVarConfig>,
+    #[serde(default)]
+    pub protection: ProtectionConfig,
+}
+
+impl Default for GridConfig {
+    fn default() -> Self {
+        Self {
+            nominal_voltage_v: default_grid_nominal_voltage_v(),
+            nominal_frequency_hz: default_grid_nominal_frequency_hz(),
+            network:           default_grid_network(),
+            phase_imbalance:   PhaseImbalanceConfig::default(),
+            export_limit_kw:   None,
+            load:              SiteLoadConfig::default(),
+            freq_watt:         None,
+            volt_var:          None,
+            protection:        ProtectionConfig::default(),
+        }
+    }
+}
+
+impl GridConfig {
+    /// Interface protection for this connection. The default V< / V> limits
+    /// (±10 % of 230 V) follow the nominal voltage and the default f< / f>
+    /// ones (±0.5 Hz) the nominal frequency; limits written in the config are
+    /// kept as they are.
+    pub fn protection_limits(&self) -> ProtectionConfig {
+        let scale = self.nominal_voltage_v / default_grid_nominal_voltage_v();
+        let shift = self.nominal_frequency_hz - default_grid_nominal_frequency_hz();
+        let mut protection = self.protection;
+        if protection.undervoltage_v == default_undervoltage_v() {
+            protection.undervoltage_v *= scale;
+        }
+        if protection.overvoltage_v == default_overvoltage_v() {
+            protection.overvoltage_v *= scale;
+        }
+        if protection.underfrequency_hz == default_underfrequency_hz() {
+            protection.underfrequency_hz += shift;
+        }
+        if protection.overfrequency_hz == default_overfrequency_hz() {
+            protection.overfrequency_hz += shift;
+        }
+        protection
+    }
+
+    /// P(f) droop for this connection, the default threshold following the
+    /// nominal frequency like the protection limits.
+    pub fn freq_watt_config(&self) -> Option<FreqWattConfig> {
+        let mut config = self.freq_watt?;
+        if config.threshold_hz == default_freq_watt_threshold_hz() {
+            config.threshold_hz += self.nominal_frequency_hz - default_grid_nominal_frequency_hz();
+        }
+        Some(config)
+    }
+}
+
+/// Steady unbalance between the three phases: each phase sits within
+/// ± the given percentage of the three-phase average.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, ToSchema)]
+pub struct PhaseImbalanceConfig {
+    /// Phase voltage spread (%)
+    #[serde(default = "default_voltage_imbalance_pct")]
+    pub voltage_pct: f64,
+    /// Phase current spread (%)
+    #[serde(default = "default_current_imbalance_pct")]
+    pub current_pct: f64,
+}
+
+impl Default for PhaseImbalanceConfig {
+    fn default() -> Self {
+        Self { voltage_pct: default_voltage_imbalance_pct(), current_pct: default_current_imbalance_pct() }
+    }
+}
+
+/// Interface protection (CEI 0-21, EN 50549-1): each function trips the
+/// inverter once its limit has been violated for its delay, and the inverter
+/// reconnects after the grid has been back within limits for the observation
+/// time. Voltages are phase-to-neutral averages.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, ToSchema)]
+pub struct ProtectionConfig {
+    /// V< threshold (V)
+    #[serde(default = "default_undervoltage_v")]
+    pub undervoltage_v: f64,
+    /// V< trip delay (s)
+    #[serde(default = "default_undervoltage_trip_s")]
+    pub undervoltage_trip_s: f64,
+    /// V> threshold (V)
+    #[serde(default = "default_overvoltage_v")]
+    pub overvoltage_v: f64,
+    /// V> trip delay (s)
+    #[serde(default = "default_overvoltage_trip_s")]
+    pub overvoltage_trip_s: f64,
+    /// f< threshold (Hz)
+    #[serde(default = "default_underfrequency_hz")]
+    pub underfrequency_hz: f64,
+    /// f< trip delay (s)
+    #[serde(default = "default_frequency_trip_s")]
+    pub underfrequency_trip_s: f64,
+    /// f> threshold (Hz)
+    #[serde(default = "default_overfrequency_hz")]
+    pub overfrequency_hz: f64,
+    /// f> trip delay (s)
+    #[serde(default = "default_frequency_trip_s")]
+    pub overfrequency_trip_s: f64,
+    /// RoCoF threshold, either direction (Hz/s)
+    #[serde(default = "default_rocof_hz_s")]
+    pub rocof_hz_s: f64,
+    /// RoCoF trip delay (s)
+    #[serde(default)]
+    pub rocof_trip_s: f64,
+    /// Observation time with the grid within limits before reconnecting (s)
+    #[serde(default = "default_reconnect_delay_s")]
+    pub reconnect_delay_s: f64,
+}
+
+impl Default for ProtectionConfig {
+    fn default() -> Self {
+        Self {
+            undervoltage_v:        default_undervoltage_v(),
+            undervoltage_trip_s:   default_undervoltage_trip_s(),
+            overvoltage_v:         default_overvoltage_v(),
+            overvoltage_trip_s:    default_overvoltage_trip_s(),
+            underfrequency_hz:     default_underfrequency_hz(),
+            underfrequency_trip_s: default_frequency_trip_s(),
+            overfrequency_hz:      default_overfrequency_hz(),
+            overfrequency_trip_s:  default_frequency_trip_s(),
+            rocof_hz_s:            default_rocof_hz_s(),
+            rocof_trip_s:          0.0,
+            reconnect_delay_s:     default_reconnect_delay_s(),
+        }
+    }
+}
+
+/// Alarm engine: a condition raises its alarm once it has lasted for the
+/// activation delay, and the alarm clears once the value is back inside the
+/// limit by the hysteresis, so a reading hovering on a limit does not
+/// chatter. The AC voltage and frequency alarms use the `grid.protection`
+/// limits.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, ToSchema)]
+pub struct AlarmConfig {
+    /// V< / V> alarm delay (s)
+    #[serde(default = "default_voltage_alarm_delay_s")]
+    pub voltage_delay_s: f64,
+    /// V< / V> alarm hysteresis (V)
+    #[serde(default = "default_voltage_alarm_hysteresis_v")]
+    pub voltage_hysteresis_v: f64,
+    /// f< / f> alarm delay (s)
+    #[serde(default = "default_frequency_alarm_delay_s")]
+    pub frequency_delay_s: f64,
+    /// f< / f> alarm hysteresis (Hz)
+    #[serde(default = "default_frequency_alarm_hysteresis_hz")]
+    pub frequency_hysteresis_hz: f64,
+    /// Inverter heatsink temperature alarm threshold (°C)
+    #[serde(default = "default_overtemperature_c")]
+    pub overtemperature_c: f64,
+    /// Overtemperature alarm delay (s)
+    #[serde(default = "default_overtemperature_delay_s")]
+    pub overtemperature_delay_s: f64,
+    /// Overtemperature alarm hysteresis (°C)
+    #[serde(default = "default_overtemperature_hysteresis_c")]
+    pub overtemperature_hysteresis_c: f64,
+    /// DC-to-ground isolation resistance below which the Riso check fails
+    /// and the inverter stays off the grid (MΩ, IEC 62109-2)
+    #[serde(default = "default_isolation_mohm")]
+    pub isolation_mohm: f64,
+    /// Isolation alarm delay (s)
+    #[serde(default)]
+    pub isolation_delay_s: f64,
+    /// Isolation resistance recovery needed to clear the fault (MΩ)
+    #[serde(default = "default_isolation_hysteresis_mohm")]
+    pub isolation_hysteresis_mohm: f64,
+    /// Largest deviation of an MPPT input's string current from the average
+    /// of the inputs (%); inputs facing different ways need a wider limit
+    #[serde(default = "default_mppt_deviation_pct")]
+    pub mppt_deviation_pct: f64,
+    /// MPPT deviation alarm delay (s)
+    #[serde(default = "default_mppt_deviation_delay_s")]
+    pub mppt_deviation_delay_s: f64,
+    /// MPPT deviation alarm hysteresis (percentage points)
+    #[serde(default = "default_mppt_deviation_hysteresis_pct")]
+    pub mppt_deviation_hysteresis_pct: f64,
+}
+
+impl Default for AlarmConfig {
+    fn default() -> Self {
+        Self {
+            voltage_delay_s:               default_voltage_alarm_delay_s(),
+            voltage_hysteresis_v:          default_voltage_alarm_hysteresis_v(),
+            frequency_delay_s:             default_frequency_alarm_delay_s(),
+            frequency_hysteresis_hz:       default_frequency_alarm_hysteresis_hz(),
+            overtemperature_c:             default_overtemperature_c(),
+            overtemperature_delay_s:       default_overtemperature_delay_s(),
+            overtemperature_hysteresis_c:  default_overtemperature_hysteresis_c(),
+            isolation_mohm:                default_isolation_mohm(),
+            isolation_delay_s:             0.0,
+            isolation_hysteresis_mohm:     default_isolation_hysteresis_mohm(),
+            mppt_deviation_pct:            default_mppt_deviation_pct(),
+            mppt_deviation_delay_s:        default_mppt_deviation_delay_s(),
+            mppt_deviation_hysteresis_pct: default_mppt_deviation_hysteresis_pct(),
+        }
+    }
+}
+
+/// Over-frequency active power droop (EN 50549-1, IEEE 1547 frequency-watt).
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, ToSchema)]
+pub struct FreqWattConfig {
+    /// Frequency (Hz) above which the output is reduced
+    #[serde(default = "default_freq_watt_threshold_hz")]
+    pub threshold_hz: f64,
+    /// Droop: frequency rise, in % of the nominal frequency, that takes the output to zero
+    #[serde(default = "default_freq_watt_droop_pct")]
+    pub droop_pct: f64,
+}
+
+impl Default for FreqWattConfig {
+    fn default() -> Self {
+        Self { threshold_hz: default_freq_watt_threshold_hz(), droop_pct: default_freq_watt_droop_pct() }
+    }
+}
+
+/// Q(U) curve: reactive power against the grid voltage.
+#[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
+pub struct VoltVarConfig {
+    /// `[voltage p.u., Q % of max_kva]` points, + = over-excited,
+    /// interpolated linearly (default IEEE 1547 category B)
+    #[serde(default = "default_volt_var_points")]
+    pub points: Vec<[f64; 2]>,
+}
+
+impl Default for VoltVarConfig {
+    fn default() -> Self {
+        Self { points: default_volt_var_points() }
+    }
+}
+
+/// Consumption behind the grid meter, repeating every day on the local clock.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, ToSchema)]
+pub struct SiteLoadConfig {
+    /// Constant consumption (kW)
+    #[serde(default)]
+    pub base_kw: f64,
+    /// Height of the daily shape above the base (kW)
+    #[serde(default)]
+    pub peak_kw: f64,
+    #[serde(default)]
+    pub shape: LoadShape,
+}
+
+/// Daily shape of the site load.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum LoadShape {
+    /// Morning and larger evening peaks
+    #[default]
+    Residential,
+    /// Plateau over working hours (08:00–18:00)
+    Commercial,
+    /// Base load only
+    Flat,
+}
+
+/// Linke turbidity: one value for the whole year or twelve monthly values (January first).
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, PartialEq, ToSchema)]
+#[serde(untagged)]
+pub enum LinkeTurbidity {
+    Annual(f64),
+    Monthly([f64; 12]),
+}
+
+impl LinkeTurbidity {
+    /// Turbidity for month `month0` (0 = January).
+    pub fn for_month(&self, month0: u32) -> f64 {
+        match self {
+            Self::Annual(tl)   => *tl,
+            Self::Monthly(tls) => tls[month0 as usize % 12],
+        }
+    }
+}
+
+/// Inverter rating of a plant.
+#[derive(Debug, Deserialize, Serialize, Clone, Default, ToSchema)]
+pub struct InverterConfig {
+    /// Apparent power limit (kVA); active power is derated to stay within it.
+    /// Unset = the AC rating
+    #[serde(default)]
+    pub max_kva: Option<f64>,
+    /// AC power rating (kW); the output clips here when the DC array
+    /// (`nominal_power_kw`) delivers more
+    #[serde(default)]
+    pub max_ac_kw: Option<f64>,
+    /// DC/AC oversizing ratio, used when `max_ac_kw` is unset
+    #[serde(default)]
+    pub dc_ac_ratio: Option<f64>,
+    /// Conversion efficiency against load
+    #[serde(default)]
+    pub efficiency_curve: EfficiencyCurveConfig,
+    /// POA irradiance (W/m²) above which the inverter starts its grid
+    /// connection sequence; unset = 30
+    #[serde(default)]
+    pub start_irradiance_w_m2: Option<f64>,
+    /// POA irradiance (W/m²) below which a connected inverter shuts down;
+    /// unset = 15, and it must stay below the start threshold
+    #[serde(default)]
+    pub stop_irradiance_w_m2: Option<f64>,
+    /// Update cycles spent in the Starting state before connecting; unset = 6
+    #[serde(default)]
+    pub startup_delay_cycles: Option<u32>,
+    /// Update cycles spent searching the maximum power point (`status` = 5)
+    /// after connecting; unset = 3
+    #[serde(default)]
+    pub mppt_search_cycles: Option<u32>,
+    /// Fastest rise of the output (kW/min); unset = the AC rating per minute
+    #[serde(default)]
+    pub ramp_rate_kw_per_min: Option<f64>,
+}
+
+/// Inverter efficiency curve: a datasheet preset or explicit
+/// `[load_fraction, efficiency]` points, load relative to the AC rating.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, ToSchema)]
+#[serde(untagged)]
+pub enum EfficiencyCurveConfig {
+    Preset(EfficiencyPreset),
+    /// e.g. `[[0.05, 0.95], [0.2, 0.975], [1.0, 0.97]]`, interpolated linearly
+    Points(Vec<[f64; 2]>),
+}
+
+impl Default for EfficiencyCurveConfig {
+    fn default() -> Self {
+        EfficiencyCurveConfig::Preset(EfficiencyPreset::default())
+    }
+}
+
+/// Named inverter efficiency curves, after their weighted nameplate efficiency.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+pub enum EfficiencyPreset {
+    /// European efficiency 98.0 %, typical string inverter
+    #[default]
+    #[serde(rename = "euro_98")]
+    Euro98,
+    /// CEC efficiency 97.5 %, typical central inverter
+    #[serde(rename = "cec_97_5")]
+    Cec975,
 }
 
 /// Starting Modbus register address for this plant.
 /// All 27 variables are mapped at [base_address + offset] where offsets
 /// are the REG_* constants in modbus_server.rs. Use ≥100-register blocks
 /// between plants to avoid overlaps  (plant_1=0, plant_2=100, plant_3=200).
+///
+/// When `unit_id` is set the plant is addressed by the Modbus unit identifier
+/// instead, and every such plant may use the same base_address.
+///
+/// With any other `profile` than "generic" the plant is served at the fixed
+/// addresses of that device family instead, and base_address is ignored.
 #[derive(Debug, Deserialize, Serialize, Clone, ToSchema)]
 pub struct ModbusMapping {
+    #[serde(default)]
     pub base_address: u16,
+    #[serde(default)]
+    pub unit_id: Option<u8>,
+    /// Dedicated Modbus TCP port serving only this plant; unset = shared `modbus.port`
+    #[serde(default)]
+    pub port: Option<u16>,
+    /// Also answer as a weather station on unit_id + 1 and a revenue meter on
+    /// unit_id + 2 (site gateway emulation); requires `unit_id`
+    #[serde(default)]
+    pub virtual_devices: bool,
+    #[serde(default)]
+    pub profile: ProfileKind,
+    /// SCADA must write the watchdog register at least this often (seconds);
+    /// unset = no supervision
+    #[serde(default)]
+    pub watchdog_timeout_s: Option<u64>,
+    /// Active power limit (%) applied while the watchdog is stale; unset = no curtailment
+    #[serde(default)]
+    pub watchdog_fallback_pct: Option<f64>,
+    /// Per-variable register scale overrides (raw = physical × scale), keyed by
+    /// variable name, e.g. `{"watchdog_age_s": 0.1}`; writable parameters keep theirs
+    #[serde(default)]
+    pub scaling: HashMap<String, f64>,
+    /// Encoding of the cell, inverter and ambient temperature registers
+    #[serde(default)]
+    pub temperature_encoding: TemperatureEncoding,
+}
+
+/// How temperature registers are encoded in the simulator layout.
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TemperatureEncoding {
+    /// IEEE 754 float32, two registers
+    #[default]
+    Float32,
+    /// Two's-complement int16 in 0.1 °C (scale 10), one register
+    Int16,
+}
+
+/// Register model a plant is exposed with (see `profiles.rs`).
+#[derive(Debug, Deserialize, Serialize, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ProfileKind {
+    /// Simulator layout (REGISTER_LAYOUT) at base_address
+    #[default]
+    #[serde(alias = "generic")]
+    Custom,
+    /// SunSpec Common Model 1 + Inverter Model 103 at 40000
+    Sunspec,
+    /// SunSpec block as served by a Fronius Datamanager
+    Fronius,
+    /// SMA Modbus interface (30201..)
+    Sma,
+    /// Huawei SUN2000 (32064..)
+    Huawei,
 }
 
 impl Config {
     pub fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
         let content = std::fs::read_to_string(path)?;
-        let config = serde_json::from_str(&content)?;
+        let config: Config = serde_json::from_str(&content)?;
+        config.validate_plants()?;
+        config.validate_sim_clock()?;
+        config.validate_auth()?;
+        config.validate_rate_limit()?;
+        config.validate_cors()?;
         Ok(config)
     }
+
+    /// Every check on the plant list, as run at startup and on each plant
+    /// created or changed over the API.
+    pub fn validate_plants(&self) -> Result<(), String> {
+        self.validate_sites()?;
+        self.validate_register_blocks()?;
+        self.validate_grids()?;
+        self.validate_update_intervals()?;
+        self.validate_meters()
+    }
+
+    /// Fail on an empty or repeated plant id, coordinates off the globe or a
+    /// nominal power that is not positive.
+    pub fn validate_sites(&self) -> Result<(), String> {
+        let mut ids = std::collections::HashSet::new();
+        for plant in &self.plants {
+            if plant.id.trim().is_empty() {
+                return Err("plant id must not be empty".to_string());
+            }
+            if !ids.insert(plant.id.as_str()) {
+                return Err(format!("plant id '{}' is used twice", plant.id));
+            }
+            if !(-90.0..=90.0).contains(&plant.latitude) || !(-180.0..=180.0).contains(&plant.longitude) {
+                return Err(format!(
+                    "plant '{}': latitude must be within ±90 and longitude within ±180, not {}, {}",
+                    plant.id, plant.latitude, plant.longitude,
+                ));
+            }
+            if !(plant.nominal_power_kw > 0.0) {
+                return Err(format!("plant '{}': nominal_power_kw must be positive, not {}", plant.id, plant.nominal_power_kw));
+            }
+        }
+        Ok(())
+    }
+
+    /// Fail on a simulation clock that is off the wall clock in online mode,
+    /// where Open-Meteo only serves the current weather.
+    pub fn validate_sim_clock(&self) -> Result<(), String> {
+        let sim = &self.simulation;
+        let clock = crate::services::sim_clock::SimClock::new(sim.speed, sim.start, Utc::now())
+            .map_err(|e| format!("simulation.{}", e))?;
+        if !clock.is_realtime() && !self.offline_mode {
+            return Err("simulation.speed and simulation.start need offline_mode: Open-Meteo serves the current weather only".to_string());
+        }
+        Ok(())
+    }
+
+    /// Fail on an `auth` section without keys, or with an empty or repeated key.
+    pub fn validate_auth(&self) -> Result<(), String> {
+        let Some(auth) = &self.auth else { return Ok(()) };
+        if auth.keys.is_empty() {
+            return Err("auth.keys must hold at least one key; remove the auth section to open the API".to_string());
+        }
+        let mut seen = std::collections::HashSet::new();
+        for (i, key) in auth.keys.iter().enumerate() {
+            if key.key.trim().is_empty() {
+                return Err(format!("auth.keys[{}]: key must not be empty", i));
+            }
+            if !seen.insert(key.key.as_str()) {
+                return Err(format!("auth.keys[{}]: the same key is listed twice", i));
+            }
+        }
+        Ok(())
+    }
+
+    /// Fail on a rate limit window outside 1–3600 s.
+    pub fn validate_rate_limit(&self) -> Result<(), String> {
+        match &self.server.rate_limit {
+            Some(limit) if !(1..=3600).contains(&limit.window_s) => Err(format!(
+                "server.rate_limit.window_s must be between 1 and 3600, not {}", limit.window_s,
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Fail on a CORS origin, method or header the browser could not be told.
+    pub fn validate_cors(&self) -> Result<(), String> {
+        match &self.server.cors {
+            Some(cors) => crate::http_layers::cors_layer(cors).map(|_| ()),
+            None => Ok(()),
+        }
+    }
+
+    /// Fail on an update interval outside 1–300 s, or on a staleness
+    /// threshold shorter than the interval it supervises.
+    pub fn validate_update_intervals(&self) -> Result<(), String> {
+        if let Some(plant) = self.plants.iter().find(|p| !(1.0..=300.0).contains(&p.update_interval_s)) {
+            return Err(format!(
+                "plant '{}': update_interval_s must be between 1 and 300, not {}", plant.id, plant.update_interval_s,
+            ));
+        }
+        let too_short = |p: &&PlantConfig| p.stale_after_s.is_some_and(|s| !(s > p.update_interval_s));
+        match self.plants.iter().find(too_short) {
+            Some(plant) => Err(format!(
+                "plant '{}': stale_after_s must exceed update_interval_s ({} s), not {}",
+                plant.id, plant.update_interval_s, plant.stale_after_s.unwrap_or_default(),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Fail on a meter accuracy class outside (0, 5] %.
+    pub fn validate_meters(&self) -> Result<(), String> {
+        let invalid = |p: &&PlantConfig| p.meter.accuracy_class.is_some_and(|c| !(c > 0.0 && c <= 5.0));
+        match self.plants.iter().find(invalid) {
+            Some(plant) => Err(format!(
+                "plant '{}': meter.accuracy_class must be above 0 and at most 5, not {}",
+                plant.id, plant.meter.accuracy_class.unwrap_or_default(),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Fail on a nominal frequency other than 50 or 60 Hz, or on plants that
+    /// share a grid network but not its nominal frequency.
+    pub fn validate_grids(&self) -> Result<(), String> {
+        let mut networks: HashMap<&str, (&str, f64)> = HashMap::new();
+        for plant in &self.plants {
+            let (network, hz) = (plant.grid.network.as_str(), plant.grid.nominal_frequency_hz);
+            if hz != 50.0 && hz != 60.0 {
+                return Err(format!("plant '{}': grid.nominal_frequency_hz must be 50 or 60, not {}", plant.id, hz));
+            }
+            match networks.get(network) {
+                Some(&(other, other_hz)) if other_hz != hz => return Err(format!(
+                    "plants '{}' ({} Hz) and '{}' ({} Hz) share grid network '{}'", other, other_hz, plant.id, hz, network,
+                )),
+                Some(_) => {}
+                None => { networks.insert(network, (&plant.id, hz)); }
+            }
+        }
+        Ok(())
+    }
+
+    /// Fail when two plants answering on the same Modbus device (TCP port and
+    /// unit id) have overlapping register blocks, which would make one shadow
+    /// the other. Layout blocks closer than the documented 100-register spacing
+    /// only produce a warning.
+    pub fn validate_register_blocks(&self) -> Result<(), String> {
+        for plant in &self.plants {
+            let block = crate::modbus_server::plant_block(plant);
+            if block.end > u16::MAX as u32 + 1 {
+                return Err(format!(
+                    "plant {} register block {}..{} runs past address 65535: lower modbus_mapping.base_address",
+                    plant.id, block.start, block.end - 1
+                ));
+            }
+            if plant.modbus_mapping.virtual_devices {
+                match plant.modbus_mapping.unit_id {
+                    Some(unit) if unit <= u8::MAX - 2 => {}
+                    Some(unit) => return Err(format!(
+                        "plant {}: virtual_devices need unit ids {}..{}, past 255", plant.id, unit, unit as u16 + 2
+                    )),
+                    None => return Err(format!("plant {}: modbus_mapping.virtual_devices requires a unit_id", plant.id)),
+                }
+            }
+        }
+        self.validate_register_overlaps()
+    }
+
+    /// The overlap part of `validate_register_blocks`: a plant created over
+    /// the API that collides with another one is a conflict, not a bad request.
+    pub fn validate_register_overlaps(&self) -> Result<(), String> {
+        const BLOCK_SPACING: u32 = 100;
+        let mut devices: HashMap<(u16, Option<u8>), Vec<(&PlantConfig, Range<u32>)>> = HashMap::new();
+        for plant in &self.plants {
+            let port = self.modbus_port_of(plant);
+            devices.entry((port, plant.modbus_mapping.unit_id)).or_default().push((plant, crate::modbus_server::plant_block(plant)));
+            for (unit, device) in crate::profiles::virtual_devices(plant) {
+                let block = device.block();
+                devices.entry((port, Some(unit))).or_default().push((plant, block.start as u32..block.end as u32));
+            }
+        }
+        for ((port, unit), mut blocks) in devices {
+            let unit = unit.map_or("any".to_string(), |u| u.to_string());
+            blocks.sort_by_key(|(_, block)| block.start);
+            for pair in blocks.windows(2) {
+                let [(a, block_a), (b, block_b)] = pair else { continue };
+                if block_b.start < block_a.end {
+                    return Err(format!(
+                        "Modbus register blocks overlap on port {} unit {}: plant {} ({}..{}) and plant {} ({}..{})",
+                        port, unit, a.id, block_a.start, block_a.end - 1, b.id, block_b.start, block_b.end - 1
+                    ));
+                }
+                let layouts = a.modbus_mapping.profile == ProfileKind::Custom && b.modbus_mapping.profile == ProfileKind::Custom;
+                if layouts && block_b.start - block_a.start < BLOCK_SPACING {
+                    eprintln!(
+                        "[MODBUS] Plants {} (base {}) and {} (base {}) are less than {} registers apart on port {} unit {}",
+                        a.id, block_a.start, b.id, block_b.start, BLOCK_SPACING, port, unit
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Listen address of the HTTP server.
+    pub fn http_addr(&self) -> Result<SocketAddr, String> {
+        listen_addr("server.bind_address", &self.server.bind_address, self.server.port)
+    }
+
+    /// Listen address of the Modbus TCP server.
+    pub fn modbus_addr(&self) -> Result<SocketAddr, String> {
+        listen_addr("modbus.bind_address", &self.modbus.bind_address, self.modbus.port)
+    }
+
+    /// Modbus TCP port serving `plant`.
+    pub fn modbus_port_of(&self, plant: &PlantConfig) -> u16 {
+        plant.modbus_mapping.port.unwrap_or(self.modbus.port)
+    }
+
+    /// Listen address of the Modbus TCP server serving `plant`.
+    pub fn modbus_addr_of(&self, plant: &PlantConfig) -> Result<SocketAddr, String> {
+        listen_addr("modbus.bind_address", &self.modbus.bind_address, self.modbus_port_of(plant))
+    }
+
+    /// Listen address of the Modbus TLS server, when configured.
+    pub fn modbus_tls_addr(&self) -> Result<Option<SocketAddr>, String> {
+        self.modbus.tls.as_ref()
+            .map(|tls| listen_addr("modbus.bind_address", &self.modbus.bind_address, tls.port))
+            .transpose()
+    }
+
+    /// Plain Modbus TCP endpoints and the plants each one serves: the shared
+    /// `modbus.port` with every plant that has no `modbus_mapping.port`, then
+    /// one endpoint per plant with its own port; none when `modbus.tls.require`
+    /// is set. Fails when a port is claimed twice (HTTP server, TLS port, shared
+    /// Modbus port or another plant); port 0 lets the OS pick and never conflicts.
+    pub fn modbus_endpoints(&self) -> Result<Vec<(SocketAddr, Vec<PlantConfig>)>, String> {
+        let mut owners: HashMap<u16, String> = HashMap::new();
+        let mut claim = |port: u16, owner: String| {
+            if port != 0 {
+                if let Some(other) = owners.get(&port) {
+                    return Err(format!("Modbus port conflict: {} and {} both use port {}", other, owner, port));
+                }
+                owners.insert(port, owner);
+            }
+            Ok(())
+        };
+        claim(self.server.port, "the HTTP server (server.port)".to_string())?;
+        if let Some(tls) = &self.modbus.tls {
+            claim(tls.port, "the Modbus TLS server (modbus.tls.port)".to_string())?;
+            if tls.require {
+                return Ok(Vec::new());
+            }
+        }
+
+        let shared: Vec<PlantConfig> = self.plants.iter()
+            .filter(|p| p.modbus_mapping.port.is_none())
+            .cloned()
+            .collect();
+        let mut endpoints = Vec::new();
+        if !shared.is_empty() || self.plants.is_empty() {
+            claim(self.modbus.port, "the shared Modbus server (modbus.port)".to_string())?;
+            endpoints.push((self.modbus_addr()?, shared));
+        }
+        for plant in &self.plants {
+            if let Some(port) = plant.modbus_mapping.port {
+                claim(port, format!("plant {} (modbus_mapping.port)", plant.id))?;
+                let addr = listen_addr("modbus.bind_address", &self.modbus.bind_address, port)?;
+                endpoints.push((addr, vec![plant.clone()]));
+            }
+        }
+        Ok(endpoints)
+    }
+}
+
+/// Combine a configured bind address with its port; `key` names the setting in errors.
+fn listen_addr(key: &str, bind_address: &str, port: u16) -> Result<SocketAddr, String> {
+    bind_address.trim().parse::<IpAddr>()
+        .map(|ip| SocketAddr::new(ip, port))
+        .map_err(|_| format!(
+            "invalid {} \"{}\": expected an IP address such as \"0.0.0.0\" (all interfaces), \
+             \"127.0.0.1\", \"192.168.10.5\" or \"::\"",
+            key, bind_address
+        ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bind_addresses_parse_ipv4_and_ipv6() {
+        assert_eq!(listen_addr("modbus.bind_address", "0.0.0.0", 502).unwrap().to_string(), "0.0.0.0:502");
+        assert_eq!(listen_addr("modbus.bind_address", "::1", 502).unwrap().to_string(), "[::1]:502");
+        let err = listen_addr("server.bind_address", "eth0", 8080).unwrap_err();
+        assert!(err.contains("server.bind_address") && err.contains("127.0.0.1"), "{}", err);
+    }
+
+    #[test]
+    fn default_voltage_limits_follow_a_277_480_v_nominal() {
+        let grid: GridConfig = serde_json::from_value(serde_json::json!({
+            "nominal_voltage_v": 277.0, "protection": { "overvoltage_v": 300.0 }
+        })).unwrap();
+        let limits = grid.protection_limits();
+        assert!((limits.undervoltage_v - 249.3).abs() < 0.01, "{}", limits.undervoltage_v);
+        assert_eq!(limits.overvoltage_v, 300.0);
+        assert_eq!(GridConfig::default().protection_limits().undervoltage_v, 207.0);
+        assert_eq!((grid.phase_imbalance.voltage_pct, grid.phase_imbalance.current_pct), (1.0, 2.0));
+    }
+
+    #[test]
+    fn sixty_hertz_grids_shift_the_frequency_limits_and_agree_per_network() {
+        let grid: GridConfig = serde_json::from_value(serde_json::json!({
+            "nominal_frequency_hz": 60.0, "network": "ercot", "freq_watt": {}, "protection": { "overfrequency_hz": 61.2 }
+        })).unwrap();
+        let limits = grid.protection_limits();
+        assert_eq!((limits.underfrequency_hz, limits.overfrequency_hz), (59.5, 61.2));
+        assert!((grid.freq_watt_config().unwrap().threshold_hz - 60.2).abs() < 1e-9);
+        assert_eq!(GridConfig::default().network, "default");
+
+        let config = |grids: [(&str, f64); 2]| -> Config {
+            let plants: Vec<serde_json::Value> = grids.iter().enumerate().map(|(i, (network, hz))| serde_json::json!({
+                "id": format!("plant_{}", i + 1), "name": "p", "latitude": 45.0, "longitude": 7.0,
+                "nominal_power_kw": 100.0, "timezone": "Europe/Rome",
+                "modbus_mapping": { "base_address": i * 100 },
+                "grid": { "network": network, "nominal_frequency_hz": hz }
+            })).collect();
+            serde_json::from_value(serde_json::json!({
+                "server": { "port": 3000 }, "modbus": { "port": 5020 }, "plants": plants
+            })).unwrap()
+        };
+        assert!(config([("eu", 50.0), ("us", 60.0)]).validate_grids().is_ok());
+        let err = config([("eu", 50.0), ("eu", 60.0)]).validate_grids().unwrap_err();
+        assert!(err.contains("plant_2") && err.contains("'eu'"), "{}", err);
+        assert!(config([("eu", 50.0), ("jp", 55.0)]).validate_grids().unwrap_err().contains("50 or 60"));
+    }
+
+    #[test]
+    fn an_accelerated_clock_needs_offline_mode() {
+        let config = |offline: bool, simulation: serde_json::Value| -> Config {
+            serde_json::from_value(serde_json::json!({
+                "server": { "port": 3000 }, "modbus": { "port": 5020 }, "plants": [],
+                "offline_mode": offline, "simulation": simulation
+            })).unwrap()
+        };
+        assert_eq!(config(false, serde_json::json!({})).simulation.speed, 1.0);
+        assert!(config(false, serde_json::json!({})).validate_sim_clock().is_ok());
+        assert!(config(true, serde_json::json!({ "speed": 1440.0, "start": "2025-06-21T00:00:00Z" })).validate_sim_clock().is_ok());
+        let err = config(false, serde_json::json!({ "speed": 24.0 })).validate_sim_clock().unwrap_err();
+        assert!(err.contains("offline_mode"), "{}", err);
+        assert!(config(true, serde_json::json!({ "speed": -1.0 })).validate_sim_clock().unwrap_err().contains("simulation.speed"));
+    }
+
+    fn config_with_ports(http: u16, shared: u16, plant_ports: &[Option<u16>]) -> Config {
+        let plants: Vec<serde_json::Value> = plant_ports.iter().enumerate().map(|(i, port)| serde_json::json!({
+            "id": format!("plant_{}", i + 1), "name": "p", "latitude": 45.0, "longitude": 7.0,
+            "nominal_power_kw": 100.0, "timezone": "Europe/Rome",
+            "modbus_mapping": { "base_address": 0, "port": port }
+        })).collect();
+        serde_json::from_value(serde_json::json!({
+            "server": { "port": http }, "modbus": { "port": shared }, "plants": plants
+        })).unwrap()
+    }
+
+    fn config_with_bases(plants: &[(u16, Option<u8>)]) -> Config {
+        let plants: Vec<serde_json::Value> = plants.iter().enumerate().map(|(i, (base, unit))| serde_json::json!({
+            "id": format!("plant_{}", i + 1), "name": "p", "latitude": 45.0, "longitude": 7.0,
+            "nominal_power_kw": 100.0, "timezone": "Europe/Rome",
+            "modbus_mapping": { "base_address": base, "unit_id": unit }
+        })).collect();
+        serde_json::from_value(serde_json::json!({
+            "server": { "port": 3000 }, "modbus": { "port": 5020 }, "plants": plants
+        })).unwrap()
+    }
+
+    #[test]
+    fn overlapping_register_blocks_are_rejected() {
+        let err = config_with_bases(&[(200, None), (200, None)]).validate_register_blocks().unwrap_err();
+        assert!(err.contains("plant_1 (200..299)") && err.contains("plant_2 (200..299)"), "{}", err);
+
+        let err = config_with_bases(&[(0, None), (100, None), (150, None)]).validate_register_blocks().unwrap_err();
+        assert!(err.contains("plant_2 (100..199)") && err.contains("plant_3 (150..249)"), "{}", err);
+
+        let err = config_with_bases(&[(65500, None)]).validate_register_blocks().unwrap_err();
+        assert!(err.contains("65535"), "{}", err);
+    }
+
+    #[test]
+    fn plant_ids_coordinates_and_ratings_are_checked() {
+        let mut config = config_with_bases(&[(0, None), (100, None)]);
+        assert!(config.validate_plants().is_ok());
+        config.plants[1].latitude = 91.0;
+        assert!(config.validate_sites().unwrap_err().contains("latitude"));
+        config.plants[1].latitude = 45.0;
+        config.plants[1].nominal_power_kw = f64::NAN;
+        assert!(config.validate_sites().unwrap_err().contains("nominal_power_kw"));
+        config.plants[1].nominal_power_kw = 100.0;
+        config.plants[1].id = "plant_1".to_string();
+        assert!(config.validate_sites().unwrap_err().contains("used twice"));
+    }
+
+    #[test]
+    fn adjacent_or_separately_addressed_blocks_are_valid() {
+        // Back to back at the 100-register spacing
+        assert!(config_with_bases(&[(0, None), (100, None)]).validate_register_blocks().is_ok());
+        assert!(config_with_bases(&[(0, None), (200, None), (400, None)]).validate_register_blocks().is_ok());
+        // Same base on different unit ids never collides
+        assert!(config_with_bases(&[(0, Some(1)), (0, Some(2)), (0, None)]).validate_register_blocks().is_ok());
+    }
+
+    #[test]
+    fn virtual_devices_need_free_unit_ids() {
+        let mut config = config_with_bases(&[(0, Some(1)), (0, Some(3))]);
+        config.plants[0].modbus_mapping.virtual_devices = true;
+        // plant_1's meter on unit 3 shares registers 0.. with plant_2
+        let err = config.validate_register_blocks().unwrap_err();
+        assert!(err.contains("unit 3") && err.contains("plant_1") && err.contains("plant_2"), "{}", err);
+
+        config.plants[1].modbus_mapping.unit_id = Some(4);
+        assert!(config.validate_register_blocks().is_ok());
+
+        config.plants[0].modbus_mapping.unit_id = None;
+        assert!(config.validate_register_blocks().unwrap_err().contains("requires a unit_id"));
+    }
+
+    #[test]
+    fn plants_with_their_own_port_get_their_own_endpoint() {
+        let config = config_with_ports(3000, 5020, &[None, Some(5021), None, Some(5022)]);
+        let endpoints: Vec<(u16, Vec<String>)> = config.modbus_endpoints().unwrap().into_iter()
+            .map(|(addr, plants)| (addr.port(), plants.into_iter().map(|p| p.id).collect()))
+            .collect();
+        assert_eq!(endpoints, vec![
+            (5020, vec!["plant_1".to_string(), "plant_3".to_string()]),
+            (5021, vec!["plant_2".to_string()]),
+            (5022, vec!["plant_4".to_string()]),
+        ]);
+
+        // No plant left on the shared port: it is not opened
+        let config = config_with_ports(3000, 5020, &[Some(5021)]);
+        assert_eq!(config.modbus_endpoints().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn modbus_port_conflicts_refuse_to_start() {
+        let err = config_with_ports(3000, 5020, &[None, Some(3000)]).modbus_endpoints().unwrap_err();
+        assert!(err.contains("HTTP server") && err.contains("plant_2") && err.contains("3000"), "{}", err);
+        let err = config_with_ports(3000, 5020, &[None, Some(5020)]).modbus_endpoints().unwrap_err();
+        assert!(err.contains("shared Modbus server") && err.contains("plant_2"), "{}", err);
+        let err = config_with_ports(3000, 5020, &[Some(5021), Some(5021)]).modbus_endpoints().unwrap_err();
+        assert!(err.contains("plant_1") && err.contains("plant_2"), "{}", err);
+        assert!(config_with_ports(3000, 3000, &[None]).modbus_endpoints().is_err());
+        // Port 0 is assigned by the OS and never conflicts
+        assert!(config_with_ports(0, 0, &[None, Some(0)]).modbus_endpoints().is_ok());
+    }
+
+    #[test]
+    fn linke_turbidity_is_annual_or_monthly() {
+        let parse = |v: serde_json::Value| serde_json::from_value::<AtmosphereConfig>(v);
+        let annual = parse(serde_json::json!({ "linke_turbidity": 2.5 })).unwrap();
+        assert_eq!(annual.linke_turbidity, Some(LinkeTurbidity::Annual(2.5)));
+        assert_eq!(annual.aod_scale, 1.0);
+
+        let months: Vec<f64> = (1..=12).map(f64::from).collect();
+        let monthly = parse(serde_json::json!({ "linke_turbidity": months, "aod_scale": 0.5 })).unwrap();
+        assert_eq!(monthly.linke_turbidity.unwrap().for_month(11), 12.0);
+        assert!(parse(serde_json::json!({ "linke_turbidity": [3.0, 3.0] })).is_err());
+    }
+
+    #[test]
+    fn plant_timezone_must_be_a_known_zone() {
+        let plant = |tz: &str| serde_json::from_value::<PlantConfig>(serde_json::json!({
+            "id": "plant_1", "name": "p", "latitude": 40.4, "longitude": -3.7,
+            "nominal_power_kw": 100.0, "timezone": tz,
+            "modbus_mapping": { "base_address": 0 }
+        }));
+        assert_eq!(plant("Europe/Madrid").unwrap().timezone, chrono_tz::Europe::Madrid);
+        assert!(plant("Europe/Atlantis").is_err());
+    }
 }
@@ -1,19 +1,38 @@
 use axum::{
     extract::{Path, Query, State, WebSocketUpgrade},
     extract::ws::{Message, WebSocket},
-    http::StatusCode,
+    body::Body,
+    http::{header, HeaderMap, StatusCode},
     response::IntoResponse,
     Json,
 };
 use serde::Deserialize;
 use futures_util::{SinkExt, StreamExt};
+use std::collections::BTreeMap;
 use std::time::Duration;
 
-use crate::config::{Config, PlantConfig};
+use crate::config::{Config, PlantConfig, ProfileKind, ScenarioConfig};
 use crate::models::power::{
-    Alarm, Event, GlobalPowerResponse, HealthStatus, ModbusInfo, PlantStatusResponse, SystemConfig,
+    Alarm, AlarmSeverity, ControlInterface, Daylight, EnergyPeriod, Event, EventKind, ExpectedEnergy, FaultCatalogEntry,
+    FleetProductionReport, ForecastPoint, ForecastSource, GlobalPowerResponse, HealthStatus, ModbusInfo, PlantControlResponse,
+    PlantData, PlantStatusResponse, PlantWeather, PowerForecast, ProductionReport, ReactivePowerControl, ReactivePowerMode,
+    SunInfo, SystemConfig, alarm_codes, alarm_flag_bits, status_label, weather_label,
 };
-use crate::shared_state::AppState;
+use crate::modbus_server::{effective_data_type, effective_scale, REGISTER_LAYOUT};
+use crate::profiles;
+use crate::services::expected_energy::{self, DEFAULT_STEP_MIN};
+use crate::services::forecast::{self, ForecastStep, DEFAULT_FORECAST_HOURS, MAX_FORECAST_HOURS};
+use crate::services::plant_registry::{PlantError, PlantRegistry};
+use crate::services::power_service;
+use crate::services::production_report::{self, DayTotals, FleetRow, ReportInput};
+use crate::services::grid_frequency::{Disturbance, MAX_DEVIATION_HZ, MAX_ROCOF_HZ_S};
+use crate::services::history::{self, FleetHistoryPoint, HistoryPoint, Resolution, MAX_FLEET_POINTS, MAX_RETENTION_H};
+use crate::services::inverter_efficiency::EfficiencyCurve;
+use crate::services::scenarios::Scenario;
+use crate::services::sim_clock::SimClock;
+use crate::services::solar_algorithm::{sun_times, EstimateParams, SunTimes};
+use crate::services::state_snapshot::{SnapshotError, StateSnapshot};
+use crate::shared_state::{AppState, Notification, MAX_EVENT_LOG};
 
 // ─── Plants ──────────────────────────────────────────────────────────────────
 
@@ -24,6 +43,75 @@ pub async fn list_plants(State(config): State<Config>) -> impl IntoResponse {
     Json(config.plants).into_response()
 }
 
+fn plant_error(e: PlantError) -> axum::response::Response {
+    let status = match e {
+        PlantError::NotFound(_) => StatusCode::NOT_FOUND,
+        PlantError::Conflict(_) => StatusCode::CONFLICT,
+        PlantError::Invalid(_)  => StatusCode::BAD_REQUEST,
+        PlantError::Persist(_)  => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (status, Json(serde_json::json!({ "error": e.to_string() }))).into_response()
+}
+
+/// POST /api/plants
+#[utoipa::path(post, path = "/api/plants",
+    request_body = PlantConfig,
+    responses(
+        (status = 201, description = "Plant created; its first update runs right away", body = PlantConfig),
+        (status = 400, description = "Invalid plant configuration"),
+        (status = 409, description = "Plant id, register block or Modbus port already taken"),
+        (status = 500, description = "config.json could not be rewritten (persist_changes); nothing changed")
+    ))]
+pub async fn create_plant(
+    State(registry): State<PlantRegistry>,
+    Json(plant): Json<PlantConfig>,
+) -> impl IntoResponse {
+    match registry.create(plant.clone()).await {
+        Ok(()) => (StatusCode::CREATED, Json(plant)).into_response(),
+        Err(e) => plant_error(e),
+    }
+}
+
+/// PUT /api/plants/{id}
+#[utoipa::path(put, path = "/api/plants/{id}",
+    params(("id" = String, Path, description = "Plant ID")),
+    request_body = PlantConfig,
+    responses(
+        (status = 200, description = "Plant reconfigured from its next update cycle on; telemetry and counters carry on", body = PlantConfig),
+        (status = 400, description = "Invalid plant configuration, or another id in the body"),
+        (status = 404, description = "Plant not found"),
+        (status = 409, description = "Register block or Modbus port taken by another plant"),
+        (status = 500, description = "config.json could not be rewritten (persist_changes); nothing changed")
+    ))]
+pub async fn update_plant(
+    Path(id): Path<String>,
+    State(registry): State<PlantRegistry>,
+    Json(plant): Json<PlantConfig>,
+) -> impl IntoResponse {
+    match registry.update(&id, plant.clone()).await {
+        Ok(()) => Json(plant).into_response(),
+        Err(e) => plant_error(e),
+    }
+}
+
+/// DELETE /api/plants/{id}
+#[utoipa::path(delete, path = "/api/plants/{id}",
+    params(("id" = String, Path, description = "Plant ID")),
+    responses(
+        (status = 204, description = "Plant deleted: updates stopped, state and registers freed"),
+        (status = 404, description = "Plant not found"),
+        (status = 500, description = "config.json could not be rewritten (persist_changes); nothing changed")
+    ))]
+pub async fn delete_plant(
+    Path(id): Path<String>,
+    State(registry): State<PlantRegistry>,
+) -> impl IntoResponse {
+    match registry.delete(&id).await {
+        Ok(()) => StatusCode::NO_CONTENT.into_response(),
+        Err(e) => plant_error(e),
+    }
+}
+
 // ─── Plant telemetry ──────────────────────────────────────────────────────────
 
 /// GET /api/plants/{id}/power
@@ -36,110 +124,1325 @@ pub async fn list_plants(State(config): State<Config>) -> impl IntoResponse {
 pub async fn get_plant_power(
     Path(id): Path<String>,
     State(state): State<AppState>,
+    State(config): State<Config>,
 ) -> impl IntoResponse {
     if let Some(data) = state.get_data(&id) {
-        (StatusCode::OK, Json(PlantStatusResponse { timestamp: chrono::Utc::now(), data })).into_response()
+        let timestamp = state.now();
+        let today = config.plants.iter().find(|p| p.id == id)
+            .map(|p| sun_info(p, timestamp.with_timezone(&p.timezone).date_naive()));
+        let (sunrise, sunset) = today.map_or((None, None), |sun| (sun.sunrise, sun.sunset));
+        let stale = data.stale;
+        (StatusCode::OK, Json(PlantStatusResponse { timestamp, sunrise, sunset, stale, data })).into_response()
     } else {
         (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Plant not found"}))).into_response()
     }
 }
 
+// ─── Sun times ───────────────────────────────────────────────────────────────
+
+/// Sunrise, solar noon and sunset of `date` at the plant, on its local clock.
+fn sun_info(plant: &PlantConfig, date: chrono::NaiveDate) -> SunInfo {
+    let times = sun_times(plant.latitude, plant.longitude, date);
+    let local = |t: chrono::DateTime<chrono::Utc>| t.with_timezone(&plant.timezone).fixed_offset();
+    let (daylight, sunrise, sunset) = match times {
+        SunTimes::Daylight { sunrise, sunset, .. } => (Daylight::Normal, Some(local(sunrise)), Some(local(sunset))),
+        SunTimes::PolarDay { .. }                  => (Daylight::PolarDay, None, None),
+        SunTimes::PolarNight { .. }                => (Daylight::PolarNight, None, None),
+    };
+    SunInfo {
+        plant_id:     plant.id.clone(),
+        date,
+        daylight,
+        sunrise,
+        solar_noon:   local(times.solar_noon()),
+        sunset,
+        day_length_h: times.day_length_h(),
+    }
+}
+
+#[derive(Deserialize)]
+pub struct SunQuery {
+    /// Local calendar day (YYYY-MM-DD); default = today at the plant
+    pub date: Option<chrono::NaiveDate>,
+}
+
+/// GET /api/plants/{id}/sun
+#[utoipa::path(get, path = "/api/plants/{id}/sun",
+    params(
+        ("id" = String, Path, description = "Plant ID"),
+        ("date" = Option<String>, Query, description = "Local date YYYY-MM-DD (default: today)")
+    ),
+    responses(
+        (status = 200, description = "Sunrise, solar noon and sunset in the plant's time zone", body = SunInfo),
+        (status = 404, description = "Plant not found")
+    ))]
+pub async fn get_plant_sun(
+    Path(id): Path<String>,
+    Query(q): Query<SunQuery>,
+    State(state): State<AppState>,
+    State(config): State<Config>,
+) -> impl IntoResponse {
+    let Some(plant) = config.plants.iter().find(|p| p.id == id) else {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Plant not found"}))).into_response();
+    };
+    let date = q.date.unwrap_or_else(|| state.now().with_timezone(&plant.timezone).date_naive());
+    Json(sun_info(plant, date)).into_response()
+}
+
+// ─── Live weather ────────────────────────────────────────────────────────────
+
+/// GET /api/plants/{id}/weather
+#[utoipa::path(get, path = "/api/plants/{id}/weather",
+    params(("id" = String, Path, description = "Plant ID")),
+    responses(
+        (status = 200, description = "Weather and sun position at the plant from its latest update, from Open-Meteo or the model, with their age", body = PlantWeather),
+        (status = 404, description = "Plant not found")
+    ))]
+pub async fn get_plant_weather(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let Some(data) = state.get_data(&id) else {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Plant not found"}))).into_response();
+    };
+    let observed_at = chrono::DateTime::from_timestamp(data.weather_observed_unix as i64, 0).unwrap_or_default();
+    // The observation time is kept in whole seconds, and so is its age
+    let age_s = (state.now().timestamp() - observed_at.timestamp()).max(0) as f64;
+    Json(PlantWeather {
+        plant_id:              id,
+        ambient_temp_c:        data.ambient_temp_c,
+        wind_speed_m_s:        data.wind_speed_m_s,
+        relative_humidity_pct: data.relative_humidity_pct,
+        cloud_factor:          data.cloud_factor,
+        soiling_factor:        data.soiling_factor,
+        weather_code:          data.weather_code,
+        weather_label:         weather_label(data.weather_code).to_string(),
+        is_day:                data.is_day,
+        ghi_w_m2:              data.ghi_w_m2,
+        poa_irradiance_w_m2:   data.poa_irradiance_w_m2,
+        solar_elevation_deg:   data.solar_elevation_deg,
+        solar_azimuth_deg:     data.solar_azimuth_deg,
+        source:                data.weather_source,
+        observed_at,
+        age_s,
+        stale:                 data.stale,
+    }).into_response()
+}
+
+// ─── Expected energy ─────────────────────────────────────────────────────────
+
+/// Model inputs of `plant` as its update loop sees them, with the live seed.
+fn expected_model(plant: &PlantConfig, config: &Config, state: &AppState) -> (EstimateParams, EfficiencyCurve) {
+    let params = EstimateParams {
+        cloud_correlation_km: config.simulation.cloud_correlation_km,
+        cloud_persistence:    config.simulation.cloud_persistence,
+        seed:                 plant.seed.unwrap_or_else(|| state.simulation_seed()),
+        ..EstimateParams::for_plant(plant)
+    };
+    (params, EfficiencyCurve::for_config(&plant.inverter.efficiency_curve))
+}
+
+#[derive(Deserialize)]
+pub struct ExpectedEnergyQuery {
+    /// day (default), month or year
+    #[serde(default)]
+    pub period: EnergyPeriod,
+    /// YYYY-MM-DD, YYYY-MM or YYYY to match the period; default = the current one
+    pub date: Option<String>,
+    /// Sampling step (min), 1–60; default 10
+    pub step_min: Option<u32>,
+}
+
+/// GET /api/plants/{id}/expected-energy
+#[utoipa::path(get, path = "/api/plants/{id}/expected-energy",
+    params(
+        ("id" = String, Path, description = "Plant ID"),
+        ("period" = Option<EnergyPeriod>, Query, description = "day (default), month or year"),
+        ("date" = Option<String>, Query, description = "YYYY-MM-DD, YYYY-MM or YYYY matching the period (default: the current one)"),
+        ("step_min" = Option<u32>, Query, description = "Sampling step in minutes, 1–60 (default: 10)")
+    ),
+    responses(
+        (status = 200, description = "Expected (P50) production from the clear-sky and climatological model", body = ExpectedEnergy),
+        (status = 400, description = "Date does not match the period, or step out of range"),
+        (status = 404, description = "Plant not found")
+    ))]
+pub async fn get_expected_energy(
+    Path(id): Path<String>,
+    Query(q): Query<ExpectedEnergyQuery>,
+    State(state): State<AppState>,
+    State(config): State<Config>,
+) -> impl IntoResponse {
+    let Some(plant) = config.plants.iter().find(|p| p.id == id) else {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Plant not found"}))).into_response();
+    };
+    let step_min = q.step_min.unwrap_or(DEFAULT_STEP_MIN);
+    if !(1..=60).contains(&step_min) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": "step_min must be within 1..=60"}))).into_response();
+    }
+    let today = state.now().with_timezone(&plant.timezone).date_naive();
+    let Some((start, end)) = q.period.days(q.date.as_deref(), today) else {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "date must be YYYY-MM-DD, YYYY-MM or YYYY to match the period"
+        }))).into_response();
+    };
+
+    let (params, curve) = expected_model(plant, &config, &state);
+    let max_ac_kw = plant.max_ac_kw();
+    let expected = tokio::task::spawn_blocking(move || {
+        expected_energy::expected_yield_for_days(&params, &curve, max_ac_kw, start, end, step_min)
+    }).await.unwrap_or_default();
+
+    Json(ExpectedEnergy {
+        plant_id:             plant.id.clone(),
+        period:               q.period,
+        start,
+        end:                  end.pred_opt().unwrap_or(end),
+        step_min,
+        expected_energy_kwh:  expected.energy_kwh,
+        peak_power_kw:        expected.peak_kw,
+        equivalent_sun_hours: if plant.nominal_power_kw > 0.0 { expected.energy_kwh / plant.nominal_power_kw } else { 0.0 },
+    }).into_response()
+}
+
+// ─── Production reports ──────────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+pub struct ReportQuery {
+    /// day (default) or month
+    #[serde(default)]
+    pub period: EnergyPeriod,
+    /// YYYY-MM-DD for a day, YYYY-MM for a month; default = the current one
+    pub date: Option<String>,
+}
+
+impl ReportQuery {
+    /// Local days `start..end` the report covers.
+    fn days(&self, today: chrono::NaiveDate) -> Result<(chrono::NaiveDate, chrono::NaiveDate), String> {
+        if self.period == EnergyPeriod::Year {
+            return Err("period must be day or month".to_string());
+        }
+        self.period.days(self.date.as_deref(), today)
+            .ok_or_else(|| "date must be YYYY-MM-DD for a day or YYYY-MM for a month".to_string())
+    }
+}
+
+/// Report of `plant` over the local days `days` and the 1-minute history it
+/// drew on: today's running counters, the totals of the days the event log
+/// saw close (kept across restarts with persistence), the history elsewhere.
+fn plant_production(
+    plant: &PlantConfig,
+    state: &AppState,
+    period: EnergyPeriod,
+    days: (chrono::NaiveDate, chrono::NaiveDate),
+) -> (ProductionReport, Vec<HistoryPoint>) {
+    let now = state.now();
+    let (from, to) = production_report::span(plant.timezone, days);
+    let minutes = state.get_history(&plant.id, from, to, Resolution::OneMinute);
+    // Oldest first, so a day closed twice keeps its latest totals
+    let mut counters: BTreeMap<_, _> = state
+        .find_events(Some(&EventKind::EnergyRollover), Some(&plant.id), MAX_EVENT_LOG)
+        .iter().rev()
+        .filter_map(|e| e.payload.as_ref().and_then(DayTotals::from_rollover))
+        .collect();
+    if let Some((day, data)) = state.get_data(&plant.id).and_then(|d| d.energy_day.map(|day| (day, d))) {
+        counters.insert(day, DayTotals {
+            energy_kwh:           data.daily_energy_kwh,
+            peak_power_kw:        data.daily_peak_power_kw,
+            insolation_kwh_m2:    data.daily_insolation_kwh_m2,
+            curtailed_energy_kwh: Some(data.curtailed_energy_kwh),
+        });
+    }
+    let alarms = state.get_alarms(Some(&plant.id));
+    let report = production_report::plant_report(&ReportInput {
+        plant_id:          &plant.id,
+        timezone:          plant.timezone,
+        nominal_power_kw:  plant.nominal_power_kw,
+        max_ac_kw:         plant.max_ac_kw(),
+        update_interval_s: plant.update_interval_s,
+        period,
+        days,
+        now,
+        history_since:     now - state.history_retention(&plant.id),
+        minutes:           &minutes,
+        counters:          &counters,
+        alarms:            &alarms,
+    });
+    (report, minutes)
+}
+
+/// GET /api/plants/{id}/report
+#[utoipa::path(get, path = "/api/plants/{id}/report",
+    params(
+        ("id" = String, Path, description = "Plant ID"),
+        ("period" = Option<EnergyPeriod>, Query, description = "day (default) or month"),
+        ("date" = Option<String>, Query, description = "YYYY-MM-DD for a day, YYYY-MM for a month (default: the current one on the plant's calendar)")
+    ),
+    responses(
+        (status = 200, description = "Energy, peak, full-load hours, PR, specific yield, curtailment and alarms over the period, with how much of it telemetry covers", body = ProductionReport),
+        (status = 400, description = "Year period, or a date that does not match the period"),
+        (status = 404, description = "Plant not found")
+    ))]
+pub async fn get_plant_report(
+    Path(id): Path<String>,
+    Query(q): Query<ReportQuery>,
+    State(state): State<AppState>,
+    State(config): State<Config>,
+) -> impl IntoResponse {
+    let Some(plant) = config.plants.iter().find(|p| p.id == id) else {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Plant not found"}))).into_response();
+    };
+    let today = state.now().with_timezone(&plant.timezone).date_naive();
+    let days = match q.days(today) {
+        Ok(days) => days,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e }))).into_response(),
+    };
+    Json(plant_production(plant, &state, q.period, days).0).into_response()
+}
+
+/// GET /api/power/report
+#[utoipa::path(get, path = "/api/power/report",
+    params(
+        ("period" = Option<EnergyPeriod>, Query, description = "day (default) or month"),
+        ("date" = Option<String>, Query, description = "YYYY-MM-DD for a day, YYYY-MM for a month (default: the current one in UTC); each plant reports its own local day or month")
+    ),
+    responses(
+        (status = 200, description = "Fleet totals over the period with a row per plant", body = FleetProductionReport),
+        (status = 400, description = "Year period, or a date that does not match the period")
+    ))]
+pub async fn get_fleet_report(
+    Query(q): Query<ReportQuery>,
+    State(state): State<AppState>,
+    State(config): State<Config>,
+) -> impl IntoResponse {
+    let days = match q.days(state.now().date_naive()) {
+        Ok(days) => days,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e }))).into_response(),
+    };
+    let (rows, series): (Vec<_>, Vec<_>) = config.plants.iter().map(|plant| {
+        let (report, minutes) = plant_production(plant, &state, q.period, days);
+        (FleetRow { report, nominal_power_kw: plant.nominal_power_kw, max_ac_kw: plant.max_ac_kw() }, minutes)
+    }).unzip();
+    // The first minute the fleet as a whole peaked
+    let peak = history::aggregate(&series).into_iter()
+        .filter(|b| b.point.power_kw > 0.0)
+        .min_by(|a, b| b.point.power_kw.total_cmp(&a.point.power_kw))
+        .map(|b| (b.point.power_kw, b.point.timestamp));
+    Json(production_report::fleet_report(q.period, days, rows, peak)).into_response()
+}
+
+// ─── Power forecast ──────────────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+pub struct ForecastQuery {
+    /// 1–168; default 24
+    pub hours: Option<u32>,
+    /// 15m, 30m or 1h (default)
+    pub resolution: Option<String>,
+}
+
+impl ForecastQuery {
+    fn validate(&self) -> Result<(u32, ForecastStep), String> {
+        let hours = self.hours.unwrap_or(DEFAULT_FORECAST_HOURS);
+        if !(1..=MAX_FORECAST_HOURS).contains(&hours) {
+            return Err(format!("hours must be within 1..={}", MAX_FORECAST_HOURS));
+        }
+        let step = match self.resolution.as_deref() {
+            None => ForecastStep::default(),
+            Some(r) => ForecastStep::parse(r).ok_or_else(|| format!("resolution must be 15m, 30m or 1h, not '{}'", r))?,
+        };
+        Ok((hours, step))
+    }
+}
+
+/// Forecast of `plant` from `now`: Open-Meteo's hourly radiation in online
+/// mode, the model when offline or when Open-Meteo cannot be reached.
+async fn plant_forecast(
+    plant: &PlantConfig,
+    config: &Config,
+    state: &AppState,
+    now: chrono::DateTime<chrono::Utc>,
+    hours: u32,
+    step: ForecastStep,
+) -> (Vec<ForecastPoint>, ForecastSource) {
+    let (params, curve) = expected_model(plant, config, state);
+    let max_ac_kw = plant.max_ac_kw();
+    if !state.is_offline() {
+        // The hourly series starts at today's UTC midnight
+        let days = (chrono::Timelike::hour(&now) + hours) / 24 + 1;
+        match power_service::get_hourly_forecast(&params, days).await {
+            Ok(hourly) => return (
+                forecast::radiation_forecast(&params, &curve, max_ac_kw, &hourly, now, hours, step),
+                ForecastSource::OpenMeteo,
+            ),
+            Err(e) => eprintln!("[FORECAST] {}: Open-Meteo forecast unavailable, using the model: {}", plant.id, e),
+        }
+    }
+    let points = tokio::task::spawn_blocking(move || {
+        forecast::model_forecast(&params, &curve, max_ac_kw, now, hours, step)
+    }).await.unwrap_or_default();
+    (points, ForecastSource::Model)
+}
+
+fn power_forecast(plant_id: Option<String>, source: ForecastSource, hours: u32, step: ForecastStep, points: Vec<ForecastPoint>) -> PowerForecast {
+    PowerForecast {
+        plant_id,
+        source,
+        hours,
+        resolution:       step.name().to_string(),
+        total_energy_kwh: points.iter().map(|p| p.energy_kwh).sum(),
+        points,
+    }
+}
+
+/// GET /api/plants/{id}/forecast
+#[utoipa::path(get, path = "/api/plants/{id}/forecast",
+    params(
+        ("id" = String, Path, description = "Plant ID"),
+        ("hours" = Option<u32>, Query, description = "Forecast length in hours, 1–168 (default: 24)"),
+        ("resolution" = Option<String>, Query, description = "15m, 30m or 1h (default: 1h)")
+    ),
+    responses(
+        (status = 200, description = "AC production forecast from the current step on", body = PowerForecast),
+        (status = 400, description = "Hours or resolution out of range"),
+        (status = 404, description = "Plant not found")
+    ))]
+pub async fn get_plant_forecast(
+    Path(id): Path<String>,
+    Query(q): Query<ForecastQuery>,
+    State(state): State<AppState>,
+    State(config): State<Config>,
+) -> impl IntoResponse {
+    let Some(plant) = config.plants.iter().find(|p| p.id == id) else {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Plant not found"}))).into_response();
+    };
+    let (hours, step) = match q.validate() {
+        Ok(v) => v,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e }))).into_response(),
+    };
+    let (points, source) = plant_forecast(plant, &config, &state, state.now(), hours, step).await;
+    Json(power_forecast(Some(plant.id.clone()), source, hours, step, points)).into_response()
+}
+
+/// GET /api/power/forecast
+#[utoipa::path(get, path = "/api/power/forecast",
+    params(
+        ("hours" = Option<u32>, Query, description = "Forecast length in hours, 1–168 (default: 24)"),
+        ("resolution" = Option<String>, Query, description = "15m, 30m or 1h (default: 1h)")
+    ),
+    responses(
+        (status = 200, description = "Fleet forecast: plant power and energy summed per step, irradiance averaged; `model` as soon as one plant falls back to it", body = PowerForecast),
+        (status = 400, description = "Hours or resolution out of range")
+    ))]
+pub async fn get_fleet_forecast(
+    Query(q): Query<ForecastQuery>,
+    State(state): State<AppState>,
+    State(config): State<Config>,
+) -> impl IntoResponse {
+    let (hours, step) = match q.validate() {
+        Ok(v) => v,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e }))).into_response(),
+    };
+    let now = state.now();
+    let forecasts = futures_util::future::join_all(
+        config.plants.iter().map(|p| plant_forecast(p, &config, &state, now, hours, step)),
+    ).await;
+    let source = if !forecasts.is_empty() && forecasts.iter().all(|(_, s)| *s == ForecastSource::OpenMeteo) {
+        ForecastSource::OpenMeteo
+    } else {
+        ForecastSource::Model
+    };
+    let plants: Vec<Vec<ForecastPoint>> = forecasts.into_iter().map(|(points, _)| points).collect();
+    Json(power_forecast(None, source, hours, step, forecast::aggregate(&plants))).into_response()
+}
+
+// ─── Remote control ──────────────────────────────────────────────────────────
+
+/// Actions accepted by the control endpoint
+const CONTROL_ACTIONS: [&str; 4] = ["start", "stop", "limit", "reset_faults"];
+
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct ControlBody {
+    /// start, stop, limit or reset_faults
+    pub action: String,
+    /// Active power limit for `limit` (% of nominal, 0–100)
+    pub percent: Option<f64>,
+}
+
+/// POST /api/plants/{id}/control
+///
+/// The SCADA remote controls over REST, on the same state as the Modbus
+/// writes: `start` and `stop` set the enable coil, `limit` the active power
+/// limit register and `reset_faults` releases a latched protection trip or
+/// isolation fault. Each command is logged with `"interface": "rest"`.
+#[utoipa::path(post, path = "/api/plants/{id}/control",
+    params(("id" = String, Path, description = "Plant ID")),
+    request_body = ControlBody,
+    responses(
+        (status = 200, description = "Control state and status after the command", body = PlantControlResponse),
+        (status = 404, description = "Plant not found"),
+        (status = 422, description = "Unknown action, or a limit without a percent within 0–100")
+    ))]
+pub async fn control_plant(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    State(config): State<Config>,
+    Json(body): Json<ControlBody>,
+) -> impl IntoResponse {
+    if !config.plants.iter().any(|p| p.id == id) {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Plant not found"}))).into_response();
+    }
+    let via = ControlInterface::Rest;
+    let mut reset_codes = Vec::new();
+    match body.action.as_str() {
+        "start" => state.set_inverter_enabled(&id, true, via),
+        "stop"  => state.set_inverter_enabled(&id, false, via),
+        "limit" => match body.percent {
+            Some(pct) if (0.0..=100.0).contains(&pct) => state.set_power_limit(&id, pct, via),
+            _ => return (StatusCode::UNPROCESSABLE_ENTITY, Json(serde_json::json!({
+                "error": "limit needs a percent within 0..100",
+            }))).into_response(),
+        },
+        "reset_faults" => reset_codes = state.reset_faults(&id, via).unwrap_or_default(),
+        other => return (StatusCode::UNPROCESSABLE_ENTITY, Json(serde_json::json!({
+            "error": format!("Unknown action '{}'", other), "supported_actions": CONTROL_ACTIONS,
+        }))).into_response(),
+    }
+    println!("[SETTINGS] Plant {} {} via REST", id, body.action);
+    let data = state.get_data(&id).unwrap_or_default();
+    Json(PlantControlResponse {
+        plant_id:         id,
+        action:           body.action,
+        status:           data.status,
+        status_label:     status_label(data.status).to_string(),
+        inverter_enabled: data.inverter_enabled,
+        power_limit_pct:  data.power_limit_pct,
+        fault_code:       data.fault_code,
+        reset_codes,
+    }).into_response()
+}
+
+// ─── Reactive power control ──────────────────────────────────────────────────
+
+fn reactive_control(state: &AppState, plant_id: &str) -> ReactivePowerControl {
+    let data = state.get_data(plant_id).unwrap_or_default();
+    ReactivePowerControl {
+        plant_id:         plant_id.to_string(),
+        mode:             data.reactive_mode,
+        cos_phi_setpoint: data.cos_phi_setpoint,
+        q_setpoint_kvar:  data.reactive_setpoint_kvar,
+    }
+}
+
+/// GET /api/plants/{id}/reactive-power
+#[utoipa::path(get, path = "/api/plants/{id}/reactive-power",
+    params(("id" = String, Path, description = "Plant ID")),
+    responses(
+        (status = 200, description = "Reactive power mode and setpoints", body = ReactivePowerControl),
+        (status = 404, description = "Plant not found")
+    ))]
+pub async fn get_reactive_power(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    State(config): State<Config>,
+) -> impl IntoResponse {
+    if !config.plants.iter().any(|p| p.id == id) {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Plant not found"}))).into_response();
+    }
+    Json(reactive_control(&state, &id)).into_response()
+}
+
+/// Fields left out keep their current value.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct ReactivePowerBody {
+    pub mode: Option<ReactivePowerMode>,
+    pub cos_phi_setpoint: Option<f64>,
+    pub q_setpoint_kvar: Option<f64>,
+}
+
+/// POST /api/plants/{id}/reactive-power
+///
+/// Same ranges as the Modbus parameter registers: cos φ 0.8–1.0 (or 0) and a
+/// Q setpoint that fits the int16 kvar register.
+#[utoipa::path(post, path = "/api/plants/{id}/reactive-power",
+    params(("id" = String, Path, description = "Plant ID")),
+    request_body = ReactivePowerBody,
+    responses(
+        (status = 200, description = "Updated reactive power control", body = ReactivePowerControl),
+        (status = 400, description = "Setpoint out of range"),
+        (status = 404, description = "Plant not found")
+    ))]
+pub async fn set_reactive_power(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    State(config): State<Config>,
+    Json(body): Json<ReactivePowerBody>,
+) -> impl IntoResponse {
+    if !config.plants.iter().any(|p| p.id == id) {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Plant not found"}))).into_response();
+    }
+    if body.cos_phi_setpoint.is_some_and(|c| c != 0.0 && !(0.8..=1.0).contains(&c)) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": "cos_phi_setpoint must be 0 or 0.8..1.0"}))).into_response();
+    }
+    if body.q_setpoint_kvar.is_some_and(|q| !(i16::MIN as f64..=i16::MAX as f64).contains(&q)) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": "q_setpoint_kvar must be within -32768..32767"}))).into_response();
+    }
+    if let Some(cos_phi) = body.cos_phi_setpoint {
+        state.set_cos_phi_setpoint(&id, cos_phi);
+    }
+    if let Some(kvar) = body.q_setpoint_kvar {
+        state.set_reactive_setpoint(&id, kvar);
+    }
+    if let Some(mode) = body.mode {
+        state.set_reactive_mode(&id, mode);
+    }
+    Json(reactive_control(&state, &id)).into_response()
+}
+
+/// Excursion to force on a plant's grid connection; at least one field
+/// besides `duration_s` is required.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct GridEventBody {
+    /// Frequency the grid jumps to (Hz)
+    pub frequency_hz: Option<f64>,
+    /// Step added to the grid frequency (Hz)
+    pub frequency_step_hz: Option<f64>,
+    /// Frequency ramp from the moment of injection (Hz/s)
+    pub rocof_hz_s: Option<f64>,
+    /// L-N voltage at the plant's connection (V)
+    pub voltage_v: Option<f64>,
+    pub duration_s: f64,
+}
+
+/// POST /api/plants/{id}/grid-event
+///
+/// Forces a frequency step or ramp and/or a voltage for `duration_s` (at
+/// most an hour) so the protection, P(f) and Q(U) responses can be watched
+/// on demand. The frequency is the grid network's: every plant on the
+/// plant's network sees it.
+#[utoipa::path(post, path = "/api/plants/{id}/grid-event",
+    params(("id" = String, Path, description = "Plant ID")),
+    request_body = GridEventBody,
+    responses(
+        (status = 200, description = "Grid event injected"),
+        (status = 400, description = "Nothing to force or value out of range"),
+        (status = 404, description = "Plant not found")
+    ))]
+pub async fn inject_grid_event(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    State(config): State<Config>,
+    Json(body): Json<GridEventBody>,
+) -> impl IntoResponse {
+    if !config.plants.iter().any(|p| p.id == id) {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Plant not found"}))).into_response();
+    }
+    let frequency = Disturbance {
+        frequency_hz: body.frequency_hz,
+        step_hz:      body.frequency_step_hz.unwrap_or(0.0),
+        rocof_hz_s:   body.rocof_hz_s.unwrap_or(0.0),
+    };
+    if frequency.is_none() && body.voltage_v.is_none() {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": "frequency_hz, frequency_step_hz, rocof_hz_s or voltage_v is required"
+        }))).into_response();
+    }
+    let nominal_hz = state.nominal_frequency_hz(&id);
+    let (low, high) = (nominal_hz - MAX_DEVIATION_HZ, nominal_hz + MAX_DEVIATION_HZ);
+    if body.frequency_hz.is_some_and(|f| !(low..=high).contains(&f)) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": format!("frequency_hz must be within {}..{}", low, high)}))).into_response();
+    }
+    if !(frequency.step_hz.abs() <= MAX_DEVIATION_HZ && frequency.rocof_hz_s.abs() <= MAX_ROCOF_HZ_S) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({
+            "error": format!("frequency_step_hz must be within ±{} and rocof_hz_s within ±{}", MAX_DEVIATION_HZ, MAX_ROCOF_HZ_S)
+        }))).into_response();
+    }
+    if body.voltage_v.is_some_and(|v| !(0.0..=400.0).contains(&v)) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": "voltage_v must be within 0..400"}))).into_response();
+    }
+    if !(body.duration_s > 0.0 && body.duration_s <= 3600.0) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": "duration_s must be within 0..3600"}))).into_response();
+    }
+    state.inject_grid_event(&id, frequency, body.voltage_v, body.duration_s);
+    println!("[SETTINGS] Plant {} grid event injected for {} s", id, body.duration_s);
+    Json(serde_json::json!({
+        "plant_id": id, "frequency_hz": body.frequency_hz, "frequency_step_hz": frequency.step_hz,
+        "rocof_hz_s": frequency.rocof_hz_s, "voltage_v": body.voltage_v, "duration_s": body.duration_s,
+    })).into_response()
+}
+
+/// Isolation resistance to force on a plant's array for `duration_s`.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct IsolationFaultBody {
+    pub isolation_mohm: f64,
+    pub duration_s: f64,
+}
+
+/// POST /api/plants/{id}/isolation-fault
+///
+/// Replaces the modelled DC-ground isolation resistance (a wet string, a
+/// chafed cable) for `duration_s`, at most an hour. Below 1 MΩ the Riso
+/// check faults the inverter and holds off a start until the value is back
+/// above 1.5 MΩ.
+#[utoipa::path(post, path = "/api/plants/{id}/isolation-fault",
+    params(("id" = String, Path, description = "Plant ID")),
+    request_body = IsolationFaultBody,
+    responses(
+        (status = 200, description = "Isolation fault injected"),
+        (status = 400, description = "Value out of range"),
+        (status = 404, description = "Plant not found")
+    ))]
+pub async fn inject_isolation_fault(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    State(config): State<Config>,
+    Json(body): Json<IsolationFaultBody>,
+) -> impl IntoResponse {
+    if !config.plants.iter().any(|p| p.id == id) {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Plant not found"}))).into_response();
+    }
+    if !(body.isolation_mohm > 0.0 && body.isolation_mohm <= 100.0) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": "isolation_mohm must be within 0..100"}))).into_response();
+    }
+    if !(body.duration_s > 0.0 && body.duration_s <= 3600.0) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": "duration_s must be within 0..3600"}))).into_response();
+    }
+    state.inject_isolation_fault(&id, body.isolation_mohm, body.duration_s);
+    println!("[SETTINGS] Plant {} isolation fault injected for {} s", id, body.duration_s);
+    Json(serde_json::json!({
+        "plant_id": id, "isolation_mohm": body.isolation_mohm, "duration_s": body.duration_s,
+    })).into_response()
+}
+
+/// Alarm to force on a plant for `duration_s`.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct FaultBody {
+    pub code: u16,
+    pub duration_s: f64,
+    /// Unset = the severity the simulator raises the code with
+    #[serde(default)]
+    pub severity: Option<AlarmSeverity>,
+}
+
+/// POST /api/plants/{id}/faults
+///
+/// Raises any catalog alarm on demand for `duration_s`, at most an hour:
+/// `fault_code`, `alarm_flags` and `status` = 2 follow on the next update,
+/// on every interface, until it expires or is withdrawn.
+#[utoipa::path(post, path = "/api/plants/{id}/faults",
+    params(("id" = String, Path, description = "Plant ID")),
+    request_body = FaultBody,
+    responses(
+        (status = 200, description = "Fault injected"),
+        (status = 400, description = "Duration out of range"),
+        (status = 404, description = "Plant not found"),
+        (status = 422, description = "Code not in the fault catalog")
+    ))]
+pub async fn inject_fault(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    State(config): State<Config>,
+    Json(body): Json<FaultBody>,
+) -> impl IntoResponse {
+    if !config.plants.iter().any(|p| p.id == id) {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Plant not found"}))).into_response();
+    }
+    let Some((name, default_severity)) = alarm_codes::describe(body.code) else {
+        let supported: Vec<u16> = alarm_codes::CATALOG.iter().map(|(code, ..)| *code).collect();
+        return (StatusCode::UNPROCESSABLE_ENTITY, Json(serde_json::json!({
+            "error": format!("Unknown fault code {}", body.code), "supported_codes": supported,
+        }))).into_response();
+    };
+    if !(body.duration_s > 0.0 && body.duration_s <= 3600.0) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": "duration_s must be within 0..3600"}))).into_response();
+    }
+    let severity = body.severity.unwrap_or(default_severity);
+    state.inject_fault(&id, body.code, severity.clone(), body.duration_s);
+    println!("[SETTINGS] Plant {} fault {} ({}) injected for {} s", id, body.code, name, body.duration_s);
+    Json(serde_json::json!({
+        "plant_id": id, "code": body.code, "name": name, "severity": severity, "duration_s": body.duration_s,
+    })).into_response()
+}
+
+/// DELETE /api/plants/{id}/faults/{code}
+///
+/// Withdraws an injected fault before it expires; its alarm clears at once.
+#[utoipa::path(delete, path = "/api/plants/{id}/faults/{code}",
+    params(
+        ("id" = String, Path, description = "Plant ID"),
+        ("code" = u16, Path, description = "Injected alarm code")
+    ),
+    responses(
+        (status = 200, description = "Fault withdrawn"),
+        (status = 404, description = "Plant not found, or no such fault injected")
+    ))]
+pub async fn withdraw_fault(
+    Path((id, code)): Path<(String, u16)>,
+    State(state): State<AppState>,
+    State(config): State<Config>,
+) -> impl IntoResponse {
+    if !config.plants.iter().any(|p| p.id == id) {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Plant not found"}))).into_response();
+    }
+    if !state.withdraw_fault(&id, code) {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": format!("Fault {} is not injected", code)}))).into_response();
+    }
+    println!("[SETTINGS] Plant {} fault {} withdrawn", id, code);
+    Json(serde_json::json!({ "plant_id": id, "code": code })).into_response()
+}
+
+/// GET /api/faults/catalog
+#[utoipa::path(get, path = "/api/faults/catalog",
+    responses((status = 200, description = "Alarm codes accepted by the fault injection endpoint", body = Vec<FaultCatalogEntry>)))]
+pub async fn get_fault_catalog() -> impl IntoResponse {
+    Json(alarm_codes::CATALOG.iter().map(|(code, name, severity)| FaultCatalogEntry {
+        code:        *code,
+        name:        name.to_string(),
+        severity:    severity.clone(),
+        alarm_flags: alarm_flag_bits::of_code(*code),
+    }).collect::<Vec<_>>())
+}
+
+/// POST /api/plants/{id}/clean
+///
+/// Manual panel wash: the live soiling factor goes back to 1.0 and dust starts
+/// settling again at `panel.soiling_rate_pct_per_day`.
+#[utoipa::path(post, path = "/api/plants/{id}/clean",
+    params(("id" = String, Path, description = "Plant ID")),
+    responses(
+        (status = 200, description = "Panels cleaned"),
+        (status = 404, description = "Plant not found")
+    ))]
+pub async fn clean_panels(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    State(config): State<Config>,
+) -> impl IntoResponse {
+    if !config.plants.iter().any(|p| p.id == id) {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Plant not found"}))).into_response();
+    }
+    state.clean_panels(&id);
+    println!("[SETTINGS] Plant {} panels cleaned", id);
+    Json(serde_json::json!({ "plant_id": id, "soiling_factor": 1.0 })).into_response()
+}
+
+fn default_damage_cause() -> String {
+    "hail".to_string()
+}
+
+/// Storm damage to inflict on a plant: `damage_pct` of its remaining
+/// capacity, with the `cause` recorded in the event log.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct DamageBody {
+    pub damage_pct: f64,
+    #[serde(default = "default_damage_cause")]
+    pub cause: String,
+}
+
+/// POST /api/plants/{id}/damage
+///
+/// Knocks `damage_pct` off the plant's capacity (hail, storm debris) and
+/// raises a Critical alarm; both hold until `POST /api/plants/{id}/repair`.
+/// Repeated damage compounds on what is left.
+#[utoipa::path(post, path = "/api/plants/{id}/damage",
+    params(("id" = String, Path, description = "Plant ID")),
+    request_body = DamageBody,
+    responses(
+        (status = 200, description = "Damage inflicted"),
+        (status = 400, description = "damage_pct out of range"),
+        (status = 404, description = "Plant not found")
+    ))]
+pub async fn damage_plant(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    State(config): State<Config>,
+    Json(body): Json<DamageBody>,
+) -> impl IntoResponse {
+    if !config.plants.iter().any(|p| p.id == id) {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Plant not found"}))).into_response();
+    }
+    if !(body.damage_pct > 0.0 && body.damage_pct <= 100.0) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": "damage_pct must be within 0..100"}))).into_response();
+    }
+    let total = state.damage_plant(&id, body.damage_pct, &body.cause);
+    println!("[SETTINGS] Plant {} damaged by {}: {:.1}% of the capacity lost", id, body.cause, total);
+    Json(serde_json::json!({ "plant_id": id, "damage_pct": total })).into_response()
+}
+
+/// POST /api/plants/{id}/repair
+///
+/// Operator repair after storm damage: the full capacity is back from the
+/// next sample and the damage alarm clears.
+#[utoipa::path(post, path = "/api/plants/{id}/repair",
+    params(("id" = String, Path, description = "Plant ID")),
+    responses(
+        (status = 200, description = "Plant repaired"),
+        (status = 400, description = "Nothing to repair"),
+        (status = 404, description = "Plant not found")
+    ))]
+pub async fn repair_plant(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    State(config): State<Config>,
+) -> impl IntoResponse {
+    if !config.plants.iter().any(|p| p.id == id) {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Plant not found"}))).into_response();
+    }
+    let Some(repaired) = state.repair_plant(&id) else {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": "Plant is not damaged"}))).into_response();
+    };
+    println!("[SETTINGS] Plant {} repaired ({:.1}% of the capacity restored)", id, repaired);
+    Json(serde_json::json!({ "plant_id": id, "repaired_pct": repaired, "damage_pct": 0.0 })).into_response()
+}
+
+// ─── Maintenance ─────────────────────────────────────────────────────────────
+
+/// Operator note recorded with a maintenance switch.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct MaintenanceBody {
+    /// Who took the plant out of service, and why
+    pub reason: Option<String>,
+}
+
+fn switch_plant(id: String, enabled: bool, body: Option<MaintenanceBody>, state: &AppState, config: &Config) -> axum::response::Response {
+    if !config.plants.iter().any(|p| p.id == id) {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Plant not found"}))).into_response();
+    }
+    let reason = body.and_then(|b| b.reason).filter(|r| !r.trim().is_empty());
+    if state.set_plant_enabled(&id, enabled, reason.as_deref()) {
+        println!("[SETTINGS] Plant {} {}", id, if enabled { "back in service" } else { "in maintenance" });
+    }
+    Json(serde_json::json!({ "plant_id": id, "enabled": enabled })).into_response()
+}
+
+/// POST /api/plants/{id}/disable
+///
+/// Takes the plant out of service until `POST /api/plants/{id}/enable`: it
+/// stays Stopped with no output and raises no alarms, while still listed.
+#[utoipa::path(post, path = "/api/plants/{id}/disable",
+    params(("id" = String, Path, description = "Plant ID")),
+    request_body(content = MaintenanceBody, description = "Optional operator note"),
+    responses(
+        (status = 200, description = "Plant in maintenance"),
+        (status = 404, description = "Plant not found")
+    ))]
+pub async fn disable_plant(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    State(config): State<Config>,
+    body: Option<Json<MaintenanceBody>>,
+) -> impl IntoResponse {
+    switch_plant(id, false, body.map(|Json(b)| b), &state, &config)
+}
+
+/// POST /api/plants/{id}/enable
+///
+/// Puts the plant back in service; it restarts through the Starting state.
+#[utoipa::path(post, path = "/api/plants/{id}/enable",
+    params(("id" = String, Path, description = "Plant ID")),
+    request_body(content = MaintenanceBody, description = "Optional operator note"),
+    responses(
+        (status = 200, description = "Plant in service"),
+        (status = 404, description = "Plant not found")
+    ))]
+pub async fn enable_plant(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    State(config): State<Config>,
+    body: Option<Json<MaintenanceBody>>,
+) -> impl IntoResponse {
+    switch_plant(id, true, body.map(|Json(b)| b), &state, &config)
+}
+
+// ─── Irradiance scenarios ────────────────────────────────────────────────────
+
+/// GET /api/scenarios
+///
+/// Irradiance overrides waiting for or inside their window.
+#[utoipa::path(get, path = "/api/scenarios",
+    responses((status = 200, description = "Pending and active scenarios", body = Vec<Scenario>)))]
+pub async fn list_scenarios(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.scenarios()).into_response()
+}
+
+/// POST /api/scenarios
+///
+/// Schedules a time-bounded multiplier on the irradiance of one plant (or
+/// every plant without `plant_id`), applied on top of the weather in both
+/// offline and online mode.
+#[utoipa::path(post, path = "/api/scenarios",
+    request_body = ScenarioConfig,
+    responses(
+        (status = 201, description = "Scenario scheduled", body = Scenario),
+        (status = 400, description = "Invalid window or profile"),
+        (status = 404, description = "Plant not found")
+    ))]
+pub async fn create_scenario(
+    State(state): State<AppState>,
+    State(config): State<Config>,
+    Json(body): Json<ScenarioConfig>,
+) -> impl IntoResponse {
+    if body.plant_id.as_ref().is_some_and(|id| !config.plants.iter().any(|p| &p.id == id)) {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Plant not found"}))).into_response();
+    }
+    if body.end <= state.now() {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": "end must be in the future"}))).into_response();
+    }
+    match Scenario::for_config(uuid::Uuid::new_v4().to_string(), &body) {
+        Ok(scenario) => {
+            println!("[SETTINGS] Scenario '{}' scheduled {} → {}", scenario.name, scenario.start, scenario.end);
+            state.add_scenario(scenario.clone());
+            (StatusCode::CREATED, Json(scenario)).into_response()
+        }
+        Err(e) => (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": e}))).into_response(),
+    }
+}
+
+/// DELETE /api/scenarios/{id}
+///
+/// Cancels a scenario; a running one logs its end straight away.
+#[utoipa::path(delete, path = "/api/scenarios/{id}",
+    params(("id" = String, Path, description = "Scenario ID")),
+    responses(
+        (status = 204, description = "Scenario cancelled"),
+        (status = 404, description = "Scenario not found")
+    ))]
+pub async fn cancel_scenario(
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    if !state.cancel_scenario(&id) {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Scenario not found"}))).into_response();
+    }
+    StatusCode::NO_CONTENT.into_response()
+}
+
 // ─── Global fleet summary ────────────────────────────────────────────────────
 
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum PrBasis {
+    /// Mean of the plants' live performance ratios
+    #[default]
+    Live,
+    /// Today's energy over the model's expected energy up to now
+    Expected,
+}
+
+#[derive(Deserialize)]
+pub struct GlobalPowerQuery {
+    #[serde(default)]
+    pub pr: PrBasis,
+}
+
+/// Fleet energy so far today over what the model expected up to now.
+fn fleet_pr_against_expected(config: &Config, state: &AppState) -> f64 {
+    let now = state.now();
+    let (mut actual_kwh, mut expected_kwh) = (0.0, 0.0);
+    for plant in &config.plants {
+        let Some(data) = state.get_data(&plant.id) else { continue };
+        let (params, curve) = expected_model(plant, config, state);
+        let midnight = expected_energy::local_midnight(plant.timezone, now.with_timezone(&plant.timezone).date_naive());
+        actual_kwh   += data.daily_energy_kwh;
+        expected_kwh += expected_energy::expected_yield(&params, &curve, plant.max_ac_kw(), midnight, now, DEFAULT_STEP_MIN).energy_kwh;
+    }
+    if expected_kwh > 0.01 { actual_kwh / expected_kwh } else { 0.0 }
+}
+
 /// GET /api/power/global
 #[utoipa::path(get, path = "/api/power/global",
+    params(("pr" = Option<String>, Query, description = "Fleet PR basis: live (default, mean of the plants' PR) or expected (today's energy over the expected energy so far)")),
     responses((status = 200, description = "Fleet summary", body = GlobalPowerResponse)))]
 pub async fn get_global_power(
+    Query(q): Query<GlobalPowerQuery>,
     State(state): State<AppState>,
     State(config): State<Config>,
 ) -> impl IntoResponse {
-    let all_data  = state.get_all_data();
     let total_nom : f64 = config.plants.iter().map(|p| p.nominal_power_kw).sum();
 
-    let total_power   = all_data.values().map(|d| d.power_kw).sum::<f64>();
-    let total_daily   = all_data.values().map(|d| d.daily_energy_kwh).sum::<f64>();
-    let total_monthly = all_data.values().map(|d| d.monthly_energy_kwh).sum::<f64>();
-    let total_life    = all_data.values().map(|d| d.total_energy_kwh).sum::<f64>();
-    let running       = all_data.values().filter(|d| d.status == 1 || d.status == 5).count();
-    let fleet_pr      = if !all_data.is_empty() {
-        all_data.values().map(|d| d.performance_ratio).sum::<f64>() / all_data.len() as f64
-    } else { 0.0 };
-    let per_plant = all_data.into_iter().map(|(k, v)| (k, v.power_kw)).collect();
+    // Only the summed figures and each plant's power are copied
+    let totals    = state.fleet_totals();
+    let per_plant = state.map_all_data(|d| d.power_kw).collect();
+    let fleet_pr = if q.pr == PrBasis::Expected {
+        fleet_pr_against_expected(&config, &state)
+    } else {
+        totals.mean_performance_ratio()
+    };
 
     Json(GlobalPowerResponse {
-        total_power_kw:             total_power,
+        total_power_kw:             totals.power_kw,
         total_nominal_kw:           total_nom,
-        total_daily_energy_kwh:     total_daily,
-        total_monthly_energy_kwh:   total_monthly,
-        total_lifetime_energy_kwh:  total_life,
+        total_daily_energy_kwh:     totals.daily_energy_kwh,
+        total_monthly_energy_kwh:   totals.monthly_energy_kwh,
+        total_lifetime_energy_kwh:  totals.total_energy_kwh,
         fleet_performance_ratio:    fleet_pr,
-        plants_running:             running,
+        plants_running:             totals.running,
+        plants_in_maintenance:      totals.in_maintenance,
         plants_total:               config.plants.len(),
         per_plant,
     })
 }
 
+// ─── Telemetry history ───────────────────────────────────────────────────────
+
+#[derive(Deserialize)]
+pub struct HistoryQuery {
+    /// RFC 3339; default = 24 h before `to`
+    pub from: Option<chrono::DateTime<chrono::Utc>>,
+    /// RFC 3339; default = now on the simulation clock
+    pub to: Option<chrono::DateTime<chrono::Utc>>,
+    /// 1m (default), 5m, 15m or 1h
+    #[serde(default)]
+    pub resolution: Resolution,
+    /// json or csv; default = from the Accept header, else json
+    pub format: Option<HistoryFormat>,
+    /// Comma-separated columns; default = every field in JSON, the
+    /// `history::DEFAULT_COLUMNS` in CSV
+    pub fields: Option<String>,
+    /// Fleet history only: one series per plant or per site instead of one
+    /// for the whole fleet
+    #[serde(default)]
+    pub group_by: GroupBy,
+}
+
+/// How a fleet history splits into series.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum GroupBy {
+    #[default]
+    None,
+    Plant,
+    Site,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HistoryFormat {
+    Json,
+    Csv,
+}
+
+impl HistoryQuery {
+    /// The window asked for, up to `now` by default and no longer than the
+    /// longest retention; None when it is empty or reversed.
+    fn window(&self, now: chrono::DateTime<chrono::Utc>) -> Option<(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)> {
+        let to   = self.to.unwrap_or(now);
+        let from = self.from.unwrap_or(to - chrono::Duration::hours(24));
+        let max  = chrono::Duration::minutes((MAX_RETENTION_H * 60.0) as i64);
+        (from < to).then(|| (from.max(to - max), to))
+    }
+
+    /// The format asked for, else CSV when the Accept header takes it.
+    fn format_for(&self, headers: &HeaderMap) -> HistoryFormat {
+        let wants_csv = headers.get(header::ACCEPT)
+            .and_then(|accept| accept.to_str().ok())
+            .is_some_and(|accept| accept.contains("text/csv"));
+        self.format.unwrap_or(if wants_csv { HistoryFormat::Csv } else { HistoryFormat::Json })
+    }
+}
+
+/// GET /api/plants/{id}/history
+#[utoipa::path(get, path = "/api/plants/{id}/history",
+    params(
+        ("id" = String, Path, description = "Plant ID"),
+        ("from" = Option<String>, Query, description = "Start, RFC 3339 (default: 24 h before `to`)"),
+        ("to" = Option<String>, Query, description = "End, RFC 3339 (default: now)"),
+        ("resolution" = Option<Resolution>, Query, description = "1m (default), 5m, 15m or 1h"),
+        ("format" = Option<String>, Query, description = "json or csv (default: `Accept: text/csv` gives CSV, anything else JSON)"),
+        ("fields" = Option<String>, Query, description = "Comma-separated columns, e.g. timestamp,power_kw,energy_kwh (default: every field in JSON; timestamp, power_kw, poa_irradiance_w_m2, temperature_c, daily_energy_kwh in CSV)")
+    ),
+    responses(
+        (status = 200, description = "Telemetry averaged per bucket, oldest first", content(
+            (Vec<HistoryPoint> = "application/json"),
+            (String = "text/csv")
+        )),
+        (status = 400, description = "`from` is not before `to`, or an unknown field"),
+        (status = 404, description = "Plant not found")
+    ))]
+pub async fn get_plant_history(
+    Path(id): Path<String>,
+    Query(q): Query<HistoryQuery>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    State(config): State<Config>,
+) -> impl IntoResponse {
+    if !config.plants.iter().any(|p| p.id == id) {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Plant not found"}))).into_response();
+    }
+    let Some((from, to)) = q.window(state.now()) else {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": "from must be before to"}))).into_response();
+    };
+    history_response(state.get_history(&id, from, to, q.resolution), &q, &headers, &id, (from, to))
+}
+
+/// History points as JSON or as a streamed CSV download, in the format and
+/// with the columns `q` and the Accept header ask for.
+fn history_response(
+    points: Vec<HistoryPoint>,
+    q: &HistoryQuery,
+    headers: &HeaderMap,
+    name: &str,
+    (from, to): (chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>),
+) -> axum::response::Response {
+    let bad_request = |e: &str| (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e }))).into_response();
+    let format = q.format_for(headers);
+    if format == HistoryFormat::Json && q.fields.is_none() {
+        return Json(points).into_response();
+    }
+    let columns = match history::Columns::parse(q.fields.as_deref()) {
+        Ok(columns) => columns,
+        Err(e) => return bad_request(&e),
+    };
+    if format == HistoryFormat::Json {
+        return Json(points.iter().map(|p| columns.json(p)).collect::<Vec<_>>()).into_response();
+    }
+    let stamp = |t: chrono::DateTime<chrono::Utc>| t.format("%Y%m%dT%H%MZ").to_string();
+    let name: String = name.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    let filename = format!("{}_history_{}_{}.csv", name, stamp(from), stamp(to));
+    let chunks = history::csv_chunks(points, columns).map(Ok::<_, std::convert::Infallible>);
+    (
+        [
+            (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+            (header::CONTENT_DISPOSITION, format!("attachment; filename=\"{}\"", filename)),
+        ],
+        Body::from_stream(futures_util::stream::iter(chunks)),
+    ).into_response()
+}
+
+/// Plant ids of each series of a fleet history, keyed by group.
+fn fleet_groups(config: &Config, group_by: GroupBy) -> BTreeMap<String, Vec<&str>> {
+    let mut groups: BTreeMap<String, Vec<&str>> = BTreeMap::new();
+    for plant in &config.plants {
+        let key = match group_by {
+            GroupBy::None  => "fleet",
+            GroupBy::Plant => plant.id.as_str(),
+            GroupBy::Site  => plant.site.as_deref().unwrap_or(&plant.id),
+        };
+        groups.entry(key.to_string()).or_default().push(&plant.id);
+    }
+    groups
+}
+
+/// GET /api/power/global/history
+#[utoipa::path(get, path = "/api/power/global/history",
+    params(
+        ("from" = Option<String>, Query, description = "Start, RFC 3339 (default: 24 h before `to`)"),
+        ("to" = Option<String>, Query, description = "End, RFC 3339 (default: now)"),
+        ("resolution" = Option<Resolution>, Query, description = "1m (default), 5m, 15m or 1h; the window may hold at most 30000 buckets over all series"),
+        ("group_by" = Option<GroupBy>, Query, description = "none (default): one fleet series; plant or site: a map of series keyed by plant id or site"),
+        ("format" = Option<String>, Query, description = "json or csv, as for a plant's history; csv only with group_by=none"),
+        ("fields" = Option<String>, Query, description = "Comma-separated columns, as for a plant's history")
+    ),
+    responses(
+        (status = 200, description = "Power and energy summed per bucket, irradiance and temperatures averaged; a bucket some plants have no samples for is flagged `partial`", content(
+            (Vec<FleetHistoryPoint> = "application/json"),
+            (String = "text/csv")
+        )),
+        (status = 400, description = "`from` is not before `to`, an unknown field, CSV of a grouped history, or a resolution too fine for the window")
+    ))]
+pub async fn get_global_history(
+    Query(q): Query<HistoryQuery>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    State(config): State<Config>,
+) -> impl IntoResponse {
+    let bad_request = |e: String| (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e }))).into_response();
+    let Some((from, to)) = q.window(state.now()) else {
+        return bad_request("from must be before to".to_string());
+    };
+    let groups = fleet_groups(&config, q.group_by);
+    let points = q.resolution.buckets_in(from, to) * groups.len();
+    if points > MAX_FLEET_POINTS {
+        return bad_request(format!(
+            "{} buckets asked for, at most {} are served: pick a coarser resolution or a shorter window",
+            points, MAX_FLEET_POINTS,
+        ));
+    }
+    let format = q.format_for(&headers);
+    if format == HistoryFormat::Csv && q.group_by != GroupBy::None {
+        return bad_request("CSV exports take group_by=none".to_string());
+    }
+    let mut series: BTreeMap<String, Vec<FleetHistoryPoint>> = groups.into_iter().map(|(group, ids)| {
+        let plants: Vec<_> = ids.iter().map(|id| state.get_history(id, from, to, q.resolution)).collect();
+        (group, history::aggregate(&plants))
+    }).collect();
+    if format == HistoryFormat::Csv {
+        let points = series.remove("fleet").unwrap_or_default().into_iter().map(|p| p.point).collect();
+        return history_response(points, &q, &headers, "fleet", (from, to));
+    }
+
+    // JSON: the coverage of each bucket stays, whatever the fields selected
+    let columns = match q.fields.as_deref().map(|f| history::Columns::parse(Some(f))).transpose() {
+        Ok(columns) => columns,
+        Err(e) => return bad_request(e),
+    };
+    let row = |p: FleetHistoryPoint| match &columns {
+        None => serde_json::to_value(p).unwrap_or_default(),
+        Some(columns) => {
+            let mut row = columns.json(&p.point);
+            row.insert("plants_reporting".to_string(), p.plants_reporting.into());
+            row.insert("plants_total".to_string(), p.plants_total.into());
+            row.insert("partial".to_string(), p.partial.into());
+            serde_json::Value::Object(row)
+        }
+    };
+    let mut series: BTreeMap<String, Vec<serde_json::Value>> = series.into_iter()
+        .map(|(group, points)| (group, points.into_iter().map(&row).collect()))
+        .collect();
+    match q.group_by {
+        GroupBy::None => Json(series.remove("fleet").unwrap_or_default()).into_response(),
+        GroupBy::Plant | GroupBy::Site => Json(series).into_response(),
+    }
+}
+
 // ─── Modbus register info ────────────────────────────────────────────────────
 
 /// GET /api/modbus/info
 #[utoipa::path(get, path = "/api/modbus/info",
     responses((status = 200, description = "Modbus register map", body = Vec<ModbusInfo>)))]
 pub async fn get_modbus_info(State(config): State<Config>) -> impl IntoResponse {
-    use crate::modbus_server::*;
-    // Static register layout: (offset, regs, data_type, description, unit)
-    // Offsets are the REG_* constants from modbus_server.rs.
-    const LAYOUT: &[(u16, u16, &str, &str, &str)] = &[
-        // AC Output
-        (REG_POWER_KW,            2, "float32 IE754", "Active power",                 "kW"),
-        (REG_VOLTAGE_L1_V,        2, "float32 IE754", "AC Voltage L1",                "V"),
-        (REG_CURRENT_L1_A,        2, "float32 IE754", "AC Current L1",                "A"),
-        (REG_FREQUENCY_HZ,        2, "float32 IE754", "Grid frequency",               "Hz"),
-        (REG_TEMPERATURE_C,       2, "float32 IE754", "Cell temperature",             "°C"),
-        (REG_STATUS,              1, "u16 raw",        "Inverter status (enum 0-5)",   "—"),
-        (REG_VOLTAGE_L2_V,        2, "float32 IE754", "AC Voltage L2",                "V"),
-        (REG_VOLTAGE_L3_V,        2, "float32 IE754", "AC Voltage L3",                "V"),
-        (REG_CURRENT_L2_A,        2, "float32 IE754", "AC Current L2",                "A"),
-        (REG_CURRENT_L3_A,        2, "float32 IE754", "AC Current L3",                "A"),
-        (REG_REACTIVE_POWER_KVAR, 2, "float32 IE754", "Reactive power Q",             "kvar"),
-        (REG_APPARENT_POWER_KVA,  2, "float32 IE754", "Apparent power S",             "kVA"),
-        (REG_POWER_FACTOR,        2, "float32 IE754", "Power factor cos φ",           "—"),
-        (REG_ROCOF_HZ_S,          2, "float32 IE754", "ROCOF (df/dt)",                "Hz/s"),
-        // DC / MPPT
-        (REG_DC_VOLTAGE_V,        2, "float32 IE754", "DC link voltage",              "V"),
-        (REG_DC_CURRENT_A,        2, "float32 IE754", "DC string current",            "A"),
-        (REG_DC_POWER_KW,         2, "float32 IE754", "DC input power",               "kW"),
-        (REG_MPPT_VOLTAGE_V,      2, "float32 IE754", "MPPT operating voltage",       "V"),
-        (REG_MPPT_CURRENT_A,      2, "float32 IE754", "MPPT operating current",       "A"),
-        // Thermal
-        (REG_INVERTER_TEMP_C,     2, "float32 IE754", "Inverter heatsink temperature","°C"),
-        (REG_AMBIENT_TEMP_C,      2, "float32 IE754", "Ambient temperature",          "°C"),
-        // Performance & Irradiance
-        (REG_EFFICIENCY_PCT,      2, "float32 IE754", "Inverter efficiency",          "%"),
-        (REG_POA_IRRADIANCE,      2, "float32 IE754", "Plane-of-Array irradiance",    "W/m²"),
-        (REG_SOLAR_ELEVATION,     2, "float32 IE754", "Solar elevation angle",        "°"),
-        (REG_PERF_RATIO,          2, "float32 IE754", "Performance Ratio (IEC 61724)","—"),
-        (REG_SPECIFIC_YIELD,      2, "float32 IE754", "Specific yield",               "kWh/kWp"),
-        (REG_CAPACITY_FACTOR,     2, "float32 IE754", "Capacity factor",              "%"),
-        // Safety & Alarms
-        (REG_ISOLATION_MOHM,      2, "float32 IE754", "Isolation resistance DC-GND",  "MΩ"),
-        (REG_FAULT_CODE,          1, "u16 raw",        "Active fault code (IEC)",      "—"),
-        (REG_ALARM_FLAGS,         1, "u16 raw",        "Alarm bitmask",                "—"),
-        // Energy Counters
-        (REG_DAILY_ENERGY_KWH,    2, "float32 IE754", "Energy today",                 "kWh"),
-        (REG_MONTHLY_ENERGY_KWH,  2, "float32 IE754", "Energy this month",            "kWh"),
-        (REG_TOTAL_ENERGY_KWH,    2, "float32 IE754", "Lifetime energy",              "kWh"),
-    ];
-
     let mut info = Vec::new();
     for p in &config.plants {
+        let profile_points = profiles::for_plant(p)
+            .map(|profile| (p.modbus_mapping.unit_id, profile))
+            .into_iter()
+            .chain(profiles::virtual_devices(p).into_iter().map(|(unit, device)| (Some(unit), device)));
+        for (unit_id, profile) in profile_points {
+            for point in profile.points() {
+                info.push(ModbusInfo {
+                    plant_id:         p.id.clone(),
+                    register_address: point.address,
+                    length:           point.length,
+                    data_type:        format!("{} {}", profile.name(), point.data_type),
+                    description:      format!("{} — {}", point.description, p.name),
+                    unit:             point.unit.to_string(),
+                    writable:         false,
+                    word_order:       "ABCD".to_string(),
+                    scale:            point.scale,
+                    port:             config.modbus_port_of(p),
+                    unit_id,
+                });
+            }
+        }
+        if p.modbus_mapping.profile != ProfileKind::Custom {
+            continue;
+        }
         let base = p.modbus_mapping.base_address;
-        for (offset, regs, dtype, desc, _unit) in LAYOUT {
+        for def in REGISTER_LAYOUT {
+            let data_type = effective_data_type(p, def);
             info.push(ModbusInfo {
                 plant_id:         p.id.clone(),
-                register_address: base + offset,
-                length:           *regs,
-                data_type:        dtype.to_string(),
-                description:      format!("{} — {}", desc, p.name),
+                register_address: base + def.offset,
+                length:           data_type.len(),
+                data_type:        data_type.label().to_string(),
+                description:      format!("{} — {}", def.description, p.name),
+                unit:             def.unit.to_string(),
+                writable:         def.writable,
+                word_order:       config.modbus.word_order.as_str().to_string(),
+                scale:            effective_scale(p, def),
+                port:             config.modbus_port_of(p),
+                unit_id:          p.modbus_mapping.unit_id,
             });
         }
     }
@@ -155,7 +1458,7 @@ pub async fn get_system_config(State(config): State<Config>) -> impl IntoRespons
     Json(SystemConfig {
         api_port:            config.server.port,
         modbus_port:         config.modbus.port,
-        modbus_host:         "0.0.0.0".to_string(),
+        modbus_host:         config.modbus.bind_address.clone(),
         mqtt_enabled:        config.mqtt.enabled,
         mqtt_broker:         if config.mqtt.enabled && !config.mqtt.broker_host.is_empty() {
             Some(format!("{}:{}", config.mqtt.broker_host, config.mqtt.broker_port))
@@ -166,6 +1469,63 @@ pub async fn get_system_config(State(config): State<Config>) -> impl IntoRespons
     })
 }
 
+// ─── System state snapshot ───────────────────────────────────────────────────
+
+/// GET /api/system/snapshot
+///
+/// The whole simulation state: every plant's telemetry and internal state,
+/// the active alarms, the newest events, the grid frequency walks and the
+/// simulation clock.
+#[utoipa::path(get, path = "/api/system/snapshot",
+    responses((status = 200, description = "State snapshot document, schema `version` 1")))]
+pub async fn get_state_snapshot(State(state): State<AppState>) -> impl IntoResponse {
+    Json(StateSnapshot::of(&state))
+}
+
+/// POST /api/system/snapshot
+///
+/// Replaces the running state with a document from `GET /api/system/snapshot`;
+/// the next update continues from it. Faults, grid events and isolation
+/// faults injected over the API are not part of a snapshot.
+#[utoipa::path(post, path = "/api/system/snapshot",
+    request_body(content = Object, description = "State snapshot document"),
+    responses(
+        (status = 200, description = "{ loaded_plants: u32, taken_at: datetime }"),
+        (status = 400, description = "Not a snapshot document, or values out of range"),
+        (status = 422, description = "Unsupported schema version, or plants not in the configuration")
+    ))]
+pub async fn load_state_snapshot(
+    State(state): State<AppState>,
+    State(config): State<Config>,
+    Json(document): Json<serde_json::Value>,
+) -> impl IntoResponse {
+    let snapshot = match StateSnapshot::parse(document) {
+        Ok(snapshot) => snapshot,
+        Err(e @ SnapshotError::Unsupported(_)) => {
+            return (StatusCode::UNPROCESSABLE_ENTITY, Json(serde_json::json!({ "error": e.to_string() }))).into_response();
+        }
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e.to_string() }))).into_response(),
+    };
+    let mut unknown: Vec<&str> = snapshot.plants.keys()
+        .map(String::as_str)
+        .filter(|id| !config.plants.iter().any(|p| p.id == *id))
+        .collect();
+    if !unknown.is_empty() {
+        unknown.sort_unstable();
+        return (StatusCode::UNPROCESSABLE_ENTITY, Json(serde_json::json!({
+            "error": format!("snapshot plants not in the configuration: {}", unknown.join(", "))
+        }))).into_response();
+    }
+    let (plants, taken_at) = (snapshot.plants.len(), snapshot.taken_at);
+    if let Err(e) = snapshot.restore(&state) {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e.to_string() }))).into_response();
+    }
+    let msg = format!("State snapshot of {} loaded ({} plants)", taken_at.to_rfc3339(), plants);
+    state.push_event(None, EventKind::SettingChanged, msg.clone(), Some(serde_json::json!({ "taken_at": taken_at, "plants": plants })));
+    println!("[SETTINGS] {}", msg);
+    Json(serde_json::json!({ "loaded_plants": plants, "taken_at": taken_at })).into_response()
+}
+
 // ─── Health check ────────────────────────────────────────────────────────────
 
 /// GET /health
@@ -175,115 +1535,127 @@ pub async fn health_check(
     State(state): State<AppState>,
     State(config): State<Config>,
 ) -> impl IntoResponse {
-    let all = state.get_all_data();
-    let online = all.values().filter(|d| d.status == 1 || d.status == 5).count();
+    // A stale plant's last status is no proof it is still up
+    let online = state.map_all_data(|d| !d.stale && (d.status == 1 || d.status == 5))
+        .filter(|(_, online)| *online)
+        .count();
+    let clock = state.sim_clock();
     Json(HealthStatus {
         status:         "ok".to_string(),
         version:        env!("CARGO_PKG_VERSION").to_string(),
         uptime_seconds: state.uptime_seconds(),
+        started_at:     state.started_at(),
+        git_hash:       env!("GIT_HASH").to_string(),
         plants_online:  online,
         plants_total:   config.plants.len(),
         offline_mode:   state.is_offline(),
-        mqtt_connected: state.mqtt_connected.load(std::sync::atomic::Ordering::Relaxed),
+        mqtt_connected: state.is_mqtt_connected(),
+        clock_overridden: !clock.is_realtime(),
+        simulation_time: clock.now(),
+        clock_speed:    clock.speed(),
     })
 }
 
 // ─── Prometheus metrics endpoint ─────────────────────────────────────────────
 
+/// Per-plant series on /metrics: name, help, type, decimals and value.
+const PLANT_METRICS: &[(&str, &str, &str, usize, fn(&PlantData) -> f64)] = &[
+    ("solar_power_kw", "Active power output in kW", "gauge", 4, |d| d.power_kw),
+    ("solar_data_age_seconds", "Seconds since the last telemetry update", "gauge", 0, |d| d.update_age_s()),
+    ("solar_dc_power_kw", "DC input power in kW", "gauge", 4, |d| d.dc_power_kw),
+    ("solar_efficiency_percent", "Inverter efficiency %", "gauge", 2, |d| d.efficiency_percent),
+    ("solar_voltage_l1_v", "Phase L1 voltage in V", "gauge", 3, |d| d.voltage_l1_v),
+    ("solar_frequency_hz", "Grid frequency in Hz", "gauge", 4, |d| d.frequency_hz),
+    ("solar_temperature_c", "Cell temperature in °C", "gauge", 2, |d| d.temperature_c),
+    ("solar_inverter_temp_c", "Inverter heatsink temperature in °C", "gauge", 2, |d| d.inverter_temp_c),
+    ("solar_daily_energy_kwh", "Energy produced today in kWh", "counter", 4, |d| d.daily_energy_kwh),
+    ("solar_total_energy_kwh", "Lifetime energy produced in kWh", "counter", 4, |d| d.total_energy_kwh),
+    ("solar_performance_ratio", "IEC 61724 Performance Ratio", "gauge", 4, |d| d.performance_ratio),
+    ("solar_poa_irradiance_w_m2", "Plane-of-Array irradiance W/m²", "gauge", 2, |d| d.poa_irradiance_w_m2),
+    ("solar_poa_irradiance_true_w_m2", "Plane-of-Array irradiance before sensor noise W/m²", "gauge", 2, |d| d.poa_irradiance_true_w_m2),
+    ("solar_ghi_w_m2", "Global horizontal irradiance W/m²", "gauge", 2, |d| d.ghi_w_m2),
+    ("solar_dni_w_m2", "Direct normal irradiance W/m²", "gauge", 2, |d| d.dni_w_m2),
+    ("solar_dhi_w_m2", "Diffuse horizontal irradiance W/m²", "gauge", 2, |d| d.dhi_w_m2),
+    ("solar_isolation_resistance_mohm", "Isolation resistance DC-ground MΩ", "gauge", 3, |d| d.isolation_resistance_mohm),
+    ("solar_status", "Inverter status (0=Stop,1=Run,2=Fault,3=Curt,4=Start,5=MPPT)", "gauge", 0, |d| f64::from(d.status)),
+    ("solar_alarm_flags", "Active alarm bitmask", "gauge", 0, |d| f64::from(d.alarm_flags)),
+];
+
 /// GET /metrics  — Prometheus text format
 pub async fn prometheus_metrics(State(state): State<AppState>) -> impl IntoResponse {
-    let all = state.get_all_data();
     let mut out = String::with_capacity(4096);
 
-    out.push_str("# HELP solar_power_kw Active power output in kW\n");
-    out.push_str("# TYPE solar_power_kw gauge\n");
-    for (id, d) in &all {
-        out.push_str(&format!("solar_power_kw{{plant=\"{}\"}} {:.4}\n", id, d.power_kw));
-    }
-
-    out.push_str("# HELP solar_dc_power_kw DC input power in kW\n");
-    out.push_str("# TYPE solar_dc_power_kw gauge\n");
-    for (id, d) in &all {
-        out.push_str(&format!("solar_dc_power_kw{{plant=\"{}\"}} {:.4}\n", id, d.dc_power_kw));
-    }
-
-    out.push_str("# HELP solar_efficiency_percent Inverter efficiency %\n");
-    out.push_str("# TYPE solar_efficiency_percent gauge\n");
-    for (id, d) in &all {
-        out.push_str(&format!("solar_efficiency_percent{{plant=\"{}\"}} {:.2}\n", id, d.efficiency_percent));
+    // Only the exported figures are copied, one plant locked at a time
+    let plants: Vec<(String, Vec<f64>)> = state
+        .map_all_data(|d| PLANT_METRICS.iter().map(|(.., value)| value(d)).collect())
+        .collect();
+    for (i, (name, help, kind, decimals, _)) in PLANT_METRICS.iter().enumerate() {
+        out.push_str(&format!("# HELP {} {}\n", name, help));
+        out.push_str(&format!("# TYPE {} {}\n", name, kind));
+        for (id, values) in &plants {
+            out.push_str(&format!("{}{{plant=\"{}\"}} {:.*}\n", name, id, *decimals, values[i]));
+        }
     }
 
-    out.push_str("# HELP solar_voltage_l1_v Phase L1 voltage in V\n");
-    out.push_str("# TYPE solar_voltage_l1_v gauge\n");
-    for (id, d) in &all {
-        out.push_str(&format!("solar_voltage_l1_v{{plant=\"{}\"}} {:.3}\n", id, d.voltage_l1_v));
+    let mut active_alarms: std::collections::HashMap<String, usize> = std::collections::HashMap::new();
+    for alarm in state.get_active_alarms(None) {
+        *active_alarms.entry(alarm.plant_id).or_default() += 1;
     }
-
-    out.push_str("# HELP solar_frequency_hz Grid frequency in Hz\n");
-    out.push_str("# TYPE solar_frequency_hz gauge\n");
-    for (id, d) in &all {
-        out.push_str(&format!("solar_frequency_hz{{plant=\"{}\"}} {:.4}\n", id, d.frequency_hz));
+    out.push_str("# HELP solar_active_alarms_count Number of currently active alarms\n");
+    out.push_str("# TYPE solar_active_alarms_count gauge\n");
+    for (id, _) in &plants {
+        let cnt = active_alarms.get(id).copied().unwrap_or(0);
+        out.push_str(&format!("solar_active_alarms_count{{plant=\"{}\"}} {}\n", id, cnt));
     }
 
-    out.push_str("# HELP solar_temperature_c Cell temperature in °C\n");
-    out.push_str("# TYPE solar_temperature_c gauge\n");
-    for (id, d) in &all {
-        out.push_str(&format!("solar_temperature_c{{plant=\"{}\"}} {:.2}\n", id, d.temperature_c));
+    // ── Modbus server ──
+    let mb = &state.modbus_metrics;
+    out.push_str("# HELP modbus_requests_total Modbus requests by function\n");
+    out.push_str("# TYPE modbus_requests_total counter\n");
+    for (function, n) in mb.requests() {
+        out.push_str(&format!("modbus_requests_total{{function=\"{}\"}} {}\n", function, n));
     }
 
-    out.push_str("# HELP solar_inverter_temp_c Inverter heatsink temperature in °C\n");
-    out.push_str("# TYPE solar_inverter_temp_c gauge\n");
-    for (id, d) in &all {
-        out.push_str(&format!("solar_inverter_temp_c{{plant=\"{}\"}} {:.2}\n", id, d.inverter_temp_c));
+    out.push_str("# HELP modbus_exceptions_total Modbus exception responses by code\n");
+    out.push_str("# TYPE modbus_exceptions_total counter\n");
+    for (code, n) in mb.exceptions() {
+        out.push_str(&format!("modbus_exceptions_total{{code=\"{}\"}} {}\n", code, n));
     }
 
-    out.push_str("# HELP solar_daily_energy_kwh Energy produced today in kWh\n");
-    out.push_str("# TYPE solar_daily_energy_kwh counter\n");
-    for (id, d) in &all {
-        out.push_str(&format!("solar_daily_energy_kwh{{plant=\"{}\"}} {:.4}\n", id, d.daily_energy_kwh));
-    }
+    out.push_str("# HELP modbus_bytes_served_total Data bytes returned in Modbus responses\n");
+    out.push_str("# TYPE modbus_bytes_served_total counter\n");
+    out.push_str(&format!("modbus_bytes_served_total {}\n", mb.bytes_served()));
 
-    out.push_str("# HELP solar_total_energy_kwh Lifetime energy produced in kWh\n");
-    out.push_str("# TYPE solar_total_energy_kwh counter\n");
-    for (id, d) in &all {
-        out.push_str(&format!("solar_total_energy_kwh{{plant=\"{}\"}} {:.4}\n", id, d.total_energy_kwh));
-    }
+    out.push_str("# HELP modbus_connected_clients Currently connected Modbus TCP clients\n");
+    out.push_str("# TYPE modbus_connected_clients gauge\n");
+    out.push_str(&format!("modbus_connected_clients {}\n", mb.connected_clients()));
 
-    out.push_str("# HELP solar_performance_ratio IEC 61724 Performance Ratio\n");
-    out.push_str("# TYPE solar_performance_ratio gauge\n");
-    for (id, d) in &all {
-        out.push_str(&format!("solar_performance_ratio{{plant=\"{}\"}} {:.4}\n", id, d.performance_ratio));
-    }
+    out.push_str("# HELP modbus_connections_total Modbus TCP connections accepted since start\n");
+    out.push_str("# TYPE modbus_connections_total counter\n");
+    out.push_str(&format!("modbus_connections_total {}\n", mb.connections_total()));
 
-    out.push_str("# HELP solar_poa_irradiance_w_m2 Plane-of-Array irradiance W/m²\n");
-    out.push_str("# TYPE solar_poa_irradiance_w_m2 gauge\n");
-    for (id, d) in &all {
-        out.push_str(&format!("solar_poa_irradiance_w_m2{{plant=\"{}\"}} {:.2}\n", id, d.poa_irradiance_w_m2));
-    }
+    out.push_str("# HELP modbus_rejected_connections_total Modbus TCP connections refused by max_connections\n");
+    out.push_str("# TYPE modbus_rejected_connections_total counter\n");
+    out.push_str(&format!("modbus_rejected_connections_total {}\n", mb.rejected_connections()));
 
-    out.push_str("# HELP solar_isolation_resistance_mohm Isolation resistance DC-ground MΩ\n");
-    out.push_str("# TYPE solar_isolation_resistance_mohm gauge\n");
-    for (id, d) in &all {
-        out.push_str(&format!("solar_isolation_resistance_mohm{{plant=\"{}\"}} {:.3}\n", id, d.isolation_resistance_mohm));
-    }
+    // ── Streaming clients ──
+    let http = &state.http_metrics;
+    out.push_str("# HELP ws_dropped_notifications_total Alarm and event frames WebSocket clients missed by falling behind\n");
+    out.push_str("# TYPE ws_dropped_notifications_total counter\n");
+    out.push_str(&format!("ws_dropped_notifications_total {}\n", http.dropped_notifications()));
 
-    out.push_str("# HELP solar_status Inverter status (0=Stop,1=Run,2=Fault,3=Curt,4=Start,5=MPPT)\n");
-    out.push_str("# TYPE solar_status gauge\n");
-    for (id, d) in &all {
-        out.push_str(&format!("solar_status{{plant=\"{}\"}} {}\n", id, d.status));
-    }
+    out.push_str("# HELP sse_connected_clients Currently open Server-Sent Events telemetry streams\n");
+    out.push_str("# TYPE sse_connected_clients gauge\n");
+    out.push_str(&format!("sse_connected_clients {}\n", http.sse_clients()));
 
-    out.push_str("# HELP solar_alarm_flags Active alarm bitmask\n");
-    out.push_str("# TYPE solar_alarm_flags gauge\n");
-    for (id, d) in &all {
-        out.push_str(&format!("solar_alarm_flags{{plant=\"{}\"}} {}\n", id, d.alarm_flags));
-    }
+    out.push_str("# HELP sse_connections_total Server-Sent Events telemetry streams opened since start\n");
+    out.push_str("# TYPE sse_connections_total counter\n");
+    out.push_str(&format!("sse_connections_total {}\n", http.sse_connections_total()));
 
-    out.push_str("# HELP solar_active_alarms_count Number of currently active alarms\n");
-    out.push_str("# TYPE solar_active_alarms_count gauge\n");
-    for (id, _) in &all {
-        let cnt = state.get_active_alarms(Some(id)).len();
-        out.push_str(&format!("solar_active_alarms_count{{plant=\"{}\"}} {}\n", id, cnt));
+    out.push_str("# HELP http_rate_limited_total Requests refused with 429 by the per-IP rate limiter\n");
+    out.push_str("# TYPE http_rate_limited_total counter\n");
+    for (class, count) in http.rate_limited() {
+        out.push_str(&format!("http_rate_limited_total{{class=\"{}\"}} {}\n", class, count));
     }
 
     (
@@ -295,44 +1667,188 @@ pub async fn prometheus_metrics(State(state): State<AppState>) -> impl IntoRespo
 
 // ─── Alarm endpoints ─────────────────────────────────────────────────────────
 
-#[derive(Deserialize)]
+/// Header with the number of entries matching the filters, before paging
+pub const TOTAL_COUNT: &str = "x-total-count";
+/// Header with the cursor of the next page, while there is one
+pub const NEXT_CURSOR: &str = "x-next-cursor";
+
+/// Time window and page of an alarm or event listing.
+struct Page<'a> {
+    from:   Option<chrono::DateTime<chrono::Utc>>,
+    to:     Option<chrono::DateTime<chrono::Utc>>,
+    offset: Option<usize>,
+    cursor: Option<&'a str>,
+    limit:  usize,
+}
+
+impl Page<'_> {
+    /// Entries within `[from, to)`, newest first (ties keep the newest
+    /// logged first), cut to the page: `offset` entries skipped, or every
+    /// entry up to and including the one with id `cursor`. Returns the page,
+    /// the number of entries in the window and the cursor of the next page.
+    fn select<T>(
+        &self,
+        newest_first: Vec<T>,
+        key: impl Fn(&T) -> (chrono::DateTime<chrono::Utc>, &str),
+    ) -> Result<(Vec<T>, usize, Option<String>), String> {
+        if let (Some(from), Some(to)) = (self.from, self.to) {
+            if from > to {
+                return Err(format!("from ({}) is after to ({})", from.to_rfc3339(), to.to_rfc3339()));
+            }
+        }
+        if self.offset.is_some() && self.cursor.is_some() {
+            return Err("offset and cursor cannot be combined".to_string());
+        }
+        let mut entries: Vec<T> = newest_first.into_iter()
+            .filter(|e| self.from.is_none_or(|from| key(e).0 >= from) && self.to.is_none_or(|to| key(e).0 < to))
+            .collect();
+        entries.sort_by(|a, b| key(b).0.cmp(&key(a).0));
+        let total = entries.len();
+        let start = match self.cursor {
+            Some(cursor) => entries.iter().position(|e| key(e).1 == cursor).map(|i| i + 1)
+                .ok_or_else(|| format!("cursor '{}' is not in the listing: it rotated out or does not match the filters", cursor))?,
+            None => self.offset.unwrap_or(0),
+        };
+        let page: Vec<T> = entries.into_iter().skip(start).take(self.limit).collect();
+        let next = (start + page.len() < total).then(|| page.last().map(|e| key(e).1.to_string())).flatten();
+        Ok((page, total, next))
+    }
+}
+
+/// The page as JSON with its total count and next cursor headers, or 422.
+fn page_response<T: serde::Serialize>(page: Result<(Vec<T>, usize, Option<String>), String>) -> axum::response::Response {
+    match page {
+        Ok((entries, total, next)) => {
+            let mut headers = HeaderMap::new();
+            headers.insert(TOTAL_COUNT, total.into());
+            if let Some(next) = next.and_then(|c| header::HeaderValue::from_str(&c).ok()) {
+                headers.insert(NEXT_CURSOR, next);
+            }
+            (headers, Json(entries)).into_response()
+        }
+        Err(e) => (StatusCode::UNPROCESSABLE_ENTITY, Json(serde_json::json!({ "error": e }))).into_response(),
+    }
+}
+
+#[derive(Default, Deserialize)]
 pub struct AlarmQuery {
     pub active_only: Option<bool>,
+    /// Only the alarms no operator has acknowledged yet
+    pub unacknowledged_only: Option<bool>,
+    pub severity: Option<AlarmSeverity>,
+    /// Raised at or after
+    pub from: Option<chrono::DateTime<chrono::Utc>>,
+    /// Raised before
+    pub to: Option<chrono::DateTime<chrono::Utc>>,
+    pub offset: Option<usize>,
+    /// Id of the last alarm of the previous page
+    pub cursor: Option<String>,
     pub limit: Option<usize>,
 }
 
+impl AlarmQuery {
+    fn select(&self, state: &AppState, plant_id: Option<&str>, default_limit: usize) -> axum::response::Response {
+        let active_only = self.active_only.unwrap_or(false);
+        let unacknowledged_only = self.unacknowledged_only.unwrap_or(false);
+        // The registry keeps the oldest first
+        let alarms: Vec<Alarm> = state.get_alarms(plant_id).into_iter()
+            .rev()
+            .filter(|a| !(active_only && !a.active))
+            .filter(|a| !(unacknowledged_only && a.acknowledged))
+            .filter(|a| self.severity.as_ref().is_none_or(|s| a.severity == *s))
+            .collect();
+        let page = Page {
+            from:   self.from,
+            to:     self.to,
+            offset: self.offset,
+            cursor: self.cursor.as_deref(),
+            limit:  self.limit.unwrap_or(default_limit),
+        };
+        page_response(page.select(alarms, |a| (a.timestamp, a.id.as_str())))
+    }
+}
+
 /// GET /api/plants/{id}/alarms
 #[utoipa::path(get, path = "/api/plants/{id}/alarms",
-    params(("id" = String, Path, description = "Plant ID")),
-    responses((status = 200, description = "Alarm list", body = Vec<Alarm>)))]
+    params(
+        ("id" = String, Path, description = "Plant ID"),
+        ("active_only" = Option<bool>, Query, description = "Only the active alarms"),
+        ("unacknowledged_only" = Option<bool>, Query, description = "Only the alarms no operator has acknowledged"),
+        ("severity" = Option<AlarmSeverity>, Query, description = "Only alarms of this severity, e.g. WARNING"),
+        ("from" = Option<String>, Query, description = "Raised at or after (RFC 3339)"),
+        ("to" = Option<String>, Query, description = "Raised before (RFC 3339)"),
+        ("offset" = Option<usize>, Query, description = "Alarms skipped (not with cursor)"),
+        ("cursor" = Option<String>, Query, description = "Continue after this alarm id, from X-Next-Cursor (not with offset)"),
+        ("limit" = Option<usize>, Query, description = "Alarms returned (default 100)")
+    ),
+    responses(
+        (status = 200, description = "Alarms newest first; X-Total-Count holds the number matching the filters, X-Next-Cursor the cursor of the next page", body = Vec<Alarm>),
+        (status = 422, description = "from after to, offset with cursor, or a cursor no longer listed")
+    ))]
 pub async fn get_plant_alarms(
     Path(id): Path<String>,
     Query(q): Query<AlarmQuery>,
     State(state): State<AppState>,
 ) -> impl IntoResponse {
-    let alarms = if q.active_only.unwrap_or(false) {
-        state.get_active_alarms(Some(&id))
-    } else {
-        state.get_alarms(Some(&id))
-    };
-    let limit = q.limit.unwrap_or(100);
-    Json(alarms.into_iter().take(limit).collect::<Vec<_>>())
+    q.select(&state, Some(&id), 100)
 }
 
 /// GET /api/alarms
 #[utoipa::path(get, path = "/api/alarms",
-    responses((status = 200, description = "All alarms across all plants", body = Vec<Alarm>)))]
+    params(
+        ("active_only" = Option<bool>, Query, description = "Only the active alarms"),
+        ("unacknowledged_only" = Option<bool>, Query, description = "Only the alarms no operator has acknowledged"),
+        ("severity" = Option<AlarmSeverity>, Query, description = "Only alarms of this severity, e.g. WARNING"),
+        ("from" = Option<String>, Query, description = "Raised at or after (RFC 3339)"),
+        ("to" = Option<String>, Query, description = "Raised before (RFC 3339)"),
+        ("offset" = Option<usize>, Query, description = "Alarms skipped (not with cursor)"),
+        ("cursor" = Option<String>, Query, description = "Continue after this alarm id, from X-Next-Cursor (not with offset)"),
+        ("limit" = Option<usize>, Query, description = "Alarms returned (default 200)")
+    ),
+    responses(
+        (status = 200, description = "Alarms of every plant, newest first; X-Total-Count holds the number matching the filters, X-Next-Cursor the cursor of the next page", body = Vec<Alarm>),
+        (status = 422, description = "from after to, offset with cursor, or a cursor no longer listed")
+    ))]
 pub async fn get_all_alarms(
     Query(q): Query<AlarmQuery>,
     State(state): State<AppState>,
 ) -> impl IntoResponse {
-    let alarms = if q.active_only.unwrap_or(false) {
-        state.get_active_alarms(None)
-    } else {
-        state.get_alarms(None)
-    };
-    let limit = q.limit.unwrap_or(200);
-    Json(alarms.into_iter().take(limit).collect::<Vec<_>>())
+    q.select(&state, None, 200)
+}
+
+/// Operator acknowledging an alarm.
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct AlarmAckBody {
+    pub acknowledged_by: String,
+    #[serde(default)]
+    pub note: Option<String>,
+}
+
+/// POST /api/alarms/{alarm_id}/ack
+///
+/// Marks one alarm as seen by an operator, with an optional note. An active
+/// alarm stays active while its condition persists and clears on its own.
+#[utoipa::path(post, path = "/api/alarms/{alarm_id}/ack",
+    params(("alarm_id" = String, Path, description = "Alarm ID")),
+    request_body = AlarmAckBody,
+    responses(
+        (status = 200, description = "Alarm acknowledged", body = Alarm),
+        (status = 400, description = "acknowledged_by is empty"),
+        (status = 404, description = "Alarm not found")
+    ))]
+pub async fn acknowledge_alarm(
+    Path(alarm_id): Path<String>,
+    State(state): State<AppState>,
+    Json(body): Json<AlarmAckBody>,
+) -> impl IntoResponse {
+    let operator = body.acknowledged_by.trim();
+    if operator.is_empty() {
+        return (StatusCode::BAD_REQUEST, Json(serde_json::json!({"error": "acknowledged_by must not be empty"}))).into_response();
+    }
+    match state.acknowledge_alarm(&alarm_id, operator, body.note.as_deref()) {
+        Some(alarm) => Json(alarm).into_response(),
+        None => (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Alarm not found"}))).into_response(),
+    }
 }
 
 /// DELETE /api/plants/{id}/alarms  — acknowledge all active alarms
@@ -346,20 +1862,49 @@ pub async fn clear_plant_alarms(
 
 // ─── Event log ───────────────────────────────────────────────────────────────
 
-#[derive(Deserialize)]
+#[derive(Default, Deserialize)]
 pub struct EventQuery {
     pub limit: Option<usize>,
+    pub kind: Option<EventKind>,
+    pub plant_id: Option<String>,
+    /// Logged at or after
+    pub from: Option<chrono::DateTime<chrono::Utc>>,
+    /// Logged before
+    pub to: Option<chrono::DateTime<chrono::Utc>>,
+    pub offset: Option<usize>,
+    /// Id of the last event of the previous page
+    pub cursor: Option<String>,
 }
 
 /// GET /api/events
 #[utoipa::path(get, path = "/api/events",
-    responses((status = 200, description = "System event log", body = Vec<Event>)))]
+    params(
+        ("limit" = Option<usize>, Query, description = "Events returned (default 100)"),
+        ("kind" = Option<EventKind>, Query, description = "Only events of this kind, e.g. ALARM_RAISED"),
+        ("plant_id" = Option<String>, Query, description = "Only events about this plant"),
+        ("from" = Option<String>, Query, description = "Logged at or after (RFC 3339)"),
+        ("to" = Option<String>, Query, description = "Logged before (RFC 3339)"),
+        ("offset" = Option<usize>, Query, description = "Events skipped (not with cursor)"),
+        ("cursor" = Option<String>, Query, description = "Continue after this event id, from X-Next-Cursor (not with offset)")
+    ),
+    responses(
+        (status = 200, description = "System event log, newest first; X-Total-Count holds the number matching the filters, X-Next-Cursor the cursor of the next page", body = Vec<Event>),
+        (status = 400, description = "Unknown event kind"),
+        (status = 422, description = "from after to, offset with cursor, or a cursor no longer listed")
+    ))]
 pub async fn get_events(
     Query(q): Query<EventQuery>,
     State(state): State<AppState>,
 ) -> impl IntoResponse {
-    let limit = q.limit.unwrap_or(100).min(1000);
-    Json(state.get_events(limit))
+    let events = state.find_events(q.kind.as_ref(), q.plant_id.as_deref(), MAX_EVENT_LOG);
+    let page = Page {
+        from:   q.from,
+        to:     q.to,
+        offset: q.offset,
+        cursor: q.cursor.as_deref(),
+        limit:  q.limit.unwrap_or(100).min(MAX_EVENT_LOG),
+    };
+    page_response(page.select(events, |e| (e.timestamp, e.id.as_str())))
 }
 
 // ─── Settings: Offline Mode ──────────────────────────────────────────────────
@@ -378,11 +1923,19 @@ pub struct OfflineModeBody {
 
 /// POST /api/settings/offline-mode
 #[utoipa::path(post, path = "/api/settings/offline-mode",
-    responses((status = 200, description = "{ offline_mode: bool, message: string }")))]
+    responses(
+        (status = 200, description = "{ offline_mode: bool, message: string }"),
+        (status = 409, description = "The simulation clock is off the wall clock")
+    ))]
 pub async fn set_offline_mode(
     State(state): State<AppState>,
     Json(body): Json<OfflineModeBody>,
 ) -> impl IntoResponse {
+    if !body.enabled && !state.sim_clock().is_realtime() {
+        return (StatusCode::CONFLICT, Json(serde_json::json!({
+            "error": "Online mode needs the real-time clock: set the simulation clock back to 1× now first"
+        }))).into_response();
+    }
     state.set_offline(body.enabled);
     let msg = if body.enabled {
         "Offline mode ENABLED — using solar geometry algorithm"
@@ -390,42 +1943,324 @@ pub async fn set_offline_mode(
         "Online mode ENABLED — fetching from Open-Meteo API"
     };
     println!("[SETTINGS] {}", msg);
-    Json(serde_json::json!({ "offline_mode": body.enabled, "message": msg }))
+    Json(serde_json::json!({ "offline_mode": body.enabled, "message": msg })).into_response()
+}
+
+// ─── Settings: Simulation seed ───────────────────────────────────────────────
+
+/// GET /api/settings/simulation-seed
+#[utoipa::path(get, path = "/api/settings/simulation-seed",
+    responses((status = 200, description = "{ simulation_seed: u64 }")))]
+pub async fn get_simulation_seed(State(state): State<AppState>) -> impl IntoResponse {
+    Json(serde_json::json!({ "simulation_seed": state.simulation_seed() }))
+}
+
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct SimulationSeedBody {
+    pub seed: u64,
+}
+
+/// POST /api/settings/simulation-seed
+///
+/// Replaces `simulation.seed` until restart; plants with their own `seed` keep it.
+#[utoipa::path(post, path = "/api/settings/simulation-seed",
+    request_body = SimulationSeedBody,
+    responses((status = 200, description = "{ simulation_seed: u64 }")))]
+pub async fn set_simulation_seed(
+    State(state): State<AppState>,
+    Json(body): Json<SimulationSeedBody>,
+) -> impl IntoResponse {
+    state.set_simulation_seed(body.seed);
+    state.push_event(None, EventKind::SettingChanged, format!("Simulation seed changed to {}", body.seed), None);
+    println!("[SETTINGS] Simulation seed set to {}", body.seed);
+    Json(serde_json::json!({ "simulation_seed": body.seed }))
+}
+
+// ─── Settings: Simulation clock ──────────────────────────────────────────────
+
+fn sim_clock_json(clock: &SimClock) -> serde_json::Value {
+    serde_json::json!({
+        "speed": clock.speed(), "now": clock.now(), "offset_s": clock.offset_s(), "realtime": clock.is_realtime(),
+    })
+}
+
+/// GET /api/settings/sim-clock
+#[utoipa::path(get, path = "/api/settings/sim-clock",
+    responses((status = 200, description = "{ speed: f64, now: datetime, offset_s: f64, realtime: bool }")))]
+pub async fn get_sim_clock(State(state): State<AppState>) -> impl IntoResponse {
+    Json(sim_clock_json(&state.sim_clock()))
+}
+
+#[derive(Deserialize, utoipa::ToSchema)]
+pub struct SimClockBody {
+    /// Simulated seconds per wall-clock second; default = unchanged
+    pub speed: Option<f64>,
+    /// Simulated time to continue from, RFC 3339 with any offset (e.g. the
+    /// plant's local time); default = the current simulated time
+    pub start: Option<chrono::DateTime<chrono::Utc>>,
+    /// Or: continue this many seconds from the wall clock (negative = in the past)
+    pub offset_s: Option<f64>,
+    /// Back to the wall clock at 1×; excludes the other fields
+    #[serde(default)]
+    pub realtime: bool,
+}
+
+/// POST /api/settings/sim-clock
+///
+/// Replaces `simulation.speed` / `simulation.start` until restart. Only in
+/// offline mode: Open-Meteo serves the current weather only.
+#[utoipa::path(post, path = "/api/settings/sim-clock",
+    request_body = SimClockBody,
+    responses(
+        (status = 200, description = "{ speed: f64, now: datetime, offset_s: f64, realtime: bool }"),
+        (status = 400, description = "Speed out of range, or conflicting fields"),
+        (status = 409, description = "Acceleration or a moved clock requested in online mode")
+    ))]
+pub async fn set_sim_clock(
+    State(state): State<AppState>,
+    Json(body): Json<SimClockBody>,
+) -> impl IntoResponse {
+    let bad_request = |e: &str| (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e }))).into_response();
+    let moved = body.speed.is_some() || body.start.is_some() || body.offset_s.is_some();
+    if body.realtime && moved {
+        return bad_request("realtime excludes speed, start and offset_s");
+    }
+    if body.start.is_some() && body.offset_s.is_some() {
+        return bad_request("give start or offset_s, not both");
+    }
+    let wall = chrono::Utc::now();
+    let current = state.sim_clock();
+    let start = match (body.start, body.offset_s) {
+        (Some(start), _) => start,
+        (_, Some(offset_s)) => wall + chrono::Duration::milliseconds((offset_s * 1000.0) as i64),
+        _ => current.now(),
+    };
+    let clock = if body.realtime {
+        SimClock::default()
+    } else {
+        match SimClock::new(body.speed.unwrap_or(current.speed()), Some(start), wall) {
+            // Back at 1× within a second of the wall clock is real time again
+            Ok(clock) if clock.speed() == 1.0 && clock.offset_s().abs() < 1.0 => SimClock::default(),
+            Ok(clock) => clock,
+            Err(e) => return bad_request(&e),
+        }
+    };
+    if let Err(e) = state.set_sim_clock(clock) {
+        return (StatusCode::CONFLICT, Json(serde_json::json!({ "error": e }))).into_response();
+    }
+    let msg = if clock.is_realtime() {
+        "Simulation clock back to real time".to_string()
+    } else {
+        format!("Simulation clock set to {}× from {}", clock.speed(), clock.now().to_rfc3339())
+    };
+    state.push_event(None, EventKind::SettingChanged, msg.clone(), Some(sim_clock_json(&clock)));
+    println!("[SETTINGS] {}", msg);
+    Json(sim_clock_json(&clock)).into_response()
 }
 
 // ─── WebSocket real-time telemetry ────────────────────────────────────────────
 
-/// GET /ws/telemetry — WebSocket endpoint streaming all plant telemetry at 2s
+/// Seconds between two telemetry frames until a client subscribes otherwise
+const WS_DEFAULT_INTERVAL_S: u64 = 2;
+/// Slowest push interval a subscription may ask for (s)
+const WS_MAX_INTERVAL_S: u64 = 60;
+
+/// What a WebSocket client receives: unset plants or fields mean all of them.
+#[derive(Clone, Debug, Default, Deserialize)]
+pub struct Subscription {
+    pub plants:     Option<Vec<String>>,
+    pub fields:     Option<Vec<String>>,
+    /// Seconds between telemetry frames, 1–60 (default 2)
+    pub interval_s: Option<u64>,
+    /// Alarm frames as alarms are raised, cleared or acknowledged (default true)
+    pub alarms:     Option<bool>,
+    /// Event frames as events are logged (default true)
+    pub events:     Option<bool>,
+}
+
+/// The one frame a client sends: `{"subscribe": {…}}`.
+#[derive(Deserialize)]
+struct ClientFrame {
+    subscribe: Subscription,
+}
+
+/// Names of the telemetry fields a subscription may pick.
+fn telemetry_fields() -> Vec<String> {
+    match serde_json::to_value(PlantData::default()) {
+        Ok(serde_json::Value::Object(fields)) => fields.into_iter().map(|(name, _)| name).collect(),
+        _ => Vec::new(),
+    }
+}
+
+impl Subscription {
+    /// The subscription checked against the configured plants and the
+    /// telemetry fields; a socket `pinned` to one plant streams only that one.
+    fn validate(self, known_plants: &[String], pinned: Option<&str>) -> Result<Subscription, String> {
+        if let Some(plants) = &self.plants {
+            if let Some(unknown) = plants.iter().find(|id| !known_plants.contains(id)) {
+                return Err(format!("unknown plant '{}'", unknown));
+            }
+            if let Some(pinned) = pinned {
+                if plants.iter().any(|id| id != pinned) {
+                    return Err(format!("this socket only streams plant '{}'", pinned));
+                }
+            }
+        }
+        if let Some(fields) = &self.fields {
+            let known = telemetry_fields();
+            if let Some(unknown) = fields.iter().find(|f| !known.contains(f)) {
+                return Err(format!("unknown field '{}'", unknown));
+            }
+        }
+        if let Some(interval_s) = self.interval_s {
+            if !(1..=WS_MAX_INTERVAL_S).contains(&interval_s) {
+                return Err(format!("interval_s must be 1–{}, got {}", WS_MAX_INTERVAL_S, interval_s));
+            }
+        }
+        Ok(Subscription {
+            plants: self.plants.or_else(|| pinned.map(|id| vec![id.to_string()])),
+            ..self
+        })
+    }
+
+    fn interval_s(&self) -> u64 {
+        self.interval_s.unwrap_or(WS_DEFAULT_INTERVAL_S)
+    }
+
+    /// Acknowledgement with the subscription now in effect (null = all).
+    fn ack(&self) -> serde_json::Value {
+        serde_json::json!({
+            "type":       "subscribed",
+            "plants":     self.plants,
+            "fields":     self.fields,
+            "interval_s": self.interval_s(),
+            "alarms":     self.alarms.unwrap_or(true),
+            "events":     self.events.unwrap_or(true),
+        })
+    }
+
+    /// Frame of an alarm or event, unless opted out or about a plant not
+    /// subscribed; events about no plant in particular always pass.
+    fn notification(&self, notification: &Notification) -> Option<serde_json::Value> {
+        let (kind, plant_id, value) = match notification {
+            Notification::Alarm(alarm) if self.alarms.unwrap_or(true) =>
+                ("alarm", Some(alarm.plant_id.as_str()), serde_json::to_value(alarm)),
+            Notification::Event(event) if self.events.unwrap_or(true) =>
+                ("event", event.plant_id.as_deref(), serde_json::to_value(event)),
+            _ => return None,
+        };
+        if let (Some(plants), Some(plant_id)) = (&self.plants, plant_id) {
+            if !plants.iter().any(|id| id == plant_id) {
+                return None;
+            }
+        }
+        let mut frame = value.ok()?;
+        frame.as_object_mut()?.insert("type".to_string(), kind.into());
+        Some(frame)
+    }
+
+    /// Telemetry frame of the subscribed plants, trimmed to the subscribed fields.
+    fn telemetry(&self, state: &AppState) -> serde_json::Value {
+        // Each plant is serialised under its own lock, without a copy of its telemetry
+        let frame = |data: &PlantData| {
+            let mut value = serde_json::to_value(data).unwrap_or_default();
+            if let (Some(fields), serde_json::Value::Object(map)) = (&self.fields, &mut value) {
+                map.retain(|name, _| fields.contains(name));
+            }
+            value
+        };
+        let plants: serde_json::Map<String, serde_json::Value> = match &self.plants {
+            Some(ids) => ids.iter().filter_map(|id| state.plant_data.read(id, frame).map(|v| (id.clone(), v))).collect(),
+            None      => state.map_all_data(frame).collect(),
+        };
+        serde_json::json!({
+            "type": "telemetry",
+            "timestamp": state.now().to_rfc3339(),
+            "plants": plants,
+        })
+    }
+}
+
+/// GET /ws/telemetry — WebSocket endpoint streaming all plant telemetry at 2s,
+/// trimmed by the subscription the client sends
 pub async fn ws_telemetry(
     ws: WebSocketUpgrade,
     State(state): State<AppState>,
+    State(registry): State<PlantRegistry>,
 ) -> impl IntoResponse {
-    ws.on_upgrade(move |socket| handle_ws(socket, state))
+    ws.on_upgrade(move |socket| handle_ws(socket, state, registry, None))
 }
 
-async fn handle_ws(socket: WebSocket, state: AppState) {
+/// GET /ws/plants/{id} — the same stream for one plant
+pub async fn ws_plant_telemetry(
+    ws: WebSocketUpgrade,
+    Path(id): Path<String>,
+    State(state): State<AppState>,
+    State(registry): State<PlantRegistry>,
+) -> impl IntoResponse {
+    if !registry.plants().iter().any(|p| p.id == id) {
+        return (StatusCode::NOT_FOUND, Json(serde_json::json!({"error": "Plant not found"}))).into_response();
+    }
+    ws.on_upgrade(move |socket| handle_ws(socket, state, registry, Some(id))).into_response()
+}
+
+/// Push loop of both endpoints: telemetry on the interval, alarms and events
+/// as they happen. A subscription replaces the previous one; a rejected one
+/// leaves it in place and the socket open.
+async fn handle_ws(socket: WebSocket, state: AppState, registry: PlantRegistry, pinned: Option<String>) {
     let (mut sender, mut receiver) = socket.split();
-    let mut interval = tokio::time::interval(Duration::from_secs(2));
+    let mut notifications = state.subscribe_notifications();
+    let mut subscription = Subscription { plants: pinned.clone().map(|id| vec![id]), ..Subscription::default() };
+    let mut interval = tokio::time::interval(Duration::from_secs(subscription.interval_s()));
 
     loop {
         tokio::select! {
             _ = interval.tick() => {
-                let all = state.get_all_data();
-                let payload = serde_json::json!({
-                    "type": "telemetry",
-                    "timestamp": chrono::Utc::now().to_rfc3339(),
-                    "plants": all,
-                });
+                let payload = subscription.telemetry(&state);
                 if sender.send(Message::Text(payload.to_string().into())).await.is_err() {
                     break; // client disconnected
                 }
             }
+            notice = notifications.recv() => {
+                let frame = match notice {
+                    Ok(notice) => subscription.notification(&notice),
+                    // Too slow a client misses frames rather than hold the others up
+                    Err(tokio::sync::broadcast::error::RecvError::Lagged(missed)) => {
+                        state.http_metrics.record_dropped_notifications(missed);
+                        None
+                    }
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                };
+                if let Some(frame) = frame {
+                    if sender.send(Message::Text(frame.to_string().into())).await.is_err() {
+                        break;
+                    }
+                }
+            }
             msg = receiver.next() => {
                 match msg {
                     Some(Ok(Message::Close(_))) | None => break,
                     Some(Ok(Message::Ping(d))) => {
                         let _ = sender.send(Message::Pong(d)).await;
                     }
+                    Some(Ok(Message::Text(text))) => {
+                        let known: Vec<String> = registry.plants().into_iter().map(|p| p.id).collect();
+                        let requested = serde_json::from_str::<ClientFrame>(&text)
+                            .map_err(|e| format!("expected {{\"subscribe\": {{\"plants\", \"fields\", \"interval_s\"}}}}: {}", e))
+                            .and_then(|frame| frame.subscribe.validate(&known, pinned.as_deref()));
+                        let reply = match requested {
+                            Ok(accepted) => {
+                                subscription = accepted;
+                                // The first tick comes at once: the trimmed frame follows the ack
+                                interval = tokio::time::interval(Duration::from_secs(subscription.interval_s()));
+                                subscription.ack()
+                            }
+                            Err(e) => serde_json::json!({ "type": "error", "error": e }),
+                        };
+                        if sender.send(Message::Text(reply.to_string().into())).await.is_err() {
+                            break;
+                        }
+                    }
                     _ => {}
                 }
             }
@@ -433,4 +2268,597 @@ async fn handle_ws(socket: WebSocket, state: AppState) {
     }
 }
 
+// ─── Server-Sent Events telemetry ─────────────────────────────────────────────
+
+/// Comment lines sent between events so proxies keep the stream open
+const SSE_HEARTBEAT: Duration = Duration::from_secs(15);
+
+#[derive(Deserialize)]
+pub struct StreamQuery {
+    /// Seconds between events, 1–60 (default 2)
+    pub interval_s: Option<u64>,
+    /// Comma-separated plant ids (default: every plant)
+    pub plants: Option<String>,
+    /// Comma-separated telemetry fields (default: every field)
+    pub fields: Option<String>,
+}
+
+/// Counts one open event stream on /metrics while it lives.
+struct SseClient(std::sync::Arc<crate::shared_state::HttpMetrics>);
+
+impl SseClient {
+    fn open(state: &AppState) -> Self {
+        state.http_metrics.sse_client_connected();
+        SseClient(state.http_metrics.clone())
+    }
+}
+
+impl Drop for SseClient {
+    fn drop(&mut self) {
+        self.0.sse_client_disconnected();
+    }
+}
+
+/// GET /api/stream/telemetry
+///
+/// The WebSocket telemetry frames as a `text/event-stream`, for clients
+/// behind proxies that do not pass WebSocket. Event ids count up; a client
+/// reconnecting with `Last-Event-ID` carries on from the next one, with the
+/// current telemetry.
+#[utoipa::path(get, path = "/api/stream/telemetry",
+    params(
+        ("interval_s" = Option<u64>, Query, description = "Seconds between events, 1–60 (default 2)"),
+        ("plants" = Option<String>, Query, description = "Comma-separated plant ids (default: every plant)"),
+        ("fields" = Option<String>, Query, description = "Comma-separated telemetry fields, e.g. power_kw,status (default: every field)"),
+        ("Last-Event-ID" = Option<u64>, Header, description = "Id of the last event received, to resume after it")
+    ),
+    responses(
+        (status = 200, description = "`telemetry` events with the WebSocket payload, heartbeat comments every 15 s", content_type = "text/event-stream", body = String),
+        (status = 400, description = "Unknown plant or field, or interval out of range")
+    ))]
+pub async fn stream_telemetry(
+    Query(q): Query<StreamQuery>,
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    State(registry): State<PlantRegistry>,
+) -> impl IntoResponse {
+    use axum::response::sse::{Event as SseEvent, KeepAlive, Sse};
+
+    let list = |csv: Option<String>| csv.map(|csv| csv.split(',').map(|s| s.trim().to_string()).filter(|s| !s.is_empty()).collect());
+    let requested = Subscription { plants: list(q.plants), fields: list(q.fields), interval_s: q.interval_s, ..Subscription::default() };
+    let known: Vec<String> = registry.plants().into_iter().map(|p| p.id).collect();
+    let subscription = match requested.validate(&known, None) {
+        Ok(subscription) => subscription,
+        Err(e) => return (StatusCode::BAD_REQUEST, Json(serde_json::json!({ "error": e }))).into_response(),
+    };
+    let first_id = headers.get("last-event-id")
+        .and_then(|id| id.to_str().ok())
+        .and_then(|id| id.trim().parse::<u64>().ok())
+        .map_or(1, |last| last.saturating_add(1));
+    let interval = tokio::time::interval(Duration::from_secs(subscription.interval_s()));
+    let client = SseClient::open(&state);
+    let events = futures_util::stream::unfold((interval, first_id, client), move |(mut interval, id, client)| {
+        let state = state.clone();
+        let subscription = subscription.clone();
+        async move {
+            interval.tick().await;
+            let event = SseEvent::default()
+                .id(id.to_string())
+                .event("telemetry")
+                .data(subscription.telemetry(&state).to_string());
+            Some((Ok::<_, std::convert::Infallible>(event), (interval, id + 1, client)))
+        }
+    });
+    Sse::new(events)
+        .keep_alive(KeepAlive::new().interval(SSE_HEARTBEAT).text("heartbeat"))
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::WordOrder;
+    use crate::modbus_server::{build_register_map, read_registers, RegisterTable};
+    use crate::models::power::{SimulationData, WeatherSource};
+    use crate::services::pv_string::StringLayout;
+    use crate::shared_state::tests::{rated, sample, steady_grid, tick};
+    use crate::shared_state::SharedState;
+    use chrono::Utc;
+
+    #[tokio::test]
+    async fn reactive_setpoints_are_shared_by_rest_and_modbus() {
+        use crate::modbus_server::{write_registers, REG_COS_PHI_SETPOINT, REG_Q_MODE, REG_Q_SETPOINT_KVAR};
+
+        let config: crate::config::Config = serde_json::from_value(serde_json::json!({
+            "server": { "port": 3000 }, "modbus": { "port": 5020 },
+            "plants": [{
+                "id": "plant_1", "name": "plant_1", "latitude": 45.0, "longitude": 7.0,
+                "nominal_power_kw": 1000.0, "timezone": "Europe/Rome",
+                "modbus_mapping": { "base_address": 0 }, "inverter": { "max_kva": 1100.0 }
+            }]
+        })).unwrap();
+        assert_eq!(config.plants[0].max_kva(), 1100.0);
+        let state = AppState::new(true);
+        let maps = build_register_map(&config.plants);
+        let holding = |addr| read_registers(&state, &maps.shared.registers, RegisterTable::Holding,
+            WordOrder::Abcd, addr, 1).unwrap()[0];
+        let body = |response: axum::response::Response| async move {
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            serde_json::from_slice::<serde_json::Value>(&bytes).unwrap()
+        };
+
+        let response = set_reactive_power(Path("plant_1".into()), State(state.clone()), State(config.clone()),
+            axum::Json(ReactivePowerBody { mode: Some(ReactivePowerMode::FixedQ), cos_phi_setpoint: Some(0.9), q_setpoint_kvar: Some(-250.0) }),
+        ).await.into_response();
+        assert_eq!(body(response).await["mode"], "fixed_q");
+        assert_eq!(holding(REG_Q_MODE), 2);
+        assert_eq!(holding(REG_Q_SETPOINT_KVAR), (-250i16) as u16);
+        assert_eq!(holding(REG_COS_PHI_SETPOINT), 900);
+
+        // A Modbus write shows up on REST
+        write_registers(&state, &maps.shared.registers, REG_Q_MODE, &[ReactivePowerMode::CosPhi.register_value()]).unwrap();
+        let response = get_reactive_power(Path("plant_1".into()), State(state.clone()), State(config.clone())).await.into_response();
+        assert_eq!(body(response).await["mode"], "cos_phi");
+
+        let response = set_reactive_power(Path("plant_1".into()), State(state.clone()), State(config.clone()),
+            axum::Json(ReactivePowerBody { mode: None, cos_phi_setpoint: Some(0.5), q_setpoint_kvar: None }),
+        ).await.into_response();
+        assert_eq!(response.status(), axum::http::StatusCode::BAD_REQUEST);
+        assert_eq!(holding(REG_COS_PHI_SETPOINT), 900);
+    }
+
+    #[tokio::test]
+    async fn the_weather_endpoint_reports_the_latest_readings_and_their_age() {
+        let state = AppState::new(true);
+        steady_grid(&state);
+        tick(&state);
+        let layout = StringLayout::sized_for(100.0);
+        let weather = |state: AppState, plant_id: &str| {
+            let plant_id = plant_id.to_string();
+            async move {
+                let response = get_plant_weather(Path(plant_id), State(state)).await.into_response();
+                let status = response.status();
+                let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&bytes).unwrap())
+            }
+        };
+
+        // Open-Meteo's current conditions are ten minutes old
+        state.set_data("plant_1", &rated(100.0), &SimulationData {
+            timestamp: state.now() - chrono::Duration::minutes(10), source: WeatherSource::OpenMeteo,
+            weather_code: 61, wind_speed_m_s: 7.5, relative_humidity_pct: 88.0, solar_azimuth_deg: 200.0,
+            ..sample(&layout, 300.0, 20.0)
+        }, 0.0);
+        let (status, online) = weather(state.clone(), "plant_1").await;
+        assert_eq!(status, axum::http::StatusCode::OK);
+        assert_eq!((online["weather_code"].as_u64(), online["weather_label"].as_str()), (Some(61), Some("Slight rain")));
+        assert_eq!((online["wind_speed_m_s"].as_f64(), online["relative_humidity_pct"].as_f64()), (Some(7.5), Some(88.0)));
+        assert_eq!(online["solar_azimuth_deg"].as_f64(), Some(200.0));
+        assert_eq!(online["source"], "open_meteo");
+        assert_eq!(online["age_s"].as_f64(), Some(600.0));
+
+        // The model's readings are as fresh as the update that made them
+        tick(&state);
+        state.set_data("plant_1", &rated(100.0), &SimulationData { timestamp: state.now(), ..sample(&layout, 300.0, 20.0) }, 0.0);
+        let (_, offline) = weather(state.clone(), "plant_1").await;
+        assert_eq!((offline["source"].as_str(), offline["weather_label"].as_str()), (Some("model"), Some("Clear sky")));
+        assert_eq!(offline["age_s"].as_f64(), Some(0.0));
+
+        assert_eq!(weather(state.clone(), "plant_9").await.0, axum::http::StatusCode::NOT_FOUND);
+        assert_eq!(weather_label(4), "Unknown");
+    }
+
+    #[tokio::test]
+    async fn the_history_downloads_as_csv_with_the_columns_asked_for() {
+        use axum::http::HeaderValue;
+        use chrono::TimeZone;
+
+        let state = AppState::new(true);
+        steady_grid(&state);
+        let layout = StringLayout::sized_for(100.0);
+        let start = Utc.with_ymd_and_hms(2025, 6, 21, 10, 0, 0).unwrap();
+        state.set_clock(start);
+        // Ten minutes of updates, the last one at 10:09:55
+        for _ in 0..10 * 12 - 1 {
+            tick(&state);
+            state.set_data("plant_1", &rated(100.0), &sample(&layout, 800.0, 40.0), 0.0);
+        }
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "server": { "port": 3000 }, "modbus": { "port": 5020 }, "plants": [{
+                "id": "plant_1", "name": "p", "latitude": 45.0, "longitude": 7.0, "nominal_power_kw": 100.0,
+                "timezone": "UTC", "modbus_mapping": { "base_address": 0 }
+            }]
+        })).unwrap();
+        let history = |format: Option<HistoryFormat>, fields: Option<&str>, accept: Option<&str>| {
+            let query = HistoryQuery {
+                from: Some(start), to: Some(start + chrono::Duration::hours(1)), resolution: Resolution::OneMinute,
+                format, fields: fields.map(str::to_string), group_by: GroupBy::None,
+            };
+            let mut headers = HeaderMap::new();
+            if let Some(accept) = accept {
+                headers.insert(header::ACCEPT, HeaderValue::from_str(accept).unwrap());
+            }
+            let (state, config) = (state.clone(), config.clone());
+            async move {
+                let response = get_plant_history(Path("plant_1".into()), Query(query), headers, State(state), State(config))
+                    .await.into_response();
+                let (parts, body) = response.into_parts();
+                (parts, String::from_utf8(axum::body::to_bytes(body, usize::MAX).await.unwrap().to_vec()).unwrap())
+            }
+        };
+
+        let (parts, csv) = history(None, None, Some("text/csv")).await;
+        assert_eq!(parts.status, 200);
+        assert_eq!(parts.headers[header::CONTENT_TYPE], "text/csv; charset=utf-8");
+        assert_eq!(parts.headers[header::CONTENT_DISPOSITION],
+            "attachment; filename=\"plant_1_history_20250621T1000Z_20250621T1100Z.csv\"");
+        let rows: Vec<Vec<&str>> = csv.split_terminator("\r\n").map(|r| r.split(',').collect()).collect();
+        assert_eq!(rows[0], ["timestamp", "power_kw", "poa_irradiance_w_m2", "temperature_c", "daily_energy_kwh"]);
+        assert_eq!(rows.len(), 1 + 10, "a header and one row per minute");
+        assert!(rows[1..].iter().all(|r| r.len() == 5));
+
+        let (_, csv) = history(Some(HistoryFormat::Csv), Some("daily_energy_kwh,timestamp"), None).await;
+        let rows: Vec<&str> = csv.split_terminator("\r\n").collect();
+        assert_eq!((rows[0], rows.len()), ("daily_energy_kwh,timestamp", 11));
+        assert!(rows[10].ends_with(",2025-06-21T10:09:00Z"), "{}", rows[10]);
+
+        // JSON stays the default, whatever the selection
+        let (parts, json) = history(None, Some("power_kw"), Some("application/json")).await;
+        assert_eq!(parts.status, 200);
+        let points: Vec<serde_json::Value> = serde_json::from_str(&json).unwrap();
+        assert_eq!(points.len(), 10);
+        assert!(points.iter().all(|p| p.as_object().unwrap().len() == 1 && p["power_kw"].is_number()));
+        let (_, json) = history(None, None, None).await;
+        assert_eq!(serde_json::from_str::<Vec<serde_json::Value>>(&json).unwrap()[0].as_object().unwrap().len(), 10);
+
+        let (parts, error) = history(Some(HistoryFormat::Csv), Some("power_kw,voltage"), None).await;
+        assert_eq!(parts.status, 400);
+        assert!(error.contains("voltage"), "{error}");
+    }
+
+    #[tokio::test]
+    async fn the_fleet_history_adds_up_its_plants_and_sites_and_flags_gaps() {
+        use chrono::TimeZone;
+
+        let state = AppState::new(true);
+        let layout = StringLayout::sized_for(100.0);
+        let start = Utc.with_ymd_and_hms(2025, 6, 21, 10, 0, 0).unwrap();
+        let end = start + chrono::Duration::minutes(30);
+        state.set_clock(start);
+        for id in ["plant_1", "plant_2", "plant_3"] {
+            state.inject_grid_event(id, Disturbance::to(50.0), Some(230.0), 3600.0);
+        }
+        // Half an hour of updates, the last one at 10:29:55; plant_3 joins at 10:15
+        for step in 0..30 * 12 - 1 {
+            tick(&state);
+            for (id, poa) in [("plant_1", 800.0), ("plant_2", 600.0), ("plant_3", 700.0)] {
+                if id != "plant_3" || step >= 15 * 12 {
+                    state.set_data(id, &rated(100.0), &sample(&layout, poa, 40.0), 0.0);
+                }
+            }
+        }
+        let plant = |id: &str, base: u16, site: Option<&str>| serde_json::json!({
+            "id": id, "name": id, "latitude": 45.0, "longitude": 7.0, "nominal_power_kw": 100.0,
+            "timezone": "UTC", "modbus_mapping": { "base_address": base }, "site": site
+        });
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "server": { "port": 3000 }, "modbus": { "port": 5020 },
+            "plants": [plant("plant_1", 0, Some("north")), plant("plant_2", 100, Some("north")), plant("plant_3", 200, None)]
+        })).unwrap();
+        let history = |group_by, resolution, to| {
+            let query = HistoryQuery { from: Some(start), to: Some(to), resolution, format: None, fields: None, group_by };
+            let (state, config) = (state.clone(), config.clone());
+            async move {
+                let response = get_global_history(Query(query), HeaderMap::new(), State(state), State(config))
+                    .await.into_response();
+                let status = response.status();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                (status, serde_json::from_slice::<serde_json::Value>(&body).unwrap())
+            }
+        };
+
+        let (status, fleet) = history(GroupBy::None, Resolution::FifteenMinutes, end).await;
+        assert_eq!(status, 200);
+        let fleet = fleet.as_array().unwrap().clone();
+        assert_eq!(fleet.len(), 2);
+        assert_eq!((&fleet[0]["partial"], &fleet[0]["plants_reporting"]), (&serde_json::json!(true), &serde_json::json!(2)));
+        assert_eq!((&fleet[1]["partial"], &fleet[1]["plants_total"]), (&serde_json::json!(false), &serde_json::json!(3)));
+
+        let (_, plants) = history(GroupBy::Plant, Resolution::FifteenMinutes, end).await;
+        let (_, sites) = history(GroupBy::Site, Resolution::FifteenMinutes, end).await;
+        assert_eq!(sites.as_object().unwrap().keys().collect::<Vec<_>>(), ["north", "plant_3"]);
+        assert_eq!(sites["plant_3"].as_array().unwrap().len(), 1);
+        // Every bucket of the fleet is the sum of its plants, and of its sites
+        for bucket in &fleet {
+            let sum = |groups: &serde_json::Value, field: &str| -> f64 {
+                groups.as_object().unwrap().values()
+                    .filter_map(|series| series.as_array().unwrap().iter().find(|p| p["timestamp"] == bucket["timestamp"]))
+                    .map(|p| p[field].as_f64().unwrap())
+                    .sum()
+            };
+            for field in ["power_kw", "energy_kwh"] {
+                let total = bucket[field].as_f64().unwrap();
+                assert!(total > 0.0);
+                assert!((sum(&plants, field) - total).abs() < 1e-9, "{field} {bucket}");
+                assert!((sum(&sites, field) - total).abs() < 1e-9, "{field} {bucket}");
+            }
+        }
+
+        // A week of minutes for each of three plants is too much
+        let (status, error) = history(GroupBy::Plant, Resolution::OneMinute, start + chrono::Duration::days(7)).await;
+        assert_eq!(status, 400);
+        assert!(error["error"].as_str().unwrap().contains("coarser resolution"), "{error}");
+        assert_eq!(history(GroupBy::None, Resolution::OneMinute, start + chrono::Duration::days(7)).await.0, 200);
+    }
+
+    #[tokio::test]
+    async fn the_offline_forecast_runs_the_model_in_the_steps_asked_for() {
+        use chrono::TimeZone;
+
+        let state = AppState::new(true);
+        state.set_clock(Utc.with_ymd_and_hms(2025, 6, 21, 5, 40, 0).unwrap());
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "server": { "port": 3000 }, "modbus": { "port": 5020 }, "plants": [{
+                "id": "plant_1", "name": "p", "latitude": 45.0, "longitude": 7.0, "nominal_power_kw": 100.0,
+                "timezone": "UTC", "modbus_mapping": { "base_address": 0 }
+            }]
+        })).unwrap();
+        let query = |hours: Option<u32>, resolution: Option<&str>| ForecastQuery { hours, resolution: resolution.map(str::to_string) };
+        let body = |response: axum::response::Response| async move {
+            let status = response.status();
+            (status, axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap())
+        };
+
+        let (status, bytes) = body(get_plant_forecast(
+            Path("plant_1".into()), Query(query(Some(48), Some("15m"))), State(state.clone()), State(config.clone()),
+        ).await.into_response()).await;
+        assert_eq!(status, 200);
+        let forecast: PowerForecast = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!((forecast.source, forecast.hours, forecast.resolution.as_str()), (ForecastSource::Model, 48, "15m"));
+        assert_eq!(forecast.points.len(), 48 * 4);
+        assert_eq!(forecast.points[0].timestamp, Utc.with_ymd_and_hms(2025, 6, 21, 5, 30, 0).unwrap());
+        let total: f64 = forecast.points.iter().map(|p| p.energy_kwh).sum();
+        assert!((forecast.total_energy_kwh - total).abs() < 1e-9 && total > 100.0, "{total} kWh");
+
+        let (status, bytes) = body(get_fleet_forecast(Query(query(None, None)), State(state.clone()), State(config.clone()))
+            .await.into_response()).await;
+        assert_eq!(status, 200);
+        let fleet: PowerForecast = serde_json::from_slice(&bytes).unwrap();
+        assert_eq!((fleet.plant_id, fleet.points.len(), fleet.resolution.as_str()), (None, 24, "1h"));
+
+        for q in [query(Some(200), None), query(Some(0), None), query(None, Some("10m"))] {
+            let (status, _) = body(get_plant_forecast(Path("plant_1".into()), Query(q), State(state.clone()), State(config.clone()))
+                .await.into_response()).await;
+            assert_eq!(status, 400);
+        }
+        let (status, _) = body(get_plant_forecast(
+            Path("plant_9".into()), Query(query(None, None)), State(state.clone()), State(config.clone()),
+        ).await.into_response()).await;
+        assert_eq!(status, 404);
+    }
+
+    #[tokio::test]
+    async fn plants_are_created_and_deleted_over_the_api() {
+        use crate::shutdown::Shutdown;
+        use axum::extract::FromRef;
+
+        let plant = |id: &str, base: u16| -> PlantConfig {
+            serde_json::from_value(serde_json::json!({
+                "id": id, "name": id, "latitude": 45.0, "longitude": 7.0, "nominal_power_kw": 100.0,
+                "timezone": "UTC", "modbus_mapping": { "base_address": base }
+            })).unwrap()
+        };
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "server": { "port": 3000 }, "modbus": { "port": 5020 }, "offline_mode": true, "plants": [plant("plant_1", 0)]
+        })).unwrap();
+        let state = AppState::new(true);
+        let shared = SharedState { app: state.clone(), plants: PlantRegistry::new(state.clone(), config, "config.json", Shutdown::new()) };
+        let registry = || State(PlantRegistry::from_ref(&shared));
+        let body = |response: axum::response::Response| async move {
+            let status = response.status();
+            let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+            (status, serde_json::from_slice::<serde_json::Value>(&bytes).unwrap_or_default())
+        };
+
+        let (status, created) = body(create_plant(registry(), Json(plant("plant_2", 100))).await.into_response()).await;
+        assert_eq!(status, 201);
+        assert_eq!(created["id"], "plant_2");
+        let (status, error) = body(create_plant(registry(), Json(plant("plant_3", 150))).await.into_response()).await;
+        assert_eq!(status, 409);
+        assert!(error["error"].as_str().unwrap().contains("plant_2"), "{error}");
+        assert_eq!(body(create_plant(registry(), Json(plant("plant_2", 300))).await.into_response()).await.0, 409);
+
+        // Handlers reading the configuration see the change
+        let (_, plants) = body(list_plants(State(Config::from_ref(&shared))).await.into_response()).await;
+        assert_eq!(plants.as_array().unwrap().len(), 2);
+        let moved = PlantConfig { longitude: 12.5, ..plant("plant_2", 100) };
+        assert_eq!(body(update_plant(Path("plant_2".into()), registry(), Json(moved)).await.into_response()).await.0, 200);
+        assert_eq!(Config::from_ref(&shared).plants[1].longitude, 12.5);
 
+        assert_eq!(delete_plant(Path("plant_2".into()), registry()).await.into_response().status(), 204);
+        assert_eq!(delete_plant(Path("plant_2".into()), registry()).await.into_response().status(), 404);
+        assert_eq!(Config::from_ref(&shared).plants.len(), 1);
+        assert!(state.get_data("plant_2").is_none());
+    }
+
+    #[tokio::test]
+    async fn an_acknowledged_alarm_stays_active_until_its_condition_clears() {
+
+        let state = AppState::new(true);
+        steady_grid(&state);
+        let layout = StringLayout::sized_for(100.0);
+        let feed = || state.set_data("plant_1", &rated(100.0), &sample(&layout, 600.0, 25.0), 0.0);
+        feed();
+        state.inject_fault("plant_1", alarm_codes::INTERNAL_FAULT, AlarmSeverity::Fault, 600.0);
+        feed();
+        let alarm_id = state.get_active_alarms(Some("plant_1"))[0].id.clone();
+
+        let ack = |alarm_id: &str, by: &str| {
+            let (state, alarm_id) = (state.clone(), alarm_id.to_string());
+            let body = AlarmAckBody { acknowledged_by: by.to_string(), note: Some("crew dispatched".to_string()) };
+            async move { acknowledge_alarm(Path(alarm_id), State(state), Json(body)).await.into_response() }
+        };
+        assert_eq!(ack("no-such-alarm", "J. Doe").await.status(), 404);
+        assert_eq!(ack(&alarm_id, " ").await.status(), 400);
+        let response = ack(&alarm_id, "J. Doe").await;
+        assert_eq!(response.status(), 200);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let acked: Alarm = serde_json::from_slice(&body).unwrap();
+        assert!(acked.active && acked.acknowledged && acked.acknowledged_at.is_some());
+        assert_eq!((acked.acknowledged_by.as_deref(), acked.note.as_deref()), (Some("J. Doe"), Some("crew dispatched")));
+        assert!(state.get_events(5).iter().any(|e| matches!(e.kind, EventKind::AlarmAcknowledged)));
+
+        // Still active while the condition persists, but out of the unacknowledged list
+        feed();
+        assert!(state.get_active_alarms(Some("plant_1")).iter().any(|a| a.id == alarm_id && a.acknowledged));
+        let unacknowledged = |active_only: bool| {
+            let query = AlarmQuery { active_only: Some(active_only), unacknowledged_only: Some(true), ..AlarmQuery::default() };
+            let state = state.clone();
+            async move {
+                let response = get_all_alarms(Query(query), State(state)).await.into_response();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                serde_json::from_slice::<Vec<Alarm>>(&body).unwrap()
+            }
+        };
+        assert!(unacknowledged(true).await.iter().all(|a| a.id != alarm_id));
+
+        // The condition goes: the alarm clears as usual, keeping its acknowledgement
+        state.withdraw_fault("plant_1", alarm_codes::INTERNAL_FAULT);
+        let cleared = state.get_alarms(Some("plant_1")).into_iter().find(|a| a.id == alarm_id).unwrap();
+        assert!(!cleared.active && cleared.acknowledged);
+        assert!(unacknowledged(false).await.iter().all(|a| a.id != alarm_id));
+    }
+
+    #[tokio::test]
+    async fn alarm_lists_filter_by_severity_and_time_and_page_newest_first() {
+        use chrono::TimeZone;
+
+        let state = AppState::new(true);
+        let t0 = chrono::Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+        let raised = [
+            ("plant_1", alarm_codes::AC_OVERVOLTAGE, AlarmSeverity::Warning),
+            ("plant_1", alarm_codes::DC_OVERVOLTAGE, AlarmSeverity::Fault),
+            ("plant_2", alarm_codes::AC_OVERVOLTAGE, AlarmSeverity::Warning),
+            ("plant_1", alarm_codes::MPPT_FAILURE, AlarmSeverity::Warning),
+            ("plant_1", alarm_codes::ISOLATION_FAULT, AlarmSeverity::Fault),
+        ];
+        for (plant, code, severity) in raised {
+            state.raise_alarm(plant, code, severity, "test");
+        }
+        // One minute apart; the last two raised in the same second
+        for (i, alarm) in state.alarms.write().unwrap().iter_mut().enumerate() {
+            alarm.timestamp = t0 + chrono::Duration::minutes(i.min(3) as i64);
+        }
+        let list = |plant: Option<&'static str>, query: AlarmQuery| {
+            let state = state.clone();
+            async move {
+                let response = match plant {
+                    Some(id) => get_plant_alarms(Path(id.to_string()), Query(query), State(state)).await.into_response(),
+                    None     => get_all_alarms(Query(query), State(state)).await.into_response(),
+                };
+                let status = response.status();
+                let header = |name: &str| response.headers().get(name).map(|v| v.to_str().unwrap().to_string());
+                let (total, next) = (header("x-total-count"), header("x-next-cursor"));
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                let codes = serde_json::from_slice::<Vec<Alarm>>(&body).unwrap_or_default().iter().map(|a| a.code).collect::<Vec<_>>();
+                (status, codes, total, next)
+            }
+        };
+
+        // Newest first, the tie in the order raised newest first
+        let (status, codes, total, next) = list(None, AlarmQuery::default()).await;
+        assert_eq!(status, 200);
+        assert_eq!(codes, [alarm_codes::ISOLATION_FAULT, alarm_codes::MPPT_FAILURE, alarm_codes::AC_OVERVOLTAGE,
+                           alarm_codes::DC_OVERVOLTAGE, alarm_codes::AC_OVERVOLTAGE]);
+        assert_eq!((total.as_deref(), next), (Some("5"), None));
+
+        // Severity and time window combine with the plant
+        let faults = AlarmQuery { severity: Some(AlarmSeverity::Fault), ..AlarmQuery::default() };
+        assert_eq!(list(Some("plant_1"), faults).await.1, [alarm_codes::ISOLATION_FAULT, alarm_codes::DC_OVERVOLTAGE]);
+        let window = AlarmQuery {
+            from: Some(t0 + chrono::Duration::minutes(1)),
+            to: Some(t0 + chrono::Duration::minutes(3)),
+            ..AlarmQuery::default()
+        };
+        let (_, codes, total, _) = list(Some("plant_1"), window).await;
+        assert_eq!((codes, total.as_deref()), (vec![alarm_codes::DC_OVERVOLTAGE], Some("1")));
+
+        // Offset paging keeps the total of the whole selection
+        let page = AlarmQuery { offset: Some(1), limit: Some(2), ..AlarmQuery::default() };
+        let (_, codes, total, next) = list(None, page).await;
+        assert_eq!(codes, [alarm_codes::MPPT_FAILURE, alarm_codes::AC_OVERVOLTAGE]);
+        assert_eq!(total.as_deref(), Some("5"));
+        assert!(next.is_some());
+
+        // Following the cursor walks the whole list once
+        let mut cursor = None;
+        let mut walked = Vec::new();
+        loop {
+            let page = AlarmQuery { cursor: cursor.take(), limit: Some(2), ..AlarmQuery::default() };
+            let (status, codes, _, next) = list(None, page).await;
+            assert_eq!(status, 200);
+            walked.extend(codes);
+            match next { Some(next) => cursor = Some(next), None => break }
+        }
+        assert_eq!(walked, list(None, AlarmQuery::default()).await.1);
+
+        let listed = state.get_alarms(None)[0].id.clone();
+        let rejected = [
+            AlarmQuery { from: Some(t0 + chrono::Duration::minutes(5)), to: Some(t0), ..AlarmQuery::default() },
+            AlarmQuery { offset: Some(1), cursor: Some(listed), ..AlarmQuery::default() },
+            AlarmQuery { cursor: Some("no-such-alarm".into()), ..AlarmQuery::default() },
+        ];
+        for query in rejected {
+            assert_eq!(list(None, query).await.0, 422);
+        }
+    }
+
+    #[tokio::test]
+    async fn the_event_log_filters_by_kind_and_time_and_pages_newest_first() {
+        use chrono::TimeZone;
+
+        let state = AppState::new(true);
+        let t0 = chrono::Utc.with_ymd_and_hms(2025, 6, 1, 12, 0, 0).unwrap();
+        for i in 0..6 {
+            let kind = if i % 2 == 0 { EventKind::ModeChange } else { EventKind::SettingChanged };
+            state.push_event(Some("plant_1".into()), kind, format!("event {i}"), None);
+        }
+        // Logged newest first, a minute apart
+        for (i, event) in state.events.write().unwrap().iter_mut().enumerate() {
+            event.timestamp = t0 - chrono::Duration::minutes(i as i64);
+        }
+        let list = |query: EventQuery| {
+            let state = state.clone();
+            async move {
+                let response = get_events(Query(query), State(state)).await.into_response();
+                let status = response.status();
+                let total = response.headers().get("x-total-count").map(|v| v.to_str().unwrap().to_string());
+                let next = response.headers().get("x-next-cursor").map(|v| v.to_str().unwrap().to_string());
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                let messages = serde_json::from_slice::<Vec<Event>>(&body).unwrap_or_default().into_iter().map(|e| e.message).collect::<Vec<_>>();
+                (status, messages, total, next)
+            }
+        };
+
+        let settings = EventQuery {
+            kind: Some(EventKind::SettingChanged),
+            from: Some(t0 - chrono::Duration::minutes(3)),
+            ..EventQuery::default()
+        };
+        let (status, messages, total, _) = list(settings).await;
+        assert_eq!(status, 200);
+        assert_eq!((messages, total.as_deref()), (vec!["event 5".to_string(), "event 3".to_string()], Some("2")));
+
+        let before = EventQuery { to: Some(t0 - chrono::Duration::minutes(2)), ..EventQuery::default() };
+        assert_eq!(list(before).await.1, ["event 2", "event 1", "event 0"]);
+
+        let (_, first, total, next) = list(EventQuery { limit: Some(4), ..EventQuery::default() }).await;
+        assert_eq!((first.len(), total.as_deref()), (4, Some("6")));
+        let (_, rest, _, end) = list(EventQuery { limit: Some(4), cursor: next, ..EventQuery::default() }).await;
+        assert_eq!((rest, end), (vec!["event 1".to_string(), "event 0".to_string()], None));
+        assert_eq!(list(EventQuery { offset: Some(5), ..EventQuery::default() }).await.1, ["event 0"]);
+
+        let backwards = EventQuery { from: Some(t0), to: Some(t0 - chrono::Duration::hours(1)), ..EventQuery::default() };
+        assert_eq!(list(backwards).await.0, 422);
+    }
+}
@@ -0,0 +1,176 @@
+use std::time::Duration;
+
+use axum::http::{HeaderName, HeaderValue, Method};
+use tower_http::compression::predicate::{NotForContentType, Predicate, SizeAbove};
+use tower_http::compression::CompressionLayer;
+use tower_http::cors::{AllowHeaders, AllowMethods, AllowOrigin, CorsLayer};
+
+use crate::config::{CompressionConfig, CorsConfig};
+use crate::controllers::power_controller::{NEXT_CURSOR, TOTAL_COUNT};
+
+fn is_wildcard(list: &[String]) -> bool {
+    list.iter().any(|v| v.trim() == "*")
+}
+
+/// CORS layer for `server.cors`, or why the browser could not be told it.
+/// The paging headers of the alarm and event lists are always exposed.
+pub fn cors_layer(cors: &CorsConfig) -> Result<CorsLayer, String> {
+    if cors.allowed_origins.is_empty() {
+        return Err("server.cors.allowed_origins must list at least one origin, or \"*\"".to_string());
+    }
+    let origins = if is_wildcard(&cors.allowed_origins) {
+        if cors.allowed_origins.len() > 1 {
+            return Err("server.cors.allowed_origins: \"*\" cannot be combined with other origins".to_string());
+        }
+        AllowOrigin::any()
+    } else {
+        let origins = cors.allowed_origins.iter().map(|origin| {
+            // Browsers send the origin without a trailing slash
+            let origin = origin.trim().trim_end_matches('/');
+            if !(origin.starts_with("http://") || origin.starts_with("https://")) {
+                return Err(format!("server.cors.allowed_origins: '{}' is not an http(s) origin", origin));
+            }
+            HeaderValue::from_str(origin)
+                .map_err(|_| format!("server.cors.allowed_origins: '{}' is not a valid origin", origin))
+        }).collect::<Result<Vec<_>, _>>()?;
+        AllowOrigin::list(origins)
+    };
+    let methods = if is_wildcard(&cors.allowed_methods) {
+        AllowMethods::any()
+    } else {
+        AllowMethods::list(cors.allowed_methods.iter().map(|m| {
+            Method::from_bytes(m.trim().to_uppercase().as_bytes())
+                .map_err(|_| format!("server.cors.allowed_methods: '{}' is not an HTTP method", m))
+        }).collect::<Result<Vec<_>, _>>()?)
+    };
+    let headers = if is_wildcard(&cors.allowed_headers) {
+        AllowHeaders::any()
+    } else {
+        AllowHeaders::list(cors.allowed_headers.iter().map(|h| {
+            HeaderName::from_bytes(h.trim().to_lowercase().as_bytes())
+                .map_err(|_| format!("server.cors.allowed_headers: '{}' is not a header name", h))
+        }).collect::<Result<Vec<_>, _>>()?)
+    };
+
+    let mut layer = CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods(methods)
+        .allow_headers(headers)
+        .expose_headers([HeaderName::from_static(TOTAL_COUNT), HeaderName::from_static(NEXT_CURSOR)]);
+    if let Some(max_age_s) = cors.max_age_s {
+        layer = layer.max_age(Duration::from_secs(max_age_s));
+    }
+    Ok(layer)
+}
+
+/// Compression layer for `server.compression`. Bodies under the threshold,
+/// images and the Server-Sent Events stream (which must not be buffered) go
+/// out as they are; so do WebSocket upgrades, which have no body.
+pub fn compression_layer(compression: &CompressionConfig) -> CompressionLayer<impl Predicate + use<>> {
+    CompressionLayer::new()
+        .gzip(compression.gzip)
+        .br(compression.brotli)
+        .compress_when(
+            SizeAbove::new(compression.min_size_bytes)
+                .and(NotForContentType::GRPC)
+                .and(NotForContentType::IMAGES)
+                .and(NotForContentType::SSE),
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{http::header, routing::get, Router};
+
+    fn cors(origins: &[&str]) -> CorsConfig {
+        CorsConfig {
+            allowed_origins: origins.iter().map(|o| o.to_string()).collect(),
+            allowed_methods: vec!["GET".into(), "POST".into()],
+            allowed_headers: vec!["content-type".into(), "x-api-key".into()],
+            max_age_s: Some(600),
+        }
+    }
+
+    /// Serve `app` on a free port and return its base URL.
+    async fn serve(app: Router) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let base = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        base
+    }
+
+    #[test]
+    fn origins_methods_and_headers_are_checked() {
+        assert!(cors_layer(&cors(&["http://localhost:5173", "https://scada.example.com/"])).is_ok());
+        assert!(cors_layer(&cors(&["*"])).is_ok());
+        assert!(cors_layer(&cors(&[])).is_err());
+        assert!(cors_layer(&cors(&["*", "http://localhost:5173"])).is_err());
+        assert!(cors_layer(&cors(&["localhost:5173"])).is_err());
+        assert!(cors_layer(&CorsConfig { allowed_methods: vec!["GE T".into()], ..cors(&["*"]) }).is_err());
+        assert!(cors_layer(&CorsConfig { allowed_headers: vec!["x api key".into()], ..cors(&["*"]) }).is_err());
+    }
+
+    #[tokio::test]
+    async fn allowed_origins_pass_preflight_and_others_get_no_headers() {
+        let app = Router::new()
+            .route("/api/alarms", get(|| async { ([(TOTAL_COUNT, "3")], "[]") }))
+            .layer(cors_layer(&cors(&["http://localhost:5173"])).unwrap());
+        let base = serve(app).await;
+        let client = reqwest::Client::new();
+        let preflight = |origin: &'static str| client
+            .request(Method::OPTIONS, format!("{base}/api/alarms"))
+            .header(header::ORIGIN, origin)
+            .header(header::ACCESS_CONTROL_REQUEST_METHOD, "POST")
+            .header(header::ACCESS_CONTROL_REQUEST_HEADERS, "x-api-key")
+            .send();
+
+        let allowed = preflight("http://localhost:5173").await.unwrap();
+        assert_eq!(allowed.status(), 200);
+        let headers = allowed.headers();
+        assert_eq!(headers[header::ACCESS_CONTROL_ALLOW_ORIGIN], "http://localhost:5173");
+        assert!(headers[header::ACCESS_CONTROL_ALLOW_METHODS].to_str().unwrap().contains("POST"));
+        assert!(headers[header::ACCESS_CONTROL_ALLOW_HEADERS].to_str().unwrap().contains("x-api-key"));
+        assert_eq!(headers[header::ACCESS_CONTROL_MAX_AGE], "600");
+
+        let refused = preflight("http://evil.example").await.unwrap();
+        assert!(refused.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+
+        // Actual requests carry the origin and expose the paging headers
+        let response = client.get(format!("{base}/api/alarms"))
+            .header(header::ORIGIN, "http://localhost:5173")
+            .send().await.unwrap();
+        assert_eq!(response.headers()[header::ACCESS_CONTROL_ALLOW_ORIGIN], "http://localhost:5173");
+        assert!(response.headers()[header::ACCESS_CONTROL_EXPOSE_HEADERS].to_str().unwrap().contains(TOTAL_COUNT));
+    }
+
+    #[tokio::test]
+    async fn large_bodies_are_compressed_and_small_ones_and_streams_are_not() {
+        let large = "{\"power_kw\":42.0}".repeat(200);
+        let app = Router::new()
+            .route("/large", get(move || async move { large }))
+            .route("/small", get(|| async { "ok" }))
+            .route("/stream", get(|| async {
+                ([(header::CONTENT_TYPE, "text/event-stream")], "data: x\n\n".repeat(500))
+            }))
+            .layer(compression_layer(&CompressionConfig { min_size_bytes: 1024, gzip: true, brotli: true }));
+        let base = serve(app).await;
+        let client = reqwest::Client::new();
+        let encoding = |path: &'static str, accept: Option<&'static str>| {
+            let mut request = client.get(format!("{base}{path}"));
+            if let Some(accept) = accept {
+                request = request.header(header::ACCEPT_ENCODING, accept);
+            }
+            async move {
+                let response = request.send().await.unwrap();
+                response.headers().get(header::CONTENT_ENCODING).map(|v| v.to_str().unwrap().to_string())
+            }
+        };
+
+        assert_eq!(encoding("/large", Some("gzip")).await.as_deref(), Some("gzip"));
+        assert_eq!(encoding("/large", Some("br")).await.as_deref(), Some("br"));
+        assert_eq!(encoding("/large", None).await, None);
+        assert_eq!(encoding("/small", Some("gzip, br")).await, None);
+        assert_eq!(encoding("/stream", Some("gzip, br")).await, None);
+    }
+}
@@ -0,0 +1,15 @@
+pub mod routes;
+pub mod controllers;
+pub mod services;
+pub mod models;
+pub mod api_docs;
+pub mod shared_state;
+pub(crate) mod plant_store;
+pub mod modbus_server;
+pub(crate) mod sunspec;
+pub mod profiles;
+pub mod shutdown;
+pub mod auth;
+pub mod rate_limit;
+pub mod http_layers;
+pub mod config;
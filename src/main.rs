@@ -1,29 +1,24 @@
-mod routes;
-mod controllers;
-mod services;
-mod models;
-mod api_docs;
-mod shared_state;
-mod modbus_server;
-mod config;
-
-use std::net::SocketAddr;
+use std::sync::Arc;
 use std::time::Duration;
 use axum::{Router, routing::get, response::Html};
-use crate::routes::power_routes::api_routes;
+use solar_panel_sim::{auth, controllers, http_layers, rate_limit, modbus_server, models, profiles, services};
+use solar_panel_sim::routes::power_routes::api_routes;
 use utoipa::OpenApi;
 use utoipa_scalar::Scalar;
-use crate::api_docs::ApiDoc;
-use crate::shared_state::{AppState, SharedState};
-use crate::config::Config;
+use solar_panel_sim::api_docs::ApiDoc;
+use solar_panel_sim::shared_state::{AppState, SharedState};
+use solar_panel_sim::config::Config;
+use solar_panel_sim::shutdown::{self, Shutdown, SHUTDOWN_TIMEOUT};
 
-use std::collections::HashMap;
 use tower_http::services::ServeDir;
 
+/// Read at startup; rewritten by plant changes when `persist_changes` is set
+const CONFIG_PATH: &str = "config.json";
+
 #[tokio::main]
 async fn main() {
     // 1. Load configuration
-    let config = match Config::load("config.json") {
+    let config = match Config::load(CONFIG_PATH) {
         Ok(c) => c,
         Err(e) => {
             eprintln!("Failed to load config.json: {}", e);
@@ -31,178 +26,235 @@ async fn main() {
         }
     };
     println!("Configuration loaded: {} plants", config.plants.len());
+    let (http_addr, modbus_endpoints, modbus_tls_addr) =
+        match (config.http_addr(), config.modbus_endpoints(), config.modbus_tls_addr()) {
+            (Ok(http), Ok(endpoints), Ok(tls)) => (http, endpoints, tls),
+            (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => {
+                eprintln!("Failed to load config.json: {}", e);
+                return;
+            }
+        };
+    // Modbus/TCP Security: an unusable certificate or key stops startup
+    let modbus_tls = match config.modbus.tls.as_ref().map(modbus_server::load_tls_acceptor).transpose() {
+        Ok(acceptor) => acceptor,
+        Err(e) => {
+            eprintln!("Failed to load Modbus TLS certificate: {}", e);
+            return;
+        }
+    };
+
+    // SIGINT / SIGTERM start a graceful shutdown of every task below
+    let shutdown = Shutdown::new();
+    tokio::spawn(shutdown::listen_for_signals(shutdown.clone()));
+    let mut tasks = Vec::new();
 
     // 2. Initialize shared state (seed offline flag from config)
     let state = AppState::new(config.offline_mode);
+    state.set_simulation_seed(config.simulation.seed);
+    // Validated with the config: only offline mode runs off the wall clock
+    if let Ok(clock) = services::sim_clock::SimClock::new(config.simulation.speed, config.simulation.start, chrono::Utc::now()) {
+        if !clock.is_realtime() {
+            println!("[CLOCK] Simulation at {}× from {}", clock.speed(), clock.now().to_rfc3339());
+        }
+        let _ = state.set_sim_clock(clock);
+    }
+    state.configure_measurement_noise(
+        services::measurement_noise::MeasurementNoise::for_config(&config.simulation.measurement_noise),
+    );
+    for plant in &config.plants {
+        services::plant_registry::configure_plant(&state, &config, plant);
+        if !plant.enabled {
+            state.set_plant_enabled(&plant.id, false, Some("disabled in config.json"));
+        }
+    }
+    for scenario in &config.scenarios {
+        match services::scenarios::Scenario::for_config(uuid::Uuid::new_v4().to_string(), scenario) {
+            Ok(scenario) => state.add_scenario(scenario),
+            Err(e)       => eprintln!("[SCENARIO] Ignored: {}", e),
+        }
+    }
+    // Counters, soiling, active alarms and events from the previous run
+    if let Some(persistence) = &config.persistence {
+        match services::persistence::load(&persistence.path) {
+            Ok(Some(snapshot)) => {
+                println!("[PERSISTENCE] Restored {} plants from {}", snapshot.plants.len(), persistence.path);
+                snapshot.restore(&state);
+            }
+            Ok(None) => println!("[PERSISTENCE] No snapshot at {}, starting fresh", persistence.path),
+            Err(e)   => eprintln!("[PERSISTENCE] Snapshot unreadable, starting fresh: {}", e),
+        }
+        tasks.push(tokio::spawn(services::persistence::run_saver(
+            state.clone(),
+            persistence.path.clone(),
+            Duration::from_secs(persistence.interval_s.max(1)),
+            shutdown.clone(),
+        )));
+    }
+    state.push_event(
+        None,
+        models::power::EventKind::SimulatorStart,
+        format!("Simulator v{} started with {} plants", env!("CARGO_PKG_VERSION"), config.plants.len()),
+        Some(serde_json::json!({
+            "version":      env!("CARGO_PKG_VERSION"),
+            "plants":       config.plants.iter().map(|p| p.id.as_str()).collect::<Vec<_>>(),
+            "offline_mode": config.offline_mode,
+            "clock_speed":  config.simulation.speed,
+        })),
+    );
     if config.offline_mode {
         println!("[MODE] Offline mode ENABLED — using solar geometry algorithm");
     } else {
         println!("[MODE] Online mode — will fetch from Open-Meteo API");
     }
 
-    // 3. Start background tasks for each plant
-    for plant in &config.plants {
-        let state_clone = state.clone();
-        let plant_config = plant.clone();
-        
-        tokio::spawn(async move {
+    // 3. Start background tasks for each plant, staggered across their
+    // interval so the updates do not all land on the same instant; plants
+    // created, changed or deleted over the API go through the same registry
+    let registry = services::plant_registry::PlantRegistry::new(state.clone(), config.clone(), CONFIG_PATH, shutdown.clone());
+    registry.start_plants().await;
+
+    // Stale-data supervision: a plant whose task stops updating (stalled,
+    // failing fetches) is flagged until its next update
+    tasks.push(tokio::spawn({
+        let state = state.clone();
+        let shutdown = shutdown.clone();
+        async move {
             loop {
-                let offline = state_clone.is_offline();
-                let result = if offline {
-                    // Pure offline – no API call
-                    let data = services::power_service::get_offline_data(
-                        plant_config.latitude,
-                        plant_config.longitude,
-                        plant_config.nominal_power_kw,
-                    );
-                    Ok(data)
-                } else {
-                    // Online: call Open-Meteo, falls back to offline on error
-                    services::power_service::get_current_data(
-                        plant_config.latitude,
-                        plant_config.longitude,
-                        plant_config.nominal_power_kw,
-                    ).await
-                };
-
-                match result {
-                    Ok(data) => {
-                        let mode_tag = if offline { "OFFLINE" } else { "ONLINE" };
-                        state_clone.set_data(
-                            &plant_config.id,
-                            data.power_kw,
-                            data.temperature_c,
-                            data.ambient_temp_c,
-                            plant_config.nominal_power_kw,
-                            data.weather_code,
-                            data.is_day,
-                            data.poa_irradiance_w_m2,
-                            data.cloud_factor,
-                            data.solar_elevation_deg,
-                            data.wind_speed_m_s,
-                            data.relative_humidity_pct,
-                            data.soiling_factor,
-                        );
-                        println!(
-                            "[{} UPDATE] Plant: {} | DC Power: {:.2} kW | Temp: {:.1}°C",
-                            mode_tag, plant_config.id, data.power_kw, data.temperature_c
-                        );
-                    }
-                    Err(e) => {
-                        eprintln!("Error updating plant {}: {}", plant_config.id, e);
-                    }
+                tokio::select! {
+                    _ = tokio::time::sleep(Duration::from_secs(1)) => {}
+                    _ = shutdown.wait() => break,
                 }
-                tokio::time::sleep(Duration::from_secs(5)).await;
+                state.check_stale_plants();
             }
-        });
-    }
+        }
+    }));
 
-    // 4. Start Modbus TCP server
-    let modbus_port = config.modbus.port;
-    let modbus_addr = SocketAddr::from(([0, 0, 0, 0], modbus_port));
-    let state_modbus = state.clone();
+    // 4. Start Modbus TCP servers
+    let modbus_cfg = config.modbus.clone();
 
     // Build register map: each plant gets a 100-register block starting at base_address.
     // Float32 values → 2 u16 registers (IEEE 754 BE, high word first).
     // u16 values      → 1 register.
-    use modbus_server::*;
-    let mut register_map = HashMap::new();
+    // The RTU line serves every plant; each TCP endpoint only the plants bound to its port.
+    for plant in &config.plants {
+        if let Some(timeout_s) = plant.modbus_mapping.watchdog_timeout_s {
+            println!(
+                "[MODBUS] Plant: {} | SCADA watchdog {} s, fallback {}",
+                plant.id, timeout_s,
+                plant.modbus_mapping.watchdog_fallback_pct.map_or("none".to_string(), |p| format!("{:.0} %", p))
+            );
+        }
+    }
+    let span = modbus_server::layout_span();
     for plant in &config.plants {
         let base = plant.modbus_mapping.base_address;
-
-        macro_rules! ins_f {
-            ($off:expr, $vt:ident) => {
-                register_map.insert(base + $off,     (plant.id.clone(), VariableType::$vt, 0u8));
-                register_map.insert(base + $off + 1, (plant.id.clone(), VariableType::$vt, 1u8));
-            };
+        let port = config.modbus_port_of(plant);
+        let unit = plant.modbus_mapping.unit_id
+            .map(|u| format!("unit={}", u))
+            .unwrap_or_else(|| "unit=any".to_string());
+        for (virtual_unit, device) in profiles::virtual_devices(plant) {
+            let block = device.block();
+            println!(
+                "[MODBUS] Plant: {} | port={} | unit={} | {} | regs {}..{}",
+                plant.id, port, virtual_unit, device.name(), block.start, block.end - 1
+            );
         }
-        macro_rules! ins_u {
-            ($off:expr, $vt:ident) => {
-                register_map.insert(base + $off, (plant.id.clone(), VariableType::$vt, 0u8));
-            };
+        if let Some(profile) = profiles::for_plant(plant) {
+            let block = profile.block();
+            println!(
+                "[MODBUS] Plant: {} | port={} | {} | {} profile | regs {}..{}",
+                plant.id, port, unit, profile.name(), block.start, block.end - 1
+            );
+            continue;
         }
-
-        // AC Output
-        ins_f!(REG_POWER_KW,            PowerKw);
-        ins_f!(REG_VOLTAGE_L1_V,        VoltageL1V);
-        ins_f!(REG_CURRENT_L1_A,        CurrentL1A);
-        ins_f!(REG_FREQUENCY_HZ,        FrequencyHz);
-        ins_f!(REG_TEMPERATURE_C,       TemperatureC);
-        ins_u!(REG_STATUS,              Status);
-        ins_f!(REG_VOLTAGE_L2_V,        VoltageL2V);
-        ins_f!(REG_VOLTAGE_L3_V,        VoltageL3V);
-        ins_f!(REG_CURRENT_L2_A,        CurrentL2A);
-        ins_f!(REG_CURRENT_L3_A,        CurrentL3A);
-        ins_f!(REG_REACTIVE_POWER_KVAR, ReactivePowerKvar);
-        ins_f!(REG_APPARENT_POWER_KVA,  ApparentPowerKva);
-        ins_f!(REG_POWER_FACTOR,        PowerFactor);
-        ins_f!(REG_ROCOF_HZ_S,          RocofHzS);
-        // DC / MPPT
-        ins_f!(REG_DC_VOLTAGE_V,        DcVoltageV);
-        ins_f!(REG_DC_CURRENT_A,        DcCurrentA);
-        ins_f!(REG_DC_POWER_KW,         DcPowerKw);
-        ins_f!(REG_MPPT_VOLTAGE_V,      MpptVoltageV);
-        ins_f!(REG_MPPT_CURRENT_A,      MpptCurrentA);
-        // Thermal
-        ins_f!(REG_INVERTER_TEMP_C,     InverterTempC);
-        ins_f!(REG_AMBIENT_TEMP_C,      AmbientTempC);
-        // Performance & Irradiance
-        ins_f!(REG_EFFICIENCY_PCT,      EfficiencyPct);
-        ins_f!(REG_POA_IRRADIANCE,      PoaIrradianceWM2);
-        ins_f!(REG_SOLAR_ELEVATION,     SolarElevationDeg);
-        ins_f!(REG_PERF_RATIO,          PerformanceRatio);
-        ins_f!(REG_SPECIFIC_YIELD,      SpecificYieldKwhKwp);
-        ins_f!(REG_CAPACITY_FACTOR,     CapacityFactorPct);
-        // Safety & Alarms
-        ins_f!(REG_ISOLATION_MOHM,      IsolationMohm);
-        ins_u!(REG_FAULT_CODE,          FaultCode);
-        ins_u!(REG_ALARM_FLAGS,         AlarmFlags);
-        // Energy Counters
-        ins_f!(REG_DAILY_ENERGY_KWH,    DailyEnergyKwh);
-        ins_f!(REG_MONTHLY_ENERGY_KWH,  MonthlyEnergyKwh);
-        ins_f!(REG_TOTAL_ENERGY_KWH,    TotalEnergyKwh);
-
         println!(
-            "[MODBUS] Plant: {} | base={} | regs {}..{} (63 variables, 100-reg block)",
-            plant.id, base, base, base + 62
+            "[MODBUS] Plant: {} | port={} | {} | base={} | regs {}..{} ({} variables, 100-reg block)",
+            plant.id, port, unit, base, base, base + span - 1, modbus_server::REGISTER_LAYOUT.len()
         );
     }
 
-    tokio::spawn(async move {
-        if let Err(e) = modbus_server::run_server(modbus_addr, state_modbus, register_map).await {
-            eprintln!("Modbus server error: {}", e);
-        }
-    });
+    if let Some(serial) = config.modbus.serial.clone() {
+        let rtu_state = state.clone();
+        let rtu_maps  = registry.fleet_maps().await;
+        let rtu_cfg   = modbus_cfg.clone();
+        let rtu_stop  = shutdown.clone();
+        tasks.push(tokio::spawn(async move {
+            if let Err(e) = modbus_server::run_rtu_server(serial, rtu_state, rtu_maps, rtu_cfg, rtu_stop).await {
+                eprintln!("[MODBUS-RTU] Serial server not running: {}", e);
+            }
+        }));
+    }
+
+    let modbus_addrs: Vec<_> = modbus_endpoints.iter().map(|(addr, _)| *addr).collect();
+    registry.start_modbus().await;
+
+    if let (Some(acceptor), Some(tls_addr)) = (modbus_tls, modbus_tls_addr) {
+        let tls_state = state.clone();
+        let tls_maps  = registry.fleet_maps().await;
+        let tls_cfg   = modbus_cfg.clone();
+        let tls_stop  = shutdown.clone();
+        tasks.push(tokio::spawn(async move {
+            if let Err(e) = modbus_server::run_tls_server(tls_addr, acceptor, tls_state, tls_maps, tls_cfg, tls_stop).await {
+                eprintln!("Modbus TLS server error on {}: {}", tls_addr, e);
+            }
+        }));
+    }
 
     // 5. Optionally start MQTT publisher
     if config.mqtt.enabled {
         let mqtt_cfg   = config.mqtt.clone();
         let mqtt_state = state.clone();
-        let mqtt_plants = config.plants.clone();
-        tokio::spawn(async move {
-            services::mqtt_service::run_publisher(mqtt_cfg, mqtt_state, mqtt_plants).await;
-        });
+        let mqtt_plants = registry.clone();
+        let mqtt_stop   = shutdown.clone();
+        tasks.push(tokio::spawn(async move {
+            services::mqtt_service::run_publisher(mqtt_cfg, mqtt_state, mqtt_plants, mqtt_stop).await;
+        }));
         println!("[MQTT] Publisher task started → {}:{}", config.mqtt.broker_host, config.mqtt.broker_port);
     }
 
     // 6. Start Axum HTTP server
-    let server_port = config.server.port;
-    let shared = SharedState { app: state.clone(), config: config.clone() };
+    let shared = SharedState { app: state.clone(), plants: registry.clone() };
 
-    let app = Router::new()
+    let mut app = Router::new()
         // Top-level routes (health, metrics, WebSocket telemetry)
-        .route("/health",       get(crate::controllers::power_controller::health_check))
-        .route("/metrics",      get(crate::controllers::power_controller::prometheus_metrics))
-        .route("/ws/telemetry", get(crate::controllers::power_controller::ws_telemetry))
+        .route("/health",       get(controllers::power_controller::health_check))
+        .route("/metrics",      get(controllers::power_controller::prometheus_metrics))
+        .route("/ws/telemetry", get(controllers::power_controller::ws_telemetry))
+        .route("/ws/plants/{id}", get(controllers::power_controller::ws_plant_telemetry))
         .with_state(shared.clone())
         // API routes nested under /api
-        .nest("/api", api_routes(shared))
+        .nest("/api", api_routes(shared));
+    // API keys guard the routes above; the docs and the static UI stay open
+    if let Some(auth_cfg) = config.auth.clone() {
+        println!("[AUTH] API keys required ({} configured)", auth_cfg.keys.len());
+        app = app.layer(axum::middleware::from_fn_with_state(Arc::new(auth_cfg), auth::require_api_key));
+    }
+    // Layered last so it runs first: floods are turned away before the key check
+    if let Some(limits) = config.server.rate_limit.clone().filter(|l| l.enabled) {
+        println!(
+            "[RATE] {} read / {} mutation / {} metrics requests per client every {} s",
+            limits.read, limits.mutation, limits.metrics, limits.window_s,
+        );
+        let limiter = rate_limit::RateLimiter::new(limits, state.http_metrics.clone());
+        app = app.layer(axum::middleware::from_fn_with_state(Arc::new(limiter), rate_limit::limit_requests));
+    }
+    let mut app = app
         .route("/scalar", get(|| async {
             Html(Scalar::new(ApiDoc::openapi()).to_html())
         }))
         .fallback_service(ServeDir::new("static"));
+    if let Some(compression) = &config.server.compression {
+        println!("[HTTP] Compressing responses above {} bytes", compression.min_size_bytes);
+        app = app.layer(http_layers::compression_layer(compression));
+    }
+    // Outermost, so preflights are answered before the key check and refusals
+    // still carry the CORS headers the browser needs to read them
+    if let Some(cors) = &config.server.cors {
+        println!("[HTTP] CORS allowed for {}", cors.allowed_origins.join(", "));
+        app = app.layer(http_layers::cors_layer(cors).expect("validated at load"));
+    }
 
-    let addr = SocketAddr::from(([0, 0, 0, 0], server_port));
+    let addr = http_addr;
     println!("─────────────────────────────────────────────────────");
     println!(" Solar Panel Simulator | v{}", env!("CARGO_PKG_VERSION"));
     println!("─────────────────────────────────────────────────────");
@@ -211,11 +263,44 @@ async fn main() {
     println!(" Health:      http://{}/health", addr);
     println!(" Metrics:     http://{}/metrics", addr);
     println!(" WebSocket:   ws://{}/ws/telemetry", addr);
-    println!(" Modbus TCP:  {}", modbus_addr);
+    println!("              ws://{}/ws/plants/{{id}}", addr);
+    for modbus_addr in &modbus_addrs {
+        println!(" Modbus TCP:  {}", modbus_addr);
+    }
+    if let Some(tls_addr) = modbus_tls_addr {
+        println!(" Modbus TLS:  {}", tls_addr);
+    }
     println!("─────────────────────────────────────────────────────");
 
+    // In-flight HTTP requests get SHUTDOWN_TIMEOUT to finish once shutdown starts
+    let http_handle = axum_server::Handle::new();
+    tokio::spawn({
+        let http_handle = http_handle.clone();
+        let shutdown    = shutdown.clone();
+        async move {
+            shutdown.wait().await;
+            http_handle.graceful_shutdown(Some(SHUTDOWN_TIMEOUT));
+        }
+    });
+
     axum_server::bind(addr)
-        .serve(app.into_make_service())
+        .handle(http_handle)
+        .serve(app.into_make_service_with_connect_info::<std::net::SocketAddr>())
         .await
         .unwrap();
+
+    // 7. Wait for Modbus draining, MQTT death message and plant tasks
+    shutdown.trigger();
+    tasks.extend(registry.take_handles().await);
+    let stopped = tokio::time::timeout(SHUTDOWN_TIMEOUT, futures_util::future::join_all(tasks)).await;
+    if stopped.is_err() {
+        eprintln!("[SHUTDOWN] Tasks still running after {:?}, exiting anyway", SHUTDOWN_TIMEOUT);
+    }
+    if let Some(persistence) = &config.persistence {
+        match services::persistence::save(&state, &persistence.path) {
+            Ok(())  => println!("[PERSISTENCE] Snapshot saved to {}", persistence.path),
+            Err(e)  => eprintln!("[PERSISTENCE] Snapshot not saved: {}", e),
+        }
+    }
+    println!("[SHUTDOWN] Simulator stopped");
 }
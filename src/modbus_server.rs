@@ -1,16 +1,32 @@
 use std::collections::HashMap;
 use std::future::Future;
+use std::io;
 use std::net::SocketAddr;
+use std::ops::Range;
 use std::pin::Pin;
+use std::sync::{Arc, RwLock};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::TcpListener;
+use tokio_rustls::TlsAcceptor;
+use tokio_rustls::server::TlsStream;
+use tokio_rustls::rustls;
+use tokio::time::{Instant, Sleep};
 use tokio_modbus::prelude::*;
 use tokio_modbus::server::Service;
 use tokio_modbus::ExceptionCode;
 
-use crate::shared_state::AppState;
+use crate::config::{ModbusConfig, ModbusTlsConfig, PlantConfig, SerialConfig, SerialParity, TemperatureEncoding, WordOrder};
+use crate::models::power::{ControlInterface, PlantData, ReactivePowerMode};
+use crate::shared_state::{AppState, ModbusMetrics};
+use crate::shutdown::{Shutdown, SHUTDOWN_TIMEOUT};
+use crate::profiles::{self, RegisterProfile};
 
 // ─── Register offset constants (relative to plant base_address) ──────────────
-// All float32 variables occupy TWO consecutive u16 registers (IEEE 754 big-endian:
-// high word at base+offset, low word at base+offset+1).
+// All float32 variables occupy TWO consecutive u16 registers (IEEE 754; by default
+// big-endian with the high word at base+offset, see `modbus.word_order`).
 // u16 variables occupy ONE register.
 //
 // Recommended block size: 100 registers per plant.
@@ -60,10 +76,47 @@ pub const REG_DAILY_ENERGY_KWH:    u16 = 57;  // float32  kWh
 pub const REG_MONTHLY_ENERGY_KWH:  u16 = 59;  // float32  kWh
 pub const REG_TOTAL_ENERGY_KWH:    u16 = 61;  // float32  kWh
 
-/// Total registers per plant: 63 (offsets 0..=62).
+/// Energy Counters — integer Wh (exact at any plant size)
+pub const REG_DAILY_ENERGY_WH:     u16 = 63;  // u32      Wh
+pub const REG_MONTHLY_ENERGY_WH:   u16 = 65;  // u32      Wh
+pub const REG_TOTAL_ENERGY_WH:     u16 = 67;  // u64      Wh
+
+/// Data freshness
+pub const REG_LAST_UPDATE_UNIX:    u16 = 71;  // u32      Unix time of last telemetry update
+pub const REG_UPDATE_AGE_S:        u16 = 73;  // u16      s since last telemetry update
+
+/// SCADA supervision
+pub const REG_WATCHDOG_AGE_S:      u16 = 74;  // u16      s since last watchdog write
+
+/// Grid meter (site load behind the export limit)
+pub const REG_SITE_LOAD_KW:        u16 = 75;  // float32  kW
+pub const REG_GRID_EXPORT_KW:      u16 = 77;  // float32  kW, negative = import
+
+// Telemetry per plant: offsets 0..=78, then 86..=99 after the parameter block.
+
+/// Parameter block (holding registers only, writable)
+pub const REG_POWER_LIMIT_PCT:     u16 = 80;  // u16      % of nominal (0-100)
+pub const REG_Q_SETPOINT_KVAR:     u16 = 81;  // i16      kvar, + = over-excited (mode 2)
+pub const REG_COS_PHI_SETPOINT:    u16 = 82;  // u16      cos φ ×1000 (800-1000, 0 = 1.0; mode 1)
+pub const REG_ENABLE:              u16 = 83;  // u16      0 = stop, 1 = run (mirrors the enable coil)
+pub const REG_WATCHDOG:            u16 = 84;  // u16      SCADA watchdog, any value
+pub const REG_Q_MODE:              u16 = 85;  // u16      0 = PF 1.0, 1 = cos φ, 2 = fixed Q, 3 = Q(U)
+
+/// Per-MPPT telemetry (first two MPPT inputs; 0 when absent)
+pub const REG_MPPT1_VOLTAGE_V:     u16 = 86;  // float32  V
+pub const REG_MPPT1_CURRENT_A:     u16 = 88;  // float32  A
+pub const REG_MPPT1_POWER_KW:      u16 = 90;  // float32  kW
+pub const REG_MPPT2_VOLTAGE_V:     u16 = 92;  // float32  V
+pub const REG_MPPT2_CURRENT_A:     u16 = 94;  // float32  A
+pub const REG_MPPT2_POWER_KW:      u16 = 96;  // float32  kW
+pub const REG_CURTAILED_ENERGY_WH: u16 = 98;  // u32      Wh held back by the export limit today
+
+// ─── Coil offset constants (relative to plant base_address) ──────────────────
+/// Inverter enable: ON = grid-connected operation, OFF = stopped
+pub const COIL_INVERTER_ENABLE:    u16 = 0;
 
 // ─── Variable type enum ───────────────────────────────────────────────────────
-#[derive(Clone, Debug)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum VariableType {
     // ── float32 (2 registers) ──
     PowerKw,
@@ -73,121 +126,2062 @@ pub enum VariableType {
     TemperatureC, InverterTempC, AmbientTempC,
     DcVoltageV, DcCurrentA, DcPowerKw,
     MpptVoltageV, MpptCurrentA,
+    Mppt1VoltageV, Mppt1CurrentA, Mppt1PowerKw,
+    Mppt2VoltageV, Mppt2CurrentA, Mppt2PowerKw,
     ReactivePowerKvar, ApparentPowerKva, PowerFactor,
     EfficiencyPct, PoaIrradianceWM2, SolarElevationDeg,
     PerformanceRatio, SpecificYieldKwhKwp, CapacityFactorPct,
     IsolationMohm,
     DailyEnergyKwh, MonthlyEnergyKwh, TotalEnergyKwh,
+    SiteLoadKw, GridExportKw,
+    // ── u32 / u64 integer Wh counters ──
+    DailyEnergyWh, MonthlyEnergyWh, TotalEnergyWh, CurtailedEnergyWh,
+    LastUpdateUnix,
     // ── u16 raw (1 register) ──
     Status,
     FaultCode,
     AlarmFlags,
+    UpdateAgeS,
+    WatchdogAgeS,
+    // ── writable parameters ──
+    PowerLimitPct,
+    ReactiveSetpointKvar,
+    CosPhiSetpoint,
+    EnableFlag,
+    Watchdog,
+    ReactiveMode,
+}
+
+impl VariableType {
+    /// Temperature-class variable, affected by `modbus_mapping.temperature_encoding`.
+    pub const fn is_temperature(self) -> bool {
+        matches!(self, VariableType::TemperatureC | VariableType::InverterTempC | VariableType::AmbientTempC)
+    }
+
+    /// Name used in `modbus_mapping.scaling` and the documentation.
+    pub const fn name(self) -> &'static str {
+        match self {
+            VariableType::PowerKw              => "power_kw",
+            VariableType::VoltageL1V           => "voltage_l1_v",
+            VariableType::VoltageL2V           => "voltage_l2_v",
+            VariableType::VoltageL3V           => "voltage_l3_v",
+            VariableType::CurrentL1A           => "current_l1_a",
+            VariableType::CurrentL2A           => "current_l2_a",
+            VariableType::CurrentL3A           => "current_l3_a",
+            VariableType::FrequencyHz          => "frequency_hz",
+            VariableType::RocofHzS             => "rocof_hz_s",
+            VariableType::TemperatureC         => "temperature_c",
+            VariableType::InverterTempC        => "inverter_temp_c",
+            VariableType::AmbientTempC         => "ambient_temp_c",
+            VariableType::DcVoltageV           => "dc_voltage_v",
+            VariableType::DcCurrentA           => "dc_current_a",
+            VariableType::DcPowerKw            => "dc_power_kw",
+            VariableType::MpptVoltageV         => "mppt_voltage_v",
+            VariableType::MpptCurrentA         => "mppt_current_a",
+            VariableType::Mppt1VoltageV        => "mppt1_voltage_v",
+            VariableType::Mppt1CurrentA        => "mppt1_current_a",
+            VariableType::Mppt1PowerKw         => "mppt1_power_kw",
+            VariableType::Mppt2VoltageV        => "mppt2_voltage_v",
+            VariableType::Mppt2CurrentA        => "mppt2_current_a",
+            VariableType::Mppt2PowerKw         => "mppt2_power_kw",
+            VariableType::ReactivePowerKvar    => "reactive_power_kvar",
+            VariableType::ApparentPowerKva     => "apparent_power_kva",
+            VariableType::PowerFactor          => "power_factor",
+            VariableType::EfficiencyPct        => "efficiency_pct",
+            VariableType::PoaIrradianceWM2     => "poa_irradiance_w_m2",
+            VariableType::SolarElevationDeg    => "solar_elevation_deg",
+            VariableType::PerformanceRatio     => "performance_ratio",
+            VariableType::SpecificYieldKwhKwp  => "specific_yield_kwh_kwp",
+            VariableType::CapacityFactorPct    => "capacity_factor_pct",
+            VariableType::IsolationMohm        => "isolation_mohm",
+            VariableType::DailyEnergyKwh       => "daily_energy_kwh",
+            VariableType::MonthlyEnergyKwh     => "monthly_energy_kwh",
+            VariableType::TotalEnergyKwh       => "total_energy_kwh",
+            VariableType::DailyEnergyWh        => "daily_energy_wh",
+            VariableType::MonthlyEnergyWh      => "monthly_energy_wh",
+            VariableType::TotalEnergyWh        => "total_energy_wh",
+            VariableType::SiteLoadKw           => "site_load_kw",
+            VariableType::GridExportKw         => "grid_export_kw",
+            VariableType::CurtailedEnergyWh    => "curtailed_energy_wh",
+            VariableType::Status               => "status",
+            VariableType::FaultCode            => "fault_code",
+            VariableType::AlarmFlags           => "alarm_flags",
+            VariableType::LastUpdateUnix       => "last_update_unix",
+            VariableType::UpdateAgeS           => "update_age_s",
+            VariableType::WatchdogAgeS         => "watchdog_age_s",
+            VariableType::PowerLimitPct        => "power_limit_pct",
+            VariableType::ReactiveSetpointKvar => "q_setpoint_kvar",
+            VariableType::CosPhiSetpoint       => "cos_phi_setpoint",
+            VariableType::EnableFlag           => "enable",
+            VariableType::Watchdog             => "watchdog",
+            VariableType::ReactiveMode         => "q_mode",
+        }
+    }
+
+    /// Live value of this variable in `data`, widened to f64.
+    pub fn value(self, data: &PlantData) -> f64 {
+        match self {
+            VariableType::PowerKw              => data.power_kw,
+            VariableType::VoltageL1V           => data.voltage_l1_v,
+            VariableType::VoltageL2V           => data.voltage_l2_v,
+            VariableType::VoltageL3V           => data.voltage_l3_v,
+            VariableType::CurrentL1A           => data.current_l1_a,
+            VariableType::CurrentL2A           => data.current_l2_a,
+            VariableType::CurrentL3A           => data.current_l3_a,
+            VariableType::FrequencyHz          => data.frequency_hz,
+            VariableType::RocofHzS             => data.rocof_hz_s,
+            VariableType::TemperatureC         => data.temperature_c,
+            VariableType::InverterTempC        => data.inverter_temp_c,
+            VariableType::AmbientTempC         => data.ambient_temp_c,
+            VariableType::DcVoltageV           => data.dc_voltage_v,
+            VariableType::DcCurrentA           => data.dc_current_a,
+            VariableType::DcPowerKw            => data.dc_power_kw,
+            VariableType::MpptVoltageV         => data.mppt_voltage_v,
+            VariableType::MpptCurrentA         => data.mppt_current_a,
+            VariableType::Mppt1VoltageV        => data.mppt.first().map_or(0.0, |m| m.voltage_v),
+            VariableType::Mppt1CurrentA        => data.mppt.first().map_or(0.0, |m| m.current_a),
+            VariableType::Mppt1PowerKw         => data.mppt.first().map_or(0.0, |m| m.power_kw),
+            VariableType::Mppt2VoltageV        => data.mppt.get(1).map_or(0.0, |m| m.voltage_v),
+            VariableType::Mppt2CurrentA        => data.mppt.get(1).map_or(0.0, |m| m.current_a),
+            VariableType::Mppt2PowerKw         => data.mppt.get(1).map_or(0.0, |m| m.power_kw),
+            VariableType::ReactivePowerKvar    => data.reactive_power_kvar,
+            VariableType::ApparentPowerKva     => data.apparent_power_kva,
+            VariableType::PowerFactor          => data.power_factor,
+            VariableType::EfficiencyPct        => data.efficiency_percent,
+            VariableType::PoaIrradianceWM2     => data.poa_irradiance_w_m2,
+            VariableType::SolarElevationDeg    => data.solar_elevation_deg,
+            VariableType::PerformanceRatio     => data.performance_ratio,
+            VariableType::SpecificYieldKwhKwp  => data.specific_yield_kwh_kwp,
+            VariableType::CapacityFactorPct    => data.capacity_factor_percent,
+            VariableType::IsolationMohm        => data.isolation_resistance_mohm,
+            VariableType::DailyEnergyKwh       => data.daily_energy_kwh,
+            VariableType::MonthlyEnergyKwh     => data.monthly_energy_kwh,
+            VariableType::TotalEnergyKwh       => data.total_energy_kwh,
+            VariableType::DailyEnergyWh        => data.daily_energy_kwh * 1000.0,
+            VariableType::MonthlyEnergyWh      => data.monthly_energy_kwh * 1000.0,
+            VariableType::TotalEnergyWh        => data.total_energy_kwh * 1000.0,
+            VariableType::SiteLoadKw           => data.site_load_kw,
+            VariableType::GridExportKw         => data.grid_export_kw,
+            VariableType::CurtailedEnergyWh    => data.curtailed_energy_kwh * 1000.0,
+            VariableType::Status               => data.status as f64,
+            VariableType::FaultCode            => data.fault_code as f64,
+            VariableType::AlarmFlags           => data.alarm_flags as f64,
+            VariableType::LastUpdateUnix       => data.last_update_unix as f64,
+            VariableType::UpdateAgeS           => data.update_age_s(),
+            VariableType::WatchdogAgeS         => data.watchdog_age_s,
+            VariableType::PowerLimitPct        => data.power_limit_pct,
+            VariableType::ReactiveSetpointKvar => data.reactive_setpoint_kvar,
+            VariableType::CosPhiSetpoint       => data.cos_phi_setpoint,
+            VariableType::EnableFlag           => if data.inverter_enabled { 1.0 } else { 0.0 },
+            VariableType::Watchdog             => data.watchdog_value as f64,
+            VariableType::ReactiveMode         => data.reactive_mode.register_value() as f64,
+        }
+    }
+}
+
+// ─── Register layout (single source of truth) ────────────────────────────────
+/// Wire encoding of a variable.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DataType {
+    /// IEEE 754 float32, two registers, high word first
+    F32,
+    /// Raw unsigned integer, one register
+    U16,
+    /// Two's-complement signed integer, one register
+    I16,
+    /// Unsigned 32-bit integer, two registers (word order as float32)
+    U32,
+    /// Unsigned 64-bit integer, four registers (word order extended to 64 bits)
+    U64,
+}
+
+impl DataType {
+    /// Number of registers occupied.
+    pub const fn len(self) -> u16 {
+        match self {
+            DataType::F32 => 2,
+            DataType::U16 => 1,
+            DataType::I16 => 1,
+            DataType::U32 => 2,
+            DataType::U64 => 4,
+        }
+    }
+
+    /// Label published by `/api/modbus/info`.
+    pub const fn label(self) -> &'static str {
+        match self {
+            DataType::F32 => "float32 IE754",
+            DataType::U16 => "u16 raw",
+            DataType::I16 => "int16",
+            DataType::U32 => "u32 integer",
+            DataType::U64 => "u64 integer",
+        }
+    }
+}
+
+/// One variable in the per-plant register block.
+#[derive(Debug)]
+pub struct RegisterDef {
+    pub offset:      u16,
+    pub var:         VariableType,
+    pub data_type:   DataType,
+    pub description: &'static str,
+    pub unit:        &'static str,
+    /// Holding-register parameter: accepts WriteSingleRegister / WriteMultipleRegisters
+    /// and is not served as an input register
+    pub writable:    bool,
+    /// Raw register value = physical value × scale
+    pub scale:       f64,
+}
+
+impl RegisterDef {
+    const fn scaled(self, scale: f64) -> RegisterDef {
+        RegisterDef { scale, ..self }
+    }
+}
+
+const fn f32_reg(offset: u16, var: VariableType, description: &'static str, unit: &'static str) -> RegisterDef {
+    RegisterDef { offset, var, data_type: DataType::F32, description, unit, writable: false, scale: 1.0 }
+}
+
+const fn u16_reg(offset: u16, var: VariableType, description: &'static str, unit: &'static str) -> RegisterDef {
+    RegisterDef { offset, var, data_type: DataType::U16, description, unit, writable: false, scale: 1.0 }
+}
+
+const fn u32_reg(offset: u16, var: VariableType, description: &'static str, unit: &'static str) -> RegisterDef {
+    RegisterDef { offset, var, data_type: DataType::U32, description, unit, writable: false, scale: 1.0 }
+}
+
+const fn u64_reg(offset: u16, var: VariableType, description: &'static str, unit: &'static str) -> RegisterDef {
+    RegisterDef { offset, var, data_type: DataType::U64, description, unit, writable: false, scale: 1.0 }
+}
+
+const fn u16_rw_reg(offset: u16, var: VariableType, description: &'static str, unit: &'static str) -> RegisterDef {
+    RegisterDef { offset, var, data_type: DataType::U16, description, unit, writable: true, scale: 1.0 }
+}
+
+const fn i16_rw_reg(offset: u16, var: VariableType, description: &'static str, unit: &'static str) -> RegisterDef {
+    RegisterDef { offset, var, data_type: DataType::I16, description, unit, writable: true, scale: 1.0 }
+}
+
+/// Static register layout shared by the Modbus server and `/api/modbus/info`.
+pub const REGISTER_LAYOUT: &[RegisterDef] = &[
+    // AC Output
+    f32_reg(REG_POWER_KW,            VariableType::PowerKw,             "Active power",                  "kW"),
+    f32_reg(REG_VOLTAGE_L1_V,        VariableType::VoltageL1V,          "AC Voltage L1",                 "V"),
+    f32_reg(REG_CURRENT_L1_A,        VariableType::CurrentL1A,          "AC Current L1",                 "A"),
+    f32_reg(REG_FREQUENCY_HZ,        VariableType::FrequencyHz,         "Grid frequency",                "Hz"),
+    f32_reg(REG_TEMPERATURE_C,       VariableType::TemperatureC,        "Cell temperature",              "°C"),
+    u16_reg(REG_STATUS,              VariableType::Status,              "Inverter status (enum 0-5)",    "—"),
+    f32_reg(REG_VOLTAGE_L2_V,        VariableType::VoltageL2V,          "AC Voltage L2",                 "V"),
+    f32_reg(REG_VOLTAGE_L3_V,        VariableType::VoltageL3V,          "AC Voltage L3",                 "V"),
+    f32_reg(REG_CURRENT_L2_A,        VariableType::CurrentL2A,          "AC Current L2",                 "A"),
+    f32_reg(REG_CURRENT_L3_A,        VariableType::CurrentL3A,          "AC Current L3",                 "A"),
+    f32_reg(REG_REACTIVE_POWER_KVAR, VariableType::ReactivePowerKvar,   "Reactive power Q",              "kvar"),
+    f32_reg(REG_APPARENT_POWER_KVA,  VariableType::ApparentPowerKva,    "Apparent power S",              "kVA"),
+    f32_reg(REG_POWER_FACTOR,        VariableType::PowerFactor,         "Power factor cos φ",            "—"),
+    f32_reg(REG_ROCOF_HZ_S,          VariableType::RocofHzS,            "ROCOF (df/dt)",                 "Hz/s"),
+    // DC / MPPT
+    f32_reg(REG_DC_VOLTAGE_V,        VariableType::DcVoltageV,          "DC link voltage",               "V"),
+    f32_reg(REG_DC_CURRENT_A,        VariableType::DcCurrentA,          "DC string current",             "A"),
+    f32_reg(REG_DC_POWER_KW,         VariableType::DcPowerKw,           "DC input power",                "kW"),
+    f32_reg(REG_MPPT_VOLTAGE_V,      VariableType::MpptVoltageV,        "MPPT operating voltage",        "V"),
+    f32_reg(REG_MPPT_CURRENT_A,      VariableType::MpptCurrentA,        "MPPT operating current",        "A"),
+    // Thermal
+    f32_reg(REG_INVERTER_TEMP_C,     VariableType::InverterTempC,       "Inverter heatsink temperature", "°C"),
+    f32_reg(REG_AMBIENT_TEMP_C,      VariableType::AmbientTempC,        "Ambient temperature",           "°C"),
+    // Performance & Irradiance
+    f32_reg(REG_EFFICIENCY_PCT,      VariableType::EfficiencyPct,       "Inverter efficiency",           "%"),
+    f32_reg(REG_POA_IRRADIANCE,      VariableType::PoaIrradianceWM2,    "Plane-of-Array irradiance",     "W/m²"),
+    f32_reg(REG_SOLAR_ELEVATION,     VariableType::SolarElevationDeg,   "Solar elevation angle",         "°"),
+    f32_reg(REG_PERF_RATIO,          VariableType::PerformanceRatio,    "Performance Ratio (IEC 61724)", "—"),
+    f32_reg(REG_SPECIFIC_YIELD,      VariableType::SpecificYieldKwhKwp, "Specific yield",                "kWh/kWp"),
+    f32_reg(REG_CAPACITY_FACTOR,     VariableType::CapacityFactorPct,   "Capacity factor",               "%"),
+    // Safety & Alarms
+    f32_reg(REG_ISOLATION_MOHM,      VariableType::IsolationMohm,       "Isolation resistance DC-GND",   "MΩ"),
+    u16_reg(REG_FAULT_CODE,          VariableType::FaultCode,           "Active fault code (IEC)",       "—"),
+    u16_reg(REG_ALARM_FLAGS,         VariableType::AlarmFlags,          "Alarm bitmask",                 "—"),
+    // Energy Counters
+    f32_reg(REG_DAILY_ENERGY_KWH,    VariableType::DailyEnergyKwh,      "Energy today",                  "kWh"),
+    f32_reg(REG_MONTHLY_ENERGY_KWH,  VariableType::MonthlyEnergyKwh,    "Energy this month",             "kWh"),
+    f32_reg(REG_TOTAL_ENERGY_KWH,    VariableType::TotalEnergyKwh,      "Lifetime energy",               "kWh"),
+    u32_reg(REG_DAILY_ENERGY_WH,     VariableType::DailyEnergyWh,       "Energy today (kWh × 1000)",     "Wh"),
+    u32_reg(REG_MONTHLY_ENERGY_WH,   VariableType::MonthlyEnergyWh,     "Energy this month (kWh × 1000)", "Wh"),
+    u64_reg(REG_TOTAL_ENERGY_WH,     VariableType::TotalEnergyWh,       "Lifetime energy (kWh × 1000)",  "Wh"),
+    // Data freshness
+    u32_reg(REG_LAST_UPDATE_UNIX,    VariableType::LastUpdateUnix,      "Last telemetry update (Unix time)", "s"),
+    u16_reg(REG_UPDATE_AGE_S,        VariableType::UpdateAgeS,          "Seconds since telemetry update", "s"),
+    // SCADA supervision
+    u16_reg(REG_WATCHDOG_AGE_S,      VariableType::WatchdogAgeS,        "Seconds since watchdog write",  "s"),
+    // Grid meter
+    f32_reg(REG_SITE_LOAD_KW,        VariableType::SiteLoadKw,          "Site load",                     "kW"),
+    f32_reg(REG_GRID_EXPORT_KW,      VariableType::GridExportKw,        "Grid export (negative = import)", "kW"),
+    // Parameters (holding registers)
+    u16_rw_reg(REG_POWER_LIMIT_PCT,  VariableType::PowerLimitPct,       "Active power limit",            "%"),
+    i16_rw_reg(REG_Q_SETPOINT_KVAR,  VariableType::ReactiveSetpointKvar, "Reactive power setpoint (mode 2)", "kvar"),
+    u16_rw_reg(REG_COS_PHI_SETPOINT, VariableType::CosPhiSetpoint,      "cos φ setpoint (mode 1, 0 = 1.0)", "—").scaled(1000.0),
+    u16_rw_reg(REG_ENABLE,           VariableType::EnableFlag,          "Inverter enable (0/1)",         "—"),
+    u16_rw_reg(REG_WATCHDOG,         VariableType::Watchdog,            "SCADA watchdog",                "—"),
+    u16_rw_reg(REG_Q_MODE,           VariableType::ReactiveMode,        "Reactive mode (0 = PF 1, 1 = cos φ, 2 = Q, 3 = Q(U))", "—"),
+    // Per-MPPT telemetry
+    f32_reg(REG_MPPT1_VOLTAGE_V,     VariableType::Mppt1VoltageV,       "MPPT 1 voltage",                "V"),
+    f32_reg(REG_MPPT1_CURRENT_A,     VariableType::Mppt1CurrentA,       "MPPT 1 current",                "A"),
+    f32_reg(REG_MPPT1_POWER_KW,      VariableType::Mppt1PowerKw,        "MPPT 1 DC power",               "kW"),
+    f32_reg(REG_MPPT2_VOLTAGE_V,     VariableType::Mppt2VoltageV,       "MPPT 2 voltage",                "V"),
+    f32_reg(REG_MPPT2_CURRENT_A,     VariableType::Mppt2CurrentA,       "MPPT 2 current",                "A"),
+    f32_reg(REG_MPPT2_POWER_KW,      VariableType::Mppt2PowerKw,        "MPPT 2 DC power",               "kW"),
+    // Export limit
+    u32_reg(REG_CURTAILED_ENERGY_WH, VariableType::CurtailedEnergyWh,   "Export-limited energy today (kWh × 1000)", "Wh"),
+];
+
+/// Register table addressed by a read request.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RegisterTable {
+    /// Input registers (0x04): read-only telemetry
+    Input,
+    /// Holding registers (0x03): telemetry plus the writable parameter block
+    Holding,
+}
+
+impl RegisterTable {
+    /// Whether `def` is served in this table.
+    fn serves(self, def: &RegisterDef) -> bool {
+        self == RegisterTable::Holding || !def.writable
+    }
+}
+
+/// A single mapped register address.
+#[derive(Clone, Debug)]
+pub struct RegisterEntry {
+    pub plant_id:  String,
+    pub def:       &'static RegisterDef,
+    /// Word index within a multi-register value (0 = first register)
+    pub word:      u8,
+    /// Scale in effect for this plant (layout default or `modbus_mapping.scaling`)
+    pub scale:     f64,
+    /// Encoding in effect for this plant (see `effective_data_type`)
+    pub data_type: DataType,
+    /// Set once an out-of-range value has been reported, shared by all words
+    clamp_warned: Arc<AtomicBool>,
+}
+
+/// Register address → mapped variable word.
+pub type RegisterMap = HashMap<u16, RegisterEntry>;
+
+/// Number of registers a plant block actually uses (highest offset + width).
+pub fn layout_span() -> u16 {
+    table_span(RegisterTable::Holding)
+}
+
+/// Registers of a plant block visible in `table` (highest offset + width).
+pub fn table_span(table: RegisterTable) -> u16 {
+    REGISTER_LAYOUT.iter()
+        .filter(|d| table.serves(d))
+        .map(|d| d.offset + d.data_type.len())
+        .max()
+        .unwrap_or(0)
+}
+
+/// Address windows of a plant block visible in `table`: runs of served
+/// registers, split where registers served only by the other table lie in
+/// between (the holding-only parameter block). Gaps between served registers
+/// stay inside a window.
+pub fn table_windows(table: RegisterTable) -> Vec<Range<u16>> {
+    let mut defs: Vec<&RegisterDef> = REGISTER_LAYOUT.iter().collect();
+    defs.sort_by_key(|d| d.offset);
+    let mut windows: Vec<Range<u16>> = Vec::new();
+    let mut open = false;
+    for def in defs {
+        if !table.serves(def) {
+            open = false;
+            continue;
+        }
+        let end = def.offset + def.data_type.len();
+        match windows.last_mut() {
+            Some(window) if open => window.end = window.end.max(end),
+            _                    => windows.push(def.offset..end),
+        }
+        open = true;
+    }
+    windows
+}
+
+/// Coil address → plant whose inverter-enable coil lives there.
+pub type CoilMap = HashMap<u16, String>;
+
+/// Everything served on one Modbus unit identifier.
+#[derive(Default)]
+pub struct DeviceMap {
+    pub registers: RegisterMap,
+    pub coils:     CoilMap,
+    /// Input-register windows of each plant block (see `table_windows`)
+    pub input_blocks:   Vec<Range<u32>>,
+    /// Holding-register window of each plant block (includes the parameter block)
+    pub holding_blocks: Vec<Range<u32>>,
+    /// Plants served with a vendor profile (SunSpec, SMA, …)
+    pub profiles:  Vec<Box<dyn RegisterProfile>>,
+}
+
+impl DeviceMap {
+    fn is_empty(&self) -> bool {
+        self.registers.is_empty() && self.coils.is_empty() && self.profiles.is_empty()
+    }
+
+    /// True when every address of `addr .. addr + cnt` lies inside some plant block
+    /// of `table`.
+    ///
+    /// A read that only partially overlaps a block is rejected as a whole, as a
+    /// real inverter does; gaps *inside* a block still read as 0.
+    fn covers(&self, table: RegisterTable, addr: u16, cnt: u16) -> bool {
+        let blocks = match table {
+            RegisterTable::Input   => &self.input_blocks,
+            RegisterTable::Holding => &self.holding_blocks,
+        };
+        (addr as u32..addr as u32 + cnt as u32)
+            .all(|a| blocks.iter().any(|block| block.contains(&a)))
+    }
+}
+
+/// Device maps keyed by Modbus unit identifier.
+///
+/// Plants with a `unit_id` get their own map; all other plants share `shared`,
+/// which keeps the legacy behaviour of answering on any unit id.
+#[derive(Default)]
+pub struct RegisterMaps {
+    pub shared: DeviceMap,
+    pub units:  HashMap<u8, DeviceMap>,
+}
+
+impl RegisterMaps {
+    /// Map serving `unit`, or None when no plant answers on it.
+    fn for_unit(&self, unit: u8) -> Option<&DeviceMap> {
+        self.units.get(&unit)
+            .or_else(|| (!self.shared.is_empty()).then_some(&self.shared))
+    }
+}
+
+/// Register maps a server answers from, swapped as a whole when plants are
+/// created, changed or deleted at runtime. A request resolves against the
+/// maps current when it arrives.
+#[derive(Clone, Default)]
+pub struct LiveRegisterMaps(Arc<RwLock<Arc<RegisterMaps>>>);
+
+impl LiveRegisterMaps {
+    pub fn new(maps: RegisterMaps) -> Self {
+        Self(Arc::new(RwLock::new(Arc::new(maps))))
+    }
+
+    pub fn current(&self) -> Arc<RegisterMaps> {
+        self.0.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn replace(&self, maps: RegisterMaps) {
+        *self.0.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(maps);
+    }
+}
+
+/// Registers a plant occupies on its device: the vendor profile block, or
+/// `base_address` plus the full layout span (parameter block included).
+pub fn plant_block(plant: &PlantConfig) -> Range<u32> {
+    match profiles::for_plant(plant) {
+        Some(profile) => {
+            let block = profile.block();
+            block.start as u32..block.end as u32
+        }
+        None => {
+            let base = plant.modbus_mapping.base_address as u32;
+            base..base + layout_span() as u32
+        }
+    }
+}
+
+/// Build the address → variable maps for every plant: each documented
+/// REGISTER_LAYOUT entry is placed at `base_address + offset`, either in the
+/// plant's own unit map or in the shared one, together with the plant's coils.
+/// Register addresses in the gaps of a block are left unmapped and read as 0.
+/// The block window itself is recorded so reads outside it can be rejected.
+/// Plants with a vendor profile only record the profile's block; registers are
+/// generated on read. Virtual devices (weather station, meter) get their own unit.
+pub fn build_register_map(plants: &[PlantConfig]) -> RegisterMaps {
+    let mut maps = RegisterMaps::default();
+    for plant in plants {
+        for (unit, profile) in profiles::virtual_devices(plant) {
+            let device = maps.units.entry(unit).or_default();
+            let block = profile.block();
+            let block = block.start as u32..block.end as u32;
+            device.input_blocks.push(block.clone());
+            device.holding_blocks.push(block);
+            device.profiles.push(profile);
+        }
+        let device = match plant.modbus_mapping.unit_id {
+            Some(unit) => maps.units.entry(unit).or_default(),
+            None       => &mut maps.shared,
+        };
+        if let Some(profile) = profiles::for_plant(plant) {
+            let block = profile.block();
+            let block = block.start as u32..block.end as u32;
+            device.input_blocks.push(block.clone());
+            device.holding_blocks.push(block);
+            device.profiles.push(profile);
+            continue;
+        }
+        for name in plant.modbus_mapping.scaling.keys() {
+            match REGISTER_LAYOUT.iter().find(|d| d.var.name() == name) {
+                None => eprintln!("[MODBUS] Plant {}: unknown scaling variable '{}' ignored", plant.id, name),
+                Some(def) if def.writable => eprintln!(
+                    "[MODBUS] Plant {}: parameter '{}' has a fixed scale of {}, scaling ignored",
+                    plant.id, name, def.scale
+                ),
+                Some(_) => {}
+            }
+        }
+        let base = plant.modbus_mapping.base_address;
+        for def in REGISTER_LAYOUT {
+            let scale        = effective_scale(plant, def);
+            let data_type    = effective_data_type(plant, def);
+            let clamp_warned = Arc::new(AtomicBool::new(false));
+            for word in 0..data_type.len() {
+                device.registers.insert(base + def.offset + word, RegisterEntry {
+                    plant_id:     plant.id.clone(),
+                    def,
+                    word:         word as u8,
+                    scale,
+                    data_type,
+                    clamp_warned: clamp_warned.clone(),
+                });
+            }
+        }
+        device.coils.insert(base + COIL_INVERTER_ENABLE, plant.id.clone());
+        for table in [RegisterTable::Input, RegisterTable::Holding] {
+            let blocks = match table {
+                RegisterTable::Input   => &mut device.input_blocks,
+                RegisterTable::Holding => &mut device.holding_blocks,
+            };
+            blocks.extend(table_windows(table).into_iter()
+                .map(|w| base as u32 + w.start as u32..base as u32 + w.end as u32));
+        }
+    }
+    maps
+}
+
+/// Scale of int16 temperature registers (0.1 °C resolution).
+pub const INT16_TEMPERATURE_SCALE: f64 = 10.0;
+
+/// Whether `plant` serves `def` as an int16 temperature instead of the layout float32.
+fn int16_temperature(plant: &PlantConfig, def: &RegisterDef) -> bool {
+    def.var.is_temperature() && plant.modbus_mapping.temperature_encoding == TemperatureEncoding::Int16
+}
+
+/// Encoding of `def` for `plant`. An int16 temperature keeps its two-register
+/// slot; the second register is left unmapped.
+pub fn effective_data_type(plant: &PlantConfig, def: &RegisterDef) -> DataType {
+    if int16_temperature(plant, def) { DataType::I16 } else { def.data_type }
+}
+
+/// Scale applied to `def` for `plant`: the `modbus_mapping.scaling` override when
+/// it is a positive finite number, otherwise the layout default (×10 for int16
+/// temperatures). Parameters keep their fixed scale so write validation stays
+/// meaningful.
+pub fn effective_scale(plant: &PlantConfig, def: &RegisterDef) -> f64 {
+    if def.writable {
+        return def.scale;
+    }
+    let default = if int16_temperature(plant, def) { INT16_TEMPERATURE_SCALE } else { def.scale };
+    plant.modbus_mapping.scaling.get(def.var.name())
+        .copied()
+        .filter(|s| s.is_finite() && *s > 0.0)
+        .unwrap_or(default)
+}
+
+/// Round a scaled value into `min..=max`. Out-of-range values saturate instead
+/// of wrapping, with one warning per plant variable.
+fn clamp_scaled(entry: &RegisterEntry, value: f64, min: f64, max: f64) -> f64 {
+    let rounded = value.round();
+    let clamped = rounded.clamp(min, max);
+    if clamped != rounded && !entry.clamp_warned.swap(true, Ordering::Relaxed) {
+        eprintln!(
+            "[MODBUS] Plant {}: {} = {} at scale {} does not fit in {} — clamped to {}",
+            entry.plant_id, entry.def.var.name(), value / entry.scale, entry.scale,
+            entry.data_type.label(), clamped
+        );
+    }
+    clamped
+}
+
+/// Split a 32-bit value into two registers, in transmission order.
+fn u32_to_words(bits: u32, order: WordOrder) -> (u16, u16) {
+    let high = (bits >> 16) as u16;
+    let low  = (bits & 0xFFFF) as u16;
+    match order {
+        WordOrder::Abcd => (high, low),
+        WordOrder::Cdab => (low, high),
+        WordOrder::Badc => (high.swap_bytes(), low.swap_bytes()),
+        WordOrder::Dcba => (low.swap_bytes(), high.swap_bytes()),
+    }
+}
+
+/// Split a 64-bit value into four registers, in transmission order.
+/// The 32-bit ordering is extended: CDAB/DCBA send the least significant word first.
+fn u64_to_words(v: u64, order: WordOrder) -> [u16; 4] {
+    let mut words = [(v >> 48) as u16, (v >> 32) as u16, (v >> 16) as u16, v as u16];
+    if matches!(order, WordOrder::Cdab | WordOrder::Dcba) {
+        words.reverse();
+    }
+    if matches!(order, WordOrder::Badc | WordOrder::Dcba) {
+        words = words.map(u16::swap_bytes);
+    }
+    words
+}
+
+/// Encode a f32 (IEEE 754) into two registers using `order`.
+fn float_to_words(v: f32, order: WordOrder) -> (u16, u16) {
+    u32_to_words(v.to_bits(), order)
+}
+
+/// Resolve `cnt` consecutive registers starting at `addr`.
+///
+/// Each register is looked up independently, so a read may start on the low
+/// word of a float32 pair or span several variables. Plant data is fetched at
+/// most once per plant per request so every word of a float comes from the same
+/// snapshot. Unmapped addresses, and parameters read through the input table,
+/// read as 0.
+pub fn read_registers(
+    state: &AppState,
+    register_map: &RegisterMap,
+    table: RegisterTable,
+    order: WordOrder,
+    addr: u16,
+    cnt: u16,
+) -> Result<Vec<u16>, ExceptionCode> {
+    if addr.checked_add(cnt.saturating_sub(1)).is_none() {
+        return Err(ExceptionCode::IllegalDataAddress);
+    }
+    let mut snapshots: HashMap<&str, Option<PlantData>> = HashMap::new();
+    let regs = (0..cnt).map(|i| {
+        let Some(entry) = register_map.get(&(addr + i)).filter(|e| table.serves(e.def)) else { return 0 };
+        let snapshot = snapshots
+            .entry(entry.plant_id.as_str())
+            .or_insert_with(|| state.get_data(&entry.plant_id));
+        let Some(data) = snapshot else { return 0 };
+
+        let value = entry.def.var.value(data) * entry.scale;
+        let words = match entry.data_type {
+            // ── u16 single-register variables ──────────────────────
+            DataType::U16 => [clamp_scaled(entry, value, 0.0, u16::MAX as f64) as u16, 0, 0, 0],
+            DataType::I16 => [clamp_scaled(entry, value, i16::MIN as f64, i16::MAX as f64) as i16 as u16, 0, 0, 0],
+            // ── float32 two-register variables ─────────────────────
+            DataType::F32 => {
+                let (first, second) = float_to_words(value as f32, order);
+                [first, second, 0, 0]
+            }
+            // ── integer counters ───────────────────────────────────
+            DataType::U32 => {
+                let (first, second) = u32_to_words(clamp_scaled(entry, value, 0.0, u32::MAX as f64) as u32, order);
+                [first, second, 0, 0]
+            }
+            DataType::U64 => u64_to_words(clamp_scaled(entry, value, 0.0, u64::MAX as f64) as u64, order),
+        };
+        words[entry.word as usize]
+    }).collect();
+    Ok(regs)
+}
+
+/// Read from a vendor profile block when the request lies inside one, otherwise
+/// from the simulator register map.
+fn read_device(
+    state: &AppState,
+    device: &DeviceMap,
+    table: RegisterTable,
+    order: WordOrder,
+    addr: u16,
+    cnt: u16,
+) -> Result<Vec<u16>, ExceptionCode> {
+    match device.profiles.iter().find(|p| p.contains(addr, cnt)) {
+        Some(profile) => Ok(profile.read(state, addr, cnt)),
+        None          => read_registers(state, &device.registers, table, order, addr, cnt),
+    }
 }
 
-/// Encode a f32 into two big-endian u16 words (IEEE 754).
-fn float_to_words(v: f32) -> (u16, u16) {
-    let bits = v.to_bits();
-    ((bits >> 16) as u16, (bits & 0xFFFF) as u16)
+/// Whether `value` is an acceptable raw register value for parameter `var`.
+fn parameter_in_range(var: VariableType, value: u16) -> bool {
+    match var {
+        VariableType::PowerLimitPct  => value <= 100,
+        VariableType::CosPhiSetpoint => value == 0 || (800..=1000).contains(&value),
+        VariableType::EnableFlag     => value <= 1,
+        VariableType::ReactiveMode   => ReactivePowerMode::from_register(value).is_some(),
+        _                            => true,
+    }
+}
+
+/// Write `values` to consecutive registers starting at `addr`.
+///
+/// The whole request is validated before anything is applied: a write touching
+/// an unmapped or read-only register fails with IllegalDataAddress, an out-of-range
+/// value with IllegalDataValue, and in both cases no register is changed.
+pub(crate) fn write_registers(state: &AppState, register_map: &RegisterMap, addr: u16, values: &[u16]) -> Result<(), ExceptionCode> {
+    let mut writes = Vec::with_capacity(values.len());
+    for (i, &value) in values.iter().enumerate() {
+        let reg = u16::try_from(i).ok()
+            .and_then(|i| addr.checked_add(i))
+            .ok_or(ExceptionCode::IllegalDataAddress)?;
+        let Some(entry) = register_map.get(&reg) else { return Err(ExceptionCode::IllegalDataAddress) };
+        if !entry.def.writable {
+            return Err(ExceptionCode::IllegalDataAddress);
+        }
+        if !parameter_in_range(entry.def.var, value) {
+            return Err(ExceptionCode::IllegalDataValue);
+        }
+        writes.push((entry, value));
+    }
+    for (entry, value) in writes {
+        let plant_id = entry.plant_id.as_str();
+        match entry.def.var {
+            VariableType::PowerLimitPct        => state.set_power_limit(plant_id, value as f64, ControlInterface::Modbus),
+            VariableType::ReactiveSetpointKvar => state.set_reactive_setpoint(plant_id, value as i16 as f64),
+            VariableType::CosPhiSetpoint       => state.set_cos_phi_setpoint(plant_id, value as f64 / entry.def.scale),
+            VariableType::EnableFlag           => state.set_inverter_enabled(plant_id, value == 1, ControlInterface::Modbus),
+            VariableType::Watchdog             => state.set_watchdog(plant_id, value),
+            VariableType::ReactiveMode         => {
+                state.set_reactive_mode(plant_id, ReactivePowerMode::from_register(value).unwrap_or_default())
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Mask Write Register (0x16) result, as defined by the Modbus spec.
+const fn apply_mask(current: u16, and_mask: u16, or_mask: u16) -> u16 {
+    (current & and_mask) | (or_mask & !and_mask)
+}
+
+/// Mask Write Register on the alarm bitmask: every bit the mask clears
+/// acknowledges its alarm. Masks that would set a bit fail with
+/// IllegalDataValue without changing anything; any other register is
+/// IllegalDataAddress.
+fn mask_write_alarm_flags(
+    state: &AppState,
+    register_map: &RegisterMap,
+    addr: u16,
+    and_mask: u16,
+    or_mask: u16,
+) -> Result<(), ExceptionCode> {
+    let Some(entry) = register_map.get(&addr).filter(|e| e.def.var == VariableType::AlarmFlags) else {
+        return Err(ExceptionCode::IllegalDataAddress);
+    };
+    let current = state.get_data(&entry.plant_id).map_or(0, |d| d.alarm_flags as u16);
+    let result  = apply_mask(current, and_mask, or_mask);
+    if result & !current != 0 {
+        return Err(ExceptionCode::IllegalDataValue);
+    }
+    let cleared = current & !result;
+    if cleared != 0 {
+        state.acknowledge_alarm_flags(&entry.plant_id, cleared as u32);
+    }
+    Ok(())
+}
+
+/// Read `cnt` coils starting at `addr`; every address must be a defined coil.
+fn read_coils(state: &AppState, coils: &CoilMap, addr: u16, cnt: u16) -> Result<Vec<bool>, ExceptionCode> {
+    (0..cnt).map(|i| {
+        let plant_id = addr.checked_add(i)
+            .and_then(|a| coils.get(&a))
+            .ok_or(ExceptionCode::IllegalDataAddress)?;
+        Ok(state.get_data(plant_id).map(|d| d.inverter_enabled).unwrap_or(true))
+    }).collect()
+}
+
+/// Write consecutive coils starting at `addr`, validating every address first.
+fn write_coils(state: &AppState, coils: &CoilMap, addr: u16, values: &[bool]) -> Result<(), ExceptionCode> {
+    let targets = values.iter().enumerate().map(|(i, &on)| {
+        u16::try_from(i).ok()
+            .and_then(|i| addr.checked_add(i))
+            .and_then(|a| coils.get(&a))
+            .map(|plant_id| (plant_id, on))
+            .ok_or(ExceptionCode::IllegalDataAddress)
+    }).collect::<Result<Vec<_>, _>>()?;
+    for (plant_id, on) in targets {
+        state.set_inverter_enabled(plant_id, on, ControlInterface::Modbus);
+    }
+    Ok(())
 }
 
 struct MbService {
     state: AppState,
-    register_map: HashMap<u16, (String, VariableType, u8)>,
+    register_maps: LiveRegisterMaps,
+    /// Serve reads outside every plant block as zeros instead of IllegalDataAddress
+    zero_fill_unmapped: bool,
+    word_order: WordOrder,
+    /// RTU only: the single slave address served on the serial line
+    rtu_slave: Option<u8>,
+    /// TCP only: tracks the client connection for as long as the service lives
+    connection: Option<ClientConnection>,
+}
+
+/// Counts a TCP client as connected until its service is dropped with the connection.
+struct ClientConnection {
+    state: AppState,
+    peer:  SocketAddr,
+}
+
+impl ClientConnection {
+    fn open(state: AppState, peer: SocketAddr) -> Self {
+        state.modbus_metrics.client_connected();
+        println!("[MODBUS] Client connected: {}", peer);
+        Self { state, peer }
+    }
+}
+
+impl Drop for ClientConnection {
+    fn drop(&mut self) {
+        self.state.modbus_metrics.client_disconnected();
+        println!("[MODBUS] Client disconnected: {}", self.peer);
+    }
+}
+
+impl MbService {
+    fn new(state: AppState, register_maps: LiveRegisterMaps, modbus: &ModbusConfig) -> Self {
+        Self {
+            state,
+            register_maps,
+            zero_fill_unmapped: modbus.zero_fill_unmapped,
+            word_order:         modbus.word_order,
+            rtu_slave:          None,
+            connection:         None,
+        }
+    }
+
+    /// Resolve one request against the unit's device map.
+    fn handle(&self, req: SlaveRequest<'static>) -> Result<Response, ExceptionCode> {
+        if self.rtu_slave.is_some_and(|slave| slave != req.slave) {
+            return Err(ExceptionCode::GatewayTargetDevice);
+        }
+        let register_maps = self.register_maps.current();
+        let Some(device) = register_maps.for_unit(req.slave) else {
+            return Err(ExceptionCode::GatewayTargetDevice);
+        };
+        let register_map = &device.registers;
+        let outside_blocks = match req.request {
+            Request::ReadInputRegisters(addr, cnt) => {
+                !self.zero_fill_unmapped && !device.covers(RegisterTable::Input, addr, cnt)
+            }
+            Request::ReadHoldingRegisters(addr, cnt)
+            | Request::ReadWriteMultipleRegisters(addr, cnt, _, _) => {
+                !self.zero_fill_unmapped && !device.covers(RegisterTable::Holding, addr, cnt)
+            }
+            _ => false,
+        };
+        if outside_blocks {
+            return Err(ExceptionCode::IllegalDataAddress);
+        }
+        match req.request {
+            Request::ReadInputRegisters(addr, cnt) => {
+                read_device(&self.state, device, RegisterTable::Input, self.word_order, addr, cnt)
+                    .map(Response::ReadInputRegisters)
+            }
+            Request::ReadHoldingRegisters(addr, cnt) => {
+                read_device(&self.state, device, RegisterTable::Holding, self.word_order, addr, cnt)
+                    .map(Response::ReadHoldingRegisters)
+            }
+            Request::WriteSingleRegister(addr, value) => {
+                write_registers(&self.state, register_map, addr, &[value])
+                    .map(|_| Response::WriteSingleRegister(addr, value))
+            }
+            Request::WriteMultipleRegisters(addr, values) => {
+                write_registers(&self.state, register_map, addr, &values)
+                    .map(|_| Response::WriteMultipleRegisters(addr, values.len() as u16))
+            }
+            Request::ReadWriteMultipleRegisters(read_addr, cnt, write_addr, values) => {
+                // Spec ordering: the write is applied before the read is served
+                write_registers(&self.state, register_map, write_addr, &values)?;
+                read_device(&self.state, device, RegisterTable::Holding, self.word_order, read_addr, cnt)
+                    .map(Response::ReadWriteMultipleRegisters)
+            }
+            Request::MaskWriteRegister(addr, and_mask, or_mask) => {
+                mask_write_alarm_flags(&self.state, register_map, addr, and_mask, or_mask)
+                    .map(|_| Response::MaskWriteRegister(addr, and_mask, or_mask))
+            }
+            Request::ReadCoils(addr, cnt) => {
+                read_coils(&self.state, &device.coils, addr, cnt).map(Response::ReadCoils)
+            }
+            Request::WriteSingleCoil(addr, on) => {
+                write_coils(&self.state, &device.coils, addr, &[on])
+                    .map(|_| Response::WriteSingleCoil(addr, on))
+            }
+            Request::WriteMultipleCoils(addr, values) => {
+                write_coils(&self.state, &device.coils, addr, &values)
+                    .map(|_| Response::WriteMultipleCoils(addr, values.len() as u16))
+            }
+            Request::Custom(FC_DIAGNOSTICS, data) => {
+                diagnostics(&self.state.modbus_metrics, &data)
+                    .map(|data| Response::Custom(FC_DIAGNOSTICS, data.into()))
+            }
+            _ => Err(ExceptionCode::IllegalFunction),
+        }
+    }
+}
+
+// ─── Diagnostics (0x08) ──────────────────────────────────────────────────────
+
+const FC_DIAGNOSTICS: u8 = 0x08;
+
+const DIAG_RETURN_QUERY_DATA:      u16 = 0x00;
+const DIAG_CLEAR_COUNTERS:         u16 = 0x0A;
+const DIAG_BUS_MESSAGE_COUNT:      u16 = 0x0B;
+const DIAG_SERVER_EXCEPTION_COUNT: u16 = 0x0D;
+const DIAG_SERVER_MESSAGE_COUNT:   u16 = 0x0E;
+
+/// Answer a Diagnostics request (sub-function + data) with the response data field.
+/// Counters are 16-bit on the wire and wrap like on a real device.
+fn diagnostics(metrics: &ModbusMetrics, data: &[u8]) -> Result<Vec<u8>, ExceptionCode> {
+    let [hi, lo, ..] = *data else {
+        return Err(ExceptionCode::IllegalDataValue);
+    };
+    let counter = |value: u64| {
+        let [c_hi, c_lo] = (value as u16).to_be_bytes();
+        vec![hi, lo, c_hi, c_lo]
+    };
+    match u16::from_be_bytes([hi, lo]) {
+        DIAG_RETURN_QUERY_DATA => Ok(data.to_vec()),
+        DIAG_CLEAR_COUNTERS => {
+            metrics.clear_diagnostics();
+            Ok(data.to_vec())
+        }
+        DIAG_BUS_MESSAGE_COUNT      => Ok(counter(metrics.bus_messages())),
+        DIAG_SERVER_EXCEPTION_COUNT => Ok(counter(metrics.exception_responses())),
+        DIAG_SERVER_MESSAGE_COUNT   => Ok(counter(metrics.server_messages())),
+        _ => Err(ExceptionCode::IllegalFunction),
+    }
+}
+
+/// Metrics label of a request function code.
+fn function_label(request: &Request<'_>) -> &'static str {
+    match request {
+        Request::ReadCoils(..)                  => "read_coils",
+        Request::ReadHoldingRegisters(..)       => "read_holding",
+        Request::ReadInputRegisters(..)         => "read_input",
+        Request::WriteSingleCoil(..)            => "write_single_coil",
+        Request::WriteMultipleCoils(..)         => "write_multiple_coils",
+        Request::WriteSingleRegister(..)        => "write_single_register",
+        Request::WriteMultipleRegisters(..)     => "write_multiple_registers",
+        Request::ReadWriteMultipleRegisters(..) => "read_write_multiple_registers",
+        Request::MaskWriteRegister(..)          => "mask_write_register",
+        Request::Custom(FC_DIAGNOSTICS, _)      => "diagnostics",
+        _                                       => "other",
+    }
+}
+
+/// Data bytes carried by a response PDU (excluding function code and headers).
+fn response_bytes(response: &Response) -> u64 {
+    match response {
+        Response::ReadInputRegisters(regs)
+        | Response::ReadHoldingRegisters(regs)
+        | Response::ReadWriteMultipleRegisters(regs) => regs.len() as u64 * 2,
+        Response::ReadCoils(coils) => coils.len().div_ceil(8) as u64,
+        // Mask write echoes address + both masks
+        Response::MaskWriteRegister(..) => 6,
+        Response::Custom(_, data) => data.len() as u64,
+        // Write responses echo address + value/quantity
+        _ => 4,
+    }
 }
 
 impl Service for MbService {
-    type Request = Request<'static>;
+    type Request = SlaveRequest<'static>;
     type Response = Response;
     type Exception = ExceptionCode;
     type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Exception>> + Send + Sync>>;
 
     fn call(&self, req: Self::Request) -> Self::Future {
-        let state = self.state.clone();
-        let register_map = self.register_map.clone();
-
-        Box::pin(async move {
-            let resolve = |reg_addr: u16| -> u16 {
-                let Some((plant_id, var_type, word_idx)) = register_map.get(&reg_addr) else { return 0 };
-                let Some(data)                           = state.get_data(plant_id)     else { return 0 };
-
-                match var_type {
-                    // ── u16 single-register variables ──────────────────────
-                    VariableType::Status     => data.status,
-                    VariableType::FaultCode  => data.fault_code,
-                    VariableType::AlarmFlags => data.alarm_flags as u16,
-
-                    // ── float32 two-register variables ─────────────────────
-                    _ => {
-                        let f: f32 = match var_type {
-                            VariableType::PowerKw              => data.power_kw               as f32,
-                            VariableType::VoltageL1V           => data.voltage_l1_v           as f32,
-                            VariableType::VoltageL2V           => data.voltage_l2_v           as f32,
-                            VariableType::VoltageL3V           => data.voltage_l3_v           as f32,
-                            VariableType::CurrentL1A           => data.current_l1_a           as f32,
-                            VariableType::CurrentL2A           => data.current_l2_a           as f32,
-                            VariableType::CurrentL3A           => data.current_l3_a           as f32,
-                            VariableType::FrequencyHz          => data.frequency_hz           as f32,
-                            VariableType::RocofHzS             => data.rocof_hz_s             as f32,
-                            VariableType::TemperatureC         => data.temperature_c          as f32,
-                            VariableType::InverterTempC        => data.inverter_temp_c        as f32,
-                            VariableType::AmbientTempC         => data.ambient_temp_c         as f32,
-                            VariableType::DcVoltageV           => data.dc_voltage_v           as f32,
-                            VariableType::DcCurrentA           => data.dc_current_a           as f32,
-                            VariableType::DcPowerKw            => data.dc_power_kw            as f32,
-                            VariableType::MpptVoltageV         => data.mppt_voltage_v         as f32,
-                            VariableType::MpptCurrentA         => data.mppt_current_a         as f32,
-                            VariableType::ReactivePowerKvar    => data.reactive_power_kvar    as f32,
-                            VariableType::ApparentPowerKva     => data.apparent_power_kva     as f32,
-                            VariableType::PowerFactor          => data.power_factor           as f32,
-                            VariableType::EfficiencyPct        => data.efficiency_percent     as f32,
-                            VariableType::PoaIrradianceWM2     => data.poa_irradiance_w_m2    as f32,
-                            VariableType::SolarElevationDeg    => data.solar_elevation_deg    as f32,
-                            VariableType::PerformanceRatio     => data.performance_ratio      as f32,
-                            VariableType::SpecificYieldKwhKwp  => data.specific_yield_kwh_kwp as f32,
-                            VariableType::CapacityFactorPct    => data.capacity_factor_percent as f32,
-                            VariableType::IsolationMohm        => data.isolation_resistance_mohm as f32,
-                            VariableType::DailyEnergyKwh       => data.daily_energy_kwh       as f32,
-                            VariableType::MonthlyEnergyKwh     => data.monthly_energy_kwh     as f32,
-                            VariableType::TotalEnergyKwh       => data.total_energy_kwh       as f32,
-                            // u16 variants handled above — unreachable here
-                            VariableType::Status | VariableType::FaultCode | VariableType::AlarmFlags => 0.0,
-                        };
-                        let (high, low) = float_to_words(f);
-                        if *word_idx == 0 { high } else { low }
-                    }
-                }
-            };
+        let function = function_label(&req.request);
+        let metrics = &self.state.modbus_metrics;
+        metrics.record_message(self.rtu_slave.is_none_or(|slave| slave == req.slave));
+        let result = self.handle(req);
+        match &result {
+            Ok(response) => metrics.record_request(function, response_bytes(response)),
+            Err(code)    => {
+                metrics.record_request(function, 0);
+                metrics.record_exception(format!("{:?}", code));
+            }
+        }
+        Box::pin(std::future::ready(result))
+    }
+}
 
-            match req {
-                Request::ReadInputRegisters(addr, cnt) => {
-                    let regs: Vec<u16> = (0..cnt).map(|i| resolve(addr + i)).collect();
-                    Ok(Response::ReadInputRegisters(regs))
-                }
-                Request::ReadHoldingRegisters(addr, cnt) => {
-                    let regs: Vec<u16> = (0..cnt).map(|i| resolve(addr + i)).collect();
-                    Ok(Response::ReadHoldingRegisters(regs))
+/// TCP stream that fails with `TimedOut` once no request bytes arrived for `timeout`,
+/// which makes the Modbus server drop the connection. Once `shutdown` fires it
+/// ends as the client closing it would, at the next wait for a request: the
+/// answer to the request in progress still goes out.
+struct IdleTimeoutStream<S> {
+    inner:    S,
+    timeout:  Option<Duration>,
+    deadline: Pin<Box<Sleep>>,
+    closing:  Pin<Box<dyn Future<Output = ()> + Send>>,
+}
+
+impl<S> IdleTimeoutStream<S> {
+    fn new(inner: S, timeout: Option<Duration>, shutdown: &Shutdown) -> Self {
+        let deadline = Box::pin(tokio::time::sleep(timeout.unwrap_or(Duration::MAX / 4)));
+        let shutdown = shutdown.clone();
+        let closing = Box::pin(async move { shutdown.wait().await });
+        Self { inner, timeout, deadline, closing }
+    }
+}
+
+impl<S: AsyncRead + Unpin> AsyncRead for IdleTimeoutStream<S> {
+    fn poll_read(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        let this = &mut *self;
+        let before = buf.filled().len();
+        match Pin::new(&mut this.inner).poll_read(cx, buf) {
+            Poll::Ready(result) => {
+                if let (Some(timeout), true) = (this.timeout, buf.filled().len() > before) {
+                    this.deadline.as_mut().reset(Instant::now() + timeout);
                 }
-                _ => Err(ExceptionCode::IllegalFunction),
+                Poll::Ready(result)
             }
-        })
+            Poll::Pending if this.timeout.is_some() && this.deadline.as_mut().poll(cx).is_ready() => {
+                Poll::Ready(Err(io::Error::new(io::ErrorKind::TimedOut, "Modbus client idle timeout")))
+            }
+            // Nothing read: the server sees end of stream and closes the connection
+            Poll::Pending if this.closing.as_mut().poll(cx).is_ready() => Poll::Ready(Ok(())),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<S: AsyncWrite + Unpin> AsyncWrite for IdleTimeoutStream<S> {
+    fn poll_write(mut self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        Pin::new(&mut self.inner).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Pin::new(&mut self.inner).poll_shutdown(cx)
     }
 }
 
 pub async fn run_server(
     addr: SocketAddr,
     state: AppState,
-    register_map: HashMap<u16, (String, VariableType, u8)>,
+    register_maps: LiveRegisterMaps,
+    modbus: ModbusConfig,
+    shutdown: Shutdown,
 ) -> Result<(), Box<dyn std::error::Error>> {
-    println!("Modbus TCP server listening on {}", addr);
-    let listener = tokio::net::TcpListener::bind(addr).await?;
+    println!(
+        "Modbus TCP server listening on {} (word order {}, max {} clients, idle timeout {} s)",
+        addr, modbus.word_order.as_str(), modbus.max_connections, modbus.idle_timeout_s
+    );
+    let listener = TcpListener::bind(addr).await?;
+    serve_tcp(listener, state, register_maps, modbus, shutdown).await?;
+    Ok(())
+}
+
+/// Wait (bounded by SHUTDOWN_TIMEOUT) for the connections to close. Each one
+/// closes as soon as its request in progress is answered (see
+/// `IdleTimeoutStream`); those still open at the deadline are closed when the
+/// process exits.
+async fn drain_clients(state: &AppState) {
+    let metrics = &state.modbus_metrics;
+    let clients = metrics.connected_clients();
+    if clients == 0 {
+        return;
+    }
+    println!("[MODBUS] Closing {} client connection(s)", clients);
+    if tokio::time::timeout(SHUTDOWN_TIMEOUT, metrics.all_disconnected()).await.is_err() {
+        println!("[MODBUS] {} client(s) still connected, closing at exit", metrics.connected_clients());
+    }
+}
+
+/// Service for a new client `peer`, or None once the connection limit is reached.
+fn admit_client(state: &AppState, register_maps: &LiveRegisterMaps, modbus: &ModbusConfig, peer: SocketAddr) -> Option<MbService> {
+    if state.modbus_metrics.connected_clients() as usize >= modbus.max_connections {
+        state.modbus_metrics.client_rejected();
+        println!("[MODBUS] Connection limit ({}) reached, refusing {}", modbus.max_connections, peer);
+        return None;
+    }
+    let mut service = MbService::new(state.clone(), register_maps.clone(), modbus);
+    service.connection = Some(ClientConnection::open(state.clone(), peer));
+    Some(service)
+}
+
+/// Accept Modbus TCP clients on `listener`, enforcing the connection limit and
+/// idle timeout, until `shutdown` fires. New clients are then turned away, open
+/// connections close once their request in progress is answered (see
+/// `drain_clients`) and the listener is closed.
+async fn serve_tcp(
+    listener: TcpListener,
+    state: AppState,
+    register_maps: LiveRegisterMaps,
+    modbus: ModbusConfig,
+    shutdown: Shutdown,
+) -> io::Result<()> {
     let server = tokio_modbus::server::tcp::Server::new(listener);
+    let idle_timeout = (modbus.idle_timeout_s > 0).then(|| Duration::from_secs(modbus.idle_timeout_s));
 
-    let on_connected = move |socket, _addr| {
-        let state        = state.clone();
-        let register_map = register_map.clone();
-        async move { Ok::<_, std::io::Error>(Some((MbService { state, register_map }, socket))) }
+    let (closing, connections) = (shutdown.clone(), state.clone());
+    let on_connected = move |socket, peer| {
+        let accepted = if closing.is_triggered() { None } else {
+            admit_client(&state, &register_maps, &modbus, peer)
+                .map(|service| (service, IdleTimeoutStream::new(socket, idle_timeout, &closing)))
+        };
+        async move { Ok::<_, io::Error>(accepted) }
     };
 
-    server.serve(&on_connected, |err| { eprintln!("Modbus server error: {:?}", err); }).await?;
+    // The server keeps running while its connections close: dropping it
+    // would cancel their tasks mid-response
+    let drained = async {
+        shutdown.wait().await;
+        drain_clients(&connections).await;
+    };
+    tokio::select! {
+        result = server.serve(&on_connected, |err| { eprintln!("Modbus server error: {:?}", err); }) => return result,
+        _ = drained => {}
+    }
+    println!("[MODBUS] TCP listener closed");
+    Ok(())
+}
+
+// ─── Modbus/TCP Security (TLS) ────────────────────────────────────────────────
+
+/// Clients must complete the TLS handshake within this time.
+const TLS_HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Build the TLS acceptor from the PEM certificate chain and private key of `tls`.
+/// Unreadable files, missing PEM blocks and a key not matching the certificate
+/// are reported with the offending path.
+pub fn load_tls_acceptor(tls: &ModbusTlsConfig) -> Result<TlsAcceptor, String> {
+    let open = |path: &str| std::fs::File::open(path)
+        .map(io::BufReader::new)
+        .map_err(|e| format!("cannot read {}: {}", path, e));
+
+    let certs = rustls_pemfile::certs(&mut open(&tls.cert_path)?)
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| format!("invalid certificate {}: {}", tls.cert_path, e))?;
+    if certs.is_empty() {
+        return Err(format!("invalid certificate {}: no PEM certificate found", tls.cert_path));
+    }
+    let key = rustls_pemfile::private_key(&mut open(&tls.key_path)?)
+        .map_err(|e| format!("invalid private key {}: {}", tls.key_path, e))?
+        .ok_or_else(|| format!("invalid private key {}: no PEM private key found", tls.key_path))?;
+
+    let config = rustls::ServerConfig::builder()
+        .with_no_client_auth()
+        .with_single_cert(certs, key)
+        .map_err(|e| format!("certificate {} / key {} rejected: {}", tls.cert_path, tls.key_path, e))?;
+    Ok(TlsAcceptor::from(Arc::new(config)))
+}
+
+/// Serve every plant over Modbus/TCP Security on `addr` until shutdown.
+pub async fn run_tls_server(
+    addr: SocketAddr,
+    acceptor: TlsAcceptor,
+    state: AppState,
+    register_maps: LiveRegisterMaps,
+    modbus: ModbusConfig,
+    shutdown: Shutdown,
+) -> Result<(), Box<dyn std::error::Error>> {
+    println!("Modbus TLS server listening on {}", addr);
+    let listener = TcpListener::bind(addr).await?;
+    serve_tls(listener, acceptor, state, register_maps, modbus, shutdown).await?;
     Ok(())
 }
+
+/// Server side of a TLS connection whose handshake runs on first use, i.e. in
+/// the task tokio-modbus spawns for the connection rather than in its accept
+/// loop, so a client stalling the handshake only delays itself.
+enum TlsHandshakeStream {
+    Handshaking(Pin<Box<dyn Future<Output = io::Result<TlsStream<tokio::net::TcpStream>>> + Send>>),
+    Ready(TlsStream<tokio::net::TcpStream>),
+}
+
+impl TlsHandshakeStream {
+    fn new(acceptor: &TlsAcceptor, socket: tokio::net::TcpStream, peer: SocketAddr) -> Self {
+        let accept = acceptor.accept(socket);
+        Self::Handshaking(Box::pin(async move {
+            match tokio::time::timeout(TLS_HANDSHAKE_TIMEOUT, accept).await {
+                Ok(Ok(stream)) => Ok(stream),
+                Ok(Err(e)) => {
+                    eprintln!("[MODBUS-TLS] Handshake with {} failed: {}", peer, e);
+                    Err(e)
+                }
+                Err(_) => {
+                    eprintln!("[MODBUS-TLS] Handshake with {} timed out", peer);
+                    Err(io::Error::new(io::ErrorKind::TimedOut, "TLS handshake timeout"))
+                }
+            }
+        }))
+    }
+
+    /// Drive the handshake to completion, then hand out the TLS stream.
+    fn poll_stream(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<&mut TlsStream<tokio::net::TcpStream>>> {
+        if let Self::Handshaking(handshake) = self {
+            match handshake.as_mut().poll(cx) {
+                Poll::Ready(Ok(stream)) => *self = Self::Ready(stream),
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        match self {
+            Self::Ready(stream) => Poll::Ready(Ok(stream)),
+            Self::Handshaking(_) => unreachable!("handshake completed above"),
+        }
+    }
+}
+
+impl AsyncRead for TlsHandshakeStream {
+    fn poll_read(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &mut ReadBuf<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut().poll_stream(cx) {
+            Poll::Ready(Ok(stream)) => Pin::new(stream).poll_read(cx, buf),
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl AsyncWrite for TlsHandshakeStream {
+    fn poll_write(self: Pin<&mut Self>, cx: &mut Context<'_>, buf: &[u8]) -> Poll<io::Result<usize>> {
+        match self.get_mut().poll_stream(cx) {
+            Poll::Ready(Ok(stream)) => Pin::new(stream).poll_write(cx, buf),
+            Poll::Ready(Err(e)) => Poll::Ready(Err(e)),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Ready(stream) => Pin::new(stream).poll_flush(cx),
+            Self::Handshaking(_) => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Ready(stream) => Pin::new(stream).poll_shutdown(cx),
+            Self::Handshaking(_) => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+/// As `serve_tcp`, with a TLS handshake before the first request. The accept
+/// loop only admits the client; the handshake runs in the connection's own
+/// task, so a failed or stalled handshake closes that connection alone and
+/// never holds up other clients connecting.
+async fn serve_tls(
+    listener: TcpListener,
+    acceptor: TlsAcceptor,
+    state: AppState,
+    register_maps: LiveRegisterMaps,
+    modbus: ModbusConfig,
+    shutdown: Shutdown,
+) -> io::Result<()> {
+    let server = tokio_modbus::server::tcp::Server::new(listener);
+    let idle_timeout = (modbus.idle_timeout_s > 0).then(|| Duration::from_secs(modbus.idle_timeout_s));
+
+    let (closing, connections) = (shutdown.clone(), state.clone());
+    let on_connected = move |socket: tokio::net::TcpStream, peer: SocketAddr| {
+        let accepted = if closing.is_triggered() { None } else {
+            admit_client(&state, &register_maps, &modbus, peer).map(|service| {
+                (service, IdleTimeoutStream::new(TlsHandshakeStream::new(&acceptor, socket, peer), idle_timeout, &closing))
+            })
+        };
+        async move { Ok::<_, io::Error>(accepted) }
+    };
+
+    let drained = async {
+        shutdown.wait().await;
+        drain_clients(&connections).await;
+    };
+    tokio::select! {
+        result = server.serve(&on_connected, |err| { eprintln!("Modbus TLS server error: {:?}", err); }) => return result,
+        _ = drained => {}
+    }
+    println!("[MODBUS-TLS] TLS listener closed");
+    Ok(())
+}
+
+/// Serve the same register maps as Modbus RTU on a serial line.
+///
+/// Only requests addressed to `serial.slave_id` are answered; that id is
+/// resolved against the unit maps exactly like a TCP unit identifier.
+pub async fn run_rtu_server(
+    serial: SerialConfig,
+    state: AppState,
+    register_maps: LiveRegisterMaps,
+    modbus: ModbusConfig,
+    shutdown: Shutdown,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let parity = match serial.parity {
+        SerialParity::None => tokio_serial::Parity::None,
+        SerialParity::Even => tokio_serial::Parity::Even,
+        SerialParity::Odd  => tokio_serial::Parity::Odd,
+    };
+    let stop_bits = match serial.stop_bits {
+        2 => tokio_serial::StopBits::Two,
+        _ => tokio_serial::StopBits::One,
+    };
+    let builder = tokio_serial::new(&serial.device, serial.baud_rate)
+        .data_bits(tokio_serial::DataBits::Eight)
+        .parity(parity)
+        .stop_bits(stop_bits);
+    let port = tokio_serial::SerialStream::open(&builder)
+        .map_err(|e| format!("cannot open serial device {}: {}", serial.device, e))?;
+
+    println!(
+        "Modbus RTU server on {} ({} baud, parity {:?}, {} stop bit(s), slave {})",
+        serial.device, serial.baud_rate, serial.parity, serial.stop_bits, serial.slave_id
+    );
+    let mut service = MbService::new(state, register_maps, &modbus);
+    service.rtu_slave = Some(serial.slave_id);
+    tokio::select! {
+        result = tokio_modbus::server::rtu::Server::new(port).serve_forever(service) => result?,
+        _ = shutdown.wait() => println!("[MODBUS-RTU] Serial server stopped"),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::power::{SimulationData, WeatherSource};
+    use crate::shared_state::PlantRating;
+    use crate::sunspec::{SUNSPEC_BASE, SUNSPEC_LEN};
+
+    fn plant(id: &str, base: u16) -> PlantConfig {
+        plant_with_mapping(id, serde_json::json!({ "base_address": base }))
+    }
+
+    fn plant_with_mapping(id: &str, mapping: serde_json::Value) -> PlantConfig {
+        serde_json::from_value(serde_json::json!({
+            "id": id, "name": id, "latitude": 45.0, "longitude": 7.0,
+            "nominal_power_kw": 1000.0, "timezone": "Europe/Rome",
+            "modbus_mapping": mapping
+        })).unwrap()
+    }
+
+    /// Reassemble a 32-bit value from two registers in transmission order.
+    fn words_to_u32(first: u16, second: u16, order: WordOrder) -> u32 {
+        let (high, low) = match order {
+            WordOrder::Abcd => (first, second),
+            WordOrder::Cdab => (second, first),
+            WordOrder::Badc => (first.swap_bytes(), second.swap_bytes()),
+            WordOrder::Dcba => (second.swap_bytes(), first.swap_bytes()),
+        };
+        ((high as u32) << 16) | low as u32
+    }
+
+    fn sample_data() -> PlantData {
+        PlantData {
+            power_kw: 812.5, dc_voltage_v: 731.2, mppt_current_a: 1102.0,
+            reactive_power_kvar: 41.0, power_factor: 0.987, rocof_hz_s: -0.02,
+            performance_ratio: 0.83, isolation_resistance_mohm: 27.4,
+            status: 5, fault_code: 301, alarm_flags: 0x0008,
+            daily_energy_kwh: 3120.0, monthly_energy_kwh: 51000.0, total_energy_kwh: 1.25e6,
+            ..PlantData::default()
+        }
+    }
+
+    /// One update cycle of a 1000 kW plant_1 under 900 W/m².
+    fn update(state: &AppState) {
+        let point = crate::services::pv_string::StringLayout::sized_for(1000.0).operating_point(900.0, 35.0, 1.0);
+        let rating = PlantRating {
+            nominal_power_kw: 1000.0, max_kva: 1000.0, max_ac_kw: 1000.0, timezone: chrono_tz::Tz::UTC,
+            grid_voltage_v: 230.0, phase_imbalance: crate::config::PhaseImbalanceConfig::default(),
+        };
+        state.set_data("plant_1", &rating, &SimulationData {
+            timestamp: chrono::Utc::now(), power_kw: point.power_kw(), temperature_c: 35.0, ambient_temp_c: 25.0,
+            weather_code: 0, is_day: true, poa_irradiance_w_m2: 900.0, ghi_w_m2: 900.0, dni_w_m2: 0.0, dhi_w_m2: 900.0,
+            rear_irradiance_w_m2: 0.0, cloud_factor: 1.0, solar_elevation_deg: 50.0, solar_azimuth_deg: 180.0,
+            source: WeatherSource::Model, wind_speed_m_s: 3.0, relative_humidity_pct: 50.0, soiling_factor: 1.0,
+            rain_mm_h: 0.0, snow_cover_factor: 0.0, degradation_factor: 1.0, tracker_stowed: false, mppt: vec![point],
+        }, 0.0);
+    }
+
+    fn state_with(plant_id: &str, data: PlantData) -> AppState {
+        let state = AppState::new(true);
+        state.plant_data.insert(plant_id, data);
+        state
+    }
+
+    #[test]
+    fn every_layout_offset_serves_live_value() {
+        let data = sample_data();
+        let state = state_with("plant_2", data.clone());
+        let maps = build_register_map(&[plant("plant_1", 0), plant("plant_2", 200)]);
+        let map = &maps.shared.registers;
+
+        for def in REGISTER_LAYOUT {
+            let regs = read_registers(&state, map, RegisterTable::Holding, WordOrder::Abcd, 200 + def.offset, def.data_type.len()).unwrap();
+            let expected = def.var.value(&data);
+            match def.data_type {
+                DataType::F32 => {
+                    let got = f32::from_bits(((regs[0] as u32) << 16) | regs[1] as u32);
+                    assert_eq!(got, expected as f32, "{:?} at offset {}", def.var, def.offset);
+                }
+                DataType::U16 => assert_eq!(regs[0], expected as u16, "{:?} at offset {}", def.var, def.offset),
+                DataType::I16 => assert_eq!(regs[0] as i16, expected as i16, "{:?} at offset {}", def.var, def.offset),
+                DataType::U32 | DataType::U64 => {
+                    let got = regs.iter().fold(0u64, |acc, &w| (acc << 16) | w as u64);
+                    assert_eq!(got, expected.round() as u64, "{:?} at offset {}", def.var, def.offset);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn reads_may_start_mid_float_and_span_variables() {
+        let state = state_with("plant_1", sample_data());
+        let maps = build_register_map(&[plant("plant_1", 0)]);
+        let map = &maps.shared.registers;
+
+        let all = read_registers(&state, map, RegisterTable::Holding, WordOrder::Abcd, 0, layout_span()).unwrap();
+        let tail = read_registers(&state, map, RegisterTable::Holding, WordOrder::Abcd, REG_POWER_KW + 1, 12).unwrap();
+        assert_eq!(&all[1..13], &tail[..]);
+        // Holes inside the block and unconfigured plants read as zero
+        assert_eq!(read_registers(&state, map, RegisterTable::Holding, WordOrder::Abcd, 79, 1).unwrap(), vec![0]);
+    }
+
+    #[test]
+    fn float_round_trips_through_every_word_order() {
+        let bits = 123.456_f32.to_bits();
+        let (first, second) = float_to_words(123.456, WordOrder::Abcd);
+        assert_eq!([first, second], [0x42F6, 0xE979]);
+
+        for order in [WordOrder::Abcd, WordOrder::Cdab, WordOrder::Badc, WordOrder::Dcba] {
+            let (first, second) = float_to_words(123.456, order);
+            assert_eq!(words_to_u32(first, second, order), bits, "{}", order.as_str());
+        }
+        assert_eq!(float_to_words(123.456, WordOrder::Cdab), (0xE979, 0x42F6));
+        assert_eq!(float_to_words(123.456, WordOrder::Badc), (0xF642, 0x79E9));
+        assert_eq!(float_to_words(123.456, WordOrder::Dcba), (0x79E9, 0xF642));
+    }
+
+    #[test]
+    fn energy_counters_beyond_70_gwh_are_exact() {
+        let data = PlantData {
+            daily_energy_kwh: 4_321.5, monthly_energy_kwh: 5_000_000.0, total_energy_kwh: 72_345_678.901,
+            ..PlantData::default()
+        };
+        let state = state_with("plant_1", data);
+        let maps = build_register_map(&[plant("plant_1", 0)]);
+        let map = &maps.shared.registers;
+
+        for order in [WordOrder::Abcd, WordOrder::Cdab, WordOrder::Badc, WordOrder::Dcba] {
+            let mut regs = read_registers(&state, map, RegisterTable::Input, order, REG_TOTAL_ENERGY_WH, 4).unwrap();
+            if matches!(order, WordOrder::Badc | WordOrder::Dcba) {
+                regs.iter_mut().for_each(|w| *w = w.swap_bytes());
+            }
+            if matches!(order, WordOrder::Cdab | WordOrder::Dcba) {
+                regs.reverse();
+            }
+            let wh = regs.iter().fold(0u64, |acc, &w| (acc << 16) | w as u64);
+            assert_eq!(wh, 72_345_678_901, "{}", order.as_str());
+
+            let daily = read_registers(&state, map, RegisterTable::Input, order, REG_DAILY_ENERGY_WH, 2).unwrap();
+            assert_eq!(words_to_u32(daily[0], daily[1], order), 4_321_500, "{}", order.as_str());
+        }
+
+        // 5 GWh in a month does not fit the u32 Wh register: it saturates, with a warning
+        let monthly = read_registers(&state, map, RegisterTable::Input, WordOrder::Abcd, REG_MONTHLY_ENERGY_WH, 2).unwrap();
+        assert_eq!(words_to_u32(monthly[0], monthly[1], WordOrder::Abcd), u32::MAX);
+        assert!(map[&REG_MONTHLY_ENERGY_WH].clamp_warned.load(Ordering::Relaxed));
+        assert!(!map[&REG_TOTAL_ENERGY_WH].clamp_warned.load(Ordering::Relaxed));
+    }
+
+    #[test]
+    fn reads_outside_plant_blocks_are_rejected() {
+        let maps = build_register_map(&[plant("plant_1", 0), plant("plant_2", 200)]);
+        let device = &maps.shared;
+        let span = layout_span();
+
+        assert!(device.covers(RegisterTable::Holding, 0, span));
+        assert!(device.covers(RegisterTable::Holding, 200 + REG_STATUS, 1));
+        // Holes inside a block are still part of it, wherever the layout leaves one
+        let mapped = |a: u16| REGISTER_LAYOUT.iter().any(|d| (d.offset..d.offset + d.data_type.len()).contains(&a));
+        let hole = (0..span).find(|&a| !mapped(a)).expect("the layout leaves a hole");
+        assert!(device.covers(RegisterTable::Holding, hole, 1));
+        assert!(device.covers(RegisterTable::Holding, hole - 1, 3));
+        // Entirely between blocks, or straddling a block edge
+        assert!(!device.covers(RegisterTable::Holding, span, 1));
+        assert!(!device.covers(RegisterTable::Holding, 150, 10));
+        assert!(!device.covers(RegisterTable::Holding, span - 1, 2));
+        assert!(!device.covers(RegisterTable::Holding, 200 + span - 2, 4));
+        assert!(!device.covers(RegisterTable::Holding, u16::MAX, 1));
+    }
+
+    #[test]
+    fn unit_ids_route_to_their_own_plant() {
+        let state = state_with("plant_2", PlantData { power_kw: 42.0, ..PlantData::default() });
+        let maps = build_register_map(&[
+            plant_with_mapping("plant_1", serde_json::json!({ "unit_id": 1 })),
+            plant_with_mapping("plant_2", serde_json::json!({ "unit_id": 2 })),
+        ]);
+
+        let unit2 = maps.for_unit(2).unwrap();
+        let regs = read_registers(&state, &unit2.registers, RegisterTable::Holding, WordOrder::Abcd, REG_POWER_KW, 2).unwrap();
+        assert_eq!(f32::from_bits(((regs[0] as u32) << 16) | regs[1] as u32), 42.0);
+        // Both plants share base 0 but unit 1 resolves to plant_1
+        assert_eq!(maps.for_unit(1).unwrap().registers.get(&0).unwrap().plant_id, "plant_1");
+        // No shared plants → unknown units are not served
+        assert!(maps.for_unit(3).is_none());
+    }
+
+    #[test]
+    fn sunspec_plants_answer_at_40000() {
+        let state = state_with("plant_2", sample_data());
+        let maps = build_register_map(&[
+            plant_with_mapping("plant_1", serde_json::json!({ "unit_id": 1 })),
+            plant_with_mapping("plant_2", serde_json::json!({ "unit_id": 2, "profile": "sunspec" })),
+        ]);
+
+        let unit2 = maps.for_unit(2).unwrap();
+        assert!(unit2.registers.is_empty());
+        assert!(unit2.covers(RegisterTable::Holding, SUNSPEC_BASE, SUNSPEC_LEN));
+        let marker = read_device(&state, unit2, RegisterTable::Input, WordOrder::Abcd, SUNSPEC_BASE, 4).unwrap();
+        assert_eq!(marker, vec![0x5375, 0x6e53, 1, 66]);
+        // The custom profile is unaffected on the other unit
+        assert!(!maps.for_unit(1).unwrap().covers(RegisterTable::Holding, SUNSPEC_BASE, 1));
+    }
+
+    #[test]
+    fn virtual_devices_answer_on_the_following_units() {
+        let state = state_with("plant_1", PlantData { ambient_temp_c: -4.5, wind_speed_m_s: 6.2, ..sample_data() });
+        let maps = LiveRegisterMaps::new(build_register_map(&[
+            plant_with_mapping("plant_1", serde_json::json!({ "unit_id": 1, "base_address": 0, "virtual_devices": true })),
+        ]));
+        let modbus: ModbusConfig = serde_json::from_value(serde_json::json!({ "port": 0 })).unwrap();
+        let service = MbService::new(state, maps, &modbus);
+        let read = |slave, addr, cnt| service.handle(SlaveRequest { slave, request: Request::ReadInputRegisters(addr, cnt) });
+
+        // Unit 1: inverter layout
+        let Ok(Response::ReadInputRegisters(power)) = read(1, REG_POWER_KW, 2) else { panic!("inverter not served") };
+        assert_eq!(f32::from_bits(words_to_u32(power[0], power[1], WordOrder::Abcd)), 812.5);
+        // Unit 2: weather station, ambient temperature and wind in 0.1 units
+        let Ok(Response::ReadInputRegisters(weather)) = read(2, 0, 3) else { panic!("weather station not served") };
+        assert_eq!((weather[0] as i16, weather[2]), (-45, 62));
+        // Unit 3: revenue meter, total power in W and lifetime energy in Wh
+        let Ok(Response::ReadInputRegisters(meter)) = read(3, 0, 16) else { panic!("meter not served") };
+        assert_eq!(words_to_u32(meter[0], meter[1], WordOrder::Abcd), 812_500);
+        assert_eq!(meter[12..16].iter().fold(0u64, |acc, &w| (acc << 16) | w as u64), 1_250_000_000);
+        // Reads past the compact tables, or on unit 4, are rejected
+        assert_eq!(read(2, 0, 6), Err(ExceptionCode::IllegalDataAddress));
+        assert_eq!(read(4, 0, 1), Err(ExceptionCode::GatewayTargetDevice));
+    }
+
+    #[test]
+    fn vendor_profiles_answer_on_their_own_unit() {
+        let state = state_with("plant_3", sample_data());
+        let maps = LiveRegisterMaps::new(build_register_map(&[
+            plant("plant_1", 0),
+            plant_with_mapping("plant_3", serde_json::json!({ "unit_id": 3, "profile": "sma" })),
+        ]));
+        let modbus: ModbusConfig = serde_json::from_value(serde_json::json!({ "port": 0 })).unwrap();
+        let service = MbService::new(state, maps, &modbus);
+
+        let read = |slave, addr, cnt| service.handle(SlaveRequest { slave, request: Request::ReadHoldingRegisters(addr, cnt) });
+        let Ok(Response::ReadHoldingRegisters(power)) = read(3, 30775, 2) else { panic!("SMA power not served") };
+        assert_eq!(words_to_u32(power[0], power[1], WordOrder::Abcd), 812_500);
+        assert_eq!(read(3, 30775, 300), Err(ExceptionCode::IllegalDataAddress));
+        assert_eq!(read(1, 30775, 2), Err(ExceptionCode::IllegalDataAddress));
+    }
+
+    #[test]
+    fn service_counts_requests_and_exceptions() {
+        let state = state_with("plant_1", sample_data());
+        let maps = LiveRegisterMaps::new(build_register_map(&[plant("plant_1", 0)]));
+        let modbus: ModbusConfig = serde_json::from_value(serde_json::json!({ "port": 0 })).unwrap();
+        let service = MbService::new(state.clone(), maps, &modbus);
+
+        let read = |addr, cnt| SlaveRequest { slave: 1, request: Request::ReadInputRegisters(addr, cnt) };
+        drop(service.call(read(REG_POWER_KW, 4)));
+        drop(service.call(read(5000, 1)));
+        drop(service.call(SlaveRequest { slave: 1, request: Request::ReadCoils(COIL_INVERTER_ENABLE, 1) }));
+
+        let metrics = &state.modbus_metrics;
+        assert_eq!(metrics.requests(), vec![("read_coils", 1), ("read_input", 2)]);
+        assert_eq!(metrics.exceptions(), vec![("IllegalDataAddress".to_string(), 1)]);
+        assert_eq!(metrics.bytes_served(), 4 * 2 + 1);
+    }
+
+    async fn start_tcp_server(modbus: serde_json::Value) -> (SocketAddr, AppState) {
+        let (addr, state, _) = start_stoppable_tcp_server(modbus).await;
+        (addr, state)
+    }
+
+    /// `start_tcp_server` with the shutdown signal that stops it.
+    async fn start_stoppable_tcp_server(modbus: serde_json::Value) -> (SocketAddr, AppState, Shutdown) {
+        let state = state_with("plant_1", sample_data());
+        let maps = LiveRegisterMaps::new(build_register_map(&[plant("plant_1", 0)]));
+        let modbus: ModbusConfig = serde_json::from_value(modbus).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let shutdown = Shutdown::new();
+        tokio::spawn(serve_tcp(listener, state.clone(), maps, modbus, shutdown.clone()));
+        (addr, state, shutdown)
+    }
+
+    /// Send "read input register 0" and return the number of response bytes (0 = closed).
+    async fn poll_once(stream: &mut tokio::net::TcpStream) -> usize {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let request = [0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, 0x04, 0x00, 0x00, 0x00, 0x01];
+        if stream.write_all(&request).await.is_err() {
+            return 0;
+        }
+        let mut response = [0u8; 11];
+        match stream.read_exact(&mut response).await {
+            Ok(n) => n,
+            Err(_) => 0,
+        }
+    }
+
+    #[tokio::test]
+    async fn connections_beyond_limit_are_refused() {
+        let (addr, state) = start_tcp_server(serde_json::json!({ "port": 0, "max_connections": 2 })).await;
+
+        let mut first  = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let mut second = tokio::net::TcpStream::connect(addr).await.unwrap();
+        assert_eq!(poll_once(&mut first).await, 11);
+        assert_eq!(poll_once(&mut second).await, 11);
+        assert_eq!(state.modbus_metrics.connected_clients(), 2);
+
+        let mut third = tokio::net::TcpStream::connect(addr).await.unwrap();
+        assert_eq!(poll_once(&mut third).await, 0);
+        assert_eq!(state.modbus_metrics.rejected_connections(), 1);
+
+        // Closing a client frees its slot, as soon as the server has seen it go
+        drop(first);
+        let freed = async {
+            while state.modbus_metrics.connected_clients() > 1 {
+                tokio::task::yield_now().await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), freed).await.expect("closed client released");
+        let mut fourth = tokio::net::TcpStream::connect(addr).await.unwrap();
+        assert_eq!(poll_once(&mut fourth).await, 11);
+    }
+
+    #[tokio::test]
+    async fn shutdown_closes_polling_clients_without_waiting_for_them() {
+        use tokio::io::AsyncReadExt;
+        let (addr, state, shutdown) = start_stoppable_tcp_server(serde_json::json!({ "port": 0 })).await;
+        let mut scada = tokio::net::TcpStream::connect(addr).await.unwrap();
+        assert_eq!(poll_once(&mut scada).await, 11);
+
+        // A master that keeps polling is closed by the server, not waited out
+        shutdown.trigger();
+        let mut rest = Vec::new();
+        let closed = tokio::time::timeout(Duration::from_secs(2), scada.read_to_end(&mut rest)).await;
+        assert_eq!(closed.expect("connection closed").unwrap(), 0);
+        tokio::time::timeout(Duration::from_secs(2), state.modbus_metrics.all_disconnected()).await.expect("drained");
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn idle_clients_are_dropped() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        let (mut client, server) = tokio::io::duplex(64);
+        let mut stream = IdleTimeoutStream::new(server, Some(Duration::from_secs(1)), &Shutdown::new());
+        let mut buf = [0u8; 12];
+
+        // Each request within the timeout starts it over
+        for _ in 0..2 {
+            tokio::time::advance(Duration::from_millis(900)).await;
+            client.write_all(b"request").await.unwrap();
+            assert_eq!(stream.read(&mut buf).await.unwrap(), 7);
+        }
+
+        // One second of silence drops the client
+        let started = Instant::now();
+        let err = stream.read(&mut buf).await.unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::TimedOut);
+        assert_eq!(started.elapsed(), Duration::from_secs(1));
+    }
+
+    /// Serve plant_1 over TLS with a fresh self-signed certificate in `dir`;
+    /// returns the address and a connector trusting that certificate.
+    async fn start_tls_server(dir: &std::path::Path) -> (SocketAddr, tokio_rustls::TlsConnector, ModbusTlsConfig) {
+        let rcgen::CertifiedKey { cert, key_pair } = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+        std::fs::create_dir_all(dir).unwrap();
+        let tls: ModbusTlsConfig = serde_json::from_value(serde_json::json!({
+            "cert_path": dir.join("cert.pem"), "key_path": dir.join("key.pem")
+        })).unwrap();
+        std::fs::write(&tls.cert_path, cert.pem()).unwrap();
+        std::fs::write(&tls.key_path, key_pair.serialize_pem()).unwrap();
+        let acceptor = load_tls_acceptor(&tls).unwrap();
+
+        let state = state_with("plant_1", sample_data());
+        let maps = LiveRegisterMaps::new(build_register_map(&[plant("plant_1", 0)]));
+        let modbus: ModbusConfig = serde_json::from_value(serde_json::json!({ "port": 0 })).unwrap();
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(serve_tls(listener, acceptor, state, maps, modbus, Shutdown::new()));
+
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(cert.der().clone()).unwrap();
+        let client_config = rustls::ClientConfig::builder().with_root_certificates(roots).with_no_client_auth();
+        (addr, tokio_rustls::TlsConnector::from(Arc::new(client_config)), tls)
+    }
+
+    /// Complete a TLS handshake with `addr` and read the active power of unit 1.
+    async fn read_power_over_tls(addr: SocketAddr, connector: &tokio_rustls::TlsConnector) -> f32 {
+        use tokio_modbus::client::Reader;
+        let socket = tokio::net::TcpStream::connect(addr).await.unwrap();
+        let server_name = rustls::pki_types::ServerName::try_from("localhost").unwrap();
+        let stream = connector.connect(server_name, socket).await.unwrap();
+
+        let mut client = tokio_modbus::client::tcp::attach_slave(stream, Slave(1));
+        let power = client.read_input_registers(REG_POWER_KW, 2).await.unwrap().unwrap();
+        f32::from_bits(words_to_u32(power[0], power[1], WordOrder::Abcd))
+    }
+
+    #[tokio::test]
+    async fn input_registers_round_trip_over_tls() {
+        let dir = std::env::temp_dir().join(format!("solar-sim-tls-{}", std::process::id()));
+        let (addr, connector, tls) = start_tls_server(&dir).await;
+        assert_eq!(read_power_over_tls(addr, &connector).await, 812.5);
+
+        // A certificate that is not PEM fails loudly with its path
+        std::fs::write(&tls.cert_path, "not a certificate").unwrap();
+        let err = load_tls_acceptor(&tls).err().unwrap();
+        assert!(err.contains("cert.pem"), "{}", err);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn a_stalled_tls_handshake_does_not_hold_up_other_clients() {
+        let dir = std::env::temp_dir().join(format!("solar-sim-tls-stall-{}", std::process::id()));
+        let (addr, connector, _) = start_tls_server(&dir).await;
+
+        // Connects but never sends a ClientHello
+        let _stalled = tokio::net::TcpStream::connect(addr).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(50)).await;
+
+        let power = tokio::time::timeout(Duration::from_secs(2), read_power_over_tls(addr, &connector))
+            .await
+            .expect("second client served while the first one stalls");
+        assert_eq!(power, 812.5);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn read_write_multiple_registers_writes_before_reading() {
+        use tokio_modbus::client::Reader;
+        let (addr, state) = start_tcp_server(serde_json::json!({ "port": 0 })).await;
+        let mut client = tokio_modbus::client::tcp::connect_slave(addr, Slave(1)).await.unwrap();
+
+        // Write power limit + Q setpoint, read back the whole parameter block
+        let regs = client
+            .read_write_multiple_registers(REG_POWER_LIMIT_PCT, 5, REG_POWER_LIMIT_PCT, &[40, (-25i16) as u16])
+            .await.unwrap().unwrap();
+        assert_eq!(regs[0], 40);
+        assert_eq!(regs[1] as i16, -25);
+        assert_eq!(state.get_data("plant_1").unwrap().power_limit_pct, 40.0);
+
+        // A write range touching read-only telemetry is rejected and nothing changes
+        let rejected = client
+            .read_write_multiple_registers(REG_POWER_LIMIT_PCT, 1, REG_WATCHDOG_AGE_S, &[0; 8])
+            .await.unwrap();
+        assert_eq!(rejected, Err(ExceptionCode::IllegalDataAddress));
+        assert_eq!(state.get_data("plant_1").unwrap().power_limit_pct, 40.0);
+    }
+
+    #[test]
+    fn mask_write_follows_spec_and_only_clears_alarm_bits() {
+        // Example from the Modbus application protocol spec, 6.16
+        assert_eq!(apply_mask(0x12, 0xF2, 0x25), 0x17);
+
+        let state = state_with("plant_1", PlantData { alarm_flags: 0x0019, ..sample_data() });
+        let maps = LiveRegisterMaps::new(build_register_map(&[plant("plant_1", 0)]));
+        let modbus: ModbusConfig = serde_json::from_value(serde_json::json!({ "port": 0 })).unwrap();
+        let service = MbService::new(state.clone(), maps, &modbus);
+        let mask = |addr, and_mask, or_mask| service.handle(SlaveRequest {
+            slave: 1, request: Request::MaskWriteRegister(addr, and_mask, or_mask),
+        });
+        let flags = || state.get_data("plant_1").unwrap().alarm_flags;
+
+        // AND clears bit 0; OR bits under a 1 in the AND mask have no effect
+        assert_eq!(mask(REG_ALARM_FLAGS, !0x0001, 0x0008), Ok(Response::MaskWriteRegister(REG_ALARM_FLAGS, !0x0001, 0x0008)));
+        assert_eq!(flags(), 0x0018);
+        // OR on a bit the AND mask clears would set it: rejected, nothing changes
+        assert_eq!(mask(REG_ALARM_FLAGS, !0x0020, 0x0020), Err(ExceptionCode::IllegalDataValue));
+        assert_eq!(mask(REG_ALARM_FLAGS, 0x0000, 0x0001), Err(ExceptionCode::IllegalDataValue));
+        assert_eq!(flags(), 0x0018);
+        // OR re-asserting a bit that is already set is a plain clear of the others
+        assert_eq!(mask(REG_ALARM_FLAGS, 0x0000, 0x0010).map(|_| flags()), Ok(0x0010));
+        // Only the alarm bitmask accepts mask writes
+        assert_eq!(mask(REG_FAULT_CODE, 0x0000, 0x0000), Err(ExceptionCode::IllegalDataAddress));
+        assert_eq!(mask(REG_POWER_LIMIT_PCT, 0xFFFF, 0x0000), Err(ExceptionCode::IllegalDataAddress));
+    }
+
+    #[test]
+    fn int16_temperatures_below_zero_are_twos_complement() {
+        let data = PlantData { temperature_c: -12.3, inverter_temp_c: -12.3, ambient_temp_c: -12.3, ..sample_data() };
+        let state = state_with("plant_1", data.clone());
+        state.plant_data.insert("plant_3", data);
+        let maps = build_register_map(&[
+            plant_with_mapping("plant_1", serde_json::json!({ "base_address": 0, "temperature_encoding": "int16" })),
+            plant_with_mapping("plant_3", serde_json::json!({ "unit_id": 3, "profile": "huawei" })),
+        ]);
+        let map = &maps.shared.registers;
+        let read = |addr, cnt| read_registers(&state, map, RegisterTable::Input, WordOrder::Abcd, addr, cnt).unwrap();
+
+        for reg in [REG_TEMPERATURE_C, REG_INVERTER_TEMP_C, REG_AMBIENT_TEMP_C] {
+            assert_eq!(read(reg, 2), vec![0xFF85, 0], "offset {}", reg);
+            assert_eq!(map[&reg].data_type.label(), "int16");
+        }
+        // Other float32 variables are unaffected
+        let power = read(REG_POWER_KW, 2);
+        assert_eq!(f32::from_bits(words_to_u32(power[0], power[1], WordOrder::Abcd)), 812.5);
+
+        // Integer vendor profiles encode temperatures signed as well
+        let huawei = maps.units[&3].profiles[0].read(&state, 32087, 1);
+        assert_eq!(huawei, vec![0xFF85]);
+    }
+
+    #[test]
+    fn scaled_registers_of_a_2_mw_plant_saturate_instead_of_wrapping() {
+        let mut plant = plant_with_mapping("plant_1", serde_json::json!({
+            "base_address": 0,
+            "temperature_encoding": "int16",
+            "scaling": {
+                "watchdog_age_s": 10.0, "daily_energy_wh": 0.001, "inverter_temp_c": 1000.0, "cos_phi_setpoint": 1.0
+            }
+        }));
+        plant.nominal_power_kw = 2000.0;
+        let mut sunspec = plant_with_mapping("plant_2", serde_json::json!({ "unit_id": 2, "profile": "sunspec" }));
+        sunspec.nominal_power_kw = 2000.0;
+        let data = PlantData {
+            power_kw: 1987.25, daily_energy_kwh: 14_250.0, watchdog_age_s: 7_000.0, inverter_temp_c: 45.0,
+            cos_phi_setpoint: 0.95,
+            ..PlantData::default()
+        };
+        let state = state_with("plant_1", data.clone());
+        state.plant_data.insert("plant_2", data);
+        let maps = build_register_map(&[plant, sunspec]);
+        let map = &maps.shared.registers;
+        let read = |table, addr, cnt| read_registers(&state, map, table, WordOrder::Abcd, addr, cnt).unwrap();
+
+        // 1 987 250 W would wrap an int16: SunSpec W carries it in hundreds of
+        // watts (W at 40084, W_SF at 40085)
+        let sunspec = read_device(&state, maps.for_unit(2).unwrap(), RegisterTable::Input, WordOrder::Abcd,
+            SUNSPEC_BASE + 84, 2).unwrap();
+        assert_eq!(sunspec, vec![19_873, 2]);
+        // 14 250 kWh in kWh units (× 1000 × 0.001)
+        let energy = read(RegisterTable::Input, REG_DAILY_ENERGY_WH, 2);
+        assert_eq!(words_to_u32(energy[0], energy[1], WordOrder::Abcd), 14_250);
+        // 70 000 does not fit in a u16: saturates instead of wrapping to 4 464
+        assert_eq!(read(RegisterTable::Input, REG_WATCHDOG_AGE_S, 1), vec![u16::MAX]);
+        // 45 000 does not fit in an int16: saturates instead of wrapping to -20 536
+        assert_eq!(read(RegisterTable::Input, REG_INVERTER_TEMP_C, 2), vec![i16::MAX as u16, 0]);
+        // Parameters keep their fixed scale
+        assert_eq!(read(RegisterTable::Holding, REG_COS_PHI_SETPOINT, 1), vec![950]);
+
+        let scale_of = |var| map.values().find(|e| e.def.var == var).unwrap().scale;
+        assert_eq!(scale_of(VariableType::WatchdogAgeS), 10.0);
+        assert_eq!(scale_of(VariableType::InverterTempC), 1000.0);
+        assert_eq!(scale_of(VariableType::CosPhiSetpoint), 1000.0);
+        assert_eq!(scale_of(VariableType::PowerKw), 1.0);
+    }
+
+    #[test]
+    fn power_limit_register_round_trips() {
+        let state = state_with("plant_1", sample_data());
+        let maps = build_register_map(&[plant("plant_1", 0)]);
+        let map = &maps.shared.registers;
+
+        write_registers(&state, map, REG_POWER_LIMIT_PCT, &[60]).unwrap();
+        assert_eq!(read_registers(&state, map, RegisterTable::Holding, WordOrder::Abcd, REG_POWER_LIMIT_PCT, 1).unwrap(), vec![60]);
+        assert_eq!(state.get_data("plant_1").unwrap().power_limit_pct, 60.0);
+
+        // Out-of-range values and read-only telemetry are rejected untouched
+        assert_eq!(write_registers(&state, map, REG_POWER_LIMIT_PCT, &[101]), Err(ExceptionCode::IllegalDataValue));
+        assert_eq!(write_registers(&state, map, REG_POWER_KW, &[1]), Err(ExceptionCode::IllegalDataAddress));
+        assert_eq!(read_registers(&state, map, RegisterTable::Holding, WordOrder::Abcd, REG_POWER_LIMIT_PCT, 1).unwrap(), vec![60]);
+    }
+
+    #[test]
+    fn parameter_block_is_holding_only_and_validated() {
+        let state = state_with("plant_1", sample_data());
+        let maps = build_register_map(&[plant("plant_1", 0)]);
+        let device = &maps.shared;
+        let map = &device.registers;
+        let holding = |addr, cnt| read_registers(&state, map, RegisterTable::Holding, WordOrder::Abcd, addr, cnt).unwrap();
+
+        // Input registers skip the parameter block; the per-MPPT telemetry and curtailed energy follow it
+        assert_eq!(table_windows(RegisterTable::Input), vec![0..REG_GRID_EXPORT_KW + 2, REG_MPPT1_VOLTAGE_V..REG_CURTAILED_ENERGY_WH + 2]);
+        assert_eq!(table_windows(RegisterTable::Holding), vec![0..layout_span()]);
+        assert!(device.covers(RegisterTable::Input, REG_POWER_KW, REG_GRID_EXPORT_KW + 2));
+        assert!(device.covers(RegisterTable::Input, REG_MPPT1_VOLTAGE_V, 14));
+        assert!(!device.covers(RegisterTable::Input, REG_POWER_LIMIT_PCT, 1));
+        assert!(!device.covers(RegisterTable::Input, REG_Q_MODE, 3));
+        assert!(device.covers(RegisterTable::Holding, REG_POWER_LIMIT_PCT, 6));
+        assert_eq!(read_registers(&state, map, RegisterTable::Input, WordOrder::Abcd, REG_POWER_LIMIT_PCT, 1).unwrap(), vec![0]);
+        assert_eq!(holding(REG_POWER_LIMIT_PCT, 6), vec![100, 0, 0, 1, 0, 0]);
+
+        write_registers(&state, map, REG_POWER_LIMIT_PCT, &[70, (-25i16) as u16, 950, 1, 1234, 1]).unwrap();
+        assert_eq!(holding(REG_POWER_LIMIT_PCT, 6), vec![70, (-25i16) as u16, 950, 1, 1234, 1]);
+        let data = state.get_data("plant_1").unwrap();
+        assert_eq!((data.reactive_setpoint_kvar, data.cos_phi_setpoint), (-25.0, 0.95));
+        assert_eq!(data.reactive_mode, ReactivePowerMode::CosPhi);
+
+        for (reg, bad) in [
+            (REG_Q_MODE, 4u16), (REG_COS_PHI_SETPOINT, 799), (REG_COS_PHI_SETPOINT, 1001), (REG_ENABLE, 2),
+        ] {
+            assert_eq!(write_registers(&state, map, reg, &[bad]), Err(ExceptionCode::IllegalDataValue), "{} = {}", reg, bad);
+        }
+        // A rejected multi-register write leaves every parameter untouched
+        assert_eq!(write_registers(&state, map, REG_POWER_LIMIT_PCT, &[50, 0, 2000]), Err(ExceptionCode::IllegalDataValue));
+        assert_eq!(holding(REG_POWER_LIMIT_PCT, 3), vec![70, (-25i16) as u16, 950]);
+
+        write_registers(&state, map, REG_ENABLE, &[0]).unwrap();
+        assert!(!state.get_data("plant_1").unwrap().inverter_enabled);
+    }
+
+    #[test]
+    fn stale_watchdog_raises_communication_loss_until_written() {
+        let state = state_with("plant_1", PlantData::default());
+        let maps = build_register_map(&[plant("plant_1", 0)]);
+        let map = &maps.shared.registers;
+        let has_comm_loss = |state: &AppState| state.get_active_alarms(Some("plant_1"))
+            .iter().any(|a| a.code == crate::models::power::alarm_codes::COMMUNICATION_LOSS);
+
+        // A zero timeout is stale from the start
+        state.configure_watchdog("plant_1", 0, Some(20.0));
+        update(&state);
+        let data = state.get_data("plant_1").unwrap();
+        assert_ne!(data.alarm_flags & crate::models::power::alarm_flag_bits::COMMUNICATION_LOSS, 0);
+        assert!(data.power_kw <= 200.0 + 1e-9);
+        assert!(has_comm_loss(&state));
+
+        write_registers(&state, map, REG_WATCHDOG, &[1]).unwrap();
+        assert!(!has_comm_loss(&state));
+        assert_eq!(read_registers(&state, map, RegisterTable::Input, WordOrder::Abcd, REG_WATCHDOG_AGE_S, 1).unwrap(), vec![0]);
+    }
+
+    #[test]
+    fn each_phase_serves_its_own_voltage_and_current() {
+        let state = state_with("plant_1", PlantData::default());
+        let maps = build_register_map(&[plant("plant_1", 0)]);
+        let read = |addr| {
+            let regs = read_registers(&state, &maps.shared.registers, RegisterTable::Input, WordOrder::Abcd, addr, 2).unwrap();
+            f32::from_bits(words_to_u32(regs[0], regs[1], WordOrder::Abcd))
+        };
+        for _ in 0..50 {
+            update(&state);
+        }
+        let volts = [REG_VOLTAGE_L1_V, REG_VOLTAGE_L2_V, REG_VOLTAGE_L3_V].map(read);
+        let amps  = [REG_CURRENT_L1_A, REG_CURRENT_L2_A, REG_CURRENT_L3_A].map(read);
+        assert!(volts[0] != volts[1] && volts[1] != volts[2] && volts[0] != volts[2], "{volts:?}");
+        assert!(amps[0] != amps[1] && amps[1] != amps[2] && amps[0] != amps[2] && amps[0] > 0.0, "{amps:?}");
+    }
+
+    #[test]
+    fn update_age_grows_while_telemetry_updates_are_paused() {
+        let state = state_with("plant_1", PlantData::default());
+        let maps = build_register_map(&[plant("plant_1", 0)]);
+        let read = |addr, cnt| read_registers(&state, &maps.shared.registers, RegisterTable::Input, WordOrder::Abcd, addr, cnt).unwrap();
+
+        // Never updated: both registers read 0
+        assert_eq!(read(REG_LAST_UPDATE_UNIX, 3), vec![0, 0, 0]);
+
+        update(&state);
+        let stamp = read(REG_LAST_UPDATE_UNIX, 2);
+        let updated_at = words_to_u32(stamp[0], stamp[1], WordOrder::Abcd) as u64;
+        assert_eq!(updated_at, state.get_data("plant_1").unwrap().last_update_unix);
+        assert!(read(REG_UPDATE_AGE_S, 1)[0] <= 1);
+
+        // No update for 30 s: the timestamp holds, the age counts them
+        state.plant_data.modify("plant_1", |d| d.updated_at = d.updated_at.map(|t| t - Duration::from_secs(30)));
+        let regs = read(REG_LAST_UPDATE_UNIX, 3);
+        assert_eq!(words_to_u32(regs[0], regs[1], WordOrder::Abcd) as u64, updated_at);
+        assert_eq!(regs[2], 30);
+
+        update(&state);
+        assert!(read(REG_UPDATE_AGE_S, 1)[0] <= 1);
+    }
+
+    #[test]
+    fn enable_coil_stops_and_restarts_inverter() {
+        let state = state_with("plant_1", sample_data());
+        let maps = build_register_map(&[plant("plant_1", 0), plant("plant_2", 200)]);
+        let coils = &maps.shared.coils;
+
+        assert_eq!(read_coils(&state, coils, COIL_INVERTER_ENABLE, 1).unwrap(), vec![true]);
+        write_coils(&state, coils, COIL_INVERTER_ENABLE, &[false]).unwrap();
+        let data = state.get_data("plant_1").unwrap();
+        assert!(!data.inverter_enabled);
+        assert_eq!((data.status, data.power_kw, data.dc_current_a), (0, 0.0, 0.0));
+        assert_eq!(read_coils(&state, coils, COIL_INVERTER_ENABLE, 1).unwrap(), vec![false]);
+
+        write_coils(&state, coils, COIL_INVERTER_ENABLE, &[true]).unwrap();
+        assert!(state.get_data("plant_1").unwrap().inverter_enabled);
+
+        // Only base+0 of each block is a coil
+        assert_eq!(read_coils(&state, coils, 1, 1), Err(ExceptionCode::IllegalDataAddress));
+        assert_eq!(read_coils(&state, coils, 0, 2), Err(ExceptionCode::IllegalDataAddress));
+        assert_eq!(write_coils(&state, coils, 5, &[true]), Err(ExceptionCode::IllegalDataAddress));
+    }
+
+    /// Write one raw MBAP frame and read back a response of `len` bytes.
+    async fn exchange(stream: &mut tokio::net::TcpStream, frame: &[u8], len: usize) -> Vec<u8> {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        stream.write_all(frame).await.unwrap();
+        let mut response = vec![0u8; len];
+        stream.read_exact(&mut response).await.unwrap();
+        response
+    }
+
+    #[tokio::test]
+    async fn diagnostics_loopback_and_counters_on_the_wire() {
+        let (addr, state) = start_tcp_server(serde_json::json!({ "port": 0 })).await;
+        let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+
+        // Return Query Data echoes the request frame unchanged
+        let loopback = [0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, 0x08, 0x00, 0x00, 0xA5, 0x37];
+        assert_eq!(exchange(&mut stream, &loopback, 12).await, loopback);
+
+        // Out-of-range read: one exception response
+        let bad_read = [0x00, 0x02, 0x00, 0x00, 0x00, 0x06, 0x01, 0x04, 0x13, 0x88, 0x00, 0x01];
+        assert_eq!(exchange(&mut stream, &bad_read, 9).await, [0x00, 0x02, 0x00, 0x00, 0x00, 0x03, 0x01, 0x84, 0x02]);
+
+        // Bus Message Count includes the counting request itself
+        let bus_count = [0x00, 0x03, 0x00, 0x00, 0x00, 0x06, 0x01, 0x08, 0x00, 0x0B, 0x00, 0x00];
+        assert_eq!(
+            exchange(&mut stream, &bus_count, 12).await,
+            [0x00, 0x03, 0x00, 0x00, 0x00, 0x06, 0x01, 0x08, 0x00, 0x0B, 0x00, 0x03]
+        );
+        let exception_count = [0x00, 0x04, 0x00, 0x00, 0x00, 0x06, 0x01, 0x08, 0x00, 0x0D, 0x00, 0x00];
+        assert_eq!(
+            exchange(&mut stream, &exception_count, 12).await,
+            [0x00, 0x04, 0x00, 0x00, 0x00, 0x06, 0x01, 0x08, 0x00, 0x0D, 0x00, 0x01]
+        );
+
+        // Clear Counters echoes the request and resets the counts, not the metrics
+        let clear = [0x00, 0x05, 0x00, 0x00, 0x00, 0x06, 0x01, 0x08, 0x00, 0x0A, 0x00, 0x00];
+        assert_eq!(exchange(&mut stream, &clear, 12).await, clear);
+        assert_eq!(state.modbus_metrics.bus_messages(), 0);
+        assert_eq!(state.modbus_metrics.exception_responses(), 0);
+        assert_eq!(state.modbus_metrics.requests(), vec![("diagnostics", 4), ("read_input", 1)]);
+
+        // Restart Communications is not supported
+        let restart = [0x00, 0x06, 0x00, 0x00, 0x00, 0x06, 0x01, 0x08, 0x00, 0x01, 0x00, 0x00];
+        assert_eq!(exchange(&mut stream, &restart, 9).await, [0x00, 0x06, 0x00, 0x00, 0x00, 0x03, 0x01, 0x88, 0x01]);
+    }
+
+    #[test]
+    fn server_message_count_skips_other_rtu_slaves() {
+        let state = state_with("plant_1", sample_data());
+        let maps = LiveRegisterMaps::new(build_register_map(&[plant("plant_1", 0)]));
+        let modbus: ModbusConfig = serde_json::from_value(serde_json::json!({ "port": 0 })).unwrap();
+        let mut service = MbService::new(state.clone(), maps, &modbus);
+        service.rtu_slave = Some(1);
+
+        let read = |slave| SlaveRequest { slave, request: Request::ReadInputRegisters(REG_POWER_KW, 2) };
+        drop(service.call(read(1)));
+        drop(service.call(read(7)));
+
+        let metrics = &state.modbus_metrics;
+        assert_eq!((metrics.bus_messages(), metrics.server_messages()), (2, 1));
+    }
+}
@@ -1,12 +1,19 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
 use serde::{Deserialize, Serialize};
 use utoipa::ToSchema;
 
+use crate::services::pv_string::DcOperatingPoint;
+
 // ─── Core plant status ───────────────────────────────────────────────────────
 
 #[derive(Debug, Serialize, ToSchema)]
 pub struct PlantStatusResponse {
     pub timestamp: DateTime<Utc>,
+    /// Today's sunrise and sunset in the plant's time zone (null in polar day/night)
+    pub sunrise: Option<DateTime<FixedOffset>>,
+    pub sunset: Option<DateTime<FixedOffset>>,
+    /// No update within the plant's staleness threshold: `data` is the last one received
+    pub stale: bool,
     pub data: PlantData,
 }
 
@@ -48,6 +55,9 @@ pub struct PlantData {
     pub mppt_voltage_v: f64,
     /// MPPT tracker operating current (A_mpp)
     pub mppt_current_a: f64,
+    /// Per-MPPT readings (MPPT 1 first); the aggregate fields above show MPPT 1
+    #[serde(default)]
+    pub mppt: Vec<MpptData>,
 
     // ── Thermal ──────────────────────────────────────────────────────────────
     /// Panel/cell temperature (°C)
@@ -60,19 +70,44 @@ pub struct PlantData {
     // ── Inverter metrics ─────────────────────────────────────────────────────
     /// Inverter AC conversion efficiency (%)
     pub efficiency_percent: f64,
-    /// Plane-of-Array irradiance (W/m²)
+    /// AC output held at the inverter rating (DC array oversized)
+    pub clipping: bool,
+    /// AC energy lost to clipping today (kWh)
+    pub clipped_energy_kwh: f64,
+    /// AC energy held back by the grid export limit today (kWh)
+    pub curtailed_energy_kwh: f64,
+    /// Site consumption behind the grid meter (kW)
+    pub site_load_kw: f64,
+    /// Power flowing into the grid at the meter (kW, negative = import)
+    pub grid_export_kw: f64,
+    /// Plane-of-Array irradiance as read by the irradiance sensor (W/m²)
     pub poa_irradiance_w_m2: f64,
+    /// Plane-of-Array irradiance before sensor noise, drift and dropouts (W/m²)
+    pub poa_irradiance_true_w_m2: f64,
+    /// Global horizontal irradiance (W/m²)
+    pub ghi_w_m2: f64,
+    /// Direct normal irradiance (W/m²)
+    pub dni_w_m2: f64,
+    /// Diffuse horizontal irradiance (W/m²)
+    pub dhi_w_m2: f64,
+    /// Rear-side irradiance of bifacial modules (W/m²); 0 for monofacial
+    pub rear_irradiance_w_m2: f64,
     /// Solar elevation angle (deg)
     pub solar_elevation_deg: f64,
+    /// Solar azimuth (deg from North, clockwise)
+    #[serde(default)]
+    pub solar_azimuth_deg: f64,
     /// Cloud attenuation factor [0..1]
     pub cloud_factor: f64,
+    /// An irradiance override scenario (e.g. an eclipse) is dimming the light
+    pub scenario_active: bool,
 
     // ── Safety / Grid protection ─────────────────────────────────────────────
     /// Isolation resistance DC-ground (MΩ) — IEC 62109: must be >1 MΩ
     pub isolation_resistance_mohm: f64,
-    /// Status: 0=Stopped, 1=Running, 2=Fault, 3=Curtailed, 4=Starting, 5=MPPT
+    /// Status: 0=Stopped, 1=Running, 2=Fault, 3=Curtailed, 4=Starting, 5=MPPT search
     pub status: u16,
-    /// Active IEC/VDE fault code (0 = no fault)
+    /// Code of the most severe active alarm (0 = no fault)
     pub fault_code: u16,
     /// Bitmask of active alarm flags
     pub alarm_flags: u32,
@@ -80,6 +115,13 @@ pub struct PlantData {
     // ── Weather ───────────────────────────────────────────────────────────────
     pub weather_code: u16,
     pub is_day: bool,
+    /// Where the weather readings come from: Open-Meteo, or the model when
+    /// offline or when Open-Meteo could not be reached
+    #[serde(default)]
+    pub weather_source: WeatherSource,
+    /// Unix time (s) the weather readings were observed (0 = never)
+    #[serde(default)]
+    pub weather_observed_unix: u64,
 
     // ── Energy counters ───────────────────────────────────────────────────────
     /// Energy produced today (kWh)
@@ -90,11 +132,18 @@ pub struct PlantData {
     pub total_energy_kwh: f64,
 
     // ── Performance KPIs ──────────────────────────────────────────────────────
-    /// Performance Ratio = AC yield / theoretical yield (IEC 61724)
+    /// Performance Ratio of the day so far (IEC 61724) = AC energy /
+    /// (nominal kW × POA insolation / 1 kW/m²)
     pub performance_ratio: f64,
+    /// Plane-of-array insolation received today (kWh/m²)
+    pub daily_insolation_kwh_m2: f64,
+    /// Share of nameplate capacity left after module ageing [0..1]
+    pub degradation_factor: f64,
+    /// Capacity lost to injected storm damage (%) until an operator repair — 0 = intact
+    pub damage_pct: f64,
     /// Specific yield = daily kWh / kWp
     pub specific_yield_kwh_kwp: f64,
-    /// Capacity factor (%)
+    /// Capacity factor of the day so far (%) = daily kWh / (kWp × hours since local midnight)
     pub capacity_factor_percent: f64,
 
     // ── Environmental conditions ──────────────────────────────────────────────
@@ -102,8 +151,12 @@ pub struct PlantData {
     pub wind_speed_m_s: f64,
     /// Relative humidity at surface (%)
     pub relative_humidity_pct: f64,
-    /// Panel soiling factor [0.85..1.0] — 1.0 = clean
+    /// Live panel soiling factor [0.7..1.0] — 1.0 = clean; rain and washes reset it
     pub soiling_factor: f64,
+    /// Share of the array buried under snow [0..1] — 0.0 = no snow
+    pub snow_cover_factor: f64,
+    /// Tracker parked flat because of high wind (always false for fixed mounting)
+    pub tracker_stowed: bool,
 
     // ── Multi-string MPPT (dual-tracker typical residential/commercial inverter) ─
     /// MPPT string 1 voltage (V)
@@ -133,13 +186,87 @@ pub struct PlantData {
     /// Inverter cooling fan speed (0 = off, 1500–3600 RPM in operation)
     pub inverter_fan_speed_rpm: u16,
 
+    // ── Remote control ────────────────────────────────────────────────────────
+    /// Active power limit commanded over Modbus or REST (% of nominal, 100 = unlimited)
+    pub power_limit_pct: f64,
+    /// Inverter enable coil: false = remotely stopped
+    pub inverter_enabled: bool,
+    /// Reactive power control mode commanded by the grid operator
+    pub reactive_mode: ReactivePowerMode,
+    /// cos φ setpoint used in `cos_phi` mode (0 = 1.0)
+    pub cos_phi_setpoint: f64,
+    /// Reactive power setpoint used in `fixed_q` mode (kvar, + = over-excited)
+    pub reactive_setpoint_kvar: f64,
+    /// Last value written to the SCADA watchdog register
+    pub watchdog_value: u16,
+    /// Seconds since the SCADA watchdog was last written (0 when not supervised)
+    pub watchdog_age_s: f64,
+
+    // ── Data freshness ────────────────────────────────────────────────────────
+    /// Unix time (s) of the last successful telemetry update on the simulation clock (0 = never)
+    pub last_update_unix: u64,
+    /// Seconds between two updates of this plant; an age well past it means the plant is stale
+    pub update_interval_s: f64,
+    /// No update within the staleness threshold (COMMUNICATION_LOSS raised) until the next one
+    pub stale: bool,
+
+    // ── Maintenance ───────────────────────────────────────────────────────────
+    /// Plant taken out of service: Stopped, no output and no alarms until re-enabled
+    pub maintenance: bool,
+
     // ── Internal simulation state (not serialised to API clients) ─────────────
-    /// Ramp factor for sunrise startup / sunset shutdown [0.0..1.0]
+    /// Share of the available DC power drawn while ramping [0.0..1.0]
     #[serde(skip)]
     pub ramp_factor: f64,
-    /// Day-of-year of the last midnight daily-energy reset
+    /// DC power drawn last cycle, the base of the output ramp (kW)
+    #[serde(skip)]
+    pub ramped_dc_kw: f64,
+    /// Inverter connected to the grid
+    #[serde(skip)]
+    pub grid_connected: bool,
+    /// Update cycles spent in the Starting state so far
+    #[serde(skip)]
+    pub start_cycles: u32,
+    /// MPPT search cycles left after connecting
+    #[serde(skip)]
+    pub search_cycles: u32,
+    /// Connected at some point since the last nightfall (startup event logged)
+    #[serde(skip)]
+    pub online_today: bool,
+    /// AC output when the frequency crossed the P(f) threshold, while the droop is active (kW)
+    #[serde(skip)]
+    pub freq_watt_ref_kw: Option<f64>,
+    /// Limit (% of nominal) of the curtailment schedule window the plant is in
+    #[serde(skip)]
+    pub scheduled_limit_pct: Option<f64>,
+    /// Consecutive update cycles each protection function (V<, V>, f<, f>, RoCoF) has seen its limit violated
+    #[serde(skip)]
+    pub protection_cycles: [u32; 5],
+    /// Alarm code of the protection function that tripped the inverter, until it reconnects
+    #[serde(skip)]
+    pub protection_trip: Option<u16>,
+    /// Update cycles the grid has stayed within limits since the trip
+    #[serde(skip)]
+    pub reconnect_cycles: u32,
+    /// Update cycles the sensor readings stay frozen by a logger dropout
+    #[serde(skip)]
+    pub dropout_cycles: u32,
+    /// Isolation fault latched by the Riso check, until the array dries out
+    #[serde(skip)]
+    pub isolation_fault: bool,
+    /// Plant-local date the daily energy counters belong to
+    #[serde(skip)]
+    pub energy_day: Option<NaiveDate>,
+    /// Time of the previous update, the start of the next energy integral
     #[serde(skip)]
-    pub last_day_reset: u32,
+    pub last_update_at: Option<DateTime<Utc>>,
+    /// Wall-clock instant of the previous update, for its age
+    #[serde(skip)]
+    pub updated_at: Option<std::time::Instant>,
+    /// The simulation clock jumped since the last update: the energy integral
+    /// restarts and the model rebuilds the soiling for the new date
+    #[serde(skip)]
+    pub clock_jumped: bool,
     /// Whether a fan-fault event is currently injected
     #[serde(skip)]
     pub fan_fault_active: bool,
@@ -165,28 +292,48 @@ impl Default for PlantData {
             dc_power_kw: 0.0,
             mppt_voltage_v: 600.0,
             mppt_current_a: 0.0,
+            mppt: Vec::new(),
             temperature_c: 25.0,
             inverter_temp_c: 35.0,
             ambient_temp_c: 20.0,
             efficiency_percent: 0.0,
+            clipping: false,
+            clipped_energy_kwh: 0.0,
+            curtailed_energy_kwh: 0.0,
+            site_load_kw: 0.0,
+            grid_export_kw: 0.0,
             poa_irradiance_w_m2: 0.0,
+            poa_irradiance_true_w_m2: 0.0,
+            ghi_w_m2: 0.0,
+            dni_w_m2: 0.0,
+            dhi_w_m2: 0.0,
+            rear_irradiance_w_m2: 0.0,
             solar_elevation_deg: 0.0,
+            solar_azimuth_deg: 0.0,
             cloud_factor: 1.0,
+            scenario_active: false,
             isolation_resistance_mohm: 10.0,
             status: 0,
             fault_code: 0,
             alarm_flags: 0,
             weather_code: 0,
             is_day: false,
+            weather_source: WeatherSource::Model,
+            weather_observed_unix: 0,
             daily_energy_kwh: 0.0,
             monthly_energy_kwh: 0.0,
             total_energy_kwh: 0.0,
             performance_ratio: 0.0,
+            daily_insolation_kwh_m2: 0.0,
+            degradation_factor: 1.0,
+            damage_pct: 0.0,
             specific_yield_kwh_kwp: 0.0,
             capacity_factor_percent: 0.0,
             wind_speed_m_s: 3.0,
             relative_humidity_pct: 60.0,
             soiling_factor: 1.0,
+            snow_cover_factor: 0.0,
+            tracker_stowed: false,
             string1_voltage_v: 600.0,
             string1_current_a: 0.0,
             string2_voltage_v: 600.0,
@@ -197,16 +344,106 @@ impl Default for PlantData {
             daily_peak_power_kw: 0.0,
             co2_avoided_kg: 0.0,
             inverter_fan_speed_rpm: 0,
+            power_limit_pct: 100.0,
+            inverter_enabled: true,
+            reactive_mode: ReactivePowerMode::FixedPf,
+            cos_phi_setpoint: 0.0,
+            reactive_setpoint_kvar: 0.0,
+            watchdog_value: 0,
+            watchdog_age_s: 0.0,
+            last_update_unix: 0,
+            update_interval_s: 5.0,
+            stale: false,
+            maintenance: false,
             ramp_factor: 0.0,
-            last_day_reset: 0,
+            ramped_dc_kw: 0.0,
+            grid_connected: false,
+            start_cycles: 0,
+            search_cycles: 0,
+            online_today: false,
+            freq_watt_ref_kw: None,
+            scheduled_limit_pct: None,
+            protection_cycles: [0; 5],
+            protection_trip: None,
+            reconnect_cycles: 0,
+            dropout_cycles: 0,
+            isolation_fault: false,
+            energy_day: None,
+            last_update_at: None,
+            updated_at: None,
+            clock_jumped: false,
             fan_fault_active: false,
         }
     }
 }
 
+/// Name of an inverter `status` value, as published on MQTT.
+pub const fn status_label(status: u16) -> &'static str {
+    match status {
+        1 => "RUNNING", 2 => "FAULT", 3 => "CURTAILED",
+        4 => "STARTING", 5 => "MPPT", _ => "STOPPED",
+    }
+}
+
+impl PlantData {
+    /// Wall-clock seconds elapsed since the last telemetry update (0 if
+    /// never updated), whatever the simulation clock does.
+    pub fn update_age_s(&self) -> f64 {
+        self.updated_at.map_or(0.0, |t| t.elapsed().as_secs() as f64)
+    }
+}
+
+/// One MPPT input of the inverter.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct MpptData {
+    /// Operating voltage (V_mpp)
+    pub voltage_v: f64,
+    /// Operating current (A)
+    pub current_a: f64,
+    /// DC power into the inverter (kW)
+    pub power_kw: f64,
+}
+
+/// Reactive power control mode (Modbus register value in brackets).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReactivePowerMode {
+    /// [0] Unity power factor, no reactive power
+    #[default]
+    FixedPf,
+    /// [1] Fixed cos φ (`cos_phi_setpoint`)
+    CosPhi,
+    /// [2] Fixed reactive power (`reactive_setpoint_kvar`)
+    FixedQ,
+    /// [3] Q(U): reactive power from the grid voltage along the plant's curve
+    VoltVar,
+}
+
+impl ReactivePowerMode {
+    pub const fn from_register(value: u16) -> Option<Self> {
+        match value {
+            0 => Some(Self::FixedPf),
+            1 => Some(Self::CosPhi),
+            2 => Some(Self::FixedQ),
+            3 => Some(Self::VoltVar),
+            _ => None,
+        }
+    }
+
+    pub const fn register_value(self) -> u16 {
+        match self {
+            Self::FixedPf => 0,
+            Self::CosPhi  => 1,
+            Self::FixedQ  => 2,
+            Self::VoltVar => 3,
+        }
+    }
+}
+
 // ─── Alarm / Event system ────────────────────────────────────────────────────
 
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq)]
+/// Declared from least to most severe; `fault_code` reports the most severe active alarm.
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq, PartialOrd, Ord)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum AlarmSeverity {
     Info,
@@ -225,22 +462,97 @@ pub struct Alarm {
     pub timestamp: DateTime<Utc>,
     pub active: bool,
     pub cleared_at: Option<DateTime<Utc>>,
+    /// Seen by an operator; the alarm stays active while its condition persists
+    #[serde(default)]
+    pub acknowledged: bool,
+    #[serde(default)]
+    pub acknowledged_by: Option<String>,
+    #[serde(default)]
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    /// Operator note left with the acknowledgement
+    #[serde(default)]
+    pub note: Option<String>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
 #[serde(rename_all = "SCREAMING_SNAKE_CASE")]
 pub enum EventKind {
+    /// The simulator process started, with the configuration it loaded
+    SimulatorStart,
+    /// First telemetry update of a plant since the simulator started
+    PlantOnline,
     PlantStartup,
     PlantShutdown,
     ModeChange,
     AlarmRaised,
     AlarmCleared,
+    AlarmAcknowledged,
     FaultTrip,
     GridDisconnect,
     GridReconnect,
     CurtailmentStart,
     CurtailmentEnd,
     SettingChanged,
+    PanelCleaning,
+    ScenarioStart,
+    ScenarioEnd,
+    ModuleDamage,
+    ModuleRepair,
+    EnergyRollover,
+    StatusChange,
+    MaintenanceStart,
+    MaintenanceEnd,
+    CommunicationLost,
+    CommunicationRestored,
+}
+
+/// Interface a remote control command arrived on, recorded with its event.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, ToSchema, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ControlInterface {
+    Modbus,
+    Rest,
+}
+
+impl ControlInterface {
+    pub const fn label(self) -> &'static str {
+        match self {
+            ControlInterface::Modbus => "Modbus",
+            ControlInterface::Rest   => "REST",
+        }
+    }
+}
+
+/// Remote control state of a plant after a REST control command.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PlantControlResponse {
+    pub plant_id: String,
+    /// start, stop, limit or reset_faults
+    pub action: String,
+    /// Status: 0=Stopped, 1=Running, 2=Fault, 3=Curtailed, 4=Starting, 5=MPPT search
+    pub status: u16,
+    /// Label of `status` (RUNNING, FAULT, …)
+    pub status_label: String,
+    /// Inverter enable coil: false = remotely stopped
+    pub inverter_enabled: bool,
+    /// Active power limit (% of nominal, 100 = unlimited)
+    pub power_limit_pct: f64,
+    /// Code of the most severe active alarm (0 = no fault)
+    pub fault_code: u16,
+    /// Latched alarm codes released by `reset_faults`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub reset_codes: Vec<u16>,
+}
+
+/// One alarm code the simulator can raise, as listed by `GET /api/faults/catalog`.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FaultCatalogEntry {
+    pub code: u16,
+    pub name: String,
+    /// Severity the simulator raises it with
+    pub severity: AlarmSeverity,
+    /// Bits it sets in `alarm_flags` (0 = none, `fault_code` only)
+    pub alarm_flags: u32,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
@@ -271,7 +583,41 @@ pub mod alarm_codes {
     pub const OVERTEMPERATURE: u16      = 401;
     pub const FAN_FAULT: u16            = 402;
     pub const COMMUNICATION_LOSS: u16   = 501;
+    pub const TRACKER_WIND_STOW: u16    = 601;
+    pub const SNOW_COVER: u16           = 602;
+    pub const MODULE_DAMAGE: u16        = 603;
     pub const INTERNAL_FAULT: u16       = 999;
+
+    use super::AlarmSeverity;
+
+    /// Every code the simulator raises, with its name and usual severity.
+    pub const CATALOG: [(u16, &str, AlarmSeverity); 18] = [
+        (AC_OVERVOLTAGE,       "AC_OVERVOLTAGE",       AlarmSeverity::Warning),
+        (AC_UNDERVOLTAGE,      "AC_UNDERVOLTAGE",      AlarmSeverity::Warning),
+        (AC_OVERFREQUENCY,     "AC_OVERFREQUENCY",     AlarmSeverity::Warning),
+        (AC_UNDERFREQUENCY,    "AC_UNDERFREQUENCY",    AlarmSeverity::Warning),
+        (ROCOF_TRIP,           "ROCOF_TRIP",           AlarmSeverity::Critical),
+        (GRID_ISLAND_DETECTED, "GRID_ISLAND_DETECTED", AlarmSeverity::Fault),
+        (DC_OVERVOLTAGE,       "DC_OVERVOLTAGE",       AlarmSeverity::Warning),
+        (DC_UNDERVOLTAGE,      "DC_UNDERVOLTAGE",      AlarmSeverity::Warning),
+        (MPPT_FAILURE,         "MPPT_FAILURE",         AlarmSeverity::Warning),
+        (ISOLATION_FAULT,      "ISOLATION_FAULT",      AlarmSeverity::Fault),
+        (GROUND_FAULT,         "GROUND_FAULT",         AlarmSeverity::Critical),
+        (OVERTEMPERATURE,      "OVERTEMPERATURE",      AlarmSeverity::Critical),
+        (FAN_FAULT,            "FAN_FAULT",            AlarmSeverity::Warning),
+        (COMMUNICATION_LOSS,   "COMMUNICATION_LOSS",   AlarmSeverity::Warning),
+        (TRACKER_WIND_STOW,    "TRACKER_WIND_STOW",    AlarmSeverity::Warning),
+        (SNOW_COVER,           "SNOW_COVER",           AlarmSeverity::Info),
+        (MODULE_DAMAGE,        "MODULE_DAMAGE",        AlarmSeverity::Critical),
+        (INTERNAL_FAULT,       "INTERNAL_FAULT",       AlarmSeverity::Fault),
+    ];
+
+    /// Name and usual severity of `code`; None for codes outside the catalog.
+    pub fn describe(code: u16) -> Option<(&'static str, AlarmSeverity)> {
+        CATALOG.iter()
+            .find(|(c, ..)| *c == code)
+            .map(|(_, name, severity)| (*name, severity.clone()))
+    }
 }
 
 pub mod alarm_flag_bits {
@@ -288,6 +634,39 @@ pub mod alarm_flag_bits {
     pub const GROUND_FAULT: u32        = 1 << 10;
     pub const DC_OVERVOLTAGE: u32      = 1 << 11;
     pub const LEAKAGE_CURRENT: u32     = 1 << 12;
+    pub const TRACKER_STOW: u32        = 1 << 13;
+    pub const SNOW_COVER: u32          = 1 << 14;
+    pub const MODULE_DAMAGE: u32       = 1 << 15;
+
+    use super::alarm_codes;
+
+    /// Alarm codes reported through the single bit `flag`.
+    pub const fn codes(flag: u32) -> &'static [u16] {
+        match flag {
+            AC_OVERVOLTAGE     => &[alarm_codes::AC_OVERVOLTAGE],
+            AC_UNDERVOLTAGE    => &[alarm_codes::AC_UNDERVOLTAGE],
+            FREQUENCY_FAULT    => &[alarm_codes::AC_OVERFREQUENCY, alarm_codes::AC_UNDERFREQUENCY],
+            ISOLATION_FAULT    => &[alarm_codes::ISOLATION_FAULT],
+            OVERTEMPERATURE    => &[alarm_codes::OVERTEMPERATURE],
+            MPPT_DEVIATION     => &[alarm_codes::MPPT_FAILURE],
+            GRID_DISCONNECT    => &[alarm_codes::GRID_ISLAND_DETECTED],
+            COMMUNICATION_LOSS => &[alarm_codes::COMMUNICATION_LOSS],
+            ROCOF_TRIP         => &[alarm_codes::ROCOF_TRIP],
+            FAN_FAULT          => &[alarm_codes::FAN_FAULT],
+            GROUND_FAULT       => &[alarm_codes::GROUND_FAULT],
+            DC_OVERVOLTAGE     => &[alarm_codes::DC_OVERVOLTAGE],
+            LEAKAGE_CURRENT    => &[alarm_codes::GROUND_FAULT],
+            TRACKER_STOW       => &[alarm_codes::TRACKER_WIND_STOW],
+            SNOW_COVER         => &[alarm_codes::SNOW_COVER],
+            MODULE_DAMAGE      => &[alarm_codes::MODULE_DAMAGE],
+            _                  => &[],
+        }
+    }
+
+    /// Flag bits that report alarm `code` (0 for codes without one).
+    pub fn of_code(code: u16) -> u32 {
+        (0..u32::BITS).map(|i| 1u32 << i).filter(|b| codes(*b).contains(&code)).fold(0, |flags, b| flags | b)
+    }
 }
 
 // ─── Open-Meteo wire types ────────────────────────────────────────────────────
@@ -304,6 +683,31 @@ pub struct CurrentData {
     pub temperature_2m: Option<f64>,
     pub weather_code: Option<u16>,
     pub is_day: Option<u8>,
+    pub rain: Option<f64>,
+    /// Requested in m/s (`wind_speed_unit=ms`)
+    pub wind_speed_10m: Option<f64>,
+    pub relative_humidity_2m: Option<f64>,
+    pub direct_normal_irradiance: Option<f64>,
+    pub diffuse_radiation: Option<f64>,
+}
+
+/// Open-Meteo `hourly` forecast: radiation averaged over the hour ending at
+/// each time, temperature and wind at it.
+#[derive(Debug, Deserialize)]
+pub struct HourlyForecastResponse {
+    pub hourly: HourlyData,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct HourlyData {
+    pub time: Vec<String>,
+    #[serde(default)]
+    pub shortwave_radiation: Vec<Option<f64>>,
+    #[serde(default)]
+    pub temperature_2m: Vec<Option<f64>>,
+    /// Requested in m/s (`wind_speed_unit=ms`)
+    #[serde(default)]
+    pub wind_speed_10m: Vec<Option<f64>>,
 }
 
 // ─── Internal simulation data ────────────────────────────────────────────────
@@ -317,14 +721,36 @@ pub struct SimulationData {
     pub weather_code: u16,
     pub is_day: bool,
     pub poa_irradiance_w_m2: f64,
+    /// Global horizontal irradiance (W/m²)
+    pub ghi_w_m2: f64,
+    /// Direct normal irradiance (W/m²)
+    pub dni_w_m2: f64,
+    /// Diffuse horizontal irradiance (W/m²)
+    pub dhi_w_m2: f64,
+    /// Rear-side irradiance of bifacial modules (W/m²)
+    pub rear_irradiance_w_m2: f64,
     pub cloud_factor: f64,
     pub solar_elevation_deg: f64,
+    /// Solar azimuth (deg from North, clockwise)
+    pub solar_azimuth_deg: f64,
+    /// Open-Meteo, or the model
+    pub source: WeatherSource,
     /// Wind speed at 10 m (m/s)
     pub wind_speed_m_s: f64,
     /// Relative humidity (%)
     pub relative_humidity_pct: f64,
-    /// Panel soiling factor [0.85..1.0]
+    /// Panel soiling factor [0.7..1.0]
     pub soiling_factor: f64,
+    /// Rain rate (mm/h) — washes the panels
+    pub rain_mm_h: f64,
+    /// Share of the array buried under snow [0..1]
+    pub snow_cover_factor: f64,
+    /// Share of nameplate capacity left after module ageing [0..1]
+    pub degradation_factor: f64,
+    /// Tracker parked flat because of high wind
+    pub tracker_stowed: bool,
+    /// String voltages and currents of each MPPT input behind `power_kw`
+    pub mppt: Vec<DcOperatingPoint>,
 }
 
 // ─── REST API response types ──────────────────────────────────────────────────
@@ -336,6 +762,320 @@ pub struct ModbusInfo {
     pub length: u16,
     pub data_type: String,
     pub description: String,
+    pub unit: String,
+    /// Holding-register parameter accepting writes (not served as input register)
+    pub writable: bool,
+    /// Register order of 32-bit values ("ABCD", "CDAB", "BADC", "DCBA")
+    pub word_order: String,
+    /// Raw register value = physical value × scale
+    pub scale: f64,
+    /// Modbus TCP port the plant is served on
+    pub port: u16,
+    /// Unit id the register answers on (null = any)
+    pub unit_id: Option<u8>,
+}
+
+/// Whether the sun rises and sets on a given day.
+#[derive(Debug, Serialize, Clone, Copy, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Daylight {
+    Normal,
+    /// Midnight sun: no sunset
+    PolarDay,
+    /// No sunrise
+    PolarNight,
+}
+
+/// Sun times of one day, in the plant's time zone.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SunInfo {
+    pub plant_id: String,
+    pub date: NaiveDate,
+    pub daylight: Daylight,
+    /// Null in polar day/night
+    pub sunrise: Option<DateTime<FixedOffset>>,
+    pub solar_noon: DateTime<FixedOffset>,
+    /// Null in polar day/night
+    pub sunset: Option<DateTime<FixedOffset>>,
+    /// Hours between sunrise and sunset (24 in polar day, 0 in polar night)
+    pub day_length_h: f64,
+}
+
+/// Where a plant's live weather readings come from.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum WeatherSource {
+    /// The solar model's clear-sky and cloud simulation
+    #[default]
+    Model,
+    /// Open-Meteo's current conditions
+    OpenMeteo,
+}
+
+/// Label of a WMO weather interpretation code, as Open-Meteo uses them.
+pub fn weather_label(code: u16) -> &'static str {
+    match code {
+        0  => "Clear sky",
+        1  => "Mainly clear",
+        2  => "Partly cloudy",
+        3  => "Overcast",
+        45 => "Fog",
+        48 => "Depositing rime fog",
+        51 => "Light drizzle",
+        53 => "Moderate drizzle",
+        55 => "Dense drizzle",
+        56 => "Light freezing drizzle",
+        57 => "Dense freezing drizzle",
+        61 => "Slight rain",
+        63 => "Moderate rain",
+        65 => "Heavy rain",
+        66 => "Light freezing rain",
+        67 => "Heavy freezing rain",
+        71 => "Slight snowfall",
+        73 => "Moderate snowfall",
+        75 => "Heavy snowfall",
+        77 => "Snow grains",
+        80 => "Slight rain showers",
+        81 => "Moderate rain showers",
+        82 => "Violent rain showers",
+        85 => "Slight snow showers",
+        86 => "Heavy snow showers",
+        95 => "Thunderstorm",
+        96 => "Thunderstorm with slight hail",
+        99 => "Thunderstorm with heavy hail",
+        _  => "Unknown",
+    }
+}
+
+/// Live weather at a plant, from its latest update.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct PlantWeather {
+    pub plant_id: String,
+    /// Ambient air temperature (°C)
+    pub ambient_temp_c: f64,
+    /// Wind speed at 10 m (m/s)
+    pub wind_speed_m_s: f64,
+    /// Relative humidity at surface (%)
+    pub relative_humidity_pct: f64,
+    /// Cloud attenuation factor [0..1] — 1.0 = clear sky
+    pub cloud_factor: f64,
+    /// Panel soiling factor [0.7..1.0] — 1.0 = clean
+    pub soiling_factor: f64,
+    /// WMO weather interpretation code
+    pub weather_code: u16,
+    /// Label of `weather_code`: 0 Clear sky, 1 Mainly clear, 2 Partly cloudy,
+    /// 3 Overcast, 45 Fog, 48 Depositing rime fog, 51/53/55 Light/Moderate/Dense
+    /// drizzle, 56/57 Light/Dense freezing drizzle, 61/63/65 Slight/Moderate/Heavy
+    /// rain, 66/67 Light/Heavy freezing rain, 71/73/75 Slight/Moderate/Heavy
+    /// snowfall, 77 Snow grains, 80/81/82 Slight/Moderate/Violent rain showers,
+    /// 85/86 Slight/Heavy snow showers, 95 Thunderstorm, 96/99 Thunderstorm with
+    /// slight/heavy hail; any other code is Unknown
+    pub weather_label: String,
+    pub is_day: bool,
+    /// Global horizontal irradiance (W/m²)
+    pub ghi_w_m2: f64,
+    /// Plane-of-array irradiance as read by the irradiance sensor (W/m²)
+    pub poa_irradiance_w_m2: f64,
+    /// Solar elevation angle (deg)
+    pub solar_elevation_deg: f64,
+    /// Solar azimuth (deg from North, clockwise)
+    pub solar_azimuth_deg: f64,
+    pub source: WeatherSource,
+    /// When the readings were observed: Open-Meteo reports its current
+    /// conditions every 15 minutes, the model at every update
+    pub observed_at: DateTime<Utc>,
+    /// Whole seconds between the observation and now on the simulation clock
+    pub age_s: f64,
+    /// The plant missed its updates: the readings are the last ones it sent
+    pub stale: bool,
+}
+
+/// Calendar span of an expected-energy figure.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, Default, PartialEq, Eq, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum EnergyPeriod {
+    #[default]
+    Day,
+    Month,
+    Year,
+}
+
+impl EnergyPeriod {
+    /// Local days `start..end` (end exclusive) of the period named by `date`
+    /// (`YYYY-MM-DD`, `YYYY-MM` or `YYYY`), or of the one containing `today`.
+    pub fn days(self, date: Option<&str>, today: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+        use chrono::Datelike;
+        let start = match (self, date) {
+            (_, None)               => today,
+            (Self::Day, Some(d))    => NaiveDate::parse_from_str(d, "%Y-%m-%d").ok()?,
+            (Self::Month, Some(d))  => NaiveDate::parse_from_str(&format!("{d}-01"), "%Y-%m-%d").ok()?,
+            (Self::Year, Some(d))   => NaiveDate::from_ymd_opt(d.parse().ok()?, 1, 1)?,
+        };
+        match self {
+            Self::Day   => Some((start, start.succ_opt()?)),
+            Self::Month => {
+                let first = start.with_day(1)?;
+                Some((first, first.checked_add_months(chrono::Months::new(1))?))
+            }
+            Self::Year  => {
+                let first = NaiveDate::from_ymd_opt(start.year(), 1, 1)?;
+                Some((first, NaiveDate::from_ymd_opt(start.year() + 1, 1, 1)?))
+            }
+        }
+    }
+}
+
+/// Production the clear-sky and climatological model expects from a plant.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ExpectedEnergy {
+    pub plant_id: String,
+    pub period: EnergyPeriod,
+    /// First local day of the period
+    pub start: NaiveDate,
+    /// Last local day of the period
+    pub end: NaiveDate,
+    /// Sampling step of the integration (min)
+    pub step_min: u32,
+    /// Expected AC energy (kWh)
+    pub expected_energy_kwh: f64,
+    /// Highest expected AC output (kW)
+    pub peak_power_kw: f64,
+    /// Expected energy over the DC rating: hours at full nameplate (kWh/kWp)
+    pub equivalent_sun_hours: f64,
+}
+
+/// Alarms raised within a report period, by severity.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, ToSchema)]
+pub struct AlarmCounts {
+    pub info: usize,
+    pub warning: usize,
+    pub critical: usize,
+    pub fault: usize,
+}
+
+impl AlarmCounts {
+    pub fn add(&mut self, severity: &AlarmSeverity) {
+        match severity {
+            AlarmSeverity::Info     => self.info += 1,
+            AlarmSeverity::Warning  => self.warning += 1,
+            AlarmSeverity::Critical => self.critical += 1,
+            AlarmSeverity::Fault    => self.fault += 1,
+        }
+    }
+
+    pub fn merge(&mut self, other: AlarmCounts) {
+        self.info += other.info;
+        self.warning += other.warning;
+        self.critical += other.critical;
+        self.fault += other.fault;
+    }
+}
+
+/// What a plant produced over a local day or month.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct ProductionReport {
+    pub plant_id: String,
+    pub period: EnergyPeriod,
+    /// First local day of the period
+    pub start: NaiveDate,
+    /// Last local day of the period
+    pub end: NaiveDate,
+    /// AC energy produced, as booked on the counters (kWh)
+    pub energy_kwh: f64,
+    /// Highest AC output (kW)
+    pub peak_power_kw: f64,
+    /// When `peak_power_kw` was reached: the history minute it comes from;
+    /// null when the peak comes from a day's counters, which keep no time
+    pub peak_power_at: Option<DateTime<Utc>>,
+    /// Energy over the inverter AC rating: hours at full output (h)
+    pub full_load_hours: f64,
+    /// Energy over the reference yield of the POA insolation (IEC 61724);
+    /// null without insolation
+    pub performance_ratio: Option<f64>,
+    /// Energy over the DC rating (kWh/kWp)
+    pub specific_yield_kwh_kwp: f64,
+    /// Plane-of-array insolation (kWh/m²)
+    pub insolation_kwh_m2: f64,
+    /// Energy held back by the export cap (kWh)
+    pub curtailed_energy_kwh: f64,
+    /// Alarms raised within the period
+    pub alarms: AlarmCounts,
+    /// Share of the period so far backed by telemetry (%): minutes without
+    /// samples count as missing, as do whole days the simulator did not run
+    pub completeness_pct: f64,
+}
+
+/// What the fleet produced over a day or month, with a row per plant; each
+/// plant keeps to its own local calendar.
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct FleetProductionReport {
+    pub period: EnergyPeriod,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub energy_kwh: f64,
+    /// Highest simultaneous fleet output in the history (kW)
+    pub peak_power_kw: f64,
+    pub peak_power_at: Option<DateTime<Utc>>,
+    /// Energy over the summed AC ratings (h)
+    pub full_load_hours: f64,
+    /// Energy over the capacity-weighted reference yield; null without insolation
+    pub performance_ratio: Option<f64>,
+    /// Energy over the summed DC ratings (kWh/kWp)
+    pub specific_yield_kwh_kwp: f64,
+    pub curtailed_energy_kwh: f64,
+    pub alarms: AlarmCounts,
+    /// Plant completeness weighted by DC rating (%)
+    pub completeness_pct: f64,
+    pub plants: Vec<ProductionReport>,
+}
+
+/// One step of a power forecast.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize, ToSchema)]
+pub struct ForecastPoint {
+    /// Start of the step
+    pub timestamp: DateTime<Utc>,
+    /// Mean AC output over the step (kW)
+    pub power_kw: f64,
+    /// AC energy over the step (kWh)
+    pub energy_kwh: f64,
+    /// Plane-of-array irradiance (W/m²)
+    pub poa_w_m2: f64,
+}
+
+/// Where a forecast comes from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ForecastSource {
+    /// The clear-sky and climatological cloud model run forward
+    Model,
+    /// Open-Meteo's hourly radiation forecast through the plant model
+    OpenMeteo,
+}
+
+/// AC production forecast of a plant or of the fleet.
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+pub struct PowerForecast {
+    /// Plant forecast; unset for the fleet
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plant_id: Option<String>,
+    pub source: ForecastSource,
+    pub hours: u32,
+    /// 15m, 30m or 1h
+    pub resolution: String,
+    pub points: Vec<ForecastPoint>,
+    /// Expected AC energy over the whole forecast (kWh)
+    pub total_energy_kwh: f64,
+}
+
+/// Reactive power control of a plant, as also exposed on Modbus offsets 81, 82 and 85.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct ReactivePowerControl {
+    pub plant_id: String,
+    pub mode: ReactivePowerMode,
+    /// cos φ used in `cos_phi` mode (0 = 1.0)
+    pub cos_phi_setpoint: f64,
+    /// Q used in `fixed_q` mode (kvar, + = over-excited)
+    pub q_setpoint_kvar: f64,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -355,10 +1095,21 @@ pub struct HealthStatus {
     pub status: String,
     pub version: String,
     pub uptime_seconds: u64,
+    /// When the process started
+    pub started_at: DateTime<Utc>,
+    /// Commit the binary was built from ("unknown" outside a git checkout)
+    pub git_hash: String,
     pub plants_online: usize,
     pub plants_total: usize,
     pub offline_mode: bool,
     pub mqtt_connected: bool,
+    /// The simulation clock is accelerated or moved off the wall clock: the
+    /// telemetry is simulated history, not live data
+    pub clock_overridden: bool,
+    /// Current time on the simulation clock
+    pub simulation_time: DateTime<Utc>,
+    /// Simulated seconds per wall-clock second
+    pub clock_speed: f64,
 }
 
 #[derive(Debug, Serialize, ToSchema)]
@@ -370,6 +1121,8 @@ pub struct GlobalPowerResponse {
     pub total_lifetime_energy_kwh: f64,
     pub fleet_performance_ratio: f64,
     pub plants_running: usize,
+    /// Plants taken out of service, still listed in `per_plant`
+    pub plants_in_maintenance: usize,
     pub plants_total: usize,
     pub per_plant: std::collections::HashMap<String, f64>,
 }
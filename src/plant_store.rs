@@ -0,0 +1,161 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+
+use crate::models::power::PlantData;
+
+/// One plant's live telemetry behind its own lock.
+pub type PlantSlot = Arc<RwLock<PlantData>>;
+
+// ─── Per-plant telemetry store ───────────────────────────────────────────────
+/// Live telemetry of every plant, each behind its own lock: an update cycle
+/// holds only its own plant, and a reader holds one plant at a time while it
+/// reads it. The outer map is locked just long enough to find a slot or to
+/// add a plant.
+#[derive(Clone, Debug, Default)]
+pub struct PlantStore {
+    plants: Arc<RwLock<HashMap<String, PlantSlot>>>,
+}
+
+impl PlantStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Slot of `plant_id`, if it has one.
+    pub fn slot(&self, plant_id: &str) -> Option<PlantSlot> {
+        self.plants.read().ok()?.get(plant_id).cloned()
+    }
+
+    /// Slot of `plant_id`, created with default telemetry on first use.
+    pub fn entry(&self, plant_id: &str) -> Option<PlantSlot> {
+        if let Some(slot) = self.slot(plant_id) {
+            return Some(slot);
+        }
+        let mut plants = self.plants.write().ok()?;
+        Some(plants.entry(plant_id.to_string()).or_default().clone())
+    }
+
+    /// Every slot with its plant id, taken under one short lock of the map.
+    fn slots(&self) -> Vec<(String, PlantSlot)> {
+        self.plants.read()
+            .map(|p| p.iter().map(|(id, slot)| (id.clone(), slot.clone())).collect())
+            .unwrap_or_default()
+    }
+
+    pub fn insert(&self, plant_id: &str, data: PlantData) {
+        if let Ok(mut plants) = self.plants.write() {
+            plants.insert(plant_id.to_string(), Arc::new(RwLock::new(data)));
+        }
+    }
+
+    /// Drop the slot of `plant_id`; a reader still holding it keeps its copy.
+    pub fn remove(&self, plant_id: &str) -> Option<PlantData> {
+        let slot = self.plants.write().ok()?.remove(plant_id)?;
+        let data = slot.read().ok()?.clone();
+        Some(data)
+    }
+
+    pub fn contains(&self, plant_id: &str) -> bool {
+        self.plants.read().is_ok_and(|p| p.contains_key(plant_id))
+    }
+
+    pub fn plant_ids(&self) -> Vec<String> {
+        self.plants.read().map(|p| p.keys().cloned().collect()).unwrap_or_default()
+    }
+
+    /// Copy of the telemetry of `plant_id`.
+    pub fn get(&self, plant_id: &str) -> Option<PlantData> {
+        self.read(plant_id, PlantData::clone)
+    }
+
+    /// Apply `f` to the telemetry of `plant_id`, if it has any.
+    pub fn read<R>(&self, plant_id: &str, f: impl FnOnce(&PlantData) -> R) -> Option<R> {
+        let slot = self.slot(plant_id)?;
+        let data = slot.read().ok()?;
+        Some(f(&data))
+    }
+
+    /// Change the telemetry of `plant_id`, if it has any.
+    pub fn modify<R>(&self, plant_id: &str, f: impl FnOnce(&mut PlantData) -> R) -> Option<R> {
+        let slot = self.slot(plant_id)?;
+        let mut data = slot.write().ok()?;
+        Some(f(&mut data))
+    }
+
+    /// Change the telemetry of `plant_id`, starting from the defaults for a
+    /// plant not seen yet. None only on a poisoned lock.
+    pub fn update<R>(&self, plant_id: &str, f: impl FnOnce(&mut PlantData) -> R) -> Option<R> {
+        let slot = self.entry(plant_id)?;
+        let mut data = slot.write().ok()?;
+        Some(f(&mut data))
+    }
+
+    /// Change every plant's telemetry, one plant at a time.
+    pub fn update_all(&self, mut f: impl FnMut(&str, &mut PlantData)) {
+        for (id, slot) in self.slots() {
+            if let Ok(mut data) = slot.write() {
+                f(&id, &mut data);
+            }
+        }
+    }
+
+    /// `f` of every plant's telemetry, read one plant at a time as the
+    /// iterator advances: only what `f` extracts is copied.
+    pub fn map<R>(&self, mut f: impl FnMut(&PlantData) -> R) -> impl Iterator<Item = (String, R)> {
+        self.slots().into_iter().filter_map(move |(id, slot)| {
+            let value = slot.read().ok().map(|data| f(&data))?;
+            Some((id, value))
+        })
+    }
+
+    /// Copy of every plant's telemetry.
+    pub fn snapshot(&self) -> HashMap<String, PlantData> {
+        self.map(PlantData::clone).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn plants_are_created_on_update_and_read_one_at_a_time() {
+        let store = PlantStore::new();
+        assert!(store.get("plant_1").is_none());
+        assert_eq!(store.modify("plant_1", |d| d.power_kw = 1.0), None);
+        assert!(!store.contains("plant_1"));
+
+        store.update("plant_1", |d| d.power_kw = 12.5);
+        store.insert("plant_2", PlantData { power_kw: 7.5, ..PlantData::default() });
+        assert_eq!(store.read("plant_1", |d| d.power_kw), Some(12.5));
+        assert_eq!(store.modify("plant_2", |d| std::mem::replace(&mut d.power_kw, 8.0)), Some(7.5));
+
+        let mut powers: Vec<(String, f64)> = store.map(|d| d.power_kw).collect();
+        powers.sort_by(|a, b| a.0.cmp(&b.0));
+        assert_eq!(powers, vec![("plant_1".to_string(), 12.5), ("plant_2".to_string(), 8.0)]);
+
+        store.update_all(|_, d| d.power_kw = 0.0);
+        assert!(store.snapshot().values().all(|d| d.power_kw == 0.0));
+        let mut ids = store.plant_ids();
+        ids.sort();
+        assert_eq!(ids, ["plant_1", "plant_2"]);
+
+        assert_eq!(store.remove("plant_2").map(|d| d.power_kw), Some(0.0));
+        assert!(!store.contains("plant_2") && store.remove("plant_2").is_none());
+    }
+
+    #[test]
+    fn a_reader_holding_one_plant_does_not_block_another_plants_update() {
+        let store = PlantStore::new();
+        store.update("plant_1", |_| ());
+        store.update("plant_2", |_| ());
+        let slot = store.slot("plant_1").unwrap();
+        let _reading = slot.read().unwrap();
+        let writer = {
+            let store = store.clone();
+            std::thread::spawn(move || store.update("plant_2", |d| d.power_kw = 3.0))
+        };
+        assert_eq!(writer.join().unwrap(), Some(()));
+        assert_eq!(store.read("plant_2", |d| d.power_kw), Some(3.0));
+    }
+}
@@ -0,0 +1,399 @@
+use std::ops::Range;
+
+use crate::config::{PlantConfig, ProfileKind};
+use crate::models::power::PlantData;
+use crate::shared_state::AppState;
+use crate::sunspec::SunSpecDevice;
+
+// ─── Register profiles ────────────────────────────────────────────────────────
+// A profile serves one plant at the addresses documented by a real device family,
+// so existing driver libraries can be pointed at the simulator unmodified.
+// Every profile answers on both input and holding registers, big-endian.
+//
+//   sunspec   SunSpec Common Model 1 + Inverter Model 103 at 40000
+//   fronius   as sunspec, manufacturer "Fronius" (Fronius Datamanager layout)
+//   sma       SMA Modbus (Sunny Tripower / Sunny Boy) 30201..30982, unit 3 by convention
+//   huawei    Huawei SUN2000 32064..32115
+//
+// With `modbus_mapping.virtual_devices` a plant on unit N also answers as a
+// weather station on unit N+1 and a revenue meter on unit N+2, each with a
+// compact table starting at register 0.
+
+/// One documented point, as published by `/api/modbus/info`.
+pub struct ProfilePoint {
+    pub address:     u16,
+    pub length:      u16,
+    pub data_type:   &'static str,
+    pub description: &'static str,
+    pub unit:        &'static str,
+    /// Raw register value = physical value × scale (1 for self-describing points)
+    pub scale:       f64,
+}
+
+/// Maps the live `PlantData` of one plant onto a vendor register block.
+pub trait RegisterProfile: Send + Sync {
+    /// Profile name shown in logs and `/api/modbus/info`.
+    fn name(&self) -> &'static str;
+
+    /// Plant served by this device.
+    fn plant_id(&self) -> &str;
+
+    /// Absolute register addresses of the block.
+    fn block(&self) -> Range<u16>;
+
+    /// Register image of the whole block for one data snapshot.
+    fn image(&self, data: &PlantData) -> Vec<u16>;
+
+    /// Documented points of the block.
+    fn points(&self) -> Vec<ProfilePoint>;
+
+    /// True when `addr .. addr + cnt` lies entirely inside the block.
+    fn contains(&self, addr: u16, cnt: u16) -> bool {
+        let block = self.block();
+        addr >= block.start && addr as u32 + cnt as u32 <= block.end as u32
+    }
+
+    /// Read `cnt` registers starting at absolute address `addr` (must be contained).
+    fn read(&self, state: &AppState, addr: u16, cnt: u16) -> Vec<u16> {
+        let data  = state.get_data(self.plant_id()).unwrap_or_default();
+        let start = (addr - self.block().start) as usize;
+        self.image(&data)[start..start + cnt as usize].to_vec()
+    }
+}
+
+/// Device serving `plant`, or None when it uses the simulator's own layout.
+pub fn for_plant(plant: &PlantConfig) -> Option<Box<dyn RegisterProfile>> {
+    match plant.modbus_mapping.profile {
+        ProfileKind::Custom  => None,
+        ProfileKind::Sunspec
+        | ProfileKind::Fronius => Some(Box::new(SunSpecDevice::new(plant))),
+        ProfileKind::Sma     => Some(Box::new(VendorDevice::new(plant, "SMA", SMA_POINTS))),
+        ProfileKind::Huawei  => Some(Box::new(VendorDevice::new(plant, "Huawei", HUAWEI_POINTS))),
+    }
+}
+
+/// Site gateway companions of `plant` with the unit id each answers on: the
+/// weather station (unit N+1) and revenue meter (unit N+2) of a plant on unit N.
+/// Empty unless `virtual_devices` is set; units past 255 are left out.
+pub fn virtual_devices(plant: &PlantConfig) -> Vec<(u8, Box<dyn RegisterProfile>)> {
+    let Some(unit) = plant.modbus_mapping.unit_id.filter(|_| plant.modbus_mapping.virtual_devices) else {
+        return Vec::new();
+    };
+    let mut devices: Vec<(u8, Box<dyn RegisterProfile>)> = Vec::new();
+    if let Some(weather) = unit.checked_add(1) {
+        devices.push((weather, Box::new(VendorDevice::new(plant, "Weather station", WEATHER_POINTS))));
+    }
+    if let Some(meter) = unit.checked_add(2) {
+        devices.push((meter, Box::new(VendorDevice::new(plant, "Revenue meter", METER_POINTS))));
+    }
+    devices
+}
+
+// ─── Table-driven vendor maps ─────────────────────────────────────────────────
+/// Register encoding of a vendor point (big-endian, high word first).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum VendorType {
+    U16,
+    I16,
+    U32,
+    I32,
+    U64,
+}
+
+impl VendorType {
+    const fn len(self) -> u16 {
+        match self {
+            VendorType::U16 | VendorType::I16 => 1,
+            VendorType::U32 | VendorType::I32 => 2,
+            VendorType::U64                   => 4,
+        }
+    }
+
+    const fn label(self) -> &'static str {
+        match self {
+            VendorType::U16 => "uint16",
+            VendorType::I16 => "int16",
+            VendorType::U32 => "uint32",
+            VendorType::I32 => "int32",
+            VendorType::U64 => "uint64",
+        }
+    }
+}
+
+struct VendorPoint {
+    address:     u16,
+    data_type:   VendorType,
+    /// Vendor gain: raw = value × scale (SMA FIXn = 10ⁿ, Huawei "gain")
+    scale:       f64,
+    value:       fn(&PlantData) -> f64,
+    description: &'static str,
+    unit:        &'static str,
+}
+
+const fn point(
+    address: u16,
+    data_type: VendorType,
+    scale: f64,
+    value: fn(&PlantData) -> f64,
+    description: &'static str,
+    unit: &'static str,
+) -> VendorPoint {
+    VendorPoint { address, data_type, scale, value, description, unit }
+}
+
+/// Write `v` big-endian across `regs` (one, two or four registers).
+fn put_be(regs: &mut [u16], v: u64) {
+    let n = regs.len();
+    for (i, reg) in regs.iter_mut().enumerate() {
+        *reg = (v >> (16 * (n - 1 - i))) as u16;
+    }
+}
+
+impl VendorPoint {
+    /// Encode the live value into this point's registers. Float → integer casts
+    /// saturate, so out-of-range values stick at the type limits.
+    fn encode(&self, data: &PlantData, regs: &mut [u16]) {
+        let v = ((self.value)(data) * self.scale).round();
+        match self.data_type {
+            VendorType::U16 => regs[0] = v as u16,
+            VendorType::I16 => regs[0] = v as i16 as u16,
+            VendorType::U32 => put_be(regs, v as u32 as u64),
+            VendorType::I32 => put_be(regs, v as i32 as u32 as u64),
+            VendorType::U64 => put_be(regs, v as u64),
+        }
+    }
+}
+
+/// A plant served through one of the static vendor tables below.
+pub struct VendorDevice {
+    plant_id: String,
+    name:     &'static str,
+    points:   &'static [VendorPoint],
+}
+
+impl VendorDevice {
+    fn new(plant: &PlantConfig, name: &'static str, points: &'static [VendorPoint]) -> Self {
+        Self { plant_id: plant.id.clone(), name, points }
+    }
+}
+
+impl RegisterProfile for VendorDevice {
+    fn name(&self) -> &'static str {
+        self.name
+    }
+
+    fn plant_id(&self) -> &str {
+        &self.plant_id
+    }
+
+    fn block(&self) -> Range<u16> {
+        let start = self.points.iter().map(|p| p.address).min().unwrap_or(0);
+        let end   = self.points.iter().map(|p| p.address + p.data_type.len()).max().unwrap_or(0);
+        start..end
+    }
+
+    /// Gaps between documented points read as 0.
+    fn image(&self, data: &PlantData) -> Vec<u16> {
+        let block = self.block();
+        let mut regs = vec![0u16; block.len()];
+        for p in self.points {
+            let at = (p.address - block.start) as usize;
+            p.encode(data, &mut regs[at..at + p.data_type.len() as usize]);
+        }
+        regs
+    }
+
+    fn points(&self) -> Vec<ProfilePoint> {
+        self.points.iter().map(|p| ProfilePoint {
+            address:     p.address,
+            length:      p.data_type.len(),
+            data_type:   p.data_type.label(),
+            description: p.description,
+            unit:        p.unit,
+            scale:       p.scale,
+        }).collect()
+    }
+}
+
+// ── SMA ──────────────────────────────────────────────────────────────────────
+// SMA Modbus interface, device class "Solar Inverter"; FIXn = value × 10ⁿ.
+
+const SMA_FAULT: f64   = 35.0;
+const SMA_OFF: f64     = 303.0;
+const SMA_OK: f64      = 307.0;
+const SMA_WARNING: f64 = 455.0;
+
+/// Device condition (Operation.Health).
+fn sma_condition(d: &PlantData) -> f64 {
+    match d.status {
+        2                         => SMA_FAULT,
+        _ if !d.inverter_enabled  => SMA_OFF,
+        _ if d.alarm_flags != 0   => SMA_WARNING,
+        _                         => SMA_OK,
+    }
+}
+
+const SMA_POINTS: &[VendorPoint] = &[
+    point(30201, VendorType::U32, 1.0,    sma_condition,                      "Condition (35 Fault, 303 Off, 307 Ok, 455 Warning)", "—"),
+    point(30513, VendorType::U64, 1.0,    |d| d.total_energy_kwh * 1000.0,    "Total yield (Metering.TotWhOut)",    "Wh"),
+    point(30517, VendorType::U64, 1.0,    |d| d.daily_energy_kwh * 1000.0,    "Day yield (Metering.DyWhOut)",       "Wh"),
+    point(30529, VendorType::U32, 1.0,    |d| d.total_energy_kwh * 1000.0,    "Total yield, 32-bit",                "Wh"),
+    point(30535, VendorType::U32, 1.0,    |d| d.daily_energy_kwh * 1000.0,    "Day yield, 32-bit",                  "Wh"),
+    point(30769, VendorType::I32, 1000.0, |d| d.dc_current_a,                 "DC current input (FIX3)",            "A"),
+    point(30771, VendorType::I32, 100.0,  |d| d.dc_voltage_v,                 "DC voltage input (FIX2)",            "V"),
+    point(30773, VendorType::I32, 1.0,    |d| d.dc_power_kw * 1000.0,         "DC power input",                     "W"),
+    point(30775, VendorType::I32, 1.0,    |d| d.power_kw * 1000.0,            "Active power (GridMs.TotW)",         "W"),
+    point(30783, VendorType::U32, 100.0,  |d| d.voltage_l1_v,                 "Grid voltage phase L1 (FIX2)",       "V"),
+    point(30785, VendorType::U32, 100.0,  |d| d.voltage_l2_v,                 "Grid voltage phase L2 (FIX2)",       "V"),
+    point(30787, VendorType::U32, 100.0,  |d| d.voltage_l3_v,                 "Grid voltage phase L3 (FIX2)",       "V"),
+    point(30803, VendorType::U32, 100.0,  |d| d.frequency_hz,                 "Grid frequency (FIX2)",              "Hz"),
+    point(30805, VendorType::I32, 1.0,    |d| d.reactive_power_kvar * 1000.0, "Reactive power",                     "var"),
+    point(30813, VendorType::I32, 1.0,    |d| d.apparent_power_kva * 1000.0,  "Apparent power",                     "VA"),
+    point(30949, VendorType::U32, 1000.0, |d| d.power_factor,                 "Displacement power factor (FIX3)",   "—"),
+    point(30953, VendorType::I32, 10.0,   |d| d.inverter_temp_c,              "Internal temperature (FIX1)",        "°C"),
+    point(30977, VendorType::I32, 1000.0, |d| d.current_l1_a,                 "Grid current phase L1 (FIX3)",       "A"),
+    point(30979, VendorType::I32, 1000.0, |d| d.current_l2_a,                 "Grid current phase L2 (FIX3)",       "A"),
+    point(30981, VendorType::I32, 1000.0, |d| d.current_l3_a,                 "Grid current phase L3 (FIX3)",       "A"),
+];
+
+// ── Huawei ───────────────────────────────────────────────────────────────────
+// SUN2000 Modbus interface definitions; raw = value × gain.
+
+/// Device status register (32089).
+fn huawei_status(d: &PlantData) -> f64 {
+    let code: u16 = match d.status {
+        1 | 5                    => 0x0200,  // On-grid
+        2                        => 0x0300,  // Shutdown: fault
+        3                        => 0x0201,  // Grid connection: power limited
+        4                        => 0x0100,  // Starting
+        _ if !d.inverter_enabled => 0x0301,  // Shutdown: command
+        _                        => 0xA000,  // Standby: no irradiation
+    };
+    code as f64
+}
+
+const HUAWEI_POINTS: &[VendorPoint] = &[
+    point(32064, VendorType::I32, 1000.0, |d| d.dc_power_kw,                  "Input power",                   "kW"),
+    point(32069, VendorType::U16, 10.0,   |d| d.voltage_l1_v,                 "Phase A voltage",               "V"),
+    point(32070, VendorType::U16, 10.0,   |d| d.voltage_l2_v,                 "Phase B voltage",               "V"),
+    point(32071, VendorType::U16, 10.0,   |d| d.voltage_l3_v,                 "Phase C voltage",               "V"),
+    point(32072, VendorType::I32, 1000.0, |d| d.current_l1_a,                 "Phase A current",               "A"),
+    point(32074, VendorType::I32, 1000.0, |d| d.current_l2_a,                 "Phase B current",               "A"),
+    point(32076, VendorType::I32, 1000.0, |d| d.current_l3_a,                 "Phase C current",               "A"),
+    point(32078, VendorType::I32, 1000.0, |d| d.daily_peak_power_kw,          "Peak active power of current day", "kW"),
+    point(32080, VendorType::I32, 1000.0, |d| d.power_kw,                     "Active power",                  "kW"),
+    point(32082, VendorType::I32, 1000.0, |d| d.reactive_power_kvar,          "Reactive power",                "kvar"),
+    point(32084, VendorType::I16, 1000.0, |d| d.power_factor,                 "Power factor",                  "—"),
+    point(32085, VendorType::U16, 100.0,  |d| d.frequency_hz,                 "Grid frequency",                "Hz"),
+    point(32086, VendorType::U16, 100.0,  |d| d.efficiency_percent,           "Efficiency",                    "%"),
+    point(32087, VendorType::I16, 10.0,   |d| d.inverter_temp_c,              "Internal temperature",          "°C"),
+    point(32088, VendorType::U16, 1000.0, |d| d.isolation_resistance_mohm,    "Insulation resistance",         "MΩ"),
+    point(32089, VendorType::U16, 1.0,    huawei_status,                      "Device status",                 "—"),
+    point(32090, VendorType::U16, 1.0,    |d| d.fault_code as f64,            "Fault code",                    "—"),
+    point(32106, VendorType::U32, 100.0,  |d| d.total_energy_kwh,             "Accumulated energy yield",      "kWh"),
+    point(32114, VendorType::U32, 100.0,  |d| d.daily_energy_kwh,             "Daily energy yield",            "kWh"),
+];
+
+// ── Virtual devices ──────────────────────────────────────────────────────────
+// Compact maps of the site gateway companions (unit N+1, N+2), starting at 0.
+
+const WEATHER_POINTS: &[VendorPoint] = &[
+    point(0, VendorType::I16, 10.0,   |d| d.ambient_temp_c,                "Ambient temperature",           "°C"),
+    point(1, VendorType::U16, 1.0,    |d| d.poa_irradiance_w_m2,           "Plane-of-array irradiance",     "W/m²"),
+    point(2, VendorType::U16, 10.0,   |d| d.wind_speed_m_s,                "Wind speed",                    "m/s"),
+    point(3, VendorType::U16, 10.0,   |d| d.relative_humidity_pct,         "Relative humidity",             "%"),
+    point(4, VendorType::I16, 10.0,   |d| d.temperature_c,                 "Module temperature",            "°C"),
+];
+
+/// Active power per phase (W): V × I × cos φ.
+fn power_l1_w(d: &PlantData) -> f64 { d.voltage_l1_v * d.current_l1_a * d.power_factor }
+fn power_l2_w(d: &PlantData) -> f64 { d.voltage_l2_v * d.current_l2_a * d.power_factor }
+fn power_l3_w(d: &PlantData) -> f64 { d.voltage_l3_v * d.current_l3_a * d.power_factor }
+
+const METER_POINTS: &[VendorPoint] = &[
+    point(0,  VendorType::I32, 1.0,    |d| d.power_kw * 1000.0,             "Total active power",            "W"),
+    point(2,  VendorType::I32, 1.0,    power_l1_w,                          "Active power L1",               "W"),
+    point(4,  VendorType::I32, 1.0,    power_l2_w,                          "Active power L2",               "W"),
+    point(6,  VendorType::I32, 1.0,    power_l3_w,                          "Active power L3",               "W"),
+    point(8,  VendorType::I32, 1.0,    |d| d.reactive_power_kvar * 1000.0,  "Total reactive power",          "var"),
+    point(10, VendorType::U16, 100.0,  |d| d.frequency_hz,                  "Frequency",                     "Hz"),
+    point(11, VendorType::I16, 1000.0, |d| d.power_factor,                  "Power factor",                  "—"),
+    point(12, VendorType::U64, 1.0,    |d| d.total_energy_kwh * 1000.0,     "Exported energy, total",        "Wh"),
+    point(16, VendorType::U32, 1.0,    |d| d.monthly_energy_kwh * 1000.0,   "Exported energy, this month",   "Wh"),
+    point(18, VendorType::U32, 1.0,    |d| d.daily_energy_kwh * 1000.0,     "Exported energy, today",        "Wh"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plant(profile: &str) -> PlantConfig {
+        serde_json::from_value(serde_json::json!({
+            "id": "plant_1", "name": "plant_1", "latitude": 45.0, "longitude": 7.0,
+            "nominal_power_kw": 1000.0, "timezone": "Europe/Rome",
+            "modbus_mapping": { "unit_id": 3, "profile": profile }
+        })).unwrap()
+    }
+
+    fn state() -> AppState {
+        let state = AppState::new(true);
+        state.plant_data.insert("plant_1", PlantData {
+            power_kw: 812.5, daily_energy_kwh: 3120.25, total_energy_kwh: 1.25e6,
+            status: 5, ..PlantData::default()
+        });
+        state
+    }
+
+    fn u32_at(regs: &[u16]) -> u32 {
+        ((regs[0] as u32) << 16) | regs[1] as u32
+    }
+
+    #[test]
+    fn sma_serves_power_and_day_yield_at_documented_addresses() {
+        let device = for_plant(&plant("sma")).unwrap();
+        let state = state();
+        assert!(device.contains(30201, 1) && device.contains(30981, 2) && !device.contains(30981, 3));
+
+        assert_eq!(u32_at(&device.read(&state, 30775, 2)) as i32, 812_500);
+        assert_eq!(u32_at(&device.read(&state, 30535, 2)), 3_120_250);
+        let day = device.read(&state, 30517, 4);
+        assert_eq!(day.iter().fold(0u64, |acc, &w| (acc << 16) | w as u64), 3_120_250);
+        assert_eq!(u32_at(&device.read(&state, 30201, 2)), SMA_OK as u32);
+    }
+
+    #[test]
+    fn huawei_serves_power_and_daily_yield_at_documented_addresses() {
+        let device = for_plant(&plant("huawei")).unwrap();
+        let state = state();
+        assert_eq!(device.block(), 32064..32116);
+
+        // Active power: kW with gain 1000; daily yield: kWh with gain 100
+        assert_eq!(u32_at(&device.read(&state, 32080, 2)) as i32, 812_500);
+        assert_eq!(u32_at(&device.read(&state, 32114, 2)), 312_025);
+        assert_eq!(device.read(&state, 32089, 1), vec![0x0200]);
+    }
+
+    #[test]
+    fn virtual_devices_follow_the_plant_unit() {
+        let mut plant = plant("generic");
+        assert!(virtual_devices(&plant).is_empty());
+
+        plant.modbus_mapping.virtual_devices = true;
+        let devices = virtual_devices(&plant);
+        let units: Vec<u8> = devices.iter().map(|(unit, _)| *unit).collect();
+        assert_eq!(units, vec![4, 5]);
+        assert_eq!(devices[0].1.block(), 0..5);
+        assert_eq!(devices[1].1.block(), 0..20);
+
+        // Units past 255 do not exist
+        plant.modbus_mapping.unit_id = Some(254);
+        assert_eq!(virtual_devices(&plant).len(), 1);
+    }
+
+    #[test]
+    fn generic_profile_uses_the_simulator_layout() {
+        assert!(for_plant(&plant("generic")).is_none());
+        assert!(for_plant(&plant("custom")).is_none());
+        assert_eq!(for_plant(&plant("fronius")).unwrap().name(), "SunSpec");
+    }
+}
@@ -0,0 +1,191 @@
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+
+use crate::config::RateLimitConfig;
+use crate::shared_state::HttpMetrics;
+
+/// Windows kept before the expired ones are swept out
+const SWEEP_AT: usize = 1024;
+
+/// Which budget a request is charged to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum RouteClass {
+    Read,
+    Mutation,
+    Metrics,
+}
+
+impl RouteClass {
+    pub fn of(method: &Method, path: &str) -> Self {
+        if path == "/metrics" {
+            RouteClass::Metrics
+        } else if matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS) {
+            RouteClass::Read
+        } else {
+            RouteClass::Mutation
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            RouteClass::Read     => "read",
+            RouteClass::Mutation => "mutation",
+            RouteClass::Metrics  => "metrics",
+        }
+    }
+}
+
+/// Requests one client sent to one class of routes in the current window.
+#[derive(Debug, Clone, Copy)]
+struct Window {
+    started: Instant,
+    count:   u32,
+}
+
+/// Fixed-window request counter per (client IP, route class).
+pub struct RateLimiter {
+    config:  RateLimitConfig,
+    windows: Mutex<HashMap<(IpAddr, RouteClass), Window>>,
+    metrics: Arc<HttpMetrics>,
+}
+
+impl RateLimiter {
+    pub fn new(config: RateLimitConfig, metrics: Arc<HttpMetrics>) -> Self {
+        Self { config, windows: Mutex::new(HashMap::new()), metrics }
+    }
+
+    fn budget(&self, class: RouteClass) -> u32 {
+        match class {
+            RouteClass::Read     => self.config.read,
+            RouteClass::Mutation => self.config.mutation,
+            RouteClass::Metrics  => self.config.metrics,
+        }
+    }
+
+    /// Count one request of `ip` at `now`; Err carries how long until its
+    /// window resets once the budget is spent.
+    pub fn check(&self, ip: IpAddr, class: RouteClass, now: Instant) -> Result<(), Duration> {
+        let budget = self.budget(class);
+        if !self.config.enabled || budget == 0 {
+            return Ok(());
+        }
+        let window = Duration::from_secs(self.config.window_s);
+        let Ok(mut windows) = self.windows.lock() else { return Ok(()) };
+        if windows.len() >= SWEEP_AT {
+            windows.retain(|_, w| now.duration_since(w.started) < window);
+        }
+        let current = windows.entry((ip, class)).or_insert(Window { started: now, count: 0 });
+        if now.duration_since(current.started) >= window {
+            *current = Window { started: now, count: 0 };
+        }
+        if current.count >= budget {
+            self.metrics.record_rate_limited(class.label());
+            return Err(window.saturating_sub(now.duration_since(current.started)));
+        }
+        current.count += 1;
+        Ok(())
+    }
+}
+
+/// Middleware in front of /api, /ws, /health and /metrics when
+/// `server.rate_limit` is enabled: 429 with `Retry-After` once the client's
+/// budget for the window is spent.
+pub async fn limit_requests(State(limiter): State<Arc<RateLimiter>>, request: Request, next: Next) -> Response {
+    // Without ConnectInfo (in-process routers) every request shares one budget
+    let ip = request.extensions().get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(addr)| addr.ip())
+        .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+    let class = RouteClass::of(request.method(), request.uri().path());
+    match limiter.check(ip, class, Instant::now()) {
+        Ok(()) => next.run(request).await,
+        Err(retry_after) => {
+            // Whole seconds, rounded up so a client waiting that long gets through
+            let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+            (
+                StatusCode::TOO_MANY_REQUESTS,
+                [(header::RETRY_AFTER, seconds.max(1).to_string())],
+                Json(serde_json::json!({
+                    "error": format!("rate limit for {} requests exceeded, retry in {} s", class.label(), seconds.max(1)),
+                })),
+            ).into_response()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Router};
+
+    fn limits(read: u32, mutation: u32) -> RateLimitConfig {
+        RateLimitConfig { enabled: true, window_s: 1, read, mutation, metrics: 1 }
+    }
+
+    #[test]
+    fn budgets_are_kept_per_client_and_class() {
+        let limiter = RateLimiter::new(limits(2, 0), Arc::new(HttpMetrics::default()));
+        let (a, b) = (IpAddr::from([10, 0, 0, 1]), IpAddr::from([10, 0, 0, 2]));
+        let t0 = Instant::now();
+
+        assert!(limiter.check(a, RouteClass::Read, t0).is_ok());
+        assert!(limiter.check(a, RouteClass::Read, t0).is_ok());
+        let retry = limiter.check(a, RouteClass::Read, t0 + Duration::from_millis(400)).unwrap_err();
+        assert_eq!(retry, Duration::from_millis(600));
+        // Another client, or another class, has its own budget; 0 = unlimited
+        assert!(limiter.check(b, RouteClass::Read, t0).is_ok());
+        assert!(limiter.check(a, RouteClass::Metrics, t0).is_ok());
+        assert!(limiter.check(a, RouteClass::Metrics, t0).is_err());
+        assert!((0..100).all(|_| limiter.check(a, RouteClass::Mutation, t0).is_ok()));
+        // A new window starts afresh
+        assert!(limiter.check(a, RouteClass::Read, t0 + Duration::from_secs(1)).is_ok());
+
+        assert_eq!(RouteClass::of(&Method::GET, "/metrics"), RouteClass::Metrics);
+        assert_eq!(RouteClass::of(&Method::POST, "/api/settings/offline-mode"), RouteClass::Mutation);
+        assert_eq!(RouteClass::of(&Method::GET, "/api/plants"), RouteClass::Read);
+
+        let disabled = RateLimiter::new(RateLimitConfig { enabled: false, ..limits(1, 1) }, Arc::new(HttpMetrics::default()));
+        assert!((0..10).all(|_| disabled.check(a, RouteClass::Read, t0).is_ok()));
+    }
+
+    #[tokio::test]
+    async fn hammering_a_route_gets_429_until_the_window_ends() {
+        let metrics = Arc::new(HttpMetrics::default());
+        let limiter = Arc::new(RateLimiter::new(limits(5, 2), metrics.clone()));
+        let app = Router::new()
+            .route("/api/plants", get(|| async { "plants" }).post(|| async { "created" }))
+            .layer(axum::middleware::from_fn_with_state(limiter, limit_requests));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/api/plants", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>()).await.unwrap();
+        });
+        let client = reqwest::Client::new();
+
+        let mut statuses = Vec::new();
+        for _ in 0..8 {
+            statuses.push(client.get(&url).send().await.unwrap().status().as_u16());
+        }
+        assert_eq!(statuses, [200, 200, 200, 200, 200, 429, 429, 429]);
+        let refused = client.get(&url).send().await.unwrap();
+        assert_eq!(refused.status(), 429);
+        assert_eq!(refused.headers()[header::RETRY_AFTER], "1");
+        assert!(refused.text().await.unwrap().contains("read"));
+        // Mutations are counted apart from reads
+        assert_eq!(client.post(&url).send().await.unwrap().status(), 200);
+
+        tokio::time::sleep(Duration::from_millis(1100)).await;
+        assert_eq!(client.get(&url).send().await.unwrap().status(), 200);
+        assert_eq!(metrics.rate_limited().get("read"), Some(&4));
+        assert_eq!(metrics.rate_limited().get("mutation"), None);
+    }
+}
@@ -1,13 +1,26 @@
-use axum::{routing::get, Router};
+use axum::{routing::{delete, get, post, put}, Router};
 use crate::controllers::power_controller::{
     // Plants & telemetry
-    list_plants, get_plant_power, get_global_power,
+    list_plants, create_plant, update_plant, delete_plant,
+    get_plant_power, get_plant_sun, get_plant_weather, get_expected_energy, get_global_power, get_plant_history, get_global_history,
+    get_plant_forecast, get_fleet_forecast, get_plant_report, get_fleet_report,
+    // Remote control & grid support
+    control_plant, get_reactive_power, set_reactive_power, inject_grid_event, inject_isolation_fault,
+    // Fault injection
+    inject_fault, withdraw_fault, get_fault_catalog,
+    // Maintenance
+    clean_panels, damage_plant, repair_plant, enable_plant, disable_plant,
+    // Scenarios
+    list_scenarios, create_scenario, cancel_scenario,
     // Modbus & config
-    get_modbus_info, get_system_config,
+    get_modbus_info, get_system_config, get_state_snapshot, load_state_snapshot,
     // Alarms & events
-    get_plant_alarms, get_all_alarms, clear_plant_alarms, get_events,
+    get_plant_alarms, get_all_alarms, acknowledge_alarm, clear_plant_alarms, get_events,
     // Settings
-    get_offline_mode, set_offline_mode,
+    get_offline_mode, set_offline_mode, get_simulation_seed, set_simulation_seed,
+    get_sim_clock, set_sim_clock,
+    // Streaming
+    stream_telemetry,
 };
 use crate::shared_state::SharedState;
 
@@ -16,14 +29,43 @@ use crate::shared_state::SharedState;
 /// `FromRef<SharedState>` — a single `.with_state(shared)` covers both.
 pub fn api_routes(shared: SharedState) -> Router {
     Router::new()
-        .route("/plants",                  get(list_plants))
-        .route("/plants/{id}/power",       get(get_plant_power))
-        .route("/power/global",            get(get_global_power))
-        .route("/modbus/info",             get(get_modbus_info))
-        .route("/system/config",           get(get_system_config))
-        .route("/plants/{id}/alarms",      get(get_plant_alarms).delete(clear_plant_alarms))
-        .route("/alarms",                  get(get_all_alarms))
-        .route("/events",                  get(get_events))
-        .route("/settings/offline-mode",   get(get_offline_mode).post(set_offline_mode))
+        .route("/plants",                      get(list_plants).post(create_plant))
+        .route("/plants/{id}",                 put(update_plant).delete(delete_plant))
+        .route("/plants/{id}/power",           get(get_plant_power))
+        .route("/plants/{id}/sun",             get(get_plant_sun))
+        .route("/plants/{id}/weather",         get(get_plant_weather))
+        .route("/plants/{id}/expected-energy", get(get_expected_energy))
+        .route("/plants/{id}/history",         get(get_plant_history))
+        .route("/plants/{id}/forecast",        get(get_plant_forecast))
+        .route("/plants/{id}/report",          get(get_plant_report))
+        .route("/plants/{id}/control",         post(control_plant))
+        .route("/plants/{id}/reactive-power",  get(get_reactive_power).post(set_reactive_power))
+        .route("/plants/{id}/grid-event",      post(inject_grid_event))
+        .route("/plants/{id}/isolation-fault", post(inject_isolation_fault))
+        .route("/plants/{id}/faults",          post(inject_fault))
+        .route("/plants/{id}/faults/{code}",   delete(withdraw_fault))
+        .route("/faults/catalog",              get(get_fault_catalog))
+        .route("/plants/{id}/clean",           post(clean_panels))
+        .route("/plants/{id}/damage",          post(damage_plant))
+        .route("/plants/{id}/repair",          post(repair_plant))
+        .route("/plants/{id}/enable",          post(enable_plant))
+        .route("/plants/{id}/disable",         post(disable_plant))
+        .route("/scenarios",                   get(list_scenarios).post(create_scenario))
+        .route("/scenarios/{id}",              delete(cancel_scenario))
+        .route("/power/global",                get(get_global_power))
+        .route("/power/global/history",        get(get_global_history))
+        .route("/power/forecast",              get(get_fleet_forecast))
+        .route("/power/report",                get(get_fleet_report))
+        .route("/modbus/info",                 get(get_modbus_info))
+        .route("/system/config",               get(get_system_config))
+        .route("/system/snapshot",             get(get_state_snapshot).post(load_state_snapshot))
+        .route("/plants/{id}/alarms",          get(get_plant_alarms).delete(clear_plant_alarms))
+        .route("/alarms",                      get(get_all_alarms))
+        .route("/alarms/{alarm_id}/ack",       post(acknowledge_alarm))
+        .route("/events",                      get(get_events))
+        .route("/settings/offline-mode",       get(get_offline_mode).post(set_offline_mode))
+        .route("/settings/simulation-seed",    get(get_simulation_seed).post(set_simulation_seed))
+        .route("/settings/sim-clock",          get(get_sim_clock).post(set_sim_clock))
+        .route("/stream/telemetry",            get(stream_telemetry))
         .with_state(shared)
 }
@@ -0,0 +1,118 @@
+use chrono::{DateTime, Datelike, NaiveTime, TimeZone, Weekday};
+
+use crate::config::CurtailmentWindow;
+
+// ─── Scheduled curtailment ───────────────────────────────────
+/// Weekly active power limits of one plant, evaluated on its local clock.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct CurtailmentSchedule {
+    windows: Vec<Window>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Window {
+    /// Days the window starts on; empty = every day
+    days:      Vec<Weekday>,
+    start:     NaiveTime,
+    end:       NaiveTime,
+    limit_pct: f64,
+}
+
+impl Window {
+    fn starts_on(&self, day: Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&day)
+    }
+
+    /// Whether local wall-clock `time` on `day` falls inside the window. A
+    /// window ending at or before its start runs into the next day.
+    fn contains(&self, day: Weekday, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            self.starts_on(day) && time >= self.start && time < self.end
+        } else {
+            (self.starts_on(day) && time >= self.start) || (self.starts_on(day.pred()) && time < self.end)
+        }
+    }
+}
+
+impl CurtailmentSchedule {
+    pub fn for_config(windows: &[CurtailmentWindow]) -> Self {
+        Self {
+            windows: windows.iter()
+                .filter(|w| w.limit_pct.is_finite())
+                .map(|w| Window { days: w.days.clone(), start: w.start, end: w.end, limit_pct: w.limit_pct.clamp(0.0, 100.0) })
+                .collect(),
+        }
+    }
+
+    /// Tightest limit (% of nominal) of the windows open at `time`, on the
+    /// clock of its time zone; None outside every window.
+    pub fn limit_pct_at<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> Option<f64> {
+        let (day, clock) = (time.weekday(), time.time());
+        self.windows.iter()
+            .filter(|w| w.contains(day, clock))
+            .map(|w| w.limit_pct)
+            .reduce(f64::min)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn window(days: &[Weekday], start: &str, end: &str, limit_pct: f64) -> CurtailmentWindow {
+        CurtailmentWindow { days: days.to_vec(), start: start.parse().unwrap(), end: end.parse().unwrap(), limit_pct }
+    }
+
+    #[test]
+    fn windows_follow_the_local_clock_across_midnight_and_dst() {
+        let rome = chrono_tz::Europe::Rome;
+        let at = |y, m, d, h, min| rome.with_ymd_and_hms(y, m, d, h, min, 0).earliest().unwrap();
+        let weekdays = [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri];
+        let schedule = CurtailmentSchedule::for_config(&[
+            window(&weekdays, "11:00", "13:00", 60.0),
+            window(&[Weekday::Fri], "22:00", "02:00", 30.0),
+            window(&[], "12:00", "12:30", 80.0),
+        ]);
+
+        // Wednesday 2025-06-11: the weekday window, tightest limit on the overlap
+        assert_eq!(schedule.limit_pct_at(&at(2025, 6, 11, 10, 59)), None);
+        assert_eq!(schedule.limit_pct_at(&at(2025, 6, 11, 11, 0)), Some(60.0));
+        assert_eq!(schedule.limit_pct_at(&at(2025, 6, 11, 12, 15)), Some(60.0));
+        assert_eq!(schedule.limit_pct_at(&at(2025, 6, 11, 13, 0)), None);
+        // Sunday: only the every-day window
+        assert_eq!(schedule.limit_pct_at(&at(2025, 6, 15, 11, 30)), None);
+        assert_eq!(schedule.limit_pct_at(&at(2025, 6, 15, 12, 10)), Some(80.0));
+
+        // Friday night runs into Saturday, but Saturday night does not start one
+        assert_eq!(schedule.limit_pct_at(&at(2025, 6, 13, 21, 59)), None);
+        assert_eq!(schedule.limit_pct_at(&at(2025, 6, 13, 23, 30)), Some(30.0));
+        assert_eq!(schedule.limit_pct_at(&at(2025, 6, 14, 1, 59)), Some(30.0));
+        assert_eq!(schedule.limit_pct_at(&at(2025, 6, 14, 2, 0)), None);
+        assert_eq!(schedule.limit_pct_at(&at(2025, 6, 14, 23, 0)), None);
+        assert_eq!(schedule.limit_pct_at(&at(2025, 6, 15, 1, 0)), None);
+
+        // Same wall-clock window, whatever the UTC offset of the day
+        let spring = rome.with_ymd_and_hms(2025, 3, 31, 11, 0, 0).unwrap();
+        assert_eq!(spring.with_timezone(&Utc).format("%H:%M").to_string(), "09:00");
+        assert_eq!(schedule.limit_pct_at(&spring), Some(60.0));
+        let winter = rome.with_ymd_and_hms(2025, 3, 28, 11, 0, 0).unwrap();
+        assert_eq!(winter.with_timezone(&Utc).format("%H:%M").to_string(), "10:00");
+        assert_eq!(schedule.limit_pct_at(&winter), Some(60.0));
+
+        // Spring forward on Sunday 30 March: 02:xx never happens, so the
+        // 01:00–04:00 window only lasts two hours
+        let utc = |h, min| Utc.with_ymd_and_hms(2025, 3, 30, h, min, 0).unwrap().with_timezone(&rome);
+        let sunday = CurtailmentSchedule::for_config(&[window(&[Weekday::Sun], "01:00", "04:00", 50.0)]);
+        assert_eq!(sunday.limit_pct_at(&utc(0, 30)), Some(50.0)); // 01:30 CET
+        assert_eq!(sunday.limit_pct_at(&utc(1, 30)), Some(50.0)); // 03:30 CEST
+        assert_eq!(sunday.limit_pct_at(&utc(2, 0)), None);        // 04:00 CEST
+
+        // Fall back: 02:30 happens twice and both are inside the window
+        let fall = CurtailmentSchedule::for_config(&[window(&[Weekday::Sun], "02:00", "03:00", 40.0)]);
+        let utc = |h, min| Utc.with_ymd_and_hms(2025, 10, 26, h, min, 0).unwrap().with_timezone(&rome);
+        assert_eq!(fall.limit_pct_at(&utc(0, 30)), Some(40.0));  // 02:30 CEST
+        assert_eq!(fall.limit_pct_at(&utc(1, 30)), Some(40.0));  // 02:30 CET
+        assert_eq!(fall.limit_pct_at(&utc(2, 0)), None);          // 03:00 CET
+    }
+}
@@ -0,0 +1,67 @@
+use crate::services::measurement_noise::uniform;
+
+/// Draw streams of the meter error
+const GAIN: u64         = 48;
+const NONLINEARITY: u64 = 49;
+
+/// Share of the class the gain error may take; the low-load nonlinearity
+/// takes the rest, so the error never leaves the class
+const GAIN_SHARE: f64 = 0.6;
+/// Load (share of the AC rating) below which the nonlinearity sets in
+const LOW_LOAD: f64 = 0.1;
+
+// ─── Revenue meter ───────────────────────────────────────────
+/// Energy meter of a given accuracy class (IEC 62053-21): a gain error fixed
+/// for the meter, plus an error growing towards zero load. Both are drawn
+/// from the plant and the scenario seed, so a seed replays the same meter.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EnergyMeter {
+    class_pct: f64,
+}
+
+impl EnergyMeter {
+    pub fn new(class_pct: f64) -> Self {
+        Self { class_pct }
+    }
+
+    pub fn class_pct(&self) -> f64 {
+        self.class_pct
+    }
+
+    /// Relative error of the meter of `plant_id` at `load` (share of the AC
+    /// rating): within ±class at any load.
+    pub fn error(&self, plant_id: &str, seed: u64, load: f64) -> f64 {
+        let class = self.class_pct / 100.0;
+        let gain = class * GAIN_SHARE * (2.0 * uniform(plant_id, seed, 0, GAIN) - 1.0);
+        let low_load = (1.0 - load.clamp(0.0, 1.0) / LOW_LOAD).max(0.0);
+        let nonlinearity = class * (1.0 - GAIN_SHARE) * low_load * (2.0 * uniform(plant_id, seed, 0, NONLINEARITY) - 1.0);
+        gain + nonlinearity
+    }
+
+    /// What the meter books for `true_kwh` delivered at `load`.
+    pub fn metered_kwh(&self, plant_id: &str, seed: u64, true_kwh: f64, load: f64) -> f64 {
+        true_kwh * (1.0 + self.error(plant_id, seed, load))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_error_stays_in_the_class_and_replays_under_a_seed() {
+        let meter = EnergyMeter::new(1.0);
+        for seed in 0..200 {
+            for load in [0.0, 0.02, 0.05, 0.1, 0.5, 1.0] {
+                let e = meter.error("plant_1", seed, load);
+                assert!(e.abs() <= 0.01, "seed {seed}, load {load}: {e}");
+            }
+            // Above the low-load range only the gain is left
+            assert_eq!(meter.error("plant_1", seed, 0.3), meter.error("plant_1", seed, 0.9));
+        }
+        assert_eq!(meter.error("plant_1", 7, 0.5), meter.error("plant_1", 7, 0.5));
+        assert_ne!(meter.error("plant_1", 7, 0.5), meter.error("plant_1", 8, 0.5));
+        assert_ne!(meter.error("plant_1", 7, 0.5), meter.error("plant_2", 7, 0.5));
+        assert!((meter.metered_kwh("plant_1", 7, 10.0, 0.5) - 10.0 * (1.0 + meter.error("plant_1", 7, 0.5))).abs() < 1e-12);
+    }
+}
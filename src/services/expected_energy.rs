@@ -0,0 +1,143 @@
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+use crate::services::inverter_efficiency::EfficiencyCurve;
+use crate::services::solar_algorithm::{estimate, sun_times, EstimateParams, SunTimes};
+
+/// Default sampling step of the expected-energy integration (min).
+pub const DEFAULT_STEP_MIN: u32 = 10;
+
+// ─── Expected (P50) energy ───────────────────────────────────
+/// AC production the clear-sky and climatological model expects over a span.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ExpectedYield {
+    pub energy_kwh: f64,
+    pub peak_kw: f64,
+}
+
+/// Expected AC production of a plant over the local calendar days
+/// `start..end` (end exclusive).
+pub fn expected_yield_for_days(
+    params: &EstimateParams,
+    curve: &EfficiencyCurve,
+    max_ac_kw: f64,
+    start: NaiveDate,
+    end: NaiveDate,
+    step_min: u32,
+) -> ExpectedYield {
+    let from = local_midnight(params.timezone, start);
+    let to   = local_midnight(params.timezone, end);
+    expected_yield(params, curve, max_ac_kw, from, to, step_min)
+}
+
+/// Expected AC production of a plant between `from` and `to`, sampling
+/// `solar_algorithm::estimate` every `step_min` minutes. Sunrise and sunset
+/// are worked out once per day and the night is never sampled.
+pub fn expected_yield(
+    params: &EstimateParams,
+    curve: &EfficiencyCurve,
+    max_ac_kw: f64,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    step_min: u32,
+) -> ExpectedYield {
+    let step = Duration::minutes(step_min.max(1) as i64);
+    let mut total = ExpectedYield::default();
+    if to <= from {
+        return total;
+    }
+    // The solar day before the first local date can run past its midnight
+    let first = from.with_timezone(&params.timezone).date_naive().pred_opt().unwrap_or(NaiveDate::MIN);
+    let last  = to.with_timezone(&params.timezone).date_naive();
+    for date in first.iter_days().take_while(|d| *d <= last) {
+        let (sunrise, sunset) = match sun_times(params.lat_deg, params.lon_deg, date) {
+            SunTimes::Daylight { sunrise, sunset, .. } => (sunrise, sunset),
+            SunTimes::PolarDay { solar_noon }         => (solar_noon - Duration::hours(12), solar_noon + Duration::hours(12)),
+            SunTimes::PolarNight { .. }               => continue,
+        };
+        let (mut t, end) = (sunrise.max(from), sunset.min(to));
+        while t < end {
+            // Midpoint of each step, the last one cut short at sunset
+            let dt = step.min(end - t);
+            let dc_kw = estimate(params, t + dt / 2).power_kw;
+            let ac_kw = ac_output_kw(curve, max_ac_kw, dc_kw);
+            total.energy_kwh += ac_kw * dt.num_seconds() as f64 / 3600.0;
+            total.peak_kw = total.peak_kw.max(ac_kw);
+            t += dt;
+        }
+    }
+    total
+}
+
+/// AC output of `dc_kw` through the inverter, clipped at its rating.
+pub fn ac_output_kw(curve: &EfficiencyCurve, max_ac_kw: f64, dc_kw: f64) -> f64 {
+    if max_ac_kw <= 0.0 {
+        return 0.0;
+    }
+    (dc_kw * curve.efficiency(dc_kw / max_ac_kw)).min(max_ac_kw)
+}
+
+/// Start of local calendar day `date` (the first valid instant when a DST
+/// change skips midnight).
+pub fn local_midnight(tz: Tz, date: NaiveDate) -> DateTime<Utc> {
+    let midnight = date.and_time(NaiveTime::MIN);
+    tz.from_local_datetime(&midnight).earliest()
+        .or_else(|| tz.from_local_datetime(&(midnight + Duration::hours(1))).earliest())
+        .map_or_else(|| midnight.and_utc(), |t| t.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn expected_energy_adds_up_over_the_daylight_hours() {
+        let params = EstimateParams { timezone: chrono_tz::Europe::Rome, ..EstimateParams::new(45.0, 9.0, 100.0) };
+        let curve = EfficiencyCurve::default();
+        let day = |m, d| NaiveDate::from_ymd_opt(2025, m, d).unwrap();
+
+        let june = expected_yield_for_days(&params, &curve, 100.0, day(6, 21), day(6, 22), DEFAULT_STEP_MIN);
+        assert!(june.peak_kw > 30.0 && june.peak_kw <= 100.0, "{} kW", june.peak_kw);
+        assert!(june.energy_kwh > 150.0 && june.energy_kwh < june.peak_kw * 16.0, "{} kWh", june.energy_kwh);
+        // A short winter day makes less
+        let december = expected_yield_for_days(&params, &curve, 100.0, day(12, 21), day(12, 22), DEFAULT_STEP_MIN);
+        assert!(december.energy_kwh < june.energy_kwh);
+
+        // A finer step converges on the same figure
+        let fine = expected_yield_for_days(&params, &curve, 100.0, day(6, 21), day(6, 22), 2);
+        assert!((fine.energy_kwh - june.energy_kwh).abs() < 0.05 * june.energy_kwh, "{} vs {} kWh", fine.energy_kwh, june.energy_kwh);
+
+        // Consecutive days add up to the span that covers both
+        let both = expected_yield_for_days(&params, &curve, 100.0, day(6, 21), day(6, 23), DEFAULT_STEP_MIN);
+        let next = expected_yield_for_days(&params, &curve, 100.0, day(6, 22), day(6, 23), DEFAULT_STEP_MIN);
+        assert!((both.energy_kwh - june.energy_kwh - next.energy_kwh).abs() < 1e-6);
+
+        // Polar night makes nothing, the midnight sun is sampled round the clock
+        let arctic = EstimateParams { timezone: chrono_tz::Europe::Oslo, ..EstimateParams::new(78.0, 15.0, 100.0) };
+        assert_eq!(expected_yield_for_days(&arctic, &curve, 100.0, day(12, 21), day(12, 22), DEFAULT_STEP_MIN), ExpectedYield::default());
+        let midnight_sun = expected_yield_for_days(&arctic, &curve, 100.0, day(6, 21), day(6, 22), DEFAULT_STEP_MIN);
+        assert!(midnight_sun.energy_kwh > 0.0);
+    }
+
+    #[test]
+    fn periods_cover_their_calendar_days() {
+        use crate::models::power::EnergyPeriod;
+        let day = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+        let today = day(2024, 2, 10);
+        assert_eq!(EnergyPeriod::Day.days(None, today), Some((today, day(2024, 2, 11))));
+        assert_eq!(EnergyPeriod::Month.days(None, today), Some((day(2024, 2, 1), day(2024, 3, 1))));
+        assert_eq!(EnergyPeriod::Month.days(Some("2025-12"), today), Some((day(2025, 12, 1), day(2026, 1, 1))));
+        assert_eq!(EnergyPeriod::Year.days(Some("2025"), today), Some((day(2025, 1, 1), day(2026, 1, 1))));
+        assert_eq!(EnergyPeriod::Day.days(Some("2025-06"), today), None);
+        assert_eq!(EnergyPeriod::Month.days(Some("2025-13"), today), None);
+    }
+
+    #[test]
+    fn local_midnight_follows_the_plant_time_zone() {
+        let date = NaiveDate::from_ymd_opt(2025, 6, 21).unwrap();
+        assert_eq!(local_midnight(chrono_tz::Europe::Rome, date).to_rfc3339(), "2025-06-20T22:00:00+00:00");
+        // Chile springs forward at midnight: the day starts at 01:00
+        let skipped = NaiveDate::from_ymd_opt(2024, 9, 8).unwrap();
+        assert_eq!(local_midnight(chrono_tz::America::Santiago, skipped).to_rfc3339(), "2024-09-08T04:00:00+00:00");
+    }
+}
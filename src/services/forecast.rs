@@ -0,0 +1,201 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Duration, DurationRound, Utc};
+
+use crate::models::power::{CurrentData, ForecastPoint, HourlyData};
+use crate::services::expected_energy::ac_output_kw;
+use crate::services::inverter_efficiency::EfficiencyCurve;
+use crate::services::power_service::from_current_weather;
+use crate::services::solar_algorithm::{estimate, EstimateParams};
+
+/// Longest forecast (h): Open-Meteo forecasts a week ahead
+pub const MAX_FORECAST_HOURS: u32 = 168;
+/// Forecast length without `hours`
+pub const DEFAULT_FORECAST_HOURS: u32 = 24;
+
+// ─── Forecast step ───────────────────────────────────────────
+/// Step of a forecast.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum ForecastStep {
+    FifteenMinutes,
+    ThirtyMinutes,
+    #[default]
+    OneHour,
+}
+
+impl ForecastStep {
+    /// `15m`, `30m` or `1h`.
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "15m" => Some(Self::FifteenMinutes),
+            "30m" => Some(Self::ThirtyMinutes),
+            "1h"  => Some(Self::OneHour),
+            _     => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Self::FifteenMinutes => "15m",
+            Self::ThirtyMinutes  => "30m",
+            Self::OneHour        => "1h",
+        }
+    }
+
+    pub fn duration(self) -> Duration {
+        match self {
+            Self::FifteenMinutes => Duration::minutes(15),
+            Self::ThirtyMinutes  => Duration::minutes(30),
+            Self::OneHour        => Duration::hours(1),
+        }
+    }
+
+    /// Step starts of a forecast of `hours` from the step `now` falls in.
+    pub fn starts(self, now: DateTime<Utc>, hours: u32) -> impl Iterator<Item = DateTime<Utc>> {
+        let step = self.duration();
+        let first = now.duration_trunc(step).unwrap_or(now);
+        let count = (hours as i64 * 60) / step.num_minutes();
+        (0..count).map(move |i| first + step * i as i32)
+    }
+}
+
+// ─── Forecasts ───────────────────────────────────────────────
+/// Point of the step starting at `start`, from the AC output and the
+/// irradiance at its midpoint.
+fn point(start: DateTime<Utc>, step: ForecastStep, ac_kw: f64, poa_w_m2: f64) -> ForecastPoint {
+    ForecastPoint {
+        timestamp:  start,
+        power_kw:   ac_kw,
+        energy_kwh: ac_kw * step.duration().num_seconds() as f64 / 3600.0,
+        poa_w_m2,
+    }
+}
+
+/// Forecast from the clear-sky and climatological cloud model run forward
+/// from `now`: the production the offline simulation will show.
+pub fn model_forecast(
+    params: &EstimateParams,
+    curve: &EfficiencyCurve,
+    max_ac_kw: f64,
+    now: DateTime<Utc>,
+    hours: u32,
+    step: ForecastStep,
+) -> Vec<ForecastPoint> {
+    step.starts(now, hours).map(|start| {
+        let est = estimate(params, start + step.duration() / 2);
+        point(start, step, ac_output_kw(curve, max_ac_kw, est.power_kw), est.poa_w_m2)
+    }).collect()
+}
+
+/// Forecast from Open-Meteo's hourly radiation, temperature and wind,
+/// converted to power like a live Open-Meteo reading. Steps past the end
+/// of the hourly series produce nothing.
+pub fn radiation_forecast(
+    params: &EstimateParams,
+    curve: &EfficiencyCurve,
+    max_ac_kw: f64,
+    hourly: &HourlyData,
+    now: DateTime<Utc>,
+    hours: u32,
+    step: ForecastStep,
+) -> Vec<ForecastPoint> {
+    let times: Vec<Option<DateTime<Utc>>> = hourly.time.iter()
+        .map(|t| format!("{}:00Z", t).parse().ok())
+        .collect();
+    step.starts(now, hours).map(|start| {
+        let mid = start + step.duration() / 2;
+        // Radiation is the mean over the hour ending at each time
+        let hour = times.iter().position(|t| t.is_some_and(|t| t >= mid && t - Duration::hours(1) < mid));
+        let Some(i) = hour else {
+            return point(start, step, 0.0, 0.0);
+        };
+        let at = |series: &[Option<f64>]| series.get(i).copied().flatten();
+        let current = CurrentData {
+            time:                     hourly.time[i].clone(),
+            shortwave_radiation:      at(&hourly.shortwave_radiation),
+            temperature_2m:           at(&hourly.temperature_2m),
+            weather_code:             None,
+            is_day:                   None,
+            rain:                     None,
+            wind_speed_10m:           at(&hourly.wind_speed_10m),
+            relative_humidity_2m:     None,
+            direct_normal_irradiance: None,
+            diffuse_radiation:        None,
+        };
+        let data = from_current_weather(params, &current, mid);
+        point(start, step, ac_output_kw(curve, max_ac_kw, data.power_kw), data.poa_irradiance_w_m2)
+    }).collect()
+}
+
+/// Fleet forecast: power and energy add up across plants, the irradiance
+/// averages over them.
+pub fn aggregate(plants: &[Vec<ForecastPoint>]) -> Vec<ForecastPoint> {
+    let mut steps: BTreeMap<DateTime<Utc>, (ForecastPoint, u32)> = BTreeMap::new();
+    for p in plants.iter().flatten() {
+        let (acc, n) = steps.entry(p.timestamp).or_insert_with(|| (ForecastPoint {
+            timestamp: p.timestamp, power_kw: 0.0, energy_kwh: 0.0, poa_w_m2: 0.0,
+        }, 0));
+        acc.power_kw   += p.power_kw;
+        acc.energy_kwh += p.energy_kwh;
+        acc.poa_w_m2   += p.poa_w_m2;
+        *n += 1;
+    }
+    steps.into_values().map(|(p, n)| ForecastPoint { poa_w_m2: p.poa_w_m2 / n.max(1) as f64, ..p }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn the_model_forecast_covers_the_hours_in_steps() {
+        let params = EstimateParams { timezone: chrono_tz::Europe::Rome, ..EstimateParams::new(45.0, 9.0, 100.0) };
+        let curve = EfficiencyCurve::default();
+        let now = Utc.with_ymd_and_hms(2025, 6, 21, 3, 7, 12).unwrap();
+
+        let quarter = model_forecast(&params, &curve, 100.0, now, 48, ForecastStep::FifteenMinutes);
+        assert_eq!(quarter.len(), 48 * 4);
+        assert_eq!(quarter[0].timestamp, Utc.with_ymd_and_hms(2025, 6, 21, 3, 0, 0).unwrap());
+        assert_eq!(quarter[1].timestamp - quarter[0].timestamp, Duration::minutes(15));
+        assert!(quarter.iter().all(|p| (p.energy_kwh - p.power_kw / 4.0).abs() < 1e-9 && p.power_kw <= 100.0));
+        // Midnight makes nothing, midday does
+        let at = |h: u32| quarter.iter().find(|p| p.timestamp == Utc.with_ymd_and_hms(2025, 6, 21, h, 0, 0).unwrap()).unwrap();
+        assert_eq!((at(22).power_kw, at(22).poa_w_m2), (0.0, 0.0));
+        assert!(at(10).power_kw > 20.0, "{} kW", at(10).power_kw);
+
+        // A coarser step forecasts about the same energy
+        let hourly = model_forecast(&params, &curve, 100.0, now, 48, ForecastStep::OneHour);
+        assert_eq!(hourly.len(), 48);
+        let total = |points: &[ForecastPoint]| points.iter().map(|p| p.energy_kwh).sum::<f64>();
+        assert!((total(&hourly) - total(&quarter)).abs() < 0.05 * total(&quarter), "{} vs {} kWh", total(&hourly), total(&quarter));
+        assert_eq!(ForecastStep::parse("30m"), Some(ForecastStep::ThirtyMinutes));
+        assert_eq!(ForecastStep::parse("10m"), None);
+    }
+
+    #[test]
+    fn hourly_radiation_converts_to_power_hour_by_hour() {
+        let params = EstimateParams::new(45.07, 7.33, 100.0);
+        let curve = EfficiencyCurve::default();
+        let hourly: HourlyData = serde_json::from_value(serde_json::json!({
+            "time": ["2025-06-21T10:00", "2025-06-21T11:00", "2025-06-21T12:00"],
+            "shortwave_radiation": [600.0, 850.0, null],
+            "temperature_2m": [24.0, 28.0, 29.0],
+            "wind_speed_10m": [2.0, 2.0, 2.0]
+        })).unwrap();
+        let now = Utc.with_ymd_and_hms(2025, 6, 21, 9, 30, 0).unwrap();
+        let points = radiation_forecast(&params, &curve, 100.0, &hourly, now, 4, ForecastStep::ThirtyMinutes);
+        assert_eq!(points.len(), 8);
+        // 09:30–10:00 lies in the hour ending at 10:00, 10:00–10:30 in the next
+        assert_eq!(points[0].poa_w_m2, 600.0);
+        assert_eq!((points[1].poa_w_m2, points[2].poa_w_m2), (850.0, 850.0));
+        assert!(points[1].power_kw > points[0].power_kw && points[1].power_kw <= 100.0);
+        // No radiation in the series, and no series past its end
+        assert_eq!(points[3].power_kw, 0.0);
+        assert!(points[5..].iter().all(|p| p.power_kw == 0.0 && p.energy_kwh == 0.0));
+
+        let fleet = aggregate(&[points.clone(), points.clone()]);
+        assert_eq!(fleet.len(), 8);
+        assert_eq!((fleet[1].power_kw, fleet[1].poa_w_m2), (2.0 * points[1].power_kw, 850.0));
+    }
+}
@@ -0,0 +1,224 @@
+use std::time::Instant;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::services::measurement_noise::{gaussian, uniform};
+
+/// Noise streams of the grid model
+const WALK: u64      = 32;
+const OVER: u64      = 33;
+const UNDER: u64     = 34;
+const MAGNITUDE: u64 = 35;
+
+/// Pull of the walk back to the nominal (1/s): primary control restores a
+/// deviation within a couple of minutes
+const REVERSION_PER_S: f64 = 1.0 / 120.0;
+/// Walk volatility (Hz/√s); the steady-state spread is about ±0.03 Hz
+const VOLATILITY_HZ: f64   = 0.004;
+/// The walk stays inside the EN 50160 normal band
+const WALK_BAND_HZ: f64    = 0.2;
+/// Window of the RoCoF measurement (s), as for the ENTSO-E 500 ms RoCoF
+const ROCOF_WINDOW_S: f64  = 0.5;
+/// Bounds on what any disturbance can do to the frequency (Hz) and its rate (Hz/s)
+pub const MAX_DEVIATION_HZ: f64 = 5.0;
+pub const MAX_ROCOF_HZ_S: f64 = 10.0;
+/// Excursion windows (s) and the chance of an over- or under-frequency one:
+/// about one every 2.8 h per grid
+const EXCURSION_EPOCH_S: u64 = 300;
+const P_EXCURSION: f64       = 0.015;
+
+// ─── Injected disturbance ────────────────────────────────────
+/// Frequency disturbance forced over the API: the grid jumps to
+/// `frequency_hz` (or keeps its own course), shifted by `step_hz` and
+/// ramping at `rocof_hz_s` from the moment it was injected.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Disturbance {
+    pub frequency_hz: Option<f64>,
+    pub step_hz:      f64,
+    pub rocof_hz_s:   f64,
+}
+
+impl Disturbance {
+    /// Hold the grid at `frequency_hz`.
+    pub fn to(frequency_hz: f64) -> Self {
+        Self { frequency_hz: Some(frequency_hz), ..Self::default() }
+    }
+
+    pub fn is_none(&self) -> bool {
+        self.frequency_hz.is_none() && self.step_hz == 0.0 && self.rocof_hz_s == 0.0
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Injected {
+    disturbance: Disturbance,
+    start:       DateTime<Utc>,
+    until:       Instant,
+}
+
+// ─── Grid frequency ──────────────────────────────────────────
+/// Frequency of one synchronous grid, shared by every plant connected to it:
+/// a mean-reverting random walk inside the normal band, the rare
+/// over/under-frequency excursion lasting a few minutes, and any injected
+/// step or ramp on top. Every draw hashes the grid name, the scenario seed
+/// and the timestamp, so a seed replays the same frequency.
+/// Random walk position of a grid: its deviation and the last two samples.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct WalkState {
+    pub deviation_hz: f64,
+    pub last:         Option<(DateTime<Utc>, f64)>,
+    pub previous:     Option<(DateTime<Utc>, f64)>,
+}
+
+#[derive(Clone, Debug)]
+pub struct GridFrequency {
+    name:         String,
+    nominal_hz:   f64,
+    deviation_hz: f64,
+    /// Latest sample and the one before it, for the RoCoF
+    last:         Option<(DateTime<Utc>, f64)>,
+    previous:     Option<(DateTime<Utc>, f64)>,
+    injected:     Option<Injected>,
+}
+
+impl GridFrequency {
+    pub fn new(name: &str, nominal_hz: f64) -> Self {
+        Self { name: name.to_string(), nominal_hz, deviation_hz: 0.0, last: None, previous: None, injected: None }
+    }
+
+    pub fn nominal_hz(&self) -> f64 {
+        self.nominal_hz
+    }
+
+    /// Where the random walk stands, for a state snapshot.
+    pub fn walk(&self) -> WalkState {
+        WalkState { deviation_hz: self.deviation_hz, last: self.last, previous: self.previous }
+    }
+
+    /// Resume the random walk from `walk`.
+    pub fn restore_walk(&mut self, walk: WalkState) {
+        self.deviation_hz = walk.deviation_hz;
+        self.last         = walk.last;
+        self.previous     = walk.previous;
+    }
+
+    /// Force `disturbance` from `at` until `until`, replacing any earlier one.
+    pub fn inject(&mut self, disturbance: Disturbance, at: DateTime<Utc>, until: Instant) {
+        self.injected = Some(Injected { disturbance, start: at, until });
+    }
+
+    /// Frequency (Hz) and RoCoF (Hz/s) at `at`. Plants updating at the same
+    /// instant read the same values; a later instant moves the walk on and
+    /// the RoCoF is the slope from the previous instant. A clock going
+    /// backwards starts the walk over.
+    pub fn sample(&mut self, at: DateTime<Utc>, seed: u64) -> (f64, f64) {
+        match self.last {
+            Some((last, _)) if at > last => {
+                let dt_s = (at - last).num_milliseconds() as f64 / 1000.0;
+                let decay = (-REVERSION_PER_S * dt_s).exp();
+                let spread = VOLATILITY_HZ * ((1.0 - decay * decay) / (2.0 * REVERSION_PER_S)).sqrt();
+                let step = spread * gaussian(&self.name, seed, at.timestamp() as u64, WALK);
+                self.deviation_hz = (self.deviation_hz * decay + step).clamp(-WALK_BAND_HZ, WALK_BAND_HZ);
+                self.previous = self.last;
+            }
+            Some((last, _)) if at == last => {}
+            _ => {
+                self.deviation_hz = 0.0;
+                self.previous = None;
+            }
+        }
+
+        let mut frequency_hz = self.nominal_hz + self.deviation_hz + self.excursion_hz(at, seed);
+        if let Some(i) = self.injected.filter(|i| i.until > Instant::now()) {
+            let ramp_s = (at - i.start).num_milliseconds().max(0) as f64 / 1000.0;
+            frequency_hz = i.disturbance.frequency_hz.unwrap_or(frequency_hz)
+                + i.disturbance.step_hz
+                + i.disturbance.rocof_hz_s * ramp_s;
+        }
+        let frequency_hz = frequency_hz.clamp(self.nominal_hz - MAX_DEVIATION_HZ, self.nominal_hz + MAX_DEVIATION_HZ);
+        self.last = Some((at, frequency_hz));
+
+        let rocof_hz_s = self.previous.map_or(0.0, |(t, f)| {
+            let dt_s = ((at - t).num_milliseconds() as f64 / 1000.0).max(ROCOF_WINDOW_S);
+            ((frequency_hz - f) / dt_s).clamp(-MAX_ROCOF_HZ_S, MAX_ROCOF_HZ_S)
+        });
+        (frequency_hz, rocof_hz_s)
+    }
+
+    /// Over- or under-frequency excursion of the window holding `at`:
+    /// 0.55–0.80 Hz off the nominal, past the default f> / f< limits.
+    fn excursion_hz(&self, at: DateTime<Utc>, seed: u64) -> f64 {
+        let epoch = at.timestamp() as u64 / EXCURSION_EPOCH_S;
+        let magnitude = 0.55 + uniform(&self.name, seed, epoch, MAGNITUDE) * 0.25;
+        if uniform(&self.name, seed, epoch, OVER) < P_EXCURSION {
+            magnitude
+        } else if uniform(&self.name, seed, epoch, UNDER) < P_EXCURSION {
+            -magnitude
+        } else {
+            0.0
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn start() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 6, 21, 0, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn the_walk_stays_near_nominal_and_replays_under_a_seed() {
+        let day = |seed: u64| {
+            let mut grid = GridFrequency::new("continental", 50.0);
+            (0..17_280i64)
+                .map(|i| grid.sample(start() + chrono::Duration::seconds(5 * i), seed))
+                .collect::<Vec<_>>()
+        };
+        let samples = day(7);
+        assert_eq!(samples, day(7));
+        assert_ne!(samples, day(8));
+
+        // Outside the excursions the grid sits inside the normal band
+        let normal: Vec<f64> = samples.iter().map(|(f, _)| *f).filter(|f| (f - 50.0).abs() <= WALK_BAND_HZ).collect();
+        assert!(normal.len() > samples.len() * 9 / 10);
+        let mean = normal.iter().sum::<f64>() / normal.len() as f64;
+        assert!((mean - 50.0).abs() < 0.02, "{mean} Hz");
+        assert!(normal.iter().any(|f| (f - 50.0).abs() > 0.01), "the frequency does not move");
+
+        // RoCoF is the slope between consecutive samples
+        for pair in samples.windows(2) {
+            let ((f0, _), (f1, rocof)) = (pair[0], pair[1]);
+            assert!((rocof - (f1 - f0) / 5.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn injected_steps_and_ramps_ride_on_the_walk_within_bounds() {
+        let mut grid = GridFrequency::new("north_america", 60.0);
+        let until = Instant::now() + std::time::Duration::from_secs(60);
+        let at = |s: i64| start() + chrono::Duration::seconds(s);
+        let (before, _) = grid.sample(at(0), 1);
+        assert!((before - 60.0).abs() < 1.0, "{before} Hz");
+
+        // Every reader of the same instant gets the same sample
+        grid.inject(Disturbance { step_hz: -0.3, rocof_hz_s: -0.5, ..Disturbance::default() }, at(0), until);
+        let (stepped, _) = grid.sample(at(0), 1);
+        assert!((stepped - (before - 0.3)).abs() < 1e-12);
+        let (ramped, rocof) = grid.sample(at(2), 1);
+        assert_eq!(grid.sample(at(2), 1), (ramped, rocof));
+        assert!((rocof + 0.5).abs() < 0.05, "{rocof} Hz/s");
+
+        // A long ramp stops at the bounds
+        let (floor, _) = grid.sample(at(60), 1);
+        assert_eq!(floor, 60.0 - MAX_DEVIATION_HZ);
+
+        // A forced frequency replaces the walk; the clock going back starts over
+        grid.inject(Disturbance::to(59.0), at(60), until);
+        assert_eq!(grid.sample(at(65), 1), (59.0, (59.0 - floor) / 5.0));
+        assert_eq!(grid.sample(at(0), 1), (59.0, 0.0));
+    }
+}
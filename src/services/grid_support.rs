@@ -0,0 +1,89 @@
+use crate::config::{FreqWattConfig, VoltVarConfig};
+
+// ─── P(f) droop ──────────────────────────────────────────────
+/// Over-frequency active power droop: above the threshold the output falls
+/// linearly from its level at the crossing.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FreqWatt {
+    pub threshold_hz: f64,
+    pub droop_pct: f64,
+    /// Nominal grid frequency the droop is expressed against (Hz)
+    pub nominal_hz: f64,
+}
+
+impl FreqWatt {
+    pub fn for_config(config: &FreqWattConfig, nominal_hz: f64) -> Self {
+        Self { threshold_hz: config.threshold_hz, droop_pct: config.droop_pct.max(0.1), nominal_hz }
+    }
+
+    /// Active power (kW) allowed at `frequency_hz` for a plant that was
+    /// producing `reference_kw` when the frequency crossed the threshold.
+    pub fn limit_kw(&self, frequency_hz: f64, reference_kw: f64) -> f64 {
+        let excess_hz = (frequency_hz - self.threshold_hz).max(0.0);
+        reference_kw * (1.0 - excess_hz / (self.nominal_hz * self.droop_pct / 100.0)).max(0.0)
+    }
+}
+
+// ─── Q(U) curve ──────────────────────────────────────────────
+/// Reactive power against grid voltage, interpolated linearly between points
+/// and held flat beyond the last one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct VoltVarCurve {
+    /// (voltage p.u., Q % of the kVA rating) sorted by voltage
+    points: Vec<(f64, f64)>,
+}
+
+impl VoltVarCurve {
+    pub fn for_config(config: &VoltVarConfig) -> Self {
+        let mut points: Vec<(f64, f64)> = config.points.iter()
+            .filter(|[v, q]| v.is_finite() && q.is_finite())
+            .map(|[v, q]| (*v, q.clamp(-100.0, 100.0)))
+            .collect();
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+        if points.is_empty() {
+            return Self::default();
+        }
+        Self { points }
+    }
+
+    /// Reactive power (% of the kVA rating, + = over-excited) at `voltage_pu`.
+    pub fn q_pct(&self, voltage_pu: f64) -> f64 {
+        let (first, last) = (self.points[0], self.points[self.points.len() - 1]);
+        if voltage_pu <= first.0 {
+            return first.1;
+        }
+        if voltage_pu >= last.0 {
+            return last.1;
+        }
+        let upper = self.points.iter().position(|p| p.0 >= voltage_pu).unwrap_or(self.points.len() - 1);
+        let ((v0, q0), (v1, q1)) = (self.points[upper - 1], self.points[upper]);
+        q0 + (q1 - q0) * (voltage_pu - v0) / (v1 - v0)
+    }
+}
+
+impl Default for VoltVarCurve {
+    fn default() -> Self {
+        Self::for_config(&VoltVarConfig::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn droop_and_volt_var_follow_their_curves() {
+        let droop = FreqWatt::for_config(&FreqWattConfig::default(), 50.0);
+        assert_eq!(droop.limit_kw(50.1, 800.0), 800.0);
+        // 5 % droop: 2.5 Hz above the threshold takes the output to zero
+        assert!((droop.limit_kw(50.6, 800.0) - 800.0 * (1.0 - 0.4 / 2.5)).abs() < 1e-9);
+        assert_eq!(droop.limit_kw(53.0, 800.0), 0.0);
+
+        let curve = VoltVarCurve::default();
+        assert_eq!(curve.q_pct(1.0), 0.0);
+        assert!((curve.q_pct(0.95) - 22.0).abs() < 1e-9);
+        assert!((curve.q_pct(1.05) + 22.0).abs() < 1e-9);
+        assert_eq!(curve.q_pct(0.80), 44.0);
+        assert_eq!(curve.q_pct(1.20), -44.0);
+    }
+}
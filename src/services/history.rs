@@ -0,0 +1,525 @@
+use std::collections::{BTreeMap, VecDeque};
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+use crate::models::power::PlantData;
+
+/// Longest retention (h): a week of minutes is ~10k points per plant
+pub const MAX_RETENTION_H: f64 = 168.0;
+
+/// Most buckets a fleet history may return over all its series: a day of
+/// 15-minute buckets for 300 plants
+pub const MAX_FLEET_POINTS: usize = 30_000;
+
+// ─── History points ──────────────────────────────────────────
+/// Telemetry averaged over one bucket, stamped with the bucket start.
+#[derive(Clone, Debug, PartialEq, Serialize, ToSchema)]
+pub struct HistoryPoint {
+    #[schema(value_type = String)]
+    pub timestamp:           DateTime<Utc>,
+    /// Mean AC power (kW)
+    pub power_kw:            f64,
+    /// Mean plane-of-array irradiance (W/m²)
+    pub poa_irradiance_w_m2: f64,
+    /// Mean cell temperature (°C)
+    pub temperature_c:       f64,
+    /// Mean ambient temperature (°C)
+    pub ambient_temp_c:      f64,
+    /// Mean inverter temperature (°C)
+    pub inverter_temp_c:     f64,
+    /// Energy produced inside the bucket, as booked on the counters (kWh)
+    pub energy_kwh:          f64,
+    /// Energy produced inside the bucket before the revenue meter's error
+    /// (kWh); equal to `energy_kwh` for a plant without `meter.accuracy_class`
+    pub true_energy_kwh:     f64,
+    /// Energy held back by the export cap inside the bucket (kWh)
+    pub curtailed_energy_kwh: f64,
+    /// Today's energy counter at the end of the bucket (kWh)
+    pub daily_energy_kwh:    f64,
+}
+
+/// Bucket width of a history query.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Deserialize, Serialize, ToSchema)]
+pub enum Resolution {
+    #[default]
+    #[serde(rename = "1m")]
+    OneMinute,
+    #[serde(rename = "5m")]
+    FiveMinutes,
+    #[serde(rename = "15m")]
+    FifteenMinutes,
+    #[serde(rename = "1h")]
+    OneHour,
+}
+
+impl Resolution {
+    pub fn seconds(self) -> i64 {
+        match self {
+            Resolution::OneMinute   => 60,
+            Resolution::FiveMinutes => 300,
+            Resolution::FifteenMinutes => 900,
+            Resolution::OneHour     => 3600,
+        }
+    }
+
+    /// Buckets `[from, to)` spans, counting the partial ones at either end.
+    pub fn buckets_in(self, from: DateTime<Utc>, to: DateTime<Utc>) -> usize {
+        let first = self.bucket_of(from).timestamp();
+        ((to.timestamp() - first).max(0) as usize).div_ceil(self.seconds() as usize)
+    }
+
+    fn bucket_of(self, at: DateTime<Utc>) -> DateTime<Utc> {
+        let secs = at.timestamp();
+        DateTime::from_timestamp(secs - secs.rem_euclid(self.seconds()), 0).unwrap_or(at)
+    }
+}
+
+/// Running sums of the minute being filled.
+#[derive(Clone, Debug)]
+struct OpenMinute {
+    start:        DateTime<Utc>,
+    samples:      u32,
+    power_kw:     f64,
+    poa_w_m2:     f64,
+    cell_c:       f64,
+    ambient_c:    f64,
+    inverter_c:   f64,
+    energy_kwh:   f64,
+    true_kwh:     f64,
+    curtailed_kwh: f64,
+    daily_kwh:    f64,
+}
+
+impl OpenMinute {
+    fn point(&self) -> HistoryPoint {
+        let n = self.samples.max(1) as f64;
+        HistoryPoint {
+            timestamp:           self.start,
+            power_kw:            self.power_kw / n,
+            poa_irradiance_w_m2: self.poa_w_m2 / n,
+            temperature_c:       self.cell_c / n,
+            ambient_temp_c:      self.ambient_c / n,
+            inverter_temp_c:     self.inverter_c / n,
+            energy_kwh:          self.energy_kwh,
+            true_energy_kwh:     self.true_kwh,
+            curtailed_energy_kwh: self.curtailed_kwh,
+            daily_energy_kwh:    self.daily_kwh,
+        }
+    }
+}
+
+// ─── Per-plant ring buffer ───────────────────────────────────
+/// One plant's telemetry at 1-minute resolution, folded from the 5 s updates
+/// and capped at `capacity` minutes whatever the uptime.
+#[derive(Clone, Debug)]
+pub struct History {
+    capacity:   usize,
+    minutes:    VecDeque<HistoryPoint>,
+    open:       Option<OpenMinute>,
+    /// Lifetime counter at the previous update, to book each update's energy
+    last_total: Option<f64>,
+    /// Today's curtailed energy at the previous update
+    last_curtailed: Option<f64>,
+}
+
+impl History {
+    pub fn new(retention_h: f64) -> Self {
+        let capacity = (retention_h.clamp(1.0 / 60.0, MAX_RETENTION_H) * 60.0).round() as usize;
+        Self {
+            capacity, minutes: VecDeque::with_capacity(capacity), open: None, last_total: None, last_curtailed: None,
+        }
+    }
+
+    /// How far back the buffer reaches once full.
+    pub fn retention(&self) -> chrono::Duration {
+        chrono::Duration::minutes(self.capacity as i64)
+    }
+
+    /// Fold one update into its minute, closing the previous one when the
+    /// minute has turned; `true_kwh` is the update's energy before the meter.
+    pub fn record(&mut self, at: DateTime<Utc>, data: &PlantData, true_kwh: f64) {
+        let start = Resolution::OneMinute.bucket_of(at);
+        if self.open.as_ref().is_some_and(|m| m.start != start) {
+            self.close();
+        }
+        let energy = self.last_total.map_or(0.0, |t| (data.total_energy_kwh - t).max(0.0));
+        self.last_total = Some(data.total_energy_kwh);
+        // The curtailment counter is a daily one: after midnight it restarts from 0
+        let curtailed = self.last_curtailed.map_or(0.0, |c| match data.curtailed_energy_kwh - c {
+            delta if delta >= 0.0 => delta,
+            _ => data.curtailed_energy_kwh,
+        });
+        self.last_curtailed = Some(data.curtailed_energy_kwh);
+        let m = self.open.get_or_insert_with(|| OpenMinute {
+            start, samples: 0, power_kw: 0.0, poa_w_m2: 0.0, cell_c: 0.0, ambient_c: 0.0,
+            inverter_c: 0.0, energy_kwh: 0.0, true_kwh: 0.0, curtailed_kwh: 0.0, daily_kwh: 0.0,
+        });
+        m.samples    += 1;
+        m.power_kw   += data.power_kw;
+        m.poa_w_m2   += data.poa_irradiance_w_m2;
+        m.cell_c     += data.temperature_c;
+        m.ambient_c  += data.ambient_temp_c;
+        m.inverter_c += data.inverter_temp_c;
+        m.energy_kwh += energy;
+        m.true_kwh   += true_kwh;
+        m.curtailed_kwh += curtailed;
+        m.daily_kwh   = data.daily_energy_kwh;
+    }
+
+    fn close(&mut self) {
+        if let Some(m) = self.open.take() {
+            self.minutes.push_back(m.point());
+            while self.minutes.len() > self.capacity {
+                self.minutes.pop_front();
+            }
+        }
+    }
+
+    /// Minutes starting within `[from, to)`, the one still filling included,
+    /// downsampled to `resolution`.
+    pub fn points(&self, from: DateTime<Utc>, to: DateTime<Utc>, resolution: Resolution) -> Vec<HistoryPoint> {
+        let open = self.open.as_ref().map(OpenMinute::point);
+        let minutes = self.minutes.iter().chain(open.as_ref())
+            .filter(|p| p.timestamp >= from && p.timestamp < to);
+        downsample(minutes, resolution)
+    }
+}
+
+/// Average minutes into `resolution` buckets: means for the instantaneous
+/// values, sums for the energy, the last daily counter of each bucket.
+pub fn downsample<'a>(minutes: impl Iterator<Item = &'a HistoryPoint>, resolution: Resolution) -> Vec<HistoryPoint> {
+    let mut buckets: BTreeMap<DateTime<Utc>, (HistoryPoint, u32)> = BTreeMap::new();
+    for p in minutes {
+        let start = resolution.bucket_of(p.timestamp);
+        let (acc, n) = buckets.entry(start).or_insert_with(|| (HistoryPoint { timestamp: start, ..zero() }, 0));
+        acc.power_kw            += p.power_kw;
+        acc.poa_irradiance_w_m2 += p.poa_irradiance_w_m2;
+        acc.temperature_c       += p.temperature_c;
+        acc.ambient_temp_c      += p.ambient_temp_c;
+        acc.inverter_temp_c     += p.inverter_temp_c;
+        acc.energy_kwh          += p.energy_kwh;
+        acc.true_energy_kwh     += p.true_energy_kwh;
+        acc.curtailed_energy_kwh += p.curtailed_energy_kwh;
+        acc.daily_energy_kwh     = p.daily_energy_kwh;
+        *n += 1;
+    }
+    buckets.into_values().map(|(p, n)| mean_of(p, n)).collect()
+}
+
+/// One bucket of a fleet series, with how many of its plants it covers.
+#[derive(Clone, Debug, PartialEq, Serialize, ToSchema)]
+pub struct FleetHistoryPoint {
+    #[serde(flatten)]
+    pub point:            HistoryPoint,
+    /// Plants with samples in the bucket
+    pub plants_reporting: usize,
+    /// Plants in the series
+    pub plants_total:     usize,
+    /// Some plants have no samples in the bucket: its power and energy
+    /// under-report the series
+    pub partial:          bool,
+}
+
+/// Fleet series: power and energy add up across plants, irradiance and
+/// temperatures average over the plants reporting in each bucket, which is
+/// flagged partial unless every plant does.
+pub fn aggregate(plants: &[Vec<HistoryPoint>]) -> Vec<FleetHistoryPoint> {
+    let mut buckets: BTreeMap<DateTime<Utc>, (HistoryPoint, u32)> = BTreeMap::new();
+    for p in plants.iter().flatten() {
+        let (acc, n) = buckets.entry(p.timestamp).or_insert_with(|| (HistoryPoint { timestamp: p.timestamp, ..zero() }, 0));
+        acc.power_kw            += p.power_kw;
+        acc.poa_irradiance_w_m2 += p.poa_irradiance_w_m2;
+        acc.temperature_c       += p.temperature_c;
+        acc.ambient_temp_c      += p.ambient_temp_c;
+        acc.inverter_temp_c     += p.inverter_temp_c;
+        acc.energy_kwh          += p.energy_kwh;
+        acc.true_energy_kwh     += p.true_energy_kwh;
+        acc.curtailed_energy_kwh += p.curtailed_energy_kwh;
+        acc.daily_energy_kwh    += p.daily_energy_kwh;
+        *n += 1;
+    }
+    buckets.into_values().map(|(p, n)| {
+        let mean = mean_of(p.clone(), n);
+        FleetHistoryPoint {
+            point:            HistoryPoint { power_kw: p.power_kw, ..mean },
+            plants_reporting: n as usize,
+            plants_total:     plants.len(),
+            partial:          (n as usize) < plants.len(),
+        }
+    }).collect()
+}
+
+fn zero() -> HistoryPoint {
+    HistoryPoint {
+        timestamp: DateTime::UNIX_EPOCH, power_kw: 0.0, poa_irradiance_w_m2: 0.0, temperature_c: 0.0,
+        ambient_temp_c: 0.0, inverter_temp_c: 0.0, energy_kwh: 0.0, true_energy_kwh: 0.0, curtailed_energy_kwh: 0.0,
+        daily_energy_kwh: 0.0,
+    }
+}
+
+/// Divide the instantaneous sums of `p` by `n`; energies stay as they are.
+fn mean_of(p: HistoryPoint, n: u32) -> HistoryPoint {
+    let n = n.max(1) as f64;
+    HistoryPoint {
+        power_kw:            p.power_kw / n,
+        poa_irradiance_w_m2: p.poa_irradiance_w_m2 / n,
+        temperature_c:       p.temperature_c / n,
+        ambient_temp_c:      p.ambient_temp_c / n,
+        inverter_temp_c:     p.inverter_temp_c / n,
+        ..p
+    }
+}
+
+// ─── Export ──────────────────────────────────────────────────
+/// Columns a history export can select, in the order of `HistoryPoint`.
+pub const COLUMNS: &[(&str, fn(&HistoryPoint) -> serde_json::Value)] = &[
+    ("timestamp",           |p| p.timestamp.to_rfc3339_opts(SecondsFormat::Secs, true).into()),
+    ("power_kw",            |p| p.power_kw.into()),
+    ("poa_irradiance_w_m2", |p| p.poa_irradiance_w_m2.into()),
+    ("temperature_c",       |p| p.temperature_c.into()),
+    ("ambient_temp_c",      |p| p.ambient_temp_c.into()),
+    ("inverter_temp_c",     |p| p.inverter_temp_c.into()),
+    ("energy_kwh",          |p| p.energy_kwh.into()),
+    ("true_energy_kwh",     |p| p.true_energy_kwh.into()),
+    ("curtailed_energy_kwh", |p| p.curtailed_energy_kwh.into()),
+    ("daily_energy_kwh",    |p| p.daily_energy_kwh.into()),
+];
+
+/// Columns of an export without a `fields` selection
+pub const DEFAULT_COLUMNS: &[&str] = &["timestamp", "power_kw", "poa_irradiance_w_m2", "temperature_c", "daily_energy_kwh"];
+
+/// Export rows per chunk of a streamed CSV body
+const CSV_ROWS_PER_CHUNK: usize = 256;
+
+/// Columns picked for an export, in the order asked for.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Columns(Vec<usize>);
+
+impl Columns {
+    /// Comma-separated column names; None = `DEFAULT_COLUMNS`.
+    pub fn parse(fields: Option<&str>) -> Result<Self, String> {
+        let names: Vec<&str> = match fields {
+            Some(fields) => fields.split(',').map(str::trim).filter(|f| !f.is_empty()).collect(),
+            None => DEFAULT_COLUMNS.to_vec(),
+        };
+        if names.is_empty() {
+            return Err("fields selects no column".to_string());
+        }
+        names.into_iter().map(|name| {
+            COLUMNS.iter().position(|(column, _)| *column == name).ok_or_else(|| format!(
+                "unknown history field '{}': expected any of {}",
+                name, COLUMNS.iter().map(|(c, _)| *c).collect::<Vec<_>>().join(", "),
+            ))
+        }).collect::<Result<_, _>>().map(Self)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &'static str> + '_ {
+        self.0.iter().map(|&i| COLUMNS[i].0)
+    }
+
+    /// `point` as a JSON object of the selected columns.
+    pub fn json(&self, point: &HistoryPoint) -> serde_json::Map<String, serde_json::Value> {
+        self.0.iter().map(|&i| (COLUMNS[i].0.to_string(), (COLUMNS[i].1)(point))).collect()
+    }
+
+    fn csv_line(fields: impl Iterator<Item = String>) -> String {
+        let mut line = fields.map(|f| csv_field(&f)).collect::<Vec<_>>().join(",");
+        line.push_str("\r\n");
+        line
+    }
+
+    pub fn csv_header(&self) -> String {
+        Self::csv_line(self.names().map(str::to_string))
+    }
+
+    pub fn csv_row(&self, point: &HistoryPoint) -> String {
+        Self::csv_line(self.0.iter().map(|&i| match (COLUMNS[i].1)(point) {
+            serde_json::Value::String(s) => s,
+            serde_json::Value::Null => String::new(),
+            value => value.to_string(),
+        }))
+    }
+}
+
+/// One CSV field, quoted (RFC 4180) when it holds a comma, a quote or a
+/// line break.
+pub fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// CSV body of `points` in chunks of rows, the header first, for a
+/// response that streams instead of holding the whole text.
+pub fn csv_chunks(points: Vec<HistoryPoint>, columns: Columns) -> impl Iterator<Item = String> {
+    let header = columns.csv_header();
+    let mut rows = points.into_iter();
+    std::iter::once(header).chain(std::iter::from_fn(move || {
+        let chunk: String = rows.by_ref().take(CSV_ROWS_PER_CHUNK).map(|p| columns.csv_row(&p)).collect();
+        (!chunk.is_empty()).then_some(chunk)
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(h: u32, m: u32, s: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 6, 21, h, m, s).unwrap()
+    }
+
+    /// Feed 5 s updates from `start` for `minutes`, at `power_kw` and its
+    /// energy on the counters.
+    fn feed(history: &mut History, start: DateTime<Utc>, minutes: i64, power_kw: f64, data: &mut PlantData) {
+        for step in 0..minutes * 12 {
+            data.power_kw = power_kw;
+            data.total_energy_kwh += power_kw * 5.0 / 3600.0;
+            data.daily_energy_kwh += power_kw * 5.0 / 3600.0;
+            history.record(start + chrono::Duration::seconds(step * 5), data, power_kw * 5.0 / 3600.0);
+        }
+    }
+
+    #[test]
+    fn five_second_updates_fold_into_minutes() {
+        let mut history = History::new(24.0);
+        let mut data = PlantData { poa_irradiance_w_m2: 800.0, ..PlantData::default() };
+        feed(&mut history, at(10, 0, 0), 2, 60.0, &mut data);
+        feed(&mut history, at(10, 2, 0), 1, 120.0, &mut data);
+
+        let points = history.points(at(0, 0, 0), at(23, 0, 0), Resolution::OneMinute);
+        assert_eq!(points.iter().map(|p| p.timestamp).collect::<Vec<_>>(), vec![at(10, 0, 0), at(10, 1, 0), at(10, 2, 0)]);
+        assert_eq!(points[1].power_kw, 60.0);
+        assert_eq!(points[2].power_kw, 120.0);
+        assert!((points[1].energy_kwh - 1.0).abs() < 1e-9, "{}", points[1].energy_kwh);
+        // The first update has no previous counter to book from
+        assert!((points[0].energy_kwh - 1.0 * 11.0 / 12.0).abs() < 1e-9);
+        assert!((points[2].daily_energy_kwh - data.daily_energy_kwh).abs() < 1e-9);
+        assert_eq!(points[0].poa_irradiance_w_m2, 800.0);
+
+        // The 5-minute bucket averages the power and adds up the energy
+        let five = history.points(at(0, 0, 0), at(23, 0, 0), Resolution::FiveMinutes);
+        assert_eq!(five.len(), 1);
+        assert!((five[0].power_kw - 80.0).abs() < 1e-9);
+        assert!((five[0].energy_kwh - points.iter().map(|p| p.energy_kwh).sum::<f64>()).abs() < 1e-9);
+
+        // The window filters on the bucket start
+        assert_eq!(history.points(at(10, 1, 0), at(10, 2, 0), Resolution::OneMinute).len(), 1);
+    }
+
+    #[test]
+    fn memory_stays_bounded_by_the_retention() {
+        let mut history = History::new(1.0);
+        let mut data = PlantData::default();
+        feed(&mut history, at(6, 0, 0), 180, 10.0, &mut data);
+        let points = history.points(DateTime::UNIX_EPOCH, at(23, 0, 0), Resolution::OneMinute);
+        // 60 closed minutes plus the one still filling
+        assert_eq!(points.len(), 61);
+        assert_eq!(points[0].timestamp, at(7, 59, 0));
+        assert_eq!(History::new(1e6).capacity, (MAX_RETENTION_H * 60.0) as usize);
+    }
+
+    /// Split CSV text back into records, honouring RFC 4180 quoting.
+    fn parse_csv(text: &str) -> Vec<Vec<String>> {
+        let (mut records, mut record, mut field, mut quoted) = (Vec::new(), Vec::new(), String::new(), false);
+        let mut chars = text.chars().peekable();
+        while let Some(c) = chars.next() {
+            match (c, quoted) {
+                ('"', true) if chars.peek() == Some(&'"') => { chars.next(); field.push('"'); }
+                ('"', _) => quoted = !quoted,
+                (',', false) => record.push(std::mem::take(&mut field)),
+                ('\r', false) => {}
+                ('\n', false) => {
+                    record.push(std::mem::take(&mut field));
+                    records.push(std::mem::take(&mut record));
+                }
+                (c, _) => field.push(c),
+            }
+        }
+        records
+    }
+
+    #[test]
+    fn a_csv_export_parses_back_to_the_selected_columns() {
+        let mut history = History::new(24.0);
+        let mut data = PlantData { poa_irradiance_w_m2: 800.0, temperature_c: 41.5, ..PlantData::default() };
+        feed(&mut history, at(6, 0, 0), 300, 60.0, &mut data);
+        let points = history.points(at(0, 0, 0), at(23, 0, 0), Resolution::OneMinute);
+        assert_eq!(points.len(), 300);
+
+        let chunks: Vec<String> = csv_chunks(points.clone(), Columns::parse(None).unwrap()).collect();
+        assert_eq!(chunks.len(), 1 + points.len().div_ceil(CSV_ROWS_PER_CHUNK), "streamed in chunks of rows");
+        let records = parse_csv(&chunks.concat());
+        assert_eq!(records[0], DEFAULT_COLUMNS);
+        assert_eq!(records.len(), 1 + points.len());
+        assert_eq!(records[1][0], "2025-06-21T06:00:00Z");
+        assert_eq!(records[1][3].parse::<f64>().unwrap(), 41.5);
+        assert_eq!(records[300][4].parse::<f64>().unwrap(), points[299].daily_energy_kwh);
+
+        // The selection keeps the order asked for
+        let columns = Columns::parse(Some("energy_kwh, timestamp,true_energy_kwh")).unwrap();
+        let records = parse_csv(&csv_chunks(points.clone(), columns.clone()).collect::<String>());
+        assert_eq!(records[0], ["energy_kwh", "timestamp", "true_energy_kwh"]);
+        assert!(records.iter().all(|r| r.len() == 3));
+        let json = columns.json(&points[0]);
+        assert_eq!(json.len(), 3);
+        assert_eq!(json["timestamp"], "2025-06-21T06:00:00Z");
+        assert!(Columns::parse(Some("power_kw,voltage")).unwrap_err().contains("'voltage'"));
+        assert!(Columns::parse(Some(" , ")).is_err());
+
+        // RFC 4180 quoting
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("say \"hi\"\r\n"), "\"say \"\"hi\"\"\r\n\"");
+        assert_eq!(parse_csv(&format!("{},{}\r\n", csv_field("a,\"b\""), csv_field("c"))), [["a,\"b\"", "c"]]);
+    }
+
+    #[test]
+    fn the_fleet_sums_power_and_averages_the_weather() {
+        let point = |power_kw, poa| HistoryPoint {
+            timestamp: at(12, 0, 0), power_kw, poa_irradiance_w_m2: poa, energy_kwh: power_kw / 60.0, ..zero()
+        };
+        let fleet = aggregate(&[vec![point(100.0, 900.0)], vec![point(50.0, 700.0)]]);
+        assert_eq!(fleet.len(), 1);
+        assert_eq!((fleet[0].point.power_kw, fleet[0].point.poa_irradiance_w_m2), (150.0, 800.0));
+        assert!((fleet[0].point.energy_kwh - 2.5).abs() < 1e-9);
+        assert!(!fleet[0].partial);
+    }
+
+    #[test]
+    fn three_plants_sum_to_the_fleet_and_gaps_are_flagged() {
+        let mut histories: Vec<History> = (0..3).map(|_| History::new(24.0)).collect();
+        for (i, history) in histories.iter_mut().enumerate() {
+            let mut data = PlantData::default();
+            // plant 3 comes online at 10:30, half way into the day's chart
+            let (start, minutes) = if i == 2 { (at(10, 30, 0), 90) } else { (at(10, 0, 0), 120) };
+            feed(history, start, minutes, 40.0 * (i + 1) as f64, &mut data);
+        }
+        let series: Vec<Vec<HistoryPoint>> = histories.iter()
+            .map(|h| h.points(at(10, 0, 0), at(12, 0, 0), Resolution::FifteenMinutes))
+            .collect();
+        let fleet = aggregate(&series);
+        assert_eq!(fleet.len(), 8);
+
+        for bucket in &fleet {
+            let plants: Vec<&HistoryPoint> = series.iter().flatten().filter(|p| p.timestamp == bucket.point.timestamp).collect();
+            let power: f64 = plants.iter().map(|p| p.power_kw).sum();
+            let energy: f64 = plants.iter().map(|p| p.energy_kwh).sum();
+            assert!((bucket.point.power_kw - power).abs() < 1e-9, "{:?}", bucket.point.timestamp);
+            assert!((bucket.point.energy_kwh - energy).abs() < 1e-9, "{:?}", bucket.point.timestamp);
+            assert_eq!((bucket.plants_reporting, bucket.plants_total), (plants.len(), 3));
+        }
+        // Before 10:30 only two plants report, and say so
+        assert!(fleet[..2].iter().all(|b| b.partial && b.plants_reporting == 2));
+        assert!(fleet[2..].iter().all(|b| !b.partial));
+        assert!((fleet[2].point.power_kw - 240.0).abs() < 1e-9);
+
+        assert_eq!(Resolution::FifteenMinutes.buckets_in(at(10, 0, 0), at(12, 0, 0)), 8);
+        assert_eq!(Resolution::FifteenMinutes.buckets_in(at(10, 5, 0), at(12, 0, 0)), 8);
+        assert_eq!(Resolution::OneHour.buckets_in(at(0, 0, 0), at(23, 59, 0)), 24);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::config::{EfficiencyCurveConfig, EfficiencyPreset};
+
+/// Below this load the inverter's own consumption dominates and the
+/// efficiency falls linearly to zero.
+pub const LOW_LOAD_CLIFF: f64 = 0.02;
+
+/// European efficiency weights (load fraction, weight), EN 50530.
+pub const EURO_WEIGHTS: [(f64, f64); 6] = [
+    (0.05, 0.03), (0.10, 0.06), (0.20, 0.13), (0.30, 0.10), (0.50, 0.48), (1.00, 0.20),
+];
+
+/// California Energy Commission weights (load fraction, weight).
+pub const CEC_WEIGHTS: [(f64, f64); 6] = [
+    (0.10, 0.04), (0.20, 0.05), (0.30, 0.12), (0.50, 0.21), (0.75, 0.53), (1.00, 0.05),
+];
+
+/// Which weighted efficiency a datasheet quotes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Weighting {
+    Euro,
+    Cec,
+}
+
+impl Weighting {
+    pub const fn weights(self) -> &'static [(f64, f64); 6] {
+        match self {
+            Weighting::Euro => &EURO_WEIGHTS,
+            Weighting::Cec  => &CEC_WEIGHTS,
+        }
+    }
+}
+
+impl EfficiencyPreset {
+    /// Weighted efficiency on the datasheet this preset is named after.
+    pub const fn nameplate(self) -> (Weighting, f64) {
+        match self {
+            EfficiencyPreset::Euro98 => (Weighting::Euro, 0.980),
+            EfficiencyPreset::Cec975 => (Weighting::Cec, 0.975),
+        }
+    }
+
+    /// Efficiency at each load fraction, from the cliff to full load.
+    const fn points(self) -> &'static [(f64, f64)] {
+        match self {
+            EfficiencyPreset::Euro98 => &[
+                (0.02, 0.900), (0.05, 0.960), (0.10, 0.975), (0.20, 0.981),
+                (0.30, 0.982), (0.50, 0.982), (0.75, 0.980), (1.00, 0.978),
+            ],
+            EfficiencyPreset::Cec975 => &[
+                (0.02, 0.880), (0.05, 0.945), (0.10, 0.962), (0.20, 0.972),
+                (0.30, 0.975), (0.50, 0.977), (0.75, 0.976), (1.00, 0.973),
+            ],
+        }
+    }
+}
+
+// ─── Efficiency curve ────────────────────────────────────────
+/// Inverter efficiency against load, interpolated linearly between points
+/// and held flat beyond the last one.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EfficiencyCurve {
+    /// (load fraction, efficiency) sorted by load
+    points: Vec<(f64, f64)>,
+}
+
+impl EfficiencyCurve {
+    pub fn preset(preset: EfficiencyPreset) -> Self {
+        Self { points: preset.points().to_vec() }
+    }
+
+    /// Curve of an inverter config; points are sorted and clamped to [0, 1].
+    pub fn for_config(config: &EfficiencyCurveConfig) -> Self {
+        match config {
+            EfficiencyCurveConfig::Preset(preset) => Self::preset(*preset),
+            EfficiencyCurveConfig::Points(points) => {
+                let mut points: Vec<(f64, f64)> = points.iter()
+                    .filter(|[load, eff]| load.is_finite() && eff.is_finite())
+                    .map(|[load, eff]| (load.max(0.0), eff.clamp(0.0, 1.0)))
+                    .collect();
+                points.sort_by(|a, b| a.0.total_cmp(&b.0));
+                if points.is_empty() {
+                    return Self::default();
+                }
+                Self { points }
+            }
+        }
+    }
+
+    /// Efficiency [0..1] at `load` (DC power over the AC rating).
+    pub fn efficiency(&self, load: f64) -> f64 {
+        if load <= 0.0 {
+            return 0.0;
+        }
+        if load < LOW_LOAD_CLIFF {
+            return self.interpolate(LOW_LOAD_CLIFF) * load / LOW_LOAD_CLIFF;
+        }
+        self.interpolate(load)
+    }
+
+    fn interpolate(&self, load: f64) -> f64 {
+        let (first, last) = (self.points[0], self.points[self.points.len() - 1]);
+        if load <= first.0 {
+            return first.1;
+        }
+        if load >= last.0 {
+            return last.1;
+        }
+        let upper = self.points.iter().position(|p| p.0 >= load).unwrap_or(self.points.len() - 1);
+        let ((l0, e0), (l1, e1)) = (self.points[upper - 1], self.points[upper]);
+        e0 + (e1 - e0) * (load - l0) / (l1 - l0)
+    }
+
+    /// Weighted efficiency of this curve.
+    pub fn weighted(&self, weighting: Weighting) -> f64 {
+        weighting.weights().iter().map(|(load, w)| w * self.efficiency(*load)).sum()
+    }
+}
+
+impl Default for EfficiencyCurve {
+    fn default() -> Self {
+        Self::preset(EfficiencyPreset::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_match_their_nameplate() {
+        for preset in [EfficiencyPreset::Euro98, EfficiencyPreset::Cec975] {
+            let (weighting, nameplate) = preset.nameplate();
+            let weighted = EfficiencyCurve::preset(preset).weighted(weighting);
+            assert!((weighted - nameplate).abs() < 0.001, "{preset:?}: {:.3} %", weighted * 100.0);
+        }
+        for weighting in [Weighting::Euro, Weighting::Cec] {
+            let total: f64 = weighting.weights().iter().map(|(_, w)| w).sum();
+            assert!((total - 1.0).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn explicit_points_interpolate_and_fall_off_the_cliff() {
+        let curve = EfficiencyCurve::for_config(&EfficiencyCurveConfig::Points(vec![
+            [1.0, 0.97], [0.1, 0.95], [0.5, 0.98],
+        ]));
+        assert!((curve.efficiency(0.3) - 0.965).abs() < 1e-12);
+        assert_eq!(curve.efficiency(1.2), 0.97);
+        assert_eq!(curve.efficiency(0.05), 0.95);
+
+        // Below 2 % load the efficiency collapses towards zero
+        assert!((curve.efficiency(0.01) - 0.475).abs() < 1e-12);
+        assert_eq!(curve.efficiency(0.0), 0.0);
+        let euro = EfficiencyCurve::default();
+        assert!(euro.efficiency(0.019) < 0.9 && euro.efficiency(0.021) > 0.9);
+    }
+}
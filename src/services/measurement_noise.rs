@@ -0,0 +1,152 @@
+use std::f64::consts::PI;
+
+use crate::config::MeasurementNoiseConfig;
+use crate::models::power::PlantData;
+
+const SECONDS_PER_YEAR: f64 = 365.25 * 86_400.0;
+
+/// Noise streams, one per signal (the phase voltages take three)
+const POA: u64       = 0;
+const VOLTAGE: u64   = 1;
+const FREQUENCY: u64 = 4;
+const DROPOUT: u64   = 5;
+
+// ─── Instrument readings ─────────────────────────────────────
+/// Telemetry values that come from a sensor or meter rather than from the
+/// inverter's own set-points.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Readings {
+    pub poa_w_m2:     f64,
+    pub voltage_v:    [f64; 3],
+    pub frequency_hz: f64,
+}
+
+impl Readings {
+    pub fn of(data: &PlantData) -> Self {
+        Self {
+            poa_w_m2:     data.poa_irradiance_w_m2,
+            voltage_v:    [data.voltage_l1_v, data.voltage_l2_v, data.voltage_l3_v],
+            frequency_hz: data.frequency_hz,
+        }
+    }
+
+    pub fn write_to(self, data: &mut PlantData) {
+        data.poa_irradiance_w_m2 = self.poa_w_m2;
+        [data.voltage_l1_v, data.voltage_l2_v, data.voltage_l3_v] = self.voltage_v;
+        data.frequency_hz = self.frequency_hz;
+    }
+}
+
+// ─── Measurement noise ───────────────────────────────────────
+/// Error model of the plant instruments: Gaussian noise on every reading, a
+/// slow calibration drift of the irradiance sensor and logger dropouts that
+/// hold the last readings. Every draw hashes the plant, the scenario seed
+/// and the timestamp, so a seed replays the same noise.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MeasurementNoise {
+    config: MeasurementNoiseConfig,
+}
+
+impl MeasurementNoise {
+    /// None when the noise is switched off.
+    pub fn for_config(config: &MeasurementNoiseConfig) -> Option<Self> {
+        config.enabled.then_some(Self { config: *config })
+    }
+
+    /// Update cycles the readings freeze for when a dropout starts at
+    /// `unix_s`; 0 = the logger keeps up.
+    pub fn dropout_cycles(&self, plant_id: &str, seed: u64, unix_s: u64) -> u32 {
+        if uniform(plant_id, seed, unix_s, DROPOUT) < self.config.dropout_probability {
+            self.config.dropout_cycles
+        } else {
+            0
+        }
+    }
+
+    /// What the instruments show for `truth` at `unix_s`, with the
+    /// irradiance sensor `sensor_age_s` past its calibration.
+    pub fn measure(&self, plant_id: &str, seed: u64, unix_s: u64, sensor_age_s: f64, truth: Readings) -> Readings {
+        let noise = |stream| gaussian(plant_id, seed, unix_s, stream);
+        let drift = 1.0 + self.config.poa_drift_pct_per_year / 100.0 * sensor_age_s.max(0.0) / SECONDS_PER_YEAR;
+        let poa_w_m2 = truth.poa_w_m2 * drift * (1.0 + self.config.poa_sigma_pct / 100.0 * noise(POA));
+        Readings {
+            poa_w_m2:     poa_w_m2.max(0.0),
+            voltage_v:    [0, 1, 2].map(|phase| truth.voltage_v[phase] + self.config.voltage_sigma_v * noise(VOLTAGE + phase as u64)),
+            frequency_hz: truth.frequency_hz + self.config.frequency_sigma_hz * noise(FREQUENCY),
+        }
+    }
+}
+
+/// Standard normal draw (Box–Muller) for one plant (or grid), seed, second and stream.
+pub fn gaussian(plant_id: &str, seed: u64, unix_s: u64, stream: u64) -> f64 {
+    let u1 = 1.0 - uniform(plant_id, seed, unix_s, stream * 2 + 16); // (0, 1]
+    let u2 = uniform(plant_id, seed, unix_s, stream * 2 + 17);
+    (-2.0 * u1.ln()).sqrt() * (2.0 * PI * u2).cos()
+}
+
+/// Uniform draw in [0, 1) for one plant (or grid), seed, second and stream.
+pub fn uniform(plant_id: &str, seed: u64, unix_s: u64, stream: u64) -> f64 {
+    let mut h = seed ^ unix_s.wrapping_mul(0x9e3779b97f4a7c15) ^ stream.wrapping_mul(0xc2b2ae3d27d4eb4f);
+    for b in plant_id.bytes() {
+        h = (h ^ b as u64).wrapping_mul(0x100000001b3);
+    }
+    // SplitMix64 finaliser
+    h = (h ^ (h >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    h = (h ^ (h >> 27)).wrapping_mul(0x94d049bb133111eb);
+    ((h ^ (h >> 31)) >> 11) as f64 / (1u64 << 53) as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn enabled() -> MeasurementNoise {
+        MeasurementNoise::for_config(&MeasurementNoiseConfig { enabled: true, ..MeasurementNoiseConfig::default() }).unwrap()
+    }
+
+    #[test]
+    fn readings_scatter_by_their_sigma_and_replay_under_a_seed() {
+        assert_eq!(MeasurementNoise::for_config(&MeasurementNoiseConfig::default()), None);
+        let noise = enabled();
+        let truth = Readings { poa_w_m2: 800.0, voltage_v: [230.0; 3], frequency_hz: 50.0 };
+
+        // One simulated day of 5-second samples from a freshly calibrated sensor
+        let samples: Vec<Readings> = (0..17_280u64)
+            .map(|i| noise.measure("plant_1", 42, 1_750_000_000 + 5 * i, 0.0, truth))
+            .collect();
+        let stats = |value: fn(&Readings) -> f64| {
+            let n = samples.len() as f64;
+            let mean = samples.iter().map(value).sum::<f64>() / n;
+            let sd = (samples.iter().map(|r| (value(r) - mean).powi(2)).sum::<f64>() / n).sqrt();
+            (mean, sd)
+        };
+        let (poa_mean, poa_sd) = stats(|r| r.poa_w_m2);
+        assert!((poa_mean - 800.0).abs() < 1.0 && (poa_sd - 16.0).abs() < 1.0, "{poa_mean:.1} ± {poa_sd:.2} W/m²");
+        let (v_mean, v_sd) = stats(|r| r.voltage_v[2]);
+        assert!((v_mean - 230.0).abs() < 0.02 && (v_sd - 0.3).abs() < 0.02, "{v_mean:.3} ± {v_sd:.3} V");
+        let (f_mean, f_sd) = stats(|r| r.frequency_hz);
+        assert!((f_mean - 50.0).abs() < 1e-3 && (f_sd - 0.01).abs() < 1e-3, "{f_mean:.4} ± {f_sd:.4} Hz");
+
+        // The same seed and timestamp replay the reading; another seed does not
+        let t = 1_750_000_000;
+        assert_eq!(noise.measure("plant_1", 42, t, 0.0, truth), samples[0]);
+        assert_ne!(noise.measure("plant_1", 7, t, 0.0, truth), samples[0]);
+        assert_ne!(noise.measure("plant_2", 42, t, 0.0, truth), samples[0]);
+
+        // Dropouts start about once per thousand cycles
+        let dropouts = (0..100_000u64).filter(|i| noise.dropout_cycles("plant_1", 42, *i * 5) > 0).count();
+        assert!((50..150).contains(&dropouts), "{dropouts}");
+    }
+
+    #[test]
+    fn irradiance_sensor_drifts_slowly_off_calibration() {
+        let noise = MeasurementNoise::for_config(&MeasurementNoiseConfig {
+            enabled: true, poa_sigma_pct: 0.0, ..MeasurementNoiseConfig::default()
+        }).unwrap();
+        let truth = Readings { poa_w_m2: 1000.0, ..Readings::default() };
+        let read = |age_s| noise.measure("plant_1", 0, 0, age_s, truth).poa_w_m2;
+        assert_eq!(read(0.0), 1000.0);
+        assert!((read(SECONDS_PER_YEAR / 2.0) - 995.0).abs() < 1e-9);
+        assert!((read(2.0 * SECONDS_PER_YEAR) - 980.0).abs() < 1e-9);
+    }
+}
@@ -1,3 +1,20 @@
-pub mod power_service;
-pub mod solar_algorithm;
+pub(crate) mod power_service;
+pub(crate) mod solar_algorithm;
+pub mod pv_string;
+pub(crate) mod inverter_efficiency;
+pub(crate) mod site_load;
+pub(crate) mod grid_support;
+pub(crate) mod grid_frequency;
+pub mod sim_clock;
+pub(crate) mod curtailment_schedule;
+pub(crate) mod expected_energy;
+pub mod measurement_noise;
+pub mod scenarios;
+pub mod persistence;
+pub(crate) mod history;
+pub(crate) mod production_report;
 pub mod mqtt_service;
+pub(crate) mod state_snapshot;
+pub(crate) mod energy_meter;
+pub(crate) mod forecast;
+pub mod plant_registry;
@@ -2,21 +2,30 @@
 ///
 /// Publishes plant telemetry as JSON payloads to a configured MQTT broker.
 /// Topic structure: `{prefix}/{plant_id}/telemetry`
-/// Also publishes system-wide summary: `{prefix}/system/summary`
+/// Also publishes system-wide summary: `{prefix}/system/summary`,
+/// the retained `online`/`offline` availability of each plant on
+/// `{prefix}/{plant_id}/availability` (offline while in maintenance)
+/// and every new log event on `{prefix}/{plant_id}/events` (`{prefix}/system/events`
+/// for events not tied to a plant).
 ///
 /// Standard-compatible: payloads follow the Sparkplug B field naming convention
 /// where possible, but serialised as plain JSON for maximum compatibility.
 
+use std::collections::HashMap;
 use std::time::Duration;
-use rumqttc::{AsyncClient, MqttOptions, QoS};
+use rumqttc::{AsyncClient, LastWill, MqttOptions, QoS};
 use crate::config::MqttConfig;
 use crate::shared_state::AppState;
+use crate::shutdown::Shutdown;
 use crate::config::PlantConfig;
+use crate::services::plant_registry::PlantRegistry;
+use crate::models::power::{status_label, PlantData};
 
 pub async fn run_publisher(
     cfg: MqttConfig,
     state: AppState,
-    plants: Vec<PlantConfig>,
+    registry: PlantRegistry,
+    shutdown: Shutdown,
 ) {
     if !cfg.enabled || cfg.broker_host.is_empty() {
         println!("[MQTT] Disabled or no broker configured — skipping MQTT publisher");
@@ -45,10 +54,15 @@ pub async fn run_publisher(
         opts.set_credentials(user, pass);
     }
 
+    // The broker publishes the retained OFFLINE status itself if we vanish
+    let status_topic   = format!("{}/system/status", prefix);
+    let offline_payload = serde_json::json!({ "status": "OFFLINE" }).to_string();
+    opts.set_last_will(LastWill::new(&status_topic, offline_payload.clone(), QoS::AtLeastOnce, true));
+
     let (client, mut eventloop) = AsyncClient::new(opts, 64);
 
     // Publish birth message
-    let birth_topic = format!("{}/system/status", prefix);
+    let birth_topic = status_topic.clone();
     let birth_payload = serde_json::json!({
         "status": "ONLINE",
         "version": env!("CARGO_PKG_VERSION"),
@@ -62,25 +76,30 @@ pub async fn run_publisher(
     ).await {
         eprintln!("[MQTT] Failed to publish birth message: {}", e);
     } else {
-        state.mqtt_connected.store(true, std::sync::atomic::Ordering::Relaxed);
+        state.set_mqtt_connected(true);
         println!("[MQTT] Connected, birth message published to {}", birth_topic);
     }
 
-    // Will message topic (set before connect — for next reconnect cycle)
-    let _will_topic  = format!("{}/system/status", prefix);
-    let will_payload = serde_json::json!({ "status": "OFFLINE" });
-    drop(will_payload); // MqttOptions::set_last_will could be set before creating client
+    // Only events logged from now on are published
+    let mut last_event_id = state.get_events(1).first().map(|e| e.id.clone());
+    // Availability last published per plant, republished when it flips
+    let mut availability: HashMap<String, bool> = HashMap::new();
 
     loop {
         // Drain event loop without blocking the publish loop
         tokio::select! {
+            _ = shutdown.wait() => {
+                publish_death(&client, &mut eventloop, &status_topic, &offline_payload).await;
+                state.set_mqtt_connected(false);
+                return;
+            }
             _ = tokio::time::sleep(Duration::from_secs(interval_s)) => {}
             event = eventloop.poll() => {
                 match event {
                     Ok(_) => {}
                     Err(e) => {
                         eprintln!("[MQTT] Event loop error: {} — will reconnect", e);
-                        state.mqtt_connected.store(false, std::sync::atomic::Ordering::Relaxed);
+                        state.set_mqtt_connected(false);
                         tokio::time::sleep(Duration::from_secs(5)).await;
                     }
                 }
@@ -88,75 +107,20 @@ pub async fn run_publisher(
             }
         }
 
-        // Publish per-plant telemetry
+        // Publish per-plant telemetry, for the plants as changed over the API
+        let plants = registry.plants();
         for plant in &plants {
             if let Some(data) = state.get_data(&plant.id) {
-                let status_label = match data.status {
-                    1 => "RUNNING", 2 => "FAULT", 3 => "CURTAILED",
-                    4 => "STARTING", 5 => "MPPT", _ => "STOPPED",
-                };
-                let payload = serde_json::json!({
-                    // Identity
-                    "plant_id":   plant.id,
-                    "plant_name": plant.name,
-                    "timestamp":  chrono::Utc::now().to_rfc3339(),
-                    // AC Output
-                    "ac": {
-                        "power_kw":           data.power_kw,
-                        "voltage_l1_v":       data.voltage_l1_v,
-                        "voltage_l2_v":       data.voltage_l2_v,
-                        "voltage_l3_v":       data.voltage_l3_v,
-                        "current_l1_a":       data.current_l1_a,
-                        "current_l2_a":       data.current_l2_a,
-                        "current_l3_a":       data.current_l3_a,
-                        "frequency_hz":       data.frequency_hz,
-                        "rocof_hz_s":         data.rocof_hz_s,
-                        "power_factor":       data.power_factor,
-                        "reactive_kvar":      data.reactive_power_kvar,
-                        "apparent_kva":       data.apparent_power_kva,
-                    },
-                    // DC / MPPT
-                    "dc": {
-                        "voltage_v":          data.dc_voltage_v,
-                        "current_a":          data.dc_current_a,
-                        "power_kw":           data.dc_power_kw,
-                        "mppt_voltage_v":     data.mppt_voltage_v,
-                        "mppt_current_a":     data.mppt_current_a,
-                    },
-                    // Thermal
-                    "thermal": {
-                        "cell_temp_c":        data.temperature_c,
-                        "inverter_temp_c":    data.inverter_temp_c,
-                        "ambient_temp_c":     data.ambient_temp_c,
-                    },
-                    // Irradiance
-                    "irradiance": {
-                        "poa_w_m2":           data.poa_irradiance_w_m2,
-                        "cloud_factor":       data.cloud_factor,
-                        "solar_elevation_deg": data.solar_elevation_deg,
-                    },
-                    // Status & protection
-                    "status": status_label,
-                    "fault_code":             data.fault_code,
-                    "alarm_flags":            data.alarm_flags,
-                    "isolation_resistance_mohm": data.isolation_resistance_mohm,
-                    // Energy
-                    "energy": {
-                        "daily_kwh":          data.daily_energy_kwh,
-                        "monthly_kwh":        data.monthly_energy_kwh,
-                        "total_kwh":          data.total_energy_kwh,
-                    },
-                    // KPIs
-                    "kpi": {
-                        "efficiency_percent":     data.efficiency_percent,
-                        "performance_ratio":      data.performance_ratio,
-                        "specific_yield_kwh_kwp": data.specific_yield_kwh_kwp,
-                        "capacity_factor_percent": data.capacity_factor_percent,
-                    },
-                    // Weather
-                    "weather_code": data.weather_code,
-                    "is_day":       data.is_day,
-                });
+                let available = !data.maintenance;
+                if availability.get(&plant.id) != Some(&available) {
+                    let topic = format!("{}/{}/availability", prefix, plant.id);
+                    let payload = if available { "online" } else { "offline" };
+                    if client.publish(&topic, QoS::AtLeastOnce, true, payload.as_bytes()).await.is_ok() {
+                        availability.insert(plant.id.clone(), available);
+                    }
+                }
+
+                let payload = telemetry_payload(plant, &data, state.now());
 
                 let topic = format!("{}/{}/telemetry", prefix, plant.id);
                 if let Err(e) = client.publish(
@@ -166,9 +130,9 @@ pub async fn run_publisher(
                     payload.to_string().as_bytes(),
                 ).await {
                     eprintln!("[MQTT] Publish error for {}: {}", topic, e);
-                    state.mqtt_connected.store(false, std::sync::atomic::Ordering::Relaxed);
+                    state.set_mqtt_connected(false);
                 } else {
-                    state.mqtt_connected.store(true, std::sync::atomic::Ordering::Relaxed);
+                    state.set_mqtt_connected(true);
                 }
 
                 // Also publish alarms topic if any active alarms
@@ -181,24 +145,26 @@ pub async fn run_publisher(
             }
         }
 
+        // Publish new events, oldest first
+        for event in state.events_since(last_event_id.as_deref()) {
+            let topic = format!("{}/{}/events", prefix, event.plant_id.as_deref().unwrap_or("system"));
+            let payload = serde_json::to_string(&event).unwrap_or_default();
+            let _ = client.publish(&topic, QoS::AtLeastOnce, false, payload.as_bytes()).await;
+            last_event_id = Some(event.id);
+        }
+
         // Publish fleet summary
-        let all_data  = state.get_all_data();
-        let total_kw  : f64 = all_data.values().map(|d| d.power_kw).sum();
-        let total_kwh : f64 = all_data.values().map(|d| d.daily_energy_kwh).sum();
+        let totals    = state.fleet_totals();
         let total_nom : f64 = plants.iter().map(|p| p.nominal_power_kw).sum();
-        let running   = all_data.values().filter(|d| d.status == 1 || d.status == 5).count();
-        let fleet_pr  : f64 = if !all_data.is_empty() {
-            all_data.values().map(|d| d.performance_ratio).sum::<f64>() / all_data.len() as f64
-        } else { 0.0 };
 
         let summary = serde_json::json!({
-            "timestamp":            chrono::Utc::now().to_rfc3339(),
-            "total_power_kw":       total_kw,
+            "timestamp":            state.now().to_rfc3339(),
+            "total_power_kw":       totals.power_kw,
             "total_nominal_kw":     total_nom,
-            "total_daily_kwh":      total_kwh,
-            "plants_running":       running,
+            "total_daily_kwh":      totals.daily_energy_kwh,
+            "plants_running":       totals.running,
             "plants_total":         plants.len(),
-            "fleet_pr":             fleet_pr,
+            "fleet_pr":             totals.mean_performance_ratio(),
             "offline_mode":         state.is_offline(),
         });
 
@@ -211,3 +177,101 @@ pub async fn run_publisher(
         ).await;
     }
 }
+
+/// Publish the retained OFFLINE status and disconnect cleanly, driving the
+/// event loop until the broker connection is closed (at most 2 s).
+async fn publish_death(client: &AsyncClient, eventloop: &mut rumqttc::EventLoop, topic: &str, payload: &str) {
+    if let Err(e) = client.publish(topic, QoS::AtLeastOnce, true, payload.as_bytes()).await {
+        eprintln!("[MQTT] Failed to publish death message: {}", e);
+        return;
+    }
+    let _ = client.disconnect().await;
+    let flushed = tokio::time::timeout(Duration::from_secs(2), async {
+        while eventloop.poll().await.is_ok() {}
+    }).await;
+    match flushed {
+        Ok(())  => println!("[MQTT] OFFLINE status published, disconnected"),
+        Err(_)  => eprintln!("[MQTT] Broker did not acknowledge disconnect in time"),
+    }
+}
+
+/// JSON telemetry published on `{prefix}/{plant_id}/telemetry`, stamped
+/// `at` on the simulation clock.
+pub fn telemetry_payload(plant: &PlantConfig, data: &PlantData, at: chrono::DateTime<chrono::Utc>) -> serde_json::Value {
+    serde_json::json!({
+        // Identity
+        "plant_id":   plant.id,
+        "plant_name": plant.name,
+        "timestamp":  at.to_rfc3339(),
+        // AC Output
+        "ac": {
+            "power_kw":           data.power_kw,
+            "voltage_l1_v":       data.voltage_l1_v,
+            "voltage_l2_v":       data.voltage_l2_v,
+            "voltage_l3_v":       data.voltage_l3_v,
+            "current_l1_a":       data.current_l1_a,
+            "current_l2_a":       data.current_l2_a,
+            "current_l3_a":       data.current_l3_a,
+            "frequency_hz":       data.frequency_hz,
+            "rocof_hz_s":         data.rocof_hz_s,
+            "power_factor":       data.power_factor,
+            "reactive_kvar":      data.reactive_power_kvar,
+            "apparent_kva":       data.apparent_power_kva,
+        },
+        // DC / MPPT
+        "dc": {
+            "voltage_v":          data.dc_voltage_v,
+            "current_a":          data.dc_current_a,
+            "power_kw":           data.dc_power_kw,
+            "mppt_voltage_v":     data.mppt_voltage_v,
+            "mppt_current_a":     data.mppt_current_a,
+            "mppt":               data.mppt,
+        },
+        // Thermal
+        "thermal": {
+            "cell_temp_c":        data.temperature_c,
+            "inverter_temp_c":    data.inverter_temp_c,
+            "ambient_temp_c":     data.ambient_temp_c,
+        },
+        // Irradiance
+        "irradiance": {
+            "poa_w_m2":           data.poa_irradiance_w_m2,
+            "poa_true_w_m2":      data.poa_irradiance_true_w_m2,
+            "ghi_w_m2":           data.ghi_w_m2,
+            "dni_w_m2":           data.dni_w_m2,
+            "dhi_w_m2":           data.dhi_w_m2,
+            "rear_w_m2":          data.rear_irradiance_w_m2,
+            "cloud_factor":       data.cloud_factor,
+            "solar_elevation_deg": data.solar_elevation_deg,
+        },
+        // Status & protection
+        "status":                 status_label(data.status),
+        "fault_code":             data.fault_code,
+        "alarm_flags":            data.alarm_flags,
+        "isolation_resistance_mohm": data.isolation_resistance_mohm,
+        // Energy
+        "energy": {
+            "daily_kwh":          data.daily_energy_kwh,
+            "monthly_kwh":        data.monthly_energy_kwh,
+            "total_kwh":          data.total_energy_kwh,
+            "clipped_kwh":        data.clipped_energy_kwh,
+            "curtailed_kwh":      data.curtailed_energy_kwh,
+        },
+        // Grid meter
+        "grid": {
+            "site_load_kw":       data.site_load_kw,
+            "export_kw":          data.grid_export_kw,
+        },
+        // KPIs
+        "kpi": {
+            "efficiency_percent":     data.efficiency_percent,
+            "performance_ratio":      data.performance_ratio,
+            "specific_yield_kwh_kwp": data.specific_yield_kwh_kwp,
+            "capacity_factor_percent": data.capacity_factor_percent,
+            "damage_pct":             data.damage_pct,
+        },
+        // Weather
+        "weather_code": data.weather_code,
+        "is_day":       data.is_day,
+    })
+}
@@ -0,0 +1,232 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::Duration;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::power::{Alarm, Event, PlantData};
+use crate::shared_state::AppState;
+use crate::shutdown::Shutdown;
+
+// ─── Per-plant state ─────────────────────────────────────────
+/// What a plant keeps across a restart: its energy counters and the slow
+/// state the weather model cannot replay (soiling, storm damage).
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PlantCounters {
+    pub daily_energy_kwh:     f64,
+    pub monthly_energy_kwh:   f64,
+    pub total_energy_kwh:     f64,
+    pub daily_peak_power_kw:  f64,
+    pub clipped_energy_kwh:   f64,
+    pub curtailed_energy_kwh: f64,
+    pub co2_avoided_kg:       f64,
+    /// POA insolation behind today's performance ratio
+    pub daily_insolation_kwh_m2: f64,
+    /// Plant-local date the daily counters belong to
+    pub energy_day:           Option<NaiveDate>,
+    pub soiling_factor:       f64,
+    pub damage_pct:           f64,
+}
+
+impl Default for PlantCounters {
+    fn default() -> Self {
+        Self::of(&PlantData::default())
+    }
+}
+
+impl PlantCounters {
+    pub fn of(data: &PlantData) -> Self {
+        Self {
+            daily_energy_kwh:     data.daily_energy_kwh,
+            monthly_energy_kwh:   data.monthly_energy_kwh,
+            total_energy_kwh:     data.total_energy_kwh,
+            daily_peak_power_kw:  data.daily_peak_power_kw,
+            clipped_energy_kwh:   data.clipped_energy_kwh,
+            curtailed_energy_kwh: data.curtailed_energy_kwh,
+            co2_avoided_kg:       data.co2_avoided_kg,
+            daily_insolation_kwh_m2: data.daily_insolation_kwh_m2,
+            energy_day:           data.energy_day,
+            soiling_factor:       data.soiling_factor,
+            damage_pct:           data.damage_pct,
+        }
+    }
+
+    pub fn write_to(self, data: &mut PlantData) {
+        data.daily_energy_kwh     = self.daily_energy_kwh;
+        data.monthly_energy_kwh   = self.monthly_energy_kwh;
+        data.total_energy_kwh     = self.total_energy_kwh;
+        data.daily_peak_power_kw  = self.daily_peak_power_kw;
+        data.clipped_energy_kwh   = self.clipped_energy_kwh;
+        data.curtailed_energy_kwh = self.curtailed_energy_kwh;
+        data.co2_avoided_kg       = self.co2_avoided_kg;
+        data.daily_insolation_kwh_m2 = self.daily_insolation_kwh_m2;
+        data.energy_day           = self.energy_day;
+        data.soiling_factor       = self.soiling_factor;
+        data.damage_pct           = self.damage_pct;
+    }
+}
+
+// ─── Snapshot ────────────────────────────────────────────────
+/// Everything written to `persistence.path`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Snapshot {
+    pub saved_at: Option<DateTime<Utc>>,
+    pub plants:   HashMap<String, PlantCounters>,
+    /// Alarms still active when the snapshot was taken
+    pub alarms:   Vec<Alarm>,
+    /// Event log, newest first
+    pub events:   Vec<Event>,
+}
+
+impl Snapshot {
+    pub fn of(state: &AppState) -> Self {
+        let plants = state.map_all_data(PlantCounters::of).collect();
+        let alarms = state.alarms.read().unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .filter(|a| a.active)
+            .cloned()
+            .collect();
+        let events = state.events.read().unwrap_or_else(|e| e.into_inner()).iter().cloned().collect();
+        Self { saved_at: Some(Utc::now()), plants, alarms, events }
+    }
+
+    /// Load the snapshot into a freshly started state, before the update
+    /// tasks run. Conditions that cleared while the simulator was down clear
+    /// their restored alarms on the first update.
+    pub fn restore(self, state: &AppState) {
+        for (id, counters) in self.plants {
+            state.plant_data.update(&id, |data| counters.write_to(data));
+        }
+        if let Ok(mut alarms) = state.alarms.write() {
+            alarms.extend(self.alarms);
+        }
+        if let Ok(mut log) = state.events.write() {
+            log.extend(self.events);
+        }
+    }
+}
+
+/// Read the snapshot at `path`: None when there is none yet, an error when
+/// the file cannot be read or parsed.
+pub fn load(path: &str) -> Result<Option<Snapshot>, String> {
+    if !Path::new(path).exists() {
+        return Ok(None);
+    }
+    let content = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path, e))?;
+    serde_json::from_str(&content).map(Some).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Write the state to `path` through a temporary file, so a crash mid-write
+/// leaves the previous snapshot in place.
+pub fn save(state: &AppState, path: &str) -> Result<(), String> {
+    let json = serde_json::to_string(&Snapshot::of(state)).map_err(|e| e.to_string())?;
+    let tmp = format!("{}.tmp", path);
+    std::fs::write(&tmp, json).map_err(|e| format!("{}: {}", tmp, e))?;
+    std::fs::rename(&tmp, path).map_err(|e| format!("{}: {}", path, e))
+}
+
+/// Snapshot the state every `interval` until shutdown; the final snapshot is
+/// taken once the plant tasks have stopped.
+pub async fn run_saver(state: AppState, path: String, interval: Duration, shutdown: Shutdown) {
+    loop {
+        tokio::select! {
+            _ = tokio::time::sleep(interval) => {}
+            _ = shutdown.wait() => break,
+        }
+        if let Err(e) = save(&state, &path) {
+            eprintln!("[PERSISTENCE] Snapshot not saved: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use crate::models::power::{AlarmSeverity, EventKind};
+    use crate::services::power_service::from_estimate;
+    use crate::services::solar_algorithm::{estimate, EstimateParams};
+    use crate::shared_state::PlantRating;
+
+    fn temp_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("solar-sim-{}-{}.json", name, std::process::id()))
+            .to_string_lossy()
+            .into_owned()
+    }
+
+    fn alarm(code: u16, active: bool) -> Alarm {
+        Alarm {
+            id:         format!("a{}", code),
+            plant_id:   "plant_1".into(),
+            code,
+            severity:   AlarmSeverity::Warning,
+            message:    String::new(),
+            timestamp:  Utc::now(),
+            active,
+            cleared_at: None,
+            acknowledged:    false,
+            acknowledged_by: None,
+            acknowledged_at: None,
+            note:            None,
+        }
+    }
+
+    #[test]
+    fn a_restart_carries_the_lifetime_energy_on() {
+        let path = temp_path("restart");
+        let noon = Utc.with_ymd_and_hms(2025, 6, 21, 10, 0, 0).unwrap();
+        let plant: crate::config::PlantConfig = serde_json::from_value(serde_json::json!({
+            "id": "plant_1", "name": "p", "latitude": 45.07, "longitude": 7.33,
+            "nominal_power_kw": 100.0, "timezone": "Europe/Rome",
+            "modbus_mapping": { "base_address": 0 }
+        })).unwrap();
+        let rating = PlantRating::of(&plant);
+        let weather = from_estimate(noon, estimate(&EstimateParams::for_plant(&plant), noon));
+
+        let before = AppState::new(true);
+        before.plant_data.insert("plant_1", PlantData {
+            total_energy_kwh: 12_345.0, monthly_energy_kwh: 2_100.0, soiling_factor: 0.93, damage_pct: 12.0,
+            ..PlantData::default()
+        });
+        before.set_clock(noon);
+        before.set_data("plant_1", &rating, &weather, 0.0);
+        before.alarms.write().unwrap().extend([alarm(301, true), alarm(101, false)]);
+        before.push_event(Some("plant_1".into()), EventKind::PanelCleaning, "wash".into(), None);
+        save(&before, &path).unwrap();
+
+        let after = AppState::new(true);
+        load(&path).unwrap().expect("snapshot written").restore(&after);
+        let _ = std::fs::remove_file(&path);
+        let (was, is) = (before.get_data("plant_1").unwrap(), after.get_data("plant_1").unwrap());
+        assert_eq!(PlantCounters::of(&is), PlantCounters::of(&was));
+        assert_eq!(after.damage_pct("plant_1"), 12.0);
+        let restored = after.get_alarms(None);
+        assert!(restored.iter().any(|a| a.code == 301) && restored.iter().all(|a| a.active), "{restored:?}");
+        assert_eq!(after.get_events(10).len(), before.get_events(10).len());
+
+        // The restarted plant counts on from where it stopped
+        after.set_clock(noon + chrono::Duration::seconds(5));
+        after.set_data("plant_1", &rating, &weather, 0.0);
+        let resumed = after.get_data("plant_1").unwrap();
+        assert!(resumed.total_energy_kwh >= was.total_energy_kwh);
+        assert!(resumed.monthly_energy_kwh >= 2_100.0);
+    }
+
+    #[test]
+    fn missing_or_corrupt_files_start_fresh() {
+        let path = temp_path("corrupt");
+        let _ = std::fs::remove_file(&path);
+        assert!(load(&path).unwrap().is_none());
+        std::fs::write(&path, "{ \"plants\": { \"plant_1\": ").unwrap();
+        assert!(load(&path).is_err());
+        std::fs::write(&path, r#"{ "plants": { "plant_1": { "total_energy_kwh": 5.0 } } }"#).unwrap();
+        let snapshot = load(&path).unwrap().unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(snapshot.plants["plant_1"].total_energy_kwh, 5.0);
+        assert_eq!(snapshot.plants["plant_1"].soiling_factor, PlantData::default().soiling_factor);
+    }
+}
@@ -0,0 +1,485 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::Duration;
+
+use tokio::task::JoinHandle;
+use tokio::time::Instant;
+
+use crate::config::{Config, PlantConfig, SimulationConfig};
+use crate::modbus_server::{self, LiveRegisterMaps};
+use crate::models::power::{EventKind, ReactivePowerMode};
+use crate::services::{
+    curtailment_schedule::CurtailmentSchedule, energy_meter::EnergyMeter, grid_support, inverter_efficiency::EfficiencyCurve,
+    power_service, site_load::SiteLoad, solar_algorithm::EstimateParams,
+};
+use crate::shared_state::{self, AppState, PlantRating};
+use crate::shutdown::Shutdown;
+
+// ─── Plant settings ──────────────────────────────────────────
+/// Hand the settings of `plant` to the shared state, at startup and for a
+/// plant created or changed over the API.
+pub fn configure_plant(state: &AppState, config: &Config, plant: &PlantConfig) {
+    state.configure_efficiency_curve(&plant.id, EfficiencyCurve::for_config(&plant.inverter.efficiency_curve));
+    state.configure_startup(
+        &plant.id,
+        plant.inverter.start_irradiance_w_m2.unwrap_or(shared_state::IRRAD_START_W_M2),
+        plant.inverter.stop_irradiance_w_m2.unwrap_or(shared_state::IRRAD_STOP_W_M2),
+        plant.inverter.startup_delay_cycles.unwrap_or(shared_state::STARTUP_DELAY_CYCLES),
+        plant.inverter.mppt_search_cycles.unwrap_or(shared_state::MPPT_SEARCH_CYCLES),
+        plant.inverter.ramp_rate_kw_per_min,
+    );
+    if let Some(limit_kw) = plant.grid.export_limit_kw {
+        state.configure_export_limit(&plant.id, limit_kw);
+    }
+    if let Some(class_pct) = plant.meter.accuracy_class {
+        state.configure_energy_meter(&plant.id, EnergyMeter::new(class_pct));
+    }
+    state.configure_grid_support(
+        &plant.id,
+        plant.grid.freq_watt_config().map(|c| grid_support::FreqWatt::for_config(&c, plant.grid.nominal_frequency_hz)),
+        plant.grid.volt_var.as_ref().map(grid_support::VoltVarCurve::for_config).unwrap_or_default(),
+    );
+    if plant.grid.volt_var.is_some() {
+        state.set_reactive_mode(&plant.id, ReactivePowerMode::VoltVar);
+    }
+    state.configure_grid(&plant.id, &plant.grid.network, plant.grid.nominal_frequency_hz);
+    state.configure_update_interval(&plant.id, plant.update_interval_s);
+    if let Some(stale_after_s) = plant.stale_after_s {
+        state.configure_stale_after(&plant.id, stale_after_s);
+    }
+    state.configure_protection(&plant.id, plant.grid.protection_limits());
+    state.configure_alarms(&plant.id, plant.alarms);
+    state.configure_history(&plant.id, config.history.retention_h);
+    if !plant.curtailment_schedule.is_empty() {
+        state.configure_curtailment_schedule(&plant.id, CurtailmentSchedule::for_config(&plant.curtailment_schedule));
+    }
+    if let Some(timeout_s) = plant.modbus_mapping.watchdog_timeout_s {
+        state.configure_watchdog(&plant.id, timeout_s, plant.modbus_mapping.watchdog_fallback_pct);
+    }
+}
+
+// ─── Update task ─────────────────────────────────────────────
+/// Update cycle of one plant, every `update_interval_s` from `first` until
+/// `stop`: a sample from the model offline, from Open-Meteo online.
+pub async fn run_plant(state: AppState, plant: PlantConfig, simulation: SimulationConfig, first: Instant, stop: Shutdown) {
+    let mut estimate_params = EstimateParams::for_plant(&plant);
+    estimate_params.cloud_correlation_km = simulation.cloud_correlation_km;
+    estimate_params.cloud_persistence    = simulation.cloud_persistence;
+    let rating = PlantRating::of(&plant);
+    let site_load = SiteLoad::for_config(&plant.grid.load);
+    let mut ticks = tokio::time::interval_at(first, Duration::from_secs_f64(plant.update_interval_s));
+    ticks.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        tokio::select! {
+            _ = ticks.tick() => {}
+            _ = stop.wait() => break,
+        }
+        // Simulated time of this update, ahead of the wall clock when accelerated
+        let now = state.now();
+        // The global seed can change at runtime; a plant's own seed wins
+        estimate_params.seed = plant.seed.unwrap_or_else(|| state.simulation_seed());
+        // Soiling is live state once the plant has published; before that, or after a
+        // jump of the simulation clock, the model replays it for the date
+        estimate_params.soiling_factor = state.soiling_factor(&plant.id);
+        // Scenario overrides (eclipses) dim the light on top of the weather
+        estimate_params.irradiance_factor = state.irradiance_factor(&plant.id, now);
+        // Storm damage holds the capacity down until an operator repair
+        estimate_params.damage_pct = state.damage_pct(&plant.id);
+        let offline = state.is_offline();
+        let result = if offline {
+            // Pure offline – no API call
+            Ok(power_service::get_offline_data(&estimate_params, now))
+        } else {
+            // Online: call Open-Meteo, falls back to offline on error
+            power_service::get_current_data(&estimate_params).await
+        };
+
+        match result {
+            Ok(data) => {
+                let mode_tag = if offline { "OFFLINE" } else { "ONLINE" };
+                state.set_data(&plant.id, &rating, &data, site_load.load_at(&now.with_timezone(&plant.timezone)));
+                state.advance_soiling(&plant.id, plant.panel.soiling_rate_pct_per_day, data.rain_mm_h);
+                estimate_params.tracker_stowed = data.tracker_stowed;
+                println!(
+                    "[{} UPDATE] Plant: {} | DC Power: {:.2} kW | Temp: {:.1}°C",
+                    mode_tag, plant.id, data.power_kw, data.temperature_c
+                );
+            }
+            Err(e) => {
+                eprintln!("Error updating plant {}: {}", plant.id, e);
+            }
+        }
+    }
+}
+
+// ─── Registry ────────────────────────────────────────────────
+/// Why a plant was not created, changed or deleted.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PlantError {
+    NotFound(String),
+    /// The id, the register block or the Modbus port of another plant
+    Conflict(String),
+    /// The plant configuration does not validate
+    Invalid(String),
+    /// config.json could not be rewritten; nothing was changed
+    Persist(String),
+}
+
+impl std::fmt::Display for PlantError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::NotFound(e) | Self::Conflict(e) | Self::Invalid(e) | Self::Persist(e) => f.write_str(e),
+        }
+    }
+}
+
+/// A running task and the signal stopping it.
+struct Task {
+    stop:   Shutdown,
+    handle: JoinHandle<()>,
+}
+
+impl Task {
+    /// Stop the task and wait for the cycle it may be in to finish.
+    async fn stop(self) {
+        self.stop.trigger();
+        let _ = self.handle.await;
+    }
+}
+
+/// Modbus server on a plant's own port.
+struct DedicatedServer {
+    port: u16,
+    maps: LiveRegisterMaps,
+    task: Task,
+}
+
+#[derive(Default)]
+struct Tasks {
+    /// Update task of each plant
+    plants:    HashMap<String, Task>,
+    /// Plain Modbus TCP is served (not before startup, nor with `modbus.tls.require`)
+    modbus:    bool,
+    /// Plants on the shared `modbus.port`
+    shared:    Option<(LiveRegisterMaps, Task)>,
+    /// Plants on a port of their own
+    dedicated: HashMap<String, DedicatedServer>,
+    /// Every plant: the TLS server and the RTU line
+    fleet:     Vec<LiveRegisterMaps>,
+}
+
+/// The plants being simulated: the live configuration, the update task of
+/// each plant and the Modbus maps serving them. Plants created, changed or
+/// deleted over the API take effect without a restart; changes are applied
+/// one at a time.
+#[derive(Clone)]
+pub struct PlantRegistry {
+    state:    AppState,
+    config:   Arc<RwLock<Config>>,
+    /// config.json, rewritten on each change when `persist_changes` is set
+    path:     String,
+    shutdown: Shutdown,
+    tasks:    Arc<tokio::sync::Mutex<Tasks>>,
+}
+
+impl PlantRegistry {
+    pub fn new(state: AppState, config: Config, path: &str, shutdown: Shutdown) -> Self {
+        Self {
+            state,
+            config:   Arc::new(RwLock::new(config)),
+            path:     path.to_string(),
+            shutdown,
+            tasks:    Arc::default(),
+        }
+    }
+
+    /// The configuration as changed so far.
+    pub fn config(&self) -> Config {
+        self.config.read().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    pub fn plants(&self) -> Vec<PlantConfig> {
+        self.config.read().unwrap_or_else(|e| e.into_inner()).plants.clone()
+    }
+
+    /// Start the update task of every configured plant, staggered across
+    /// their interval so the updates do not all land on the same instant.
+    pub async fn start_plants(&self) {
+        let config = self.config();
+        let mut tasks = self.tasks.lock().await;
+        for (index, plant) in config.plants.iter().enumerate() {
+            let period = Duration::from_secs_f64(plant.update_interval_s);
+            let first = Instant::now() + period.mul_f64(index as f64 / config.plants.len() as f64);
+            self.spawn_plant(&mut tasks, &config, plant, first);
+        }
+    }
+
+    /// Serve the plants over plain Modbus TCP: the shared `modbus.port` and
+    /// the plants with a port of their own.
+    pub async fn start_modbus(&self) {
+        let config = self.config();
+        let mut tasks = self.tasks.lock().await;
+        tasks.modbus = !config.modbus.tls.as_ref().is_some_and(|tls| tls.require);
+        self.sync_modbus(&mut tasks, &config);
+    }
+
+    /// Register maps of every plant, kept current for a server serving the
+    /// whole fleet (TLS, RTU).
+    pub async fn fleet_maps(&self) -> LiveRegisterMaps {
+        let maps = LiveRegisterMaps::new(modbus_server::build_register_map(&self.plants()));
+        self.tasks.lock().await.fleet.push(maps.clone());
+        maps
+    }
+
+    /// Handles of every task started so far, for the shutdown to wait on.
+    pub async fn take_handles(&self) -> Vec<JoinHandle<()>> {
+        let mut tasks = self.tasks.lock().await;
+        let Tasks { plants, shared, dedicated, .. } = &mut *tasks;
+        let plants = plants.drain().map(|(_, task)| task.handle);
+        let shared = shared.take().map(|(_, task)| task.handle);
+        let dedicated = dedicated.drain().map(|(_, server)| server.task.handle);
+        plants.chain(shared).chain(dedicated).collect()
+    }
+
+    /// Add `plant` and start simulating it right away.
+    pub async fn create(&self, plant: PlantConfig) -> Result<(), PlantError> {
+        let mut tasks = self.tasks.lock().await;
+        let mut config = self.config();
+        if config.plants.iter().any(|p| p.id == plant.id) {
+            return Err(PlantError::Conflict(format!("plant '{}' already exists", plant.id)));
+        }
+        config.plants.push(plant.clone());
+        self.commit(&config)?;
+
+        configure_plant(&self.state, &config, &plant);
+        if !plant.enabled {
+            self.state.set_plant_enabled(&plant.id, false, Some("created disabled over the API"));
+        }
+        self.spawn_plant(&mut tasks, &config, &plant, Instant::now());
+        self.sync_modbus(&mut tasks, &config);
+        self.state.push_event(
+            Some(plant.id.clone()),
+            EventKind::SettingChanged,
+            format!("Plant '{}' created", plant.id),
+            serde_json::to_value(&plant).ok(),
+        );
+        println!("[PLANTS] Plant {} created", plant.id);
+        Ok(())
+    }
+
+    /// Replace the configuration of plant `id`; its telemetry, counters and
+    /// history carry on, and the next cycle runs with the new settings.
+    pub async fn update(&self, id: &str, plant: PlantConfig) -> Result<(), PlantError> {
+        if plant.id != id {
+            return Err(PlantError::Invalid(format!("the plant id in the body ('{}') must be '{}'", plant.id, id)));
+        }
+        let mut tasks = self.tasks.lock().await;
+        let mut config = self.config();
+        let Some(index) = config.plants.iter().position(|p| p.id == id) else {
+            return Err(PlantError::NotFound(format!("plant '{}' not found", id)));
+        };
+        let previous = std::mem::replace(&mut config.plants[index], plant.clone());
+        self.commit(&config)?;
+
+        if let Some(task) = tasks.plants.remove(id) {
+            task.stop().await;
+        }
+        self.state.forget_plant_settings(id);
+        configure_plant(&self.state, &config, &plant);
+        if previous.enabled != plant.enabled {
+            self.state.set_plant_enabled(id, plant.enabled, Some("changed over the API"));
+        }
+        let first = Instant::now() + Duration::from_secs_f64(plant.update_interval_s);
+        self.spawn_plant(&mut tasks, &config, &plant, first);
+        self.sync_modbus(&mut tasks, &config);
+        self.state.push_event(
+            Some(id.to_string()),
+            EventKind::SettingChanged,
+            format!("Plant '{}' reconfigured", id),
+            serde_json::to_value(&plant).ok(),
+        );
+        println!("[PLANTS] Plant {} reconfigured", id);
+        Ok(())
+    }
+
+    /// Stop simulating plant `id` and forget its state; its registers stop
+    /// answering and its own Modbus port, if any, closes.
+    pub async fn delete(&self, id: &str) -> Result<(), PlantError> {
+        let mut tasks = self.tasks.lock().await;
+        let mut config = self.config();
+        let Some(index) = config.plants.iter().position(|p| p.id == id) else {
+            return Err(PlantError::NotFound(format!("plant '{}' not found", id)));
+        };
+        config.plants.remove(index);
+        self.commit(&config)?;
+
+        // The last cycle finishes before the state goes, so it cannot bring the plant back
+        if let Some(task) = tasks.plants.remove(id) {
+            task.stop().await;
+        }
+        self.state.remove_plant(id);
+        self.sync_modbus(&mut tasks, &config);
+        self.state.push_event(
+            Some(id.to_string()),
+            EventKind::SettingChanged,
+            format!("Plant '{}' deleted", id),
+            None,
+        );
+        println!("[PLANTS] Plant {} deleted", id);
+        Ok(())
+    }
+
+    /// Validate the changed plant list, write it to config.json when asked
+    /// to, then make it the live configuration. Collisions with another plant
+    /// are conflicts, anything else an invalid plant.
+    fn commit(&self, config: &Config) -> Result<(), PlantError> {
+        config.validate_register_overlaps().map_err(PlantError::Conflict)?;
+        config.modbus_endpoints().map_err(PlantError::Conflict)?;
+        config.validate_plants().map_err(PlantError::Invalid)?;
+        if config.persist_changes {
+            self.persist(config).map_err(|e| PlantError::Persist(format!("{} not updated: {}", self.path, e)))?;
+        }
+        *self.config.write().unwrap_or_else(|e| e.into_inner()) = config.clone();
+        Ok(())
+    }
+
+    /// Rewrite the plant list of config.json, leaving every other setting as written.
+    fn persist(&self, config: &Config) -> Result<(), String> {
+        let content = std::fs::read_to_string(&self.path).map_err(|e| e.to_string())?;
+        let mut document: serde_json::Value = serde_json::from_str(&content).map_err(|e| e.to_string())?;
+        document["plants"] = serde_json::to_value(&config.plants).map_err(|e| e.to_string())?;
+        let json = serde_json::to_string_pretty(&document).map_err(|e| e.to_string())?;
+        let tmp = format!("{}.tmp", self.path);
+        std::fs::write(&tmp, json + "\n").map_err(|e| format!("{}: {}", tmp, e))?;
+        std::fs::rename(&tmp, &self.path).map_err(|e| format!("{}: {}", self.path, e))
+    }
+
+    fn spawn_plant(&self, tasks: &mut Tasks, config: &Config, plant: &PlantConfig, first: Instant) {
+        let stop = self.shutdown.child();
+        let handle = tokio::spawn(run_plant(self.state.clone(), plant.clone(), config.simulation.clone(), first, stop.clone()));
+        tasks.plants.insert(plant.id.clone(), Task { stop, handle });
+    }
+
+    /// Bring the Modbus servers in line with `config`: every map rebuilt, a
+    /// server started for a port newly needed and stopped for a port no
+    /// longer used.
+    fn sync_modbus(&self, tasks: &mut Tasks, config: &Config) {
+        for maps in &tasks.fleet {
+            maps.replace(modbus_server::build_register_map(&config.plants));
+        }
+        if !tasks.modbus {
+            return;
+        }
+        let shared: Vec<PlantConfig> = config.plants.iter()
+            .filter(|p| p.modbus_mapping.port.is_none())
+            .cloned()
+            .collect();
+        if let Some((maps, _)) = &tasks.shared {
+            maps.replace(modbus_server::build_register_map(&shared));
+        } else if !shared.is_empty() || config.plants.is_empty() {
+            match config.modbus_addr() {
+                Ok(addr) => {
+                    let maps = LiveRegisterMaps::new(modbus_server::build_register_map(&shared));
+                    let task = self.spawn_modbus(config, addr, maps.clone());
+                    tasks.shared = Some((maps, task));
+                }
+                Err(e) => eprintln!("[MODBUS] Shared port not served: {}", e),
+            }
+        }
+
+        let ports: HashMap<&str, (u16, &PlantConfig)> = config.plants.iter()
+            .filter_map(|p| p.modbus_mapping.port.map(|port| (p.id.as_str(), (port, p))))
+            .collect();
+        let closed: Vec<String> = tasks.dedicated.iter()
+            .filter(|(id, server)| ports.get(id.as_str()).is_none_or(|(port, _)| *port != server.port))
+            .map(|(id, _)| id.clone())
+            .collect();
+        for id in closed {
+            if let Some(server) = tasks.dedicated.remove(&id) {
+                server.maps.replace(modbus_server::RegisterMaps::default());
+                server.task.stop.trigger();
+            }
+        }
+        for (id, (port, plant)) in ports {
+            let maps = modbus_server::build_register_map(std::slice::from_ref(plant));
+            if let Some(server) = tasks.dedicated.get(id) {
+                server.maps.replace(maps);
+                continue;
+            }
+            match config.modbus_addr_of(plant) {
+                Ok(addr) => {
+                    let maps = LiveRegisterMaps::new(maps);
+                    let task = self.spawn_modbus(config, addr, maps.clone());
+                    tasks.dedicated.insert(id.to_string(), DedicatedServer { port, maps, task });
+                }
+                Err(e) => eprintln!("[MODBUS] Plant {} not served: {}", id, e),
+            }
+        }
+    }
+
+    fn spawn_modbus(&self, config: &Config, addr: std::net::SocketAddr, maps: LiveRegisterMaps) -> Task {
+        let stop = self.shutdown.child();
+        let (state, modbus, server_stop) = (self.state.clone(), config.modbus.clone(), stop.clone());
+        let handle = tokio::spawn(async move {
+            if let Err(e) = modbus_server::run_server(addr, state, maps, modbus, server_stop).await {
+                eprintln!("Modbus server error on {}: {}", addr, e);
+            }
+        });
+        Task { stop, handle }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(plants: serde_json::Value) -> Config {
+        serde_json::from_value(serde_json::json!({
+            "server": { "port": 3000 }, "modbus": { "port": 5020 }, "offline_mode": true, "plants": plants
+        })).unwrap()
+    }
+
+    fn plant(id: &str, base: u16) -> PlantConfig {
+        serde_json::from_value(serde_json::json!({
+            "id": id, "name": id, "latitude": 45.0, "longitude": 7.0, "nominal_power_kw": 100.0,
+            "timezone": "UTC", "modbus_mapping": { "base_address": base }
+        })).unwrap()
+    }
+
+    #[tokio::test]
+    async fn plants_come_and_go_while_collisions_are_refused() {
+        let state = AppState::new(true);
+        let registry = PlantRegistry::new(state.clone(), config(serde_json::json!([plant("plant_1", 0)])), "config.json", Shutdown::new());
+        configure_plant(&state, &registry.config(), &plant("plant_1", 0));
+        registry.start_plants().await;
+        let fleet = registry.fleet_maps().await;
+
+        assert!(matches!(registry.create(plant("plant_1", 200)).await, Err(PlantError::Conflict(_))));
+        let Err(PlantError::Conflict(e)) = registry.create(plant("plant_2", 50)).await else { panic!("overlap accepted") };
+        assert!(e.contains("plant_1") && e.contains("plant_2"), "{e}");
+        let invalid = PlantConfig { nominal_power_kw: 0.0, ..plant("plant_2", 200) };
+        assert!(matches!(registry.create(invalid).await, Err(PlantError::Invalid(_))));
+        assert_eq!(registry.plants().len(), 1);
+
+        registry.create(plant("plant_2", 200)).await.unwrap();
+        assert_eq!(registry.plants().len(), 2);
+        assert!(fleet.current().shared.registers.contains_key(&200));
+        // The new plant publishes on its first cycle
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        assert!(state.get_data("plant_2").is_some());
+
+        let moved = PlantConfig { latitude: -33.9, nominal_power_kw: 250.0, ..plant("plant_2", 200) };
+        registry.update("plant_2", moved).await.unwrap();
+        assert_eq!(registry.config().plants[1].nominal_power_kw, 250.0);
+        assert!(matches!(registry.update("plant_9", plant("plant_9", 400)).await, Err(PlantError::NotFound(_))));
+        assert!(matches!(registry.update("plant_2", plant("plant_1", 400)).await, Err(PlantError::Invalid(_))));
+
+        registry.delete("plant_2").await.unwrap();
+        assert!(state.get_data("plant_2").is_none());
+        assert!(!fleet.current().shared.registers.contains_key(&200));
+        assert!(matches!(registry.delete("plant_2").await, Err(PlantError::NotFound(_))));
+        let changes = state.find_events(Some(&EventKind::SettingChanged), Some("plant_2"), 10);
+        assert_eq!(changes.len(), 3);
+    }
+}
@@ -2,97 +2,188 @@ use chrono::{DateTime, Utc};
 use reqwest::Error;
 
 use crate::models::power::{
+    CurrentData,
     CurrentWeatherResponse,
+    HourlyData,
+    HourlyForecastResponse,
     SimulationData,
+    WeatherSource,
 };
-use crate::services::solar_algorithm;
+use crate::services::pv_string::DcOperatingPoint;
+use crate::services::solar_algorithm::{self, EstimateParams, OfflineEstimate};
 
-fn estimate_cell_temperature(ambient_temp_c: f64, g_w_m2: f64) -> f64 {
-    // T_cell = T_ambient + (NOCT - 20) * (G / 800)   (NOCT ≈ 45 °C, c-Si typical)
-    let noct = 45.0;
-    ambient_temp_c + (noct - 20.0) * (g_w_m2 / 800.0)
-}
-
-fn estimate_power_kw_from_radiation(g_w_m2: f64, nominal_power_kw: f64, cell_temp_c: f64) -> f64 {
-    let alpha = -0.004; // temperature coefficient %/°C
-    let temp_factor = 1.0 + alpha * (cell_temp_c - 25.0);
-    (nominal_power_kw * (g_w_m2 / 1000.0) * temp_factor).max(0.0)
+/// Cell temperature and per-MPPT DC operating points from measured radiation,
+/// with the same module model as the offline estimate. The measurement
+/// carries no orientation, so every MPPT array sees the same light.
+fn estimate_dc_from_radiation(params: &EstimateParams, g_w_m2: f64, capacity_factor: f64, ambient_temp_c: f64, wind_speed_m_s: f64) -> (Vec<DcOperatingPoint>, f64) {
+    let cell_temp_c = params.module.cell_temperature(ambient_temp_c, g_w_m2, wind_speed_m_s);
+    let mppt = params.arrays().iter()
+        .map(|array| array.dc_output(g_w_m2, cell_temp_c, capacity_factor))
+        .collect();
+    (mppt, cell_temp_c)
 }
 
 /// Fetch current data from Open-Meteo API; falls back to offline on failure.
-pub async fn get_current_data(
-    lat: f64,
-    lon: f64,
-    nominal_power_kw: f64,
-) -> Result<SimulationData, Error> {
+pub async fn get_current_data(params: &EstimateParams) -> Result<SimulationData, Error> {
     let url = format!(
-        "https://api.open-meteo.com/v1/forecast?latitude={}&longitude={}&current=shortwave_radiation,temperature_2m,weather_code,is_day",
-        lat, lon
+        "https://api.open-meteo.com/v1/forecast?latitude={}&longitude={}&current=shortwave_radiation,temperature_2m,weather_code,is_day,rain,wind_speed_10m,relative_humidity_2m,direct_normal_irradiance,diffuse_radiation&wind_speed_unit=ms",
+        params.lat_deg, params.lon_deg
     );
 
     match reqwest::get(&url).await {
         Ok(response) => {
             match response.json::<CurrentWeatherResponse>().await {
-                Ok(resp) => {
-                    let g           = resp.current.shortwave_radiation.unwrap_or(0.0);
-                    let ambient_t   = resp.current.temperature_2m.unwrap_or(20.0);
-                    let weather_c   = resp.current.weather_code.unwrap_or(0);
-                    let is_day      = resp.current.is_day.unwrap_or(1) == 1;
-                    let cell_temp   = estimate_cell_temperature(ambient_t, g);
-                    let power_kw    = estimate_power_kw_from_radiation(g, nominal_power_kw, cell_temp);
-
-                    let ts_fixed    = format!("{}:00Z", resp.current.time);
-                    let timestamp   = ts_fixed.parse::<DateTime<Utc>>().unwrap_or(Utc::now());
-
-                    // Cloud factor approximated from the radiation value
-                    let cloud_guessed = if g > 10.0 { (g / 1000.0).min(1.0) } else { 0.0 };
-
-                    // Wind/humidity/soiling: derive from offline model at current time
-                    // (Open-Meteo basic endpoint does not supply these)
-                    let aux = solar_algorithm::estimate(lat, lon, 0.0, Utc::now());
-
-                    return Ok(SimulationData {
-                        timestamp,
-                        power_kw,
-                        temperature_c: cell_temp,
-                        ambient_temp_c: ambient_t,
-                        weather_code: weather_c,
-                        is_day,
-                        poa_irradiance_w_m2: g,
-                        cloud_factor: cloud_guessed,
-                        solar_elevation_deg: 0.0, // not available from Open-Meteo
-                        wind_speed_m_s:       aux.wind_speed_m_s,
-                        relative_humidity_pct: aux.relative_humidity_pct,
-                        soiling_factor:        aux.soiling_factor,
-                    });
-                }
-                Err(e) => eprintln!("Failed to parse weather data: {}", e),
+                Ok(resp) => return Ok(from_current_weather(params, &resp.current, Utc::now())),
+                Err(e)   => eprintln!("Failed to parse weather data: {}", e),
             }
         }
         Err(e) => eprintln!("Failed to fetch weather data: {}", e),
     }
 
     // API failed → fall back to offline algorithm
-    Ok(get_offline_data(lat, lon, nominal_power_kw))
+    Ok(get_offline_data(params, Utc::now()))
+}
+
+/// Open-Meteo's hourly radiation, temperature and wind forecast for the
+/// next `days` days, in UTC.
+pub async fn get_hourly_forecast(params: &EstimateParams, days: u32) -> Result<HourlyData, String> {
+    let url = format!(
+        "https://api.open-meteo.com/v1/forecast?latitude={}&longitude={}&hourly=shortwave_radiation,temperature_2m,wind_speed_10m&forecast_days={}&wind_speed_unit=ms&timezone=UTC",
+        params.lat_deg, params.lon_deg, days.clamp(1, 16)
+    );
+    let response = reqwest::get(&url).await.map_err(|e| e.to_string())?;
+    response.json::<HourlyForecastResponse>().await.map(|r| r.hourly).map_err(|e| e.to_string())
+}
+
+/// Simulation data from an Open-Meteo `current` block measured around `now`.
+pub(crate) fn from_current_weather(params: &EstimateParams, current: &CurrentData, now: DateTime<Utc>) -> SimulationData {
+    // An active scenario (eclipse) dims the measured light as well
+    let scale       = params.irradiance_factor;
+    let g           = current.shortwave_radiation.unwrap_or(0.0) * scale;
+    // Without the split, all of the measured light counts as diffuse
+    let dni         = current.direct_normal_irradiance.unwrap_or(0.0) * scale;
+    let dhi         = current.diffuse_radiation.map_or(g, |d| d * scale);
+    let ambient_t   = current.temperature_2m.unwrap_or(20.0);
+    let weather_c   = current.weather_code.unwrap_or(0);
+    let is_day      = current.is_day.unwrap_or(1) == 1;
+    // Rain over the current interval, taken as the hourly rate
+    let rain_mm_h   = current.rain.unwrap_or(0.0);
+    // Soiling/snow and the bifacial rear side: derive from offline model at
+    // current time (Open-Meteo does not supply these); so do wind and
+    // humidity when the response leaves them out
+    let aux         = solar_algorithm::estimate(params, now);
+    let wind_m_s    = current.wind_speed_10m.map_or(aux.wind_speed_m_s, |w| w.max(0.0));
+    let humidity    = current.relative_humidity_2m.map_or(aux.relative_humidity_pct, |h| h.clamp(0.0, 100.0));
+    let front       = g * (1.0 - aux.snow_cover_factor * (1.0 - solar_algorithm::SNOW_TRANSMITTANCE));
+    let g_eff       = (front + params.bifaciality.unwrap_or(0.0) * aux.rear_irradiance_w_m2)
+        * aux.soiling_factor;
+    let degradation = params.degradation_factor(now);
+    let (mppt, cell_temp) = estimate_dc_from_radiation(params, g_eff, params.capacity_factor(now), ambient_t, wind_m_s);
+
+    let ts_fixed    = format!("{}:00Z", current.time);
+    let timestamp   = ts_fixed.parse::<DateTime<Utc>>().unwrap_or(now);
+
+    // Cloud factor approximated from the radiation value
+    let cloud_guessed = if g > 10.0 { (g / 1000.0).min(1.0) } else { 0.0 };
+
+    SimulationData {
+        timestamp,
+        power_kw: mppt.iter().map(DcOperatingPoint::power_kw).sum(),
+        temperature_c: cell_temp,
+        ambient_temp_c: ambient_t,
+        weather_code: weather_c,
+        is_day,
+        poa_irradiance_w_m2: g,
+        ghi_w_m2: g,
+        dni_w_m2: dni,
+        dhi_w_m2: dhi,
+        rear_irradiance_w_m2: aux.rear_irradiance_w_m2,
+        cloud_factor: cloud_guessed,
+        solar_elevation_deg: aux.solar_elevation_deg, // sun position from the model (not in the Open-Meteo block)
+        solar_azimuth_deg:   aux.solar_azimuth_deg,
+        source:              WeatherSource::OpenMeteo,
+        wind_speed_m_s:        wind_m_s,
+        relative_humidity_pct: humidity,
+        soiling_factor:        aux.soiling_factor,
+        rain_mm_h,
+        snow_cover_factor:     aux.snow_cover_factor,
+        degradation_factor:    degradation,
+        tracker_stowed:        aux.tracker_stowed,
+        mppt,
+    }
+}
+
+/// Pure offline estimation at `now` — no network calls.
+pub fn get_offline_data(params: &EstimateParams, now: DateTime<Utc>) -> SimulationData {
+    from_estimate(now, solar_algorithm::estimate(params, now))
 }
 
-/// Pure offline estimation — no network calls.
-pub fn get_offline_data(lat: f64, lon: f64, nominal_power_kw: f64) -> SimulationData {
-    let now = Utc::now();
-    let est = solar_algorithm::estimate(lat, lon, nominal_power_kw, now);
+/// Simulation data from a solar model estimate for `timestamp`.
+pub fn from_estimate(timestamp: DateTime<Utc>, est: OfflineEstimate) -> SimulationData {
     SimulationData {
-        timestamp:             now,
+        timestamp,
         power_kw:              est.power_kw,
         temperature_c:         est.cell_temp_c,
         ambient_temp_c:        est.ambient_temp_c,
         weather_code:          est.weather_code,
         is_day:                est.is_day,
-        poa_irradiance_w_m2:   est.ghi_w_m2,
+        poa_irradiance_w_m2:   est.poa_w_m2,
+        ghi_w_m2:              est.ghi_w_m2,
+        dni_w_m2:              est.dni_w_m2,
+        dhi_w_m2:              est.dhi_w_m2,
+        rear_irradiance_w_m2:  est.rear_irradiance_w_m2,
         cloud_factor:          est.cloud_factor,
         solar_elevation_deg:   est.solar_elevation_deg,
+        solar_azimuth_deg:     est.solar_azimuth_deg,
+        source:                WeatherSource::Model,
         wind_speed_m_s:        est.wind_speed_m_s,
         relative_humidity_pct: est.relative_humidity_pct,
         soiling_factor:        est.soiling_factor,
+        rain_mm_h:             est.rain_mm_h,
+        snow_cover_factor:     est.snow_cover_factor,
+        degradation_factor:    est.degradation_factor,
+        tracker_stowed:        est.tracker_stowed,
+        mppt:                  est.mppt,
     }
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn response(wind_m_s: f64) -> CurrentWeatherResponse {
+        serde_json::from_str(&format!(r#"{{"current": {{
+            "time": "2025-06-21T11:00", "shortwave_radiation": 850.0, "temperature_2m": 28.0,
+            "weather_code": 0, "is_day": 1, "rain": 0.0,
+            "wind_speed_10m": {wind_m_s}, "relative_humidity_2m": 41.0
+        }}}}"#)).unwrap()
+    }
+
+    #[test]
+    fn measured_wind_cools_the_cells() {
+        let params = EstimateParams::new(45.07, 7.33, 100.0);
+        let now    = Utc.with_ymd_and_hms(2025, 6, 21, 11, 0, 0).unwrap();
+        let calm   = from_current_weather(&params, &response(0.0).current, now);
+        let windy  = from_current_weather(&params, &response(10.0).current, now);
+
+        // Faiman: 850 W/m² over U0 = 25 vs U0 + 10·U1 = 93.4 W/(m²·K)
+        let cooling = calm.temperature_c - windy.temperature_c;
+        assert!(cooling > 20.0 && cooling < 30.0, "{cooling:.1} °C");
+        assert!(windy.power_kw > calm.power_kw);
+        assert_eq!((windy.wind_speed_m_s, windy.relative_humidity_pct), (10.0, 41.0));
+        assert_eq!(windy.timestamp, now);
+
+        // Older responses without wind fall back to the modelled value
+        let mut bare = response(0.0).current;
+        bare.wind_speed_10m = None;
+        let modelled = from_current_weather(&params, &bare, now);
+        assert_eq!(modelled.wind_speed_m_s, solar_algorithm::estimate(&params, now).wind_speed_m_s);
+
+        // A scenario dims the measured light as well
+        let eclipsed = from_current_weather(&EstimateParams { irradiance_factor: 0.1, ..params.clone() }, &response(0.0).current, now);
+        assert!((eclipsed.poa_irradiance_w_m2 - 85.0).abs() < 1e-9);
+        assert!(eclipsed.power_kw < 0.15 * calm.power_kw, "{:.1} of {:.1} kW", eclipsed.power_kw, calm.power_kw);
+    }
+}
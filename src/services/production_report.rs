@@ -0,0 +1,312 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use chrono_tz::Tz;
+
+use crate::models::power::{Alarm, AlarmCounts, EnergyPeriod, FleetProductionReport, ProductionReport};
+use crate::services::expected_energy::local_midnight;
+use crate::services::history::HistoryPoint;
+
+/// Reference yield (kWh) below which no performance ratio is given, as for
+/// the running one
+const MIN_REFERENCE_YIELD_KWH: f64 = 0.1;
+
+// ─── Day totals ──────────────────────────────────────────────
+/// A day as the energy counters booked it: today's running counters, or the
+/// totals an ENERGY_ROLLOVER closed the day with.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DayTotals {
+    pub energy_kwh:        f64,
+    pub peak_power_kw:     f64,
+    pub insolation_kwh_m2: f64,
+    /// None on rollovers logged before the counter was part of them
+    pub curtailed_energy_kwh: Option<f64>,
+}
+
+impl DayTotals {
+    /// Closed day and its totals from an ENERGY_ROLLOVER payload.
+    pub fn from_rollover(payload: &serde_json::Value) -> Option<(NaiveDate, Self)> {
+        let date = NaiveDate::parse_from_str(payload["date"].as_str()?, "%Y-%m-%d").ok()?;
+        Some((date, Self {
+            energy_kwh:           payload["daily_energy_kwh"].as_f64()?,
+            peak_power_kw:        payload["peak_power_kw"].as_f64().unwrap_or(0.0),
+            insolation_kwh_m2:    payload["insolation_kwh_m2"].as_f64().unwrap_or(0.0),
+            curtailed_energy_kwh: payload["curtailed_energy_kwh"].as_f64(),
+        }))
+    }
+}
+
+// ─── Plant report ────────────────────────────────────────────
+/// Everything a plant's report is worked out from.
+pub struct ReportInput<'a> {
+    pub plant_id:          &'a str,
+    pub timezone:          Tz,
+    pub nominal_power_kw:  f64,
+    pub max_ac_kw:         f64,
+    /// A plant updating less than once a minute fills fewer history minutes
+    pub update_interval_s: f64,
+    pub period:            EnergyPeriod,
+    /// Local days `start..end` (end exclusive)
+    pub days:              (NaiveDate, NaiveDate),
+    pub now:               DateTime<Utc>,
+    /// Oldest minute the history buffer can still hold
+    pub history_since:     DateTime<Utc>,
+    /// 1-minute history over the period
+    pub minutes:           &'a [HistoryPoint],
+    /// Counter totals by local day
+    pub counters:          &'a BTreeMap<NaiveDate, DayTotals>,
+    pub alarms:            &'a [Alarm],
+}
+
+/// UTC span of the local days `start..end`.
+pub fn span(timezone: Tz, (start, end): (NaiveDate, NaiveDate)) -> (DateTime<Utc>, DateTime<Utc>) {
+    (local_midnight(timezone, start), local_midnight(timezone, end))
+}
+
+fn ratio(numerator: f64, denominator: f64) -> f64 {
+    if denominator > 0.0 { numerator / denominator } else { 0.0 }
+}
+
+/// A plant's figures over the period, day by day: the counters' totals where
+/// they exist, the history's sums elsewhere. Days still to come add nothing.
+pub fn plant_report(input: &ReportInput) -> ProductionReport {
+    let step_s = input.update_interval_s.max(60.0);
+    let (mut energy, mut insolation, mut curtailed, mut peak) = (0.0, 0.0, 0.0, 0.0_f64);
+    let mut peak_at: Option<(f64, DateTime<Utc>)> = None;
+    let (mut known_s, mut elapsed_s) = (0.0, 0.0);
+
+    for day in input.days.0.iter_days().take_while(|d| *d < input.days.1) {
+        let Some(next) = day.succ_opt() else { break };
+        let (from, to) = span(input.timezone, (day, next));
+        if from >= input.now {
+            break;
+        }
+        let until = to.min(input.now);
+        let minutes: Vec<&HistoryPoint> = input.minutes.iter()
+            .filter(|p| p.timestamp >= from && p.timestamp < until)
+            .collect();
+        // The first of equally high minutes; a night has no peak
+        if let Some(top) = minutes.iter().filter(|p| p.power_kw > 0.0).min_by(|a, b| b.power_kw.total_cmp(&a.power_kw)) {
+            if peak_at.is_none_or(|(kw, _)| top.power_kw > kw) {
+                peak_at = Some((top.power_kw, top.timestamp));
+            }
+        }
+        let history_curtailed: f64 = minutes.iter().map(|p| p.curtailed_energy_kwh).sum();
+        match input.counters.get(&day) {
+            Some(totals) => {
+                energy     += totals.energy_kwh;
+                insolation += totals.insolation_kwh_m2;
+                curtailed  += totals.curtailed_energy_kwh.unwrap_or(history_curtailed);
+                peak        = peak.max(totals.peak_power_kw);
+            }
+            None => {
+                energy     += minutes.iter().map(|p| p.energy_kwh).sum::<f64>();
+                insolation += minutes.iter().map(|p| p.poa_irradiance_w_m2 / 1000.0 * step_s / 3600.0).sum::<f64>();
+                curtailed  += history_curtailed;
+                peak        = peak.max(minutes.iter().map(|p| p.power_kw).fold(0.0, f64::max));
+            }
+        }
+
+        // Within the history's reach a minute without samples is missing;
+        // beyond it, a day is known only if the counters closed it
+        let reach = input.history_since.clamp(from, until);
+        let in_reach_s = (until - reach).num_seconds() as f64;
+        let covered_s = (minutes.len() as f64 * step_s).min(in_reach_s);
+        let beyond_s = if input.counters.contains_key(&day) { (reach - from).num_seconds() as f64 } else { 0.0 };
+        known_s   += covered_s + beyond_s;
+        elapsed_s += (until - from).num_seconds() as f64;
+    }
+
+    let (from, to) = span(input.timezone, input.days);
+    let mut alarms = AlarmCounts::default();
+    for alarm in input.alarms.iter().filter(|a| a.timestamp >= from && a.timestamp < to) {
+        alarms.add(&alarm.severity);
+    }
+    let reference_kwh = input.nominal_power_kw * insolation;
+    ProductionReport {
+        plant_id:               input.plant_id.to_string(),
+        period:                 input.period,
+        start:                  input.days.0,
+        end:                    input.days.1.pred_opt().unwrap_or(input.days.1),
+        energy_kwh:             energy,
+        peak_power_kw:          peak,
+        // The counters keep no time: a peak of theirs above any minute has none
+        peak_power_at:          peak_at.filter(|(kw, _)| *kw >= peak).map(|(_, at)| at),
+        full_load_hours:        ratio(energy, input.max_ac_kw),
+        performance_ratio:      (reference_kwh > MIN_REFERENCE_YIELD_KWH).then(|| (energy / reference_kwh).clamp(0.0, 1.0)),
+        specific_yield_kwh_kwp: ratio(energy, input.nominal_power_kw),
+        insolation_kwh_m2:      insolation,
+        curtailed_energy_kwh:   curtailed,
+        alarms,
+        completeness_pct:       (ratio(known_s, elapsed_s) * 100.0).min(100.0),
+    }
+}
+
+// ─── Fleet report ────────────────────────────────────────────
+/// One plant of a fleet report with the ratings its figures are weighed by.
+pub struct FleetRow {
+    pub report:           ProductionReport,
+    pub nominal_power_kw: f64,
+    pub max_ac_kw:        f64,
+}
+
+/// Fleet totals over the plants' rows; `peak` is the highest simultaneous
+/// output of the fleet, from its aggregated history.
+pub fn fleet_report(
+    period: EnergyPeriod,
+    days: (NaiveDate, NaiveDate),
+    rows: Vec<FleetRow>,
+    peak: Option<(f64, DateTime<Utc>)>,
+) -> FleetProductionReport {
+    let nominal: f64 = rows.iter().map(|r| r.nominal_power_kw).sum();
+    let max_ac: f64 = rows.iter().map(|r| r.max_ac_kw).sum();
+    let energy: f64 = rows.iter().map(|r| r.report.energy_kwh).sum();
+    let reference_kwh: f64 = rows.iter().map(|r| r.nominal_power_kw * r.report.insolation_kwh_m2).sum();
+    let mut alarms = AlarmCounts::default();
+    for row in &rows {
+        alarms.merge(row.report.alarms);
+    }
+    FleetProductionReport {
+        period,
+        start:                  days.0,
+        end:                    days.1.pred_opt().unwrap_or(days.1),
+        energy_kwh:             energy,
+        peak_power_kw:          peak.map_or(0.0, |(kw, _)| kw),
+        peak_power_at:          peak.map(|(_, at)| at),
+        full_load_hours:        ratio(energy, max_ac),
+        performance_ratio:      (reference_kwh > MIN_REFERENCE_YIELD_KWH).then(|| (energy / reference_kwh).clamp(0.0, 1.0)),
+        specific_yield_kwh_kwp: ratio(energy, nominal),
+        curtailed_energy_kwh:   rows.iter().map(|r| r.report.curtailed_energy_kwh).sum(),
+        alarms,
+        completeness_pct:       ratio(rows.iter().map(|r| r.report.completeness_pct * r.nominal_power_kw).sum(), nominal),
+        plants:                 rows.into_iter().map(|r| r.report).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use crate::models::power::AlarmSeverity;
+
+    fn date(day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, 6, day).unwrap()
+    }
+
+    /// Minutes of a steady 60 kW under 800 W/m² from `from` for `count` minutes.
+    fn minutes(from: DateTime<Utc>, count: i64) -> Vec<HistoryPoint> {
+        (0..count).map(|m| HistoryPoint {
+            timestamp: from + Duration::minutes(m), power_kw: 60.0, poa_irradiance_w_m2: 800.0,
+            temperature_c: 40.0, ambient_temp_c: 25.0, inverter_temp_c: 45.0, energy_kwh: 1.0,
+            true_energy_kwh: 1.0, curtailed_energy_kwh: 0.01, daily_energy_kwh: 0.0,
+        }).collect()
+    }
+
+    fn alarm(severity: AlarmSeverity, at: DateTime<Utc>) -> Alarm {
+        Alarm {
+            id: "a".into(), plant_id: "plant_1".into(), code: 301, severity, message: String::new(),
+            timestamp: at, active: true, cleared_at: None, acknowledged: false, acknowledged_by: None,
+            acknowledged_at: None, note: None,
+        }
+    }
+
+    fn input<'a>(days: (NaiveDate, NaiveDate), now: DateTime<Utc>, minutes: &'a [HistoryPoint],
+                 counters: &'a BTreeMap<NaiveDate, DayTotals>, alarms: &'a [Alarm]) -> ReportInput<'a> {
+        ReportInput {
+            plant_id: "plant_1", timezone: Tz::UTC, nominal_power_kw: 100.0, max_ac_kw: 80.0,
+            update_interval_s: 5.0, period: EnergyPeriod::Day, days, now,
+            history_since: now - Duration::hours(24), minutes, counters, alarms,
+        }
+    }
+
+    #[test]
+    fn a_day_from_the_history_alone_flags_its_gaps() {
+        let midnight = Utc.with_ymd_and_hms(2025, 6, 21, 0, 0, 0).unwrap();
+        let now = midnight + Duration::hours(12);
+        // Six hours of samples out of the twelve so far
+        let history = minutes(midnight + Duration::hours(6), 360);
+        let alarms = [
+            alarm(AlarmSeverity::Warning, midnight + Duration::hours(7)),
+            alarm(AlarmSeverity::Fault, midnight + Duration::hours(8)),
+            alarm(AlarmSeverity::Fault, midnight - Duration::hours(1)),
+        ];
+        let report = plant_report(&input((date(21), date(22)), now, &history, &BTreeMap::new(), &alarms));
+
+        assert_eq!(report.energy_kwh, 360.0);
+        assert_eq!((report.peak_power_kw, report.peak_power_at), (60.0, Some(midnight + Duration::hours(6))));
+        assert!((report.insolation_kwh_m2 - 4.8).abs() < 1e-9);
+        assert_eq!(report.full_load_hours, 4.5);
+        assert_eq!(report.specific_yield_kwh_kwp, 3.6);
+        assert!((report.performance_ratio.unwrap() - 0.75).abs() < 1e-9);
+        assert!((report.curtailed_energy_kwh - 3.6).abs() < 1e-9);
+        assert_eq!(report.alarms, AlarmCounts { warning: 1, fault: 1, ..AlarmCounts::default() });
+        assert!((report.completeness_pct - 50.0).abs() < 1e-9, "{}", report.completeness_pct);
+    }
+
+    #[test]
+    fn closed_days_take_the_counters_and_days_never_seen_are_missing() {
+        let now = Utc.with_ymd_and_hms(2025, 6, 10, 12, 0, 0).unwrap();
+        let counters = BTreeMap::from([
+            (date(3), DayTotals { energy_kwh: 500.0, peak_power_kw: 78.0, insolation_kwh_m2: 6.0, curtailed_energy_kwh: Some(2.0) }),
+            (date(4), DayTotals { energy_kwh: 450.0, peak_power_kw: 75.0, insolation_kwh_m2: 5.5, curtailed_energy_kwh: None }),
+        ]);
+        let month = (date(1), NaiveDate::from_ymd_opt(2025, 7, 1).unwrap());
+        let report = plant_report(&ReportInput { period: EnergyPeriod::Month, ..input(month, now, &[], &counters, &[]) });
+
+        assert_eq!(report.energy_kwh, 950.0);
+        assert_eq!((report.peak_power_kw, report.peak_power_at), (78.0, None));
+        assert_eq!(report.curtailed_energy_kwh, 2.0);
+        assert!((report.performance_ratio.unwrap() - 950.0 / 1150.0).abs() < 1e-9);
+        assert_eq!((report.start, report.end), (date(1), date(30)));
+        // Two closed days out of nine and a half elapsed; the history reaching
+        // back 24 h holds nothing either
+        assert!((report.completeness_pct - 2.0 / 9.5 * 100.0).abs() < 1e-9, "{}", report.completeness_pct);
+
+        // A day still to come has nothing to report
+        let future = plant_report(&input((date(11), date(12)), now, &[], &counters, &[]));
+        assert_eq!((future.energy_kwh, future.completeness_pct, future.performance_ratio), (0.0, 0.0, None));
+    }
+
+    #[test]
+    fn the_peak_time_belongs_to_the_reported_peak() {
+        let midnight = Utc.with_ymd_and_hms(2025, 6, 21, 0, 0, 0).unwrap();
+        let now = midnight + Duration::hours(12);
+        let history = minutes(midnight + Duration::hours(6), 60);
+        let day = (date(21), date(22));
+
+        // The counters caught a 5 s peak above every minute's mean: no time for it
+        let counters = BTreeMap::from([(date(21), DayTotals { peak_power_kw: 72.0, ..DayTotals::default() })]);
+        let report = plant_report(&input(day, now, &history, &counters, &[]));
+        assert_eq!((report.peak_power_kw, report.peak_power_at), (72.0, None));
+
+        // Where the minute is the peak, its time is given
+        let counters = BTreeMap::from([(date(21), DayTotals { peak_power_kw: 60.0, ..DayTotals::default() })]);
+        let report = plant_report(&input(day, now, &history, &counters, &[]));
+        assert_eq!((report.peak_power_kw, report.peak_power_at), (60.0, Some(midnight + Duration::hours(6))));
+    }
+
+    #[test]
+    fn the_fleet_adds_up_its_plants_weighted_by_rating() {
+        let row = |id: &str, energy_kwh, insolation_kwh_m2, nominal_power_kw, completeness_pct| FleetRow {
+            report: ProductionReport {
+                plant_id: id.to_string(), period: EnergyPeriod::Day, start: date(21), end: date(21), energy_kwh,
+                peak_power_kw: 0.0, peak_power_at: None, full_load_hours: 0.0, performance_ratio: None,
+                specific_yield_kwh_kwp: 0.0, insolation_kwh_m2, curtailed_energy_kwh: 1.0,
+                alarms: AlarmCounts { critical: 1, ..AlarmCounts::default() }, completeness_pct,
+            },
+            nominal_power_kw,
+            max_ac_kw: nominal_power_kw,
+        };
+        let at = Utc.with_ymd_and_hms(2025, 6, 21, 11, 0, 0).unwrap();
+        let fleet = fleet_report(EnergyPeriod::Day, (date(21), date(22)),
+            vec![row("plant_1", 400.0, 5.0, 100.0, 100.0), row("plant_2", 1200.0, 6.0, 300.0, 50.0)], Some((350.0, at)));
+
+        assert_eq!(fleet.energy_kwh, 1600.0);
+        assert_eq!(fleet.specific_yield_kwh_kwp, 4.0);
+        assert!((fleet.performance_ratio.unwrap() - 1600.0 / 2300.0).abs() < 1e-9);
+        assert_eq!((fleet.peak_power_kw, fleet.peak_power_at), (350.0, Some(at)));
+        assert_eq!((fleet.curtailed_energy_kwh, fleet.alarms.critical), (2.0, 2));
+        assert_eq!(fleet.completeness_pct, 62.5);
+        assert_eq!(fleet.plants.len(), 2);
+    }
+}
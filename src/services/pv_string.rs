@@ -0,0 +1,135 @@
+use crate::config::{ModuleRating, PanelConfig};
+
+/// Modules per string when the layout leaves it unset: about 580 V at the MPP,
+/// and a cold-morning V_oc inside the 770 V DC input.
+pub const DEFAULT_MODULES_PER_STRING: u32 = 14;
+
+/// Voltage gain per e-fold of irradiance, relative to the STC voltage:
+/// the diode term n·kT/q over a c-Si cell V_oc (1.2 × 25.7 mV / 0.68 V).
+const LN_IRRADIANCE_V: f64 = 0.045;
+
+// ─── String layout ───────────────────────────────────────────
+/// Series/parallel arrangement of identical modules behind one MPPT.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct StringLayout {
+    pub module: ModuleRating,
+    pub modules_per_string: u32,
+    pub strings: u32,
+}
+
+impl StringLayout {
+    /// The panel's layout; a missing string count is sized to `nominal_power_kw`.
+    pub fn for_panel(panel: &PanelConfig, nominal_power_kw: f64) -> Self {
+        let module = panel.module;
+        let modules_per_string = panel.modules_per_string.unwrap_or(DEFAULT_MODULES_PER_STRING).max(1);
+        let strings = panel.strings.unwrap_or_else(|| {
+            let string_kw = module.vmp_v * module.imp_a * modules_per_string as f64 / 1000.0;
+            (nominal_power_kw / string_kw).round().max(1.0) as u32
+        });
+        Self { module, modules_per_string, strings }
+    }
+
+    /// Default modules sized to `nominal_power_kw`.
+    pub fn sized_for(nominal_power_kw: f64) -> Self {
+        Self::for_panel(&PanelConfig::default(), nominal_power_kw)
+    }
+
+    /// Array power at STC (kW)
+    pub fn stc_power_kw(&self) -> f64 {
+        self.module.vmp_v * self.module.imp_a * (self.modules_per_string * self.strings) as f64 / 1000.0
+    }
+
+    /// Operating point at `poa_w_m2` effective irradiance and `cell_temp_c`.
+    /// Currents scale with irradiance and `derate` (module ageing); voltages
+    /// fall with temperature and log-linearly at low light.
+    pub fn operating_point(&self, poa_w_m2: f64, cell_temp_c: f64, derate: f64) -> DcOperatingPoint {
+        let m = &self.module;
+        let g = poa_w_m2 / 1000.0;
+        if g <= 0.0 {
+            return DcOperatingPoint { strings: self.strings, ..DcOperatingPoint::default() };
+        }
+        let dt = cell_temp_c - 25.0;
+        let current_k = g * (1.0 + m.isc_temp_coeff_pct_per_c / 100.0 * dt) * derate.max(0.0);
+        let voltage_k = ((1.0 + m.voc_temp_coeff_pct_per_c / 100.0 * dt) * (1.0 + LN_IRRADIANCE_V * g.ln())).max(0.0);
+        let series = self.modules_per_string as f64;
+        DcOperatingPoint {
+            voc_v:   m.voc_v * series * voltage_k,
+            isc_a:   m.isc_a * current_k,
+            vmp_v:   m.vmp_v * series * voltage_k,
+            imp_a:   m.imp_a * current_k,
+            strings: self.strings,
+        }
+    }
+}
+
+// ─── Operating point ─────────────────────────────────────────
+/// String voltages and per-string currents of an array.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DcOperatingPoint {
+    /// String open-circuit voltage (V)
+    pub voc_v: f64,
+    /// String short-circuit current (A)
+    pub isc_a: f64,
+    /// String voltage at the maximum power point (V)
+    pub vmp_v: f64,
+    /// String current at the maximum power point (A)
+    pub imp_a: f64,
+    pub strings: u32,
+}
+
+impl DcOperatingPoint {
+    /// DC power: V_mp × I_mp × strings (kW)
+    pub fn power_kw(&self) -> f64 {
+        self.vmp_v * self.imp_a * self.strings as f64 / 1000.0
+    }
+
+    /// Array current at the maximum power point (A)
+    pub fn array_current_a(&self) -> f64 {
+        self.imp_a * self.strings as f64
+    }
+
+    /// Same voltages with the currents rescaled to deliver `power_kw`, for
+    /// arrays whose power comes from the nominal-power model.
+    pub fn with_power(self, power_kw: f64) -> Self {
+        let now = self.power_kw();
+        let k = if now > 0.0 { power_kw.max(0.0) / now } else { 0.0 };
+        Self { isc_a: self.isc_a * k, imp_a: self.imp_a * k, ..self }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hot_cells_lower_the_mpp_voltage() {
+        let layout = StringLayout::sized_for(1000.0);
+        let cool = layout.operating_point(1000.0, 25.0, 1.0);
+        let hot  = layout.operating_point(1000.0, 65.0, 1.0);
+
+        let drop = 1.0 - hot.vmp_v / cool.vmp_v;
+        assert!((drop - 0.08).abs() < 0.005, "V_mp drop {:.1} %", drop * 100.0);
+        assert!(hot.imp_a > cool.imp_a);
+        assert!((cool.vmp_v - 14.0 * 41.5).abs() < 1e-9);
+        assert!((cool.power_kw() - layout.stc_power_kw()).abs() < 1e-9);
+        assert!((layout.stc_power_kw() / 1000.0 - 1.0).abs() < 0.01, "{:.1} kW", layout.stc_power_kw());
+    }
+
+    #[test]
+    fn current_follows_irradiance() {
+        let layout = StringLayout::sized_for(100.0);
+        let full = layout.operating_point(1000.0, 25.0, 1.0);
+        let low  = layout.operating_point(200.0, 25.0, 1.0);
+        assert!((low.imp_a / full.imp_a - 0.2).abs() < 1e-9);
+        assert!((low.isc_a / full.isc_a - 0.2).abs() < 1e-9);
+        // Low light costs a little voltage too
+        assert!(low.vmp_v < full.vmp_v && low.vmp_v > 0.9 * full.vmp_v);
+
+        let dark = layout.operating_point(0.0, 10.0, 1.0);
+        assert_eq!((dark.power_kw(), dark.voc_v), (0.0, 0.0));
+
+        let fitted = low.with_power(12.5);
+        assert!((fitted.power_kw() - 12.5).abs() < 1e-9);
+        assert_eq!(fitted.vmp_v, low.vmp_v);
+    }
+}
@@ -0,0 +1,145 @@
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+use crate::config::ScenarioConfig;
+
+/// Largest irradiance multiplier a profile may ask for (cloud-edge enhancement)
+pub const MAX_FACTOR: f64 = 2.0;
+
+// ─── Irradiance scenarios ────────────────────────────────────
+/// Irradiance override in force between `start` and `end`.
+#[derive(Clone, Debug, PartialEq, Serialize, ToSchema)]
+pub struct Scenario {
+    pub id: String,
+    pub name: String,
+    /// None = every plant
+    pub plant_id: Option<String>,
+    #[schema(value_type = String)]
+    pub start: DateTime<Utc>,
+    #[schema(value_type = String)]
+    pub end: DateTime<Utc>,
+    /// [share of the window, multiplier] points, sorted
+    pub profile: Vec<[f64; 2]>,
+    /// Inside its window (the start has been logged)
+    pub active: bool,
+}
+
+impl Scenario {
+    /// Checks the window and the profile of `config`.
+    pub fn for_config(id: String, config: &ScenarioConfig) -> Result<Self, String> {
+        if config.end <= config.start {
+            return Err(format!("scenario {}: end must come after start", config.name));
+        }
+        if config.profile.is_empty() {
+            return Err(format!("scenario {}: profile needs at least one point", config.name));
+        }
+        if let Some([at, factor]) = config.profile.iter().find(|[at, factor]| {
+            !(0.0..=1.0).contains(at) || !(0.0..=MAX_FACTOR).contains(factor)
+        }) {
+            return Err(format!(
+                "scenario {}: point [{}, {}] must lie within [0..1, 0..{}]", config.name, at, factor, MAX_FACTOR,
+            ));
+        }
+        let mut profile = config.profile.clone();
+        profile.sort_by(|a, b| a[0].total_cmp(&b[0]));
+        Ok(Self {
+            id,
+            name:     config.name.clone(),
+            plant_id: config.plant_id.clone(),
+            start:    config.start,
+            end:      config.end,
+            profile,
+            active:   false,
+        })
+    }
+
+    pub fn applies_to(&self, plant_id: &str) -> bool {
+        self.plant_id.as_deref().is_none_or(|id| id == plant_id)
+    }
+
+    /// Irradiance multiplier at `time`; None outside the window. The
+    /// profile holds its first and last values beyond its ends.
+    pub fn factor_at(&self, time: DateTime<Utc>) -> Option<f64> {
+        if time < self.start || time >= self.end {
+            return None;
+        }
+        let span_s = (self.end - self.start).num_milliseconds() as f64;
+        let x = (time - self.start).num_milliseconds() as f64 / span_s;
+        let (first, last) = (self.profile[0], self.profile[self.profile.len() - 1]);
+        Some(match self.profile.iter().position(|p| p[0] > x) {
+            None    => last[1],
+            Some(0) => first[1],
+            Some(i) => {
+                let ([x0, y0], [x1, y1]) = (self.profile[i - 1], self.profile[i]);
+                y0 + (y1 - y0) * (x - x0) / (x1 - x0)
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{Duration, TimeZone};
+    use crate::services::solar_algorithm::{estimate, EstimateParams};
+
+    fn eclipse(start: DateTime<Utc>, plant_id: Option<&str>) -> Scenario {
+        Scenario::for_config("e1".into(), &ScenarioConfig {
+            name: "eclipse".into(),
+            plant_id: plant_id.map(str::to_string),
+            start,
+            end: start + Duration::hours(2),
+            profile: vec![[0.0, 1.0], [0.5, 0.1], [1.0, 1.0]],
+        }).unwrap()
+    }
+
+    #[test]
+    fn synthetic_eclipse_dips_the_power_and_recovers() {
+        let start = Utc.with_ymd_and_hms(2025, 6, 21, 9, 0, 0).unwrap();
+        let scenario = eclipse(start, None);
+        let params = EstimateParams::new(45.07, 7.33, 1000.0);
+        let power = |t: DateTime<Utc>| {
+            let factor = scenario.factor_at(t).unwrap_or(1.0);
+            (estimate(&EstimateParams { irradiance_factor: factor, ..params.clone() }, t).power_kw, estimate(&params, t).power_kw)
+        };
+
+        // Untouched before and after the window
+        for t in [start - Duration::minutes(5), start + Duration::hours(2), start + Duration::hours(3)] {
+            let (eclipsed, normal) = power(t);
+            assert_eq!(eclipsed, normal, "{t}");
+        }
+        // Maximum at mid-window: about a tenth of the light, so of the power
+        let (eclipsed, normal) = power(start + Duration::hours(1));
+        assert!(normal > 100.0 && (eclipsed / normal - 0.1).abs() < 0.03, "{eclipsed:.1} of {normal:.1} kW");
+        // Down the V and back up
+        let ratios: Vec<f64> = (0..=24).map(|i| {
+            let (eclipsed, normal) = power(start + Duration::minutes(5 * i));
+            eclipsed / normal
+        }).collect();
+        assert!(ratios[..12].windows(2).all(|w| w[1] < w[0]), "{ratios:?}");
+        assert!(ratios[12..].windows(2).all(|w| w[1] > w[0]), "{ratios:?}");
+        assert_eq!(ratios[24], 1.0);
+    }
+
+    #[test]
+    fn profiles_are_checked_and_scoped() {
+        let start = Utc.with_ymd_and_hms(2026, 8, 12, 17, 30, 0).unwrap();
+        let config = |end, profile| ScenarioConfig { name: "s".into(), plant_id: None, start, end, profile };
+        assert!(Scenario::for_config("x".into(), &config(start, vec![[0.0, 1.0]])).is_err());
+        assert!(Scenario::for_config("x".into(), &config(start + Duration::hours(1), vec![])).is_err());
+        assert!(Scenario::for_config("x".into(), &config(start + Duration::hours(1), vec![[1.5, 1.0]])).is_err());
+        assert!(Scenario::for_config("x".into(), &config(start + Duration::hours(1), vec![[0.5, -0.1]])).is_err());
+
+        // A single point holds all window long; unsorted points are sorted
+        let flat = Scenario::for_config("x".into(), &config(start + Duration::hours(1), vec![[0.3, 0.5]])).unwrap();
+        assert_eq!(flat.factor_at(start), Some(0.5));
+        assert_eq!(flat.factor_at(start + Duration::minutes(59)), Some(0.5));
+        let ramp = Scenario::for_config("x".into(), &config(start + Duration::hours(1), vec![[1.0, 0.0], [0.0, 1.0]])).unwrap();
+        assert_eq!(ramp.factor_at(start + Duration::minutes(15)), Some(0.75));
+
+        let one = eclipse(start, Some("plant_1"));
+        assert!(one.applies_to("plant_1") && !one.applies_to("plant_2"));
+        assert!(eclipse(start, None).applies_to("plant_2"));
+    }
+}
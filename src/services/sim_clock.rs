@@ -0,0 +1,81 @@
+use chrono::{DateTime, Duration, Utc};
+
+/// Fastest the virtual clock may run: a day in about 9 s
+pub const MAX_SPEED: f64 = 10_000.0;
+
+// ─── Virtual clock ───────────────────────────────────────────
+/// Simulation time: `speed` virtual seconds pass per wall-clock second,
+/// counted from `start` at the moment the clock was set. At 1× from the
+/// current time it is the wall clock.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SimClock {
+    speed:       f64,
+    wall_anchor: DateTime<Utc>,
+    sim_anchor:  DateTime<Utc>,
+}
+
+impl Default for SimClock {
+    fn default() -> Self {
+        let now = Utc::now();
+        Self { speed: 1.0, wall_anchor: now, sim_anchor: now }
+    }
+}
+
+impl SimClock {
+    /// Clock running at `speed` from `start` (unset = the current time) as of `wall`.
+    pub fn new(speed: f64, start: Option<DateTime<Utc>>, wall: DateTime<Utc>) -> Result<Self, String> {
+        if !(speed > 0.0 && speed <= MAX_SPEED) {
+            return Err(format!("speed must be above 0 and at most {}, not {}", MAX_SPEED, speed));
+        }
+        Ok(Self { speed, wall_anchor: wall, sim_anchor: start.unwrap_or(wall) })
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.speed
+    }
+
+    /// Virtual time at wall-clock time `wall`.
+    pub fn at(&self, wall: DateTime<Utc>) -> DateTime<Utc> {
+        let elapsed_ms = (wall - self.wall_anchor).num_milliseconds() as f64 * self.speed;
+        self.sim_anchor + Duration::milliseconds(elapsed_ms.round() as i64)
+    }
+
+    pub fn now(&self) -> DateTime<Utc> {
+        self.at(Utc::now())
+    }
+
+    /// Seconds the clock is ahead of the wall clock (negative = behind).
+    pub fn offset_s(&self) -> f64 {
+        let wall = Utc::now();
+        (self.at(wall) - wall).num_milliseconds() as f64 / 1000.0
+    }
+
+    /// Whether the clock follows the wall clock.
+    pub fn is_realtime(&self) -> bool {
+        self.speed == 1.0 && self.sim_anchor == self.wall_anchor
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn the_virtual_clock_runs_at_its_speed_from_its_start() {
+        let wall = Utc.with_ymd_and_hms(2026, 1, 15, 9, 0, 0).unwrap();
+        let start = Utc.with_ymd_and_hms(2025, 6, 21, 0, 0, 0).unwrap();
+        let clock = SimClock::new(1440.0, Some(start), wall).unwrap();
+        assert_eq!(clock.at(wall), start);
+        assert_eq!(clock.at(wall + Duration::minutes(1)), start + Duration::days(1));
+        assert_eq!(clock.at(wall + Duration::milliseconds(500)), start + Duration::seconds(720));
+        assert!(!clock.is_realtime());
+
+        let realtime = SimClock::new(1.0, None, wall).unwrap();
+        assert!(realtime.is_realtime());
+        assert_eq!(realtime.at(wall + Duration::seconds(5)), wall + Duration::seconds(5));
+
+        assert!(SimClock::new(0.0, None, wall).is_err());
+        assert!(SimClock::new(MAX_SPEED * 2.0, None, wall).unwrap_err().contains("at most"));
+    }
+}
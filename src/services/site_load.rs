@@ -0,0 +1,64 @@
+use chrono::{DateTime, TimeZone, Timelike};
+
+use crate::config::{LoadShape, SiteLoadConfig};
+
+// ─── Site load ───────────────────────────────────────────────
+/// Consumption behind a plant's grid meter as a function of the local hour.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SiteLoad {
+    pub base_kw: f64,
+    pub peak_kw: f64,
+    pub shape: LoadShape,
+}
+
+impl SiteLoad {
+    pub fn for_config(config: &SiteLoadConfig) -> Self {
+        Self { base_kw: config.base_kw.max(0.0), peak_kw: config.peak_kw.max(0.0), shape: config.shape }
+    }
+
+    /// Load (kW) at `local_h` hours after local midnight.
+    pub fn load_kw(&self, local_h: f64) -> f64 {
+        let h = local_h.rem_euclid(24.0);
+        let shape = match self.shape {
+            // Breakfast, then cooking and lighting in the evening
+            LoadShape::Residential => 0.6 * bump(h, 7.5, 1.2) + bump(h, 19.5, 2.0),
+            // Smooth edges at opening and closing time
+            LoadShape::Commercial  => 0.5 * (((h - 8.0) / 0.7).tanh() - ((h - 18.0) / 0.7).tanh()),
+            LoadShape::Flat        => 0.0,
+        };
+        self.base_kw + self.peak_kw * shape
+    }
+
+    /// Load (kW) at `time`, on the clock of its time zone.
+    pub fn load_at<Tz: TimeZone>(&self, time: &DateTime<Tz>) -> f64 {
+        self.load_kw(time.hour() as f64 + time.minute() as f64 / 60.0 + time.second() as f64 / 3600.0)
+    }
+}
+
+/// Gaussian bump of unit height centred on `centre_h`, wrapping at midnight.
+fn bump(h: f64, centre_h: f64, width_h: f64) -> f64 {
+    let d = (h - centre_h + 12.0).rem_euclid(24.0) - 12.0;
+    (-0.5 * (d / width_h).powi(2)).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shapes_peak_when_people_use_power() {
+        let load = |shape| SiteLoad { base_kw: 0.5, peak_kw: 3.0, shape };
+
+        let home = load(LoadShape::Residential);
+        assert!((home.load_kw(19.5) - 3.5).abs() < 0.01);
+        assert!(home.load_kw(7.5) > home.load_kw(12.0) && home.load_kw(12.0) < 1.0);
+        assert!((home.load_kw(3.0) - 0.5).abs() < 0.01);
+        assert_eq!(home.load_kw(-4.5), home.load_kw(19.5));
+
+        let office = load(LoadShape::Commercial);
+        assert!((office.load_kw(13.0) - 3.5).abs() < 0.01);
+        assert!(office.load_kw(22.0) < 0.51);
+
+        assert_eq!(load(LoadShape::Flat).load_kw(12.0), 0.5);
+    }
+}
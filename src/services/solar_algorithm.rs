@@ -7,85 +7,449 @@
 ///   2. Extraterrestrial irradiance – eccentricity-corrected solar constant
 ///   3. Clear-sky model  – Ineichen / Bird & Hulstrom simplified:
 ///                         DNI, DHI, GHI on horizontal plane
-///   4. Panel tilt / IAM – irradiance on tilted surface (transposition)
+///   4. Panel tilt / IAM – irradiance on tilted surface (isotropic or
+///                        Perez transposition), plus the rear side of
+///                        bifacial modules
 ///   5. Climatological cloud/haze factor – latitude + season + deterministic
 ///                         pseudo-random daily variation
 ///   6. Ambient temperature model – latitude × season × diurnal cycle
 ///   7. Cell temperature  – Faiman / Ross model
-///   8. Power output      – P = P_nom × (G_poa/1000) × η_temp
+///   8. Power output      – P = P_nom × (G_poa/1000) × η_temp, or
+///                        V_mp × I_mp × strings from the module ratings
 /// ============================================================
 
-use chrono::{DateTime, Utc, Datelike, Timelike};
+use chrono::{DateTime, NaiveDate, Utc, Datelike, Timelike};
+use chrono_tz::Tz;
 use std::f64::consts::PI;
 
+use crate::config::{HorizonPoint, LinkeTurbidity, Mounting, PanelConfig, PlantConfig, Technology, Transposition};
+use crate::services::pv_string::{DcOperatingPoint, StringLayout};
+
 // ─── Physical constants ──────────────────────────────────────
 const SC: f64 = 1361.0; // Solar constant W/m²
 const DEG: f64 = PI / 180.0;
+/// Environmental lapse rate (°C per km of altitude)
+const LAPSE_RATE_C_PER_KM: f64 = 6.5;
+
+/// A stowed tracker resumes once wind drops below this fraction of `stow_wind_m_s`.
+const STOW_RESUME_RATIO: f64 = 0.8;
+
+/// Great-circle distance per degree of latitude (km).
+const KM_PER_DEG: f64 = 111.2;
+
+/// Slant width (m) of a collector row — two modules in portrait.
+const COLLECTOR_WIDTH_M: f64 = 2.0;
+
+/// Share of the light that still reaches cells under a full snow layer.
+pub const SNOW_TRANSMITTANCE: f64 = 0.03;
+
+/// Dirtiest the modules get: dust crusts over but light still gets through.
+pub const MIN_SOILING_FACTOR: f64 = 0.70;
+
+/// Incidence angle modifier for sky and ground light, which arrives from
+/// every direction at once (ASHRAE b0 = 0.05 averaged over the hemisphere).
+const DIFFUSE_IAM: f64 = 0.95;
 
 // ─── Public output ───────────────────────────────────────────
+#[derive(Debug, PartialEq)]
 pub struct OfflineEstimate {
     pub power_kw: f64,
+    /// Plane-of-array irradiance on the front face (W/m²)
+    pub poa_w_m2: f64,
+    /// Global horizontal irradiance (W/m²), as a level pyranometer reads it
     pub ghi_w_m2: f64,
+    /// Direct normal irradiance (W/m²)
+    pub dni_w_m2: f64,
+    /// Diffuse horizontal irradiance (W/m²)
+    pub dhi_w_m2: f64,
+    /// Irradiance on the rear side of bifacial modules (0 for monofacial)
+    pub rear_irradiance_w_m2: f64,
     pub cell_temp_c: f64,
     pub ambient_temp_c: f64,
     pub weather_code: u16,
     pub is_day: bool,
     pub cloud_factor: f64,
     pub solar_elevation_deg: f64,
+    /// Solar azimuth (degrees from North, clockwise)
+    pub solar_azimuth_deg: f64,
+    /// Clear-sky direct normal irradiance (W/m²)
+    pub dni_clear_sky_w_m2: f64,
+    /// Linke turbidity used by the clear-sky model
+    pub linke_turbidity: f64,
     /// Wind speed at 10 m (m/s) — affects cell cooling
     pub wind_speed_m_s: f64,
     /// Relative humidity at surface (%) — affects dew/soiling
     pub relative_humidity_pct: f64,
     /// Panel soiling factor [0..1] (1.0 = perfectly clean panel)
     pub soiling_factor: f64,
+    /// Rain rate (mm/h); snowfall does not count
+    pub rain_mm_h: f64,
+    /// Share of the front face buried under snow [0..1] (0.0 = no snow)
+    pub snow_cover_factor: f64,
+    /// Share of nameplate capacity left after module ageing [0..1]
+    pub degradation_factor: f64,
+    /// Angle between the sun and the panel normal (90° while the sun is down)
+    pub angle_of_incidence_deg: f64,
+    /// Share of the beam transmitted through the glass at that angle [0..1]
+    pub iam: f64,
+    /// Tracker parked flat because of high wind
+    pub tracker_stowed: bool,
+    /// String voltages and currents of each MPPT input; the powers add up to `power_kw`
+    pub mppt: Vec<DcOperatingPoint>,
+}
+
+// ─── Plant input ─────────────────────────────────────────────
+/// Site and array description used by `estimate`.
+#[derive(Clone, Debug)]
+pub struct EstimateParams {
+    /// Geographic latitude (−90 … +90)
+    pub lat_deg: f64,
+    /// Geographic longitude (−180 … +180)
+    pub lon_deg: f64,
+    /// Peak DC capacity of the plant
+    pub nominal_power_kw: f64,
+    /// Site elevation above sea level (m)
+    pub altitude_m: f64,
+    /// Panel tilt from horizontal; None = |latitude| capped at 60°
+    pub tilt_deg: Option<f64>,
+    /// Surface azimuth (degrees from North, clockwise); None = facing the equator
+    pub azimuth_deg: Option<f64>,
+    /// Ground reflectance; None = 0.20
+    pub albedo: Option<f64>,
+    pub mounting: Mounting,
+    /// Elevation range (°) a tracker can point its panels at
+    pub tracker_elevation_deg: (f64, f64),
+    /// Wind speed (m/s) above which a tracker stows flat; None = never
+    pub stow_wind_m_s: Option<f64>,
+    /// Stow state of the previous cycle, for the resume hysteresis
+    pub tracker_stowed: bool,
+    /// Rear-to-front efficiency ratio; None = monofacial modules
+    pub bifaciality: Option<f64>,
+    /// Clearance between the lower module edge and the ground (m)
+    pub height_m: f64,
+    /// Ground coverage ratio of the row field; None = a single row
+    pub gcr: Option<f64>,
+    /// Number of rows; None = a field large enough to ignore the front row
+    pub rows: Option<u32>,
+    /// Beam loss per unit of shaded collector area
+    pub electrical_shading_factor: f64,
+    /// Skyline as (azimuth°, elevation°) sorted by azimuth; empty = open horizon
+    pub horizon: Vec<(f64, f64)>,
+    /// Start of module ageing; None = a new plant
+    pub commissioning_date: Option<NaiveDate>,
+    /// Capacity loss per year of service (%)
+    pub degradation_pct_per_year: f64,
+    /// Capacity loss over the first year (%); None = the yearly rate
+    pub first_year_degradation_pct: Option<f64>,
+    pub transposition: Transposition,
+    /// Linke turbidity; None = latitude/season heuristic
+    pub linke_turbidity: Option<LinkeTurbidity>,
+    /// Multiplier on the aerosol optical depth
+    pub aod_scale: f64,
+    /// Bias on the relative humidity model (RH points)
+    pub humidity_offset_pct: f64,
+    pub module: ModuleParams,
+    /// Modules and strings behind the MPPT
+    pub string_layout: StringLayout,
+    /// DC power from the module ratings of `string_layout` rather than
+    /// `nominal_power_kw` and the technology preset
+    pub layout_rated: bool,
+    /// Separately oriented arrays on their own MPPTs; empty = the array above
+    pub mppt_inputs: Vec<MpptInput>,
+    /// Civil time zone for the daily weather cycle
+    pub timezone: Tz,
+    /// Weather scenario seed mixed into every noise hash (0 = built-in scenario)
+    pub seed: u64,
+    /// Distance over which plants share cloud noise (km)
+    pub cloud_correlation_km: f64,
+    /// Day-to-day persistence of the cloudiness anomaly [0..1)
+    pub cloud_persistence: f64,
+    /// Dust build-up between rains (% of output per day)
+    pub soiling_rate_pct_per_day: f64,
+    /// Live soiling of the modules; None = replayed from the weather history
+    pub soiling_factor: Option<f64>,
+    /// ASHRAE incidence angle modifier coefficient; 0 = no reflection loss
+    pub iam_b0: f64,
+    /// Multiplier on the light reaching the site from an active scenario
+    /// (eclipse, rehearsal); 1 = the weather model alone
+    pub irradiance_factor: f64,
+    /// Capacity lost to storm damage awaiting repair (%); 0 = intact
+    pub damage_pct: f64,
+}
+
+impl EstimateParams {
+    /// Fixed, equator-facing array at latitude tilt.
+    pub fn new(lat_deg: f64, lon_deg: f64, nominal_power_kw: f64) -> Self {
+        Self {
+            lat_deg, lon_deg, nominal_power_kw, altitude_m: 0.0,
+            tilt_deg: None, azimuth_deg: None, albedo: None,
+            mounting: Mounting::Fixed, tracker_elevation_deg: (0.0, 90.0),
+            stow_wind_m_s: None, tracker_stowed: false,
+            bifaciality: None, height_m: 1.0,
+            gcr: None, rows: None, electrical_shading_factor: 1.0,
+            horizon: Vec::new(),
+            commissioning_date: None, degradation_pct_per_year: 0.5, first_year_degradation_pct: None,
+            transposition: Transposition::Isotropic,
+            linke_turbidity: None, aod_scale: 1.0, humidity_offset_pct: 0.0,
+            module: ModuleParams::preset(Technology::MonoSi),
+            string_layout: StringLayout::sized_for(nominal_power_kw), layout_rated: false,
+            mppt_inputs: Vec::new(),
+            timezone: Tz::UTC, seed: 0, cloud_correlation_km: 20.0, cloud_persistence: 0.7,
+            soiling_rate_pct_per_day: 0.3, soiling_factor: None,
+            iam_b0: 0.05,
+            irradiance_factor: 1.0,
+            damage_pct: 0.0,
+        }
+    }
+
+    pub fn for_plant(plant: &PlantConfig) -> Self {
+        let panel = &plant.panel;
+        Self {
+            altitude_m:                 plant.altitude_m.unwrap_or(0.0),
+            tilt_deg:                   panel.tilt_deg,
+            azimuth_deg:                panel.azimuth_deg,
+            albedo:                     panel.albedo,
+            mounting:                   panel.mounting,
+            tracker_elevation_deg:      (panel.tracker_min_elevation_deg, panel.tracker_max_elevation_deg),
+            stow_wind_m_s:              panel.stow_wind_m_s,
+            bifaciality:                panel.bifacial.then_some(panel.bifaciality),
+            height_m:                   panel.height_m,
+            gcr:                        panel.gcr.or(panel.row_pitch_m.map(|pitch| COLLECTOR_WIDTH_M / pitch)),
+            rows:                       panel.rows,
+            electrical_shading_factor:  panel.electrical_shading_factor,
+            horizon:                    horizon_profile(&plant.horizon),
+            commissioning_date:         plant.commissioning_date,
+            degradation_pct_per_year:   panel.degradation_pct_per_year,
+            first_year_degradation_pct: panel.first_year_degradation_pct,
+            transposition:              panel.transposition,
+            linke_turbidity:            plant.atmosphere.linke_turbidity,
+            aod_scale:                  plant.atmosphere.aod_scale,
+            humidity_offset_pct:        plant.atmosphere.humidity_offset_pct,
+            module:                     ModuleParams::for_panel(panel.technology, panel.temp_coeff_pct_per_c),
+            string_layout:              StringLayout::for_panel(panel, plant.nominal_power_kw),
+            layout_rated:               panel.modules_per_string.is_some() && panel.strings.is_some(),
+            mppt_inputs:                MpptInput::for_plant(plant),
+            timezone:                   plant.timezone,
+            seed:                       plant.seed.unwrap_or(0),
+            soiling_rate_pct_per_day:   panel.soiling_rate_pct_per_day,
+            iam_b0:                     panel.iam_b0,
+            ..Self::new(plant.latitude, plant.longitude, plant.nominal_power_kw)
+        }
+    }
+
+    /// Share of nameplate capacity left at `utc_now`: the first-year loss
+    /// (when set) over year one, then the yearly rate.
+    pub fn degradation_factor(&self, utc_now: DateTime<Utc>) -> f64 {
+        let Some(commissioned) = self.commissioning_date else { return 1.0 };
+        let age_years = ((utc_now.date_naive() - commissioned).num_days() as f64 / 365.25).max(0.0);
+        let loss_pct = match self.first_year_degradation_pct {
+            Some(first) => first * age_years.min(1.0) + self.degradation_pct_per_year * (age_years - 1.0).max(0.0),
+            None        => self.degradation_pct_per_year * age_years,
+        };
+        (1.0 - loss_pct / 100.0).clamp(0.0, 1.0)
+    }
+
+    /// Share of nameplate the array can still deliver at `utc_now`: ageing
+    /// and any unrepaired damage.
+    pub fn capacity_factor(&self, utc_now: DateTime<Utc>) -> f64 {
+        self.degradation_factor(utc_now) * (1.0 - self.damage_pct / 100.0).clamp(0.0, 1.0)
+    }
+
+    /// One set of parameters per MPPT input, each with its own orientation
+    /// and strings; just this array without MPPT inputs.
+    pub fn arrays(&self) -> Vec<EstimateParams> {
+        if self.mppt_inputs.is_empty() {
+            return vec![self.clone()];
+        }
+        self.mppt_inputs.iter().map(|input| EstimateParams {
+            tilt_deg:         input.tilt_deg,
+            azimuth_deg:      input.azimuth_deg,
+            nominal_power_kw: input.nominal_power_kw,
+            string_layout:    input.string_layout,
+            layout_rated:     input.layout_rated,
+            mppt_inputs:      Vec::new(),
+            ..self.clone()
+        }).collect()
+    }
+
+    /// DC operating point at `poa_w_m2` effective irradiance. Without module
+    /// ratings the nominal-power model sets the power and the default string
+    /// layout only shapes voltages and currents.
+    pub fn dc_output(&self, poa_w_m2: f64, cell_temp_c: f64, degradation_factor: f64) -> DcOperatingPoint {
+        let point = self.string_layout.operating_point(poa_w_m2, cell_temp_c, degradation_factor);
+        if self.layout_rated {
+            point
+        } else {
+            point.with_power(self.module.dc_power_kw(poa_w_m2, self.nominal_power_kw * degradation_factor, cell_temp_c))
+        }
+    }
+
+    /// Whether the tracker is stowed at `wind_m_s`: it stows above
+    /// `stow_wind_m_s` and resumes below `STOW_RESUME_RATIO` of it.
+    fn stows_at(&self, wind_m_s: f64) -> bool {
+        match (self.mounting, self.stow_wind_m_s) {
+            (Mounting::Tracker2Axis, Some(limit)) if self.tracker_stowed => wind_m_s >= limit * STOW_RESUME_RATIO,
+            (Mounting::Tracker2Axis, Some(limit))                        => wind_m_s > limit,
+            _ => false,
+        }
+    }
+}
+
+// ─── MPPT inputs ─────────────────────────────────────────────
+/// Array on one MPPT input of the inverter.
+#[derive(Clone, Debug)]
+pub struct MpptInput {
+    /// Panel tilt from horizontal; None = |latitude| capped at 60°
+    pub tilt_deg: Option<f64>,
+    /// Surface azimuth (degrees from North, clockwise); None = facing the equator
+    pub azimuth_deg: Option<f64>,
+    /// Share of the plant's DC capacity on this input
+    pub nominal_power_kw: f64,
+    pub string_layout: StringLayout,
+    /// DC power from the module ratings of `string_layout`
+    pub layout_rated: bool,
+}
+
+impl MpptInput {
+    /// The plant's MPPT inputs; each one without a string count takes an
+    /// equal share of `nominal_power_kw`.
+    pub fn for_plant(plant: &PlantConfig) -> Vec<Self> {
+        let share = plant.nominal_power_kw / plant.mppt_inputs.len().max(1) as f64;
+        plant.mppt_inputs.iter().map(|input| {
+            let panel = PanelConfig {
+                modules_per_string: input.modules_per_string.or(plant.panel.modules_per_string),
+                strings:            input.strings,
+                ..plant.panel.clone()
+            };
+            Self {
+                tilt_deg:         input.tilt_deg.or(plant.panel.tilt_deg),
+                azimuth_deg:      input.azimuth_deg.or(plant.panel.azimuth_deg),
+                nominal_power_kw: share,
+                string_layout:    StringLayout::for_panel(&panel, share),
+                layout_rated:     panel.modules_per_string.is_some() && panel.strings.is_some(),
+            }
+        }).collect()
+    }
+}
+
+// ─── Module technology ───────────────────────────────────────
+/// Electrical and thermal behaviour of a module technology.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ModuleParams {
+    /// Power temperature coefficient (%/°C)
+    pub temp_coeff_pct_per_c: f64,
+    /// Faiman constant heat loss U0 (W/(m²·K))
+    pub u0: f64,
+    /// Faiman wind-dependent heat loss U1 (W/(m²·K·(m/s)))
+    pub u1: f64,
+    /// Low-light efficiency loss k in η_rel = 1 + k·ln(G/1000); None = linear in G
+    pub low_light_coeff: Option<f64>,
+}
+
+impl ModuleParams {
+    /// Typical datasheet and field-fitted (Faiman) values per technology.
+    pub fn preset(technology: Technology) -> Self {
+        let (temp_coeff_pct_per_c, u0, u1, low_light_coeff) = match technology {
+            Technology::MonoSi => (-0.40, 25.00, 6.84, None),
+            Technology::PolySi => (-0.42, 25.00, 6.84, Some(0.010)),
+            Technology::Cdte   => (-0.28, 23.37, 5.44, None),
+            Technology::Cigs   => (-0.36, 22.64, 4.45, Some(0.015)),
+            Technology::Hjt    => (-0.26, 25.00, 6.84, None),
+        };
+        Self { temp_coeff_pct_per_c, u0, u1, low_light_coeff }
+    }
+
+    /// `technology` preset with an optional explicit temperature coefficient.
+    pub fn for_panel(technology: Technology, temp_coeff_pct_per_c: Option<f64>) -> Self {
+        let preset = Self::preset(technology);
+        Self { temp_coeff_pct_per_c: temp_coeff_pct_per_c.unwrap_or(preset.temp_coeff_pct_per_c), ..preset }
+    }
+
+    /// Cell temperature (Faiman 2008): T_cell = T_ambient + G_poa / (U0 + U1 · wind)
+    pub fn cell_temperature(&self, ambient_temp_c: f64, poa_w_m2: f64, wind_speed_m_s: f64) -> f64 {
+        ambient_temp_c + poa_w_m2 / (self.u0 + self.u1 * wind_speed_m_s)
+    }
+
+    /// DC power: P = P_nom × (G/1000) × η_temp × η_low-light
+    pub fn dc_power_kw(&self, poa_w_m2: f64, nominal_power_kw: f64, cell_temp_c: f64) -> f64 {
+        let temp_factor = 1.0 + self.temp_coeff_pct_per_c / 100.0 * (cell_temp_c - 25.0);
+        let low_light = match self.low_light_coeff {
+            Some(k) if poa_w_m2 > 0.0 && poa_w_m2 < 1000.0 => (1.0 + k * (poa_w_m2 / 1000.0).ln()).max(0.5),
+            _ => 1.0,
+        };
+        (nominal_power_kw * (poa_w_m2 / 1000.0) * temp_factor * low_light).max(0.0)
+    }
 }
 
 /// Main entry point – call once per update cycle.
 ///
-/// * `lat_deg`  – geographic latitude  (−90 … +90)
-/// * `lon_deg`  – geographic longitude (−180 … +180)
-/// * `nominal_power_kw` – peak DC capacity of the plant
-/// * `utc_now`  – current UTC timestamp (from Utc::now())
-pub fn estimate(
-    lat_deg: f64,
-    lon_deg: f64,
-    nominal_power_kw: f64,
-    utc_now: DateTime<Utc>,
-) -> OfflineEstimate {
+/// * `params`  – site location, capacity and array orientation
+/// * `utc_now` – current UTC timestamp (from Utc::now())
+///
+/// With several MPPT inputs each array runs through the irradiance pipeline
+/// on its own and the inverter adds up their DC power.
+pub fn estimate(params: &EstimateParams, utc_now: DateTime<Utc>) -> OfflineEstimate {
+    if params.mppt_inputs.is_empty() {
+        return estimate_array(params, utc_now);
+    }
+    let arrays: Vec<(f64, OfflineEstimate)> = params.arrays().iter()
+        .map(|array| (array.nominal_power_kw, estimate_array(array, utc_now)))
+        .collect();
+    combine_arrays(arrays)
+}
+
+/// Plant-level view of several MPPT arrays: powers add up, irradiance and
+/// temperatures are capacity-weighted means, the weather is shared.
+fn combine_arrays(arrays: Vec<(f64, OfflineEstimate)>) -> OfflineEstimate {
+    let total_kw: f64 = arrays.iter().map(|(kw, _)| kw).sum();
+    let mean = |field: fn(&OfflineEstimate) -> f64| -> f64 {
+        if total_kw > 0.0 {
+            arrays.iter().map(|(kw, e)| kw * field(e)).sum::<f64>() / total_kw
+        } else {
+            arrays.iter().map(|(_, e)| field(e)).sum::<f64>() / arrays.len() as f64
+        }
+    };
+    let power_kw               = arrays.iter().map(|(_, e)| e.power_kw).sum();
+    let poa_w_m2               = mean(|e| e.poa_w_m2);
+    let rear_irradiance_w_m2   = mean(|e| e.rear_irradiance_w_m2);
+    let cell_temp_c            = mean(|e| e.cell_temp_c);
+    let snow_cover_factor      = mean(|e| e.snow_cover_factor);
+    let angle_of_incidence_deg = mean(|e| e.angle_of_incidence_deg);
+    let iam                    = mean(|e| e.iam);
+    let is_day                 = arrays.iter().any(|(_, e)| e.is_day);
+    let mppt                   = arrays.iter().flat_map(|(_, e)| e.mppt.iter().copied()).collect();
+    let (_, first) = arrays.into_iter().next().expect("at least one MPPT array");
+    OfflineEstimate {
+        power_kw, poa_w_m2, rear_irradiance_w_m2, cell_temp_c, snow_cover_factor,
+        angle_of_incidence_deg, iam, is_day, mppt,
+        ..first
+    }
+}
+
+/// Estimate for a single array (one orientation, one MPPT).
+fn estimate_array(params: &EstimateParams, utc_now: DateTime<Utc>) -> OfflineEstimate {
+    let EstimateParams { lat_deg, lon_deg, altitude_m, .. } = *params;
+
     // ── 1. Time decomposition ──────────────────────────────────
     let doy = utc_now.ordinal() as f64; // 1-365/366
     let ut_h = utc_now.hour() as f64
         + utc_now.minute() as f64 / 60.0
         + utc_now.second() as f64 / 3600.0; // UTC decimal hour
+    // Civil time drives the daily weather cycle; the sun only follows longitude
+    let local = utc_now.with_timezone(&params.timezone);
+    let local_doy = local.ordinal() as f64;
+    let local_h = local.hour() as f64
+        + local.minute() as f64 / 60.0
+        + local.second() as f64 / 3600.0;
 
     // ── 2. Solar geometry ──────────────────────────────────────
-    // a) Declination (Spencer 1971, degrees)
+    // a) Declination and b) Equation of Time
     let b = 2.0 * PI * (doy - 1.0) / 365.0;
-    let decl_deg = (180.0 / PI)
-        * (0.006918
-            - 0.399912 * b.cos()
-            + 0.070257 * b.sin()
-            - 0.006758 * (2.0 * b).cos()
-            + 0.000907 * (2.0 * b).sin()
-            - 0.002697 * (3.0 * b).cos()
-            + 0.00148 * (3.0 * b).sin());
-    let decl = decl_deg * DEG;
+    let (decl, eot_min) = declination_and_eot(doy);
 
-    // b) Equation of Time (minutes, Spencer 1971)
-    let eot_min = 229.18
-        * (0.000075
-            + 0.001868 * b.cos()
-            - 0.032077 * b.sin()
-            - 0.014615 * (2.0 * b).cos()
-            - 0.04089 * (2.0 * b).sin());
-
-    // c) Local Solar Time (hours)
-    let lstm_deg = 15.0 * (lon_deg / 15.0).round(); // Standard meridian
-    let tc_min = 4.0 * (lon_deg - lstm_deg) + eot_min; // Time correction
-    // UTC offset from longitude (approximate)
-    let utc_offset_h = (lon_deg / 15.0).round();
-    let local_clock_h = (ut_h + utc_offset_h).rem_euclid(24.0);
-    let lst_h = local_clock_h + tc_min / 60.0; // Local Solar Time
+    // c) Local Solar Time (hours): UTC shifted by 4 min per degree of
+    //    longitude, plus the equation of time
+    let lst_h = (ut_h + (4.0 * lon_deg + eot_min) / 60.0).rem_euclid(24.0);
 
     // d) Hour angle (degrees; negative in morning, positive afternoon)
     let omega_deg = 15.0 * (lst_h - 12.0);
@@ -97,14 +461,10 @@ pub fn estimate(
     let alpha_rad = sin_alpha.asin(); // elevation (rad)
     let alpha_deg = alpha_rad / DEG;
 
-    // f) Solar azimuth (degrees from North, clockwise)
-    let cos_az = if alpha_rad.cos().abs() > 1e-9 {
-        (decl.sin() - sin_alpha * lat.sin()) / (alpha_rad.cos() * lat.cos())
-    } else {
-        0.0
-    };
-    let az_abs = cos_az.clamp(-1.0, 1.0).acos() / DEG;
-    let azimuth_deg = if omega_deg > 0.0 { 360.0 - az_abs } else { az_abs }; // N=0°
+    // f) Solar azimuth (degrees from North, clockwise). The atan2 form stays
+    //    defined with the sun at the zenith, at the poles and at midnight
+    let azimuth_deg = (omega.sin().atan2(omega.cos() * lat.sin() - decl.tan() * lat.cos()) / DEG + 180.0)
+        .rem_euclid(360.0);
 
     // ── 3. Extraterrestrial irradiance (eccentricity correction) ─
     let e0 = SC * (1.00011
@@ -114,34 +474,44 @@ pub fn estimate(
         + 0.000077 * (2.0 * b).sin());
 
     // ── 4. Clear-sky model (Bird & Hulstrom simplified) ────────
-    let (ghi_cs, dni_cs) = if alpha_deg > 0.1 {
+    // Aerosol: variable Linke turbidity TL (1.5 = pristine, 6.5 = heavy haze),
+    // from the plant's atmosphere when configured
+    let tk = match params.linke_turbidity {
+        Some(tl) => tl.for_month(utc_now.month0()),
+        None => {
+            // Continental baseline 3.0; higher in winter (less vertical mixing, more haze)
+            let season_turb = if lat_deg >= 0.0 {
+                // NH: more turbid in winter (dec-jan) and late summer (sep dust); cleaner in spring
+                2.5 + 0.8 * (-(2.0 * PI * (doy - 200.0) / 365.0).cos())
+            } else {
+                2.5 + 0.8 * ((2.0 * PI * (doy - 20.0) / 365.0).cos())
+            };
+            // Daily pseudo-random aerosol noise ±0.7 (wind events, fires, dust storms)
+            let turb_seed = ((lat_deg * 50.0) as i64).wrapping_mul(503)
+                ^ ((lon_deg * 50.0) as i64).wrapping_mul(719)
+                ^ (doy as i64).wrapping_mul(1237)
+                ^ seed_salt(params.seed, 1);
+            let turb_noise = ((turb_seed.wrapping_mul(0x517cc1b727220a95_u64 as i64)) >> 11)
+                as f64 / (1i64 << 53) as f64;
+            (season_turb + (turb_noise - 0.5) * 1.4).clamp(1.5, 6.5)
+        }
+    };
+
+    let (ghi_cs, dni_cs, am) = if alpha_deg > 0.1 {
         // Air mass – Kasten & Young (1989)
         let am = 1.0
             / (sin_alpha
                 + 0.50572 * (alpha_deg + 6.07995_f64).powf(-1.6364));
-        let am = am.max(1.0);
+        // Thinner air at altitude: pressure-corrected (absolute) air mass
+        let am = am.max(1.0) * pressure_ratio(altitude_m);
 
         // Transmittance components (simplified Bird & Hulstrom)
         // Rayleigh
         let tr = (-0.0903 * am.powf(0.84) * (1.0 + am - am.powf(1.01))).exp();
         // Ozone (standard column 0.3 atm-cm)
         let to = 1.0 - 0.0013 * am;
-        // Aerosol: variable Linke turbidity TL (1.5 = pristine, 6.5 = heavy haze)
-        // Continental baseline 3.0; higher in winter (less vertical mixing, more haze)
-        let season_turb = if lat_deg >= 0.0 {
-            // NH: more turbid in winter (dec-jan) and late summer (sep dust); cleaner in spring
-            2.5 + 0.8 * (-(2.0 * PI * (doy - 200.0) / 365.0).cos())
-        } else {
-            2.5 + 0.8 * ((2.0 * PI * (doy - 20.0) / 365.0).cos())
-        };
-        // Daily pseudo-random aerosol noise ±0.7 (wind events, fires, dust storms)
-        let turb_seed = ((lat_deg * 50.0) as i64).wrapping_mul(503)
-            ^ ((lon_deg * 50.0) as i64).wrapping_mul(719)
-            ^ (doy as i64).wrapping_mul(1237);
-        let turb_noise = ((turb_seed.wrapping_mul(0x517cc1b727220a95_u64 as i64)) >> 11)
-            as f64 / (1i64 << 53) as f64;
-        let tk = (season_turb + (turb_noise - 0.5) * 1.4).clamp(1.5, 6.5);
-        let ta = (-0.09 * tk.powf(0.978) * am.powf(0.9455)).exp();
+        // Aerosol
+        let ta = (-0.09 * tk.powf(0.978) * am.powf(0.9455) * params.aod_scale).exp();
         // Water vapour (moderate precipitable water 1.5 cm)
         let tw = 1.0 - 0.0075 * am.powf(0.65);
 
@@ -152,18 +522,38 @@ pub fn estimate(
             * (0.5 * (1.0 - tr) + ba_scatter_coeff(ta))
             / (1.0 - am + am.powf(1.02));
         let ghi_cs = (dni_cs * sin_alpha + dhi_cs).max(0.0);
-        (ghi_cs, dni_cs)
+        (ghi_cs, dni_cs, am)
     } else {
-        (0.0, 0.0)
+        (0.0, 0.0, f64::INFINITY)
     };
+    let dhi_cs = (ghi_cs - dni_cs * sin_alpha.max(0.0)).max(0.0);
+
+    // ── 4b. Horizon: terrain blocks the beam but little of the sky ──
+    let sun_behind_horizon = alpha_deg < horizon_elevation(&params.horizon, azimuth_deg);
+    let dni_cs = if sun_behind_horizon { 0.0 } else { dni_cs };
+    let ghi_cs = dni_cs * sin_alpha.max(0.0) + dhi_cs;
 
     // ── 5. Panel tilt / POA irradiance ─────────────────────────
-    // Optimal tilt ≈ latitude (fixed-tilt south-facing in NH, north-facing in SH)
-    let tilt_deg = lat_deg.abs().min(60.0); // cap at 60°
+    // Wind at 10 m (diurnal + seasonal + daily noise); also drives tracker stow
+    let wind_speed = wind_speed_model(lat_deg, lon_deg, doy, lst_h, params.seed);
+    let tracker_stowed = params.stows_at(wind_speed);
+
+    let (tilt_deg, surf_az_deg) = match params.mounting {
+        // Default: optimal tilt ≈ latitude (fixed-tilt south-facing in NH, north-facing in SH),
+        // capped at 60°
+        Mounting::Fixed => (
+            params.tilt_deg.unwrap_or(lat_deg.abs().min(60.0)),
+            params.azimuth_deg.unwrap_or(if lat_deg >= 0.0 { 180.0 } else { 0.0 }),
+        ),
+        // Parked flat at night and in high wind
+        Mounting::Tracker2Axis if tracker_stowed || alpha_deg <= 0.0 => (0.0, azimuth_deg),
+        // Normal aimed at the sun, within the mechanical elevation range
+        Mounting::Tracker2Axis => {
+            let (min_deg, max_deg) = params.tracker_elevation_deg;
+            (90.0 - alpha_deg.clamp(min_deg, max_deg), azimuth_deg)
+        }
+    };
     let tilt = tilt_deg * DEG;
-    // Surface azimuth: 180° (south) NH; 0° (north) SH
-    let surf_az_deg = if lat_deg >= 0.0 { 180.0 } else { 0.0 };
-    let _surf_az = surf_az_deg * DEG;
 
     // Angle of incidence (θ) between sun and panel normal
     let az_diff = (azimuth_deg - surf_az_deg) * DEG;
@@ -175,84 +565,275 @@ pub fn estimate(
         0.0
     };
 
-    // Beam irradiance on tilted plane
-    let beam_poa = dni_cs * cos_theta;
+    let angle_of_incidence_deg = if alpha_deg > 0.1 { cos_theta.min(1.0).acos() / DEG } else { 90.0 };
 
-    // Diffuse (isotropic sky model)
-    let dhi_cs = (ghi_cs - dni_cs * sin_alpha.max(0.0)).max(0.0);
-    let diffuse_poa = dhi_cs * (1.0 + tilt.cos()) / 2.0;
+    // Beam irradiance on tilted plane, minus the shadow of the row in front
+    let row_shading = match params.gcr {
+        Some(gcr) if cos_theta > 0.0 => {
+            let front_rows = params.rows.map_or(1.0, |n| n.saturating_sub(1) as f64 / n.max(1) as f64);
+            let shaded = row_shaded_fraction(alpha_rad, az_diff, tilt, gcr) * front_rows;
+            (shaded * params.electrical_shading_factor).min(1.0)
+        }
+        _ => 0.0,
+    };
+    // The glass reflects more of the beam the shallower it strikes
+    let iam = iam_ashrae(params.iam_b0, cos_theta);
+    let beam_poa = dni_cs * cos_theta * (1.0 - row_shading) * iam;
+
+    // Sky diffuse: uniform sky, or Perez circumsolar + horizon brightening
+    let diffuse_poa = match params.transposition {
+        Transposition::Isotropic => dhi_cs * (1.0 + tilt.cos()) / 2.0,
+        Transposition::Perez => {
+            // No circumsolar light from a sun hidden by the terrain
+            let cos_theta_sun = if sun_behind_horizon { 0.0 } else { cos_theta };
+            perez_sky_diffuse(dhi_cs, dni_cs, e0, am, PI / 2.0 - alpha_rad, cos_theta_sun, tilt)
+        }
+    };
 
-    // Ground reflected (albedo 0.20)
-    let albedo = 0.20;
+    // Ground reflected (default albedo 0.20 — grass / concrete)
+    let albedo = params.albedo.unwrap_or(0.20);
     let reflected_poa = ghi_cs * albedo * (1.0 - tilt.cos()) / 2.0;
 
-    let ghi_poa_cs = (beam_poa + diffuse_poa + reflected_poa).max(0.0);
+    let ghi_poa_cs = (beam_poa + (diffuse_poa + reflected_poa) * DIFFUSE_IAM).max(0.0);
+
+    // Rear side of bifacial modules
+    let rear_poa_cs = if params.bifaciality.is_some() {
+        rear_irradiance(ghi_cs, dhi_cs, albedo, tilt, params.height_m, params.gcr)
+    } else {
+        0.0
+    };
 
     // ── 6. Climatological cloud / haze attenuation ─────────────
-    let cloud_factor_base = cloud_attenuation(lat_deg, doy, ut_h, lon_deg);
+    let field = CloudField {
+        seed:        params.seed,
+        cell_km:     params.cloud_correlation_km,
+        persistence: params.cloud_persistence,
+    };
+    let cloud_factor_base = cloud_attenuation(lat_deg, local_doy, local_h, lon_deg, field);
 
     // ── 6b. Short-term 5-minute stochastic cloud transient ────
     // Real clouds are broken and intermittent; model a ±18% fluctuation
-    // locked to a 5-minute slot (so it's stable within one update cycle).
+    // locked to a 5-minute slot (so it's stable within one update cycle)
+    // and shared with neighbouring plants under the same cloud field.
     let five_min_slot = (ut_h * 12.0) as i64; // 12 slots/hour
-    let trans_seed = ((lat_deg * 100.0) as i64).wrapping_mul(853)
-        ^ ((lon_deg * 100.0) as i64).wrapping_mul(619)
-        ^ (doy as i64 * 300 + five_min_slot).wrapping_mul(1031);
-    let trans_val =
-        ((trans_seed.wrapping_mul(0x9e3779b97f4a7c15_u64 as i64)) >> 11)
-        as f64 / (1i64 << 53) as f64; // [0,1)
+    let trans_val = field.sample(lat_deg, lon_deg, doy as i64 * 300 + five_min_slot, 2); // [0,1)
     let cloud_transient = (trans_val * 2.0 - 1.0) * 0.18; // ±18%
-    let cloud_factor = (cloud_factor_base + cloud_transient).clamp(0.05, 1.0);
+    let sky_cloud_factor = (cloud_factor_base + cloud_transient).clamp(0.05, 1.0);
+
+    // ── 6c. Morning radiation fog ─────────────────────────────
+    // A saturated, still dawn lays a fog bank that only the rising sun burns off
+    let fog = morning_fog(params, doy, lst_h, local_h - lst_h, field);
+    let cloud_factor = sky_cloud_factor.min(1.0 - (1.0 - fog_cloud_cap(fog.density)) * fog.opacity);
+
+    // A scenario override (eclipse) dims whatever light the weather lets through
+    let light = cloud_factor * params.irradiance_factor;
+    let ghi_poa = ghi_poa_cs * light;
+    let rear_poa = rear_poa_cs * light;
+    // Horizontal components under the same attenuation as the array
+    let (ghi, dni, dhi) = (ghi_cs * light, dni_cs * light, dhi_cs * light);
+
+    // ── 6d. Snow cover ─────────────────────────────────────────
+    // Snowfall days bury the front face; sliding and melt clear it over the
+    // following days. Trackers tip to their steepest angle to shed it.
+    let snow_tilt_deg = match params.mounting {
+        Mounting::Fixed        => tilt_deg,
+        Mounting::Tracker2Axis => 90.0 - params.tracker_elevation_deg.0,
+    };
+    let snow_cover = snow_cover_factor(lat_deg, lon_deg, altitude_m, doy, snow_tilt_deg, field);
+    let front_poa = ghi_poa * (1.0 - snow_cover * (1.0 - SNOW_TRANSMITTANCE));
 
-    let ghi_poa = ghi_poa_cs * cloud_factor;
+    // Bifacial gain counts towards the irradiance the cells convert
+    let effective_poa = front_poa + params.bifaciality.unwrap_or(0.0) * rear_poa;
 
     // ── 7. Ambient temperature model ──────────────────────────
-    let ambient_temp_c = ambient_temperature(lat_deg, doy, lst_h);
-
-    // ── 7b. Wind speed at 10 m (diurnal + seasonal + daily noise) ─
-    let wind_speed = wind_speed_model(lat_deg, lon_deg, doy, lst_h);
+    let ambient_temp_c = ambient_temperature(lat_deg, altitude_m, doy, local_h);
 
-    // ── 7c. Relative humidity ──────────────────────────────────
-    let relative_humidity = relative_humidity_model(lat_deg, doy, lst_h);
+    // ── 7b. Relative humidity ──────────────────────────────────
+    let relative_humidity = relative_humidity_model(lat_deg, doy, lst_h, params.humidity_offset_pct);
 
     // ── 8. Cell temperature (Faiman 2008) ─────────────────────
-    // T_cell = T_ambient + G_poa * (U0 + U1 * wind)^-1
-    // U0=25 W/(m²·K), U1=6.84 W/(m²·K·(m/s)) — crystalline Si
-    let u0 = 25.0_f64;
-    let u1 = 6.84_f64;
-    let cell_temp = ambient_temp_c + ghi_poa / (u0 + u1 * wind_speed);
+    // U0/U1 from the module technology (c-Si: 25 W/(m²·K), 6.84 W/(m²·K·(m/s)))
+    let cell_temp = params.module.cell_temperature(ambient_temp_c, effective_poa, wind_speed);
 
     // ── 8b. Panel soiling factor ───────────────────────────────
-    // Dust accumulates at 0.3%/day; rain (cloudy days) clears it.
-    let soiling_factor = panel_soiling_factor(lat_deg, lon_deg, doy);
+    // The plant's live state when tracked; otherwise dust accumulated since
+    // the last rainy day.
+    let soiling_factor = params.soiling_factor.unwrap_or_else(|| {
+        panel_soiling_factor(lat_deg, lon_deg, doy, field, params.soiling_rate_pct_per_day)
+    });
 
-    // ── 9. DC Power: temperature + soiling coefficients ────────
-    let alpha_temp = -0.004; // %/°C for typical c-Si
-    let temp_factor = 1.0 + alpha_temp * (cell_temp - 25.0);
+    // ── 9. DC Power: temperature + soiling + ageing coefficients ─
     // Apply soiling as an effective irradiance reduction
-    let effective_ghi = ghi_poa * soiling_factor;
-    let power_kw = (nominal_power_kw * (effective_ghi / 1000.0) * temp_factor).max(0.0);
+    let effective_ghi = effective_poa * soiling_factor;
+    // Aged (and damaged) modules deliver a fraction of their nameplate
+    let degradation_factor = params.degradation_factor(utc_now);
+    let dc = params.dc_output(effective_ghi, cell_temp, params.capacity_factor(utc_now));
+    let power_kw = dc.power_kw();
 
     // ── 10. Synthetic weather code (WMO-like)  ─────────────────
-    let weather_code = synthetic_weather_code(cloud_factor, alpha_deg, doy, lat_deg);
+    // Fog (rime fog below freezing) hides the sky; it is not precipitation
+    let weather_code = if fog.opacity <= 0.0 {
+        synthetic_weather_code(sky_cloud_factor, alpha_deg, doy, lat_deg)
+    } else if ambient_temp_c < 0.0 {
+        48
+    } else {
+        45
+    };
+    let rain_mm_h = if snow_season(lat_deg, doy) { 0.0 } else { rain_rate(sky_cloud_factor) };
 
     let is_day = alpha_deg > 0.0 && ghi_poa > 0.5;
 
     OfflineEstimate {
         power_kw,
-        ghi_w_m2: ghi_poa,
+        poa_w_m2: ghi_poa,
+        ghi_w_m2: ghi,
+        dni_w_m2: dni,
+        dhi_w_m2: dhi,
+        rear_irradiance_w_m2: rear_poa,
         cell_temp_c: cell_temp,
         ambient_temp_c,
         weather_code,
         is_day,
         cloud_factor,
         solar_elevation_deg: alpha_deg,
+        solar_azimuth_deg: azimuth_deg,
+        dni_clear_sky_w_m2: dni_cs,
+        linke_turbidity: tk,
         wind_speed_m_s: wind_speed,
         relative_humidity_pct: relative_humidity,
         soiling_factor,
+        rain_mm_h,
+        snow_cover_factor: snow_cover,
+        degradation_factor,
+        angle_of_incidence_deg,
+        iam,
+        tracker_stowed,
+        mppt: vec![dc],
+    }
+}
+
+/// Solar declination (rad) and equation of time (minutes), Spencer 1971.
+fn declination_and_eot(doy: f64) -> (f64, f64) {
+    let b = 2.0 * PI * (doy - 1.0) / 365.0;
+    let decl_deg = (180.0 / PI)
+        * (0.006918
+            - 0.399912 * b.cos()
+            + 0.070257 * b.sin()
+            - 0.006758 * (2.0 * b).cos()
+            + 0.000907 * (2.0 * b).sin()
+            - 0.002697 * (3.0 * b).cos()
+            + 0.00148 * (3.0 * b).sin());
+    let eot_min = 229.18
+        * (0.000075
+            + 0.001868 * b.cos()
+            - 0.032077 * b.sin()
+            - 0.014615 * (2.0 * b).cos()
+            - 0.04089 * (2.0 * b).sin());
+    (decl_deg * DEG, eot_min)
+}
+
+// ─── Sunrise / sunset ────────────────────────────────────────
+/// Sun elevation at rise and set: refraction plus the solar semi-diameter.
+const SUNRISE_ELEVATION_DEG: f64 = -0.833;
+
+/// Daily swing of the sun's elevation above which the full diurnal
+/// temperature cycle develops.
+const DIURNAL_SWING_DEG: f64 = 20.0;
+
+/// Sun elevation (°) at solar noon and at solar midnight on day `doy`;
+/// both are positive under the midnight sun, both negative in polar night.
+fn noon_and_midnight_elevation_deg(lat_deg: f64, doy: f64) -> (f64, f64) {
+    let decl_deg = declination_and_eot(doy).0 / DEG;
+    (90.0 - (lat_deg - decl_deg).abs(), (lat_deg + decl_deg).abs() - 90.0)
+}
+
+/// Daylight over one calendar day at a site (instants in UTC).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SunTimes {
+    /// The sun rises and sets
+    Daylight { sunrise: DateTime<Utc>, solar_noon: DateTime<Utc>, sunset: DateTime<Utc> },
+    /// Midnight sun: above the horizon all day
+    PolarDay { solar_noon: DateTime<Utc> },
+    /// Below the horizon all day
+    PolarNight { solar_noon: DateTime<Utc> },
+}
+
+impl SunTimes {
+    pub fn solar_noon(&self) -> DateTime<Utc> {
+        match *self {
+            SunTimes::Daylight { solar_noon, .. }
+            | SunTimes::PolarDay { solar_noon }
+            | SunTimes::PolarNight { solar_noon } => solar_noon,
+        }
+    }
+
+    /// Hours between sunrise and sunset (24 in polar day, 0 in polar night).
+    pub fn day_length_h(&self) -> f64 {
+        match *self {
+            SunTimes::Daylight { sunrise, sunset, .. } => (sunset - sunrise).num_seconds() as f64 / 3600.0,
+            SunTimes::PolarDay { .. }                  => 24.0,
+            SunTimes::PolarNight { .. }                => 0.0,
+        }
+    }
+}
+
+/// Sunrise, solar noon and sunset on `date`, from the hour angle at which the
+/// sun crosses the horizon: `cos ω₀ = (sin h₀ − sin φ · sin δ) / (cos φ · cos δ)`.
+pub fn sun_times(lat_deg: f64, lon_deg: f64, date: NaiveDate) -> SunTimes {
+    let (decl, eot_min) = declination_and_eot(date.ordinal() as f64);
+    let lat = lat_deg * DEG;
+
+    // Solar noon: local solar time 12:00, back to UTC
+    let noon_h = 12.0 - (4.0 * lon_deg + eot_min) / 60.0;
+    let at = |h: f64| date.and_time(chrono::NaiveTime::MIN).and_utc()
+        + chrono::Duration::seconds((h * 3600.0).round() as i64);
+    let solar_noon = at(noon_h);
+
+    let cos_omega0 = ((SUNRISE_ELEVATION_DEG * DEG).sin() - lat.sin() * decl.sin()) / (lat.cos() * decl.cos());
+    if cos_omega0 < -1.0 {
+        SunTimes::PolarDay { solar_noon }
+    } else if cos_omega0 > 1.0 {
+        SunTimes::PolarNight { solar_noon }
+    } else {
+        let half_day_h = cos_omega0.acos() / DEG / 15.0;
+        SunTimes::Daylight { sunrise: at(noon_h - half_day_h), solar_noon, sunset: at(noon_h + half_day_h) }
+    }
+}
+
+// ─── Horizon profile ─────────────────────────────────────────
+/// Normalise configured horizon points to azimuths in [0, 360), sorted.
+fn horizon_profile(points: &[HorizonPoint]) -> Vec<(f64, f64)> {
+    let mut profile: Vec<(f64, f64)> = points
+        .iter()
+        .map(|p| (p.azimuth_deg.rem_euclid(360.0), p.elevation_deg))
+        .collect();
+    profile.sort_by(|a, b| a.0.total_cmp(&b.0));
+    profile
+}
+
+/// Skyline elevation (°) at `azimuth_deg`, linearly interpolated between the
+/// neighbouring points; the segment from the last point to the first wraps
+/// through North. An empty profile is an open horizon (−∞).
+fn horizon_elevation(profile: &[(f64, f64)], azimuth_deg: f64) -> f64 {
+    let (Some(&first), Some(&last)) = (profile.first(), profile.last()) else {
+        return f64::NEG_INFINITY;
+    };
+    let az = azimuth_deg.rem_euclid(360.0);
+    match profile.iter().position(|p| p.0 > az) {
+        Some(i) if i > 0 => interpolate(profile[i - 1], profile[i], az),
+        // Before the first or past the last point
+        _ => {
+            let az = if az < first.0 { az + 360.0 } else { az };
+            interpolate(last, (first.0 + 360.0, first.1), az)
+        }
     }
 }
 
+#[inline]
+fn interpolate((x0, y0): (f64, f64), (x1, y1): (f64, f64), x: f64) -> f64 {
+    if x1 - x0 < 1e-9 { y1 } else { y0 + (y1 - y0) * (x - x0) / (x1 - x0) }
+}
+
 // ─── Helper: back-scatter term for Bird diffuse ──────────────
 #[inline]
 fn ba_scatter_coeff(ta: f64) -> f64 {
@@ -260,16 +841,180 @@ fn ba_scatter_coeff(ta: f64) -> f64 {
     0.5 * (0.92 - ta.ln().abs() / 10.0).max(0.2).min(0.5)
 }
 
+// ─── Perez 1990 sky diffuse ──────────────────────────────────
+/// Perez et al. (1990) brightness coefficients per sky-clearness bin ε:
+/// [F11, F12, F13, F21, F22, F23], bins split at the upper ε bounds.
+const PEREZ_EPSILON_BOUNDS: [f64; 7] = [1.065, 1.230, 1.500, 1.950, 2.800, 4.500, 6.200];
+const PEREZ_COEFFS: [[f64; 6]; 8] = [
+    [-0.008,  0.588, -0.062, -0.060,  0.072, -0.022],
+    [ 0.130,  0.683, -0.151, -0.019,  0.066, -0.029],
+    [ 0.330,  0.487, -0.221,  0.055, -0.064, -0.026],
+    [ 0.568,  0.187, -0.295,  0.109, -0.152, -0.014],
+    [ 0.873, -0.392, -0.362,  0.226, -0.462,  0.001],
+    [ 1.132, -1.237, -0.412,  0.288, -0.823,  0.056],
+    [ 1.060, -1.600, -0.359,  0.264, -1.127,  0.131],
+    [ 0.678, -0.327, -0.250,  0.156, -1.377,  0.251],
+];
+
+/// Sky diffuse irradiance on a plane tilted by `tilt` (rad), Perez 1990.
+///
+/// * `e0`        – extraterrestrial normal irradiance
+/// * `am`        – relative air mass
+/// * `zenith`    – solar zenith (rad)
+/// * `cos_theta` – cosine of the angle of incidence (0 when the sun is behind the plane)
+///
+/// The circumsolar ratio a/b caps b at cos 85°, which keeps it bounded at sunrise.
+fn perez_sky_diffuse(dhi: f64, dni: f64, e0: f64, am: f64, zenith: f64, cos_theta: f64, tilt: f64) -> f64 {
+    if dhi <= 0.0 {
+        return 0.0;
+    }
+    const KAPPA: f64 = 1.041; // for zenith in radians
+
+    // Sky clearness ε and brightness Δ
+    let z3 = KAPPA * zenith.powi(3);
+    let epsilon = ((dhi + dni) / dhi + z3) / (1.0 + z3);
+    let delta = dhi * am / e0;
+
+    let bin = PEREZ_EPSILON_BOUNDS.iter().take_while(|&&upper| epsilon >= upper).count();
+    let [f11, f12, f13, f21, f22, f23] = PEREZ_COEFFS[bin];
+    let f1 = (f11 + f12 * delta + f13 * zenith).max(0.0);
+    let f2 = f21 + f22 * delta + f23 * zenith;
+
+    let a = cos_theta.max(0.0);
+    let b = zenith.cos().max((85.0 * DEG).cos());
+    let sky = (1.0 - f1) * (1.0 + tilt.cos()) / 2.0 + f1 * a / b + f2 * tilt.sin();
+    (dhi * sky).max(0.0)
+}
+
+// ─── Incidence angle modifier ────────────────────────────────
+/// ASHRAE model: share of the beam transmitted at incidence θ,
+/// `1 − b0 · (1/cos θ − 1)`, clamped to [0, 1].
+fn iam_ashrae(b0: f64, cos_theta: f64) -> f64 {
+    if cos_theta <= 0.0 {
+        return 0.0;
+    }
+    (1.0 - b0 * (1.0 / cos_theta - 1.0)).clamp(0.0, 1.0)
+}
+
+// ─── Inter-row shading ───────────────────────────────────────
+/// Fraction of a collector's slant width shaded by the parallel row in front
+/// (infinite rows on flat ground). With the sun's profile angle ψ in the
+/// plane normal to the rows, the shadow covers `1 − sin ψ / (GCR · sin(β + ψ))`.
+fn row_shaded_fraction(alpha_rad: f64, az_diff: f64, tilt: f64, gcr: f64) -> f64 {
+    if gcr <= 0.0 || az_diff.cos() <= 0.0 {
+        return 0.0; // sun behind the rows
+    }
+    let psi = alpha_rad.sin().atan2(alpha_rad.cos() * az_diff.cos());
+    (1.0 - psi.sin() / (gcr * (tilt + psi).sin())).clamp(0.0, 1.0)
+}
+
+// ─── Rear-side irradiance (bifacial) ─────────────────────────
+/// Clear-sky irradiance reaching the back of a row tilted by `tilt` (rad).
+///
+/// The rear faces the ground through `(1 + cos β)/2` and the sky through
+/// `(1 − cos β)/2`. The ground it sees is darker where the rows cast their
+/// shadow: the strip right under the module (weighted by the view factor
+/// between parallel strips, which falls with mounting height) and, with
+/// several rows, a ground-coverage share of the field.
+fn rear_irradiance(ghi: f64, dhi: f64, albedo: f64, tilt: f64, height_m: f64, gcr: Option<f64>) -> f64 {
+    // Shaded ground only receives diffuse light
+    let gcr = gcr.unwrap_or(0.0).clamp(0.0, 1.0);
+    let open_ground = ghi - (ghi - dhi).max(0.0) * gcr;
+
+    // Hottel's crossed strings for two parallel strips of width w, h apart
+    let w = (COLLECTOR_WIDTH_M * tilt.cos()).max(0.1);
+    let h = height_m.max(0.0) + COLLECTOR_WIDTH_M * tilt.sin() / 2.0;
+    let vf_shadow = (1.0 + (h / w).powi(2)).sqrt() - h / w;
+
+    let ground = vf_shadow * dhi + (1.0 - vf_shadow) * open_ground;
+    albedo * ground * (1.0 + tilt.cos()) / 2.0 + dhi * (1.0 - tilt.cos()) / 2.0
+}
+
+// ─── Scenario seed ───────────────────────────────────────────
+/// Scenario term XOR-ed into the noise hash of `stream`; 0 for seed 0, so the
+/// built-in scenario is unchanged. Non-negative, keeping each hash's sign.
+fn seed_salt(seed: u64, stream: u64) -> i64 {
+    if seed == 0 {
+        return 0;
+    }
+    (mix64(seed ^ stream.wrapping_mul(0x9e3779b97f4a7c15)) >> 1) as i64
+}
+
+// ─── Shared cloud field ──────────────────────────────────────
+/// Cloud noise shared by neighbouring plants: hashed on a lattice `cell_km`
+/// wide and interpolated smoothly between nodes, so plants much closer than
+/// a cell see nearly the same clouds and plants many cells apart are independent.
+/// The blend is renormalised so the scatter is the same between nodes as on them.
+#[derive(Clone, Copy, Debug)]
+struct CloudField {
+    seed: u64,
+    cell_km: f64,
+    /// AR(1) coefficient linking each day's anomaly to the previous day's
+    persistence: f64,
+}
+
+impl CloudField {
+    /// Day-to-day cloudiness anomaly in [-1, 1].
+    ///
+    /// Weather regimes persist: an AR(1) process `a_d = φ·a_{d−1} + √(1−φ²)·ε_d`
+    /// with daily field shocks ε, replayed from a quiet start far enough back
+    /// for its influence to have faded (φⁿ < 0.1 %), so it stays stateless.
+    fn daily_noise(&self, lat_deg: f64, lon_deg: f64, doy: f64) -> f64 {
+        const MAX_MEMORY_DAYS: i32 = 60;
+        let phi = self.persistence.clamp(0.0, 0.95);
+        let memory = if phi > 0.0 { ((1e-3_f64).ln() / phi.ln()).ceil().min(MAX_MEMORY_DAYS as f64) as i32 } else { 0 };
+        let innovation = (1.0 - phi * phi).sqrt();
+
+        let mut anomaly = 0.0;
+        for back in (0..=memory).rev() {
+            let day = (doy as i32 - 1 - back).rem_euclid(365) + 1;
+            let shock = (self.sample(lat_deg, lon_deg, day as i64, 3) - 0.5) * 2.0;
+            anomaly = phi * anomaly + innovation * shock;
+        }
+        anomaly.clamp(-1.0, 1.0)
+    }
+
+    /// Field value in [0, 1) at a site for one time `key` of noise `stream`.
+    fn sample(&self, lat_deg: f64, lon_deg: f64, key: i64, stream: u64) -> f64 {
+        let cell_km = self.cell_km.max(0.1);
+        let y = lat_deg * KM_PER_DEG / cell_km;
+        let x = lon_deg * KM_PER_DEG * (lat_deg * DEG).cos() / cell_km;
+        let (ix, iy) = (x.floor(), y.floor());
+        let smooth = |t: f64| t * t * (3.0 - 2.0 * t);
+        let (fx, fy) = (smooth(x - ix), smooth(y - iy));
+
+        let salt = key as u64 ^ seed_salt(self.seed, stream) as u64 ^ stream.wrapping_mul(0x165667b19e3779f9);
+        let node = |dx: i64, dy: i64| {
+            let z = ((ix as i64 + dx) as u64).wrapping_mul(0x9e3779b97f4a7c15)
+                .wrapping_add(((iy as i64 + dy) as u64).wrapping_mul(0xc2b2ae3d27d4eb4f))
+                .wrapping_add(salt.wrapping_mul(0x27d4eb2f165667c5));
+            (mix64(z) >> 11) as f64 / (1u64 << 53) as f64
+        };
+        let weights = [(1.0 - fx) * (1.0 - fy), fx * (1.0 - fy), (1.0 - fx) * fy, fx * fy];
+        let nodes = [node(0, 0), node(1, 0), node(0, 1), node(1, 1)];
+        let deviation: f64 = weights.iter().zip(nodes).map(|(w, n)| w * (n - 0.5)).sum();
+        let norm = weights.iter().map(|w| w * w).sum::<f64>().sqrt();
+        (0.5 + deviation / norm).clamp(0.0, 1.0 - f64::EPSILON)
+    }
+}
+
+/// SplitMix64 finaliser: spreads every input bit over the whole word.
+fn mix64(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
 // ─── Climatological cloud attenuation ────────────────────────
 /// Returns a factor in [0, 1] representing the fraction of clear-sky GHI
 /// that actually reaches the panel on average for the given location & season.
 ///
 /// The model layers three effects:
 ///  a) Climate-zone baseline cloudiness (based on latitude band / season)
-///  b) Slow day-to-day variation (sinusoidal, seeded from plant location + DOY)
+///  b) Persistent day-to-day variation (AR(1) regimes on the shared cloud field)
 ///  c) Intra-day variation (morning / afternoon cloud build-up typical of
 ///     continental climates)
-fn cloud_attenuation(lat_deg: f64, doy: f64, lst_h: f64, lon_deg: f64) -> f64 {
+fn cloud_attenuation(lat_deg: f64, doy: f64, local_h: f64, lon_deg: f64, field: CloudField) -> f64 {
     // --- a) Baseline clearness index by latitude/season ---
     // Northern hemisphere: summer clear (high), winter less clear
     // Southern hemisphere: inverted phase
@@ -305,19 +1050,15 @@ fn cloud_attenuation(lat_deg: f64, doy: f64, lst_h: f64, lon_deg: f64) -> f64 {
     };
 
     // --- b) Day-to-day pseudo-random variation ----------------
-    // Deterministic hash: changes every day, consistent for same plant × day
-    let seed = ((lat_deg * 100.0) as i64).wrapping_mul(397)
-        ^ ((lon_deg * 100.0) as i64).wrapping_mul(631)
-        ^ (doy as i64).wrapping_mul(1013);
-    // Map seed to [-1, 1] smoothly
-    let daily_noise = ((seed % 1000) as f64 / 1000.0 - 0.5) * 2.0; // [-1,1]
+    // Deterministic weather regimes lasting a few days, shared by neighbouring plants
+    let daily_noise = field.daily_noise(lat_deg, lon_deg, doy); // [-1,1]
     let day_variation = daily_noise * 0.12; // ±12% daily scatter
 
     // --- c) Intra-day variation --------------------------------
     // Clouds tend to build up in afternoon in continental areas
-    // Apply small cosine curve centered on 10:00 solar (less cloud in AM)
-    let intraday = if lst_h >= 6.0 && lst_h <= 20.0 {
-        let x = (lst_h - 13.0) / 7.0; // -1 at 06:00, +1 at 20:00
+    // Apply a small linear ramp over the local day (less cloud in AM)
+    let intraday = if local_h >= 6.0 && local_h <= 20.0 {
+        let x = (local_h - 13.0) / 7.0; // -1 at 06:00, +1 at 20:00
         -0.05 * x // slight penalty in afternoon
     } else {
         0.0
@@ -329,8 +1070,9 @@ fn cloud_attenuation(lat_deg: f64, doy: f64, lst_h: f64, lon_deg: f64) -> f64 {
 // ─── Ambient temperature model ───────────────────────────────
 /// Estimates ambient 2 m temperature (°C) from:
 ///  - latitude × season (mean annual temperature + amplitude)
-///  - diurnal cycle (min ~6 h before solar noon, max ~2 h after solar noon)
-fn ambient_temperature(lat_deg: f64, doy: f64, lst_h: f64) -> f64 {
+///  - diurnal cycle on the local clock (min ~02:00, max ~14:00)
+///  - altitude (environmental lapse rate from the sea-level value)
+fn ambient_temperature(lat_deg: f64, altitude_m: f64, doy: f64, local_h: f64) -> f64 {
     let abs_lat = lat_deg.abs();
 
     // Mean annual temperature by latitude (rough model)
@@ -369,12 +1111,20 @@ fn ambient_temperature(lat_deg: f64, doy: f64, lst_h: f64) -> f64 {
     };
     let t_seasonal = t_annual_mean + t_amplitude * season_angle.cos();
 
-    // Diurnal range ±5°C peak-to-peak on surface
-    // Min ~06:00 solar, max ~14:00 solar
-    let diurnal_phase = 2.0 * PI * (lst_h - 14.0) / 24.0; // max at 14:00
-    let t_diurnal = 5.0 * diurnal_phase.cos();
+    // Diurnal range ±5°C peak-to-peak on surface, max at 14:00 local time.
+    // The sun drives it: it fades out in polar night and under a midnight sun
+    // that barely changes height over the day.
+    let (noon_deg, midnight_deg) = noon_and_midnight_elevation_deg(lat_deg, doy);
+    let sun_swing = ((noon_deg.max(0.0) - midnight_deg.max(0.0)) / DIURNAL_SWING_DEG).clamp(0.0, 1.0);
+    let diurnal_phase = 2.0 * PI * (local_h - 14.0) / 24.0;
+    let t_diurnal = 5.0 * sun_swing * diurnal_phase.cos();
 
-    t_seasonal + t_diurnal
+    t_seasonal + t_diurnal - LAPSE_RATE_C_PER_KM * altitude_m / 1000.0
+}
+
+/// Station-to-sea-level pressure ratio p/p0 (standard atmosphere).
+fn pressure_ratio(altitude_m: f64) -> f64 {
+    (1.0 - 2.25577e-5 * altitude_m).max(0.0).powf(5.25588)
 }
 
 // ─── Synthetic WMO weather code ──────────────────────────────
@@ -392,14 +1142,7 @@ fn synthetic_weather_code(cloud_factor: f64, alpha_deg: f64, doy: f64, lat_deg:
         return 0; // night – clear sky code
     }
 
-    // Estimate snowfall risk: high-lat winter
-    let abs_lat = lat_deg.abs();
-    let winter_day = if lat_deg >= 0.0 {
-        doy < 60.0 || doy > 330.0
-    } else {
-        doy > 150.0 && doy < 270.0
-    };
-    let snow_likely = abs_lat > 40.0 && winter_day;
+    let snow_likely = snow_season(lat_deg, doy);
 
     if cloud_factor > 0.85 {
         0 // clear sky
@@ -418,13 +1161,78 @@ fn synthetic_weather_code(cloud_factor: f64, alpha_deg: f64, doy: f64, lat_deg:
     }
 }
 
+/// Precipitation rate (mm/h) under the cloud factor, matching the WMO codes:
+/// none above 0.45, ~3.8 mm/h at 0.35 (moderate) and 7.6 at 0.25 (heavy).
+fn rain_rate(cloud_factor: f64) -> f64 {
+    (0.45 - cloud_factor).max(0.0) * 38.0
+}
+
+/// Snowfall risk: high-latitude winter.
+fn snow_season(lat_deg: f64, doy: f64) -> bool {
+    let winter_day = if lat_deg >= 0.0 {
+        doy < 60.0 || doy > 330.0
+    } else {
+        doy > 150.0 && doy < 270.0
+    };
+    lat_deg.abs() > 40.0 && winter_day
+}
+
+// ─── Snow cover model ────────────────────────────────────────
+/// Share of the front face under snow [0, 1] on day `doy`.
+///
+/// Replays the last two weeks day by day: a snowfall day (snow season,
+/// precipitation-level cloud and a daytime high below ~1 °C) buries the
+/// panels, then `snow_shed` clears them. Today's snowfall counts at once;
+/// today's melt shows up tomorrow, so the cover is steady within a day.
+fn snow_cover_factor(lat_deg: f64, lon_deg: f64, altitude_m: f64, doy: f64, tilt_deg: f64, field: CloudField) -> f64 {
+    const MAX_DAYS: i32        = 14;
+    const SNOWFALL_CF: f64     = 0.45;  // cloud_factor below this → precipitation (codes 71–75)
+    const SNOW_MAX_HIGH_C: f64 = 1.0;   // warmer days bring rain
+
+    let day_weather = |back: i32| {
+        let day = ((doy as i32 - 1 - back).rem_euclid(365) + 1) as f64;
+        let cf = cloud_attenuation(lat_deg, day, 12.0, lon_deg, field);
+        let high_c = ambient_temperature(lat_deg, altitude_m, day, 14.0);
+        let snowfall = snow_season(lat_deg, day) && cf < SNOWFALL_CF && high_c < SNOW_MAX_HIGH_C;
+        // No sunshine through the layer on a day the sun stays down
+        let sunshine = if noon_and_midnight_elevation_deg(lat_deg, day).0 > 0.0 { cf } else { 0.0 };
+        (snowfall, high_c, sunshine)
+    };
+
+    let mut cover = 0.0;
+    for back in (1..=MAX_DAYS).rev() {
+        let (snowfall, high_c, sunshine) = day_weather(back);
+        cover = if snowfall { 1.0 } else { snow_shed(cover, high_c, tilt_deg, sunshine) };
+    }
+    if day_weather(0).0 { 1.0 } else { cover }
+}
+
+/// Snow cover left after one day without snowfall.
+///
+/// * Melt   – degree-day model on the daytime high
+/// * Slide  – sun through the layer warms the glass; once the film at the
+///            glass thaws, snow slides off in proportion to sin(tilt)
+fn snow_shed(cover: f64, high_c: f64, tilt_deg: f64, cloud_factor: f64) -> f64 {
+    const MELT_PER_DEG_DAY: f64 = 0.08;  // cover shed per °C·day above freezing
+    const SLIDE_PER_DAY: f64    = 0.6;   // cover shed by a clear day on a vertical panel
+    const SUN_WARMING_K: f64    = 8.0;   // glass heating under snow on a clear day
+
+    let melt = MELT_PER_DEG_DAY * high_c.max(0.0);
+    let slide = if high_c + SUN_WARMING_K * cloud_factor > 0.0 {
+        SLIDE_PER_DAY * (tilt_deg * DEG).sin().max(0.0) * cloud_factor
+    } else {
+        0.0
+    };
+    (cover - melt - slide).clamp(0.0, 1.0)
+}
+
 // ─── Wind speed model ────────────────────────────────────────
 /// Estimates near-surface wind speed (m/s) at 10 m — affects cell temperature.
 ///
 /// Diurnal pattern: calm at night/dawn, peaks ~14:00 solar (convective mixing).
 /// Seasonal: stronger in winter at mid/high latitudes.
 /// Daily pseudo-random noise to simulate synoptic variability.
-fn wind_speed_model(lat_deg: f64, lon_deg: f64, doy: f64, lst_h: f64) -> f64 {
+fn wind_speed_model(lat_deg: f64, lon_deg: f64, doy: f64, lst_h: f64, seed: u64) -> f64 {
     let abs_lat = lat_deg.abs();
 
     // Climatological mean wind speed by latitude band
@@ -451,7 +1259,8 @@ fn wind_speed_model(lat_deg: f64, lon_deg: f64, doy: f64, lst_h: f64) -> f64 {
     // Daily pseudo-random synoptic factor (0.6 – 1.4 × mean)
     let seed = ((lat_deg * 73.0) as i64).wrapping_mul(701)
         ^ ((lon_deg * 73.0) as i64).wrapping_mul(449)
-        ^ (doy as i64).wrapping_mul(983);
+        ^ (doy as i64).wrapping_mul(983)
+        ^ seed_salt(seed, 4);
     let daily_factor = 0.60
         + 0.80 * (((seed.wrapping_mul(0x6c62272e07bb0142_u64 as i64)) >> 11)
             as f64 / (1i64 << 53) as f64);
@@ -463,11 +1272,12 @@ fn wind_speed_model(lat_deg: f64, lon_deg: f64, doy: f64, lst_h: f64) -> f64 {
 }
 
 // ─── Relative humidity model ─────────────────────────────────
-/// Estimates surface relative humidity (%) based on latitude/season/hour.
+/// Estimates surface relative humidity (%) based on latitude/season/hour,
+/// shifted by the site's `offset_pct`.
 ///
 /// RH is highest at dawn, lowest in early afternoon.
 /// Higher in tropical/coastal zones, lower in deserts.
-fn relative_humidity_model(lat_deg: f64, doy: f64, lst_h: f64) -> f64 {
+fn relative_humidity_model(lat_deg: f64, doy: f64, lst_h: f64, offset_pct: f64) -> f64 {
     let abs_lat = lat_deg.abs();
 
     // Climatological mean RH by latitude
@@ -490,17 +1300,93 @@ fn relative_humidity_model(lat_deg: f64, doy: f64, lst_h: f64) -> f64 {
         season_amp * (2.0 * PI * (doy - 20.0) / 365.0).cos()
     };
 
-    (base + diurnal + seasonal).clamp(15.0, 98.0)
+    ((base + diurnal + seasonal).clamp(15.0, 98.0) + offset_pct).clamp(5.0, 100.0)
+}
+
+/// Dew point (°C) of air at `temp_c` and `rh_pct`, Magnus formula.
+fn dew_point_c(temp_c: f64, rh_pct: f64) -> f64 {
+    const A: f64 = 17.62;
+    const B: f64 = 243.12; // °C
+    let gamma = (rh_pct.max(1.0) / 100.0).ln() + A * temp_c / (B + temp_c);
+    B * gamma / (A - gamma)
+}
+
+// ─── Radiation fog model ─────────────────────────────────────
+/// Morning fog over a site at one instant.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct Fog {
+    /// Thickness of the day's fog bank [0, 1]; 0 = no fog that day
+    density: f64,
+    /// Share of the bank still standing [0, 1]; 0 once it has burnt off
+    opacity: f64,
+}
+
+/// Hours before sunrise the fog bank sets in.
+const FOG_ONSET_H: f64 = 4.0;
+
+/// Radiation fog at solar hour `lst_h` of day `doy`.
+///
+/// The odds of fog come from dawn conditions: RH very high (> 88 %), the
+/// air within about a degree of its dew point, a calm wind (< 3.5 m/s) and
+/// a night long enough (> 9 h) for the ground to cool. Whether the bank
+/// forms, and how thick it is, is drawn once per day from the shared cloud
+/// field, so it holds steady across updates and covers neighbouring plants
+/// alike. It sets in before dawn and, once the sun is up, thins out over
+/// 1–3 h as the warming ground lifts it; the thicker the bank, the longer
+/// it lasts.
+fn morning_fog(params: &EstimateParams, doy: f64, lst_h: f64, civil_offset_h: f64, field: CloudField) -> Fog {
+    let EstimateParams { lat_deg, lon_deg, altitude_m, .. } = *params;
+    let decl = declination_and_eot(doy).0;
+    let lat = lat_deg * DEG;
+    let cos_omega0 = ((SUNRISE_ELEVATION_DEG * DEG).sin() - lat.sin() * decl.sin()) / (lat.cos() * decl.cos());
+    // No dawn to form fog in polar day or night
+    if !(-1.0..=1.0).contains(&cos_omega0) {
+        return Fog::default();
+    }
+    let half_day_h = cos_omega0.acos() / DEG / 15.0;
+    let sunrise_h = 12.0 - half_day_h;
+
+    // Dawn: the coolest, most humid moment of the night
+    let rh = relative_humidity_model(lat_deg, doy, sunrise_h, params.humidity_offset_pct);
+    let temp_c = ambient_temperature(lat_deg, altitude_m, doy, (sunrise_h + civil_offset_h).rem_euclid(24.0));
+    let wind = wind_speed_model(lat_deg, lon_deg, doy, sunrise_h, params.seed);
+    let spread_k = temp_c - dew_point_c(temp_c, rh);
+    let night_h = 24.0 - 2.0 * half_day_h;
+    let likelihood = ((rh - 88.0) / 8.0).clamp(0.0, 1.0)
+        * (1.5 - spread_k).clamp(0.0, 1.0)
+        * ((3.5 - wind) / 1.5).clamp(0.0, 1.0)
+        * ((night_h - 9.0) / 3.0).clamp(0.0, 1.0);
+    let draw = field.sample(lat_deg, lon_deg, doy as i64, 5);
+    if draw >= likelihood {
+        return Fog::default();
+    }
+    let density = 1.0 - draw / likelihood;
+
+    let clear_h = sunrise_h + 1.0 + 2.0 * density;
+    let opacity = if lst_h < sunrise_h - FOG_ONSET_H || lst_h >= clear_h {
+        0.0
+    } else {
+        // Thick at first, lifting as the sun climbs
+        let t = ((lst_h - sunrise_h) / (clear_h - sunrise_h)).clamp(0.0, 1.0);
+        1.0 - t * t * (3.0 - 2.0 * t)
+    };
+    Fog { density, opacity }
+}
+
+/// Cloud factor under a standing fog bank: 0.3 for a thin one, 0.15 for
+/// the thickest.
+fn fog_cloud_cap(density: f64) -> f64 {
+    0.30 - 0.15 * density.clamp(0.0, 1.0)
 }
 
 // ─── Panel soiling model (deterministic accumulation) ────────
-/// Returns soiling factor in [0.85, 1.0] (1 = clean).
+/// Returns soiling factor in [MIN_SOILING_FACTOR, 1.0] (1 = clean).
 ///
 /// Algorithm: walks back up to 30 days to find the most recent rainy day
-/// (cloud_factor < 0.40 at noon → rain). Soiling accumulates at ~0.3 %/day.
-/// Maximum soiling is −15 % irradiance (30-day dry spell).
-fn panel_soiling_factor(lat_deg: f64, lon_deg: f64, doy: f64) -> f64 {
-    const SOIL_RATE: f64    = 0.003;   // 0.3 %/day
+/// (cloud_factor < 0.40 at noon → rain), on the same weather regimes as
+/// `cloud_attenuation` so dry spells match the simulated skies.
+/// Soiling accumulates at `rate_pct_per_day` over the dry days.
+fn panel_soiling_factor(lat_deg: f64, lon_deg: f64, doy: f64, field: CloudField, rate_pct_per_day: f64) -> f64 {
     const MAX_DAYS: usize   = 30;
     const RAIN_CF: f64      = 0.42;    // cloud_factor below this → rain
 
@@ -521,10 +1407,7 @@ fn panel_soiling_factor(lat_deg: f64, lon_deg: f64, doy: f64) -> f64 {
             else if abs_lat < 55.0          { 0.62 + 0.12 * season_phase }
             else if abs_lat < 65.0          { 0.52 + 0.10 * season_phase }
             else                            { 0.45 + 0.10 * season_phase };
-        let seed = ((lat_deg * 100.0) as i64).wrapping_mul(397)
-            ^ ((lon_deg * 100.0) as i64).wrapping_mul(631)
-            ^ (past_doy as i64).wrapping_mul(1013);
-        let noise = ((seed % 1000) as f64 / 1000.0 - 0.5) * 2.0;
+        let noise = field.daily_noise(lat_deg, lon_deg, past_doy);
         let past_cf = (lat_cf_base + noise * 0.12).clamp(0.15, 1.0);
 
         if past_cf < RAIN_CF {
@@ -534,7 +1417,7 @@ fn panel_soiling_factor(lat_deg: f64, lon_deg: f64, doy: f64) -> f64 {
         dry_days += 1;
     }
 
-    (1.0 - SOIL_RATE * dry_days as f64).clamp(0.85, 1.0)
+    (1.0 - rate_pct_per_day / 100.0 * dry_days as f64).clamp(MIN_SOILING_FACTOR, 1.0)
 }
 
 #[cfg(test)]
@@ -546,20 +1429,20 @@ mod tests {
     fn test_summer_noon_italy() {
         // Turin, Italy – summer solstice noon UTC+2 → 11:00 UTC
         let t = Utc.with_ymd_and_hms(2025, 6, 21, 9, 0, 0).unwrap();
-        let r = estimate(45.07, 7.33, 1000.0, t);
+        let r = estimate(&EstimateParams::new(45.07, 7.33, 1000.0), t);
         // Should produce meaningful power at summer noon
         assert!(r.solar_elevation_deg > 60.0, "Elevation should be >60° at summer noon, got {:.1}", r.solar_elevation_deg);
-        assert!(r.ghi_w_m2 > 400.0, "GHI should be significant, got {:.1}", r.ghi_w_m2);
+        assert!(r.poa_w_m2 > 400.0, "POA should be significant, got {:.1}", r.poa_w_m2);
         assert!(r.power_kw > 200.0, "Power should be significant, got {:.1}", r.power_kw);
-        println!("Summer noon Turin: elev={:.1}° GHI={:.0} W/m² power={:.1} kW temp={:.1}°C cloud={:.2}",
-            r.solar_elevation_deg, r.ghi_w_m2, r.power_kw, r.cell_temp_c, r.cloud_factor);
+        println!("Summer noon Turin: elev={:.1}° POA={:.0} W/m² power={:.1} kW temp={:.1}°C cloud={:.2}",
+            r.solar_elevation_deg, r.poa_w_m2, r.power_kw, r.cell_temp_c, r.cloud_factor);
     }
 
     #[test]
     fn test_midnight_zero() {
         // Power at midnight should be 0
         let t = Utc.with_ymd_and_hms(2025, 6, 21, 22, 0, 0).unwrap();
-        let r = estimate(45.07, 7.33, 1000.0, t);
+        let r = estimate(&EstimateParams::new(45.07, 7.33, 1000.0), t);
         assert_eq!(r.power_kw, 0.0, "Power at night must be 0");
     }
 
@@ -567,11 +1450,630 @@ mod tests {
     fn test_winter_solstice() {
         // Turin, winter solstice at solar noon (~UTC 11:40)
         let t = Utc.with_ymd_and_hms(2025, 12, 21, 11, 0, 0).unwrap();
-        let r = estimate(45.07, 7.33, 1000.0, t);
+        let r = estimate(&EstimateParams::new(45.07, 7.33, 1000.0), t);
         // Winter noon elevation should be much lower than summer
         assert!(r.solar_elevation_deg > 15.0 && r.solar_elevation_deg < 35.0,
             "Winter elevation should be 15-35°, got {:.1}", r.solar_elevation_deg);
         println!("Winter noon Turin: elev={:.1}° GHI={:.0} W/m² power={:.1} kW",
-            r.solar_elevation_deg, r.ghi_w_m2, r.power_kw);
+            r.solar_elevation_deg, r.poa_w_m2, r.power_kw);
+    }
+
+    /// Time of day (5-minute steps, summer solstice) at which `value` peaks.
+    fn peak_time(value: impl Fn(DateTime<Utc>) -> f64) -> DateTime<Utc> {
+        let day = Utc.with_ymd_and_hms(2025, 6, 21, 0, 0, 0).unwrap();
+        (0..288)
+            .map(|i| day + chrono::Duration::minutes(5 * i))
+            .max_by(|a, b| value(*a).total_cmp(&value(*b)))
+            .unwrap()
+    }
+
+    #[test]
+    fn east_facing_arrays_peak_before_solar_noon_and_west_facing_after() {
+        let flat = EstimateParams::new(45.07, 7.33, 1000.0);
+        let solar_noon = peak_time(|t| estimate(&flat, t).solar_elevation_deg);
+
+        // 10° east-west roof on a white membrane; clouds scale both arrays
+        // alike, so compare the clear-sky plane-of-array irradiance
+        let roof_peak = |azimuth_deg| {
+            let roof = EstimateParams { tilt_deg: Some(10.0), azimuth_deg: Some(azimuth_deg), albedo: Some(0.6), ..flat.clone() };
+            peak_time(|t| { let r = estimate(&roof, t); r.poa_w_m2 / r.cloud_factor })
+        };
+        let east = roof_peak(90.0);
+        let west = roof_peak(270.0);
+        assert!(east <= solar_noon - chrono::Duration::minutes(30), "east peak {} vs noon {}", east, solar_noon);
+        assert!(west >= solar_noon + chrono::Duration::minutes(30), "west peak {} vs noon {}", west, solar_noon);
+    }
+
+    fn tracker() -> EstimateParams {
+        EstimateParams { mounting: Mounting::Tracker2Axis, ..EstimateParams::new(45.07, 7.33, 1000.0) }
+    }
+
+    #[test]
+    fn dual_axis_tracker_faces_the_sun_all_day() {
+        for hour in [5, 7, 9, 11, 13, 15, 17] {
+            let t = Utc.with_ymd_and_hms(2025, 6, 21, hour, 0, 0).unwrap();
+            let r = estimate(&tracker(), t);
+            assert!(r.solar_elevation_deg > 0.0);
+            assert!(r.angle_of_incidence_deg < 0.5, "{}:00 UTC: AOI {:.2}°", hour, r.angle_of_incidence_deg);
+            assert!(!r.tracker_stowed);
+        }
+
+        // Below the lowest tracker elevation the panels wait at the limit
+        let early = Utc.with_ymd_and_hms(2025, 6, 21, 4, 30, 0).unwrap();
+        let limited = EstimateParams { tracker_elevation_deg: (15.0, 90.0), ..tracker() };
+        let r = estimate(&limited, early);
+        assert!(r.solar_elevation_deg > 0.0 && r.solar_elevation_deg < 15.0);
+        assert!((r.angle_of_incidence_deg - (15.0 - r.solar_elevation_deg)).abs() < 0.5);
+    }
+
+    #[test]
+    fn tracker_stows_flat_in_high_wind_with_hysteresis() {
+        let t = Utc.with_ymd_and_hms(2025, 6, 21, 9, 0, 0).unwrap();
+        let wind = estimate(&tracker(), t).wind_speed_m_s;
+
+        let gusty = EstimateParams { stow_wind_m_s: Some(wind * 0.5), ..tracker() };
+        let r = estimate(&gusty, t);
+        assert!(r.tracker_stowed);
+        assert!((r.angle_of_incidence_deg - (90.0 - r.solar_elevation_deg)).abs() < 0.5);
+        assert!(r.poa_w_m2 < estimate(&tracker(), t).poa_w_m2);
+
+        // Between the resume level and the stow limit the previous state holds
+        let limit = EstimateParams { stow_wind_m_s: Some(wind / 0.9), ..tracker() };
+        assert!(estimate(&EstimateParams { tracker_stowed: true, ..limit.clone() }, t).tracker_stowed);
+        assert!(!estimate(&limit, t).tracker_stowed);
+
+        // Fixed arrays never stow
+        let fixed = EstimateParams { stow_wind_m_s: Some(0.0), ..EstimateParams::new(45.07, 7.33, 1000.0) };
+        assert!(!estimate(&fixed, t).tracker_stowed);
+    }
+
+    #[test]
+    fn bifacial_gain_grows_with_ground_albedo() {
+        let t = Utc.with_ymd_and_hms(2025, 6, 21, 11, 0, 0).unwrap();
+        let gain = |albedo| {
+            let mono = EstimateParams { albedo: Some(albedo), ..EstimateParams::new(45.07, 7.33, 1000.0) };
+            let bifacial = EstimateParams { bifaciality: Some(0.7), ..mono.clone() };
+            let (m, b) = (estimate(&mono, t), estimate(&bifacial, t));
+            assert_eq!(m.rear_irradiance_w_m2, 0.0);
+            assert!(b.rear_irradiance_w_m2 > 0.0);
+            assert_eq!(b.poa_w_m2, m.poa_w_m2, "front irradiance is unchanged");
+            b.power_kw / m.power_kw - 1.0
+        };
+        let grass = gain(0.2);
+        let gravel = gain(0.5);
+        assert!(grass > 0.02, "albedo 0.2 gain {:.3}", grass);
+        assert!(gravel > grass + 0.05, "albedo 0.5 gain {:.3} vs 0.2 gain {:.3}", gravel, grass);
+    }
+
+    #[test]
+    fn horizon_interpolation_wraps_through_north() {
+        let profile = horizon_profile(&[
+            HorizonPoint { azimuth_deg: 10.0,  elevation_deg: 30.0 },
+            HorizonPoint { azimuth_deg: -10.0, elevation_deg: 10.0 },
+        ]);
+        assert_eq!(profile, vec![(10.0, 30.0), (350.0, 10.0)]);
+        assert!((horizon_elevation(&profile, 0.0) - 20.0).abs() < 1e-9);
+        assert!((horizon_elevation(&profile, 355.0) - 15.0).abs() < 1e-9);
+        assert!((horizon_elevation(&profile, 180.0) - 20.0).abs() < 1e-9);
+        assert_eq!(horizon_elevation(&[], 90.0), f64::NEG_INFINITY);
+    }
+
+    #[test]
+    fn eastern_mountains_delay_the_morning_ramp_only() {
+        // A tracker catches the beam from sunrise, so the lost hours stand out
+        let open = tracker();
+        // 20° wall from east-north-east to south-east, open elsewhere
+        let valley = EstimateParams {
+            horizon: horizon_profile(&[
+                HorizonPoint { azimuth_deg: 30.0,  elevation_deg: 0.0 },
+                HorizonPoint { azimuth_deg: 40.0,  elevation_deg: 20.0 },
+                HorizonPoint { azimuth_deg: 140.0, elevation_deg: 20.0 },
+                HorizonPoint { azimuth_deg: 150.0, elevation_deg: 0.0 },
+            ]),
+            ..open.clone()
+        };
+
+        // First time of the summer solstice with 20% of nominal output
+        let ramp = |params: &EstimateParams| {
+            let day = Utc.with_ymd_and_hms(2025, 6, 21, 0, 0, 0).unwrap();
+            (0..288)
+                .map(|i| day + chrono::Duration::minutes(5 * i))
+                .find(|t| estimate(params, *t).power_kw > 200.0)
+                .unwrap()
+        };
+        assert!(ramp(&valley) >= ramp(&open) + chrono::Duration::minutes(30),
+            "valley ramp {} vs open {}", ramp(&valley), ramp(&open));
+
+        // Behind the wall only diffuse light is left
+        let shaded = Utc.with_ymd_and_hms(2025, 6, 21, 5, 0, 0).unwrap();
+        let r = estimate(&valley, shaded);
+        assert!(r.solar_elevation_deg > 0.0 && r.solar_elevation_deg < 20.0);
+        assert!(r.poa_w_m2 > 0.0 && r.poa_w_m2 < estimate(&open, shaded).poa_w_m2);
+
+        for hour in [12, 14, 16] {
+            let t = Utc.with_ymd_and_hms(2025, 6, 21, hour, 0, 0).unwrap();
+            assert_eq!(estimate(&valley, t).power_kw, estimate(&open, t).power_kw, "{}:00 UTC", hour);
+        }
+    }
+
+    #[test]
+    fn tight_rows_shade_winter_mornings_and_evenings() {
+        let open = EstimateParams { tilt_deg: Some(20.0), ..EstimateParams::new(45.07, 7.33, 1000.0) };
+        let dense = EstimateParams { gcr: Some(0.5), ..open.clone() };
+        let sparse = EstimateParams { gcr: Some(0.25), ..open.clone() };
+        let at = |h, m| Utc.with_ymd_and_hms(2025, 12, 21, h, m, 0).unwrap();
+
+        // 3½ h either side of solar noon (≈11:30 UTC): only the dense field loses beam
+        for t in [at(8, 0), at(15, 0)] {
+            let p = estimate(&open, t).power_kw;
+            assert!(estimate(&dense, t).power_kw < 0.95 * p, "{}: dense {:.1} vs open {:.1}", t, estimate(&dense, t).power_kw, p);
+            assert_eq!(estimate(&sparse, t).power_kw, p, "{}", t);
+        }
+        // Around noon the sun clears the row in front at either ratio
+        let noon = at(11, 30);
+        assert_eq!(estimate(&dense, noon).power_kw, estimate(&open, noon).power_kw);
+
+        // String-level losses amplify the geometric shadow; a lone row is never shaded
+        let t = at(8, 0);
+        let electrical = EstimateParams { electrical_shading_factor: 3.0, ..dense.clone() };
+        assert!(estimate(&electrical, t).power_kw < estimate(&dense, t).power_kw);
+        let single = EstimateParams { rows: Some(1), ..dense.clone() };
+        assert_eq!(estimate(&single, t).power_kw, estimate(&open, t).power_kw);
+    }
+
+    #[test]
+    fn snow_slides_off_over_several_days_after_a_storm() {
+        // A week of sunny, just-freezing days on a 35° roof
+        let mut cover = 1.0;
+        let days: Vec<f64> = (0..7).map(|_| { cover = snow_shed(cover, 0.0, 35.0, 0.7); cover }).collect();
+        assert!(days[0] > 0.5, "still mostly buried the next day: {:?}", days);
+        assert!(days.windows(2).all(|w| w[1] < w[0] || w[1] == 0.0), "{:?}", days);
+        assert!(days[2] > 0.0 && days[6] == 0.0, "{:?}", days);
+
+        // Flat or bitterly cold panels keep their snow; a thaw melts it anyway
+        assert_eq!(snow_shed(1.0, 0.0, 0.0, 0.7), 1.0);
+        assert_eq!(snow_shed(1.0, -12.0, 35.0, 0.7), 1.0);
+        assert!(snow_shed(1.0, 6.0, 0.0, 0.3) < 0.6);
+    }
+
+    #[test]
+    fn january_snowfall_buries_the_array_for_days() {
+        let params = EstimateParams::new(47.0, 8.0, 1000.0);
+        let noons: Vec<OfflineEstimate> = (1..=31)
+            .map(|d| estimate(&params, Utc.with_ymd_and_hms(2025, 1, d, 11, 0, 0).unwrap()))
+            .collect();
+        let day = noons[..30].iter().position(|r| r.snow_cover_factor == 1.0).expect("a snowfall day in January");
+
+        let buried = &noons[day];
+        assert!(buried.power_kw < 0.05 * buried.poa_w_m2, "{:.1} kW under snow at {:.0} W/m²", buried.power_kw, buried.poa_w_m2);
+        assert!(noons[day + 1].snow_cover_factor > 0.5, "cleared overnight: {:.2}", noons[day + 1].snow_cover_factor);
+
+        let summer = estimate(&params, Utc.with_ymd_and_hms(2025, 6, 21, 11, 0, 0).unwrap());
+        assert_eq!(summer.snow_cover_factor, 0.0);
+    }
+
+    #[test]
+    fn ten_year_old_modules_lose_six_to_seven_percent() {
+        let t = Utc.with_ymd_and_hms(2025, 6, 21, 11, 0, 0).unwrap();
+        let new = EstimateParams::new(45.07, 7.33, 1000.0);
+        let aged = EstimateParams {
+            commissioning_date: NaiveDate::from_ymd_opt(2015, 6, 21),
+            first_year_degradation_pct: Some(2.0),
+            ..new.clone()
+        };
+        let (n, a) = (estimate(&new, t), estimate(&aged, t));
+        assert_eq!(n.degradation_factor, 1.0);
+        let loss = 1.0 - a.power_kw / n.power_kw;
+        assert!((0.06..=0.07).contains(&loss), "10-year loss {:.2}%", loss * 100.0);
+        assert!((a.degradation_factor - (1.0 - loss)).abs() < 1e-9);
+
+        // Plants commissioned in the future have not aged yet
+        let planned = EstimateParams { commissioning_date: NaiveDate::from_ymd_opt(2026, 1, 1), ..new.clone() };
+        assert_eq!(planned.degradation_factor(t), 1.0);
+    }
+
+    #[test]
+    fn perez_adds_circumsolar_light_on_clear_winter_days() {
+        let iso = EstimateParams { tilt_deg: Some(45.0), ..EstimateParams::new(45.07, 7.33, 1000.0) };
+        let perez = EstimateParams { transposition: Transposition::Perez, ..iso.clone() };
+        // Both share the cloud factor: compare clear-sky plane-of-array irradiance
+        let clear_poa = |params: &EstimateParams, t| { let r = estimate(params, t); r.poa_w_m2 / r.cloud_factor };
+
+        let noon = Utc.with_ymd_and_hms(2025, 12, 21, 11, 30, 0).unwrap();
+        let (i, p) = (clear_poa(&iso, noon), clear_poa(&perez, noon));
+        assert!(p > 1.03 * i, "Perez {:.0} vs isotropic {:.0} W/m²", p, i);
+
+        // Around sunrise the zenith is near 90° and the sun grazes the panel
+        let mut samples = 0;
+        for minute in 0..150 {
+            let t = Utc.with_ymd_and_hms(2025, 12, 21, 6, 30, 0).unwrap() + chrono::Duration::minutes(minute);
+            let elevation = estimate(&iso, t).solar_elevation_deg;
+            if !(0.0..5.0).contains(&elevation) {
+                continue;
+            }
+            samples += 1;
+            let (i, p) = (clear_poa(&iso, t), clear_poa(&perez, t));
+            assert!(p.is_finite() && p >= 0.0, "elevation {:.2}°: Perez {}", elevation, p);
+            assert!((p - i).abs() < 50.0, "elevation {:.2}°: Perez {:.1} vs isotropic {:.1}", elevation, p, i);
+        }
+        assert!(samples > 0);
+    }
+
+    #[test]
+    fn configured_turbidity_replaces_the_climatological_haze() {
+        // Winter, when the default model is at its haziest
+        let t = Utc.with_ymd_and_hms(2025, 12, 21, 11, 30, 0).unwrap();
+        let default = EstimateParams::new(45.07, 7.33, 1000.0);
+        let desert = EstimateParams { linke_turbidity: Some(LinkeTurbidity::Annual(1.8)), aod_scale: 0.8, ..default.clone() };
+        let mut months = [3.0; 12];
+        months[11] = 6.0; // smoggy December
+        let urban = EstimateParams { linke_turbidity: Some(LinkeTurbidity::Monthly(months)), ..default.clone() };
+
+        let (d, c, u) = (estimate(&default, t), estimate(&desert, t), estimate(&urban, t));
+        assert_eq!(c.linke_turbidity, 1.8);
+        assert_eq!(u.linke_turbidity, 6.0);
+        assert!(c.dni_clear_sky_w_m2 > d.dni_clear_sky_w_m2 + 20.0, "desert {:.0} vs default {:.0} W/m²", c.dni_clear_sky_w_m2, d.dni_clear_sky_w_m2);
+        assert!(u.dni_clear_sky_w_m2 < d.dni_clear_sky_w_m2, "urban {:.0} vs default {:.0} W/m²", u.dni_clear_sky_w_m2, d.dni_clear_sky_w_m2);
+    }
+
+    #[test]
+    fn mountain_sites_get_more_sun_and_colder_air() {
+        let t = Utc.with_ymd_and_hms(2025, 6, 21, 11, 0, 0).unwrap();
+        let sea = EstimateParams::new(45.07, 7.33, 1000.0);
+        let alpine = EstimateParams { altitude_m: 2000.0, ..sea.clone() };
+        let (s, a) = (estimate(&sea, t), estimate(&alpine, t));
+
+        assert!(a.dni_clear_sky_w_m2 > s.dni_clear_sky_w_m2);
+        assert!(a.poa_w_m2 > s.poa_w_m2, "2000 m {:.0} vs sea level {:.0} W/m²", a.poa_w_m2, s.poa_w_m2);
+        assert!((s.ambient_temp_c - a.ambient_temp_c - 13.0).abs() < 1e-9);
+        assert!((pressure_ratio(2000.0) - 0.785).abs() < 0.005);
+        assert_eq!(pressure_ratio(0.0), 1.0);
+    }
+
+    #[test]
+    fn cdte_loses_less_than_silicon_on_a_hot_day() {
+        // 40 °C air, full sun, light breeze: share of nameplate left after heating
+        let derate = |m: ModuleParams| m.dc_power_kw(1000.0, 1000.0, m.cell_temperature(40.0, 1000.0, 1.0)) / 1000.0;
+        let si = derate(ModuleParams::preset(Technology::MonoSi));
+        let cdte = derate(ModuleParams::preset(Technology::Cdte));
+        assert!(cdte > si + 0.03, "CdTe {:.3} vs c-Si {:.3}", cdte, si);
+
+        // Same site and hour through the full estimate
+        let t = Utc.with_ymd_and_hms(2025, 7, 15, 13, 0, 0).unwrap();
+        let seville = EstimateParams::new(37.39, -5.98, 1000.0);
+        let thin_film = EstimateParams { module: ModuleParams::preset(Technology::Cdte), ..seville.clone() };
+        assert!(estimate(&thin_film, t).power_kw > estimate(&seville, t).power_kw);
+
+        // An explicit coefficient overrides the preset; thin-film thermal constants stay
+        let custom = ModuleParams::for_panel(Technology::Cdte, Some(-0.5));
+        assert_eq!(custom.temp_coeff_pct_per_c, -0.5);
+        assert_eq!(custom.u0, ModuleParams::preset(Technology::Cdte).u0);
+
+        // Low-light loss only for technologies that have one
+        let poly = ModuleParams::preset(Technology::PolySi);
+        assert!(poly.dc_power_kw(200.0, 1000.0, 25.0) < 200.0);
+        assert_eq!(ModuleParams::preset(Technology::MonoSi).dc_power_kw(200.0, 1000.0, 25.0), 200.0);
+    }
+
+    #[test]
+    fn glass_reflects_the_beam_at_grazing_incidence() {
+        let fixed = EstimateParams::new(45.07, 7.33, 1000.0);
+        let no_iam = EstimateParams { iam_b0: 0.0, ..fixed.clone() };
+
+        // Midsummer morning: the sun skims the face of a south-facing array
+        let mut grazing = 0;
+        for minute in (0..240).step_by(10) {
+            let t = Utc.with_ymd_and_hms(2025, 6, 21, 4, 0, 0).unwrap() + chrono::Duration::minutes(minute);
+            let (r, plain) = (estimate(&fixed, t), estimate(&no_iam, t));
+            if !(75.0..88.0).contains(&r.angle_of_incidence_deg) {
+                continue;
+            }
+            grazing += 1;
+            let beam = r.dni_clear_sky_w_m2 * (r.angle_of_incidence_deg * DEG).cos() * r.cloud_factor;
+            let loss = (plain.poa_w_m2 - r.poa_w_m2) / beam;
+            assert!(loss > 0.10, "AOI {:.1}°: beam loss {:.3}", r.angle_of_incidence_deg, loss);
+            assert!((loss - (1.0 - r.iam)).abs() < 1e-6);
+        }
+        assert!(grazing > 0);
+
+        // A tracker facing the sun loses nothing to reflection
+        let t = Utc.with_ymd_and_hms(2025, 6, 21, 11, 0, 0).unwrap();
+        let r = estimate(&tracker(), t);
+        assert!(r.iam > 0.9999);
+        let plain = estimate(&EstimateParams { iam_b0: 0.0, ..tracker() }, t);
+        assert!((plain.poa_w_m2 - r.poa_w_m2) / plain.poa_w_m2 < 1e-4);
+    }
+
+    #[test]
+    fn spanish_clock_time_runs_ahead_of_the_sun() {
+        // Madrid keeps Central European time at 3.7°W: solar noon falls near 14:00 CEST
+        let utc = EstimateParams::new(40.42, -3.70, 1000.0);
+        let madrid = EstimateParams { timezone: chrono_tz::Europe::Madrid, ..utc.clone() };
+
+        // UTC hour of the warmest and the highest-sun 10-minute sample of the day
+        let peaks = |params: &EstimateParams, month: u32| {
+            let day = Utc.with_ymd_and_hms(2025, month, 15, 0, 0, 0).unwrap();
+            let samples: Vec<_> = (0..144)
+                .map(|i| estimate(params, day + chrono::Duration::minutes(10 * i)))
+                .collect();
+            let argmax = |key: fn(&OfflineEstimate) -> f64| {
+                (0..samples.len()).max_by(|&a, &b| key(&samples[a]).total_cmp(&key(&samples[b]))).unwrap() as f64 / 6.0
+            };
+            (argmax(|r| r.ambient_temp_c), argmax(|r| r.solar_elevation_deg))
+        };
+
+        // The diurnal temperature cycle peaks at 14:00 on the local clock
+        let (warmest_summer, noon_summer) = peaks(&madrid, 7);
+        let (warmest_winter, noon_winter) = peaks(&madrid, 1);
+        assert_eq!(warmest_summer, 12.0); // 14:00 CEST (UTC+2)
+        assert_eq!(warmest_winter, 13.0); // 14:00 CET (UTC+1)
+
+        // Solar geometry only follows longitude: noon ≈ 12:15–12:30 UTC all year
+        assert!((12.0..=12.7).contains(&noon_summer), "{}", noon_summer);
+        assert!((12.0..=12.7).contains(&noon_winter), "{}", noon_winter);
+        assert_eq!(peaks(&utc, 7).1, noon_summer);
+        let t = Utc.with_ymd_and_hms(2025, 7, 15, 9, 0, 0).unwrap();
+        assert_eq!(estimate(&madrid, t).solar_elevation_deg, estimate(&utc, t).solar_elevation_deg);
+    }
+
+    #[test]
+    fn sun_times_cover_ordinary_and_polar_days() {
+        // Equinox at Turin: ~12 h of daylight centred on ≈ 11:38 UTC
+        let turin = sun_times(45.07, 7.69, NaiveDate::from_ymd_opt(2025, 3, 20).unwrap());
+        let SunTimes::Daylight { sunrise, solar_noon, sunset } = turin else { panic!("{:?}", turin) };
+        assert!((turin.day_length_h() - 12.1).abs() < 0.2, "{}", turin.day_length_h());
+        assert_eq!((solar_noon.hour(), solar_noon.minute() / 10), (11, 3));
+        assert!(sunrise < solar_noon && solar_noon < sunset);
+
+        // The sun sits at its daily peak at solar noon
+        let params = EstimateParams::new(45.07, 7.69, 1000.0);
+        let peak = estimate(&params, solar_noon).solar_elevation_deg;
+        for offset in [-20, 20] {
+            assert!(estimate(&params, solar_noon + chrono::Duration::minutes(offset)).solar_elevation_deg < peak);
+        }
+
+        // Tromsø (69.6°N): midnight sun in June, polar night in December
+        let june = sun_times(69.65, 18.96, NaiveDate::from_ymd_opt(2025, 6, 21).unwrap());
+        assert!(matches!(june, SunTimes::PolarDay { .. }), "{:?}", june);
+        assert_eq!(june.day_length_h(), 24.0);
+        let december = sun_times(69.65, 18.96, NaiveDate::from_ymd_opt(2025, 12, 21).unwrap());
+        assert!(matches!(december, SunTimes::PolarNight { .. }), "{:?}", december);
+        assert_eq!(december.day_length_h(), 0.0);
+    }
+
+    #[test]
+    fn seeds_replay_or_vary_the_weather_scenario() {
+        let day = |seed: u64| -> Vec<OfflineEstimate> {
+            let params = EstimateParams { seed, ..EstimateParams::new(45.07, 7.69, 1000.0) };
+            let start = Utc.with_ymd_and_hms(2025, 4, 10, 4, 0, 0).unwrap();
+            (0..96).map(|i| estimate(&params, start + chrono::Duration::minutes(10 * i))).collect()
+        };
+
+        // Same seed and timestamps: bit-identical telemetry
+        assert_eq!(day(42), day(42));
+
+        // Another seed moves the cloud transients
+        let (a, b) = (day(42), day(43));
+        let differing = a.iter().zip(&b).filter(|(x, y)| x.cloud_factor != y.cloud_factor).count();
+        assert!(differing > a.len() / 2, "only {} of {} samples differ", differing, a.len());
+
+        // Seed 0 keeps the built-in scenario
+        assert_eq!(seed_salt(0, 3), 0);
+        assert!(seed_salt(u64::MAX, 3) >= 0);
+    }
+
+    #[test]
+    fn neighbouring_plants_share_the_cloud_field() {
+        fn correlation(a: &[f64], b: &[f64]) -> f64 {
+            let mean = |v: &[f64]| v.iter().sum::<f64>() / v.len() as f64;
+            let (ma, mb) = (mean(a), mean(b));
+            let cov: f64 = a.iter().zip(b).map(|(x, y)| (x - ma) * (y - mb)).sum();
+            let var = |v: &[f64], m: f64| v.iter().map(|x| (x - m).powi(2)).sum::<f64>();
+            cov / (var(a, ma) * var(b, mb)).sqrt()
+        }
+
+        let field = CloudField { seed: 0, cell_km: 20.0, persistence: 0.7 };
+        let transients = |lat: f64, lon: f64| -> Vec<f64> {
+            (0..2000).map(|slot| field.sample(lat, lon, slot, 2)).collect()
+        };
+        let turin = transients(45.07, 7.69);
+        let two_km_north = transients(45.07 + 2.0 / KM_PER_DEG, 7.69);
+        let two_km_east = transients(45.07, 7.69 + 2.0 / (KM_PER_DEG * (45.07 * DEG).cos()));
+        let thousand_km_east = transients(45.07, 7.69 + 1000.0 / (KM_PER_DEG * (45.07 * DEG).cos()));
+
+        assert!(correlation(&turin, &two_km_north) > 0.9, "{:.3}", correlation(&turin, &two_km_north));
+        assert!(correlation(&turin, &two_km_east) > 0.9, "{:.3}", correlation(&turin, &two_km_east));
+        assert!(correlation(&turin, &thousand_km_east).abs() < 0.1, "{:.3}", correlation(&turin, &thousand_km_east));
+
+        // Through the full estimate: neighbours' cloud factors move together
+        let day = |lat: f64, lon: f64| -> Vec<f64> {
+            let params = EstimateParams::new(lat, lon, 1000.0);
+            let start = Utc.with_ymd_and_hms(2025, 5, 1, 0, 0, 0).unwrap();
+            (0..2000).map(|i| estimate(&params, start + chrono::Duration::minutes(5 * i)).cloud_factor).collect()
+        };
+        let near = correlation(&day(45.07, 7.69), &day(45.07 + 2.0 / KM_PER_DEG, 7.69));
+        assert!(near > 0.9, "{:.3}", near);
+    }
+
+    #[test]
+    fn cloudy_and_clear_spells_persist_for_days() {
+        fn lag1_autocorrelation(x: &[f64]) -> f64 {
+            let mean = x.iter().sum::<f64>() / x.len() as f64;
+            let cov: f64 = x.windows(2).map(|w| (w[0] - mean) * (w[1] - mean)).sum();
+            cov / x.iter().map(|v| (v - mean).powi(2)).sum::<f64>()
+        }
+        // A year of daily anomalies, averaged over sites with independent skies
+        let sites = [(45.07, 7.69), (40.4, -3.7), (52.5, 13.4), (-33.9, 18.4), (35.7, 139.7)];
+        let mean_lag1 = |persistence: f64| {
+            let field = CloudField { seed: 0, cell_km: 20.0, persistence };
+            sites.iter().map(|&(lat, lon)| {
+                let year: Vec<f64> = (1..=365).map(|doy| field.daily_noise(lat, lon, doy as f64)).collect();
+                lag1_autocorrelation(&year)
+            }).sum::<f64>() / sites.len() as f64
+        };
+
+        let persistent = mean_lag1(0.7);
+        assert!((persistent - 0.7).abs() < 0.1, "lag-1 autocorrelation {:.3}", persistent);
+        let independent = mean_lag1(0.0);
+        assert!(independent.abs() < 0.1, "lag-1 autocorrelation {:.3}", independent);
+    }
+
+    #[test]
+    fn soiling_rate_and_live_state_drive_the_soiling_factor() {
+        // Dry Andalusian summer: dust builds up faster at a higher rate
+        let at = |rate: f64, live: Option<f64>, day: u32| {
+            let params = EstimateParams {
+                soiling_rate_pct_per_day: rate,
+                soiling_factor: live,
+                ..EstimateParams::new(37.39, -5.98, 1000.0)
+            };
+            estimate(&params, Utc.with_ymd_and_hms(2025, 7, day, 11, 0, 0).unwrap()).soiling_factor
+        };
+        let mut dirtier = 0;
+        for day in 1..=31 {
+            let (slow, fast) = (at(0.3, None, day), at(1.0, None, day));
+            assert!(fast <= slow && fast >= MIN_SOILING_FACTOR, "{fast} vs {slow} on July {day}");
+            if fast < slow {
+                dirtier += 1;
+            }
+        }
+        assert!(dirtier > 0);
+
+        // Once the plant runs, the live state replaces the replay
+        assert_eq!(at(0.3, Some(0.83), 15), 0.83);
+    }
+
+    #[test]
+    fn module_ratings_set_the_dc_power_of_a_sized_layout() {
+        let t = Utc.with_ymd_and_hms(2025, 6, 21, 11, 0, 0).unwrap();
+        let nominal = EstimateParams::new(45.07, 7.69, 1000.0);
+        let plain = estimate(&nominal, t);
+        assert!((plain.mppt[0].power_kw() - plain.power_kw).abs() < 1e-9);
+        assert!(plain.mppt[0].vmp_v > 500.0 && plain.mppt[0].vmp_v < 600.0, "{:.0} V", plain.mppt[0].vmp_v);
+
+        // 120 strings of 14 × 400 W: 672 kWp whatever the nominal power says
+        let rated = EstimateParams {
+            string_layout: StringLayout { strings: 120, ..StringLayout::sized_for(1000.0) },
+            layout_rated:  true,
+            ..nominal.clone()
+        };
+        let r = estimate(&rated, t);
+        assert!((r.power_kw - r.mppt[0].vmp_v * r.mppt[0].imp_a * 120.0 / 1000.0).abs() < 1e-9);
+        assert!(r.power_kw < 0.8 * plain.power_kw, "{:.0} vs {:.0} kW", r.power_kw, plain.power_kw);
+    }
+
+    #[test]
+    fn horizontal_components_close_on_ghi() {
+        let walled = EstimateParams {
+            horizon: horizon_profile(&[HorizonPoint { azimuth_deg: 90.0, elevation_deg: 25.0 }]),
+            ..EstimateParams::new(45.07, 7.33, 100.0)
+        };
+        for hour in 3..20 {
+            let t = Utc.with_ymd_and_hms(2025, 6, 21, hour, 0, 0).unwrap();
+            for r in [estimate(&EstimateParams::new(45.07, 7.33, 100.0), t), estimate(&walled, t)] {
+                let sin_elev = (r.solar_elevation_deg * DEG).sin().max(0.0);
+                let closure = r.dni_w_m2 * sin_elev + r.dhi_w_m2;
+                assert!((r.ghi_w_m2 - closure).abs() < 0.5, "{hour}:00 UTC: GHI {:.1} vs {:.1} W/m²", r.ghi_w_m2, closure);
+                assert!(r.dhi_w_m2 >= 0.0 && r.dni_w_m2 <= r.dni_clear_sky_w_m2);
+            }
+        }
+
+        // Noon: the beam dominates, and the tilted array gathers more than the ground
+        let r = estimate(&EstimateParams::new(45.07, 7.33, 100.0), Utc.with_ymd_and_hms(2025, 6, 21, 11, 0, 0).unwrap());
+        assert!(r.dni_w_m2 * (r.solar_elevation_deg * DEG).sin() > r.dhi_w_m2);
+        assert!(r.ghi_w_m2 > 0.0 && r.poa_w_m2 > 0.0);
+    }
+
+    /// Every float in `r`, to check for NaN and infinities.
+    fn all_floats(r: &OfflineEstimate) -> Vec<(&'static str, f64)> {
+        let mut floats = vec![
+            ("power_kw", r.power_kw), ("poa_w_m2", r.poa_w_m2), ("ghi_w_m2", r.ghi_w_m2),
+            ("dni_w_m2", r.dni_w_m2), ("dhi_w_m2", r.dhi_w_m2), ("rear_irradiance_w_m2", r.rear_irradiance_w_m2),
+            ("cell_temp_c", r.cell_temp_c), ("ambient_temp_c", r.ambient_temp_c), ("cloud_factor", r.cloud_factor),
+            ("solar_elevation_deg", r.solar_elevation_deg), ("dni_clear_sky_w_m2", r.dni_clear_sky_w_m2),
+            ("linke_turbidity", r.linke_turbidity), ("wind_speed_m_s", r.wind_speed_m_s),
+            ("relative_humidity_pct", r.relative_humidity_pct), ("soiling_factor", r.soiling_factor),
+            ("rain_mm_h", r.rain_mm_h), ("snow_cover_factor", r.snow_cover_factor),
+            ("degradation_factor", r.degradation_factor), ("angle_of_incidence_deg", r.angle_of_incidence_deg),
+            ("iam", r.iam),
+        ];
+        for dc in &r.mppt {
+            floats.extend([("voc_v", dc.voc_v), ("isc_a", dc.isc_a), ("vmp_v", dc.vmp_v), ("imp_a", dc.imp_a)]);
+        }
+        floats
+    }
+
+    #[test]
+    fn still_humid_october_morning_holds_back_the_ramp_under_fog() {
+        // A river-valley plant at 45°N; 1 October 2025 dawns calm
+        let open = EstimateParams { timezone: chrono_tz::Europe::Rome, ..EstimateParams::new(45.0, 9.0, 100.0) };
+        let valley = EstimateParams { humidity_offset_pct: 12.0, ..open.clone() };
+        let SunTimes::Daylight { sunrise, .. } = sun_times(45.0, 9.0, NaiveDate::from_ymd_opt(2025, 10, 1).unwrap()) else {
+            panic!("the sun rises at 45°N");
+        };
+        assert!(estimate(&valley, sunrise).relative_humidity_pct > 96.0);
+
+        // Every 5-second update from half an hour before dawn to an hour after
+        // sees the same fog bank, and never more output than the open site
+        for step in 0..1080 {
+            let t = sunrise - chrono::Duration::minutes(30) + chrono::Duration::seconds(5 * step);
+            let (foggy, clear) = (estimate(&valley, t), estimate(&open, t));
+            assert_eq!(foggy.weather_code, 45, "{t}");
+            assert_ne!(clear.weather_code, 45, "{t}");
+            assert!(foggy.power_kw <= clear.power_kw, "{t}: {:.2} vs {:.2} kW", foggy.power_kw, clear.power_kw);
+            if t <= sunrise {
+                assert!(foggy.cloud_factor <= 0.3, "{t}: {:.2}", foggy.cloud_factor);
+            }
+        }
+
+        // Burnt off within three hours of sunrise: the ramp catches up
+        let later = sunrise + chrono::Duration::hours(3);
+        let (foggy, clear) = (estimate(&valley, later), estimate(&open, later));
+        assert_ne!(foggy.weather_code, 45);
+        assert_eq!((foggy.power_kw, foggy.cloud_factor), (clear.power_kw, clear.cloud_factor));
+    }
+
+    #[test]
+    fn svalbard_runs_through_midnight_sun_and_polar_night() {
+        // Longyearbyen, 78.2°N
+        let (lat, lon) = (78.22, 15.65);
+        let fixed = EstimateParams::new(lat, lon, 100.0);
+        let tracking = EstimateParams { mounting: Mounting::Tracker2Axis, ..fixed.clone() };
+        let day = |params: &EstimateParams, month, d| -> Vec<OfflineEstimate> {
+            let start = Utc.with_ymd_and_hms(2025, month, d, 0, 0, 0).unwrap();
+            (0..96).map(|i| estimate(params, start + chrono::Duration::minutes(15 * i))).collect()
+        };
+
+        // June 21: the sun circles above the horizon and the plant never stops
+        for params in [&fixed, &tracking] {
+            for r in day(params, 6, 21) {
+                assert!(all_floats(&r).iter().all(|(_, v)| v.is_finite()), "{:?}", r);
+                assert!(r.solar_elevation_deg > 0.0 && r.is_day && r.power_kw > 0.0, "{:?}", r);
+                assert!((-40.0..25.0).contains(&r.ambient_temp_c), "{:.1} °C", r.ambient_temp_c);
+            }
+        }
+        let midnight = sun_times(lat, lon, NaiveDate::from_ymd_opt(2025, 6, 21).unwrap()).solar_noon()
+            + chrono::Duration::hours(12);
+        let r = estimate(&tracking, midnight);
+        assert!(r.power_kw > 0.0 && r.angle_of_incidence_deg < 0.5, "AOI {:.2}°", r.angle_of_incidence_deg);
+        assert!((r.solar_elevation_deg - (lat + 23.44 - 90.0)).abs() < 0.5, "{:.2}°", r.solar_elevation_deg);
+
+        // December 21: dark all day, everything stays finite and calm
+        let night = day(&fixed, 12, 21);
+        for r in &night {
+            assert!(all_floats(r).iter().all(|(_, v)| v.is_finite()), "{:?}", r);
+            assert!(r.solar_elevation_deg < 0.0 && !r.is_day);
+            assert_eq!((r.power_kw, r.poa_w_m2, r.weather_code), (0.0, 0.0, 0));
+            assert!((-40.0..0.0).contains(&r.ambient_temp_c), "{:.1} °C", r.ambient_temp_c);
+        }
+        // No sun, no diurnal temperature cycle
+        let (coldest, warmest) = night.iter().map(|r| r.ambient_temp_c)
+            .fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), t| (lo.min(t), hi.max(t)));
+        assert!(warmest - coldest < 1e-9, "{coldest:.2} … {warmest:.2} °C");
+
+        // The North Pole itself keeps a defined azimuth
+        let pole = estimate(&EstimateParams { mounting: Mounting::Tracker2Axis, ..EstimateParams::new(90.0, 0.0, 100.0) }, midnight);
+        assert!(all_floats(&pole).iter().all(|(_, v)| v.is_finite()), "{:?}", pole);
+        assert!(pole.angle_of_incidence_deg < 0.5);
     }
 }
@@ -0,0 +1,289 @@
+use std::collections::HashMap;
+use std::time::Instant;
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::power::{Alarm, Event, PlantData};
+use crate::services::grid_frequency::WalkState;
+use crate::services::sim_clock::SimClock;
+use crate::shared_state::AppState;
+
+/// Schema version of `/api/system/snapshot`; a document of any other
+/// version is refused rather than half-loaded.
+pub const SNAPSHOT_VERSION: u32 = 1;
+/// Newest events carried by a snapshot
+pub const SNAPSHOT_EVENTS: usize = 200;
+
+// ─── Per-plant internals ─────────────────────────────────────
+/// The simulation state `PlantData` keeps from API clients: ramp, startup
+/// sequence, protection counters, energy integral. Without it the first
+/// update after a load would restart the inverter instead of continuing.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PlantInternals {
+    pub ramp_factor:         f64,
+    pub ramped_dc_kw:        f64,
+    pub grid_connected:      bool,
+    pub start_cycles:        u32,
+    pub search_cycles:       u32,
+    pub online_today:        bool,
+    pub freq_watt_ref_kw:    Option<f64>,
+    pub scheduled_limit_pct: Option<f64>,
+    pub protection_cycles:   [u32; 5],
+    pub protection_trip:     Option<u16>,
+    pub reconnect_cycles:    u32,
+    pub dropout_cycles:      u32,
+    pub isolation_fault:     bool,
+    pub energy_day:          Option<NaiveDate>,
+    pub last_update_at:      Option<DateTime<Utc>>,
+    pub fan_fault_active:    bool,
+}
+
+impl PlantInternals {
+    pub fn of(data: &PlantData) -> Self {
+        Self {
+            ramp_factor:         data.ramp_factor,
+            ramped_dc_kw:        data.ramped_dc_kw,
+            grid_connected:      data.grid_connected,
+            start_cycles:        data.start_cycles,
+            search_cycles:       data.search_cycles,
+            online_today:        data.online_today,
+            freq_watt_ref_kw:    data.freq_watt_ref_kw,
+            scheduled_limit_pct: data.scheduled_limit_pct,
+            protection_cycles:   data.protection_cycles,
+            protection_trip:     data.protection_trip,
+            reconnect_cycles:    data.reconnect_cycles,
+            dropout_cycles:      data.dropout_cycles,
+            isolation_fault:     data.isolation_fault,
+            energy_day:          data.energy_day,
+            last_update_at:      data.last_update_at,
+            fan_fault_active:    data.fan_fault_active,
+        }
+    }
+
+    /// Write the internals back; a plant updated before the snapshot counts
+    /// as updated now for its staleness.
+    pub fn write_to(self, data: &mut PlantData) {
+        data.ramp_factor         = self.ramp_factor;
+        data.ramped_dc_kw        = self.ramped_dc_kw;
+        data.grid_connected      = self.grid_connected;
+        data.start_cycles        = self.start_cycles;
+        data.search_cycles       = self.search_cycles;
+        data.online_today        = self.online_today;
+        data.freq_watt_ref_kw    = self.freq_watt_ref_kw;
+        data.scheduled_limit_pct = self.scheduled_limit_pct;
+        data.protection_cycles   = self.protection_cycles;
+        data.protection_trip     = self.protection_trip;
+        data.reconnect_cycles    = self.reconnect_cycles;
+        data.dropout_cycles      = self.dropout_cycles;
+        data.isolation_fault     = self.isolation_fault;
+        data.energy_day          = self.energy_day;
+        data.last_update_at      = self.last_update_at;
+        data.fan_fault_active    = self.fan_fault_active;
+        data.updated_at          = self.last_update_at.map(|_| Instant::now());
+        data.clock_jumped        = false;
+    }
+}
+
+/// One plant in a snapshot: the telemetry clients see and the internals.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PlantState {
+    pub data:      PlantData,
+    pub internals: PlantInternals,
+}
+
+/// Simulation clock at the time of the snapshot.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ClockState {
+    pub speed:    f64,
+    pub now:      DateTime<Utc>,
+    pub realtime: bool,
+}
+
+/// Alarm condition still inside its activation delay.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct PendingAlarm {
+    pub plant_id: String,
+    pub code:     u16,
+    pub since:    DateTime<Utc>,
+}
+
+// ─── Snapshot ────────────────────────────────────────────────
+/// The whole simulation state behind `/api/system/snapshot`. Unlike the
+/// persistence snapshot, which carries the counters across a restart, it
+/// resumes the simulation exactly where it was taken. Conditions forced
+/// over the API (faults, grid events, isolation faults) and the SCADA
+/// watchdog run on the wall clock of the process and are not carried.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StateSnapshot {
+    pub version:        u32,
+    pub taken_at:       DateTime<Utc>,
+    pub clock:          ClockState,
+    pub simulation_seed: u64,
+    pub offline_mode:   bool,
+    pub plants:         HashMap<String, PlantState>,
+    /// Alarms active when the snapshot was taken
+    pub alarms:         Vec<Alarm>,
+    #[serde(default)]
+    pub pending_alarms: Vec<PendingAlarm>,
+    /// Newest events, newest first
+    #[serde(default)]
+    pub events:         Vec<Event>,
+    /// Random walk of each grid network's frequency
+    #[serde(default)]
+    pub grids:          HashMap<String, WalkState>,
+}
+
+impl StateSnapshot {
+    pub fn of(state: &AppState) -> Self {
+        let clock = state.sim_clock();
+        let plants = state.map_all_data(|d| PlantState { data: d.clone(), internals: PlantInternals::of(d) }).collect();
+        let alarms = state.alarms.read().unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .filter(|a| a.active)
+            .cloned()
+            .collect();
+        let events = state.get_events(SNAPSHOT_EVENTS);
+        let pending_alarms = state.pending_alarms().into_iter()
+            .map(|(plant_id, code, since)| PendingAlarm { plant_id, code, since })
+            .collect();
+        Self {
+            version:         SNAPSHOT_VERSION,
+            taken_at:        Utc::now(),
+            clock:           ClockState { speed: clock.speed(), now: clock.now(), realtime: clock.is_realtime() },
+            simulation_seed: state.simulation_seed(),
+            offline_mode:    state.is_offline(),
+            plants,
+            alarms,
+            pending_alarms,
+            events,
+            grids:           state.grid_walks(),
+        }
+    }
+
+    /// Parse a snapshot document, refusing another schema version before
+    /// looking at the rest of it.
+    pub fn parse(document: serde_json::Value) -> Result<Self, SnapshotError> {
+        match document.get("version").and_then(serde_json::Value::as_u64) {
+            Some(v) if v == u64::from(SNAPSHOT_VERSION) => {}
+            Some(v) => return Err(SnapshotError::Unsupported(format!(
+                "snapshot schema version {} is not supported: this simulator reads version {}", v, SNAPSHOT_VERSION
+            ))),
+            None => return Err(SnapshotError::Unsupported(format!(
+                "snapshot has no schema version: this simulator reads version {}", SNAPSHOT_VERSION
+            ))),
+        }
+        serde_json::from_value(document).map_err(|e| SnapshotError::Invalid(e.to_string()))
+    }
+
+    /// Replace the running state with the snapshot: the mode and the clock
+    /// first, so the clock jump does not restart the energy integrals of the
+    /// plants loaded after it. The alarm registry and the event log are
+    /// replaced, not merged.
+    pub fn restore(self, state: &AppState) -> Result<(), SnapshotError> {
+        let clock = if self.clock.realtime {
+            SimClock::default()
+        } else if !self.offline_mode {
+            return Err(SnapshotError::Invalid("a snapshot off the wall clock must be in offline mode".to_string()));
+        } else {
+            SimClock::new(self.clock.speed, Some(self.clock.now), Utc::now()).map_err(SnapshotError::Invalid)?
+        };
+        state.set_offline(self.offline_mode);
+        state.set_sim_clock(clock).map_err(SnapshotError::Invalid)?;
+        state.set_simulation_seed(self.simulation_seed);
+        for (id, plant) in self.plants {
+            let mut data = plant.data;
+            plant.internals.write_to(&mut data);
+            state.plant_data.insert(&id, data);
+        }
+        if let Ok(mut alarms) = state.alarms.write() {
+            *alarms = self.alarms;
+        }
+        state.restore_pending_alarms(
+            self.pending_alarms.into_iter().map(|p| (p.plant_id, p.code, p.since)).collect(),
+        );
+        if let Ok(mut log) = state.events.write() {
+            *log = self.events.into();
+        }
+        state.restore_grid_walks(self.grids);
+        Ok(())
+    }
+}
+
+/// Why a snapshot was not loaded.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SnapshotError {
+    /// Another schema version, or none
+    Unsupported(String),
+    /// Not a snapshot document, or values out of range
+    Invalid(String),
+}
+
+impl std::fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unsupported(e) | Self::Invalid(e) => f.write_str(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use crate::services::power_service::from_estimate;
+    use crate::services::solar_algorithm::{estimate, EstimateParams};
+    use crate::shared_state::PlantRating;
+
+    #[test]
+    fn a_loaded_snapshot_updates_to_the_same_telemetry() {
+        let morning = Utc.with_ymd_and_hms(2025, 6, 21, 6, 0, 0).unwrap();
+        let plant: crate::config::PlantConfig = serde_json::from_value(serde_json::json!({
+            "id": "plant_1", "name": "p", "latitude": 45.07, "longitude": 7.33,
+            "nominal_power_kw": 100.0, "timezone": "Europe/Rome",
+            "modbus_mapping": { "base_address": 0 }
+        })).unwrap();
+        let rating = PlantRating::of(&plant);
+        let weather = |at| from_estimate(at, estimate(&EstimateParams::for_plant(&plant), at));
+
+        // Halfway through the startup sequence and the output ramp
+        let original = AppState::new(true);
+        original.set_simulation_seed(7);
+        let mut at = morning;
+        for _ in 0..4 {
+            at += chrono::Duration::seconds(5);
+            original.set_clock(at);
+            original.set_data("plant_1", &rating, &weather(at), 0.0);
+        }
+        let json = serde_json::to_string(&StateSnapshot::of(&original)).unwrap();
+
+        let loaded = AppState::new(true);
+        StateSnapshot::parse(serde_json::from_str(&json).unwrap()).unwrap().restore(&loaded).unwrap();
+        assert_eq!(loaded.simulation_seed(), 7);
+        assert_eq!(loaded.get_events(SNAPSHOT_EVENTS).len(), original.get_events(SNAPSHOT_EVENTS).len());
+
+        at += chrono::Duration::seconds(5);
+        for state in [&original, &loaded] {
+            state.set_clock(at);
+            state.set_data("plant_1", &rating, &weather(at), 0.0);
+        }
+        let (was, is) = (original.get_data("plant_1").unwrap(), loaded.get_data("plant_1").unwrap());
+        assert_eq!(serde_json::to_value(&is).unwrap(), serde_json::to_value(&was).unwrap());
+        assert_eq!(PlantInternals::of(&is), PlantInternals::of(&was));
+    }
+
+    #[test]
+    fn another_schema_version_is_refused() {
+        let mut document = serde_json::to_value(StateSnapshot::of(&AppState::new(true))).unwrap();
+        assert!(StateSnapshot::parse(document.clone()).is_ok());
+        document["version"] = serde_json::json!(SNAPSHOT_VERSION + 1);
+        let Err(SnapshotError::Unsupported(e)) = StateSnapshot::parse(document.clone()) else { panic!("version accepted") };
+        assert!(e.contains("version 2") && e.contains("version 1"), "{e}");
+        document.as_object_mut().unwrap().remove("version");
+        assert!(matches!(StateSnapshot::parse(document), Err(SnapshotError::Unsupported(_))));
+        assert!(matches!(
+            StateSnapshot::parse(serde_json::json!({ "version": SNAPSHOT_VERSION, "plants": [] })),
+            Err(SnapshotError::Invalid(_))
+        ));
+    }
+}
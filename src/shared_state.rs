@@ -1,53 +1,71 @@
-use std::collections::{HashMap, VecDeque};
-use std::sync::{Arc, RwLock};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::sync::{Arc, Mutex, RwLock};
 use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::time::{SystemTime, UNIX_EPOCH};
-use chrono::Datelike;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use chrono_tz::Tz;
+use tokio::sync::broadcast;
 
+use crate::config::{AlarmConfig, PhaseImbalanceConfig, PlantConfig, ProtectionConfig};
 use crate::models::power::{
-    Alarm, AlarmSeverity, Event, EventKind, PlantData,
-    alarm_codes, alarm_flag_bits,
+    Alarm, AlarmSeverity, ControlInterface, Event, EventKind, MpptData, PlantData, ReactivePowerMode, SimulationData,
+    alarm_codes, alarm_flag_bits, status_label,
 };
+use crate::plant_store::PlantStore;
+use crate::services::curtailment_schedule::CurtailmentSchedule;
+use crate::services::energy_meter::EnergyMeter;
+use crate::services::grid_frequency::{Disturbance, GridFrequency, WalkState};
+use crate::services::grid_support::{FreqWatt, VoltVarCurve};
+use crate::services::history::{History, HistoryPoint, Resolution};
+use crate::services::inverter_efficiency::EfficiencyCurve;
+use crate::services::measurement_noise::{MeasurementNoise, Readings};
+use crate::services::plant_registry::PlantRegistry;
+use crate::services::pv_string::DcOperatingPoint;
+use crate::services::scenarios::Scenario;
+use crate::services::sim_clock::SimClock;
+use crate::services::solar_algorithm;
 
 const MAX_ALARM_HISTORY: usize  = 500;
-const MAX_EVENT_LOG: usize      = 1000;
-/// Update interval in seconds (must match main.rs sleep)
+pub const MAX_EVENT_LOG: usize  = 5000;  // newest kept, older events rotate out
+/// Update interval in seconds of plants without their own `update_interval_s`
 const UPDATE_INTERVAL_S: f64   = 5.0;
+const STALE_AFTER_INTERVALS: f64 = 3.0;  // missed updates before a plant without its own threshold is stale
+const MAX_ENERGY_GAP_S: f64    = 60.0;   // longer gaps between updates are not integrated
+const DEFAULT_HISTORY_H: f64   = 24.0;
+/// Alarms and events a streaming client may fall behind by before it misses some
+const NOTIFICATION_BUFFER: usize = 256;
 
-// ─── Nominal DC string constants (typical c-Si array) ───────────────────────
-/// Nominal DC link voltage at STC (V). Real inverters operate 400–800 V DC.
+// ─── DC input rating ────────────────────────────────────────────────────────
+/// Nominal DC input voltage of the inverter (V); string V_oc may exceed it by 10 %.
 const V_DC_NOM: f64    = 700.0;
-/// V_mpp / V_oc ratio (≈0.80 for c-Si)
-const VMPP_VOC_RATIO: f64 = 0.80;
-/// Temperature coefficient of V_mpp (V/V/°C)
-const V_TEMP_COEFF: f64  = -0.0035;
 
 // ─── MPPT startup / shutdown thresholds ─────────────────────────────────────
 /// Minimum POA irradiance (W/m²) for the inverter to attempt grid connection
-const IRRAD_START_W_M2: f64 = 30.0;
+pub const IRRAD_START_W_M2: f64 = 30.0;
 /// Minimum POA irradiance (W/m²) to stay connected (hysteresis below start)
-const IRRAD_STOP_W_M2:  f64 = 15.0;
-/// Ramp rate per 5-second sample during startup / shutdown (fraction / sample)
-const RAMP_RATE: f64 = 0.08; // 0 → 1 in ~12.5 samples ≈ 62 s
-
-// ─── Grid limits (configurable in a real inverter) ──────────────────────────
-const V_GRID_NOM: f64       = 230.0;   // V (L-N)
-const V_OV_LIMIT: f64       = 253.0;   // +10 % EN 50160
-const V_UV_LIMIT: f64       = 207.0;   // -10 %
-const F_NOM: f64            = 50.0;    // Hz
-const F_OV_LIMIT: f64       = 50.5;    // Hz
-const F_UV_LIMIT: f64       = 49.5;    // Hz
-const ROCOF_LIMIT: f64      = 1.0;     // Hz/s (VDE 4110)
-const ISOL_FAULT_MOHM: f64  = 0.5;    // MΩ — below this triggers isolation fault
+pub const IRRAD_STOP_W_M2:  f64 = 15.0;
+/// Update cycles spent in the Starting state before connecting (30 s)
+pub const STARTUP_DELAY_CYCLES: u32 = 6;
+/// Update cycles spent searching the MPP after connecting (15 s)
+pub const MPPT_SEARCH_CYCLES: u32 = 3;
+
+// ─── Grid nominals (trip limits come from `grid.protection`) ────────────────
+const V_GRID_REF: f64       = 230.0;   // V (L-N) — the voltage excursions below are sized on it
+const F_NOM: f64            = 50.0;    // Hz — plants without a configured grid
+const DEFAULT_GRID_NETWORK: &str = "default";
 const T_OVERTEMP_C: f64     = 80.0;   // °C inverter heatsink trip
+const MPPT_MIN_STRING_A: f64 = 0.5;   // A — below this the string currents are not compared
+const SNOW_ALARM_COVER: f64 = 0.10;   // share of the array under snow that raises an alarm
+const RAIN_WASH_MM_H: f64   = 1.0;    // rain rate that washes the panels clean
+/// Alarms that hold an enabled inverter in Fault until they clear
+const TRIP_ALARMS: [u16; 4] = [
+    alarm_codes::GRID_ISLAND_DETECTED, alarm_codes::ISOLATION_FAULT,
+    alarm_codes::OVERTEMPERATURE, alarm_codes::DC_OVERVOLTAGE,
+];
 
 // ─── Fault injection probabilities ──────────────────────────────────────────
 /// Probability per 5-minute epoch that a grid-voltage swell/sag event fires.
 const P_VOLT_FAULT: f64    = 0.025;  // ~1 event / 83 min per plant
-/// Probability per 5-minute epoch for an over/under-frequency event.
-const P_FREQ_FAULT: f64    = 0.015;  // ~1 event / ~2.8 h per plant
-/// Probability per 1-hour epoch for an isolation-resistance fault.
-const P_ISOL_FAULT: f64    = 0.015;  // ~1 event / 67 h per plant (heavy rain)
 /// Probability per 15-minute epoch for an overtemperature event.
 const P_OT_FAULT: f64      = 0.005;  // ~1 event / 50 h per plant
 
@@ -66,42 +84,374 @@ fn det_hash(plant_id: &str, epoch: u64) -> f64 {
     (h >> 11) as f64 / (1u64 << 53) as f64
 }
 
-/// Assigns fault_code only when no higher-priority code is already set.
-/// Priority order: first-assigned wins (the triggering condition takes precedence).
-#[inline]
-fn try_set_fault(code: &mut u16, new_code: u16) {
-    if *code == crate::models::power::alarm_codes::NONE { *code = new_code; }
+/// DC-to-ground isolation resistance (MΩ) the inverter measures:
+///  a) 10–40 MΩ on a dry array, rising with the light as the panels warm
+///  b) dew on the connectors: on a humid morning the surface leakage takes
+///     up to two decades off until the sun is 20° up
+///  c) insulation ageing, ten times as fast as the module power loss
+fn isolation_resistance_mohm(irr_ratio: f64, solar_elevation_deg: f64, relative_humidity_pct: f64, degradation_factor: f64) -> f64 {
+    let dry   = 10.0 + irr_ratio * 30.0;
+    let wet   = ((relative_humidity_pct - 80.0) / 15.0).clamp(0.0, 1.0);
+    let dawn  = (1.0 - solar_elevation_deg / 20.0).clamp(0.0, 1.0);
+    let dew   = 10f64.powf(-2.0 * wet * dawn);
+    let aging = degradation_factor.clamp(0.0, 1.0).powi(10);
+    (dry * dew * aging).max(0.05)
+}
+
+/// Largest deviation (%) of an MPPT input's per-string current from the
+/// average of the inputs; 0 with a single input or in weak light.
+fn mppt_deviation_pct(mppt: &[DcOperatingPoint]) -> f64 {
+    if mppt.len() < 2 {
+        return 0.0;
+    }
+    let mean = mppt.iter().map(|m| m.imp_a).sum::<f64>() / mppt.len() as f64;
+    if mean < MPPT_MIN_STRING_A {
+        return 0.0;
+    }
+    mppt.iter().map(|m| (m.imp_a - mean).abs() / mean * 100.0).fold(0.0, f64::max)
+}
+
+/// Relative deviations of the three phases from their average for one plant
+/// and epoch: each within ±`spread_pct` %, and summing to zero.
+fn phase_spread(plant_id: &str, epoch: u64, spread_pct: f64) -> [f64; 3] {
+    let u = [0, 1, 2].map(|i| det_hash(plant_id, epoch.wrapping_mul(3) + i) * 2.0 - 1.0);
+    let mean = (u[0] + u[1] + u[2]) / 3.0;
+    // A deviation from the mean of three values in [-1, 1] is at most 4/3
+    u.map(|x| (x - mean) * 0.75 * spread_pct / 100.0)
+}
+
+/// Drive every output quantity of a stopped inverter to zero.
+fn stop_output(data: &mut PlantData) {
+    data.status              = 0;
+    data.grid_export_kw      = -data.site_load_kw;
+    data.grid_connected      = false;
+    data.ramp_factor         = 0.0;
+    data.ramped_dc_kw        = 0.0;
+    data.power_kw            = 0.0;
+    data.dc_power_kw         = 0.0;
+    data.dc_voltage_v        = 0.0;
+    data.dc_current_a        = 0.0;
+    data.mppt_voltage_v      = 0.0;
+    data.mppt_current_a      = 0.0;
+    data.mppt.iter_mut().for_each(|m| *m = MpptData::default());
+    data.string1_voltage_v   = 0.0;
+    data.string1_current_a   = 0.0;
+    data.string2_voltage_v   = 0.0;
+    data.string2_current_a   = 0.0;
+    data.current_l1_a        = 0.0;
+    data.current_l2_a        = 0.0;
+    data.current_l3_a        = 0.0;
+    data.apparent_power_kva  = 0.0;
+    data.reactive_power_kvar = 0.0;
+    data.efficiency_percent  = 0.0;
+    data.clipping            = false;
+    data.dc_injection_ma     = 0.0;
+    data.ac_thd_percent      = 0.0;
+}
+
+/// Split the available active power `p_kw` into the delivered (P, Q) for the
+/// commanded reactive mode, keeping S = √(P² + Q²) within `max_kva`.
+/// Reactive power has priority: P is derated first, Q only beyond the rating.
+fn dispatch_reactive(mode: ReactivePowerMode, cos_phi: f64, q_kvar: f64, p_kw: f64, max_kva: f64) -> (f64, f64) {
+    let max_kva = max_kva.max(0.0);
+    match mode {
+        ReactivePowerMode::FixedPf => (p_kw.min(max_kva), 0.0),
+        ReactivePowerMode::CosPhi => {
+            let cos_phi = if cos_phi > 0.0 { cos_phi.min(1.0) } else { 1.0 };
+            let s = (p_kw / cos_phi).min(max_kva);
+            let p = s * cos_phi;
+            (p, (s * s - p * p).max(0.0).sqrt())
+        }
+        ReactivePowerMode::FixedQ | ReactivePowerMode::VoltVar => {
+            let q = q_kvar.clamp(-max_kva, max_kva);
+            (p_kw.min((max_kva * max_kva - q * q).max(0.0).sqrt()), q)
+        }
+    }
+}
+
+/// Protection inputs captured at the end of a `set_data` cycle.
+struct AlarmSnapshot {
+    at:              DateTime<Utc>,
+    limits:          ProtectionConfig,
+    thresholds:      AlarmConfig,
+    /// Code of the protection function holding the inverter off the grid
+    protection_trip: Option<u16>,
+    v_avg:           f64,
+    is_day:          bool,
+    ac_power:        f64,
+    dc_ov:           bool,
+    frequency_hz:    f64,
+    /// Latched by the Riso check (with hysteresis)
+    isolation_fault: bool,
+    isolation_mohm:  f64,
+    inverter_temp_c: f64,
+    mppt_deviation_pct: f64,
+    rocof_hz_s:      f64,
+    leakage_ma:      f64,
+    fan_fault:       bool,
+    fan_rpm:         u16,
+    tracker_stowed:  bool,
+    wind_speed_m_s:  f64,
+    snow_cover:      f64,
+    damage_pct:      f64,
+    /// Present only while the SCADA watchdog is expired
+    stale_watchdog:  Option<Watchdog>,
+}
+
+/// One threshold of the alarm engine, evaluated on a single sample.
+struct ThresholdCheck {
+    code:     u16,
+    severity: AlarmSeverity,
+    delay_s:  f64,
+    /// Value beyond the limit: the activation delay runs
+    raise:    bool,
+    /// Value back inside the limit by the hysteresis: an active alarm clears
+    clear:    bool,
 }
 
 #[derive(Clone, Debug)]
 pub struct AppState {
-    pub plant_data:     Arc<RwLock<HashMap<String, PlantData>>>,
+    pub plant_data:     PlantStore,
     pub offline_mode:   Arc<AtomicBool>,
+    /// Weather scenario seed for plants without their own (`simulation.seed`)
+    seed:               Arc<AtomicU64>,
     pub mqtt_connected: Arc<AtomicBool>,
     /// Alarm registry: all alarms (active + historical)
     pub alarms:         Arc<RwLock<Vec<Alarm>>>,
     /// Event log ring-buffer
     pub events:         Arc<RwLock<VecDeque<Event>>>,
-    /// Unix timestamp of when the process started (for uptime)
-    pub start_time:     u64,
-    /// Previous frequency per plant for ROCOF (Hz)
-    prev_freq:          Arc<RwLock<HashMap<String, f64>>>,
+    /// When the process started, on the wall clock
+    started_at:         DateTime<Utc>,
+    /// Monotonic start of the process, for the uptime
+    started:            Instant,
+    /// Grid network each plant feeds; unconfigured plants share the default one
+    grid_networks:      Arc<RwLock<HashMap<String, String>>>,
+    /// Frequency model per grid network
+    grid_frequencies:   Arc<RwLock<HashMap<String, GridFrequency>>>,
+    /// Modbus traffic counters (TCP + RTU), exported on /metrics
+    pub modbus_metrics: Arc<ModbusMetrics>,
+    /// Streaming client counters, exported on /metrics
+    pub http_metrics:   Arc<HttpMetrics>,
+    /// Alarms and events as they happen, for the WebSocket clients
+    notifications:      broadcast::Sender<Notification>,
+    /// SCADA watchdog supervision, only for plants with a watchdog timeout
+    watchdogs:          Arc<RwLock<HashMap<String, Watchdog>>>,
+    /// Inverter efficiency curve per plant; unconfigured plants use the default preset
+    efficiency_curves:  Arc<RwLock<HashMap<String, EfficiencyCurve>>>,
+    /// Seconds between two updates, only for plants not on the default interval
+    update_intervals:   Arc<RwLock<HashMap<String, f64>>>,
+    /// Seconds without an update before a plant is stale, only for plants with their own threshold
+    stale_after:        Arc<RwLock<HashMap<String, f64>>>,
+    /// Grid connection thresholds and output ramp per plant
+    startups:           Arc<RwLock<HashMap<String, Startup>>>,
+    /// Export cap at the grid meter (kW), only for plants with one
+    export_limits:      Arc<RwLock<HashMap<String, f64>>>,
+    /// Revenue meter error model, only for plants with an accuracy class
+    energy_meters:      Arc<RwLock<HashMap<String, EnergyMeter>>>,
+    /// P(f) droop and Q(U) curve per plant
+    grid_supports:      Arc<RwLock<HashMap<String, GridSupport>>>,
+    /// Grid excursions forced over the API, until they expire
+    grid_events:        Arc<RwLock<HashMap<String, GridEvent>>>,
+    /// Isolation faults forced over the API, until they expire
+    isolation_faults:   Arc<RwLock<HashMap<String, IsolationFault>>>,
+    /// Alarms forced over the API, until they expire or are withdrawn
+    injected_faults:    Arc<RwLock<HashMap<String, Vec<InjectedFault>>>>,
+    /// Interface protection settings per plant; unconfigured plants use the defaults
+    protections:        Arc<RwLock<HashMap<String, ProtectionConfig>>>,
+    /// Alarm thresholds per plant; unconfigured plants use the defaults
+    alarm_configs:      Arc<RwLock<HashMap<String, AlarmConfig>>>,
+    /// Since when each (plant, alarm code) condition has held, while its
+    /// activation delay runs
+    pending_alarms:     Arc<RwLock<HashMap<(String, u16), DateTime<Utc>>>>,
+    /// Recurring output caps, only for plants with a curtailment schedule
+    curtailment_schedules: Arc<RwLock<HashMap<String, CurtailmentSchedule>>>,
+    /// Instrument error model for every plant; None = exact readings
+    measurement_noise:  Arc<RwLock<Option<MeasurementNoise>>>,
+    /// Irradiance overrides waiting for or inside their window
+    scenarios:          Arc<RwLock<Vec<Scenario>>>,
+    /// Simulation time pinned by a test; None = the virtual clock
+    clock:              Arc<RwLock<Option<DateTime<Utc>>>>,
+    /// Virtual clock of the update cycle (`simulation.speed`)
+    sim_clock:          Arc<RwLock<SimClock>>,
+    /// 1-minute telemetry ring buffer per plant
+    histories:          Arc<RwLock<HashMap<String, History>>>,
+}
+
+/// Nameplate and grid connection data of one plant an update cycle needs
+/// besides the sample.
+#[derive(Clone, Copy, Debug)]
+pub struct PlantRating {
+    /// DC peak power (kW)
+    pub nominal_power_kw: f64,
+    /// Inverter apparent power rating (kVA)
+    pub max_kva:          f64,
+    /// Inverter AC rating (kW)
+    pub max_ac_kw:        f64,
+    /// Plant civil time (daily counters reset at local midnight)
+    pub timezone:         Tz,
+    /// Grid nominal, phase-to-neutral (V)
+    pub grid_voltage_v:   f64,
+    pub phase_imbalance:  PhaseImbalanceConfig,
+}
+
+impl PlantRating {
+    pub fn of(plant: &PlantConfig) -> Self {
+        Self {
+            nominal_power_kw: plant.nominal_power_kw,
+            max_kva:          plant.max_kva(),
+            max_ac_kw:        plant.max_ac_kw(),
+            timezone:         plant.timezone,
+            grid_voltage_v:   plant.grid.nominal_voltage_v,
+            phase_imbalance:  plant.grid.phase_imbalance,
+        }
+    }
+}
+
+/// Grid support functions of one plant's inverter.
+#[derive(Clone, Debug, Default)]
+struct GridSupport {
+    /// None = no over-frequency droop
+    freq_watt: Option<FreqWatt>,
+    /// Used while the reactive mode is `volt_var`
+    volt_var:  VoltVarCurve,
+}
+
+/// Voltage forced on one plant's grid connection.
+#[derive(Clone, Copy, Debug)]
+struct GridEvent {
+    voltage_v: f64,
+    until:     Instant,
+}
+
+/// Isolation resistance forced on one plant's array (wet string, damaged cable).
+#[derive(Clone, Copy, Debug)]
+struct IsolationFault {
+    mohm:  f64,
+    until: Instant,
+}
+
+/// Alarm forced on one plant over the API.
+#[derive(Clone, Debug)]
+struct InjectedFault {
+    code:     u16,
+    severity: AlarmSeverity,
+    until:    Instant,
+}
+
+/// Grid connection sequence of one plant's inverter.
+#[derive(Clone, Copy, Debug)]
+struct Startup {
+    start_w_m2:      f64,
+    stop_w_m2:       f64,
+    delay_cycles:    u32,
+    search_cycles:   u32,
+    /// None = the AC rating per minute
+    ramp_kw_per_min: Option<f64>,
+}
+
+impl Default for Startup {
+    fn default() -> Self {
+        Self {
+            start_w_m2:      IRRAD_START_W_M2,
+            stop_w_m2:       IRRAD_STOP_W_M2,
+            delay_cycles:    STARTUP_DELAY_CYCLES,
+            search_cycles:   MPPT_SEARCH_CYCLES,
+            ramp_kw_per_min: None,
+        }
+    }
+}
+
+/// Communication-loss supervision of one plant's SCADA watchdog register.
+#[derive(Clone, Debug)]
+struct Watchdog {
+    timeout_s:    f64,
+    /// Active power limit (%) applied while the watchdog is stale
+    fallback_pct: Option<f64>,
+    last_write:   Instant,
+}
+
+impl Watchdog {
+    fn age_s(&self) -> f64 {
+        self.last_write.elapsed().as_secs_f64()
+    }
+
+    fn expired(&self) -> bool {
+        self.age_s() >= self.timeout_s
+    }
 }
 
 impl AppState {
     pub fn new(offline_mode_default: bool) -> Self {
-        let start = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
         Self {
-            plant_data:     Arc::new(RwLock::new(HashMap::new())),
+            plant_data:     PlantStore::new(),
             offline_mode:   Arc::new(AtomicBool::new(offline_mode_default)),
+            seed:           Arc::new(AtomicU64::new(0)),
             mqtt_connected: Arc::new(AtomicBool::new(false)),
             alarms:         Arc::new(RwLock::new(Vec::new())),
             events:         Arc::new(RwLock::new(VecDeque::new())),
-            start_time:     start,
-            prev_freq:      Arc::new(RwLock::new(HashMap::new())),
+            started_at:     Utc::now(),
+            started:        Instant::now(),
+            grid_networks:  Arc::new(RwLock::new(HashMap::new())),
+            grid_frequencies: Arc::new(RwLock::new(HashMap::new())),
+            modbus_metrics: Arc::new(ModbusMetrics::default()),
+            http_metrics:   Arc::new(HttpMetrics::default()),
+            notifications:  broadcast::channel(NOTIFICATION_BUFFER).0,
+            watchdogs:      Arc::new(RwLock::new(HashMap::new())),
+            efficiency_curves: Arc::new(RwLock::new(HashMap::new())),
+            update_intervals: Arc::new(RwLock::new(HashMap::new())),
+            stale_after:    Arc::new(RwLock::new(HashMap::new())),
+            startups:       Arc::new(RwLock::new(HashMap::new())),
+            export_limits:  Arc::new(RwLock::new(HashMap::new())),
+            energy_meters:  Arc::new(RwLock::new(HashMap::new())),
+            grid_supports:  Arc::new(RwLock::new(HashMap::new())),
+            grid_events:    Arc::new(RwLock::new(HashMap::new())),
+            isolation_faults: Arc::new(RwLock::new(HashMap::new())),
+            injected_faults: Arc::new(RwLock::new(HashMap::new())),
+            protections:    Arc::new(RwLock::new(HashMap::new())),
+            alarm_configs:  Arc::new(RwLock::new(HashMap::new())),
+            pending_alarms: Arc::new(RwLock::new(HashMap::new())),
+            curtailment_schedules: Arc::new(RwLock::new(HashMap::new())),
+            measurement_noise: Arc::new(RwLock::new(None)),
+            scenarios:      Arc::new(RwLock::new(Vec::new())),
+            clock:          Arc::new(RwLock::new(None)),
+            sim_clock:      Arc::new(RwLock::new(SimClock::default())),
+            histories:      Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Time the update cycle runs at: the virtual clock unless a test pinned it.
+    pub fn now(&self) -> DateTime<Utc> {
+        self.clock.read().ok().and_then(|c| *c).unwrap_or_else(|| self.sim_clock().now())
+    }
+
+    pub fn sim_clock(&self) -> SimClock {
+        self.sim_clock.read().map(|c| *c).unwrap_or_default()
+    }
+
+    /// Run the simulation on `clock`. Open-Meteo only serves the current
+    /// weather, so a clock off the wall clock needs offline mode.
+    pub fn set_sim_clock(&self, clock: SimClock) -> Result<(), String> {
+        if !clock.is_realtime() && !self.is_offline() {
+            return Err("the simulation clock can only be accelerated or moved in offline mode: \
+                        Open-Meteo serves the current weather only".to_string());
+        }
+        // A jump in simulated time is no production and outdates the soiling
+        // the plants carry
+        let jump_s = (clock.now() - self.sim_clock().now()).num_seconds().abs();
+        if let Ok(mut c) = self.sim_clock.write() {
+            *c = clock;
+        }
+        if jump_s > MAX_ENERGY_GAP_S as i64 {
+            self.plant_data.update_all(|_, d| d.clock_jumped = true);
+        }
+        Ok(())
+    }
+
+    /// Pin the simulation clock, e.g. to walk a plant across midnight.
+    #[cfg(test)]
+    pub(crate) fn set_clock(&self, at: DateTime<Utc>) {
+        if let Ok(mut c) = self.clock.write() {
+            *c = Some(at);
         }
     }
 
@@ -109,31 +459,54 @@ impl AppState {
         self.offline_mode.load(Ordering::Relaxed)
     }
 
+    /// Switch between the offline solar model and Open-Meteo; a change of
+    /// mode is logged.
     pub fn set_offline(&self, value: bool) {
-        self.offline_mode.store(value, Ordering::Relaxed);
+        if self.offline_mode.swap(value, Ordering::Relaxed) == value {
+            return;
+        }
         self.push_event(None, EventKind::ModeChange, format!(
             "Mode changed to {}", if value { "OFFLINE" } else { "ONLINE" }
-        ), None);
+        ), Some(serde_json::json!({ "offline_mode": value })));
+    }
+
+    /// Weather scenario seed applied to plants without their own `seed`.
+    pub fn simulation_seed(&self) -> u64 {
+        self.seed.load(Ordering::Relaxed)
+    }
+
+    pub fn set_simulation_seed(&self, seed: u64) {
+        self.seed.store(seed, Ordering::Relaxed);
     }
 
+    /// Seconds since the process started, on the monotonic clock: a wall
+    /// clock step (NTP, DST-unaware hosts) neither stalls nor rewinds it.
     pub fn uptime_seconds(&self) -> u64 {
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-        now.saturating_sub(self.start_time)
+        self.started.elapsed().as_secs()
+    }
+
+    pub fn started_at(&self) -> DateTime<Utc> {
+        self.started_at
+    }
+
+    pub fn is_mqtt_connected(&self) -> bool {
+        self.mqtt_connected.load(Ordering::Relaxed)
+    }
+
+    pub fn set_mqtt_connected(&self, connected: bool) {
+        self.mqtt_connected.store(connected, Ordering::Relaxed);
     }
 
     // ── Alarm helpers ────────────────────────────────────────────────────────
 
-    fn raise_alarm(&self, plant_id: &str, code: u16, severity: AlarmSeverity, message: &str) {
+    pub(crate) fn raise_alarm(&self, plant_id: &str, code: u16, severity: AlarmSeverity, message: &str) {
         let mut alarms = match self.alarms.write() { Ok(g) => g, Err(_) => return };
         // De-duplicate: don't raise the same active alarm twice
         if alarms.iter().any(|a| a.plant_id == plant_id && a.code == code && a.active) {
             return;
         }
         let id = uuid::Uuid::new_v4().to_string();
-        alarms.push(Alarm {
+        let alarm = Alarm {
             id:         id.clone(),
             plant_id:   plant_id.to_string(),
             code,
@@ -142,41 +515,294 @@ impl AppState {
             timestamp:  chrono::Utc::now(),
             active:     true,
             cleared_at: None,
-        });
+            acknowledged:    false,
+            acknowledged_by: None,
+            acknowledged_at: None,
+            note:            None,
+        };
+        alarms.push(alarm.clone());
         // Trim history
         if alarms.len() > MAX_ALARM_HISTORY {
             alarms.remove(0);
         }
         drop(alarms);
+        self.notify(Notification::Alarm(alarm));
         self.push_event(
             Some(plant_id.to_string()),
             EventKind::AlarmRaised,
             format!("[{:?}] {} — code {}", severity, message, code),
-            None,
+            Some(serde_json::json!({ "alarm_id": id, "code": code, "severity": severity, "message": message })),
         );
     }
 
     fn clear_alarm(&self, plant_id: &str, code: u16) {
+        // An injected fault holds its alarm until it expires or is withdrawn
+        if self.fault_injected(plant_id, Some(code)) {
+            return;
+        }
         let mut alarms = match self.alarms.write() { Ok(g) => g, Err(_) => return };
-        let mut cleared = false;
+        let mut cleared = Vec::new();
         for a in alarms.iter_mut() {
             if a.plant_id == plant_id && a.code == code && a.active {
                 a.active     = false;
                 a.cleared_at = Some(chrono::Utc::now());
-                cleared      = true;
+                cleared.push(a.clone());
             }
         }
         drop(alarms);
-        if cleared {
+        if !cleared.is_empty() {
+            cleared.into_iter().for_each(|a| self.notify(Notification::Alarm(a)));
             self.push_event(
                 Some(plant_id.to_string()),
                 EventKind::AlarmCleared,
                 format!("Alarm code {} cleared", code),
-                None,
+                Some(serde_json::json!({ "code": code })),
+            );
+        }
+    }
+
+    fn alarm_active(&self, plant_id: &str, code: u16) -> bool {
+        self.alarms.read().is_ok_and(|a| a.iter().any(|a| a.plant_id == plant_id && a.code == code && a.active))
+    }
+
+    /// Debounce one threshold: the alarm is raised once `raise` has held for
+    /// the activation delay, and an active alarm clears only when `clear`
+    /// holds; in between it keeps its state. Returns whether it is active.
+    fn check_threshold(&self, plant_id: &str, at: DateTime<Utc>, check: ThresholdCheck, message: impl FnOnce() -> String) -> bool {
+        let key = (plant_id.to_string(), check.code);
+        if self.alarm_active(plant_id, check.code) {
+            if check.clear {
+                self.clear_alarm(plant_id, check.code);
+                return false;
+            }
+            return true;
+        }
+        let mut pending = match self.pending_alarms.write() { Ok(g) => g, Err(_) => return false };
+        if !check.raise {
+            pending.remove(&key);
+            return false;
+        }
+        let since = *pending.entry(key.clone()).or_insert(at);
+        if ((at - since).num_milliseconds() as f64 / 1000.0) < check.delay_s {
+            return false;
+        }
+        pending.remove(&key);
+        drop(pending);
+        self.raise_alarm(plant_id, check.code, check.severity, &message());
+        true
+    }
+
+    /// Evaluate protection thresholds: raise/clear alarms, then publish the
+    /// alarm bitmask and the highest-severity active code to the plant data.
+    fn update_alarms(&self, plant_id: &str, s: &AlarmSnapshot) {
+        let mut new_flags: u32 = 0;
+
+        // Faults injected over the API: expired ones clear (the checks below
+        // raise them again if the condition is real), live ones are raised
+        // whatever the measurements say
+        let (live, expired) = self.take_injected_faults(plant_id);
+        for code in expired {
+            self.clear_alarm(plant_id, code);
+        }
+        for fault in live {
+            new_flags |= alarm_flag_bits::of_code(fault.code);
+            let name = alarm_codes::describe(fault.code).map_or("UNKNOWN", |(name, _)| name);
+            self.raise_alarm(plant_id, fault.code, fault.severity, &format!("Injected fault: {}", name));
+        }
+
+        let limits = &s.limits;
+
+        let th = &s.thresholds;
+        let at = s.at;
+
+        // AC voltage (V< only while the inverter is awake)
+        if self.check_threshold(plant_id, at, ThresholdCheck {
+            code: alarm_codes::AC_OVERVOLTAGE, severity: AlarmSeverity::Warning, delay_s: th.voltage_delay_s,
+            raise: s.v_avg > limits.overvoltage_v,
+            clear: s.v_avg <= limits.overvoltage_v - th.voltage_hysteresis_v,
+        }, || format!("AC overvoltage: {:.1} V (limit {:.0} V)", s.v_avg, limits.overvoltage_v)) {
+            new_flags |= alarm_flag_bits::AC_OVERVOLTAGE;
+        }
+        if self.check_threshold(plant_id, at, ThresholdCheck {
+            code: alarm_codes::AC_UNDERVOLTAGE, severity: AlarmSeverity::Warning, delay_s: th.voltage_delay_s,
+            raise: s.v_avg < limits.undervoltage_v && s.is_day,
+            clear: s.v_avg >= limits.undervoltage_v + th.voltage_hysteresis_v || !s.is_day,
+        }, || format!("AC undervoltage: {:.1} V (limit {:.0} V)", s.v_avg, limits.undervoltage_v)) {
+            new_flags |= alarm_flag_bits::AC_UNDERVOLTAGE;
+        }
+
+        // Frequency — over- and under-frequency share one flag
+        let over_f = self.check_threshold(plant_id, at, ThresholdCheck {
+            code: alarm_codes::AC_OVERFREQUENCY, severity: AlarmSeverity::Warning, delay_s: th.frequency_delay_s,
+            raise: s.frequency_hz > limits.overfrequency_hz,
+            clear: s.frequency_hz <= limits.overfrequency_hz - th.frequency_hysteresis_hz,
+        }, || format!("Over-frequency: {:.3} Hz (limit {:.2} Hz)", s.frequency_hz, limits.overfrequency_hz));
+        let under_f = self.check_threshold(plant_id, at, ThresholdCheck {
+            code: alarm_codes::AC_UNDERFREQUENCY, severity: AlarmSeverity::Warning, delay_s: th.frequency_delay_s,
+            raise: s.frequency_hz < limits.underfrequency_hz,
+            clear: s.frequency_hz >= limits.underfrequency_hz + th.frequency_hysteresis_hz,
+        }, || format!("Under-frequency: {:.3} Hz (limit {:.2} Hz)", s.frequency_hz, limits.underfrequency_hz));
+        if over_f || under_f {
+            new_flags |= alarm_flag_bits::FREQUENCY_FAULT;
+        }
+
+        // Isolation fault (the Riso check latches it with its own hysteresis)
+        if self.check_threshold(plant_id, at, ThresholdCheck {
+            code: alarm_codes::ISOLATION_FAULT, severity: AlarmSeverity::Fault, delay_s: th.isolation_delay_s,
+            raise: s.isolation_fault,
+            clear: !s.isolation_fault,
+        }, || format!("Isolation resistance too low: {:.2} MΩ (limit {:.1} MΩ)", s.isolation_mohm, th.isolation_mohm)) {
+            new_flags |= alarm_flag_bits::ISOLATION_FAULT;
+        }
+
+        // Leakage current (IEC 62109 limit 300 mA — Critical; 100 mA — Warning).
+        // Only its own flag: GROUND_FAULT is left to an actual ground fault
+        if s.leakage_ma > 300.0 {
+            new_flags |= alarm_flag_bits::LEAKAGE_CURRENT;
+            self.raise_alarm(plant_id, alarm_codes::GROUND_FAULT, AlarmSeverity::Critical,
+                &format!("Leakage current critical: {:.1} mA (trip >300 mA)", s.leakage_ma));
+        } else if s.leakage_ma > 100.0 {
+            new_flags |= alarm_flag_bits::LEAKAGE_CURRENT;
+            self.raise_alarm(plant_id, alarm_codes::GROUND_FAULT, AlarmSeverity::Warning,
+                &format!("Leakage current elevated: {:.1} mA (warn >100 mA)", s.leakage_ma));
+        } else { self.clear_alarm(plant_id, alarm_codes::GROUND_FAULT); }
+
+        // Overtemperature
+        if self.check_threshold(plant_id, at, ThresholdCheck {
+            code: alarm_codes::OVERTEMPERATURE, severity: AlarmSeverity::Critical, delay_s: th.overtemperature_delay_s,
+            raise: s.inverter_temp_c > th.overtemperature_c,
+            clear: s.inverter_temp_c <= th.overtemperature_c - th.overtemperature_hysteresis_c,
+        }, || format!("Inverter overtemperature: {:.1} °C (limit {:.0} °C)", s.inverter_temp_c, th.overtemperature_c)) {
+            new_flags |= alarm_flag_bits::OVERTEMPERATURE;
+        }
+
+        // MPPT inputs drawing unequal string currents (shaded or failed strings)
+        if self.check_threshold(plant_id, at, ThresholdCheck {
+            code: alarm_codes::MPPT_FAILURE, severity: AlarmSeverity::Warning, delay_s: th.mppt_deviation_delay_s,
+            raise: s.mppt_deviation_pct > th.mppt_deviation_pct,
+            clear: s.mppt_deviation_pct <= th.mppt_deviation_pct - th.mppt_deviation_hysteresis_pct,
+        }, || format!("MPPT deviation: string current {:.0}% off the input average (limit {:.0}%)", s.mppt_deviation_pct, th.mppt_deviation_pct)) {
+            new_flags |= alarm_flag_bits::MPPT_DEVIATION;
+        }
+
+        // Fan fault (fan stopped while inverter is hot)
+        if s.fan_fault && s.inverter_temp_c > 45.0 {
+            new_flags |= alarm_flag_bits::FAN_FAULT;
+            self.raise_alarm(plant_id, alarm_codes::FAN_FAULT, AlarmSeverity::Warning,
+                &format!("Cooling fan fault: 0 RPM at {:.1} °C heatsink", s.inverter_temp_c));
+        } else {
+            // Fan running — check for under-speed (e.g. partial stall)
+            if s.ac_power > 0.1 && s.fan_rpm > 0 && s.fan_rpm < 1200 && s.inverter_temp_c > 50.0 {
+                new_flags |= alarm_flag_bits::FAN_FAULT;
+                self.raise_alarm(plant_id, alarm_codes::FAN_FAULT, AlarmSeverity::Warning,
+                    &format!("Fan under-speed: {} RPM (expected ≥1500 RPM)", s.fan_rpm));
+            } else {
+                self.clear_alarm(plant_id, alarm_codes::FAN_FAULT);
+            }
+        }
+
+        // DC overvoltage
+        if s.dc_ov {
+            new_flags |= alarm_flag_bits::DC_OVERVOLTAGE;
+            self.raise_alarm(plant_id, alarm_codes::DC_OVERVOLTAGE, AlarmSeverity::Warning,
+                &format!("DC string over-voltage: estimated V_oc > {:.0} V rated DC bus", V_DC_NOM));
+        } else { self.clear_alarm(plant_id, alarm_codes::DC_OVERVOLTAGE); }
+
+        // ROCOF — measured frequency derivative between 5-second samples
+        if s.rocof_hz_s.abs() > limits.rocof_hz_s {
+            new_flags |= alarm_flag_bits::ROCOF_TRIP;
+            self.raise_alarm(plant_id, alarm_codes::ROCOF_TRIP, AlarmSeverity::Critical,
+                &format!("RoCoF trip: {:.3} Hz/s (limit ±{:.1} Hz/s)", s.rocof_hz_s, limits.rocof_hz_s));
+        } else { self.clear_alarm(plant_id, alarm_codes::ROCOF_TRIP); }
+
+        // Interface protection tripped — inverter held off the grid until it reconnects
+        if let Some(code) = s.protection_trip {
+            new_flags |= alarm_flag_bits::GRID_DISCONNECT;
+            self.raise_alarm(plant_id, alarm_codes::GRID_ISLAND_DETECTED, AlarmSeverity::Fault,
+                &format!("Interface protection tripped (code {}): disconnected from the grid", code));
+        } else { self.clear_alarm(plant_id, alarm_codes::GRID_ISLAND_DETECTED); }
+
+        // Tracker parked flat in high wind (recovers with hysteresis in the solar model)
+        if s.tracker_stowed {
+            new_flags |= alarm_flag_bits::TRACKER_STOW;
+            self.raise_alarm(plant_id, alarm_codes::TRACKER_WIND_STOW, AlarmSeverity::Warning,
+                &format!("Tracker stowed: wind {:.1} m/s", s.wind_speed_m_s));
+        } else { self.clear_alarm(plant_id, alarm_codes::TRACKER_WIND_STOW); }
+
+        // Snow on the modules (clears by sliding / melt in the solar model)
+        if s.snow_cover > SNOW_ALARM_COVER {
+            new_flags |= alarm_flag_bits::SNOW_COVER;
+            self.raise_alarm(plant_id, alarm_codes::SNOW_COVER, AlarmSeverity::Info,
+                &format!("Snow on modules: {:.0}% of the array covered", s.snow_cover * 100.0));
+        } else { self.clear_alarm(plant_id, alarm_codes::SNOW_COVER); }
+
+        // Storm damage — held until an operator repairs the array
+        if s.damage_pct > 0.0 {
+            new_flags |= alarm_flag_bits::MODULE_DAMAGE;
+            self.raise_alarm(plant_id, alarm_codes::MODULE_DAMAGE, AlarmSeverity::Critical,
+                &format!("Module damage: {:.1}% of the array capacity lost, repair required", s.damage_pct));
+        } else { self.clear_alarm(plant_id, alarm_codes::MODULE_DAMAGE); }
+
+        // SCADA watchdog — master stopped writing the watchdog register
+        if let Some(wd) = &s.stale_watchdog {
+            new_flags |= alarm_flag_bits::COMMUNICATION_LOSS;
+            self.raise_alarm(plant_id, alarm_codes::COMMUNICATION_LOSS, AlarmSeverity::Warning,
+                &format!("SCADA watchdog expired: no write for {:.0} s (timeout {:.0} s)", wd.age_s(), wd.timeout_s));
+        } else { self.clear_alarm(plant_id, alarm_codes::COMMUNICATION_LOSS); }
+
+        let fault_code = self.highest_active_fault(plant_id);
+        self.plant_data.modify(plant_id, |d| {
+            d.fault_code  = fault_code;
+            d.alarm_flags = new_flags;
+        });
+    }
+
+    /// Hold an enabled inverter in Fault while one of its tripping alarms is
+    /// active or a fault is injected, then log the cycle's status transition, if any.
+    fn settle_status(&self, plant_id: &str, previous: u16) {
+        let tripped = self.fault_injected(plant_id, None) || self.alarms.read().is_ok_and(|alarms| alarms.iter()
+            .any(|a| a.plant_id == plant_id && a.active && TRIP_ALARMS.contains(&a.code)));
+        let status = match self.plant_data.modify(plant_id, |d| {
+            if tripped && d.inverter_enabled && !d.maintenance {
+                d.status = 2;
+            }
+            d.status
+        }) {
+            Some(status) => status,
+            None => return,
+        };
+        self.log_status_change(plant_id, previous, status);
+    }
+
+    /// Log the status transition `previous` → `status` of `plant_id`, if it is one.
+    fn log_status_change(&self, plant_id: &str, previous: u16, status: u16) {
+        if status != previous {
+            self.push_event(
+                Some(plant_id.to_string()),
+                EventKind::StatusChange,
+                format!("Inverter status {} → {}", status_label(previous), status_label(status)),
+                Some(serde_json::json!({ "from": previous, "to": status })),
             );
         }
     }
 
+    /// Code of the most severe active alarm of `plant_id` (earliest raised on
+    /// ties), or `alarm_codes::NONE` when no alarm is active.
+    fn highest_active_fault(&self, plant_id: &str) -> u16 {
+        let alarms = match self.alarms.read() { Ok(g) => g, Err(_) => return alarm_codes::NONE };
+        alarms.iter()
+            .filter(|a| a.plant_id == plant_id && a.active)
+            .rev()
+            .max_by_key(|a| a.severity.clone())
+            .map_or(alarm_codes::NONE, |a| a.code)
+    }
+
+    /// Re-derive `fault_code` after alarms were cleared outside the update loop.
+    fn refresh_fault_code(&self, plant_id: &str) {
+        let fault_code = self.highest_active_fault(plant_id);
+        self.plant_data.modify(plant_id, |d| d.fault_code = fault_code);
+    }
+
     pub fn push_event(
         &self,
         plant_id: Option<String>,
@@ -185,17 +811,31 @@ impl AppState {
         payload: Option<serde_json::Value>,
     ) {
         let mut log = match self.events.write() { Ok(g) => g, Err(_) => return };
-        log.push_front(Event {
+        let event = Event {
             id:        uuid::Uuid::new_v4().to_string(),
             plant_id,
             kind,
             message,
             timestamp: chrono::Utc::now(),
             payload,
-        });
+        };
+        log.push_front(event.clone());
         if log.len() > MAX_EVENT_LOG {
             log.pop_back();
         }
+        drop(log);
+        self.notify(Notification::Event(event));
+    }
+
+    /// Hand an alarm change or event to the streaming clients; nobody
+    /// listening is no error, and a client too slow to keep up misses it.
+    fn notify(&self, notification: Notification) {
+        let _ = self.notifications.send(notification);
+    }
+
+    /// Every alarm change and event from now on, as it happens.
+    pub fn subscribe_notifications(&self) -> broadcast::Receiver<Notification> {
+        self.notifications.subscribe()
     }
 
     pub fn get_alarms(&self, plant_id: Option<&str>) -> Vec<Alarm> {
@@ -211,536 +851,3336 @@ impl AppState {
     }
 
     pub fn get_events(&self, limit: usize) -> Vec<Event> {
+        self.find_events(None, None, limit)
+    }
+
+    /// The newest `limit` events of `kind` and about `plant_id`, newest first
+    /// (unset = any).
+    pub fn find_events(&self, kind: Option<&EventKind>, plant_id: Option<&str>, limit: usize) -> Vec<Event> {
+        let log = self.events.read().unwrap_or_else(|e| e.into_inner());
+        log.iter()
+            .filter(|e| kind.is_none_or(|k| e.kind == *k))
+            .filter(|e| plant_id.is_none_or(|id| e.plant_id.as_deref() == Some(id)))
+            .take(limit)
+            .cloned()
+            .collect()
+    }
+
+    /// Events logged after the one with id `last_id`, oldest first
+    /// (the whole log when `last_id` is None or has rotated out).
+    pub fn events_since(&self, last_id: Option<&str>) -> Vec<Event> {
         let log = self.events.read().unwrap_or_else(|e| e.into_inner());
-        log.iter().take(limit).cloned().collect()
+        let mut newer: Vec<Event> = log.iter()
+            .take_while(|e| Some(e.id.as_str()) != last_id)
+            .cloned()
+            .collect();
+        newer.reverse();
+        newer
+    }
+
+    /// Record that `operator` has seen the alarm `alarm_id`, with an optional
+    /// `note`. The alarm stays active until its condition clears. Returns the
+    /// acknowledged alarm; None when there is no such alarm.
+    pub fn acknowledge_alarm(&self, alarm_id: &str, operator: &str, note: Option<&str>) -> Option<Alarm> {
+        let alarm = {
+            let mut alarms = self.alarms.write().ok()?;
+            let alarm = alarms.iter_mut().find(|a| a.id == alarm_id)?;
+            alarm.acknowledged    = true;
+            alarm.acknowledged_by = Some(operator.to_string());
+            alarm.acknowledged_at = Some(chrono::Utc::now());
+            alarm.note            = note.map(str::to_string);
+            alarm.clone()
+        };
+        self.notify(Notification::Alarm(alarm.clone()));
+        self.push_event(
+            Some(alarm.plant_id.clone()),
+            EventKind::AlarmAcknowledged,
+            format!("Alarm code {} acknowledged by {}", alarm.code, operator),
+            Some(serde_json::json!({ "alarm_id": alarm.id, "code": alarm.code, "acknowledged_by": operator, "note": note })),
+        );
+        Some(alarm)
     }
 
     pub fn clear_plant_alarms(&self, plant_id: &str) {
         let mut alarms = match self.alarms.write() { Ok(g) => g, Err(_) => return };
+        let mut cleared = Vec::new();
         for a in alarms.iter_mut() {
             if a.plant_id == plant_id && a.active {
                 a.active     = false;
                 a.cleared_at = Some(chrono::Utc::now());
+                cleared.push(a.clone());
             }
         }
+        drop(alarms);
+        cleared.into_iter().for_each(|a| self.notify(Notification::Alarm(a)));
+        // Conditions still present are re-raised on the next update cycle
+        self.plant_data.modify(plant_id, |d| {
+            d.fault_code  = alarm_codes::NONE;
+            d.alarm_flags = 0;
+        });
     }
 
-    // ── Main data update ─────────────────────────────────────────────────────
-
-    pub fn set_data(
-        &self,
-        plant_id: &str,
-        dc_power: f64,          // raw DC power from solar algorithm (kW)
-        temperature_c: f64,     // cell temperature (°C)
-        ambient_temp_c: f64,    // ambient temperature (°C)
-        nominal_power_kw: f64,
-        weather_code: u16,
-        is_day: bool,
-        poa_irradiance_w_m2: f64,
-        cloud_factor: f64,
-        solar_elevation_deg: f64,
-        wind_speed_m_s: f64,        // NEW: surface wind (m/s)
-        relative_humidity_pct: f64, // NEW: relative humidity (%)
-        soiling_factor: f64,        // NEW: panel soiling [0.85..1.0]
-    ) {
-        // ── 0. Timestamp for epoch-based fault injection ─────────────────────
-        let now_secs = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .unwrap_or_default()
-            .as_secs();
-
-        // ── 1. Retrieve or create entry ──────────────────────────────────────
-        let mut map = match self.plant_data.write() { Ok(g) => g, Err(_) => return };
-        let data = map.entry(plant_id.to_string()).or_default();
+    /// Acknowledge the alarms behind the `flags` bits of `plant_id`: their active
+    /// alarms are cleared (one AlarmCleared event each) and the bits dropped from
+    /// `alarm_flags`. An alarm code still reported by a bit left set stays active.
+    /// Conditions still present are re-raised on the next update cycle.
+    pub fn acknowledge_alarm_flags(&self, plant_id: &str, flags: u32) {
+        let remaining = self.get_data(plant_id).map_or(0, |d| d.alarm_flags) & !flags;
+        let bits = |mask: u32| (0..u32::BITS).map(|i| 1u32 << i).filter(move |b| mask & b != 0);
+        let kept: Vec<u16> = bits(remaining).flat_map(|b| alarm_flag_bits::codes(b).iter().copied()).collect();
+        for bit in bits(flags) {
+            for code in alarm_flag_bits::codes(bit).iter().filter(|c| !kept.contains(c)) {
+                self.clear_alarm(plant_id, *code);
+            }
+        }
+        self.plant_data.modify(plant_id, |d| d.alarm_flags &= !flags);
+        self.refresh_fault_code(plant_id);
+    }
 
-        data.weather_code          = weather_code;
-        data.is_day                = is_day;
-        data.poa_irradiance_w_m2   = poa_irradiance_w_m2;
-        data.cloud_factor          = cloud_factor;
-        data.solar_elevation_deg   = solar_elevation_deg;
-        data.temperature_c         = temperature_c;
-        data.ambient_temp_c        = ambient_temp_c;
-        data.wind_speed_m_s        = wind_speed_m_s;
-        data.relative_humidity_pct = relative_humidity_pct;
-        data.soiling_factor        = soiling_factor;
+    // ── Remote control ───────────────────────────────────────────────────────
 
-        // ── 1b. Midnight daily-energy reset ──────────────────────────────────
-        // Compare current day-of-year to last reset; reset at midnight.
-        let today_doy = chrono::Utc::now().ordinal();
-        if data.last_day_reset == 0 {
-            // First run — initialise without clearing
-            data.last_day_reset = today_doy;
-        } else if data.last_day_reset != today_doy {
-            data.daily_energy_kwh   = 0.0;
-            data.daily_peak_power_kw = 0.0;
-            data.last_day_reset     = today_doy;
-        }
-
-        // ── 2. MPPT startup / shutdown ramp ──────────────────────────────────
-        // The inverter requires minimum irradiance before grid connection.
-        // Below IRRAD_STOP_W_M2: ramp factor decays → shutdown.
-        // Above IRRAD_START_W_M2: ramp factor grows → startup.
-        // Power = dc_power × ramp_factor avoids abrupt steps.
-        let ramp_target = if poa_irradiance_w_m2 >= IRRAD_START_W_M2 && is_day {
-            1.0_f64
-        } else if poa_irradiance_w_m2 < IRRAD_STOP_W_M2 {
-            0.0_f64
+    /// Set the active power limit (% of nominal) commanded over `via`.
+    /// Logs CurtailmentStart when a limit is first applied, SettingChanged
+    /// when it moves to another value and CurtailmentEnd when it is lifted
+    /// back to 100 %.
+    pub fn set_power_limit(&self, plant_id: &str, pct: f64, via: ControlInterface) {
+        let pct = pct.clamp(0.0, 100.0);
+        let previous = match self.plant_data.update(plant_id, |d| std::mem::replace(&mut d.power_limit_pct, pct)) {
+            Some(previous) => previous,
+            None => return,
+        };
+        let (kind, msg) = if pct < 100.0 && previous >= 100.0 {
+            (EventKind::CurtailmentStart, format!("Active power limited to {:.0} % of nominal via {}", pct, via.label()))
+        } else if pct >= 100.0 && previous < 100.0 {
+            (EventKind::CurtailmentEnd, format!("Active power limit lifted via {}", via.label()))
+        } else if pct != previous {
+            (EventKind::SettingChanged, format!("Active power limit changed from {:.0} to {:.0} % via {}", previous, pct, via.label()))
         } else {
-            // Hysteresis band: keep current ramp_factor
-            data.ramp_factor
+            return;
         };
-        data.ramp_factor = (data.ramp_factor + (ramp_target - data.ramp_factor) * RAMP_RATE)
-            .clamp(0.0, 1.0);
-        let ramp = data.ramp_factor;
+        self.push_event(
+            Some(plant_id.to_string()),
+            kind,
+            msg,
+            Some(serde_json::json!({ "power_limit_pct": pct, "interface": via })),
+        );
+    }
 
-        // ── 2b. DC side: dual-MPPT string simulation ─────────────────────────
-        // V_mpp tracks irradiance via temperature coefficient.
-        let irr_ratio = (poa_irradiance_w_m2 / 1000.0).clamp(0.0, 1.1);
-        data.mppt_voltage_v = V_DC_NOM * VMPP_VOC_RATIO
-            * (1.0 + V_TEMP_COEFF * (temperature_c - 25.0));
-        // DC bus: slightly above V_mpp during MPPT tracking
-        data.dc_voltage_v  = data.mppt_voltage_v * 1.05;
-
-        // Ramped DC power
-        let dc_power_ramped = dc_power * ramp;
-        data.dc_power_kw   = dc_power_ramped;
-        data.dc_current_a  = if data.dc_voltage_v > 1.0 {
-            dc_power_ramped * 1000.0 / data.dc_voltage_v
-        } else { 0.0 };
-        data.mppt_current_a = if data.mppt_voltage_v > 1.0 {
-            dc_power_ramped * 1000.0 / data.mppt_voltage_v
-        } else { 0.0 };
+    /// Select how reactive power is controlled (fixed PF 1.0, cos φ or fixed Q).
+    pub fn set_reactive_mode(&self, plant_id: &str, mode: ReactivePowerMode) {
+        self.plant_data.update(plant_id, |d| d.reactive_mode = mode);
+    }
 
-        // ── 2c. Dual-string imbalance ──────────────────────────────────────
-        // String 1 carries ~50% + imbalance; string 2 the remainder.
-        // Imbalance driven by:  shading mismatch + manufacturing spread.
-        // Pseudo-random per plant × hour (shading stays stable ≥1 h).
-        let str_epoch = now_secs / 3600;
-        let h_str = det_hash(plant_id, str_epoch.wrapping_mul(23));
-        // imbalance: ±8 % of total current (most common real-world deviation)
-        let str_imb = (h_str * 2.0 - 1.0) * 0.08;
-        let str1_frac = (0.50 + str_imb).clamp(0.30, 0.70);
-        // Strings share the same MPPT bus voltage but carry different currents
-        data.string1_voltage_v = data.mppt_voltage_v;
-        data.string1_current_a = data.mppt_current_a * str1_frac * 2.0;
-        data.string2_voltage_v = data.mppt_voltage_v;
-        data.string2_current_a = data.mppt_current_a * (1.0 - str1_frac) * 2.0;
+    /// Set the reactive power setpoint used in `fixed_q` mode (kvar, + = over-excited).
+    pub fn set_reactive_setpoint(&self, plant_id: &str, kvar: f64) {
+        self.plant_data.update(plant_id, |d| d.reactive_setpoint_kvar = kvar);
+    }
 
-        // DC overvoltage check (panel V_oc can exceed MPPT range at cold temperatures)
-        let v_oc_est = data.mppt_voltage_v / VMPP_VOC_RATIO;
-        let dc_ov = v_oc_est > V_DC_NOM * 1.10; // >10% over rated
-
-        // ── 3. Inverter efficiency curve (PV Inverter CEC model) ────────────
-        let load_factor = if nominal_power_kw > 0.0 { dc_power_ramped / nominal_power_kw } else { 0.0 };
-        let inv_eff = if load_factor < 0.01 {
-            0.0
-        } else if load_factor < 0.1 {
-            0.80 + (load_factor / 0.1) * 0.155
-        } else if load_factor < 0.5 {
-            0.955 + ((load_factor - 0.1) / 0.4) * 0.025
-        } else {
-            0.980 - ((load_factor - 0.5) / 0.5) * 0.008
-        };
-        let temp_loss = (temperature_c - 25.0).max(0.0) * 0.0004;
-        let efficiency = (inv_eff - temp_loss).clamp(0.0, 0.999);
-        data.efficiency_percent = efficiency * 100.0;
+    /// Set the cos φ setpoint used in `cos_phi` mode (0 = 1.0).
+    pub fn set_cos_phi_setpoint(&self, plant_id: &str, cos_phi: f64) {
+        self.plant_data.update(plant_id, |d| d.cos_phi_setpoint = cos_phi.clamp(0.0, 1.0));
+    }
 
-        // ── 4. AC active power from DC through inverter ──────────────────────
-        let ac_power = dc_power_ramped * efficiency;
-        data.power_kw = ac_power;
+    /// Supervise the SCADA watchdog of `plant_id`: unless the register is
+    /// written at least every `timeout_s`, the plant reports COMMUNICATION_LOSS
+    /// and limits output to `fallback_pct`. The timer starts now.
+    pub fn configure_watchdog(&self, plant_id: &str, timeout_s: u64, fallback_pct: Option<f64>) {
+        if let Ok(mut w) = self.watchdogs.write() {
+            w.insert(plant_id.to_string(), Watchdog {
+                timeout_s:    timeout_s as f64,
+                fallback_pct: fallback_pct.map(|p| p.clamp(0.0, 100.0)),
+                last_write:   Instant::now(),
+            });
+        }
+    }
 
-        // ── 5. Inverter heatsink temperature (normalized first-order thermal model)
-        // Steady-state: T_hs = T_amb + 20°C + loss_fraction × 65°C
-        // loss_fraction = p_loss / P_nom_rated  → model scales for any plant size.
-        // A 0.5% chance per 15-min epoch injects a "thermal event" (fan failure /
-        // extreme ambient) that drives the heatsink above T_OVERTEMP_C.
-        let p_loss        = dc_power_ramped - ac_power;
-        let loss_fraction = if nominal_power_kw > 0.0 { p_loss / nominal_power_kw } else { 0.0 };
-        let ot_epoch      = now_secs / 900;          // 15-minute windows
-        let h_ot          = det_hash(plant_id, ot_epoch.wrapping_mul(17));
-        let t_hs_target   = if h_ot < P_OT_FAULT && is_day {
-            // Overtemperature event: fan failure / extreme heat → 85–95 °C
-            T_OVERTEMP_C + 5.0 + (h_ot / P_OT_FAULT) * 10.0
+    /// Conversion efficiency curve of `plant_id`'s inverter.
+    pub fn configure_efficiency_curve(&self, plant_id: &str, curve: EfficiencyCurve) {
+        if let Ok(mut c) = self.efficiency_curves.write() {
+            c.insert(plant_id.to_string(), curve);
+        }
+    }
+
+    /// Grid connection sequence of `plant_id`'s inverter: it starts above
+    /// `start_w_m2` after `delay_cycles` in the Starting state, searches the
+    /// MPP for `search_cycles`, shuts down below `stop_w_m2` (clamped under
+    /// the start threshold) and raises its output by at most
+    /// `ramp_kw_per_min` (None = the AC rating per minute).
+    pub fn configure_startup(&self, plant_id: &str, start_w_m2: f64, stop_w_m2: f64, delay_cycles: u32, search_cycles: u32, ramp_kw_per_min: Option<f64>) {
+        let start_w_m2 = start_w_m2.max(0.0);
+        if let Ok(mut s) = self.startups.write() {
+            s.insert(plant_id.to_string(), Startup {
+                start_w_m2,
+                stop_w_m2:       stop_w_m2.clamp(0.0, start_w_m2),
+                delay_cycles,
+                search_cycles,
+                ramp_kw_per_min: ramp_kw_per_min.map(|r| r.max(0.0)),
+            });
+        }
+    }
+
+    /// Cap what `plant_id` feeds into the grid at `limit_kw` above the site
+    /// load (0 = zero export).
+    pub fn configure_export_limit(&self, plant_id: &str, limit_kw: f64) {
+        if let Ok(mut e) = self.export_limits.write() {
+            e.insert(plant_id.to_string(), limit_kw.max(0.0));
+        }
+    }
+
+    fn export_limit(&self, plant_id: &str) -> Option<f64> {
+        self.export_limits.read().ok()?.get(plant_id).copied()
+    }
+
+    /// Book the energy counters of `plant_id` through `meter` rather than
+    /// at the true energy.
+    pub fn configure_energy_meter(&self, plant_id: &str, meter: EnergyMeter) {
+        if let Ok(mut m) = self.energy_meters.write() {
+            m.insert(plant_id.to_string(), meter);
+        }
+    }
+
+    fn energy_meter(&self, plant_id: &str) -> Option<EnergyMeter> {
+        self.energy_meters.read().ok()?.get(plant_id).copied()
+    }
+
+    /// Grid support functions of `plant_id`'s inverter: the over-frequency
+    /// droop (None = off) and the Q(U) curve followed in `volt_var` mode.
+    pub fn configure_grid_support(&self, plant_id: &str, freq_watt: Option<FreqWatt>, volt_var: VoltVarCurve) {
+        if let Ok(mut g) = self.grid_supports.write() {
+            g.insert(plant_id.to_string(), GridSupport { freq_watt, volt_var });
+        }
+    }
+
+    fn grid_support(&self, plant_id: &str) -> GridSupport {
+        self.grid_supports.read().ok().and_then(|g| g.get(plant_id).cloned()).unwrap_or_default()
+    }
+
+    /// Interface protection thresholds, trip delays and reconnection time of
+    /// `plant_id`'s inverter.
+    pub fn configure_protection(&self, plant_id: &str, protection: ProtectionConfig) {
+        if let Ok(mut p) = self.protections.write() {
+            p.insert(plant_id.to_string(), protection);
+        }
+    }
+
+    fn protection(&self, plant_id: &str) -> ProtectionConfig {
+        self.protections.read().ok().and_then(|p| p.get(plant_id).copied()).unwrap_or_default()
+    }
+
+    /// Alarm thresholds, activation delays and hysteresis of `plant_id`.
+    pub fn configure_alarms(&self, plant_id: &str, alarms: AlarmConfig) {
+        if let Ok(mut a) = self.alarm_configs.write() {
+            a.insert(plant_id.to_string(), alarms);
+        }
+    }
+
+    fn alarm_config(&self, plant_id: &str) -> AlarmConfig {
+        self.alarm_configs.read().ok().and_then(|a| a.get(plant_id).copied()).unwrap_or_default()
+    }
+
+    /// Weekly windows during which `plant_id`'s output is capped.
+    pub fn configure_curtailment_schedule(&self, plant_id: &str, schedule: CurtailmentSchedule) {
+        if let Ok(mut c) = self.curtailment_schedules.write() {
+            c.insert(plant_id.to_string(), schedule);
+        }
+    }
+
+    fn curtailment_schedule(&self, plant_id: &str) -> Option<CurtailmentSchedule> {
+        self.curtailment_schedules.read().ok()?.get(plant_id).cloned()
+    }
+
+    /// Noise, drift and dropouts on the sensor readings of every plant;
+    /// None switches them off.
+    pub fn configure_measurement_noise(&self, noise: Option<MeasurementNoise>) {
+        if let Ok(mut n) = self.measurement_noise.write() {
+            *n = noise;
+        }
+    }
+
+    fn measurement_noise(&self) -> Option<MeasurementNoise> {
+        self.measurement_noise.read().ok().and_then(|n| *n)
+    }
+
+    // ── Irradiance scenarios ─────────────────────────────────────────────────
+
+    /// Queue `scenario`; it dims its plants once its window opens.
+    pub fn add_scenario(&self, scenario: Scenario) {
+        if let Ok(mut s) = self.scenarios.write() {
+            s.push(scenario);
+        }
+    }
+
+    /// Scenarios waiting for or inside their window.
+    pub fn scenarios(&self) -> Vec<Scenario> {
+        self.scenarios.read().map(|s| s.clone()).unwrap_or_default()
+    }
+
+    /// Drop scenario `id`, logging its end if it was running; false when
+    /// there is no such scenario.
+    pub fn cancel_scenario(&self, id: &str) -> bool {
+        let removed = match self.scenarios.write() {
+            Ok(mut s) => s.iter().position(|sc| sc.id == id).map(|i| s.remove(i)),
+            Err(_)    => None,
+        };
+        let Some(scenario) = removed else { return false };
+        if scenario.active {
+            self.push_event(
+                scenario.plant_id.clone(),
+                EventKind::ScenarioEnd,
+                format!("Scenario '{}' cancelled", scenario.name),
+                Some(serde_json::json!({ "scenario_id": scenario.id })),
+            );
+        }
+        true
+    }
+
+    /// Product of the multipliers of the scenarios covering `plant_id` at
+    /// `time` (1 when none does).
+    pub fn irradiance_factor(&self, plant_id: &str, time: chrono::DateTime<chrono::Utc>) -> f64 {
+        self.scenarios.read()
+            .map(|s| s.iter().filter(|sc| sc.applies_to(plant_id)).filter_map(|sc| sc.factor_at(time)).product())
+            .unwrap_or(1.0)
+    }
+
+    fn scenario_active(&self, plant_id: &str) -> bool {
+        self.scenarios.read().is_ok_and(|s| s.iter().any(|sc| sc.active && sc.applies_to(plant_id)))
+    }
+
+    /// Open and close the scenario windows at `time`, logging each start and
+    /// end once; finished scenarios leave the list.
+    fn advance_scenarios(&self, time: chrono::DateTime<chrono::Utc>) {
+        let mut events = Vec::new();
+        if let Ok(mut list) = self.scenarios.write() {
+            list.retain_mut(|sc| {
+                let (kind, verb) = if time >= sc.end {
+                    if !sc.active {
+                        return false;
+                    }
+                    (EventKind::ScenarioEnd, "ended")
+                } else if !sc.active && time >= sc.start {
+                    sc.active = true;
+                    (EventKind::ScenarioStart, "started")
+                } else {
+                    return true;
+                };
+                events.push((sc.plant_id.clone(), kind, format!("Scenario '{}' {}", sc.name, verb), sc.id.clone()));
+                time < sc.end
+            });
+        }
+        for (plant_id, kind, message, id) in events {
+            self.push_event(plant_id, kind, message, Some(serde_json::json!({ "scenario_id": id })));
+        }
+    }
+
+    /// Force a frequency disturbance on `plant_id`'s grid network and/or a
+    /// voltage on its connection for `duration_s`, from the next update. The
+    /// frequency is the network's, so every plant on it sees the disturbance.
+    pub fn inject_grid_event(&self, plant_id: &str, frequency: Disturbance, voltage_v: Option<f64>, duration_s: f64) {
+        let until = Instant::now() + Duration::from_secs_f64(duration_s.max(0.0));
+        if let (Some(voltage_v), Ok(mut e)) = (voltage_v, self.grid_events.write()) {
+            e.insert(plant_id.to_string(), GridEvent { voltage_v, until });
+        }
+        if !frequency.is_none() {
+            let (network, at) = (self.grid_network(plant_id), self.now());
+            if let Ok(mut g) = self.grid_frequencies.write() {
+                g.entry(network.clone())
+                    .or_insert_with(|| GridFrequency::new(&network, F_NOM))
+                    .inject(frequency, at, until);
+            }
+        }
+        let forced: Vec<String> = [
+            frequency.frequency_hz.map(|f| format!("{:.2} Hz", f)),
+            (frequency.step_hz != 0.0).then(|| format!("step {:+.2} Hz", frequency.step_hz)),
+            (frequency.rocof_hz_s != 0.0).then(|| format!("ramp {:+.2} Hz/s", frequency.rocof_hz_s)),
+            voltage_v.map(|v| format!("{:.1} V", v)),
+        ].into_iter().flatten().collect();
+        self.push_event(
+            Some(plant_id.to_string()),
+            EventKind::SettingChanged,
+            format!("Grid event injected: {} for {:.0} s", forced.join(", "), duration_s),
+            Some(serde_json::json!({
+                "frequency_hz": frequency.frequency_hz, "frequency_step_hz": frequency.step_hz,
+                "rocof_hz_s": frequency.rocof_hz_s, "voltage_v": voltage_v, "duration_s": duration_s,
+            })),
+        );
+    }
+
+    fn grid_event(&self, plant_id: &str) -> Option<GridEvent> {
+        self.grid_events.read().ok()?.get(plant_id).copied().filter(|e| e.until > Instant::now())
+    }
+
+    /// Connect `plant_id` to grid `network` of `nominal_hz`; the first plant
+    /// on a network sets its nominal frequency.
+    pub fn configure_grid(&self, plant_id: &str, network: &str, nominal_hz: f64) {
+        if let Ok(mut n) = self.grid_networks.write() {
+            n.insert(plant_id.to_string(), network.to_string());
+        }
+        if let Ok(mut g) = self.grid_frequencies.write() {
+            g.entry(network.to_string()).or_insert_with(|| GridFrequency::new(network, nominal_hz));
+        }
+    }
+
+    fn grid_network(&self, plant_id: &str) -> String {
+        self.grid_networks.read().ok()
+            .and_then(|n| n.get(plant_id).cloned())
+            .unwrap_or_else(|| DEFAULT_GRID_NETWORK.to_string())
+    }
+
+    /// Nominal frequency (Hz) of `plant_id`'s grid.
+    pub fn nominal_frequency_hz(&self, plant_id: &str) -> f64 {
+        let network = self.grid_network(plant_id);
+        self.grid_frequencies.read().ok()
+            .and_then(|g| g.get(&network).map(GridFrequency::nominal_hz))
+            .unwrap_or(F_NOM)
+    }
+
+    /// Frequency (Hz) and RoCoF (Hz/s) of `plant_id`'s grid at `at`.
+    fn grid_frequency(&self, plant_id: &str, at: DateTime<Utc>) -> (f64, f64) {
+        let (network, seed) = (self.grid_network(plant_id), self.simulation_seed());
+        match self.grid_frequencies.write() {
+            Ok(mut g) => g.entry(network.clone())
+                .or_insert_with(|| GridFrequency::new(&network, F_NOM))
+                .sample(at, seed),
+            Err(_) => (F_NOM, 0.0),
+        }
+    }
+
+    /// Random walk position of every grid network.
+    pub fn grid_walks(&self) -> HashMap<String, WalkState> {
+        self.grid_frequencies.read()
+            .map(|g| g.iter().map(|(network, f)| (network.clone(), f.walk())).collect())
+            .unwrap_or_default()
+    }
+
+    /// Resume the random walks of the grid networks from `walks`; a network
+    /// not configured yet starts at the default nominal frequency.
+    pub fn restore_grid_walks(&self, walks: HashMap<String, WalkState>) {
+        if let Ok(mut g) = self.grid_frequencies.write() {
+            for (network, walk) in walks {
+                g.entry(network.clone())
+                    .or_insert_with(|| GridFrequency::new(&network, F_NOM))
+                    .restore_walk(walk);
+            }
+        }
+    }
+
+    /// Alarm conditions still inside their activation delay: plant, code
+    /// and since when each has held.
+    pub fn pending_alarms(&self) -> Vec<(String, u16, DateTime<Utc>)> {
+        self.pending_alarms.read()
+            .map(|p| p.iter().map(|((plant, code), since)| (plant.clone(), *code, *since)).collect())
+            .unwrap_or_default()
+    }
+
+    /// Replace the alarm conditions inside their activation delay.
+    pub fn restore_pending_alarms(&self, pending: Vec<(String, u16, DateTime<Utc>)>) {
+        if let Ok(mut p) = self.pending_alarms.write() {
+            *p = pending.into_iter().map(|(plant, code, since)| ((plant, code), since)).collect();
+        }
+    }
+
+    /// Force the isolation resistance of `plant_id` to `mohm` for
+    /// `duration_s`; the Riso check reacts from the next update.
+    pub fn inject_isolation_fault(&self, plant_id: &str, mohm: f64, duration_s: f64) {
+        if let Ok(mut f) = self.isolation_faults.write() {
+            f.insert(plant_id.to_string(), IsolationFault {
+                mohm,
+                until: Instant::now() + Duration::from_secs_f64(duration_s.max(0.0)),
+            });
+        }
+        self.push_event(
+            Some(plant_id.to_string()),
+            EventKind::SettingChanged,
+            format!("Isolation fault injected: {:.2} MΩ for {:.0} s", mohm, duration_s),
+            Some(serde_json::json!({ "isolation_mohm": mohm, "duration_s": duration_s })),
+        );
+    }
+
+    /// Force alarm `code` on `plant_id` for `duration_s`: it is raised with
+    /// `severity` on the next update and holds the inverter in Fault until it
+    /// expires or is withdrawn. Injecting an active code again replaces it.
+    pub fn inject_fault(&self, plant_id: &str, code: u16, severity: AlarmSeverity, duration_s: f64) {
+        if let Ok(mut f) = self.injected_faults.write() {
+            let faults = f.entry(plant_id.to_string()).or_default();
+            faults.retain(|fault| fault.code != code);
+            faults.push(InjectedFault {
+                code,
+                severity: severity.clone(),
+                until:    Instant::now() + Duration::from_secs_f64(duration_s.max(0.0)),
+            });
+        }
+        self.push_event(
+            Some(plant_id.to_string()),
+            EventKind::SettingChanged,
+            format!("Fault {} injected for {:.0} s", code, duration_s),
+            Some(serde_json::json!({ "code": code, "severity": severity, "duration_s": duration_s })),
+        );
+    }
+
+    /// Withdraw an injected fault and clear its alarm; false when `code` is
+    /// not injected on `plant_id`.
+    pub fn withdraw_fault(&self, plant_id: &str, code: u16) -> bool {
+        let withdrawn = self.injected_faults.write().is_ok_and(|mut f| {
+            f.get_mut(plant_id).is_some_and(|faults| {
+                let before = faults.len();
+                faults.retain(|fault| fault.code != code || fault.until <= Instant::now());
+                faults.len() < before
+            })
+        });
+        if withdrawn {
+            self.clear_alarm(plant_id, code);
+            self.refresh_fault_code(plant_id);
+        }
+        withdrawn
+    }
+
+    /// Whether `code` (None = any code) is injected on `plant_id` right now.
+    fn fault_injected(&self, plant_id: &str, code: Option<u16>) -> bool {
+        let now = Instant::now();
+        self.injected_faults.read().is_ok_and(|f| f.get(plant_id).is_some_and(|faults| {
+            faults.iter().any(|fault| fault.until > now && code.is_none_or(|c| c == fault.code))
+        }))
+    }
+
+    /// Drop `plant_id`'s expired injected faults: returns the live ones and
+    /// the codes that expired.
+    fn take_injected_faults(&self, plant_id: &str) -> (Vec<InjectedFault>, Vec<u16>) {
+        let now = Instant::now();
+        let mut f = match self.injected_faults.write() { Ok(g) => g, Err(_) => return (Vec::new(), Vec::new()) };
+        let Some(faults) = f.get_mut(plant_id) else { return (Vec::new(), Vec::new()) };
+        let expired = faults.iter().filter(|fault| fault.until <= now).map(|fault| fault.code).collect();
+        faults.retain(|fault| fault.until > now);
+        (faults.clone(), expired)
+    }
+
+    /// Isolation resistance (MΩ) forced on `plant_id`, while it lasts.
+    fn isolation_fault(&self, plant_id: &str) -> Option<f64> {
+        self.isolation_faults.read().ok()?.get(plant_id).filter(|f| f.until > Instant::now()).map(|f| f.mohm)
+    }
+
+    fn startup(&self, plant_id: &str) -> Startup {
+        self.startups.read().ok().and_then(|s| s.get(plant_id).copied()).unwrap_or_default()
+    }
+
+    /// Inverter efficiency [0..1] of `plant_id` at `load` (DC power over the AC rating).
+    fn inverter_efficiency(&self, plant_id: &str, load: f64) -> f64 {
+        match self.efficiency_curves.read().ok().as_ref().and_then(|c| c.get(plant_id)) {
+            Some(curve) => curve.efficiency(load),
+            None        => EfficiencyCurve::default().efficiency(load),
+        }
+    }
+
+    /// Record a write to the SCADA watchdog register. Any write re-arms the
+    /// watchdog and immediately clears a communication-loss alarm.
+    pub fn set_watchdog(&self, plant_id: &str, value: u16) {
+        self.plant_data.update(plant_id, |data| {
+            data.watchdog_value = value;
+            data.watchdog_age_s = 0.0;
+            data.alarm_flags &= !alarm_flag_bits::COMMUNICATION_LOSS;
+        });
+        let was_expired = match self.watchdogs.write() {
+            Ok(mut w) => match w.get_mut(plant_id) {
+                Some(wd) => {
+                    let expired = wd.expired();
+                    wd.last_write = Instant::now();
+                    expired
+                }
+                None => false,
+            },
+            Err(_) => false,
+        };
+        if was_expired {
+            self.clear_alarm(plant_id, alarm_codes::COMMUNICATION_LOSS);
+            self.refresh_fault_code(plant_id);
+        }
+    }
+
+    fn watchdog(&self, plant_id: &str) -> Option<Watchdog> {
+        self.watchdogs.read().ok()?.get(plant_id).cloned()
+    }
+
+    /// Enable or stop the inverter (Modbus enable coil, or REST start/stop).
+    /// Stopping takes effect immediately; re-enabling lets the next update
+    /// cycles ramp the plant back up through the Starting state.
+    pub fn set_inverter_enabled(&self, plant_id: &str, enabled: bool, via: ControlInterface) {
+        let previous = self.plant_data.update(plant_id, |data| {
+            let previous = (std::mem::replace(&mut data.inverter_enabled, enabled), data.status);
+            if !enabled {
+                stop_output(data);
+            }
+            previous
+        });
+        let Some((previous, previous_status)) = previous else { return };
+        if !enabled {
+            self.log_status_change(plant_id, previous_status, 0);
+        }
+        if previous != enabled {
+            let (kind, msg) = if enabled {
+                (EventKind::PlantStartup, "Inverter enabled by remote command")
+            } else {
+                (EventKind::PlantShutdown, "Inverter stopped by remote command")
+            };
+            self.push_event(
+                Some(plant_id.to_string()),
+                kind,
+                format!("{} via {}", msg, via.label()),
+                Some(serde_json::json!({ "inverter_enabled": enabled, "interface": via })),
+            );
+        }
+    }
+
+    /// Operator fault reset over `via`: releases an interface-protection trip
+    /// once the grid is back within limits, without waiting out the
+    /// reconnection time, and the isolation-fault latch, which the next Riso
+    /// check re-evaluates. Injected faults hold until they expire or are
+    /// withdrawn. Returns the alarm codes released; None for an unknown plant.
+    pub fn reset_faults(&self, plant_id: &str, via: ControlInterface) -> Option<Vec<u16>> {
+        let released = self.plant_data.modify(plant_id, |data| {
+            let mut released = Vec::new();
+            let grid_ok = data.protection_cycles.iter().all(|&c| c == 0);
+            if let Some(code) = data.protection_trip.filter(|_| grid_ok) {
+                data.protection_trip  = None;
+                data.reconnect_cycles = 0;
+                released.push(code);
+            }
+            if std::mem::take(&mut data.isolation_fault) {
+                released.push(alarm_codes::ISOLATION_FAULT);
+            }
+            released
+        })?;
+        let msg = if released.is_empty() {
+            format!("Fault reset via {}: nothing latched", via.label())
         } else {
-            ambient_temp_c + 20.0 + loss_fraction.clamp(0.0, 1.0) * 65.0
+            let codes: Vec<String> = released.iter().map(u16::to_string).collect();
+            format!("Fault reset via {}: released alarm {}", via.label(), codes.join(", "))
+        };
+        self.push_event(
+            Some(plant_id.to_string()),
+            EventKind::SettingChanged,
+            msg,
+            Some(serde_json::json!({ "reset_codes": released, "interface": via })),
+        );
+        Some(released)
+    }
+
+    // ── Panel soiling ────────────────────────────────────────────────────────
+
+    /// Live soiling factor of a plant, once its first sample has been published
+    /// and until the simulation clock jumps to another date.
+    pub fn soiling_factor(&self, plant_id: &str) -> Option<f64> {
+        self.plant_data.read(plant_id, |d| (!d.clock_jumped).then_some(d.soiling_factor))?
+    }
+
+    /// Update `plant_id` every `interval_s` seconds.
+    pub fn configure_update_interval(&self, plant_id: &str, interval_s: f64) {
+        if let Ok(mut i) = self.update_intervals.write() {
+            i.insert(plant_id.to_string(), interval_s);
+        }
+    }
+
+    /// Seconds between two updates of `plant_id`.
+    pub fn update_interval_s(&self, plant_id: &str) -> f64 {
+        self.update_intervals.read().ok()
+            .and_then(|i| i.get(plant_id).copied())
+            .unwrap_or(UPDATE_INTERVAL_S)
+    }
+
+    // ── Stale data ───────────────────────────────────────────────────────────
+
+    /// Flag `plant_id` stale once `stale_after_s` pass without an update.
+    pub fn configure_stale_after(&self, plant_id: &str, stale_after_s: f64) {
+        if let Ok(mut s) = self.stale_after.write() {
+            s.insert(plant_id.to_string(), stale_after_s);
+        }
+    }
+
+    /// Seconds without an update before `plant_id` is stale: its own
+    /// threshold, or three of its update intervals.
+    pub fn stale_after_s(&self, plant_id: &str) -> f64 {
+        self.stale_after.read().ok()
+            .and_then(|s| s.get(plant_id).copied())
+            .unwrap_or_else(|| STALE_AFTER_INTERVALS * self.update_interval_s(plant_id))
+    }
+
+    /// Flag the plants whose update task has gone quiet past their staleness
+    /// threshold: `stale`, COMMUNICATION_LOSS (501) and an event. The next
+    /// update clears them. Runs apart from the update tasks it supervises.
+    pub fn check_stale_plants(&self) {
+        let thresholds: HashMap<String, f64> = self.plant_data.plant_ids().into_iter()
+            .map(|id| { let after_s = self.stale_after_s(&id); (id, after_s) })
+            .collect();
+        let mut newly_stale: Vec<(String, f64, f64)> = Vec::new();
+        self.plant_data.update_all(|id, d| {
+            let (age_s, after_s) = (d.update_age_s(), thresholds.get(id).copied().unwrap_or(f64::INFINITY));
+            if d.stale || d.updated_at.is_none() || age_s <= after_s {
+                return;
+            }
+            d.stale = true;
+            d.alarm_flags |= alarm_flag_bits::COMMUNICATION_LOSS;
+            newly_stale.push((id.to_string(), age_s, after_s));
+        });
+        for (plant_id, age_s, after_s) in newly_stale {
+            self.raise_alarm(&plant_id, alarm_codes::COMMUNICATION_LOSS, AlarmSeverity::Warning,
+                &format!("Stale data: no update for {:.0} s (threshold {:.0} s)", age_s, after_s));
+            self.refresh_fault_code(&plant_id);
+            self.push_event(
+                Some(plant_id),
+                EventKind::CommunicationLost,
+                format!("Telemetry stale: no update for {:.0} s", age_s),
+                Some(serde_json::json!({ "age_s": age_s, "stale_after_s": after_s })),
+            );
+        }
+    }
+
+    /// Advance the rolling soiling state by one update interval.
+    /// Dust settles at `rate_pct_per_day`; rain at or above `RAIN_WASH_MM_H`
+    /// washes the panels back to clean.
+    pub fn advance_soiling(&self, plant_id: &str, rate_pct_per_day: f64, rain_mm_h: f64) {
+        let cycle_s = self.update_interval_s(plant_id) * self.sim_clock().speed();
+        self.plant_data.update(plant_id, |data| {
+            data.soiling_factor = if rain_mm_h >= RAIN_WASH_MM_H {
+                1.0
+            } else {
+                let loss = rate_pct_per_day.max(0.0) / 100.0 * cycle_s / 86_400.0;
+                (data.soiling_factor - loss).max(solar_algorithm::MIN_SOILING_FACTOR)
+            };
+        });
+    }
+
+    /// Manual panel cleaning: the array is clean again from the next sample.
+    pub fn clean_panels(&self, plant_id: &str) {
+        self.plant_data.update(plant_id, |d| d.soiling_factor = 1.0);
+        self.push_event(
+            Some(plant_id.to_string()),
+            EventKind::PanelCleaning,
+            "Panels washed by manual cleaning".to_string(),
+            None,
+        );
+    }
+
+    // ── Storm damage ─────────────────────────────────────────────────────────
+
+    /// Capacity a plant has lost to storm damage (%); 0 when intact.
+    pub fn damage_pct(&self, plant_id: &str) -> f64 {
+        self.plant_data.read(plant_id, |d| d.damage_pct).unwrap_or(0.0)
+    }
+
+    /// Storm damage (hail, flying debris) takes `pct` % of what is left of the
+    /// plant's capacity until an operator repair. Returns the total loss (%).
+    pub fn damage_plant(&self, plant_id: &str, pct: f64, cause: &str) -> f64 {
+        let total = match self.plant_data.update(plant_id, |data| {
+            data.damage_pct = 100.0 - (100.0 - data.damage_pct) * (1.0 - pct.clamp(0.0, 100.0) / 100.0);
+            data.damage_pct
+        }) {
+            Some(total) => total,
+            None => return 0.0,
+        };
+        self.push_event(
+            Some(plant_id.to_string()),
+            EventKind::ModuleDamage,
+            format!("Modules damaged by {}: {:.1}% of the capacity lost", cause, total),
+            Some(serde_json::json!({ "cause": cause, "damage_pct": pct, "total_damage_pct": total })),
+        );
+        total
+    }
+
+    /// Operator repair: the full capacity is back from the next sample.
+    /// Returns the loss repaired (%); None when the plant was not damaged.
+    pub fn repair_plant(&self, plant_id: &str) -> Option<f64> {
+        let repaired = self.plant_data.modify(plant_id, |data| std::mem::take(&mut data.damage_pct))
+            .filter(|pct| *pct > 0.0)?;
+        self.push_event(
+            Some(plant_id.to_string()),
+            EventKind::ModuleRepair,
+            format!("Modules repaired: {:.1}% of the capacity restored", repaired),
+            Some(serde_json::json!({ "repaired_pct": repaired })),
+        );
+        Some(repaired)
+    }
+
+    // ── Maintenance ──────────────────────────────────────────────────────────
+
+    /// Take `plant_id` out of service for maintenance, or put it back, with
+    /// the operator's `reason` in the event log. Out of service the plant
+    /// stays Stopped with no output and raises no alarms (the active ones
+    /// clear); back in service it restarts through the Starting state.
+    /// Returns false when the plant already was in that state.
+    pub fn set_plant_enabled(&self, plant_id: &str, enabled: bool, reason: Option<&str>) -> bool {
+        let switched = self.plant_data.update(plant_id, |data| {
+            if data.maintenance != enabled {
+                return None;
+            }
+            data.maintenance = !enabled;
+            let previous_status = data.status;
+            if !enabled {
+                stop_output(data);
+            }
+            Some(previous_status)
+        });
+        let Some(Some(previous_status)) = switched else { return false };
+        if !enabled {
+            self.clear_plant_alarms(plant_id);
+            self.log_status_change(plant_id, previous_status, 0);
+        }
+        let (kind, msg) = if enabled {
+            (EventKind::MaintenanceEnd, "Plant back in service")
+        } else {
+            (EventKind::MaintenanceStart, "Plant taken out of service for maintenance")
+        };
+        let msg = match reason {
+            Some(reason) => format!("{}: {}", msg, reason),
+            None => msg.to_string(),
+        };
+        self.push_event(Some(plant_id.to_string()), kind, msg, Some(serde_json::json!({ "enabled": enabled, "reason": reason })));
+        true
+    }
+
+    /// Whether `plant_id` is in service (not in maintenance).
+    pub fn plant_enabled(&self, plant_id: &str) -> bool {
+        self.plant_data.read(plant_id, |d| !d.maintenance).unwrap_or(true)
+    }
+
+    // ── Plants changed at runtime ────────────────────────────────────────────
+
+    /// Drop the per-plant settings of `plant_id`, so a changed configuration
+    /// applies from scratch and settings it no longer has fall back to the
+    /// defaults. Telemetry, counters and history stay.
+    pub fn forget_plant_settings(&self, plant_id: &str) {
+        forget(&self.watchdogs, plant_id);
+        forget(&self.efficiency_curves, plant_id);
+        forget(&self.update_intervals, plant_id);
+        forget(&self.stale_after, plant_id);
+        forget(&self.startups, plant_id);
+        forget(&self.export_limits, plant_id);
+        forget(&self.energy_meters, plant_id);
+        forget(&self.grid_supports, plant_id);
+        forget(&self.protections, plant_id);
+        forget(&self.alarm_configs, plant_id);
+        forget(&self.curtailment_schedules, plant_id);
+        forget(&self.grid_networks, plant_id);
+    }
+
+    /// Remove `plant_id` altogether: settings, telemetry, history, forced
+    /// conditions and alarms inside their delay. Its active alarms clear;
+    /// the alarm registry and the event log keep its past.
+    pub fn remove_plant(&self, plant_id: &str) {
+        self.clear_plant_alarms(plant_id);
+        self.forget_plant_settings(plant_id);
+        forget(&self.histories, plant_id);
+        forget(&self.grid_events, plant_id);
+        forget(&self.isolation_faults, plant_id);
+        forget(&self.injected_faults, plant_id);
+        if let Ok(mut p) = self.pending_alarms.write() {
+            p.retain(|(plant, _), _| plant != plant_id);
+        }
+        self.plant_data.remove(plant_id);
+    }
+
+    // ── Main data update ─────────────────────────────────────────────────────
+
+    /// Run one update cycle of `plant_id` on a weather/DC `sample` from the
+    /// solar model or Open-Meteo, with `site_load_kw` behind the grid meter.
+    pub fn set_data(&self, plant_id: &str, rating: &PlantRating, sample: &SimulationData, site_load_kw: f64) {
+        let PlantRating { nominal_power_kw, max_kva, max_ac_kw, timezone, grid_voltage_v, phase_imbalance } = *rating;
+        let SimulationData {
+            ref mppt, temperature_c, ambient_temp_c, degradation_factor, weather_code, is_day,
+            poa_irradiance_w_m2, ghi_w_m2, dni_w_m2, dhi_w_m2, rear_irradiance_w_m2, cloud_factor,
+            solar_elevation_deg, solar_azimuth_deg, source, wind_speed_m_s, relative_humidity_pct,
+            soiling_factor, snow_cover_factor, tracker_stowed, ..
+        } = *sample;
+
+        // ── 0. Timestamp for epoch-based fault injection ─────────────────────
+        let now = self.now();
+        let now_secs = now.timestamp().max(0) as u64;
+
+        let watchdog = self.watchdog(plant_id);
+        let watchdog_expired = watchdog.as_ref().is_some_and(Watchdog::expired);
+        let grid_event = self.grid_event(plant_id);
+        let (frequency_hz, rocof_hz_s) = self.grid_frequency(plant_id, now);
+        let support = self.grid_support(plant_id);
+        let protection = self.protection(plant_id);
+        let thresholds = self.alarm_config(plant_id);
+        let scheduled_pct = self.curtailment_schedule(plant_id)
+            .and_then(|c| c.limit_pct_at(&now.with_timezone(&timezone)));
+        let noise = self.measurement_noise();
+        let meter = self.energy_meter(plant_id);
+        let interval_s = self.update_interval_s(plant_id);
+        // Simulated seconds per update cycle: more on an accelerated clock
+        let cycle_s = interval_s * self.sim_clock().speed();
+        let isolation_fault = self.isolation_fault(plant_id);
+        self.advance_scenarios(now);
+        let scenario_active = self.scenario_active(plant_id);
+
+        // ── 1. Retrieve or create entry ──────────────────────────────────────
+        // Only this plant's lock is held: the other plants update and read freely
+        let Some(slot) = self.plant_data.entry(plant_id) else { return };
+        let mut guard = match slot.write() { Ok(g) => g, Err(_) => return };
+        let data = &mut *guard;
+        data.watchdog_age_s = watchdog.as_ref().map_or(0.0, Watchdog::age_s);
+        data.last_update_unix = now_secs;
+        let first_update = data.updated_at.is_none();
+        data.updated_at = Some(Instant::now());
+        data.update_interval_s = interval_s;
+        // Time since the previous update, for the energy integrals; a stalled
+        // update loop or a jump of the simulation clock does not book its
+        // gap as production
+        let elapsed_s = data.last_update_at
+            .filter(|_| !data.clock_jumped)
+            .map_or(0.0, |t| (now - t).num_milliseconds() as f64 / 1000.0)
+            .clamp(0.0, MAX_ENERGY_GAP_S.max(2.0 * cycle_s));
+        data.clock_jumped = false;
+        data.last_update_at = Some(now);
+        let was_stale = std::mem::take(&mut data.stale);
+        // Last cycle's readings, held through a logger dropout (step 13)
+        let last_readings = Readings::of(data);
+
+        data.weather_code          = weather_code;
+        data.is_day                = is_day;
+        data.weather_source        = source;
+        data.weather_observed_unix = sample.timestamp.timestamp().max(0) as u64;
+        data.poa_irradiance_w_m2   = poa_irradiance_w_m2;
+        data.poa_irradiance_true_w_m2 = poa_irradiance_w_m2;
+        data.ghi_w_m2              = ghi_w_m2;
+        data.dni_w_m2              = dni_w_m2;
+        data.dhi_w_m2              = dhi_w_m2;
+        data.rear_irradiance_w_m2  = rear_irradiance_w_m2;
+        data.cloud_factor          = cloud_factor;
+        data.scenario_active       = scenario_active;
+        data.solar_elevation_deg   = solar_elevation_deg;
+        data.solar_azimuth_deg     = solar_azimuth_deg;
+        data.temperature_c         = temperature_c;
+        data.ambient_temp_c        = ambient_temp_c;
+        data.wind_speed_m_s        = wind_speed_m_s;
+        data.relative_humidity_pct = relative_humidity_pct;
+        data.soiling_factor        = soiling_factor;
+        data.snow_cover_factor     = snow_cover_factor;
+        data.degradation_factor    = degradation_factor;
+        data.tracker_stowed        = tracker_stowed;
+
+        // ── 1b. Energy counter rollover at local midnight ────────────────────
+        // The daily counters restart when the plant's civil date changes, the
+        // monthly one when its month does; the lifetime counter never resets.
+        let today = now.with_timezone(&timezone).date_naive();
+        let mut rollover_event = None;
+        match data.energy_day {
+            // First run — initialise without clearing
+            None => data.energy_day = Some(today),
+            Some(day) if day != today => {
+                let month_closed = (day.year(), day.month()) != (today.year(), today.month());
+                rollover_event = Some((format!(
+                    "Day {} closed: {:.1} kWh{}", day, data.daily_energy_kwh,
+                    if month_closed { format!(", month {:.1} kWh", data.monthly_energy_kwh) } else { String::new() },
+                ), serde_json::json!({
+                    "date":               day.to_string(),
+                    "daily_energy_kwh":   data.daily_energy_kwh,
+                    "peak_power_kw":      data.daily_peak_power_kw,
+                    "monthly_energy_kwh": data.monthly_energy_kwh,
+                    "month_closed":       month_closed,
+                    "total_energy_kwh":   data.total_energy_kwh,
+                    "insolation_kwh_m2":  data.daily_insolation_kwh_m2,
+                    "performance_ratio":  data.performance_ratio,
+                    "curtailed_energy_kwh": data.curtailed_energy_kwh,
+                })));
+                data.daily_energy_kwh     = 0.0;
+                data.daily_insolation_kwh_m2 = 0.0;
+                data.daily_peak_power_kw  = 0.0;
+                data.clipped_energy_kwh   = 0.0;
+                data.curtailed_energy_kwh = 0.0;
+                if month_closed {
+                    data.monthly_energy_kwh = 0.0;
+                }
+                data.energy_day = Some(today);
+            }
+            Some(_) => {}
+        }
+
+        // ── 1c. Isolation resistance (DC-GND) and Riso check ─────────────────
+        // The inverter measures the isolation while awake; below the
+        // configured limit it latches an isolation fault that clears once the
+        // resistance has recovered by the hysteresis. A fault injected over
+        // the API forces the value.
+        let irr_ratio = (poa_irradiance_w_m2 / 1000.0).clamp(0.0, 1.1);
+        data.isolation_resistance_mohm = isolation_fault.unwrap_or_else(|| {
+            isolation_resistance_mohm(irr_ratio, solar_elevation_deg, relative_humidity_pct, degradation_factor)
+        });
+        let riso = data.isolation_resistance_mohm;
+        let riso_clear = thresholds.isolation_mohm + thresholds.isolation_hysteresis_mohm;
+        data.isolation_fault = is_day && (riso < thresholds.isolation_mohm || (data.isolation_fault && riso < riso_clear));
+
+        // ── 2. Grid connection sequence and output ramp ──────────────────────
+        // Above the start threshold the inverter spends `delay_cycles` in the
+        // Starting state before it connects; it only disconnects below the
+        // lower stop threshold, so passing clouds cannot toggle it. Once
+        // connected the output rises at the ramp rate and follows the light
+        // straight down. A remotely stopped inverter or one back from
+        // maintenance restarts from 0, one tripped by the interface protection
+        // waits for the grid (step 3c) and one failing the Riso check (step 1c)
+        // waits for the array to dry.
+        let startup = self.startup(plant_id);
+        let mut day_event = None;
+        if !data.inverter_enabled || data.maintenance || data.protection_trip.is_some() {
+            data.grid_connected = false;
+            data.start_cycles   = 0;
+        } else if data.grid_connected {
+            data.grid_connected = poa_irradiance_w_m2 >= startup.stop_w_m2;
+        } else if poa_irradiance_w_m2 >= startup.start_w_m2 && is_day && !data.isolation_fault {
+            data.start_cycles += 1;
+            if data.start_cycles > startup.delay_cycles {
+                data.grid_connected = true;
+                data.start_cycles   = 0;
+                data.search_cycles  = startup.search_cycles;
+                // Startup and shutdown are logged once per day, not per cloud
+                if !data.online_today {
+                    data.online_today = true;
+                    day_event = Some((EventKind::PlantStartup, "Inverter connected to the grid at sunrise"));
+                }
+            }
+        } else {
+            data.start_cycles = 0;
+        }
+        if !is_day && !data.grid_connected && data.online_today {
+            data.online_today = false;
+            day_event = Some((EventKind::PlantShutdown, "Inverter disconnected from the grid at sunset"));
+        }
+
+        let available_kw = mppt.iter().map(DcOperatingPoint::power_kw).sum::<f64>();
+        let target_kw    = if data.grid_connected { available_kw } else { 0.0 };
+        let ramp_step_kw = startup.ramp_kw_per_min.unwrap_or(max_ac_kw) * cycle_s / 60.0;
+        data.ramped_dc_kw = target_kw.min(data.ramped_dc_kw + ramp_step_kw);
+        data.ramp_factor  = if available_kw > 0.0 { data.ramped_dc_kw / available_kw } else { 0.0 };
+        let ramp = data.ramp_factor;
+
+        // ── 2b. DC side: string operating point per MPPT ─────────────────────
+        // Each MPPT holds its strings at V_mp; while ramping it draws a share
+        // of the MPP current at the same voltage. The inverter adds up the
+        // inputs on a DC link at the highest MPPT voltage.
+        data.mppt = mppt.iter().map(|input| MpptData {
+            voltage_v: input.vmp_v,
+            current_a: input.array_current_a() * ramp,
+            power_kw:  input.power_kw() * ramp,
+        }).collect();
+        let mut dc_power_ramped = data.mppt.iter().map(|m| m.power_kw).sum::<f64>();
+        let first = data.mppt.first().copied().unwrap_or_default();
+        data.mppt_voltage_v = first.voltage_v;
+        data.mppt_current_a = first.current_a;
+        data.dc_voltage_v   = data.mppt.iter().map(|m| m.voltage_v).fold(0.0, f64::max);
+        data.dc_current_a   = if data.dc_voltage_v > 1.0 { dc_power_ramped * 1000.0 / data.dc_voltage_v } else { 0.0 };
+        data.dc_power_kw    = dc_power_ramped;
+
+        // ── 2c. Dual-string imbalance ──────────────────────────────────────
+        // String 1 carries ~50% + imbalance; string 2 the remainder.
+        // Imbalance driven by:  shading mismatch + manufacturing spread.
+        // Pseudo-random per plant × hour (shading stays stable ≥1 h).
+        let str_epoch = now_secs / 3600;
+        let h_str = det_hash(plant_id, str_epoch.wrapping_mul(23));
+        // imbalance: ±8 % of total current (most common real-world deviation)
+        let str_imb = (h_str * 2.0 - 1.0) * 0.08;
+        let str1_frac = (0.50 + str_imb).clamp(0.30, 0.70);
+        // Strings share the same MPPT bus voltage but carry different currents
+        data.string1_voltage_v = data.mppt_voltage_v;
+        data.string1_current_a = data.mppt_current_a * str1_frac * 2.0;
+        data.string2_voltage_v = data.mppt_voltage_v;
+        data.string2_current_a = data.mppt_current_a * (1.0 - str1_frac) * 2.0;
+
+        // DC overvoltage check (panel V_oc can exceed MPPT range at cold temperatures)
+        let dc_ov = mppt.iter().any(|input| input.voc_v > V_DC_NOM * 1.10); // >10% over rated
+
+        // ── 3. Inverter efficiency curve (per-plant preset or datasheet points) ─
+        let load_factor = if max_ac_kw > 0.0 { dc_power_ramped / max_ac_kw } else { 0.0 };
+        let inv_eff = self.inverter_efficiency(plant_id, load_factor);
+        let temp_loss = (temperature_c - 25.0).max(0.0) * 0.0004;
+        let efficiency = (inv_eff - temp_loss).clamp(0.0, 0.999);
+        data.efficiency_percent = efficiency * 100.0;
+
+        // ── 3b. 3-phase AC voltage & frequency ────────────────────────────
+        // Voltage: epoch-based fault injection using det_hash:
+        //  • 5-minute windows → faults last a whole epoch (realistic for grid events)
+        //  • P_VOLT_FAULT (2.5%) chance per epoch for swell or sag
+        // Normal operation stays firmly within EN 50160 limits (±4 V).
+        // A voltage injected over the API overrides it while it lasts.
+        let grid_epoch = now_secs / 300;   // 5-minute windows
+        let h_swell    = det_hash(plant_id, grid_epoch.wrapping_mul(7));
+        let h_sag      = det_hash(plant_id, grid_epoch.wrapping_mul(7) + 1);
+
+        // Epoch-level voltage drift (slow, ±4 V — within EN 50160 normal band)
+        let v_drift = (det_hash(plant_id, grid_epoch.wrapping_mul(7) + 4) * 2.0 - 1.0) * 4.0;
+        // Per-sample fine ripple (±0.4 V — measurement noise)
+        let h_rip = det_hash(plant_id, now_secs.wrapping_mul(11) ^ 0xA5A5);
+        let v_ripple = (h_rip * 2.0 - 1.0) * 0.4;
+
+        // Grid-event override: swell/sag pushes voltage well outside trip limits
+        let v_offset = if h_swell < P_VOLT_FAULT {
+            // Swell: +28..+46 V above nominal → clearly above the default V> (253 V)
+            28.0 + (h_swell / P_VOLT_FAULT) * 18.0
+        } else if h_sag < P_VOLT_FAULT {
+            // Sag: −28..−46 V below nominal → clearly below the default V< (207 V)
+            -(28.0 + (h_sag / P_VOLT_FAULT) * 18.0)
+        } else {
+            v_drift + v_ripple
+        };
+        // Excursions are sized on 230 V; a 277/480 V network sees them in proportion
+        let v_offset = v_offset * grid_voltage_v / V_GRID_REF;
+        let v_offset = grid_event.map_or(v_offset, |e| e.voltage_v - grid_voltage_v);
+
+        // Steady unbalance (`grid.phase_imbalance`), reshuffled every grid epoch:
+        // the phases spread about the three-phase average, which stays put.
+        let v_mean = grid_voltage_v + v_offset;
+        let [dv1, dv2, dv3] = phase_spread(plant_id, grid_epoch ^ 0xCCCC, phase_imbalance.voltage_pct);
+        data.voltage_l1_v = v_mean * (1.0 + dv1);
+        data.voltage_l2_v = v_mean * (1.0 + dv2);
+        data.voltage_l3_v = v_mean * (1.0 + dv3);
+
+        // Frequency and RoCoF are the grid network's (services::grid_frequency):
+        // every plant on it sees the same walk, excursions and injected events
+        data.frequency_hz = frequency_hz;
+        data.rocof_hz_s   = rocof_hz_s;
+
+        // ── 3c. Interface protection (V<, V>, f<, f>, RoCoF) ─────────────────
+        // Each function counts the consecutive samples outside its limit and
+        // trips a connected inverter once the violation has lasted its delay;
+        // a sample only proves the condition from its own timestamp on, so the
+        // first one rides through unless the delay is 0. A tripped inverter
+        // stays off until the grid has been within every limit for the
+        // reconnection time, then restarts through the normal sequence.
+        let v_avg = (data.voltage_l1_v + data.voltage_l2_v + data.voltage_l3_v) / 3.0;
+        let functions = [
+            (v_avg < protection.undervoltage_v,                 protection.undervoltage_trip_s,   alarm_codes::AC_UNDERVOLTAGE),
+            (v_avg > protection.overvoltage_v,                  protection.overvoltage_trip_s,    alarm_codes::AC_OVERVOLTAGE),
+            (data.frequency_hz < protection.underfrequency_hz,  protection.underfrequency_trip_s, alarm_codes::AC_UNDERFREQUENCY),
+            (data.frequency_hz > protection.overfrequency_hz,   protection.overfrequency_trip_s,  alarm_codes::AC_OVERFREQUENCY),
+            (data.rocof_hz_s.abs() > protection.rocof_hz_s,     protection.rocof_trip_s,          alarm_codes::ROCOF_TRIP),
+        ];
+        let mut trip = None;
+        for ((violated, delay_s, code), cycles) in functions.into_iter().zip(data.protection_cycles.iter_mut()) {
+            *cycles = if violated { *cycles + 1 } else { 0 };
+            let elapsed_s = (*cycles as f64 - 1.0) * cycle_s;
+            if violated && trip.is_none() && elapsed_s >= delay_s {
+                trip = Some(code);
+            }
+        }
+        let grid_ok = data.protection_cycles.iter().all(|&c| c == 0);
+        let mut protection_event = None;
+        if let Some(code) = data.protection_trip {
+            data.reconnect_cycles = if grid_ok { data.reconnect_cycles + 1 } else { 0 };
+            if data.reconnect_cycles as f64 * cycle_s >= protection.reconnect_delay_s {
+                data.protection_trip  = None;
+                data.reconnect_cycles = 0;
+                protection_event = Some((EventKind::GridReconnect, format!(
+                    "Grid within limits for {:.0} s: interface protection reset (code {})", protection.reconnect_delay_s, code,
+                ), serde_json::json!({ "code": code })));
+            }
+        } else if let Some(code) = trip.filter(|_| data.grid_connected) {
+            data.protection_trip  = Some(code);
+            data.reconnect_cycles = 0;
+            data.grid_connected   = false;
+            data.freq_watt_ref_kw = None;
+            protection_event = Some((EventKind::GridDisconnect, format!(
+                "Interface protection trip (code {}): {:.1} V, {:.3} Hz, {:.3} Hz/s", code, v_avg, data.frequency_hz, data.rocof_hz_s,
+            ), serde_json::json!({
+                "code":         code,
+                "voltage_v":    v_avg,
+                "frequency_hz": data.frequency_hz,
+                "rocof_hz_s":   data.rocof_hz_s,
+            })));
+        }
+
+        // ── 4. AC active power from DC through inverter ──────────────────────
+        // A SCADA power limit caps the AC output; the inverter then moves off
+        // the MPP, so the DC-side power and currents drop by the same factor.
+        // A stale SCADA watchdog tightens the limit to its fallback value, and
+        // a scheduled window to its own; the lowest limit wins.
+        let limit_pct = match watchdog.as_ref().and_then(|w| w.fallback_pct) {
+            Some(fallback) if watchdog_expired => data.power_limit_pct.min(fallback),
+            _                                  => data.power_limit_pct,
+        };
+        let limit_pct = scheduled_pct.map_or(limit_pct, |pct| limit_pct.min(pct));
+        let schedule_event = match (data.scheduled_limit_pct, scheduled_pct) {
+            (None, Some(pct)) => Some((EventKind::CurtailmentStart, format!(
+                "Scheduled curtailment: active power limited to {:.0} % of nominal", pct,
+            ), pct)),
+            (Some(_), None) => Some((EventKind::CurtailmentEnd, "Scheduled curtailment window closed".to_string(), 100.0)),
+            _ => None,
+        };
+        data.scheduled_limit_pct = scheduled_pct;
+        // An oversized DC array clips at the AC rating.
+        // An export cap holds generation to the site load plus the cap.
+        // The kVA rating then derates it further to leave room for the commanded Q.
+        let limit_kw     = nominal_power_kw * limit_pct / 100.0;
+        let unlimited_ac = dc_power_ramped * efficiency;
+        let clipping     = unlimited_ac > max_ac_kw;
+        let available_ac = unlimited_ac.min(limit_kw).min(max_ac_kw);
+        // LVRT: riding through a sag the inverter is held at its current
+        // limit, so the deliverable power falls with the voltage.
+        let available_ac = if v_avg < protection.undervoltage_v {
+            available_ac.min(max_kva * (v_avg / grid_voltage_v).max(0.0))
+        } else {
+            available_ac
+        };
+        let export_cap   = self.export_limit(plant_id).map_or(f64::INFINITY, |cap| site_load_kw.max(0.0) + cap);
+
+        // P(f): above the threshold the output follows the droop down from
+        // its level at the crossing and is released once the frequency is back.
+        let mut droop_event = None;
+        let available_ac = match support.freq_watt {
+            Some(droop) if data.grid_connected && data.frequency_hz > droop.threshold_hz => {
+                if data.freq_watt_ref_kw.is_none() {
+                    droop_event = Some((EventKind::CurtailmentStart, format!(
+                        "Over-frequency {:.2} Hz: active power follows the {:.0} % droop", data.frequency_hz, droop.droop_pct,
+                    )));
+                }
+                let reference_kw = *data.freq_watt_ref_kw.get_or_insert(available_ac);
+                available_ac.min(droop.limit_kw(data.frequency_hz, reference_kw))
+            }
+            _ => {
+                if data.freq_watt_ref_kw.take().is_some() {
+                    droop_event = Some((EventKind::CurtailmentEnd, "Frequency back to normal: droop released".to_string()));
+                }
+                available_ac
+            }
+        };
+        // Q(U): the commanded Q comes from the grid voltage
+        let q_setpoint_kvar = match data.reactive_mode {
+            ReactivePowerMode::VoltVar => support.volt_var.q_pct(v_avg / grid_voltage_v) / 100.0 * max_kva,
+            _                          => data.reactive_setpoint_kvar,
+        };
+        let (ac_target, reactive_kvar) = dispatch_reactive(
+            data.reactive_mode, data.cos_phi_setpoint, q_setpoint_kvar,
+            available_ac.min(export_cap), max_kva,
+        );
+        let curtailed    = unlimited_ac > ac_target;
+        let curtail_k    = if curtailed && unlimited_ac > 0.0 { ac_target / unlimited_ac } else { 1.0 };
+        if curtailed {
+            dc_power_ramped        *= curtail_k;
+            data.dc_power_kw       *= curtail_k;
+            data.dc_current_a      *= curtail_k;
+            data.mppt_current_a    *= curtail_k;
+            data.string1_current_a *= curtail_k;
+            data.string2_current_a *= curtail_k;
+            for input in &mut data.mppt {
+                input.current_a *= curtail_k;
+                input.power_kw  *= curtail_k;
+            }
+        }
+        let ac_power = unlimited_ac * curtail_k;
+        data.power_kw = ac_power;
+        data.clipping = clipping;
+        if clipping {
+            data.clipped_energy_kwh += (unlimited_ac - max_ac_kw) * (elapsed_s / 3600.0);
+        }
+        if available_ac > export_cap {
+            data.curtailed_energy_kwh += (available_ac - export_cap) * (elapsed_s / 3600.0);
+        }
+
+        // ── 5. Inverter heatsink temperature (normalized first-order thermal model)
+        // Steady-state: T_hs = T_amb + 20°C + loss_fraction × 65°C
+        // loss_fraction = p_loss / P_nom_rated  → model scales for any plant size.
+        // A 0.5% chance per 15-min epoch injects a "thermal event" (fan failure /
+        // extreme ambient) that drives the heatsink above T_OVERTEMP_C.
+        let p_loss        = dc_power_ramped - ac_power;
+        let loss_fraction = if nominal_power_kw > 0.0 { p_loss / nominal_power_kw } else { 0.0 };
+        let ot_epoch      = now_secs / 900;          // 15-minute windows
+        let h_ot          = det_hash(plant_id, ot_epoch.wrapping_mul(17));
+        let t_hs_target   = if h_ot < P_OT_FAULT && is_day {
+            // Overtemperature event: fan failure / extreme heat → 85–95 °C
+            T_OVERTEMP_C + 5.0 + (h_ot / P_OT_FAULT) * 10.0
+        } else {
+            ambient_temp_c + 20.0 + loss_fraction.clamp(0.0, 1.0) * 65.0
+        };
+        // First-order thermal filter τ ≈ 5 samples (25 s) — heatsink thermal mass
+        data.inverter_temp_c = data.inverter_temp_c
+            + (t_hs_target - data.inverter_temp_c) * 0.2;
+
+        // ── 6. Power factor, apparent, reactive ──────────────────────────────
+        // Q follows the commanded mode or the Q(U) curve (see step 4). No reactive support while
+        // the inverter is not producing.
+        data.reactive_power_kvar = if ac_power > 0.01 { reactive_kvar } else { 0.0 };
+        data.apparent_power_kva  = ac_power.hypot(data.reactive_power_kvar);
+        data.power_factor        = if data.apparent_power_kva > 0.0 { ac_power / data.apparent_power_kva } else { 1.0 };
+
+        // ── 6b. AC Total Harmonic Distortion (THD) ────────────────────────────
+        // IEC 61727: THD < 5 % at rated power.
+        // Pattern: high THD at very low load (>12%), decreases to ~1.8% at rated,
+        // rises slightly above rated. Real IGBT inverters follow this profile.
+        let thd_at_load = if load_factor < 0.02 {
+            0.0 // no output → undefined, report 0
+        } else if load_factor < 0.10 {
+            12.0 - (load_factor / 0.10) * 7.5   // 12% down to 4.5% at 10% load
+        } else if load_factor < 0.50 {
+            4.5 - ((load_factor - 0.10) / 0.40) * 2.7  // 4.5% → 1.8% at 50% load
+        } else {
+            1.8 + ((load_factor - 0.50) / 0.50) * 0.5  // slight rise above 50%
+        };
+        // Fast per-cycle noise (±0.2 %) from switching ripple
+        let h_thd = det_hash(plant_id, now_secs.wrapping_mul(31) ^ 0x55AA);
+        data.ac_thd_percent = (thd_at_load + (h_thd * 2.0 - 1.0) * 0.2).max(0.0);
+
+        // ── 6c. DC injection into AC grid ──────────────────────────────────
+        // IEEE 1547 / IEC 61727: limit 0.5% of rated AC current.
+        // Model: 0.05–0.5 % of I_rated depending on load and high-frequency noise;
+        //        epoch-based to keep it stable within one cycle.
+        let dc_inj_epoch = now_secs / 60; // 1-minute windows
+        let h_dc_inj = det_hash(plant_id, dc_inj_epoch.wrapping_mul(19));
+        let i_rated_a = if grid_voltage_v > 0.0 { nominal_power_kw * 1000.0 / (3.0 * grid_voltage_v) } else { 0.0 };
+        data.dc_injection_ma = if ac_power > 0.01 {
+            i_rated_a * (0.05 + h_dc_inj * 0.45) / 100.0 * 1000.0 // 0.05–0.5 % in mA
+        } else { 0.0 };
+
+        // ── 7. Phase currents (unbalanced 3-phase split) ─────────────────────
+        // Each phase carries its share of S with the current spread of
+        // `grid.phase_imbalance`; the shares are scaled so that Σ V·I = S and,
+        // with one power factor for the inverter, Σ V·I·PF = P.
+        let [di1, di2, di3] = phase_spread(plant_id, grid_epoch ^ 0xBEEF, phase_imbalance.current_pct);
+        let weighted_v = data.voltage_l1_v * (1.0 + di1)
+                       + data.voltage_l2_v * (1.0 + di2)
+                       + data.voltage_l3_v * (1.0 + di3);
+        let i_unit = if weighted_v > 0.0 { data.apparent_power_kva * 1000.0 / weighted_v } else { 0.0 };
+        data.current_l1_a = i_unit * (1.0 + di1);
+        data.current_l2_a = i_unit * (1.0 + di2);
+        data.current_l3_a = i_unit * (1.0 + di3);
+
+        // ── 8. Leakage (residual) current to ground (mA) ─────────────────────
+        // Model: IEC 62109 — normal < 50 mA; concern zone 50–300 mA; trip > 300 mA.
+        // Higher with humidity (moisture on panel frames / cabling).
+        // More leakage when isolation resistance is low.
+        let leak_humidity_factor = 1.0 + (relative_humidity_pct - 50.0).max(0.0) * 0.012;
+        // Base leakage ∝ (1 / isolation) scaled to realistic range
+        let leak_base = (1.0 / data.isolation_resistance_mohm.max(0.01)) * 2.5 * leak_humidity_factor;
+        let h_leak = det_hash(plant_id, now_secs.wrapping_mul(43) ^ 0x1234);
+        data.leakage_current_ma = (leak_base + h_leak * 0.5).clamp(0.05, 350.0);
+
+        // ── 8b. Inverter cooling fan model ────────────────────────────────────
+        // Real inverters: fan off below 40°C heatsink, variable 1500–3600 RPM above.
+        // Fan fault: injected with P_FAN_FAULT probability per 4-hour epoch.
+        const P_FAN_FAULT: f64 = 0.008; // ~1 event per 500 h per plant
+        let fan_epoch = now_secs / 14400; // 4-hour windows
+        let h_fan = det_hash(plant_id, fan_epoch.wrapping_mul(29));
+        let fan_fail = h_fan < P_FAN_FAULT && is_day;
+        data.fan_fault_active = fan_fail;
+
+        let fan_rpm = if data.inverter_temp_c < 40.0 {
+            0u16
+        } else if fan_fail {
+            // Fan fault: rotor stall → 0 RPM (no cooling → temperature runaway)
+            0
+        } else {
+            // Linear 1500 – 3600 RPM across 40–80°C
+            let frac = ((data.inverter_temp_c - 40.0) / 40.0).clamp(0.0, 1.0);
+            (1500.0 + frac * 2100.0) as u16
+        };
+        data.inverter_fan_speed_rpm = fan_rpm;
+
+        // ── 9. Status determination ─────────────────────────────────────────
+        let previous_status = data.status;
+        let has_fault = data.protection_trip.is_some()
+            || data.isolation_fault
+            || data.inverter_temp_c > T_OVERTEMP_C
+            || (data.fan_fault_active && data.inverter_temp_c > T_OVERTEMP_C - 5.0)
+            || dc_ov;
+
+        data.site_load_kw = site_load_kw;
+        data.grid_export_kw = data.power_kw - site_load_kw;
+        if !data.inverter_enabled || data.maintenance || data.protection_trip.is_some() {
+            stop_output(data);
+        }
+
+        // Stopped → Starting (self-test) → MPPT search → Running, with
+        // Curtailed while a limit holds the output down. Fault also follows
+        // the tripping alarms once they are evaluated (step 10).
+        if !data.grid_connected {
+            data.search_cycles = 0;
+        }
+        let searching = data.search_cycles > 0;
+        data.search_cycles = data.search_cycles.saturating_sub(1);
+        data.status = if !data.inverter_enabled || data.maintenance {
+            0  // Stopped by remote command or for maintenance
+        } else if has_fault {
+            2  // Fault
+        } else if curtailed {
+            3  // Curtailed by active power limit, AC rating (clipping), export cap or kVA rating
+        } else if data.start_cycles > 0 {
+            4  // Starting (grid connection sequence)
+        } else if searching {
+            5  // MPPT search after connecting
+        } else if data.grid_connected && ac_power > 0.001 {
+            1  // Running
+        } else {
+            0  // Stopped (night or below the stop threshold)
+        };
+
+        // ── 10. Alarm / fault code logic ────────────────────────────────────
+        // Snapshot fields needed for alarm logic (before releasing write lock)
+        let snapshot = AlarmSnapshot {
+            at:              now,
+            limits:          protection,
+            thresholds,
+            protection_trip: data.protection_trip,
+            v_avg,
+            is_day,
+            ac_power,
+            dc_ov,
+            frequency_hz:    data.frequency_hz,
+            isolation_fault: data.isolation_fault,
+            isolation_mohm:  data.isolation_resistance_mohm,
+            inverter_temp_c: data.inverter_temp_c,
+            mppt_deviation_pct: if data.grid_connected { mppt_deviation_pct(mppt) } else { 0.0 },
+            rocof_hz_s:      data.rocof_hz_s,
+            leakage_ma:      data.leakage_current_ma,
+            fan_fault:       data.fan_fault_active,
+            fan_rpm:         data.inverter_fan_speed_rpm,
+            tracker_stowed:  data.tracker_stowed,
+            wind_speed_m_s:  data.wind_speed_m_s,
+            snow_cover:      data.snow_cover_factor,
+            damage_pct:      data.damage_pct,
+            stale_watchdog:  watchdog.filter(|_| watchdog_expired),
+        };
+
+        let maintenance = data.maintenance;
+        drop(guard); // release write lock before calling alarm helpers
+        if first_update {
+            self.push_event(Some(plant_id.to_string()), EventKind::PlantOnline,
+                "First telemetry update".to_string(),
+                Some(serde_json::json!({ "offline_mode": self.is_offline(), "update_interval_s": interval_s })));
+        }
+        if was_stale {
+            // The SCADA watchdog may hold the same alarm for its own reason
+            if !watchdog_expired {
+                self.clear_alarm(plant_id, alarm_codes::COMMUNICATION_LOSS);
+                self.refresh_fault_code(plant_id);
+            }
+            self.push_event(Some(plant_id.to_string()), EventKind::CommunicationRestored,
+                "Telemetry updates resumed".to_string(), None);
+        }
+        // A plant out of service raises no alarms
+        if !maintenance {
+            self.update_alarms(plant_id, &snapshot);
+        }
+        self.settle_status(plant_id, previous_status);
+        if let Some((kind, msg)) = day_event {
+            self.push_event(Some(plant_id.to_string()), kind, msg.to_string(), None);
+        }
+        if let Some((kind, msg)) = droop_event {
+            self.push_event(Some(plant_id.to_string()), kind, msg, None);
+        }
+        if let Some((kind, msg, pct)) = schedule_event {
+            self.push_event(Some(plant_id.to_string()), kind, msg, Some(serde_json::json!({ "scheduled_limit_pct": pct })));
+        }
+        if let Some((kind, msg, payload)) = protection_event {
+            self.push_event(Some(plant_id.to_string()), kind, msg, Some(payload));
+        }
+        if let Some((msg, payload)) = rollover_event {
+            self.push_event(Some(plant_id.to_string()), EventKind::EnergyRollover, msg, Some(payload));
+        }
+
+        if let Ok(mut guard) = slot.write() {
+            let d = &mut *guard;
+            // ── 11. Energy accounting ────────────────────────────────────────
+            // Every counter books the sample as it comes, so the monthly and
+            // lifetime totals always include today. A plant with a revenue
+            // meter books what the meter reads; the history keeps the true
+            // energy next to it.
+            let kwh_per_sample = d.power_kw * (elapsed_s / 3600.0);
+            let metered_kwh = match meter {
+                Some(meter) if max_ac_kw > 0.0 => {
+                    meter.metered_kwh(plant_id, self.simulation_seed(), kwh_per_sample, d.power_kw / max_ac_kw)
+                }
+                _ => kwh_per_sample,
+            };
+            d.daily_energy_kwh   += metered_kwh;
+            d.monthly_energy_kwh += metered_kwh;
+            d.total_energy_kwh   += metered_kwh;
+
+            // CO₂ avoided: ENTSO-E European grid average ≈ 0.233 kg CO₂/kWh
+            d.co2_avoided_kg += kwh_per_sample * 0.233;
+
+            // Today's peak AC power
+            if d.power_kw > d.daily_peak_power_kw {
+                d.daily_peak_power_kw = d.power_kw;
+            }
+
+            // POA insolation over the same interval, the reference for the PR
+            d.daily_insolation_kwh_m2 += d.poa_irradiance_w_m2 / 1000.0 * (elapsed_s / 3600.0);
+
+            // ── 12. Performance KPIs ─────────────────────────────────────────
+            // All three are running values of the day and restart with the
+            // daily counters. PR (IEC 61724) = final yield / reference yield:
+            // kWh/kWp over the insolation in kWh/m² at the 1 kW/m² STC
+            // irradiance, so ageing, soiling and damage all show up in it.
+            let ref_yield_kwh = nominal_power_kw * d.daily_insolation_kwh_m2;
+            d.performance_ratio = if ref_yield_kwh > 0.1 {
+                (d.daily_energy_kwh / ref_yield_kwh).clamp(0.0, 1.0)
+            } else { 0.0 };
+
+            d.specific_yield_kwh_kwp = if nominal_power_kw > 0.0 {
+                d.daily_energy_kwh / nominal_power_kw
+            } else { 0.0 };
+
+            let hours_today = now.with_timezone(&timezone).num_seconds_from_midnight() as f64 / 3600.0;
+            d.capacity_factor_percent = if nominal_power_kw > 0.0 && hours_today > 0.0 {
+                (d.daily_energy_kwh / (nominal_power_kw * hours_today) * 100.0).clamp(0.0, 100.0)
+            } else { 0.0 };
+
+            // ── 13. Measurement layer ────────────────────────────────────────
+            // The simulation and its KPIs run on the true values; what the
+            // sensors report picks up noise and calibration drift, and a
+            // logger dropout holds the previous readings for a few cycles.
+            if let Some(noise) = noise {
+                let seed = self.simulation_seed();
+                let readings = if d.dropout_cycles > 0 {
+                    d.dropout_cycles -= 1;
+                    last_readings
+                } else {
+                    match noise.dropout_cycles(plant_id, seed, now_secs) {
+                        0 => noise.measure(plant_id, seed, now_secs, now_secs.saturating_sub(self.started_at.timestamp().max(0) as u64) as f64, Readings::of(d)),
+                        n => {
+                            d.dropout_cycles = n - 1;
+                            last_readings
+                        }
+                    }
+                };
+                readings.write_to(d);
+            }
+
+            // ── 14. Telemetry history ────────────────────────────────────────
+            if let Ok(mut histories) = self.histories.write() {
+                histories.entry(plant_id.to_string())
+                    .or_insert_with(|| History::new(DEFAULT_HISTORY_H))
+                    .record(now, d, kwh_per_sample);
+            }
+
+            #[cfg(feature = "verbose_log")]
+            println!(
+                "[UPDATE] {} | AC {:.2} kW | DC {:.2} kW | eff {:.1}% | L1 {:.1}V | T_inv {:.1}°C | PR {:.2} | flags 0x{:04X}",
+                plant_id, d.power_kw, d.dc_power_kw, d.efficiency_percent,
+                d.voltage_l1_v, d.inverter_temp_c, d.performance_ratio, d.alarm_flags
+            );
+        }
+    }
+
+    /// Hours of telemetry history kept for `plant_id` (capped at a week); a
+    /// plant reconfigured at runtime keeps the history it has.
+    pub fn configure_history(&self, plant_id: &str, retention_h: f64) {
+        if let Ok(mut h) = self.histories.write() {
+            h.entry(plant_id.to_string()).or_insert_with(|| History::new(retention_h));
+        }
+    }
+
+    /// History points of `plant_id` starting within `[from, to)`; empty
+    /// before its first update.
+    pub fn get_history(&self, plant_id: &str, from: DateTime<Utc>, to: DateTime<Utc>, resolution: Resolution) -> Vec<HistoryPoint> {
+        self.histories.read().ok()
+            .and_then(|h| h.get(plant_id).map(|h| h.points(from, to, resolution)))
+            .unwrap_or_default()
+    }
+
+    /// How far back the history of `plant_id` reaches once its buffer is full.
+    pub fn history_retention(&self, plant_id: &str) -> chrono::Duration {
+        self.histories.read().ok()
+            .and_then(|h| h.get(plant_id).map(History::retention))
+            .unwrap_or_else(|| chrono::Duration::minutes((DEFAULT_HISTORY_H * 60.0) as i64))
+    }
+
+    pub fn get_data(&self, plant_id: &str) -> Option<PlantData> {
+        self.plant_data.get(plant_id)
+    }
+
+    /// Copy of every plant's telemetry; `map_all_data` copies only what a
+    /// caller needs.
+    pub fn get_all_data(&self) -> HashMap<String, PlantData> {
+        self.plant_data.snapshot()
+    }
+
+    /// `f` of every plant's telemetry, each plant read under its own lock as
+    /// the iterator advances.
+    pub fn map_all_data<R>(&self, f: impl FnMut(&PlantData) -> R) -> impl Iterator<Item = (String, R)> {
+        self.plant_data.map(f)
+    }
+
+    /// Energy, power and plant counts summed over the fleet.
+    pub fn fleet_totals(&self) -> FleetTotals {
+        self.map_all_data(FleetTotals::of).fold(FleetTotals::default(), |sum, (_, plant)| sum + plant)
+    }
+}
+
+// ─── Fleet totals ────────────────────────────────────────────────────────────
+/// Sums over the plants' telemetry for the fleet summaries (HTTP and MQTT).
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct FleetTotals {
+    pub power_kw:           f64,
+    pub daily_energy_kwh:   f64,
+    pub monthly_energy_kwh: f64,
+    pub total_energy_kwh:   f64,
+    performance_ratio_sum:  f64,
+    pub plants:             usize,
+    /// In service and producing (Running or MPPT)
+    pub running:            usize,
+    pub in_maintenance:     usize,
+}
+
+impl FleetTotals {
+    /// Totals of a fleet of one.
+    pub fn of(d: &PlantData) -> Self {
+        Self {
+            power_kw:              d.power_kw,
+            daily_energy_kwh:      d.daily_energy_kwh,
+            monthly_energy_kwh:    d.monthly_energy_kwh,
+            total_energy_kwh:      d.total_energy_kwh,
+            performance_ratio_sum: d.performance_ratio,
+            plants:                1,
+            running:               usize::from(!d.maintenance && (d.status == 1 || d.status == 5)),
+            in_maintenance:        usize::from(d.maintenance),
+        }
+    }
+
+    /// Mean performance ratio of the plants; 0 without plants.
+    pub fn mean_performance_ratio(&self) -> f64 {
+        if self.plants > 0 { self.performance_ratio_sum / self.plants as f64 } else { 0.0 }
+    }
+}
+
+impl std::ops::Add for FleetTotals {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            power_kw:              self.power_kw + other.power_kw,
+            daily_energy_kwh:      self.daily_energy_kwh + other.daily_energy_kwh,
+            monthly_energy_kwh:    self.monthly_energy_kwh + other.monthly_energy_kwh,
+            total_energy_kwh:      self.total_energy_kwh + other.total_energy_kwh,
+            performance_ratio_sum: self.performance_ratio_sum + other.performance_ratio_sum,
+            plants:                self.plants + other.plants,
+            running:               self.running + other.running,
+            in_maintenance:        self.in_maintenance + other.in_maintenance,
+        }
+    }
+}
+
+// ─── Modbus server metrics ───────────────────────────────────────────────────
+/// Counters shared between the Modbus server tasks and the /metrics handler.
+#[derive(Debug, Default)]
+pub struct ModbusMetrics {
+    /// Requests by function label (read_input, write_single_coil, …)
+    requests:          Mutex<BTreeMap<&'static str, u64>>,
+    /// Exception responses by exception code name
+    exceptions:        Mutex<BTreeMap<String, u64>>,
+    /// Payload bytes returned in successful responses
+    bytes_served:      AtomicU64,
+    connected_clients: AtomicU64,
+    /// Woken when the last connected client goes
+    disconnected:      tokio::sync::Notify,
+    connections_total: AtomicU64,
+    /// TCP connections closed because max_connections was reached
+    rejected:          AtomicU64,
+    /// Diagnostics (0x08) counters, cleared by sub-function 0x0A
+    diag_bus_messages:    AtomicU64,
+    diag_server_messages: AtomicU64,
+    diag_exceptions:      AtomicU64,
+}
+
+impl ModbusMetrics {
+    pub fn record_request(&self, function: &'static str, bytes: u64) {
+        if let Ok(mut m) = self.requests.lock() { *m.entry(function).or_default() += 1; }
+        self.bytes_served.fetch_add(bytes, Ordering::Relaxed);
+    }
+
+    pub fn record_exception(&self, code: String) {
+        if let Ok(mut m) = self.exceptions.lock() { *m.entry(code).or_default() += 1; }
+        self.diag_exceptions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Count a request seen on the bus; `addressed` when it was for this server.
+    pub fn record_message(&self, addressed: bool) {
+        self.diag_bus_messages.fetch_add(1, Ordering::Relaxed);
+        if addressed {
+            self.diag_server_messages.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Reset the Diagnostics counters; the Prometheus totals keep counting.
+    pub fn clear_diagnostics(&self) {
+        self.diag_bus_messages.store(0, Ordering::Relaxed);
+        self.diag_server_messages.store(0, Ordering::Relaxed);
+        self.diag_exceptions.store(0, Ordering::Relaxed);
+    }
+
+    pub fn client_connected(&self) {
+        self.connected_clients.fetch_add(1, Ordering::Relaxed);
+        self.connections_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn client_disconnected(&self) {
+        if self.connected_clients.fetch_sub(1, Ordering::Relaxed) == 1 {
+            self.disconnected.notify_waiters();
+        }
+    }
+
+    /// Resolves once no client is connected.
+    pub async fn all_disconnected(&self) {
+        loop {
+            let notified = self.disconnected.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+            if self.connected_clients() == 0 {
+                return;
+            }
+            notified.await;
+        }
+    }
+
+    pub fn client_rejected(&self) {
+        self.rejected.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn requests(&self) -> Vec<(&'static str, u64)> {
+        self.requests.lock().map(|m| m.iter().map(|(k, v)| (*k, *v)).collect()).unwrap_or_default()
+    }
+
+    pub fn exceptions(&self) -> Vec<(String, u64)> {
+        self.exceptions.lock().map(|m| m.iter().map(|(k, v)| (k.clone(), *v)).collect()).unwrap_or_default()
+    }
+
+    pub fn bytes_served(&self) -> u64 { self.bytes_served.load(Ordering::Relaxed) }
+    pub fn connected_clients(&self) -> u64 { self.connected_clients.load(Ordering::Relaxed) }
+    pub fn connections_total(&self) -> u64 { self.connections_total.load(Ordering::Relaxed) }
+    pub fn rejected_connections(&self) -> u64 { self.rejected.load(Ordering::Relaxed) }
+    pub fn bus_messages(&self) -> u64 { self.diag_bus_messages.load(Ordering::Relaxed) }
+    pub fn server_messages(&self) -> u64 { self.diag_server_messages.load(Ordering::Relaxed) }
+    pub fn exception_responses(&self) -> u64 { self.diag_exceptions.load(Ordering::Relaxed) }
+}
+
+// ─── Streaming notifications ─────────────────────────────────────────────────
+/// Alarm change or event pushed to the WebSocket clients as it happens.
+#[derive(Clone, Debug)]
+pub enum Notification {
+    /// Raised, cleared or acknowledged, in its new state
+    Alarm(Alarm),
+    Event(Event),
+}
+
+// ─── HTTP metrics ────────────────────────────────────────────────────────────
+/// Counters shared between the streaming endpoints, the rate limiter and the
+/// /metrics handler.
+#[derive(Debug, Default)]
+pub struct HttpMetrics {
+    /// Alarm and event frames WebSocket clients missed by falling behind
+    dropped_notifications: AtomicU64,
+    sse_clients:           AtomicU64,
+    sse_connections_total: AtomicU64,
+    /// Requests answered 429, by route class ("read", "mutation", "metrics")
+    rate_limited:          Mutex<BTreeMap<&'static str, u64>>,
+}
+
+impl HttpMetrics {
+    pub fn sse_client_connected(&self) {
+        self.sse_clients.fetch_add(1, Ordering::Relaxed);
+        self.sse_connections_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn sse_client_disconnected(&self) {
+        self.sse_clients.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    pub fn record_dropped_notifications(&self, missed: u64) {
+        self.dropped_notifications.fetch_add(missed, Ordering::Relaxed);
+    }
+
+    pub fn record_rate_limited(&self, class: &'static str) {
+        if let Ok(mut counts) = self.rate_limited.lock() {
+            *counts.entry(class).or_insert(0) += 1;
+        }
+    }
+
+    pub fn dropped_notifications(&self) -> u64 { self.dropped_notifications.load(Ordering::Relaxed) }
+    pub fn sse_clients(&self) -> u64 { self.sse_clients.load(Ordering::Relaxed) }
+    pub fn sse_connections_total(&self) -> u64 { self.sse_connections_total.load(Ordering::Relaxed) }
+    pub fn rate_limited(&self) -> BTreeMap<&'static str, u64> {
+        self.rate_limited.lock().map(|c| c.clone()).unwrap_or_default()
+    }
+}
+
+// ─── A simple uptime counter that auto-increments (for future use) ───────────
+#[allow(dead_code)]
+pub struct Counter(Arc<AtomicU64>);
+impl Counter {
+    pub fn new() -> Self { Counter(Arc::new(AtomicU64::new(0))) }
+    pub fn inc(&self) { self.0.fetch_add(1, Ordering::Relaxed); }
+    pub fn value(&self) -> u64 { self.0.load(Ordering::Relaxed) }
+}
+
+/// Drop the entry of `plant_id` from one of the per-plant maps.
+fn forget<V>(map: &RwLock<HashMap<String, V>>, plant_id: &str) {
+    if let Ok(mut m) = map.write() {
+        m.remove(plant_id);
+    }
+}
+
+// ─── Combined Axum state ─────────────────────────────────────────────────────
+/// Holds both AppState and the plant registry so that Axum handlers may
+/// extract `State<AppState>`, `State<PlantRegistry>` or `State<Config>` (the
+/// configuration as changed at runtime) using the `FromRef` trait.
+#[derive(Clone)]
+pub struct SharedState {
+    pub app:    AppState,
+    pub plants: PlantRegistry,
+}
+
+impl axum::extract::FromRef<SharedState> for AppState {
+    fn from_ref(s: &SharedState) -> AppState { s.app.clone() }
+}
+
+impl axum::extract::FromRef<SharedState> for PlantRegistry {
+    fn from_ref(s: &SharedState) -> PlantRegistry { s.plants.clone() }
+}
+
+impl axum::extract::FromRef<SharedState> for crate::config::Config {
+    fn from_ref(s: &SharedState) -> crate::config::Config { s.plants.config() }
+}
+
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use axum::extract::{Path, State};
+    use axum::response::IntoResponse;
+    use crate::config::{Config, GridConfig, LoadShape, MeasurementNoiseConfig, PlantConfig, ScenarioConfig, WordOrder};
+    use crate::modbus_server::{build_register_map, read_registers, RegisterTable, REG_ALARM_FLAGS, REG_FAULT_CODE, REG_STATUS};
+    use crate::models::power::WeatherSource;
+    use crate::services::pv_string::StringLayout;
+    use crate::services::site_load::SiteLoad;
+
+    /// Alarm inputs of a plant running normally; thresholds without
+    /// activation delays, so a single sample raises an alarm.
+    fn healthy() -> AlarmSnapshot {
+        AlarmSnapshot {
+            at: Utc::now(), limits: ProtectionConfig::default(), protection_trip: None,
+            thresholds: AlarmConfig {
+                voltage_delay_s: 0.0, frequency_delay_s: 0.0, overtemperature_delay_s: 0.0, mppt_deviation_delay_s: 0.0,
+                ..AlarmConfig::default()
+            },
+            v_avg: 230.0, is_day: true, ac_power: 500.0, dc_ov: false,
+            frequency_hz: 50.0, isolation_fault: false, isolation_mohm: 25.0, inverter_temp_c: 45.0, mppt_deviation_pct: 0.0,
+            rocof_hz_s: 0.0, leakage_ma: 10.0, fan_fault: false, fan_rpm: 2500,
+            tracker_stowed: false, wind_speed_m_s: 3.0, snow_cover: 0.0, damage_pct: 0.0, stale_watchdog: None,
+        }
+    }
+
+    /// Riso limit and the recovery that clears a latched isolation fault (MΩ).
+    fn riso_limits() -> (f64, f64) {
+        let alarms = AlarmConfig::default();
+        (alarms.isolation_mohm, alarms.isolation_mohm + alarms.isolation_hysteresis_mohm)
+    }
+
+    /// Hold `plant_1`'s grid at nominal so the random grid faults cannot trip it.
+    pub(crate) fn steady_grid(state: &AppState) {
+        state.inject_grid_event("plant_1", Disturbance::to(50.0), Some(230.0), 3600.0);
+    }
+
+    /// Move the simulation clock one update interval on, pinning it on first use.
+    pub(crate) fn tick(state: &AppState) {
+        state.set_clock(state.now() + chrono::Duration::seconds(UPDATE_INTERVAL_S as i64));
+    }
+
+    /// Plant with matching DC, AC and kVA ratings on UTC.
+    pub(crate) fn rated(kw: f64) -> PlantRating {
+        PlantRating {
+            nominal_power_kw: kw, max_kva: kw, max_ac_kw: kw, timezone: Tz::UTC,
+            grid_voltage_v: 230.0, phase_imbalance: PhaseImbalanceConfig::default(),
+        }
+    }
+
+    /// Mild clear-sky sample with `poa` W/m² on the strings of `layout` and
+    /// the cells at `cell_c`.
+    pub(crate) fn sample(layout: &StringLayout, poa: f64, cell_c: f64) -> SimulationData {
+        let point = layout.operating_point(poa, cell_c, 1.0);
+        SimulationData {
+            timestamp:             chrono::Utc::now(),
+            power_kw:              point.power_kw(),
+            temperature_c:         cell_c,
+            ambient_temp_c:        25.0,
+            weather_code:          0,
+            is_day:                true,
+            poa_irradiance_w_m2:   poa,
+            ghi_w_m2:              poa,
+            dni_w_m2:              0.0,
+            dhi_w_m2:              poa,
+            rear_irradiance_w_m2:  0.0,
+            cloud_factor:          1.0,
+            solar_elevation_deg:   50.0,
+            solar_azimuth_deg:     180.0,
+            source:                WeatherSource::Model,
+            wind_speed_m_s:        3.0,
+            relative_humidity_pct: 50.0,
+            soiling_factor:        1.0,
+            rain_mm_h:             0.0,
+            snow_cover_factor:     0.0,
+            degradation_factor:    1.0,
+            tracker_stowed:        false,
+            mppt:                  vec![point],
+        }
+    }
+
+    /// alarm_flags and fault_code as seen by Modbus, REST and MQTT.
+    async fn published(state: &AppState, plant: &PlantConfig) -> [(u64, u64); 3] {
+        let maps = build_register_map(std::slice::from_ref(plant));
+        let read = |addr| read_registers(state, &maps.shared.registers, RegisterTable::Input,
+            WordOrder::Abcd, addr, 1).unwrap()[0] as u64;
+        let modbus = (read(REG_ALARM_FLAGS), read(REG_FAULT_CODE));
+
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "server": { "port": 3000 }, "modbus": { "port": 5020 }, "plants": [plant]
+        })).unwrap();
+        let response = crate::controllers::power_controller::get_plant_power(
+            Path(plant.id.clone()), State(state.clone()), State(config)).await.into_response();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let rest: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        let rest = (rest["data"]["alarm_flags"].as_u64().unwrap(), rest["data"]["fault_code"].as_u64().unwrap());
+
+        let data = state.get_data(&plant.id).unwrap();
+        let mqtt = crate::services::mqtt_service::telemetry_payload(plant, &data, state.now());
+        let mqtt = (mqtt["alarm_flags"].as_u64().unwrap(), mqtt["fault_code"].as_u64().unwrap());
+
+        [modbus, rest, mqtt]
+    }
+
+    #[tokio::test]
+    async fn isolation_fault_reaches_every_interface() {
+        let plant: PlantConfig = serde_json::from_value(serde_json::json!({
+            "id": "plant_1", "name": "plant_1", "latitude": 45.0, "longitude": 7.0,
+            "nominal_power_kw": 1000.0, "timezone": "Europe/Rome",
+            "modbus_mapping": { "base_address": 0 }
+        })).unwrap();
+        let state = AppState::new(true);
+        state.plant_data.insert("plant_1", PlantData::default());
+
+        // The Warning overvoltage is evaluated first, but the Fault-level
+        // isolation alarm is the most severe and owns fault_code.
+        state.update_alarms("plant_1", &AlarmSnapshot { v_avg: 260.0, isolation_fault: true, isolation_mohm: 0.2, ..healthy() });
+        let expected_flags = (alarm_flag_bits::AC_OVERVOLTAGE | alarm_flag_bits::ISOLATION_FAULT) as u64;
+        for (flags, code) in published(&state, &plant).await {
+            assert_eq!(flags & (1 << 3), 1 << 3);
+            assert_eq!(flags, expected_flags);
+            assert_eq!(code, alarm_codes::ISOLATION_FAULT as u64);
+        }
+
+        state.update_alarms("plant_1", &AlarmSnapshot { v_avg: 260.0, ..healthy() });
+        for (flags, code) in published(&state, &plant).await {
+            assert_eq!(flags, alarm_flag_bits::AC_OVERVOLTAGE as u64);
+            assert_eq!(code, alarm_codes::AC_OVERVOLTAGE as u64);
+        }
+
+        state.update_alarms("plant_1", &healthy());
+        assert_eq!(published(&state, &plant).await, [(0, 0); 3]);
+    }
+
+    #[test]
+    fn acknowledging_a_flag_clears_only_its_alarm() {
+        let state = AppState::new(true);
+        state.plant_data.insert("plant_1", PlantData::default());
+        state.update_alarms("plant_1", &AlarmSnapshot { v_avg: 260.0, isolation_fault: true, isolation_mohm: 0.2, leakage_ma: 350.0, ..healthy() });
+
+        state.acknowledge_alarm_flags("plant_1", alarm_flag_bits::ISOLATION_FAULT);
+        let data = state.get_data("plant_1").unwrap();
+        // A critical leakage current raises its own bit, not the ground fault one
+        assert_eq!(data.alarm_flags, alarm_flag_bits::AC_OVERVOLTAGE | alarm_flag_bits::LEAKAGE_CURRENT);
+        assert_eq!(data.fault_code, alarm_codes::GROUND_FAULT);
+        let active: Vec<u16> = state.get_active_alarms(Some("plant_1")).iter().map(|a| a.code).collect();
+        assert_eq!(active, vec![alarm_codes::AC_OVERVOLTAGE, alarm_codes::GROUND_FAULT]);
+        assert!(state.get_events(1)[0].message.contains("301"));
+
+        state.acknowledge_alarm_flags("plant_1", alarm_flag_bits::LEAKAGE_CURRENT);
+        assert_eq!(state.get_active_alarms(Some("plant_1")).len(), 1);
+        assert_eq!(state.get_data("plant_1").unwrap().fault_code, alarm_codes::AC_OVERVOLTAGE);
+    }
+
+    #[test]
+    fn reactive_dispatch_stays_within_the_kva_rating() {
+        let close = |(p, q): (f64, f64), expected: (f64, f64)| {
+            assert!((p - expected.0).abs() < 1e-9 && (q - expected.1).abs() < 1e-9, "{:?} != {:?}", (p, q), expected);
+        };
+        close(dispatch_reactive(ReactivePowerMode::FixedPf, 0.9, 300.0, 800.0, 1000.0), (800.0, 0.0));
+        close(dispatch_reactive(ReactivePowerMode::FixedPf, 0.9, 300.0, 1100.0, 1000.0), (1000.0, 0.0));
+
+        // cos φ 0.8 at 900 kW needs 1125 kVA: P is derated to 800 kW, Q = 600 kvar
+        close(dispatch_reactive(ReactivePowerMode::CosPhi, 0.8, 0.0, 900.0, 1000.0), (800.0, 600.0));
+        close(dispatch_reactive(ReactivePowerMode::CosPhi, 0.0, 0.0, 500.0, 1000.0), (500.0, 0.0));
+
+        // Q has priority over P
+        close(dispatch_reactive(ReactivePowerMode::FixedQ, 1.0, -600.0, 900.0, 1000.0), (800.0, -600.0));
+        close(dispatch_reactive(ReactivePowerMode::FixedQ, 1.0, 200.0, 500.0, 1000.0), (500.0, 200.0));
+        close(dispatch_reactive(ReactivePowerMode::FixedQ, 1.0, 1500.0, 500.0, 1000.0), (0.0, 1000.0));
+    }
+
+    #[test]
+    fn tracker_stow_raises_a_warning_until_it_resumes() {
+        let state = AppState::new(true);
+        state.plant_data.insert("plant_1", PlantData::default());
+
+        state.update_alarms("plant_1", &AlarmSnapshot { tracker_stowed: true, wind_speed_m_s: 21.4, ..healthy() });
+        let data = state.get_data("plant_1").unwrap();
+        assert_eq!(data.alarm_flags, alarm_flag_bits::TRACKER_STOW);
+        assert_eq!(data.fault_code, alarm_codes::TRACKER_WIND_STOW);
+        let alarm = &state.get_active_alarms(Some("plant_1"))[0];
+        assert_eq!(alarm.severity, AlarmSeverity::Warning);
+        assert!(alarm.message.contains("21.4 m/s"), "{}", alarm.message);
+
+        state.update_alarms("plant_1", &healthy());
+        assert!(state.get_active_alarms(Some("plant_1")).is_empty());
+        assert_eq!(state.get_data("plant_1").unwrap().alarm_flags, 0);
+    }
+
+    #[test]
+    fn snow_cover_raises_an_info_alarm_while_buried() {
+        let state = AppState::new(true);
+        state.plant_data.insert("plant_1", PlantData::default());
+
+        state.update_alarms("plant_1", &AlarmSnapshot { snow_cover: 0.75, ..healthy() });
+        let data = state.get_data("plant_1").unwrap();
+        assert_eq!(data.alarm_flags, alarm_flag_bits::SNOW_COVER);
+        assert_eq!(data.fault_code, alarm_codes::SNOW_COVER);
+        let alarm = &state.get_active_alarms(Some("plant_1"))[0];
+        assert_eq!(alarm.severity, AlarmSeverity::Info);
+        assert!(alarm.message.contains("75%"), "{}", alarm.message);
+
+        // A dusting below the threshold clears it
+        state.update_alarms("plant_1", &AlarmSnapshot { snow_cover: 0.05, ..healthy() });
+        assert!(state.get_active_alarms(Some("plant_1")).is_empty());
+        assert_eq!(state.get_data("plant_1").unwrap().alarm_flags, 0);
+    }
+
+    #[test]
+    fn oversized_array_clips_into_a_flat_topped_curve() {
+        let state = AppState::new(true);
+        let (nominal, max_ac) = (1000.0, 1000.0 / 1.3);
+        steady_grid(&state);
+        let layout = StringLayout::sized_for(nominal);
+        let rating = PlantRating { nominal_power_kw: nominal, max_kva: max_ac, max_ac_kw: max_ac, ..rated(nominal) };
+        let feed = |dc: f64| {
+            tick(&state);
+            state.set_data("plant_1", &rating, &SimulationData {
+                mppt: vec![layout.operating_point(1000.0, 45.0, 1.0).with_power(dc)],
+                poa_irradiance_w_m2: dc, ghi_w_m2: 0.0, dhi_w_m2: 0.0,
+                ..sample(&layout, 1000.0, 45.0)
+            }, 0.0)
+        };
+        // Let the startup ramp settle before the sweep
+        for _ in 0..100 {
+            feed(100.0);
+        }
+
+        // Clear summer day: half-sine DC curve, 15-min samples from 08:00 to 16:00
+        let mut clipped_samples = 0;
+        for i in 1..32 {
+            feed(nominal * (std::f64::consts::PI * i as f64 / 32.0).sin());
+            let data = state.get_data("plant_1").unwrap();
+            if data.clipping {
+                clipped_samples += 1;
+                assert!((data.power_kw - max_ac).abs() < 1e-6, "{} kW at sample {i}", data.power_kw);
+            } else {
+                assert!(data.power_kw < max_ac, "{} kW at sample {i}", data.power_kw);
+            }
+        }
+        assert!(clipped_samples >= 8, "only {clipped_samples} samples on the plateau");
+        assert!(state.get_data("plant_1").unwrap().clipped_energy_kwh > 0.0);
+    }
+
+    #[test]
+    fn energy_counters_roll_over_at_local_midnight() {
+        use chrono::{Duration as Span, TimeZone};
+
+        let state = AppState::new(true);
+        state.plant_data.insert("plant_1", PlantData::default());
+        steady_grid(&state);
+        let rating = PlantRating { timezone: chrono_tz::Europe::Rome, ..rated(1000.0) };
+        let layout = StringLayout::sized_for(1000.0);
+        // One update at `at`; returns the energy it should have booked over `dt_s`
+        let update = |at: DateTime<Utc>, dt_s: f64| {
+            state.set_clock(at);
+            state.set_data("plant_1", &rating, &sample(&layout, 800.0, 40.0), 0.0);
+            state.get_data("plant_1").unwrap().power_kw * dt_s / 3600.0
+        };
+        let rollovers = || state.get_events(1000).into_iter()
+            .filter(|e| matches!(e.kind, EventKind::EnergyRollover)).collect::<Vec<_>>();
+
+        // Rome is on UTC+1 in winter: January closes at 23:00 UTC
+        let midnight = chrono::Utc.with_ymd_and_hms(2025, 1, 31, 23, 0, 0).unwrap();
+        let mut at = midnight - Span::minutes(10);
+        update(at, 0.0);
+        assert_eq!(state.get_data("plant_1").unwrap().daily_energy_kwh, 0.0, "the first update has nothing to integrate");
+        let mut booked = 0.0;
+        while at + Span::seconds(5) < midnight {
+            at += Span::seconds(5);
+            booked += update(at, 5.0);
+        }
+        let before = state.get_data("plant_1").unwrap();
+        assert!(booked > 10.0, "{booked} kWh");
+        assert!((before.daily_energy_kwh - booked).abs() < 1e-9, "{} vs {booked}", before.daily_energy_kwh);
+        assert_eq!((before.monthly_energy_kwh, before.total_energy_kwh), (before.daily_energy_kwh, before.daily_energy_kwh));
+        assert!(rollovers().is_empty());
+
+        // Local midnight of 1 February: the day and the month close, the lifetime carries on
+        let first = update(midnight, 5.0);
+        let after = state.get_data("plant_1").unwrap();
+        assert!((after.daily_energy_kwh - first).abs() < 1e-9);
+        assert_eq!(after.monthly_energy_kwh, after.daily_energy_kwh);
+        assert!((after.total_energy_kwh - (before.total_energy_kwh + first)).abs() < 1e-9);
+        let closed = rollovers();
+        assert_eq!(closed.len(), 1);
+        let payload = closed[0].payload.as_ref().unwrap();
+        assert_eq!(payload["date"], "2025-01-31");
+        assert_eq!(payload["daily_energy_kwh"], before.daily_energy_kwh);
+        assert_eq!(payload["month_closed"], true);
+
+        // A stalled update loop books at most a minute of production
+        let resumed = update(midnight + Span::minutes(15), MAX_ENERGY_GAP_S);
+        let d = state.get_data("plant_1").unwrap();
+        assert!((d.daily_energy_kwh - (first + resumed)).abs() < 1e-9);
+        assert!((d.total_energy_kwh - (before.total_energy_kwh + first + resumed)).abs() < 1e-9);
+
+        // Next local midnight is a new day of the same month
+        update(midnight + Span::days(1), 5.0);
+        let next = state.get_data("plant_1").unwrap();
+        assert!(next.monthly_energy_kwh > next.daily_energy_kwh);
+        assert_eq!(rollovers().len(), 2);
+    }
+
+    #[test]
+    fn dust_settles_daily_until_rain_or_a_wash() {
+        let state = AppState::new(true);
+        assert_eq!(state.soiling_factor("plant_1"), None);
+        state.plant_data.insert("plant_1", PlantData::default());
+
+        // One day of dry 5 s updates at 1 %/day
+        for _ in 0..17_280 {
+            state.advance_soiling("plant_1", 1.0, 0.0);
+        }
+        let dusty = state.soiling_factor("plant_1").unwrap();
+        assert!((dusty - 0.99).abs() < 1e-6, "{dusty}");
+
+        // Drizzle below the threshold does not wash; a real shower does
+        state.advance_soiling("plant_1", 1.0, 0.5);
+        assert!(state.soiling_factor("plant_1").unwrap() < dusty);
+        state.advance_soiling("plant_1", 1.0, 2.0);
+        assert_eq!(state.soiling_factor("plant_1"), Some(1.0));
+
+        // Years without rain bottom out at the soiling floor
+        for _ in 0..100 {
+            state.advance_soiling("plant_1", 100_000.0, 0.0);
+        }
+        assert_eq!(state.soiling_factor("plant_1"), Some(solar_algorithm::MIN_SOILING_FACTOR));
+
+        state.clean_panels("plant_1");
+        assert_eq!(state.soiling_factor("plant_1"), Some(1.0));
+        let event = &state.get_events(1)[0];
+        assert!(matches!(event.kind, EventKind::PanelCleaning));
+        assert_eq!(event.plant_id.as_deref(), Some("plant_1"));
+    }
+
+    #[test]
+    fn mppt_readings_follow_the_string_operating_point() {
+        let state = AppState::new(true);
+        state.plant_data.insert("plant_1", PlantData::default());
+        steady_grid(&state);
+        let layout = StringLayout::sized_for(1000.0);
+        let feed = |poa: f64, cell_c: f64| state.set_data("plant_1", &rated(1000.0), &sample(&layout, poa, cell_c), 0.0);
+        for _ in 0..200 {
+            feed(800.0, 50.0);
+        }
+
+        let dc = layout.operating_point(800.0, 50.0, 1.0);
+        let data = state.get_data("plant_1").unwrap();
+        assert!((data.mppt_voltage_v - dc.vmp_v).abs() < 1e-9);
+        assert!((data.mppt_current_a - dc.imp_a * dc.strings as f64).abs() < 1e-6 * data.mppt_current_a);
+        assert!((data.dc_power_kw - data.mppt_voltage_v * data.mppt_current_a / 1000.0).abs() < 1e-6 * data.dc_power_kw);
+        assert!((data.power_kw - data.dc_power_kw * data.efficiency_percent / 100.0).abs() < 1e-6 * data.power_kw);
+
+        // Hotter cells: lower string voltage for the same light
+        feed(800.0, 70.0);
+        assert!(state.get_data("plant_1").unwrap().mppt_voltage_v < data.mppt_voltage_v);
+    }
+
+    #[test]
+    fn one_update_publishes_the_whole_weather_sample() {
+        use chrono::TimeZone;
+        use crate::services::power_service::from_estimate;
+        use crate::services::solar_algorithm::{estimate, EstimateParams};
+
+        let state = AppState::new(true);
+        let at = chrono::Utc.with_ymd_and_hms(2025, 6, 21, 10, 0, 0).unwrap();
+        let weather = from_estimate(at, estimate(&EstimateParams::new(45.07, 7.33, 100.0), at));
+        assert!(weather.poa_irradiance_w_m2 > 100.0 && weather.solar_elevation_deg > 30.0);
+        state.set_data("plant_1", &rated(100.0), &weather, 0.0);
+
+        let data = state.get_data("plant_1").unwrap();
+        let published = [
+            data.poa_irradiance_w_m2, data.ghi_w_m2, data.dni_w_m2, data.dhi_w_m2, data.rear_irradiance_w_m2,
+            data.temperature_c, data.ambient_temp_c, data.cloud_factor, data.solar_elevation_deg,
+            data.wind_speed_m_s, data.relative_humidity_pct, data.soiling_factor, data.snow_cover_factor,
+            data.degradation_factor,
+        ];
+        let sampled = [
+            weather.poa_irradiance_w_m2, weather.ghi_w_m2, weather.dni_w_m2, weather.dhi_w_m2, weather.rear_irradiance_w_m2,
+            weather.temperature_c, weather.ambient_temp_c, weather.cloud_factor, weather.solar_elevation_deg,
+            weather.wind_speed_m_s, weather.relative_humidity_pct, weather.soiling_factor, weather.snow_cover_factor,
+            weather.degradation_factor,
+        ];
+        assert_eq!(published, sampled);
+        assert!(published[..4].iter().chain(&published[5..12]).all(|v| *v != 0.0), "{published:?}");
+        assert_eq!((data.weather_code, data.is_day, data.tracker_stowed), (weather.weather_code, weather.is_day, weather.tracker_stowed));
+        assert_eq!(data.mppt.len(), weather.mppt.len());
+    }
+
+    #[test]
+    fn phases_split_the_apparent_power_with_a_small_imbalance() {
+        for nominal in [230.0, 277.0] {
+            let state = AppState::new(true);
+            state.plant_data.insert("plant_1", PlantData::default());
+            let grid = GridConfig { nominal_voltage_v: nominal, ..GridConfig::default() };
+            state.configure_protection("plant_1", grid.protection_limits());
+            state.inject_grid_event("plant_1", Disturbance::to(50.0), Some(nominal), 3600.0);
+            state.set_reactive_mode("plant_1", ReactivePowerMode::FixedQ);
+            state.set_reactive_setpoint("plant_1", 150.0);
+            let rating = PlantRating { grid_voltage_v: nominal, ..rated(1000.0) };
+            let layout = StringLayout::sized_for(1000.0);
+            for _ in 0..200 {
+                state.set_data("plant_1", &rating, &sample(&layout, 800.0, 40.0), 0.0);
+            }
+
+            let d = state.get_data("plant_1").unwrap();
+            assert!(d.power_kw > 500.0 && d.power_factor < 1.0, "{} kW, PF {}", d.power_kw, d.power_factor);
+            let v = [d.voltage_l1_v, d.voltage_l2_v, d.voltage_l3_v];
+            let i = [d.current_l1_a, d.current_l2_a, d.current_l3_a];
+            // Distinct phases within ±1 % / ±2 % of their average
+            let v_avg = v.iter().sum::<f64>() / 3.0;
+            let i_avg = i.iter().sum::<f64>() / 3.0;
+            assert!((v_avg - nominal).abs() < 1e-6, "{v:?}");
+            assert!(v.iter().all(|x| (x / v_avg - 1.0).abs() <= 0.01 + 1e-12), "{v:?}");
+            assert!(i.iter().all(|x| (x / i_avg - 1.0).abs() <= 0.02 + 1e-12), "{i:?}");
+            assert!(v[0] != v[1] && v[1] != v[2] && i[0] != i[1] && i[1] != i[2], "{v:?} {i:?}");
+            // Σ V·I = S and Σ V·I·PF = P
+            let s_va: f64 = v.iter().zip(&i).map(|(v, i)| v * i).sum();
+            assert!((s_va - d.apparent_power_kva * 1000.0).abs() < 1e-6 * s_va, "{s_va} VA");
+            assert!((s_va * d.power_factor - d.power_kw * 1000.0).abs() < 1e-6 * s_va);
+            // Line current of a three-phase system: I = S / (√3 · V_LL)
+            let v_ll = nominal * 3f64.sqrt();
+            assert!((i_avg / (d.apparent_power_kva * 1000.0 / (3f64.sqrt() * v_ll)) - 1.0).abs() < 1e-3, "{i_avg} A");
+        }
+    }
+
+    #[test]
+    fn a_clear_day_yields_a_plausible_pr_and_capacity_factor() {
+        use chrono::TimeZone;
+        use crate::services::power_service::from_estimate;
+        use crate::services::solar_algorithm::{estimate, EstimateParams};
+
+        let state = AppState::new(true);
+        state.plant_data.insert("plant_1", PlantData::default());
+        steady_grid(&state);
+        let params = EstimateParams { soiling_factor: Some(1.0), ..EstimateParams::new(45.07, 7.33, 100.0) };
+        let midnight = chrono::Utc.with_ymd_and_hms(2025, 6, 21, 0, 0, 0).unwrap();
+        let update = |at: DateTime<Utc>| {
+            state.set_clock(at);
+            state.set_data("plant_1", &rated(100.0), &from_estimate(at, estimate(&params, at)), 0.0);
+            state.get_data("plant_1").unwrap()
+        };
+
+        // Minute by minute through a clear midsummer day; the KPIs run along
+        let mut noon = None;
+        for minute in 0..24 * 60 {
+            let d = update(midnight + chrono::Duration::minutes(minute));
+            if minute == 12 * 60 {
+                noon = Some(d);
+            }
+        }
+        let noon = noon.unwrap();
+        assert!(noon.performance_ratio > 0.7, "PR {:.3} at noon", noon.performance_ratio);
+        assert!(noon.capacity_factor_percent > 0.0);
+        let day = state.get_data("plant_1").unwrap();
+        assert!(day.daily_insolation_kwh_m2 > 5.5, "{:.2} kWh/m²", day.daily_insolation_kwh_m2);
+        assert!((0.8..=0.95).contains(&day.performance_ratio), "PR {:.3}", day.performance_ratio);
+        assert!((day.performance_ratio - day.specific_yield_kwh_kwp / day.daily_insolation_kwh_m2).abs() < 1e-9);
+        assert!((day.specific_yield_kwh_kwp - day.daily_energy_kwh / 100.0).abs() < 1e-9);
+        let hours = 24.0 - 1.0 / 60.0;
+        assert!((day.capacity_factor_percent - day.daily_energy_kwh / (100.0 * hours) * 100.0).abs() < 1e-9);
+        assert!((15.0..35.0).contains(&day.capacity_factor_percent), "{:.1} %", day.capacity_factor_percent);
+        assert!(day.specific_yield_kwh_kwp > noon.specific_yield_kwh_kwp);
+
+        // The next midnight closes the day and starts the KPIs over
+        let next = update(midnight + chrono::Duration::days(1));
+        assert_eq!((next.daily_insolation_kwh_m2, next.performance_ratio, next.capacity_factor_percent), (0.0, 0.0, 0.0));
+        assert_eq!(next.specific_yield_kwh_kwp, 0.0);
+        let closed = state.get_events(10).into_iter().find(|e| matches!(e.kind, EventKind::EnergyRollover)).unwrap();
+        assert_eq!(closed.payload.unwrap()["performance_ratio"], day.performance_ratio);
+    }
+
+    #[test]
+    fn the_uptime_counts_on_from_the_process_start() {
+        let state = AppState::new(true);
+        assert!(state.started_at() <= Utc::now());
+        let mut last = state.uptime_seconds();
+        assert_eq!(last, 0);
+        for _ in 0..3 {
+            std::thread::sleep(Duration::from_millis(400));
+            let uptime = state.uptime_seconds();
+            assert!(uptime >= last, "{uptime} after {last}");
+            last = uptime;
+        }
+        assert!(last >= 1, "{last} s after 1.2 s");
+        // Clones share the start
+        assert_eq!(state.clone().started_at(), state.started_at());
+    }
+
+    #[test]
+    fn the_offline_flag_starts_from_the_config_and_logs_each_change() {
+        let state = AppState::new(false);
+        assert!(!state.is_offline() && AppState::new(true).is_offline());
+        assert!(!state.is_mqtt_connected());
+        state.set_mqtt_connected(true);
+        assert!(state.clone().is_mqtt_connected());
+
+        state.set_offline(true);
+        state.set_offline(true);
+        assert!(state.is_offline());
+        state.set_offline(false);
+        assert!(!state.clone().is_offline());
+        let changes = state.find_events(Some(&EventKind::ModeChange), None, 10);
+        assert_eq!(changes.len(), 2, "only actual changes are logged");
+        assert_eq!(changes[0].payload, Some(serde_json::json!({ "offline_mode": false })));
+    }
+
+    #[test]
+    fn a_class_1_meter_books_within_one_percent_of_the_true_energy() {
+        use chrono::TimeZone;
+        use crate::services::power_service::from_estimate;
+        use crate::services::solar_algorithm::{estimate, EstimateParams};
+
+        let params = EstimateParams { soiling_factor: Some(1.0), ..EstimateParams::new(45.07, 7.33, 100.0) };
+        let midnight = chrono::Utc.with_ymd_and_hms(2025, 6, 21, 0, 0, 0).unwrap();
+        let wall = chrono::Utc.with_ymd_and_hms(2026, 1, 15, 9, 0, 0).unwrap();
+        // One simulated day at 24×, booked through a class 1 meter
+        let day = |seed: u64| {
+            let state = AppState::new(true);
+            steady_grid(&state);
+            state.set_simulation_seed(seed);
+            state.configure_energy_meter("plant_1", EnergyMeter::new(1.0));
+            let clock = SimClock::new(24.0, Some(midnight), wall).unwrap();
+            state.set_sim_clock(clock).unwrap();
+            for update in 0..(86_400.0 / 24.0 / UPDATE_INTERVAL_S) as i64 {
+                let at = clock.at(wall + chrono::Duration::seconds(update * UPDATE_INTERVAL_S as i64));
+                state.set_clock(at);
+                state.set_data("plant_1", &rated(100.0), &from_estimate(at, estimate(&params, at)), 0.0);
+            }
+            let history = state.get_history("plant_1", midnight, midnight + chrono::Duration::days(1), Resolution::OneHour);
+            let true_kwh: f64 = history.iter().map(|p| p.true_energy_kwh).sum();
+            let metered_kwh: f64 = history.iter().map(|p| p.energy_kwh).sum();
+            assert!((metered_kwh - state.get_data("plant_1").unwrap().daily_energy_kwh).abs() < 1e-6);
+            (metered_kwh, true_kwh)
+        };
+        let errors: Vec<f64> = [1, 2, 3].into_iter().map(|seed| {
+            let (metered, truth) = day(seed);
+            assert!(truth > 400.0, "{truth:.1} kWh");
+            let error = (metered - truth) / truth;
+            assert!(error.abs() <= 0.01, "seed {seed}: {metered:.2} kWh booked for {truth:.2} kWh");
+            error
+        }).collect();
+        // The seed draws the meter: it replays, and another seed draws another one
+        assert_eq!(day(2), day(2));
+        assert!(errors.iter().all(|e| *e != 0.0) && errors[0] != errors[1], "{errors:?}");
+    }
+
+    #[test]
+    fn an_accelerated_day_yields_the_energy_of_a_real_one() {
+        use chrono::TimeZone;
+        use crate::services::power_service::from_estimate;
+        use crate::services::solar_algorithm::{estimate, EstimateParams};
+
+        let params = EstimateParams { soiling_factor: Some(1.0), ..EstimateParams::new(45.07, 7.33, 100.0) };
+        let midnight = chrono::Utc.with_ymd_and_hms(2025, 6, 21, 0, 0, 0).unwrap();
+        let wall = chrono::Utc.with_ymd_and_hms(2026, 1, 15, 9, 0, 0).unwrap();
+        // One simulated midsummer day, updated every 5 wall-clock seconds at `speed`
+        let day = |speed: f64| {
+            let state = AppState::new(true);
+            steady_grid(&state);
+            let clock = SimClock::new(speed, Some(midnight), wall).unwrap();
+            state.set_sim_clock(clock).unwrap();
+            for update in 0..(86_400.0 / speed / UPDATE_INTERVAL_S) as i64 {
+                let at = clock.at(wall + chrono::Duration::seconds(update * UPDATE_INTERVAL_S as i64));
+                state.set_clock(at);
+                state.set_data("plant_1", &rated(100.0), &from_estimate(at, estimate(&params, at)), 0.0);
+            }
+            state.get_data("plant_1").unwrap()
+        };
+        let (real, accelerated) = (day(1.0), day(24.0));
+        assert!(real.daily_energy_kwh > 400.0, "{:.1} kWh", real.daily_energy_kwh);
+        let off = (accelerated.daily_energy_kwh - real.daily_energy_kwh).abs() / real.daily_energy_kwh;
+        assert!(off < 0.02, "{:.1} kWh at 24× against {:.1} kWh", accelerated.daily_energy_kwh, real.daily_energy_kwh);
+        assert!((accelerated.daily_insolation_kwh_m2 - real.daily_insolation_kwh_m2).abs() < 0.02 * real.daily_insolation_kwh_m2);
+
+        // Online mode runs on the wall clock only
+        let online = AppState::new(false);
+        let err = online.set_sim_clock(SimClock::new(24.0, None, wall).unwrap()).unwrap_err();
+        assert!(err.contains("offline mode"), "{err}");
+        assert!(online.set_sim_clock(SimClock::default()).is_ok());
+    }
+
+    #[tokio::test]
+    async fn moving_the_clock_restarts_the_day_on_the_new_date() {
+        use chrono::TimeZone;
+        let state = AppState::new(true);
+        steady_grid(&state);
+        let layout = StringLayout::sized_for(100.0);
+        let january = chrono::Utc.with_ymd_and_hms(2026, 1, 15, 12, 0, 0).unwrap();
+        let solstice = chrono::Utc.with_ymd_and_hms(2025, 6, 21, 12, 0, 0).unwrap();
+        let move_to = |at| state.set_sim_clock(SimClock::new(1.0, Some(at), Utc::now()).unwrap()).unwrap();
+
+        move_to(january);
+        for _ in 0..3 {
+            state.set_data("plant_1", &rated(100.0), &sample(&layout, 800.0, 45.0), 0.0);
+        }
+        state.plant_data.modify("plant_1", |d| d.soiling_factor = 0.9).unwrap();
+        assert_eq!(state.soiling_factor("plant_1"), Some(0.9));
+
+        // A new pace from the current simulated time is no jump
+        state.set_sim_clock(SimClock::new(2.0, Some(state.now()), Utc::now()).unwrap()).unwrap();
+        assert_eq!(state.soiling_factor("plant_1"), Some(0.9));
+
+        // Jumping to the solstice closes January 15th without booking the gap,
+        // and the model rebuilds the soiling for June
+        move_to(solstice);
+        assert_eq!(state.soiling_factor("plant_1"), None);
+        let before = state.get_data("plant_1").unwrap().total_energy_kwh;
+        state.set_data("plant_1", &rated(100.0), &sample(&layout, 800.0, 45.0), 0.0);
+        let d = state.get_data("plant_1").unwrap();
+        assert_eq!((d.total_energy_kwh, d.daily_energy_kwh), (before, 0.0));
+        assert_eq!(d.energy_day, Some(solstice.date_naive()));
+        assert!(d.last_update_unix.abs_diff(solstice.timestamp() as u64) <= 1);
+        assert!(d.update_age_s() < 1.0);
+        assert!(state.soiling_factor("plant_1").is_some());
+        let closed = state.get_events(20).into_iter().find(|e| matches!(e.kind, EventKind::EnergyRollover)).unwrap();
+        assert_eq!(closed.payload.unwrap()["date"], "2026-01-15");
+
+        // The health check flags simulated data until real time resumes
+        let config: Config = serde_json::from_value(serde_json::json!({
+            "server": { "port": 3000 }, "modbus": { "port": 5020 }, "plants": []
+        })).unwrap();
+        let health = |state: &AppState| {
+            let state = state.clone();
+            let config = config.clone();
+            async move {
+                let response = crate::controllers::power_controller::health_check(State(state), State(config)).await.into_response();
+                let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+                serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+            }
+        };
+        let simulated = health(&state).await;
+        assert_eq!(simulated["clock_overridden"], true);
+        assert!(simulated["simulation_time"].as_str().unwrap().starts_with("2025-06-21T12:00"), "{simulated}");
+        state.set_sim_clock(SimClock::default()).unwrap();
+        assert_eq!(health(&state).await["clock_overridden"], false);
+    }
+
+    #[test]
+    fn each_plant_integrates_over_its_own_measured_interval() {
+        use chrono::TimeZone;
+        let state = AppState::new(true);
+        let layout = StringLayout::sized_for(100.0);
+        let plants = [("fast", 1), ("normal", 5), ("slow", 30)];
+        for (id, interval_s) in plants {
+            state.configure_update_interval(id, interval_s as f64);
+            state.inject_grid_event(id, Disturbance::to(50.0), Some(230.0), 3600.0);
+        }
+        assert_eq!(state.update_interval_s("unconfigured"), UPDATE_INTERVAL_S);
+
+        // Ten minutes of a steady 800 W/m², each plant sampled on its own cadence
+        let start = chrono::Utc.with_ymd_and_hms(2025, 6, 21, 10, 0, 0).unwrap();
+        for second in 0..=600 {
+            state.set_clock(start + chrono::Duration::seconds(second));
+            for (id, interval_s) in plants {
+                if second % interval_s == 0 {
+                    state.set_data(id, &rated(100.0), &sample(&layout, 800.0, 45.0), 0.0);
+                }
+            }
+        }
+        for (id, interval_s) in plants {
+            let d = state.get_data(id).unwrap();
+            assert_eq!(d.update_interval_s, interval_s as f64);
+            assert_eq!(d.last_update_unix, (start + chrono::Duration::seconds(600)).timestamp() as u64);
+            let expected = 0.8 * 600.0 / 3600.0;
+            assert!((d.daily_insolation_kwh_m2 - expected).abs() < 1e-9, "{id}: {} kWh/m²", d.daily_insolation_kwh_m2);
+        }
+
+        // A 30 s plant is not stalled by one missed update, a stalled loop still is
+        state.set_clock(start + chrono::Duration::seconds(660));
+        state.set_data("slow", &rated(100.0), &sample(&layout, 800.0, 45.0), 0.0);
+        let slow = state.get_data("slow").unwrap().daily_insolation_kwh_m2;
+        assert!((slow - 0.8 * 660.0 / 3600.0).abs() < 1e-9, "{slow} kWh/m²");
+        state.set_clock(start + chrono::Duration::seconds(1260));
+        state.set_data("slow", &rated(100.0), &sample(&layout, 800.0, 45.0), 0.0);
+        let resumed = state.get_data("slow").unwrap().daily_insolation_kwh_m2;
+        assert!((resumed - slow - 0.8 * 60.0 / 3600.0).abs() < 1e-9, "{resumed} kWh/m²");
+    }
+
+    #[test]
+    fn hail_damage_lowers_the_output_and_alarms_until_repaired() {
+        use chrono::TimeZone;
+        use crate::services::power_service::from_estimate;
+        use crate::services::solar_algorithm::{estimate, EstimateParams};
+
+        let state = AppState::new(true);
+        state.plant_data.insert("plant_1", PlantData::default());
+        steady_grid(&state);
+        let noon = chrono::Utc.with_ymd_and_hms(2025, 6, 21, 11, 30, 0).unwrap();
+        // Clear midsummer noon through the model, with the plant's live damage
+        let run = || {
+            let params = EstimateParams {
+                soiling_factor: Some(1.0),
+                damage_pct:     state.damage_pct("plant_1"),
+                ..EstimateParams::new(45.07, 7.33, 100.0)
+            };
+            let noon_sample = from_estimate(noon, estimate(&params, noon));
+            for _ in 0..200 {
+                state.set_data("plant_1", &rated(100.0), &noon_sample, 0.0);
+            }
+            state.get_data("plant_1").unwrap()
         };
-        // First-order thermal filter τ ≈ 5 samples (25 s) — heatsink thermal mass
-        data.inverter_temp_c = data.inverter_temp_c
-            + (t_hs_target - data.inverter_temp_c) * 0.2;
+        let logged = |kind: fn(&EventKind) -> bool| state.get_events(1000).into_iter().filter(|e| kind(&e.kind)).collect::<Vec<_>>();
+        let intact = run();
+        assert_eq!(intact.alarm_flags & alarm_flag_bits::MODULE_DAMAGE, 0);
 
-        // ── 6. 3-phase AC voltage & frequency ─────────────────────────────
-        // Epoch-based fault injection using det_hash:
-        //  • 5-minute windows → faults last a whole epoch (realistic for grid events)
-        //  • P_VOLT_FAULT (2.5%) chance per epoch for swell or sag
-        //  • P_FREQ_FAULT (1.5%) chance per epoch for over/under-frequency
-        // Normal operation stays firmly within EN 50160 limits (±4 V, ±0.08 Hz).
-        let grid_epoch = now_secs / 300;   // 5-minute windows
-        let h_swell    = det_hash(plant_id, grid_epoch.wrapping_mul(7));
-        let h_sag      = det_hash(plant_id, grid_epoch.wrapping_mul(7) + 1);
-        let h_freq_hi  = det_hash(plant_id, grid_epoch.wrapping_mul(7) + 2);
-        let h_freq_lo  = det_hash(plant_id, grid_epoch.wrapping_mul(7) + 3);
+        // 20 % of the array lost: the output falls with it, a Critical alarm holds
+        assert_eq!(state.damage_plant("plant_1", 20.0, "hail"), 20.0);
+        let damaged = run();
+        assert_eq!(damaged.damage_pct, 20.0);
+        assert!((damaged.power_kw / intact.power_kw - 0.8).abs() < 0.02,
+            "{:.1} kW vs {:.1} kW", damaged.power_kw, intact.power_kw);
+        assert_eq!(damaged.poa_irradiance_w_m2, intact.poa_irradiance_w_m2);
+        assert_ne!(damaged.alarm_flags & alarm_flag_bits::MODULE_DAMAGE, 0);
+        let alarm = state.get_alarms(Some("plant_1")).into_iter().find(|a| a.active && a.code == alarm_codes::MODULE_DAMAGE).unwrap();
+        assert_eq!(alarm.severity, AlarmSeverity::Critical);
+        let damage = logged(|k| matches!(k, EventKind::ModuleDamage));
+        assert_eq!(damage.len(), 1);
+        assert_eq!(damage[0].payload.as_ref().unwrap()["cause"], "hail");
 
-        // Epoch-level voltage drift (slow, ±4 V — within EN 50160 normal band)
-        let v_drift = (det_hash(plant_id, grid_epoch.wrapping_mul(7) + 4) * 2.0 - 1.0) * 4.0;
-        // Per-sample fine ripple (±0.4 V — measurement noise)
-        let h_rip = det_hash(plant_id, now_secs.wrapping_mul(11) ^ 0xA5A5);
-        let v_ripple = (h_rip * 2.0 - 1.0) * 0.4;
+        // A second storm takes a share of what is left
+        let total = state.damage_plant("plant_1", 10.0, "hail");
+        assert!((total - 28.0).abs() < 1e-9);
 
-        // Grid-event override: swell/sag pushes voltage well outside trip limits
-        let v_offset = if h_swell < P_VOLT_FAULT {
-            // Swell: +28..+46 V above nominal → clearly above V_OV_LIMIT (253 V)
-            28.0 + (h_swell / P_VOLT_FAULT) * 18.0
-        } else if h_sag < P_VOLT_FAULT {
-            // Sag: −28..−46 V below nominal → clearly below V_UV_LIMIT (207 V)
-            -(28.0 + (h_sag / P_VOLT_FAULT) * 18.0)
-        } else {
-            v_drift + v_ripple
+        // The operator repair brings the plant back and clears the alarm
+        assert_eq!(state.repair_plant("plant_1"), Some(total));
+        let repaired = run();
+        assert_eq!(repaired.damage_pct, 0.0);
+        assert!((repaired.power_kw - intact.power_kw).abs() < 1e-9);
+        assert_eq!(repaired.alarm_flags & alarm_flag_bits::MODULE_DAMAGE, 0);
+        assert_eq!(repaired.fault_code, intact.fault_code);
+        assert_eq!(logged(|k| matches!(k, EventKind::ModuleRepair)).len(), 1);
+        assert_eq!(state.repair_plant("plant_1"), None);
+    }
+
+    #[test]
+    fn isolation_dips_with_dawn_dew_and_ageing() {
+        let dry_noon = isolation_resistance_mohm(1.0, 60.0, 40.0, 1.0);
+        assert_eq!(dry_noon, 40.0);
+        // Dew only matters on a humid morning, and burns off as the sun climbs
+        let (riso_fault, riso_clear) = riso_limits();
+        let dawn = |rh| isolation_resistance_mohm(0.06, 2.0, rh, 1.0);
+        assert_eq!(dawn(60.0), 11.8);
+        assert!(dawn(97.0) < riso_fault && dawn(97.0) < dawn(85.0) && dawn(85.0) < dawn(60.0));
+        assert!(isolation_resistance_mohm(0.18, 12.0, 97.0, 1.0) > riso_clear);
+        // A twenty-year-old array (90 % of its power) keeps about a third of its isolation
+        let aged = isolation_resistance_mohm(1.0, 60.0, 40.0, 0.9);
+        assert!((aged / dry_noon - 0.349).abs() < 0.001, "{aged:.2} MΩ");
+    }
+
+    #[test]
+    fn riso_check_holds_the_start_until_the_dew_dries_off() {
+        let (riso_fault, riso_clear) = riso_limits();
+        let state = AppState::new(true);
+        steady_grid(&state);
+        let layout = StringLayout::sized_for(100.0);
+        let feed = |poa: f64, elevation: f64, rh: f64| {
+            state.set_data("plant_1", &rated(100.0), &SimulationData {
+                ambient_temp_c: 10.0, solar_elevation_deg: elevation, wind_speed_m_s: 1.0, relative_humidity_pct: rh,
+                ..sample(&layout, poa, 15.0)
+            }, 0.0);
+            state.get_data("plant_1").unwrap()
         };
+        let isolation_alarm = || state.get_alarms(Some("plant_1")).iter().any(|a| a.active && a.code == alarm_codes::ISOLATION_FAULT);
 
-        // Realistic per-phase asymmetry (≤ ±0.5 V IEC 62052 class B)
-        let h_ph  = det_hash(plant_id, now_secs ^ 0xCCCC);
-        let h_ph2 = det_hash(plant_id, now_secs ^ 0xBEEF);
-        data.voltage_l1_v = V_GRID_NOM + v_offset;
-        data.voltage_l2_v = V_GRID_NOM + v_offset + (h_ph  * 2.0 - 1.0) * 0.5;
-        data.voltage_l3_v = V_GRID_NOM + v_offset - (h_ph2 * 2.0 - 1.0) * 0.5;
-
-        // Frequency: slow epoch-level oscillation ±0.08 Hz; fault events ±0.55 Hz
-        let f_drift  = (det_hash(plant_id, grid_epoch.wrapping_mul(7) + 5) * 2.0 - 1.0) * 0.08;
-        let h_frip   = det_hash(plant_id, now_secs.wrapping_mul(13) ^ 0xF0F0);
-        let f_ripple = (h_frip * 2.0 - 1.0) * 0.01;
-        let f_offset = if h_freq_hi < P_FREQ_FAULT {
-            // Over-frequency event: +0.55..+0.80 Hz above F_NOM
-            0.55 + (h_freq_hi / P_FREQ_FAULT) * 0.25
-        } else if h_freq_lo < P_FREQ_FAULT {
-            // Under-frequency event: −0.55..−0.80 Hz
-            -(0.55 + (h_freq_lo / P_FREQ_FAULT) * 0.25)
-        } else {
-            f_drift + f_ripple
-        };
-        let new_freq = F_NOM + f_offset;
-
-        // ROCOF: derivative of frequency between consecutive 5-second samples.
-        // During epoch transitions (freq step) this will briefly spike — realistic.
-        let prev_f = self.prev_freq.read()
-            .map(|m| m.get(plant_id).copied().unwrap_or(new_freq))
-            .unwrap_or(new_freq);
-        data.rocof_hz_s = (new_freq - prev_f) / UPDATE_INTERVAL_S;
-        data.frequency_hz = new_freq;
-        if let Ok(mut pf) = self.prev_freq.write() {
-            pf.insert(plant_id.to_string(), new_freq);
-        }
-
-        // ── 7. Power factor, apparent, reactive ──────────────────────────────
-        if ac_power > 0.01 {
-            let pf_base = 0.96 + 0.04 * (1.0 - (-12.0 * load_factor).exp());
-            let pf_noise = (ac_power * 11.7).sin() * 0.004;
-            data.power_factor   = (pf_base + pf_noise).clamp(0.80, 1.0);
-        } else {
-            data.power_factor   = 1.0;
+        // Soaking sunrise: the Riso check fails and the inverter stays off the grid
+        for _ in 0..20 {
+            feed(60.0, 2.0, 97.0);
         }
-        data.apparent_power_kva = if data.power_factor > 0.0 { ac_power / data.power_factor } else { ac_power };
-        let q_sq = data.apparent_power_kva.powi(2) - ac_power.powi(2);
-        data.reactive_power_kvar = if q_sq > 0.0 { q_sq.sqrt() } else { 0.0 };
+        let wet = feed(60.0, 2.0, 97.0);
+        assert!(wet.isolation_resistance_mohm < riso_fault);
+        assert!(!wet.grid_connected);
+        assert_eq!(wet.status, 2);
+        assert_ne!(wet.alarm_flags & alarm_flag_bits::ISOLATION_FAULT, 0);
+        assert!(isolation_alarm());
 
-        // ── 7b. AC Total Harmonic Distortion (THD) ────────────────────────────
-        // IEC 61727: THD < 5 % at rated power.
-        // Pattern: high THD at very low load (>12%), decreases to ~1.8% at rated,
-        // rises slightly above rated. Real IGBT inverters follow this profile.
-        let thd_at_load = if load_factor < 0.02 {
-            0.0 // no output → undefined, report 0
-        } else if load_factor < 0.10 {
-            12.0 - (load_factor / 0.10) * 7.5   // 12% down to 4.5% at 10% load
-        } else if load_factor < 0.50 {
-            4.5 - ((load_factor - 0.10) / 0.40) * 2.7  // 4.5% → 1.8% at 50% load
-        } else {
-            1.8 + ((load_factor - 0.50) / 0.50) * 0.5  // slight rise above 50%
+        // Back above 1 MΩ but not yet 1.5 MΩ: the fault holds
+        let drying = feed(150.0, 9.0, 97.0);
+        assert!(drying.isolation_resistance_mohm > riso_fault && drying.isolation_resistance_mohm < riso_clear);
+        assert_eq!(drying.status, 2);
+        assert!(!drying.grid_connected);
+
+        // Dry: the alarm clears and the normal start sequence runs
+        let dry = feed(180.0, 12.0, 97.0);
+        assert!(dry.isolation_resistance_mohm > riso_clear);
+        assert_eq!(dry.alarm_flags & alarm_flag_bits::ISOLATION_FAULT, 0);
+        assert!(!isolation_alarm());
+        for _ in 0..STARTUP_DELAY_CYCLES {
+            feed(180.0, 12.0, 97.0);
+        }
+        assert!(feed(180.0, 12.0, 97.0).grid_connected);
+
+        // A fault injected at noon trips the running inverter
+        state.inject_isolation_fault("plant_1", 0.4, 60.0);
+        let faulted = feed(800.0, 60.0, 50.0);
+        assert_eq!((faulted.isolation_resistance_mohm, faulted.status), (0.4, 2));
+        assert!(isolation_alarm());
+    }
+
+    #[test]
+    fn east_west_mppts_peak_apart_and_add_up() {
+        use chrono::TimeZone;
+        use crate::services::power_service::from_estimate;
+        use crate::services::solar_algorithm::{estimate, EstimateParams};
+
+        let plant: PlantConfig = serde_json::from_value(serde_json::json!({
+            "id": "plant_1", "name": "plant_1", "latitude": 45.0, "longitude": 7.0,
+            "nominal_power_kw": 100.0, "timezone": "UTC",
+            "modbus_mapping": { "base_address": 0 },
+            "panel": { "tilt_deg": 30.0 },
+            "mppt_inputs": [{ "azimuth_deg": 90.0 }, { "azimuth_deg": 270.0 }]
+        })).unwrap();
+        let params = EstimateParams::for_plant(&plant);
+        let start = chrono::Utc.with_ymd_and_hms(2025, 6, 21, 3, 0, 0).unwrap();
+        let day: Vec<(f64, _)> = (0..64)
+            .map(|i| (3.0 + i as f64 / 4.0, estimate(&params, start + chrono::Duration::minutes(15 * i))))
+            .collect();
+
+        // Both roofs see the same sky: east leads before solar noon (≈11:30 UTC), west after
+        let centroid_h = |k: usize| {
+            let energy: f64 = day.iter().map(|(_, e)| e.mppt[k].power_kw()).sum();
+            day.iter().map(|(h, e)| h * e.mppt[k].power_kw()).sum::<f64>() / energy
         };
-        // Fast per-cycle noise (±0.2 %) from switching ripple
-        let h_thd = det_hash(plant_id, now_secs.wrapping_mul(31) ^ 0x55AA);
-        data.ac_thd_percent = (thd_at_load + (h_thd * 2.0 - 1.0) * 0.2).max(0.0);
+        assert!(centroid_h(0) < 11.0 && centroid_h(1) > 12.0, "{:.2} h vs {:.2} h", centroid_h(0), centroid_h(1));
+        for (h, e) in &day {
+            let (east, west) = (e.mppt[0].power_kw(), e.mppt[1].power_kw());
+            if (5.0..10.0).contains(h) {
+                assert!(east > west, "{h} h: {east:.1} vs {west:.1} kW");
+            } else if (13.0..18.0).contains(h) {
+                assert!(west > east, "{h} h: {east:.1} vs {west:.1} kW");
+            }
+            assert!((e.power_kw - east - west).abs() < 1e-9);
+        }
 
-        // ── 7c. DC injection into AC grid ──────────────────────────────────
-        // IEEE 1547 / IEC 61727: limit 0.5% of rated AC current.
-        // Model: 0.05–0.5 % of I_rated depending on load and high-frequency noise;
-        //        epoch-based to keep it stable within one cycle.
-        let dc_inj_epoch = now_secs / 60; // 1-minute windows
-        let h_dc_inj = det_hash(plant_id, dc_inj_epoch.wrapping_mul(19));
-        let i_rated_a = if V_GRID_NOM > 0.0 { nominal_power_kw * 1000.0 / (3.0 * V_GRID_NOM) } else { 0.0 };
-        data.dc_injection_ma = if ac_power > 0.01 {
-            i_rated_a * (0.05 + h_dc_inj * 0.45) / 100.0 * 1000.0 // 0.05–0.5 % in mA
-        } else { 0.0 };
+        // The inverter adds the inputs up before converting to AC
+        let (_, morning) = &day[20];
+        let at = start + chrono::Duration::minutes(15 * 20);
+        let morning_sample = from_estimate(at, estimate(&params, at));
+        let state = AppState::new(true);
+        state.plant_data.insert("plant_1", PlantData::default());
+        steady_grid(&state);
+        for _ in 0..200 {
+            state.set_data("plant_1", &rated(100.0), &morning_sample, 0.0);
+        }
+        let data = state.get_data("plant_1").unwrap();
+        assert_eq!(data.mppt.len(), 2);
+        assert!(data.mppt[0].power_kw > data.mppt[1].power_kw);
+        let dc_sum = data.mppt[0].power_kw + data.mppt[1].power_kw;
+        assert!((data.dc_power_kw - dc_sum).abs() < 1e-9);
+        assert!((dc_sum - morning.power_kw).abs() < 1e-6 * dc_sum);
+        assert!((data.power_kw - dc_sum * data.efficiency_percent / 100.0).abs() < 1e-6 * data.power_kw);
+        assert_eq!(data.mppt_voltage_v, data.mppt[0].voltage_v);
+    }
 
-        // ── 8. Phase currents (balanced 3-phase split) ───────────────────────
-        let phase_va = data.apparent_power_kva * 1000.0 / 3.0;
-        data.current_l1_a = if data.voltage_l1_v > 0.0 { phase_va / data.voltage_l1_v } else { 0.0 };
-        data.current_l2_a = if data.voltage_l2_v > 0.0 { phase_va / data.voltage_l2_v } else { 0.0 };
-        data.current_l3_a = if data.voltage_l3_v > 0.0 { phase_va / data.voltage_l3_v } else { 0.0 };
-
-        // ── 9. Isolation resistance (DC-GND, three-layer model) ───────────────
-        // a) Normal 10–40 MΩ, highest at midday (dry, warm panels)
-        // b) Dawn-dew effect: panels cold and wet → reduced isolation at low elevation
-        // c) "Wet day" fault event (P_ISOL_FAULT per hour epoch): < 0.4 MΩ → trip
-        let isol_epoch = now_secs / 3600;   // 1-hour windows
-        let h_wet      = det_hash(plant_id, isol_epoch.wrapping_mul(13));
-        let isol_base  = if h_wet < P_ISOL_FAULT && is_day {
-            // Isolation fault (heavy rain, panel surface contamination)
-            0.05 + (h_wet / P_ISOL_FAULT) * 0.35   // 0.05 – 0.40 MΩ (below 0.5 MΩ threshold)
-        } else {
-            10.0 + irr_ratio * 30.0   // 10–40 MΩ scaling with irradiance
+    #[test]
+    fn sunrise_connects_once_and_ramps_up_monotonically() {
+        let state = AppState::new(true);
+        state.plant_data.insert("plant_1", PlantData::default());
+        state.configure_startup("plant_1", IRRAD_START_W_M2, IRRAD_STOP_W_M2, STARTUP_DELAY_CYCLES, MPPT_SEARCH_CYCLES, Some(6.0));
+        steady_grid(&state);
+        let layout = StringLayout::sized_for(100.0);
+        let feed = |poa: f64, is_day: bool| {
+            state.set_data("plant_1", &rated(100.0), &SimulationData { ambient_temp_c: 15.0, is_day, solar_elevation_deg: 10.0, ..sample(&layout, poa, 25.0) }, 0.0);
+            state.get_data("plant_1").unwrap()
         };
-        // Dawn-dew factor: isolation recovers linearly as panels warm (0–20° elevation)
-        let dew_factor = if is_day && solar_elevation_deg < 20.0 {
-            0.30 + (solar_elevation_deg / 20.0) * 0.70   // 0.30 at horizon → 1.0 at 20°
-        } else {
-            1.0
+        let logged = |kind: fn(&EventKind) -> bool| state.get_events(1000).iter().filter(|e| kind(&e.kind)).count();
+
+        // Dawn rising 2.5 W/m² per cycle; early clouds keep dipping it under the start threshold
+        let mut connected_at = None;
+        let (mut last_kw, mut last_dc_kw) = (0.0, 0.0);
+        for i in 0..240 {
+            let flicker = if i % 2 == 1 && i < 20 { 12.0 } else { 0.0 };
+            let data = feed(2.5 * i as f64 - flicker, true);
+            match connected_at {
+                None if data.grid_connected => connected_at = Some(i),
+                None => assert_eq!(data.power_kw, 0.0, "cycle {i}"),
+                Some(_) => {
+                    // 6 kW/min = 0.5 kW per 5 s cycle drawn from the array
+                    assert!(data.dc_power_kw - last_dc_kw <= 0.5 + 1e-9, "cycle {i}: {last_dc_kw} → {} kW", data.dc_power_kw);
+                    assert!(data.power_kw >= last_kw, "cycle {i}: {last_kw} → {} kW", data.power_kw);
+                    assert!(data.grid_connected);
+                }
+            }
+            (last_kw, last_dc_kw) = (data.power_kw, data.dc_power_kw);
+        }
+        // 40 W/m² at cycle 16 is the first start of an uninterrupted run of 7
+        assert_eq!(connected_at, Some(16 + STARTUP_DELAY_CYCLES as usize));
+        assert!((state.get_data("plant_1").unwrap().ramp_factor - 1.0).abs() < 1e-9, "ramp has caught up with the light");
+
+        // A dark cloud inside the hysteresis band does not disconnect
+        for _ in 0..10 {
+            let data = feed(20.0, true);
+            assert!(data.grid_connected && data.power_kw > 0.0);
+        }
+        // Dusk: below the stop threshold the inverter disconnects, at night it is logged
+        for poa in [600.0, 300.0, 100.0, 16.0, 14.0, 5.0] {
+            feed(poa, true);
+        }
+        assert!(!state.get_data("plant_1").unwrap().grid_connected);
+        feed(0.0, false);
+        feed(0.0, false);
+
+        assert_eq!(logged(|k| matches!(k, EventKind::PlantStartup)), 1);
+        assert_eq!(logged(|k| matches!(k, EventKind::PlantShutdown)), 1);
+    }
+
+    #[test]
+    fn zero_export_holds_generation_to_the_site_load() {
+        let state = AppState::new(true);
+        state.plant_data.insert("plant_1", PlantData::default());
+        state.configure_export_limit("plant_1", 0.0);
+        steady_grid(&state);
+        let load = SiteLoad { base_kw: 2.0, peak_kw: 20.0, shape: LoadShape::Residential };
+        let layout = StringLayout::sized_for(100.0);
+        let feed = |site_load_kw: f64| {
+            tick(&state);
+            state.set_data("plant_1", &rated(100.0), &sample(&layout, 1000.0, 45.0), site_load_kw);
+            state.get_data("plant_1").unwrap()
         };
-        data.isolation_resistance_mohm = (isol_base * dew_factor).max(0.05);
+        for _ in 0..30 {
+            feed(load.load_kw(6.0));
+        }
 
-        // ── 9b. Leakage (residual) current to ground (mA) ────────────────────
-        // Model: IEC 62109 — normal < 50 mA; concern zone 50–300 mA; trip > 300 mA.
-        // Higher with humidity (moisture on panel frames / cabling).
-        // More leakage when isolation resistance is low.
-        let leak_humidity_factor = 1.0 + (relative_humidity_pct - 50.0).max(0.0) * 0.012;
-        // Base leakage ∝ (1 / isolation) scaled to realistic range
-        let leak_base = (1.0 / data.isolation_resistance_mohm.max(0.01)) * 2.5 * leak_humidity_factor;
-        let h_leak = det_hash(plant_id, now_secs.wrapping_mul(43) ^ 0x1234);
-        data.leakage_current_ma = (leak_base + h_leak * 0.5).clamp(0.05, 350.0);
+        // Full sun all day long, the plant only ever covers what the site draws
+        for hour in 6..22 {
+            let site_load_kw = load.load_kw(hour as f64);
+            let data = feed(site_load_kw);
+            assert!((data.power_kw - site_load_kw).abs() < 1e-6, "{hour} h: {} kW for a {site_load_kw} kW load", data.power_kw);
+            assert!(data.grid_export_kw.abs() < 1e-6);
+            // The grid is pinned healthy, so curtailment is the only state left
+            assert!(data.grid_connected, "{hour} h: disconnected");
+            assert_eq!(data.status, 3, "{hour} h");
+        }
+        assert!(state.get_data("plant_1").unwrap().curtailed_energy_kwh > 0.0);
+    }
 
-        // ── 9c. Inverter cooling fan model ────────────────────────────────────
-        // Real inverters: fan off below 40°C heatsink, variable 1500–3600 RPM above.
-        // Fan fault: injected with P_FAN_FAULT probability per 4-hour epoch.
-        const P_FAN_FAULT: f64 = 0.008; // ~1 event per 500 h per plant
-        let fan_epoch = now_secs / 14400; // 4-hour windows
-        let h_fan = det_hash(plant_id, fan_epoch.wrapping_mul(29));
-        let fan_fail = h_fan < P_FAN_FAULT && is_day;
-        data.fan_fault_active = fan_fail;
+    #[test]
+    fn injected_grid_events_drive_the_droop_and_the_volt_var_curve() {
+        let state = AppState::new(true);
+        state.plant_data.insert("plant_1", PlantData::default());
+        let droop = FreqWatt { threshold_hz: 50.2, droop_pct: 5.0, nominal_hz: 50.0 };
+        state.configure_grid_support("plant_1", Some(droop), VoltVarCurve::default());
+        let layout = StringLayout::sized_for(100.0);
+        let feed = || {
+            state.set_data("plant_1", &rated(100.0), &sample(&layout, 800.0, 45.0), 0.0);
+            state.get_data("plant_1").unwrap()
+        };
+        let logged = |kind: fn(&EventKind) -> bool| state.get_events(1000).iter().filter(|e| kind(&e.kind)).count();
+        steady_grid(&state);
+        for _ in 0..40 {
+            feed();
+        }
+        let before = feed();
+        assert!(before.power_kw > 50.0 && before.reactive_power_kvar == 0.0);
 
-        let fan_rpm = if data.inverter_temp_c < 40.0 {
-            0u16
-        } else if fan_fail {
-            // Fan fault: rotor stall → 0 RPM (no cooling → temperature runaway)
-            0
-        } else {
-            // Linear 1500 – 3600 RPM across 40–80°C
-            let frac = ((data.inverter_temp_c - 40.0) / 40.0).clamp(0.0, 1.0);
-            (1500.0 + frac * 2100.0) as u16
+        // 0.25 Hz over the threshold on a 5 % droop takes 10 % off the output
+        state.inject_grid_event("plant_1", Disturbance::to(50.45), Some(230.0), 60.0);
+        let during = feed();
+        assert_eq!(during.frequency_hz, 50.45);
+        assert!((during.power_kw - before.power_kw * 0.9).abs() < 1e-6, "{} → {} kW", before.power_kw, during.power_kw);
+        assert_eq!(feed().power_kw, during.power_kw, "the reference stays frozen");
+        assert_eq!(logged(|k| matches!(k, EventKind::CurtailmentStart)), 1);
+
+        state.inject_grid_event("plant_1", Disturbance::to(50.0), Some(230.0), 60.0);
+        assert!((feed().power_kw - before.power_kw).abs() < 1e-6);
+        assert_eq!(logged(|k| matches!(k, EventKind::CurtailmentEnd)), 1);
+
+        // Q(U): 5 % overvoltage absorbs 22 % of the kVA rating
+        state.set_reactive_mode("plant_1", ReactivePowerMode::VoltVar);
+        state.inject_grid_event("plant_1", Disturbance::to(50.0), Some(241.5), 60.0);
+        let data = feed();
+        assert!((data.reactive_power_kvar + 22.0).abs() < 0.5, "{} kvar", data.reactive_power_kvar);
+        assert!(data.apparent_power_kva <= 100.0 + 1e-9);
+    }
+
+    #[test]
+    fn plants_on_one_grid_share_its_frequency_and_an_injected_ramp_trips_rocof() {
+        let state = AppState::new(true);
+        let layout = StringLayout::sized_for(100.0);
+        for (id, network, nominal_hz) in [("plant_1", "continental", 50.0), ("plant_2", "continental", 50.0), ("plant_3", "americas", 60.0)] {
+            state.plant_data.insert(id, PlantData::default());
+            state.configure_grid(id, network, nominal_hz);
+            // f< / f> at the frequency bounds, so only the RoCoF function can trip
+            state.configure_protection(id, ProtectionConfig {
+                underfrequency_hz: nominal_hz - 5.0, overfrequency_hz: nominal_hz + 5.0, rocof_hz_s: 0.5,
+                ..ProtectionConfig::default()
+            });
+            state.inject_grid_event(id, Disturbance::default(), Some(230.0), 3600.0);
+        }
+        let feed = || ["plant_1", "plant_2", "plant_3"].map(|id| {
+            state.set_data(id, &rated(100.0), &sample(&layout, 800.0, 45.0), 0.0);
+            state.get_data(id).unwrap()
+        });
+        state.set_clock(chrono::DateTime::from_timestamp(1_750_500_000, 0).unwrap());
+
+        // The walk moves both continental plants together; the 60 Hz grid has its own
+        let mut independent = false;
+        for _ in 0..60 {
+            tick(&state);
+            let [a, b, c] = feed();
+            assert_eq!((a.frequency_hz, a.rocof_hz_s), (b.frequency_hz, b.rocof_hz_s));
+            assert!((a.frequency_hz - 50.0).abs() < 1.0 && (c.frequency_hz - 60.0).abs() < 1.0, "{} / {} Hz", a.frequency_hz, c.frequency_hz);
+            assert!(a.rocof_hz_s.abs() < 0.5 && c.rocof_hz_s.abs() < 0.5);
+            independent |= a.frequency_hz - 50.0 != c.frequency_hz - 60.0;
+        }
+        assert!(independent);
+        let [a, b, c] = feed();
+        assert!(a.grid_connected && b.grid_connected && c.grid_connected);
+
+        // A 0.6 Hz/s ramp injected at one plant reaches its whole network
+        state.inject_grid_event("plant_1", Disturbance { rocof_hz_s: 0.6, ..Disturbance::default() }, None, 60.0);
+        tick(&state);
+        let [a, b, c] = feed();
+        assert!((a.rocof_hz_s - 0.6).abs() < 0.05, "{} Hz/s", a.rocof_hz_s);
+        assert_eq!((a.frequency_hz, a.rocof_hz_s), (b.frequency_hz, b.rocof_hz_s));
+        assert_eq!((a.protection_trip, b.protection_trip), (Some(alarm_codes::ROCOF_TRIP), Some(alarm_codes::ROCOF_TRIP)));
+        assert!(!a.grid_connected && !b.grid_connected);
+        assert_eq!(c.protection_trip, None);
+        assert!(c.rocof_hz_s.abs() < 0.5);
+    }
+
+    #[test]
+    fn undervoltage_trips_after_its_delay_and_reconnects_after_observation() {
+        let state = AppState::new(true);
+        state.plant_data.insert("plant_1", PlantData::default());
+        let layout = StringLayout::sized_for(100.0);
+        let feed = || {
+            state.set_data("plant_1", &rated(100.0), &sample(&layout, 800.0, 45.0), 0.0);
+            state.get_data("plant_1").unwrap()
         };
-        data.inverter_fan_speed_rpm = fan_rpm;
+        let logged = |kind: fn(&EventKind) -> bool| state.get_events(1000).iter().filter(|e| kind(&e.kind)).count();
+        steady_grid(&state);
+        for _ in 0..40 {
+            feed();
+        }
+        let before = feed();
+        assert!(before.grid_connected && before.power_kw > 50.0);
 
-        // ── 10. Status determination ─────────────────────────────────────────
-        let v_avg = (data.voltage_l1_v + data.voltage_l2_v + data.voltage_l3_v) / 3.0;
-        let has_fault = v_avg > V_OV_LIMIT || v_avg < V_UV_LIMIT
-            || data.frequency_hz > F_OV_LIMIT || data.frequency_hz < F_UV_LIMIT
-            || data.rocof_hz_s.abs() > ROCOF_LIMIT
-            || data.isolation_resistance_mohm < ISOL_FAULT_MOHM
-            || data.inverter_temp_c > T_OVERTEMP_C
-            || (data.fan_fault_active && data.inverter_temp_c > T_OVERTEMP_C - 5.0)
-            || dc_ov;
+        // A deep sag rides through the first sample at the current limit…
+        state.inject_grid_event("plant_1", Disturbance::to(50.0), Some(150.0), 60.0);
+        let sag = feed();
+        assert!(sag.grid_connected && sag.power_kw > 0.0);
+        assert!(sag.power_kw <= 100.0 * 150.0 / 230.0 + 0.5, "{} kW", sag.power_kw);
+        assert_eq!(logged(|k| matches!(k, EventKind::GridDisconnect)), 0);
 
-        data.status = if has_fault {
-            2  // Fault
-        } else if ramp < 0.05 && poa_irradiance_w_m2 < IRRAD_START_W_M2 {
-            0  // Stopped / night
-        } else if ramp < 0.99 && poa_irradiance_w_m2 >= IRRAD_START_W_M2 {
-            4  // Starting (ramp-up in progress)
-        } else if ramp > 0.0 && ramp < 1.0 && poa_irradiance_w_m2 < IRRAD_START_W_M2 {
-            3  // Curtailed / shutting down (ramp-down in progress)
-        } else if ac_power > 0.001 {
-            if load_factor < 0.999 { 5 } else { 1 }  // 5=MPPT tracking, 1=Running at rated
-        } else if is_day && solar_elevation_deg > 1.0 {
-            4  // Starting (waiting for irradiance)
-        } else {
-            0  // Stopped (night)
+        // …and trips V< once it has lasted longer than 1.5 s
+        let tripped = feed();
+        assert_eq!(tripped.status, 2);
+        assert!(!tripped.grid_connected && tripped.power_kw == 0.0);
+        assert_eq!(tripped.protection_trip, Some(alarm_codes::AC_UNDERVOLTAGE));
+        assert_ne!(tripped.alarm_flags & alarm_flag_bits::GRID_DISCONNECT, 0);
+        assert!(state.get_active_alarms(Some("plant_1")).iter().any(|a| a.code == alarm_codes::GRID_ISLAND_DETECTED));
+        let trip = state.get_events(1000).into_iter().find(|e| matches!(e.kind, EventKind::GridDisconnect)).unwrap();
+        assert_eq!(trip.payload.unwrap()["code"], alarm_codes::AC_UNDERVOLTAGE);
+
+        // Back to nominal: 60 s of observation (12 cycles), then the normal startup sequence
+        steady_grid(&state);
+        for cycle in 1..12 {
+            assert_eq!(feed().power_kw, 0.0, "cycle {cycle}");
+        }
+        assert_eq!(logged(|k| matches!(k, EventKind::GridReconnect)), 0);
+        let reset = feed();
+        assert_eq!(reset.protection_trip, None);
+        assert_eq!(logged(|k| matches!(k, EventKind::GridReconnect)), 1);
+        assert!(!state.get_active_alarms(Some("plant_1")).iter().any(|a| a.code == alarm_codes::GRID_ISLAND_DETECTED));
+        for _ in 0..40 {
+            feed();
+        }
+        assert!(feed().grid_connected);
+    }
+
+    #[test]
+    fn status_walks_through_the_inverter_states_and_logs_each_transition() {
+        let plant: PlantConfig = serde_json::from_value(serde_json::json!({
+            "id": "plant_1", "name": "plant_1", "latitude": 45.0, "longitude": 7.0,
+            "nominal_power_kw": 100.0, "timezone": "UTC",
+            "modbus_mapping": { "base_address": 0 }
+        })).unwrap();
+        let state = AppState::new(true);
+        state.plant_data.insert("plant_1", PlantData::default());
+        steady_grid(&state);
+        let layout = StringLayout::sized_for(100.0);
+        let feed = |poa: f64| {
+            state.set_data("plant_1", &rated(100.0), &SimulationData { is_day: poa > 0.0, ..sample(&layout, poa, 25.0) }, 0.0);
+            state.get_data("plant_1").unwrap().status
         };
 
-        // ── 11. Alarm / fault code logic ────────────────────────────────────
-        // Snapshot fields needed for alarm logic (before releasing write lock)
-        let snap_freq     = data.frequency_hz;
-        let snap_isol     = data.isolation_resistance_mohm;
-        let snap_inv_temp = data.inverter_temp_c;
-        let snap_rocof    = data.rocof_hz_s;
-        let snap_leak     = data.leakage_current_ma;
-        let snap_fan_fail = data.fan_fault_active;
-        let snap_fan_rpm  = data.inverter_fan_speed_rpm;
+        // Night, then the self-test and the MPP search before running
+        assert_eq!(feed(0.0), 0);
+        let morning: Vec<u16> = (0..STARTUP_DELAY_CYCLES + MPPT_SEARCH_CYCLES + 1).map(|_| feed(600.0)).collect();
+        let mut expected = vec![4; STARTUP_DELAY_CYCLES as usize];
+        expected.extend([5; MPPT_SEARCH_CYCLES as usize]);
+        expected.push(1);
+        assert_eq!(morning, expected);
+        for _ in 0..20 {
+            assert_eq!(feed(600.0), 1);
+        }
 
-        let mut new_flags: u32 = 0;
-        let mut fault_code: u16 = alarm_codes::NONE;
+        // A power limit curtails the output until it is lifted
+        state.set_power_limit("plant_1", 30.0, ControlInterface::Modbus);
+        assert_eq!(feed(600.0), 3);
+        state.set_power_limit("plant_1", 100.0, ControlInterface::Modbus);
+        assert_eq!(feed(600.0), 1);
 
-        drop(map); // release write lock before calling alarm helpers
+        // An isolation fault holds the inverter in Fault until it has recovered
+        state.inject_isolation_fault("plant_1", 0.4, 60.0);
+        assert_eq!(feed(600.0), 2);
+        state.inject_isolation_fault("plant_1", 1.2, 60.0);
+        assert_eq!(feed(600.0), 2);
+        let maps = build_register_map(std::slice::from_ref(&plant));
+        let register = read_registers(&state, &maps.shared.registers, RegisterTable::Input, WordOrder::Abcd, REG_STATUS, 1).unwrap()[0];
+        let mqtt = crate::services::mqtt_service::telemetry_payload(&plant, &state.get_data("plant_1").unwrap(), state.now());
+        assert_eq!((register, mqtt["status"].as_str().unwrap()), (2, "FAULT"));
+        state.inject_isolation_fault("plant_1", 5.0, 60.0);
+        assert_eq!(feed(600.0), 1);
 
-        // Overvoltage
-        if v_avg > V_OV_LIMIT {
-            new_flags |= alarm_flag_bits::AC_OVERVOLTAGE;
-            try_set_fault(&mut fault_code, alarm_codes::AC_OVERVOLTAGE);
-            self.raise_alarm(plant_id, alarm_codes::AC_OVERVOLTAGE, AlarmSeverity::Warning,
-                &format!("AC overvoltage: {:.1} V (limit {:.0} V)", v_avg, V_OV_LIMIT));
-        } else { self.clear_alarm(plant_id, alarm_codes::AC_OVERVOLTAGE); }
+        // A remote stop wins over everything
+        state.set_inverter_enabled("plant_1", false, ControlInterface::Modbus);
+        assert_eq!(feed(600.0), 0);
 
-        // Undervoltage
-        if v_avg < V_UV_LIMIT && is_day {
-            new_flags |= alarm_flag_bits::AC_UNDERVOLTAGE;
-            try_set_fault(&mut fault_code, alarm_codes::AC_UNDERVOLTAGE);
-            self.raise_alarm(plant_id, alarm_codes::AC_UNDERVOLTAGE, AlarmSeverity::Warning,
-                &format!("AC undervoltage: {:.1} V (limit {:.0} V)", v_avg, V_UV_LIMIT));
-        } else { self.clear_alarm(plant_id, alarm_codes::AC_UNDERVOLTAGE); }
+        let transitions: Vec<(u64, u64)> = state.get_events(1000).iter().rev()
+            .filter(|e| matches!(e.kind, EventKind::StatusChange))
+            .map(|e| {
+                let payload = e.payload.as_ref().unwrap();
+                (payload["from"].as_u64().unwrap(), payload["to"].as_u64().unwrap())
+            })
+            .collect();
+        assert_eq!(transitions, vec![(0, 4), (4, 5), (5, 1), (1, 3), (3, 1), (1, 2), (2, 1), (1, 0)]);
+    }
 
-        // Frequency — distinguish over-frequency from under-frequency
-        if snap_freq > F_OV_LIMIT {
-            new_flags |= alarm_flag_bits::FREQUENCY_FAULT;
-            try_set_fault(&mut fault_code, alarm_codes::AC_OVERFREQUENCY);
-            self.raise_alarm(plant_id, alarm_codes::AC_OVERFREQUENCY, AlarmSeverity::Warning,
-                &format!("Over-frequency: {:.3} Hz (limit {:.2} Hz)", snap_freq, F_OV_LIMIT));
-            self.clear_alarm(plant_id, alarm_codes::AC_UNDERFREQUENCY);
-        } else if snap_freq < F_UV_LIMIT {
-            new_flags |= alarm_flag_bits::FREQUENCY_FAULT;
-            try_set_fault(&mut fault_code, alarm_codes::AC_UNDERFREQUENCY);
-            self.raise_alarm(plant_id, alarm_codes::AC_UNDERFREQUENCY, AlarmSeverity::Warning,
-                &format!("Under-frequency: {:.3} Hz (limit {:.2} Hz)", snap_freq, F_UV_LIMIT));
-            self.clear_alarm(plant_id, alarm_codes::AC_OVERFREQUENCY);
-        } else {
-            self.clear_alarm(plant_id, alarm_codes::AC_OVERFREQUENCY);
-            self.clear_alarm(plant_id, alarm_codes::AC_UNDERFREQUENCY);
+    #[test]
+    fn control_commands_log_their_interface_and_a_reset_releases_the_latches() {
+        let state = AppState::new(true);
+        state.plant_data.insert("plant_1", PlantData {
+            protection_trip: Some(alarm_codes::AC_OVERVOLTAGE),
+            protection_cycles: [0, 2, 0, 0, 0],
+            isolation_fault: true,
+            ..PlantData::default()
+        });
+        let last = |kind: EventKind| state.find_events(Some(&kind), Some("plant_1"), 1).into_iter().next();
+
+        // A limit applied over REST and moved over Modbus
+        state.set_power_limit("plant_1", 40.0, ControlInterface::Rest);
+        state.set_power_limit("plant_1", 60.0, ControlInterface::Modbus);
+        state.set_power_limit("plant_1", 60.0, ControlInterface::Modbus);
+        assert_eq!(last(EventKind::CurtailmentStart).unwrap().payload.unwrap()["interface"], "rest");
+        let moved = last(EventKind::SettingChanged).unwrap();
+        assert_eq!(moved.message, "Active power limit changed from 40 to 60 % via Modbus");
+        assert_eq!(state.find_events(Some(&EventKind::SettingChanged), Some("plant_1"), 10).len(), 1);
+        state.set_inverter_enabled("plant_1", false, ControlInterface::Rest);
+        assert_eq!(last(EventKind::PlantShutdown).unwrap().payload.unwrap()["interface"], "rest");
+
+        // The protection trip holds while the grid is still out of limits
+        assert_eq!(state.reset_faults("plant_1", ControlInterface::Rest), Some(vec![alarm_codes::ISOLATION_FAULT]));
+        assert_eq!(state.get_data("plant_1").unwrap().protection_trip, Some(alarm_codes::AC_OVERVOLTAGE));
+        state.plant_data.modify("plant_1", |d| d.protection_cycles = [0; 5]).unwrap();
+        assert_eq!(state.reset_faults("plant_1", ControlInterface::Rest), Some(vec![alarm_codes::AC_OVERVOLTAGE]));
+        let data = state.get_data("plant_1").unwrap();
+        assert_eq!((data.protection_trip, data.isolation_fault), (None, false));
+        let reset = last(EventKind::SettingChanged).unwrap().payload.unwrap();
+        assert_eq!((reset["reset_codes"][0].as_u64(), reset["interface"].as_str()), (Some(101), Some("rest")));
+        assert_eq!(state.reset_faults("plant_1", ControlInterface::Rest), Some(vec![]));
+        assert_eq!(state.reset_faults("plant_9", ControlInterface::Rest), None);
+    }
+
+    #[test]
+    fn injected_faults_hold_their_alarm_until_withdrawn_or_expired() {
+        let plant: PlantConfig = serde_json::from_value(serde_json::json!({
+            "id": "plant_1", "name": "plant_1", "latitude": 45.0, "longitude": 7.0,
+            "nominal_power_kw": 100.0, "timezone": "UTC",
+            "modbus_mapping": { "base_address": 0 }
+        })).unwrap();
+        let state = AppState::new(true);
+        state.plant_data.insert("plant_1", PlantData::default());
+        steady_grid(&state);
+        let layout = StringLayout::sized_for(100.0);
+        let feed = || {
+            state.set_data("plant_1", &rated(100.0), &sample(&layout, 600.0, 25.0), 0.0);
+            state.get_data("plant_1").unwrap()
+        };
+        let active = |code| state.get_active_alarms(Some("plant_1")).into_iter().find(|a| a.code == code);
+        for _ in 0..20 {
+            feed();
         }
+        assert_eq!(feed().status, 1);
 
-        // Isolation fault
-        if snap_isol < ISOL_FAULT_MOHM {
-            new_flags |= alarm_flag_bits::ISOLATION_FAULT;
-            try_set_fault(&mut fault_code, alarm_codes::ISOLATION_FAULT);
-            self.raise_alarm(plant_id, alarm_codes::ISOLATION_FAULT, AlarmSeverity::Fault,
-                &format!("Isolation resistance too low: {:.2} MΩ (limit {:.1} MΩ)", snap_isol, ISOL_FAULT_MOHM));
-        } else { self.clear_alarm(plant_id, alarm_codes::ISOLATION_FAULT); }
+        // A healthy array does not clear an injected isolation fault
+        state.inject_fault("plant_1", alarm_codes::ISOLATION_FAULT, AlarmSeverity::Critical, 120.0);
+        for _ in 0..3 {
+            let data = feed();
+            assert_eq!((data.status, data.fault_code), (2, alarm_codes::ISOLATION_FAULT));
+            assert_eq!(data.alarm_flags, alarm_flag_bits::ISOLATION_FAULT);
+        }
+        assert_eq!(active(alarm_codes::ISOLATION_FAULT).unwrap().severity, AlarmSeverity::Critical);
+        let mqtt = crate::services::mqtt_service::telemetry_payload(&plant, &state.get_data("plant_1").unwrap(), state.now());
+        assert_eq!((mqtt["status"].as_str(), mqtt["fault_code"].as_u64()), (Some("FAULT"), Some(301)));
 
-        // Leakage current (IEC 62109 limit 300 mA — Critical; 100 mA — Warning)
-        if snap_leak > 300.0 {
-            new_flags |= alarm_flag_bits::LEAKAGE_CURRENT;
-            try_set_fault(&mut fault_code, alarm_codes::GROUND_FAULT);
-            self.raise_alarm(plant_id, alarm_codes::GROUND_FAULT, AlarmSeverity::Critical,
-                &format!("Leakage current critical: {:.1} mA (trip >300 mA)", snap_leak));
-        } else if snap_leak > 100.0 {
-            new_flags |= alarm_flag_bits::LEAKAGE_CURRENT;
-            self.raise_alarm(plant_id, alarm_codes::GROUND_FAULT, AlarmSeverity::Warning,
-                &format!("Leakage current elevated: {:.1} mA (warn >100 mA)", snap_leak));
-        } else { self.clear_alarm(plant_id, alarm_codes::GROUND_FAULT); }
+        // Withdrawn, the alarm clears at once and the inverter runs again
+        assert!(state.withdraw_fault("plant_1", alarm_codes::ISOLATION_FAULT));
+        assert!(!state.withdraw_fault("plant_1", alarm_codes::ISOLATION_FAULT));
+        assert!(active(alarm_codes::ISOLATION_FAULT).is_none());
+        let data = feed();
+        assert_eq!((data.status, data.fault_code, data.alarm_flags), (1, 0, 0));
 
-        // Overtemperature
-        if snap_inv_temp > T_OVERTEMP_C {
-            new_flags |= alarm_flag_bits::OVERTEMPERATURE;
-            try_set_fault(&mut fault_code, alarm_codes::OVERTEMPERATURE);
-            self.raise_alarm(plant_id, alarm_codes::OVERTEMPERATURE, AlarmSeverity::Critical,
-                &format!("Inverter overtemperature: {:.1} °C (limit {:.0} °C)", snap_inv_temp, T_OVERTEMP_C));
-        } else { self.clear_alarm(plant_id, alarm_codes::OVERTEMPERATURE); }
+        // An expired injection clears on the next update, even for a code no check raises
+        state.inject_fault("plant_1", alarm_codes::INTERNAL_FAULT, AlarmSeverity::Fault, 60.0);
+        assert_eq!(feed().fault_code, alarm_codes::INTERNAL_FAULT);
+        state.injected_faults.write().unwrap().get_mut("plant_1").unwrap()[0].until = Instant::now();
+        let data = feed();
+        assert_eq!((data.status, data.fault_code), (1, 0));
+        assert!(active(alarm_codes::INTERNAL_FAULT).is_none());
+    }
 
-        // Fan fault (fan stopped while inverter is hot)
-        if snap_fan_fail && snap_inv_temp > 45.0 {
-            new_flags |= alarm_flag_bits::FAN_FAULT;
-            try_set_fault(&mut fault_code, alarm_codes::FAN_FAULT);
-            self.raise_alarm(plant_id, alarm_codes::FAN_FAULT, AlarmSeverity::Warning,
-                &format!("Cooling fan fault: 0 RPM at {:.1} °C heatsink", snap_inv_temp));
-        } else {
-            // Fan running — check for under-speed (e.g. partial stall)
-            if ac_power > 0.1 && snap_fan_rpm > 0 && snap_fan_rpm < 1200 && snap_inv_temp > 50.0 {
-                new_flags |= alarm_flag_bits::FAN_FAULT;
-                self.raise_alarm(plant_id, alarm_codes::FAN_FAULT, AlarmSeverity::Warning,
-                    &format!("Fan under-speed: {} RPM (expected ≥1500 RPM)", snap_fan_rpm));
-            } else {
-                self.clear_alarm(plant_id, alarm_codes::FAN_FAULT);
-            }
+    #[test]
+    fn a_plant_in_maintenance_stays_stopped_and_silent_until_enabled() {
+        let state = AppState::new(true);
+        steady_grid(&state);
+        let layout = StringLayout::sized_for(100.0);
+        let feed = || {
+            state.set_data("plant_1", &rated(100.0), &sample(&layout, 600.0, 25.0), 0.0);
+            state.get_data("plant_1").unwrap()
+        };
+        for _ in 0..20 {
+            feed();
         }
+        state.inject_fault("plant_1", alarm_codes::INTERNAL_FAULT, AlarmSeverity::Fault, 600.0);
+        assert_eq!(feed().status, 2);
 
-        // DC overvoltage
-        if dc_ov {
-            new_flags |= alarm_flag_bits::DC_OVERVOLTAGE;
-            try_set_fault(&mut fault_code, alarm_codes::DC_OVERVOLTAGE);
-            self.raise_alarm(plant_id, alarm_codes::DC_OVERVOLTAGE, AlarmSeverity::Warning,
-                &format!("DC string over-voltage: estimated V_oc > {:.0} V rated DC bus", V_DC_NOM));
-        } else { self.clear_alarm(plant_id, alarm_codes::DC_OVERVOLTAGE); }
+        // Out of service: no output, no alarms, even with the fault still injected
+        assert!(state.set_plant_enabled("plant_1", false, Some("inverter swap by J. Doe")));
+        assert!(!state.set_plant_enabled("plant_1", false, None));
+        assert!(state.get_active_alarms(Some("plant_1")).is_empty());
+        for _ in 0..3 {
+            let data = feed();
+            assert!(data.maintenance && !state.plant_enabled("plant_1"));
+            assert_eq!((data.status, data.power_kw, data.current_l1_a, data.dc_current_a), (0, 0.0, 0.0, 0.0));
+            assert_eq!((data.fault_code, data.alarm_flags), (0, 0));
+        }
+        assert!(state.get_active_alarms(Some("plant_1")).is_empty());
+        let event = state.get_events(20).into_iter().find(|e| matches!(e.kind, EventKind::MaintenanceStart)).unwrap();
+        assert!(event.message.ends_with("inverter swap by J. Doe"), "{}", event.message);
+        assert_eq!(event.payload.unwrap()["reason"], "inverter swap by J. Doe");
 
-        // ROCOF — measured frequency derivative between 5-second samples
-        if snap_rocof.abs() > ROCOF_LIMIT {
-            new_flags |= alarm_flag_bits::ROCOF_TRIP;
-            try_set_fault(&mut fault_code, alarm_codes::ROCOF_TRIP);
-            self.raise_alarm(plant_id, alarm_codes::ROCOF_TRIP, AlarmSeverity::Critical,
-                &format!("RoCoF trip: {:.3} Hz/s (limit ±{:.1} Hz/s)", snap_rocof, ROCOF_LIMIT));
-        } else { self.clear_alarm(plant_id, alarm_codes::ROCOF_TRIP); }
+        // Back in service it starts up again from the self-test
+        state.withdraw_fault("plant_1", alarm_codes::INTERNAL_FAULT);
+        assert!(state.set_plant_enabled("plant_1", true, None));
+        let statuses: Vec<u16> = (0..STARTUP_DELAY_CYCLES + MPPT_SEARCH_CYCLES + 1).map(|_| feed().status).collect();
+        assert_eq!(statuses[0], 4);
+        assert_eq!(statuses.last(), Some(&1));
+        assert!(!feed().maintenance && feed().power_kw > 0.0);
+        assert!(state.get_events(20).iter().any(|e| matches!(e.kind, EventKind::MaintenanceEnd)));
+    }
 
-        // Write alarm flags back
-        let mut map2 = match self.plant_data.write() { Ok(g) => g, Err(_) => return };
-        if let Some(d) = map2.get_mut(plant_id) {
-            d.fault_code  = fault_code;
-            d.alarm_flags = new_flags;
+    #[test]
+    fn a_plant_that_stops_updating_goes_stale_until_its_next_update() {
+        let state = AppState::new(true);
+        steady_grid(&state);
+        let layout = StringLayout::sized_for(100.0);
+        let feed = || state.set_data("plant_1", &rated(100.0), &sample(&layout, 600.0, 25.0), 0.0);
+        for _ in 0..20 {
+            feed();
+        }
+        assert_eq!(state.stale_after_s("plant_1"), 3.0 * UPDATE_INTERVAL_S);
+        state.check_stale_plants();
+        assert!(!state.get_data("plant_1").unwrap().stale);
 
-            // ── 12. Energy accounting ────────────────────────────────────────
-            let kwh_per_sample = d.power_kw * (UPDATE_INTERVAL_S / 3600.0);
-            d.daily_energy_kwh   += kwh_per_sample;
-            d.monthly_energy_kwh += kwh_per_sample;
-            d.total_energy_kwh   += kwh_per_sample;
+        // The update task pauses: its last update ages past the threshold
+        state.configure_stale_after("plant_1", 10.0);
+        let paused_at = Instant::now().checked_sub(Duration::from_secs(12)).unwrap();
+        state.plant_data.modify("plant_1", |d| d.updated_at = Some(paused_at)).unwrap();
+        state.check_stale_plants();
+        state.check_stale_plants();
+        let data = state.get_data("plant_1").unwrap();
+        assert!(data.stale);
+        assert_eq!(data.fault_code, alarm_codes::COMMUNICATION_LOSS);
+        assert_ne!(data.alarm_flags & alarm_flag_bits::COMMUNICATION_LOSS, 0);
+        assert_eq!(state.get_active_alarms(Some("plant_1")).len(), 1);
+        let lost: Vec<_> = state.get_events(50).into_iter().filter(|e| matches!(e.kind, EventKind::CommunicationLost)).collect();
+        assert_eq!(lost.len(), 1);
+        assert_eq!(lost[0].payload.as_ref().unwrap()["stale_after_s"], 10.0);
 
-            // CO₂ avoided: ENTSO-E European grid average ≈ 0.233 kg CO₂/kWh
-            d.co2_avoided_kg += kwh_per_sample * 0.233;
+        // Updates resume: the alarm clears and the recovery is logged
+        feed();
+        let data = state.get_data("plant_1").unwrap();
+        assert!(!data.stale);
+        assert_eq!((data.fault_code, data.alarm_flags & alarm_flag_bits::COMMUNICATION_LOSS), (0, 0));
+        assert!(state.get_active_alarms(Some("plant_1")).is_empty());
+        assert!(state.get_events(50).iter().any(|e| matches!(e.kind, EventKind::CommunicationRestored)));
+    }
 
-            // Today's peak AC power
-            if d.power_kw > d.daily_peak_power_kw {
-                d.daily_peak_power_kw = d.power_kw;
-            }
+    #[test]
+    fn fleet_totals_sum_the_plants_and_count_the_running_ones() {
+        let state = AppState::new(true);
+        assert_eq!(state.fleet_totals().mean_performance_ratio(), 0.0);
+        state.plant_data.insert("plant_1", PlantData { power_kw: 40.0, daily_energy_kwh: 100.0, performance_ratio: 0.8, status: 5, ..PlantData::default() });
+        state.plant_data.insert("plant_2", PlantData { power_kw: 10.0, daily_energy_kwh: 50.0, performance_ratio: 0.6, status: 2, ..PlantData::default() });
+        state.plant_data.insert("plant_3", PlantData { status: 1, maintenance: true, ..PlantData::default() });
+        let totals = state.fleet_totals();
+        assert_eq!((totals.power_kw, totals.daily_energy_kwh), (50.0, 150.0));
+        assert_eq!((totals.plants, totals.running, totals.in_maintenance), (3, 1, 1));
+        assert!((totals.mean_performance_ratio() - 1.4 / 3.0).abs() < 1e-12);
+    }
 
-            // ── 13. Performance KPIs ─────────────────────────────────────────
-            // PR = actual yield / reference yield;  ref yield = G_poa/1000 * P_nom
-            let ref_yield = (d.poa_irradiance_w_m2 / 1000.0) * nominal_power_kw;
-            d.performance_ratio = if ref_yield > 0.1 {
-                (d.power_kw / ref_yield).clamp(0.0, 1.0)
-            } else { 0.0 };
+    #[test]
+    fn the_event_log_filters_by_kind_and_plant_and_keeps_the_newest() {
+        let state = AppState::new(true);
+        steady_grid(&state);
+        let layout = StringLayout::sized_for(100.0);
+        for plant_id in ["plant_1", "plant_2"] {
+            state.set_data(plant_id, &rated(100.0), &sample(&layout, 600.0, 25.0), 0.0);
+            state.set_data(plant_id, &rated(100.0), &sample(&layout, 600.0, 25.0), 0.0);
+        }
+        let online = state.find_events(Some(&EventKind::PlantOnline), None, 10);
+        assert_eq!(online.len(), 2, "one per plant, on its first update only");
+        assert_eq!(online[0].plant_id.as_deref(), Some("plant_2"));
+        assert_eq!(state.find_events(Some(&EventKind::PlantOnline), Some("plant_1"), 10).len(), 1);
 
-            d.specific_yield_kwh_kwp = if nominal_power_kw > 0.0 {
-                d.daily_energy_kwh / nominal_power_kw
-            } else { 0.0 };
+        // Only an actual change of mode is logged
+        state.set_offline(true);
+        state.set_offline(false);
+        let modes = state.find_events(Some(&EventKind::ModeChange), None, 10);
+        assert_eq!(modes.len(), 1);
+        assert_eq!(modes[0].payload.as_ref().unwrap()["offline_mode"], false);
 
-            d.capacity_factor_percent = if nominal_power_kw > 0.0 {
-                (d.power_kw / nominal_power_kw * 100.0).clamp(0.0, 110.0)
-            } else { 0.0 };
+        state.inject_fault("plant_1", alarm_codes::INTERNAL_FAULT, AlarmSeverity::Fault, 60.0);
+        state.set_data("plant_1", &rated(100.0), &sample(&layout, 600.0, 25.0), 0.0);
+        let raised = &state.find_events(Some(&EventKind::AlarmRaised), Some("plant_1"), 1)[0];
+        assert_eq!(raised.payload.as_ref().unwrap()["code"], alarm_codes::INTERNAL_FAULT);
+        assert!(state.find_events(Some(&EventKind::AlarmRaised), Some("plant_2"), 10).is_empty());
 
-            #[cfg(feature = "verbose_log")]
-            println!(
-                "[UPDATE] {} | AC {:.2} kW | DC {:.2} kW | eff {:.1}% | L1 {:.1}V | T_inv {:.1}°C | PR {:.2} | flags 0x{:04X}",
-                plant_id, d.power_kw, d.dc_power_kw, d.efficiency_percent,
-                d.voltage_l1_v, d.inverter_temp_c, d.performance_ratio, d.alarm_flags
-            );
+        for i in 0..MAX_EVENT_LOG {
+            state.push_event(None, EventKind::SettingChanged, format!("setting {}", i), None);
         }
+        let log = state.get_events(usize::MAX);
+        assert_eq!(log.len(), MAX_EVENT_LOG);
+        assert_eq!(log[0].message, format!("setting {}", MAX_EVENT_LOG - 1));
+        assert!(state.find_events(Some(&EventKind::PlantOnline), None, 10).is_empty());
     }
 
-    pub fn get_data(&self, plant_id: &str) -> Option<PlantData> {
-        self.plant_data.read().ok()?.get(plant_id).cloned()
+    #[test]
+    fn fault_catalog_covers_every_code_with_its_flags() {
+        assert!(alarm_codes::describe(alarm_codes::NONE).is_none());
+        assert!(alarm_codes::describe(777).is_none());
+        assert_eq!(alarm_codes::describe(401), Some(("OVERTEMPERATURE", AlarmSeverity::Critical)));
+        assert_eq!(alarm_flag_bits::of_code(alarm_codes::ISOLATION_FAULT), alarm_flag_bits::ISOLATION_FAULT);
+        assert_eq!(alarm_flag_bits::of_code(alarm_codes::GROUND_FAULT), alarm_flag_bits::GROUND_FAULT | alarm_flag_bits::LEAKAGE_CURRENT);
+        assert_eq!(alarm_flag_bits::of_code(alarm_codes::INTERNAL_FAULT), 0);
     }
 
-    pub fn get_all_data(&self) -> HashMap<String, PlantData> {
-        self.plant_data.read()
-            .map(|m| m.clone())
-            .unwrap_or_default()
+    #[test]
+    fn voltage_alarm_waits_out_its_delay_and_clears_below_the_hysteresis() {
+        let state = AppState::new(true);
+        state.plant_data.insert("plant_1", PlantData::default());
+        let layout = StringLayout::sized_for(100.0);
+        let feed = |voltage_v: f64| {
+            state.inject_grid_event("plant_1", Disturbance::to(50.0), Some(voltage_v), 60.0);
+            tick(&state);
+            state.set_data("plant_1", &rated(100.0), &sample(&layout, 800.0, 45.0), 0.0);
+            state.get_data("plant_1").unwrap().alarm_flags & alarm_flag_bits::AC_OVERVOLTAGE != 0
+        };
+        let logged = |kind: fn(&EventKind) -> bool| state.get_events(1000).iter()
+            .filter(|e| kind(&e.kind) && e.payload.as_ref().is_some_and(|p| p["code"] == alarm_codes::AC_OVERVOLTAGE))
+            .count();
+        for _ in 0..5 {
+            assert!(!feed(230.0));
+        }
+
+        // A 5 s swell is shorter than the 10 s delay
+        assert!(!feed(256.0) && !feed(256.0));
+        assert!(!feed(230.0));
+        assert_eq!(logged(|k| matches!(k, EventKind::AlarmRaised)), 0);
+
+        // A sustained one raises the alarm once it has lasted 10 s
+        assert!(!feed(256.0) && !feed(256.0));
+        assert!(feed(256.0));
+        let alarm = state.get_active_alarms(Some("plant_1")).into_iter().find(|a| a.code == alarm_codes::AC_OVERVOLTAGE).unwrap();
+        assert_eq!(alarm.severity, AlarmSeverity::Warning);
+        assert_eq!(logged(|k| matches!(k, EventKind::AlarmRaised)), 1);
+
+        // Just inside the 253 V limit the alarm holds; 2 V below it, it clears
+        assert!(feed(252.0) && feed(256.0) && feed(251.5));
+        assert!(!feed(250.0));
+        assert!(!state.get_active_alarms(Some("plant_1")).iter().any(|a| a.code == alarm_codes::AC_OVERVOLTAGE));
+        assert_eq!(logged(|k| matches!(k, EventKind::AlarmRaised)), 1);
+        assert_eq!(logged(|k| matches!(k, EventKind::AlarmCleared)), 1);
     }
-}
 
-// ─── A simple uptime counter that auto-increments (for future use) ───────────
-#[allow(dead_code)]
-pub struct Counter(Arc<AtomicU64>);
-impl Counter {
-    pub fn new() -> Self { Counter(Arc::new(AtomicU64::new(0))) }
-    pub fn inc(&self) { self.0.fetch_add(1, Ordering::Relaxed); }
-    pub fn value(&self) -> u64 { self.0.load(Ordering::Relaxed) }
-}
+    #[test]
+    fn mppt_deviation_compares_the_string_currents_of_the_inputs() {
+        let layout = StringLayout::sized_for(100.0);
+        let bright = layout.operating_point(800.0, 45.0, 1.0);
+        assert_eq!(mppt_deviation_pct(&[bright]), 0.0);
+        assert_eq!(mppt_deviation_pct(&[bright, bright]), 0.0);
+        // One input shaded to half the light: each sits a third off the average
+        let shaded = layout.operating_point(400.0, 45.0, 1.0);
+        assert!((mppt_deviation_pct(&[bright, shaded]) - 100.0 / 3.0).abs() < 1e-9);
+        // At dawn the currents are too small to compare
+        let dawn = [layout.operating_point(20.0, 5.0, 1.0), layout.operating_point(5.0, 5.0, 1.0)];
+        assert_eq!(mppt_deviation_pct(&dawn), 0.0);
+    }
 
-// ─── Combined Axum state ─────────────────────────────────────────────────────
-/// Holds both AppState and Config so that Axum handlers may extract either
-/// via `State<AppState>` or `State<Config>` using the `FromRef` trait.
-#[derive(Clone)]
-pub struct SharedState {
-    pub app:    AppState,
-    pub config: crate::config::Config,
-}
+    #[test]
+    fn scheduled_window_caps_the_output_unless_the_setpoint_is_lower() {
+        let state = AppState::new(true);
+        state.plant_data.insert("plant_1", PlantData::default());
+        steady_grid(&state);
+        let layout = StringLayout::sized_for(100.0);
+        let feed = || {
+            state.set_data("plant_1", &rated(100.0), &sample(&layout, 1000.0, 45.0), 0.0);
+            state.get_data("plant_1").unwrap()
+        };
+        let logged = |kind: fn(&EventKind) -> bool| state.get_events(1000).iter().filter(|e| kind(&e.kind)).count();
+        for _ in 0..40 {
+            feed();
+        }
+        assert!(feed().power_kw > 60.0);
 
-impl axum::extract::FromRef<SharedState> for AppState {
-    fn from_ref(s: &SharedState) -> AppState { s.app.clone() }
-}
+        // Midnight to midnight: the window is always open
+        let all_day = |limit_pct| CurtailmentSchedule::for_config(&[crate::config::CurtailmentWindow {
+            days: vec![], start: chrono::NaiveTime::MIN, end: chrono::NaiveTime::MIN, limit_pct,
+        }]);
+        state.configure_curtailment_schedule("plant_1", all_day(60.0));
+        let capped = feed();
+        assert!((capped.power_kw - 60.0).abs() < 1e-6, "{} kW", capped.power_kw);
+        assert_eq!(capped.status, 3);
+        assert_eq!(logged(|k| matches!(k, EventKind::CurtailmentStart)), 1);
 
-impl axum::extract::FromRef<SharedState> for crate::config::Config {
-    fn from_ref(s: &SharedState) -> crate::config::Config { s.config.clone() }
-}
+        // A lower SCADA setpoint takes over; a higher one does not lift the window
+        state.set_power_limit("plant_1", 40.0, ControlInterface::Modbus);
+        assert!((feed().power_kw - 40.0).abs() < 1e-6);
+        state.set_power_limit("plant_1", 80.0, ControlInterface::Modbus);
+        assert!((feed().power_kw - 60.0).abs() < 1e-6);
+
+        state.configure_curtailment_schedule("plant_1", CurtailmentSchedule::default());
+        assert!((feed().power_kw - 80.0).abs() < 1e-6);
+        assert_eq!(logged(|k| matches!(k, EventKind::CurtailmentEnd)), 1);
+    }
+
+    #[test]
+    fn sensor_noise_and_dropouts_only_touch_the_measured_values() {
+        let state = AppState::new(true);
+        steady_grid(&state);
+        let layout = StringLayout::sized_for(100.0);
+        let feed = || {
+            state.set_data("plant_1", &rated(100.0), &sample(&layout, 800.0, 40.0), 0.0);
+            state.get_data("plant_1").unwrap()
+        };
+        let exact = feed();
+        assert_eq!((exact.poa_irradiance_w_m2, exact.poa_irradiance_true_w_m2, exact.frequency_hz), (800.0, 800.0, 50.0));
+
+        let config = MeasurementNoiseConfig { enabled: true, dropout_probability: 0.0, ..MeasurementNoiseConfig::default() };
+        state.configure_measurement_noise(MeasurementNoise::for_config(&config));
+        let noisy = feed();
+        assert_eq!(noisy.poa_irradiance_true_w_m2, 800.0);
+        assert!(noisy.poa_irradiance_w_m2 != 800.0 && (noisy.poa_irradiance_w_m2 - 800.0).abs() < 100.0, "{}", noisy.poa_irradiance_w_m2);
+        assert!(noisy.frequency_hz != 50.0 && (noisy.frequency_hz - 50.0).abs() < 0.1, "{}", noisy.frequency_hz);
+        // The grid protection still sees the true grid
+        assert_eq!(noisy.protection_cycles, [0; 5]);
+
+        // A logger dropout holds the last readings while the truth moves on
+        let dropout = MeasurementNoiseConfig { dropout_probability: 1.0, dropout_cycles: 3, ..config };
+        state.configure_measurement_noise(MeasurementNoise::for_config(&dropout));
+        for _ in 0..4 {
+            let frozen = feed();
+            assert_eq!(Readings::of(&frozen), Readings::of(&noisy));
+            assert_eq!(frozen.poa_irradiance_true_w_m2, 800.0);
+        }
+
+        state.configure_measurement_noise(None);
+        assert_eq!(feed().poa_irradiance_w_m2, 800.0);
+    }
+
+    #[test]
+    fn scenarios_flag_their_plants_and_log_their_window_once() {
+        let state = AppState::new(true);
+        steady_grid(&state);
+        let layout = StringLayout::sized_for(100.0);
+        let feed = |plant_id: &str| {
+            state.set_data(plant_id, &rated(100.0), &SimulationData { ambient_temp_c: 20.0, solar_elevation_deg: 40.0, ..sample(&layout, 500.0, 30.0) }, 0.0);
+            state.get_data(plant_id).unwrap()
+        };
+        let logged = |kind: fn(&EventKind) -> bool| state.get_events(1000).iter().filter(|e| kind(&e.kind)).count();
+        let now = chrono::Utc::now();
+        let eclipse = |id: &str, plant_id: Option<&str>, from_min, to_min| Scenario::for_config(id.into(), &ScenarioConfig {
+            name:     "eclipse".into(),
+            plant_id: plant_id.map(str::to_string),
+            start:    now + chrono::Duration::minutes(from_min),
+            end:      now + chrono::Duration::minutes(to_min),
+            profile:  vec![[0.0, 1.0], [0.5, 0.1], [1.0, 1.0]],
+        }).unwrap();
+        state.add_scenario(eclipse("now", Some("plant_1"), -60, 60));
+        state.add_scenario(eclipse("later", None, 60, 120));
+
+        // Only the plant in scope is dimmed, and the start is logged once
+        assert!(feed("plant_1").scenario_active);
+        assert!(!feed("plant_2").scenario_active);
+        feed("plant_1");
+        assert_eq!(logged(|k| matches!(k, EventKind::ScenarioStart)), 1);
+        assert!((state.irradiance_factor("plant_1", now) - 0.1).abs() < 1e-9);
+        assert_eq!(state.irradiance_factor("plant_2", now), 1.0);
+
+        // An hour on the first one is over and the fleet-wide one has begun
+        state.advance_scenarios(now + chrono::Duration::minutes(61));
+        assert_eq!(logged(|k| matches!(k, EventKind::ScenarioEnd)), 1);
+        assert_eq!(logged(|k| matches!(k, EventKind::ScenarioStart)), 2);
+        assert_eq!(state.scenarios().iter().map(|s| s.id.as_str()).collect::<Vec<_>>(), ["later"]);
+        assert!(state.scenario_active("plant_2"));
+
+        // Cancelling a running scenario ends it at once
+        assert!(state.cancel_scenario("later"));
+        assert!(!state.cancel_scenario("later"));
+        assert_eq!(logged(|k| matches!(k, EventKind::ScenarioEnd)), 2);
+        assert!(!feed("plant_2").scenario_active);
+    }
+
+    #[test]
+    fn alarm_changes_and_events_reach_the_subscribers_and_laggards_miss_the_oldest() {
+        let state = AppState::new(true);
+        let mut rx = state.subscribe_notifications();
+        state.raise_alarm("plant_1", alarm_codes::AC_OVERVOLTAGE, AlarmSeverity::Warning, "test");
+        state.clear_alarm("plant_1", alarm_codes::AC_OVERVOLTAGE);
+
+        let mut received = Vec::new();
+        while let Ok(notification) = rx.try_recv() {
+            received.push(match notification {
+                Notification::Alarm(a) => format!("alarm {} active={}", a.code, a.active),
+                Notification::Event(e) => format!("event {:?}", e.kind),
+            });
+        }
+        assert_eq!(received, [
+            "alarm 101 active=true", "event AlarmRaised",
+            "alarm 101 active=false", "event AlarmCleared",
+        ]);
 
+        // A subscriber that falls behind is told how much it missed
+        for i in 0..NOTIFICATION_BUFFER + 10 {
+            state.push_event(None, EventKind::SettingChanged, format!("event {i}"), None);
+        }
+        assert!(matches!(rx.try_recv(), Err(broadcast::error::TryRecvError::Lagged(10))));
+        match rx.try_recv() {
+            Ok(Notification::Event(e)) => assert_eq!(e.message, "event 10"),
+            other => panic!("{other:?}"),
+        }
+    }
+}
@@ -0,0 +1,82 @@
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::watch;
+
+/// Upper bound for draining connections and stopping tasks once shutdown starts.
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Process-wide shutdown signal, cloned into every long-running task.
+#[derive(Clone, Debug)]
+pub struct Shutdown {
+    tx: Arc<watch::Sender<bool>>,
+}
+
+impl Shutdown {
+    pub fn new() -> Self {
+        Self { tx: Arc::new(watch::Sender::new(false)) }
+    }
+
+    /// Start shutting down; every `wait()` resolves.
+    pub fn trigger(&self) {
+        self.tx.send_replace(true);
+    }
+
+    /// Whether shutdown has been triggered.
+    pub fn is_triggered(&self) -> bool {
+        *self.tx.borrow()
+    }
+
+    /// Resolves once shutdown has been triggered (immediately if it already was).
+    pub async fn wait(&self) {
+        let mut rx = self.tx.subscribe();
+        let _ = rx.wait_for(|&triggered| triggered).await;
+    }
+
+    /// Signal of its own that also fires with this one: stops a single task
+    /// (a deleted plant's updates, its Modbus port) without stopping the process.
+    pub fn child(&self) -> Shutdown {
+        let child = Shutdown::new();
+        let (parent, forward) = (self.clone(), child.clone());
+        tokio::spawn(async move {
+            tokio::select! {
+                _ = parent.wait()  => forward.trigger(),
+                _ = forward.wait() => {}
+            }
+        });
+        child
+    }
+}
+
+impl Default for Shutdown {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Trigger `shutdown` on SIGINT (Ctrl-C) or SIGTERM.
+pub async fn listen_for_signals(shutdown: Shutdown) {
+    let ctrl_c = async {
+        if let Err(e) = tokio::signal::ctrl_c().await {
+            eprintln!("[SHUTDOWN] Cannot listen for Ctrl-C: {}", e);
+            std::future::pending::<()>().await;
+        }
+    };
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => { sigterm.recv().await; }
+            Err(e) => {
+                eprintln!("[SHUTDOWN] Cannot listen for SIGTERM: {}", e);
+                std::future::pending::<()>().await;
+            }
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c    => println!("[SHUTDOWN] SIGINT received"),
+        _ = terminate => println!("[SHUTDOWN] SIGTERM received"),
+    }
+    shutdown.trigger();
+}
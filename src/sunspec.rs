@@ -0,0 +1,426 @@
+use std::ops::Range;
+
+use crate::config::{PlantConfig, ProfileKind};
+use crate::models::power::{alarm_flag_bits, PlantData};
+use crate::profiles::{ProfilePoint, RegisterProfile};
+
+// ─── SunSpec block layout ─────────────────────────────────────────────────────
+// 40000  "SunS" marker (2 registers)
+// 40002  Common Model 1             (ID, L=66, 66 data registers)
+// 40070  Three-phase inverter 103   (ID, L=50, 50 data registers)
+// 40122  End model                  (0xFFFF, 0)
+
+pub const SUNSPEC_BASE: u16 = 40000;
+
+const SUNS_MARKER: [u16; 2]     = [0x5375, 0x6e53];  // "SunS"
+const MODEL_COMMON: u16         = 1;
+const MODEL_COMMON_LEN: u16     = 66;
+const MODEL_INVERTER_3PH: u16   = 103;
+const MODEL_INVERTER_LEN: u16   = 50;
+const MODEL_END: u16            = 0xFFFF;
+
+/// Register indices relative to SUNSPEC_BASE.
+const COMMON_HEADER: usize   = 2;
+const COMMON_DATA: usize     = COMMON_HEADER + 2;
+const INVERTER_HEADER: usize = COMMON_DATA + MODEL_COMMON_LEN as usize;
+const INVERTER_DATA: usize   = INVERTER_HEADER + 2;
+const END_HEADER: usize      = INVERTER_DATA + MODEL_INVERTER_LEN as usize;
+
+/// Registers occupied by the whole SunSpec block.
+pub const SUNSPEC_LEN: u16 = END_HEADER as u16 + 2;
+
+// ── Common Model 1 points (offset within model data) ──
+const C_MN: usize  = 0;   // string[16]  manufacturer
+const C_MD: usize  = 16;  // string[16]  model
+const C_OPT: usize = 32;  // string[8]   options
+const C_VR: usize  = 40;  // string[8]   version
+const C_SN: usize  = 48;  // string[16]  serial number
+const C_DA: usize  = 64;  // uint16      device address
+const C_PAD: usize = 65;  // pad
+
+// ── Inverter Model 103 points (offset within model data) ──
+const I_A: usize        = 0;   // uint16  total AC current
+const I_APH_A: usize    = 1;
+const I_APH_B: usize    = 2;
+const I_APH_C: usize    = 3;
+const I_A_SF: usize     = 4;
+const I_PPV_AB: usize   = 5;   // uint16  line-line voltages
+const I_PPV_BC: usize   = 6;
+const I_PPV_CA: usize   = 7;
+const I_PHV_A: usize    = 8;   // uint16  phase voltages
+const I_PHV_B: usize    = 9;
+const I_PHV_C: usize    = 10;
+const I_V_SF: usize     = 11;
+const I_W: usize        = 12;  // int16   AC power
+const I_W_SF: usize     = 13;
+const I_HZ: usize       = 14;  // uint16  line frequency
+const I_HZ_SF: usize    = 15;
+const I_VA: usize       = 16;  // int16   apparent power
+const I_VA_SF: usize    = 17;
+const I_VAR: usize      = 18;  // int16   reactive power
+const I_VAR_SF: usize   = 19;
+const I_PF: usize       = 20;  // int16   power factor (%)
+const I_PF_SF: usize    = 21;
+const I_WH: usize       = 22;  // acc32   lifetime energy
+const I_WH_SF: usize    = 24;
+const I_DCA: usize      = 25;  // uint16  DC current
+const I_DCA_SF: usize   = 26;
+const I_DCV: usize      = 27;  // uint16  DC voltage
+const I_DCV_SF: usize   = 28;
+const I_DCW: usize      = 29;  // int16   DC power
+const I_DCW_SF: usize   = 30;
+const I_TMP_CAB: usize  = 31;  // int16   temperatures
+const I_TMP_SNK: usize  = 32;
+const I_TMP_TRNS: usize = 33;
+const I_TMP_OT: usize   = 34;
+const I_TMP_SF: usize   = 35;
+const I_ST: usize       = 36;  // enum16  operating state
+const I_ST_VND: usize   = 37;  // enum16  vendor state (simulator status)
+const I_EVT1: usize     = 38;  // bitfield32
+const I_EVT_VND1: usize = 42;  // bitfield32  simulator alarm_flags
+const I_EVT_VND2: usize = 44;  // bitfield32  simulator fault_code
+
+/// "Not implemented" values defined by the SunSpec specification.
+const NOT_IMPL_I16: u16 = 0x8000;
+
+// ── Operating states (St) ──
+const ST_OFF: u16       = 1;
+const ST_STARTING: u16  = 3;
+const ST_MPPT: u16      = 4;
+const ST_THROTTLED: u16 = 5;
+const ST_FAULT: u16     = 7;
+
+// ── Event bits (Evt1) ──
+const EVT_GROUND_FAULT: u32    = 1 << 0;
+const EVT_DC_OVER_VOLT: u32    = 1 << 1;
+const EVT_GRID_DISCONNECT: u32 = 1 << 4;
+const EVT_MANUAL_SHUTDOWN: u32 = 1 << 6;
+const EVT_OVER_TEMP: u32       = 1 << 7;
+const EVT_OVER_FREQUENCY: u32  = 1 << 8;
+const EVT_UNDER_FREQUENCY: u32 = 1 << 9;
+const EVT_AC_OVER_VOLT: u32    = 1 << 10;
+const EVT_AC_UNDER_VOLT: u32   = 1 << 11;
+
+const F_GRID_NOM: f64 = 50.0;
+
+/// Fixed scale factors of one device, sized from the plant nominal power so
+/// that every value fits its 16-bit register.
+#[derive(Clone, Debug)]
+struct ScaleFactors {
+    a:   i16,
+    w:   i16,
+    dca: i16,
+}
+
+const V_SF: i16   = -1;
+const HZ_SF: i16  = -2;
+const PF_SF: i16  = -1;
+const WH_SF: i16  = 0;
+const DCV_SF: i16 = -1;
+const TMP_SF: i16 = -1;
+
+impl ScaleFactors {
+    fn for_nominal_kw(nominal_kw: f64) -> Self {
+        let nominal_w = nominal_kw * 1000.0;
+        Self {
+            a:   fit_scale(nominal_w / (3.0 * 230.0) * 1.5, 65_534.0),
+            w:   fit_scale(nominal_w * 1.5, 32_767.0),
+            dca: fit_scale(nominal_w / 400.0 * 1.5, 65_534.0),
+        }
+    }
+}
+
+/// Smallest scale factor (≥ -2) at which `max` fits below `limit`.
+fn fit_scale(max: f64, limit: f64) -> i16 {
+    let mut sf = -2;
+    while sf < 9 && max / 10f64.powi(sf as i32) > limit {
+        sf += 1;
+    }
+    sf
+}
+
+fn scaled(v: f64, sf: i16) -> f64 {
+    (v / 10f64.powi(sf as i32)).round()
+}
+
+fn uint16(v: f64, sf: i16) -> u16 {
+    scaled(v, sf).clamp(0.0, 65_534.0) as u16
+}
+
+fn int16(v: f64, sf: i16) -> u16 {
+    scaled(v, sf).clamp(-32_767.0, 32_767.0) as i16 as u16
+}
+
+/// Write a 32-bit value big-endian (high word first) as SunSpec requires.
+fn put_u32(regs: &mut [u16], v: u32) {
+    regs[0] = (v >> 16) as u16;
+    regs[1] = (v & 0xFFFF) as u16;
+}
+
+/// Write `s` as a NUL-padded SunSpec string (two ASCII bytes per register).
+fn put_str(regs: &mut [u16], s: &str) {
+    let bytes = s.as_bytes();
+    for (i, reg) in regs.iter_mut().enumerate() {
+        let hi = bytes.get(2 * i).copied().unwrap_or(0);
+        let lo = bytes.get(2 * i + 1).copied().unwrap_or(0);
+        *reg = u16::from_be_bytes([hi, lo]);
+    }
+}
+
+/// Map the simulator status enum to the SunSpec operating state.
+fn operating_state(status: u16) -> u16 {
+    match status {
+        1 | 5 => ST_MPPT,
+        2     => ST_FAULT,
+        3     => ST_THROTTLED,
+        4     => ST_STARTING,
+        _     => ST_OFF,
+    }
+}
+
+/// Translate simulator alarm flags into SunSpec Evt1 bits.
+fn events(data: &PlantData) -> u32 {
+    let flags = data.alarm_flags;
+    let mut evt = 0;
+    if flags & (alarm_flag_bits::GROUND_FAULT | alarm_flag_bits::ISOLATION_FAULT | alarm_flag_bits::LEAKAGE_CURRENT) != 0 {
+        evt |= EVT_GROUND_FAULT;
+    }
+    if flags & alarm_flag_bits::DC_OVERVOLTAGE != 0 { evt |= EVT_DC_OVER_VOLT; }
+    if flags & (alarm_flag_bits::GRID_DISCONNECT | alarm_flag_bits::ROCOF_TRIP) != 0 {
+        evt |= EVT_GRID_DISCONNECT;
+    }
+    if flags & alarm_flag_bits::OVERTEMPERATURE != 0 { evt |= EVT_OVER_TEMP; }
+    if flags & alarm_flag_bits::FREQUENCY_FAULT != 0 {
+        evt |= if data.frequency_hz >= F_GRID_NOM { EVT_OVER_FREQUENCY } else { EVT_UNDER_FREQUENCY };
+    }
+    if flags & alarm_flag_bits::AC_OVERVOLTAGE != 0 { evt |= EVT_AC_OVER_VOLT; }
+    if flags & alarm_flag_bits::AC_UNDERVOLTAGE != 0 { evt |= EVT_AC_UNDER_VOLT; }
+    if !data.inverter_enabled { evt |= EVT_MANUAL_SHUTDOWN; }
+    evt
+}
+
+/// Line-to-line voltage from two phase voltages 120° apart.
+fn line_voltage(va: f64, vb: f64) -> f64 {
+    (va * va + vb * vb + va * vb).sqrt()
+}
+
+/// One plant served with the SunSpec register model.
+#[derive(Clone, Debug)]
+pub struct SunSpecDevice {
+    pub plant_id:   String,
+    manufacturer:   String,
+    model:          String,
+    serial_number:  String,
+    device_address: u16,
+    sf:             ScaleFactors,
+}
+
+impl SunSpecDevice {
+    pub fn new(plant: &PlantConfig) -> Self {
+        let default_manufacturer = match plant.modbus_mapping.profile {
+            ProfileKind::Fronius => "Fronius",
+            _                    => "Solar Panel Simulator",
+        };
+        Self {
+            plant_id:       plant.id.clone(),
+            manufacturer:   plant.manufacturer.clone().unwrap_or_else(|| default_manufacturer.to_string()),
+            model:          plant.model.clone().unwrap_or_else(|| plant.name.clone()),
+            serial_number:  plant.serial_number.clone().unwrap_or_else(|| plant.id.clone()),
+            device_address: plant.modbus_mapping.unit_id.unwrap_or(1) as u16,
+            sf:             ScaleFactors::for_nominal_kw(plant.nominal_power_kw),
+        }
+    }
+}
+
+impl RegisterProfile for SunSpecDevice {
+    fn name(&self) -> &'static str {
+        "SunSpec"
+    }
+
+    fn plant_id(&self) -> &str {
+        &self.plant_id
+    }
+
+    fn block(&self) -> Range<u16> {
+        SUNSPEC_BASE..SUNSPEC_BASE + SUNSPEC_LEN
+    }
+
+    /// Full register image of the block for one data snapshot.
+    fn image(&self, data: &PlantData) -> Vec<u16> {
+        let mut regs = vec![0u16; SUNSPEC_LEN as usize];
+        regs[0..2].copy_from_slice(&SUNS_MARKER);
+
+        // ── Common Model 1 ──
+        regs[COMMON_HEADER]     = MODEL_COMMON;
+        regs[COMMON_HEADER + 1] = MODEL_COMMON_LEN;
+        let c = &mut regs[COMMON_DATA..INVERTER_HEADER];
+        put_str(&mut c[C_MN..C_MD],   &self.manufacturer);
+        put_str(&mut c[C_MD..C_OPT],  &self.model);
+        put_str(&mut c[C_OPT..C_VR],  "");
+        put_str(&mut c[C_VR..C_SN],   env!("CARGO_PKG_VERSION"));
+        put_str(&mut c[C_SN..C_DA],   &self.serial_number);
+        c[C_DA]  = self.device_address;
+        c[C_PAD] = NOT_IMPL_I16;
+
+        // ── Inverter Model 103 ──
+        regs[INVERTER_HEADER]     = MODEL_INVERTER_3PH;
+        regs[INVERTER_HEADER + 1] = MODEL_INVERTER_LEN;
+        let sf = &self.sf;
+        let m = &mut regs[INVERTER_DATA..END_HEADER];
+        let total_a = data.current_l1_a + data.current_l2_a + data.current_l3_a;
+        m[I_A]          = uint16(total_a, sf.a);
+        m[I_APH_A]      = uint16(data.current_l1_a, sf.a);
+        m[I_APH_B]      = uint16(data.current_l2_a, sf.a);
+        m[I_APH_C]      = uint16(data.current_l3_a, sf.a);
+        m[I_A_SF]       = sf.a as u16;
+        m[I_PPV_AB]     = uint16(line_voltage(data.voltage_l1_v, data.voltage_l2_v), V_SF);
+        m[I_PPV_BC]     = uint16(line_voltage(data.voltage_l2_v, data.voltage_l3_v), V_SF);
+        m[I_PPV_CA]     = uint16(line_voltage(data.voltage_l3_v, data.voltage_l1_v), V_SF);
+        m[I_PHV_A]      = uint16(data.voltage_l1_v, V_SF);
+        m[I_PHV_B]      = uint16(data.voltage_l2_v, V_SF);
+        m[I_PHV_C]      = uint16(data.voltage_l3_v, V_SF);
+        m[I_V_SF]       = V_SF as u16;
+        m[I_W]          = int16(data.power_kw * 1000.0, sf.w);
+        m[I_W_SF]       = sf.w as u16;
+        m[I_HZ]         = uint16(data.frequency_hz, HZ_SF);
+        m[I_HZ_SF]      = HZ_SF as u16;
+        m[I_VA]         = int16(data.apparent_power_kva * 1000.0, sf.w);
+        m[I_VA_SF]      = sf.w as u16;
+        m[I_VAR]        = int16(data.reactive_power_kvar * 1000.0, sf.w);
+        m[I_VAR_SF]     = sf.w as u16;
+        m[I_PF]         = int16(data.power_factor * 100.0, PF_SF);
+        m[I_PF_SF]      = PF_SF as u16;
+        // acc32 counters roll over like a real meter
+        put_u32(&mut m[I_WH..I_WH + 2], (data.total_energy_kwh * 1000.0).round() as u64 as u32);
+        m[I_WH_SF]      = WH_SF as u16;
+        m[I_DCA]        = uint16(data.dc_current_a, sf.dca);
+        m[I_DCA_SF]     = sf.dca as u16;
+        m[I_DCV]        = uint16(data.dc_voltage_v, DCV_SF);
+        m[I_DCV_SF]     = DCV_SF as u16;
+        m[I_DCW]        = int16(data.dc_power_kw * 1000.0, sf.w);
+        m[I_DCW_SF]     = sf.w as u16;
+        m[I_TMP_CAB]    = int16(data.inverter_temp_c, TMP_SF);
+        m[I_TMP_SNK]    = int16(data.inverter_temp_c, TMP_SF);
+        m[I_TMP_TRNS]   = NOT_IMPL_I16;
+        m[I_TMP_OT]     = NOT_IMPL_I16;
+        m[I_TMP_SF]     = TMP_SF as u16;
+        m[I_ST]         = operating_state(data.status);
+        m[I_ST_VND]     = data.status;
+        put_u32(&mut m[I_EVT1..I_EVT1 + 2], events(data));
+        put_u32(&mut m[I_EVT_VND1..I_EVT_VND1 + 2], data.alarm_flags);
+        put_u32(&mut m[I_EVT_VND2..I_EVT_VND2 + 2], data.fault_code as u32);
+
+        // ── End model ──
+        regs[END_HEADER]     = MODEL_END;
+        regs[END_HEADER + 1] = 0;
+        regs
+    }
+
+    fn points(&self) -> Vec<ProfilePoint> {
+        SUNSPEC_POINTS.iter().map(|&(offset, length, data_type, description, unit)| ProfilePoint {
+            address: SUNSPEC_BASE + offset,
+            length,
+            data_type,
+            description,
+            unit,
+            scale: 1.0,
+        }).collect()
+    }
+}
+
+/// Main points published by `/api/modbus/info` for SunSpec plants:
+/// (offset from SUNSPEC_BASE, length, type, description, unit).
+/// Scaled points carry their own scale-factor register.
+const SUNSPEC_POINTS: &[(u16, u16, &str, &str, &str)] = &[
+    (0,                                   2, "string",  "SunSpec marker \"SunS\"",          "—"),
+    (COMMON_HEADER as u16,                2, "uint16",  "Common Model 1 header (ID, L)",    "—"),
+    ((COMMON_DATA + C_MN) as u16,        16, "string",  "Manufacturer (Mn)",                "—"),
+    ((COMMON_DATA + C_MD) as u16,        16, "string",  "Model (Md)",                       "—"),
+    ((COMMON_DATA + C_SN) as u16,        16, "string",  "Serial number (SN)",               "—"),
+    (INVERTER_HEADER as u16,              2, "uint16",  "Inverter Model 103 header (ID, L)", "—"),
+    ((INVERTER_DATA + I_A) as u16,        1, "uint16",  "AC current (A, scaled by A_SF)",   "A"),
+    ((INVERTER_DATA + I_W) as u16,        1, "int16",   "AC power (W, scaled by W_SF)",     "W"),
+    ((INVERTER_DATA + I_W_SF) as u16,     1, "sunssf",  "AC power scale factor (W_SF)",     "—"),
+    ((INVERTER_DATA + I_HZ) as u16,       1, "uint16",  "Line frequency (Hz, scaled)",      "Hz"),
+    ((INVERTER_DATA + I_WH) as u16,       2, "acc32",   "Lifetime energy (WH)",             "Wh"),
+    ((INVERTER_DATA + I_ST) as u16,       1, "enum16",  "Operating state (St)",             "—"),
+    ((INVERTER_DATA + I_EVT1) as u16,     2, "bitfield32", "Event flags (Evt1)",            "—"),
+    (END_HEADER as u16,                   2, "uint16",  "End model (0xFFFF, 0)",            "—"),
+];
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sunspec_plant() -> PlantConfig {
+        serde_json::from_value(serde_json::json!({
+            "id": "plant_9", "name": "Turin Roof", "latitude": 45.0, "longitude": 7.0,
+            "nominal_power_kw": 1000.0, "timezone": "Europe/Rome",
+            "manufacturer": "ACME", "serial_number": "SN-0042",
+            "modbus_mapping": { "unit_id": 3, "profile": "sunspec" }
+        })).unwrap()
+    }
+
+    fn read_str(regs: &[u16]) -> String {
+        regs.iter()
+            .flat_map(|r| r.to_be_bytes())
+            .take_while(|&b| b != 0)
+            .map(char::from)
+            .collect()
+    }
+
+    fn scale(raw: f64, sf: u16) -> f64 {
+        raw * 10f64.powi(sf as i16 as i32)
+    }
+
+    #[test]
+    fn models_are_discoverable_by_walking_headers() {
+        let device = SunSpecDevice::new(&sunspec_plant());
+        let regs = device.image(&PlantData::default());
+        assert_eq!(&regs[0..2], &SUNS_MARKER);
+
+        // Walk the model chain as a SunSpec client would
+        let mut models = Vec::new();
+        let mut at = 2;
+        while regs[at] != MODEL_END {
+            models.push((regs[at], at));
+            at += 2 + regs[at + 1] as usize;
+        }
+        assert_eq!(models, vec![(MODEL_COMMON, COMMON_HEADER), (MODEL_INVERTER_3PH, INVERTER_HEADER)]);
+        assert_eq!(at + 2, SUNSPEC_LEN as usize);
+
+        let common = &regs[COMMON_DATA..];
+        assert_eq!(read_str(&common[C_MN..C_MD]), "ACME");
+        assert_eq!(read_str(&common[C_MD..C_OPT]), "Turin Roof");
+        assert_eq!(read_str(&common[C_SN..C_DA]), "SN-0042");
+        assert_eq!(common[C_DA], 3);
+    }
+
+    #[test]
+    fn inverter_model_scales_power_energy_and_status() {
+        let device = SunSpecDevice::new(&sunspec_plant());
+        let data = PlantData {
+            power_kw: 812.5, frequency_hz: 50.02, total_energy_kwh: 1.25e6,
+            status: 3, alarm_flags: alarm_flag_bits::OVERTEMPERATURE, ..PlantData::default()
+        };
+        let regs = device.image(&data);
+        let m = &regs[INVERTER_DATA..];
+
+        let w = scale(m[I_W] as i16 as f64, m[I_W_SF]);
+        assert!((w - 812_500.0).abs() <= scale(1.0, m[I_W_SF]), "W = {}", w);
+        assert!((scale(m[I_HZ] as f64, m[I_HZ_SF]) - 50.02).abs() < 1e-9);
+        let wh = ((m[I_WH] as u32) << 16) | m[I_WH + 1] as u32;
+        assert_eq!(wh, 1_250_000_000);
+        assert_eq!(m[I_ST], ST_THROTTLED);
+        assert_eq!(m[I_ST_VND], 3);
+        assert_eq!(((m[I_EVT1] as u32) << 16) | m[I_EVT1 + 1] as u32, EVT_OVER_TEMP);
+        assert_eq!(m[I_TMP_TRNS], NOT_IMPL_I16);
+    }
+
+    #[test]
+    fn scale_factors_keep_large_plants_in_range() {
+        let sf = ScaleFactors::for_nominal_kw(50_000.0);
+        assert!(scaled(50_000_000.0 * 1.5, sf.w) <= 32_767.0);
+        assert_eq!(fit_scale(100.0, 32_767.0), -2);
+    }
+}
@@ -0,0 +1,59 @@
+#![cfg(unix)]
+
+mod common;
+
+use common::{request, Simulator};
+
+/// Status code of one request with an optional extra header line.
+fn status(port: u16, method: &str, path: &str, auth: Option<&str>, body: &str) -> u16 {
+    request(port, method, path, auth, body).unwrap().0
+}
+
+#[test]
+fn api_keys_gate_the_api_by_role() {
+    let sim = Simulator::start("auth", |http_port, modbus_port| format!(r#"{{
+  "server":       {{ "port": {http_port}, "bind_address": "127.0.0.1" }},
+  "modbus":       {{ "port": {modbus_port}, "bind_address": "127.0.0.1" }},
+  "offline_mode": true,
+  "auth": {{
+    "keys": [
+      {{ "key": "dashboard-key", "role": "read" }},
+      {{ "key": "operator-key", "role": "control", "name": "operator" }}
+    ],
+    "public_health": true
+  }},
+  "plants": [
+    {{
+      "id": "plant_1",
+      "name": "Auth Test",
+      "latitude": 45.07,
+      "longitude": 7.33,
+      "nominal_power_kw": 100.0,
+      "timezone": "Europe/Rome",
+      "modbus_mapping": {{ "base_address": 0 }}
+    }}
+  ]
+}}"#));
+    let http_port = sim.http_port;
+
+    // /health is exempted: it answers without a key
+    assert_eq!(status(http_port, "GET", "/health", None, ""), 200);
+
+    let read = Some("Authorization: Bearer dashboard-key");
+    let control = Some("X-API-Key: operator-key");
+    let get = |auth| status(http_port, "GET", "/api/plants", auth, "");
+    let post = |auth| status(http_port, "POST", "/api/settings/simulation-seed", auth, r#"{"seed": 7}"#);
+
+    // Missing or invalid key: 401
+    assert_eq!(get(None), 401);
+    assert_eq!(get(Some("Authorization: Bearer guess")), 401);
+    assert_eq!(status(http_port, "GET", "/metrics", None, ""), 401);
+    // A read key reads but may not change anything: 403
+    assert_eq!(get(read), 200);
+    assert_eq!(post(read), 403);
+    assert_eq!(status(http_port, "POST", "/api/plants/plant_1/control", read, r#"{"action": "stop"}"#), 403);
+    // A control key does both
+    assert_eq!(get(control), 200);
+    assert_eq!(post(control), 200);
+    assert_eq!(status(http_port, "GET", "/metrics", control, ""), 200);
+}
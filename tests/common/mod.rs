@@ -0,0 +1,143 @@
+//! Harness shared by the integration tests: start the simulator binary on a
+//! config of its own and talk raw HTTP/1.1 to it.
+
+// Each test crate uses its own subset of the helpers
+#![allow(dead_code)]
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, ExitStatus};
+use std::time::{Duration, Instant};
+
+/// Startup attempts before giving up on finding free ports.
+const START_ATTEMPTS: usize = 5;
+
+/// Fresh directory for one test, named after it.
+pub fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("solar-sim-{}-{}", name, std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Start the simulator in `dir` on the `config.json` there; its output goes to
+/// `simulator.log` in the same directory.
+pub fn spawn(dir: &Path) -> Child {
+    let log = File::create(dir.join("simulator.log")).unwrap();
+    Command::new(env!("CARGO_BIN_EXE_solar-panel-sim"))
+        .current_dir(dir)
+        .stdout(log.try_clone().unwrap())
+        .stderr(log)
+        .spawn()
+        .expect("simulator starts")
+}
+
+/// Stop `child` with SIGTERM and wait for it to exit, failing after 15 s.
+pub fn sigterm(child: &mut Child) -> ExitStatus {
+    let killed = Command::new("kill")
+        .args(["-TERM", &child.id().to_string()])
+        .status()
+        .unwrap();
+    assert!(killed.success());
+
+    let deadline = Instant::now() + Duration::from_secs(15);
+    loop {
+        if let Some(status) = child.try_wait().unwrap() {
+            return status;
+        }
+        if Instant::now() > deadline {
+            let _ = child.kill();
+            panic!("simulator did not exit within 15 s of SIGTERM");
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+fn free_port() -> u16 {
+    TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port()
+}
+
+/// A running simulator with its HTTP and Modbus TCP ports. Dropping it kills
+/// the process, even when an assertion fails halfway, and removes its directory.
+pub struct Simulator {
+    child:           Child,
+    pub http_port:   u16,
+    pub modbus_port: u16,
+    pub dir:         PathBuf,
+}
+
+impl Simulator {
+    /// Start the simulator on the config `config(http_port, modbus_port)`
+    /// returns, and wait until both servers listen.
+    ///
+    /// The ports are free when picked but another test may bind them before
+    /// the simulator does; the simulator then reports the address in use and
+    /// is restarted on new ports.
+    pub fn start(name: &str, config: impl Fn(u16, u16) -> String) -> Simulator {
+        let dir = temp_dir(name);
+        for _ in 0..START_ATTEMPTS {
+            let (http_port, modbus_port) = (free_port(), free_port());
+            std::fs::write(dir.join("config.json"), config(http_port, modbus_port)).unwrap();
+            let mut child = spawn(&dir);
+            if wait_until_listening(&mut child, &dir, &[http_port, modbus_port]) {
+                return Simulator { child, http_port, modbus_port, dir };
+            }
+            let _ = child.kill();
+            let _ = child.wait();
+        }
+        panic!("simulator could not bind its ports in {} attempts", START_ATTEMPTS);
+    }
+}
+
+/// True once the simulator in `dir` accepts connections on all `ports`, false
+/// if it could not bind one of them.
+fn wait_until_listening(child: &mut Child, dir: &Path, ports: &[u16]) -> bool {
+    let log = || std::fs::read_to_string(dir.join("simulator.log")).unwrap_or_default();
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let port_taken = log().contains("Address already in use");
+        if port_taken || child.try_wait().unwrap().is_some() {
+            assert!(port_taken, "simulator exited on startup:\n{}", log());
+            return false;
+        }
+        if ports.iter().all(|&port| TcpStream::connect(("127.0.0.1", port)).is_ok()) {
+            // A port another test took accepts connections too
+            return !log().contains("Address already in use");
+        }
+        assert!(Instant::now() < deadline, "simulator not up within 10 s:\n{}", log());
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+impl Drop for Simulator {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_dir_all(&self.dir);
+    }
+}
+
+/// Send one HTTP/1.1 request with an optional extra header line and return
+/// the status code and the body.
+pub fn request(port: u16, method: &str, path: &str, header: Option<&str>, body: &str) -> std::io::Result<(u16, String)> {
+    let mut stream = TcpStream::connect(("127.0.0.1", port))?;
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+    let header = header.map(|h| format!("{h}\r\n")).unwrap_or_default();
+    write!(
+        stream,
+        "{method} {path} HTTP/1.1\r\nHost: 127.0.0.1\r\n{header}Content-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        body.len()
+    )?;
+    let mut response = String::new();
+    stream.read_to_string(&mut response)?;
+    let status = response.split(' ').nth(1).and_then(|s| s.parse().ok()).unwrap_or(0);
+    let body = response.split_once("\r\n\r\n").map_or("", |(_, b)| b).to_string();
+    Ok((status, body))
+}
+
+/// `request` without an extra header.
+pub fn http(port: u16, method: &str, path: &str, body: &str) -> std::io::Result<(u16, String)> {
+    request(port, method, path, None, body)
+}
@@ -0,0 +1,125 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+use common::{http, Simulator};
+
+/// Upper bound for any wait, so a stuck simulator fails instead of hanging.
+const GIVE_UP: Duration = Duration::from_secs(30);
+
+const REG_STATUS: u16      = 10;
+const REG_FAULT_CODE: u16  = 55;
+const REG_ALARM_FLAGS: u16 = 56;
+
+/// Read `count` input registers from `address` on unit 1 (Modbus TCP, FC 0x04).
+fn read_input_registers(port: u16, address: u16, count: u16) -> Vec<u16> {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).expect("Modbus server listening");
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let [ah, al] = address.to_be_bytes();
+    let [ch, cl] = count.to_be_bytes();
+    stream.write_all(&[0, 1, 0, 0, 0, 6, 1, 0x04, ah, al, ch, cl]).unwrap();
+    // MBAP header, function code and byte count
+    let mut header = [0u8; 9];
+    stream.read_exact(&mut header).unwrap();
+    assert_eq!(header[7], 0x04, "Modbus exception {:?}", header);
+    let mut data = vec![0u8; header[8] as usize];
+    stream.read_exact(&mut data).unwrap();
+    data.chunks(2).map(|w| u16::from_be_bytes([w[0], w[1]])).collect()
+}
+
+/// Status, fault code and alarm flags of plant_1 as a Modbus master sees them.
+fn inverter_state(port: u16) -> (u16, u16, u16) {
+    let regs = read_input_registers(port, REG_STATUS, REG_ALARM_FLAGS - REG_STATUS + 1);
+    let at = |reg: u16| regs[(reg - REG_STATUS) as usize];
+    (at(REG_STATUS), at(REG_FAULT_CODE), at(REG_ALARM_FLAGS))
+}
+
+/// Time of plant_1's last update as REST reports it, once it has published.
+fn last_update(http_port: u16) -> Option<u64> {
+    let (status, body) = http(http_port, "GET", "/api/plants/plant_1/power", "").ok()?;
+    if status != 200 {
+        return None;
+    }
+    let json: serde_json::Value = serde_json::from_str(&body).expect("power is valid JSON");
+    json["data"]["last_update_unix"].as_u64()
+}
+
+/// Poll until `done` holds on the inverter state. It must hold once the plant
+/// has completed a full update after the call, i.e. REST has reported two new
+/// update times (the first may have been in flight when the change was made).
+fn within_one_cycle(http_port: u16, modbus_port: u16, what: &str, done: impl Fn((u16, u16, u16)) -> bool) {
+    let give_up = Instant::now() + GIVE_UP;
+    let mut seen = last_update(http_port);
+    let mut updates = 0;
+    loop {
+        let state = inverter_state(modbus_port);
+        if done(state) {
+            return;
+        }
+        let latest = last_update(http_port);
+        if latest != seen {
+            seen = latest;
+            updates += 1;
+        }
+        assert!(updates < 2 && Instant::now() < give_up, "{what}: inverter still at (status, fault, flags) = {state:?}");
+        std::thread::sleep(Duration::from_millis(100));
+    }
+}
+
+#[test]
+fn an_injected_fault_reaches_modbus_within_one_cycle() {
+    let sim = Simulator::start("faults", |http_port, modbus_port| format!(r#"{{
+  "server":       {{ "port": {http_port}, "bind_address": "127.0.0.1" }},
+  "modbus":       {{ "port": {modbus_port}, "bind_address": "127.0.0.1" }},
+  "offline_mode": true,
+  "plants": [
+    {{
+      "id": "plant_1",
+      "name": "Fault Injection Test",
+      "latitude": 45.07,
+      "longitude": 7.33,
+      "nominal_power_kw": 100.0,
+      "timezone": "Europe/Rome",
+      "modbus_mapping": {{ "base_address": 0 }}
+    }}
+  ]
+}}"#));
+    let (http_port, modbus_port) = (sim.http_port, sim.modbus_port);
+
+    // Wait for the plant's first update, then read the catalog
+    let give_up = Instant::now() + GIVE_UP;
+    while last_update(http_port).is_none() {
+        assert!(Instant::now() < give_up, "plant_1 not publishing");
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    let (status, catalog) = http(http_port, "GET", "/api/faults/catalog", "").unwrap();
+    assert_eq!(status, 200);
+    assert!(catalog.contains(r#""name":"ISOLATION_FAULT""#), "{catalog}");
+
+    let inject = |body: &str| http(http_port, "POST", "/api/plants/plant_1/faults", body).unwrap();
+    let (status, body) = inject(r#"{"code":777,"duration_s":60}"#);
+    assert_eq!(status, 422);
+    assert!(body.contains("supported_codes"), "{body}");
+    assert_eq!(inject(r#"{"code":301,"duration_s":0}"#).0, 400);
+    assert_eq!(http(http_port, "POST", "/api/plants/nope/faults", r#"{"code":301,"duration_s":60}"#).unwrap().0, 404);
+
+    // An isolation fault trips the inverter
+    let (status, body) = inject(r#"{"code":301,"duration_s":120,"severity":"CRITICAL"}"#);
+    assert_eq!(status, 200, "{body}");
+    within_one_cycle(http_port, modbus_port, "injected fault", |(status, fault, flags)| {
+        status == 2 && fault == 301 && flags & (1 << 3) != 0
+    });
+    let (_, alarms) = http(http_port, "GET", "/api/plants/plant_1/alarms?active_only=true", "").unwrap();
+    assert!(alarms.contains(r#""code":301"#) && alarms.contains("CRITICAL"), "{alarms}");
+
+    // Withdrawn, it clears on the next cycle
+    assert_eq!(http(http_port, "DELETE", "/api/plants/plant_1/faults/301", "").unwrap().0, 200);
+    within_one_cycle(http_port, modbus_port, "withdrawn fault", |(status, fault, flags)| {
+        status != 2 && fault != 301 && flags & (1 << 3) == 0
+    });
+    assert_eq!(http(http_port, "DELETE", "/api/plants/plant_1/faults/301", "").unwrap().0, 404);
+}
@@ -0,0 +1,68 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::path::Path;
+use std::time::Duration;
+
+const CONFIG: &str = r#"{
+  "server":       { "port": 0, "bind_address": "127.0.0.1" },
+  "modbus":       { "port": 0, "bind_address": "127.0.0.1" },
+  "offline_mode": true,
+  "persistence":  { "path": "state.json", "interval_s": 1 },
+  "plants": [
+    {
+      "id": "plant_1",
+      "name": "Persistence Test",
+      "latitude": 45.07,
+      "longitude": 7.33,
+      "nominal_power_kw": 100.0,
+      "timezone": "Europe/Rome",
+      "modbus_mapping": { "base_address": 0 }
+    }
+  ]
+}"#;
+
+/// Start the simulator in `dir`, let it run for a while, stop it with SIGTERM
+/// and return the snapshot it left behind.
+fn run_until_sigterm(dir: &Path) -> serde_json::Value {
+    let mut child = common::spawn(dir);
+
+    std::thread::sleep(Duration::from_millis(2500));
+    assert!(child.try_wait().unwrap().is_none(), "simulator exited before SIGTERM");
+    let status = common::sigterm(&mut child);
+    assert!(status.success(), "simulator exited with {status}");
+
+    let snapshot = std::fs::read_to_string(dir.join("state.json")).expect("snapshot saved on shutdown");
+    serde_json::from_str(&snapshot).expect("snapshot is valid JSON")
+}
+
+#[test]
+fn lifetime_energy_survives_a_restart() {
+    let dir = common::temp_dir("persistence");
+    std::fs::write(dir.join("config.json"), CONFIG).unwrap();
+
+    // A previous run left a plant with 12.3 MWh on its lifetime counter
+    std::fs::write(dir.join("state.json"), r#"{ "plants": { "plant_1": { "total_energy_kwh": 12345.0 } } }"#).unwrap();
+    let first = run_until_sigterm(&dir);
+    let total = first["plants"]["plant_1"]["total_energy_kwh"].as_f64().unwrap();
+    assert!(total >= 12345.0, "lifetime energy went back to {total} kWh");
+
+    // And carries on through the next restart
+    let second = run_until_sigterm(&dir);
+    assert!(second["plants"]["plant_1"]["total_energy_kwh"].as_f64().unwrap() >= total);
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
+
+#[test]
+fn a_corrupt_snapshot_does_not_block_startup() {
+    let dir = common::temp_dir("corrupt");
+    std::fs::write(dir.join("config.json"), CONFIG).unwrap();
+    std::fs::write(dir.join("state.json"), "{ \"plants\": { \"plant_1\": ").unwrap();
+
+    let snapshot = run_until_sigterm(&dir);
+    assert!(snapshot["plants"]["plant_1"]["total_energy_kwh"].is_number());
+
+    let _ = std::fs::remove_dir_all(&dir);
+}
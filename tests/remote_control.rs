@@ -0,0 +1,99 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+use common::{http, Simulator};
+
+const COIL_INVERTER_ENABLE: u16 = 0;
+const REG_POWER_LIMIT_PCT: u16  = 80;
+
+/// One Modbus TCP request to unit 1; returns the data bytes of the reply.
+fn modbus(port: u16, function: u8, address: u16, value: u16) -> Vec<u8> {
+    let mut stream = TcpStream::connect(("127.0.0.1", port)).expect("Modbus server listening");
+    stream.set_read_timeout(Some(Duration::from_secs(5))).unwrap();
+    let [ah, al] = address.to_be_bytes();
+    let [vh, vl] = value.to_be_bytes();
+    stream.write_all(&[0, 1, 0, 0, 0, 6, 1, function, ah, al, vh, vl]).unwrap();
+    // MBAP header and function code
+    let mut header = [0u8; 8];
+    stream.read_exact(&mut header).unwrap();
+    assert_eq!(header[7], function, "Modbus exception {:?}", header);
+    let mut data = vec![0u8; u16::from_be_bytes([header[4], header[5]]) as usize - 2];
+    stream.read_exact(&mut data).unwrap();
+    data
+}
+
+/// Active power limit as a Modbus master reads it (FC 0x03).
+fn power_limit(port: u16) -> u16 {
+    let data = modbus(port, 0x03, REG_POWER_LIMIT_PCT, 1);
+    u16::from_be_bytes([data[1], data[2]])
+}
+
+/// Inverter enable coil as a Modbus master reads it (FC 0x01).
+fn enabled(port: u16) -> bool {
+    modbus(port, 0x01, COIL_INVERTER_ENABLE, 1)[1] & 1 == 1
+}
+
+#[test]
+fn rest_commands_show_up_on_modbus_and_the_other_way_round() {
+    let sim = Simulator::start("control", |http_port, modbus_port| format!(r#"{{
+  "server":       {{ "port": {http_port}, "bind_address": "127.0.0.1" }},
+  "modbus":       {{ "port": {modbus_port}, "bind_address": "127.0.0.1" }},
+  "offline_mode": true,
+  "plants": [
+    {{
+      "id": "plant_1",
+      "name": "Remote Control Test",
+      "latitude": 45.07,
+      "longitude": 7.33,
+      "nominal_power_kw": 100.0,
+      "timezone": "Europe/Rome",
+      "modbus_mapping": {{ "base_address": 0 }}
+    }}
+  ]
+}}"#));
+    let (http_port, modbus_port) = (sim.http_port, sim.modbus_port);
+
+    // Wait for the plant's first update
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        match http(http_port, "GET", "/api/plants/plant_1/power", "") {
+            Ok((200, _)) => break,
+            _ if Instant::now() > deadline => panic!("plant_1 not publishing"),
+            _ => std::thread::sleep(Duration::from_millis(100)),
+        }
+    }
+    let control = |body: &str| http(http_port, "POST", "/api/plants/plant_1/control", body).unwrap();
+
+    // Invalid commands change nothing
+    let (status, body) = control(r#"{"action":"jump"}"#);
+    assert_eq!(status, 422);
+    assert!(body.contains("supported_actions"), "{body}");
+    assert_eq!(control(r#"{"action":"limit","percent":120}"#).0, 422);
+    assert_eq!(control(r#"{"action":"limit"}"#).0, 422);
+    assert_eq!(http(http_port, "POST", "/api/plants/nope/control", r#"{"action":"stop"}"#).unwrap().0, 404);
+    assert_eq!(power_limit(modbus_port), 100);
+
+    // A REST limit is what SCADA reads back
+    let (status, body) = control(r#"{"action":"limit","percent":40}"#);
+    assert_eq!(status, 200, "{body}");
+    assert!(body.contains(r#""power_limit_pct":40.0"#), "{body}");
+    assert_eq!(power_limit(modbus_port), 40);
+
+    // A REST stop clears the enable coil; a Modbus start shows on REST
+    let (_, body) = control(r#"{"action":"stop"}"#);
+    assert!(body.contains(r#""inverter_enabled":false"#) && body.contains(r#""status_label":"STOPPED""#), "{body}");
+    assert!(!enabled(modbus_port));
+    modbus(modbus_port, 0x05, COIL_INVERTER_ENABLE, 0xFF00);
+    let (_, body) = control(r#"{"action":"reset_faults"}"#);
+    assert!(body.contains(r#""inverter_enabled":true"#), "{body}");
+
+    // Each command is logged with the interface it came from
+    let (_, events) = http(http_port, "GET", "/api/events?plant_id=plant_1", "").unwrap();
+    assert!(events.contains("CURTAILMENT_START") && events.contains(r#""interface":"rest""#), "{events}");
+    assert!(events.contains(r#""interface":"modbus""#), "{events}");
+}
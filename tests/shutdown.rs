@@ -0,0 +1,39 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::time::Duration;
+
+const CONFIG: &str = r#"{
+  "server":       { "port": 0, "bind_address": "127.0.0.1" },
+  "modbus":       { "port": 0, "bind_address": "127.0.0.1" },
+  "offline_mode": true,
+  "plants": [
+    {
+      "id": "plant_1",
+      "name": "Shutdown Test",
+      "latitude": 45.07,
+      "longitude": 7.33,
+      "nominal_power_kw": 100.0,
+      "timezone": "Europe/Rome",
+      "modbus_mapping": { "base_address": 0 }
+    }
+  ]
+}"#;
+
+#[test]
+fn sigterm_exits_cleanly() {
+    let dir = common::temp_dir("shutdown");
+    std::fs::write(dir.join("config.json"), CONFIG).unwrap();
+
+    let mut child = common::spawn(&dir);
+
+    // Let the servers bind and the plant tasks run at least once
+    std::thread::sleep(Duration::from_millis(1500));
+    assert!(child.try_wait().unwrap().is_none(), "simulator exited before SIGTERM");
+
+    let status = common::sigterm(&mut child);
+
+    let _ = std::fs::remove_dir_all(&dir);
+    assert!(status.success(), "simulator exited with {status}");
+}
@@ -0,0 +1,107 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use serde_json::{json, Value};
+
+use common::Simulator;
+
+/// One parsed `text/event-stream` event.
+#[derive(Debug)]
+struct SseEvent {
+    id:    u64,
+    event: String,
+    data:  Value,
+}
+
+/// Read events off a streaming response until `count` have arrived.
+async fn read_events(response: &mut reqwest::Response, count: usize) -> Vec<SseEvent> {
+    let mut buffer = String::new();
+    let mut events = Vec::new();
+    while events.len() < count {
+        let chunk = tokio::time::timeout(Duration::from_secs(10), response.chunk()).await
+            .expect("an event within 10 s")
+            .unwrap()
+            .expect("stream open");
+        buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+        while let Some(end) = buffer.find("\n\n") {
+            let block: String = buffer.drain(..end + 2).collect();
+            let field = |name: &str| block.lines()
+                .find_map(|line| line.strip_prefix(name).and_then(|l| l.strip_prefix(": ")))
+                .map(str::to_string);
+            // Heartbeats are comment lines only
+            let Some(data) = field("data") else { continue };
+            events.push(SseEvent {
+                id:    field("id").unwrap().parse().unwrap(),
+                event: field("event").unwrap(),
+                data:  serde_json::from_str(&data).unwrap(),
+            });
+        }
+    }
+    events
+}
+
+#[tokio::test]
+async fn the_event_stream_filters_resumes_and_is_counted() {
+    let plant = |id: &str, base: u16| json!({
+        "id": id, "name": id, "latitude": 45.07, "longitude": 7.33, "nominal_power_kw": 100.0,
+        "timezone": "Europe/Rome", "modbus_mapping": { "base_address": base }
+    });
+    let sim = Simulator::start("sse", |http_port, modbus_port| json!({
+        "server":       { "port": http_port, "bind_address": "127.0.0.1" },
+        "modbus":       { "port": modbus_port, "bind_address": "127.0.0.1" },
+        "offline_mode": true,
+        "plants":       [plant("plant_1", 0), plant("plant_2", 100)],
+    }).to_string());
+    let http_port = sim.http_port;
+    let base = format!("http://127.0.0.1:{http_port}");
+    let client = reqwest::Client::new();
+    let open = |query: &str, last_id: Option<&str>| {
+        let mut request = client.get(format!("{base}/api/stream/telemetry{query}"));
+        if let Some(last_id) = last_id {
+            request = request.header("Last-Event-ID", last_id);
+        }
+        request.send()
+    };
+
+    let mut stream = open("?interval_s=1&plants=plant_1&fields=power_kw,status", None).await.unwrap();
+    assert_eq!(stream.status(), 200);
+    assert!(stream.headers()["content-type"].to_str().unwrap().starts_with("text/event-stream"));
+
+    // One plant, two fields, every second, ids counting up from 1
+    let started = Instant::now();
+    let events = read_events(&mut stream, 3).await;
+    assert!(started.elapsed() < Duration::from_secs(4), "{:?}", started.elapsed());
+    assert_eq!(events.iter().map(|e| e.id).collect::<Vec<_>>(), [1, 2, 3]);
+    for event in &events {
+        assert_eq!(event.event, "telemetry");
+        assert_eq!(event.data["type"], "telemetry");
+        let plants = event.data["plants"].as_object().unwrap();
+        assert!(plants.keys().all(|id| id == "plant_1"), "{:?}", event.data);
+        for fields in plants.values() {
+            let mut fields: Vec<_> = fields.as_object().unwrap().keys().cloned().collect();
+            fields.sort();
+            assert_eq!(fields, ["power_kw", "status"]);
+        }
+    }
+
+    // A reconnecting client carries on after its last id
+    let mut resumed = open("", Some("41")).await.unwrap();
+    assert_eq!(read_events(&mut resumed, 1).await[0].id, 42);
+
+    let metrics = client.get(format!("{base}/metrics")).send().await.unwrap().text().await.unwrap();
+    assert!(metrics.contains("sse_connected_clients 2"), "{metrics}");
+    drop(stream);
+    drop(resumed);
+    // The server notices on its next event
+    tokio::time::sleep(Duration::from_secs(3)).await;
+    let metrics = client.get(format!("{base}/metrics")).send().await.unwrap().text().await.unwrap();
+    assert!(metrics.contains("sse_connected_clients 0"), "{metrics}");
+    assert!(metrics.contains("sse_connections_total 2"), "{metrics}");
+
+    for rejected in ["?plants=nope", "?fields=warp_factor", "?interval_s=0"] {
+        assert_eq!(open(rejected, None).await.unwrap().status(), 400, "{rejected}");
+    }
+}
@@ -0,0 +1,64 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+
+use common::{http, Simulator};
+
+/// Upper bound for the watch, so a stuck simulator fails instead of hanging.
+const GIVE_UP: Duration = Duration::from_secs(30);
+
+/// Last update time and update interval of a plant, once it has published.
+fn freshness(port: u16, plant_id: &str) -> Option<(u64, f64)> {
+    let (status, body) = http(port, "GET", &format!("/api/plants/{plant_id}/power"), "").ok()?;
+    if status != 200 {
+        return None;
+    }
+    let json: serde_json::Value = serde_json::from_str(&body).expect("status is valid JSON");
+    let data = &json["data"];
+    Some((data["last_update_unix"].as_u64()?, data["update_interval_s"].as_f64()?))
+}
+
+#[test]
+fn plants_update_on_their_own_interval() {
+    let plants = [("slow", 30.0), ("normal", 5.0), ("fast", 1.0)];
+    let plant_json: Vec<String> = plants.iter().enumerate().map(|(i, (id, interval_s))| format!(r#"{{
+      "id": "{id}",
+      "name": "Update Interval Test",
+      "latitude": 45.07,
+      "longitude": 7.33,
+      "nominal_power_kw": 100.0,
+      "timezone": "Europe/Rome",
+      "update_interval_s": {interval_s},
+      "modbus_mapping": {{ "base_address": {} }}
+    }}"#, i * 100)).collect();
+    let sim = Simulator::start("intervals", |http_port, modbus_port| format!(r#"{{
+  "server":       {{ "port": {http_port}, "bind_address": "127.0.0.1" }},
+  "modbus":       {{ "port": {modbus_port}, "bind_address": "127.0.0.1" }},
+  "offline_mode": true,
+  "plants": [{}]
+}}"#, plant_json.join(",")));
+    let http_port = sim.http_port;
+
+    // Every distinct update time seen is one update of that plant. Watch until
+    // the 5 s plant has updated three times, i.e. for two of its intervals.
+    let mut seen: Vec<HashSet<u64>> = vec![HashSet::new(); plants.len()];
+    let give_up = Instant::now() + GIVE_UP;
+    while seen[1].len() < 3 {
+        assert!(Instant::now() < give_up, "5 s plant updated {} times in {:?}", seen[1].len(), GIVE_UP);
+        for ((id, interval_s), updates) in plants.iter().zip(seen.iter_mut()) {
+            if let Some((at, reported_s)) = freshness(http_port, id) {
+                assert_eq!(reported_s, *interval_s, "{id}");
+                updates.insert(at);
+            }
+        }
+        std::thread::sleep(Duration::from_millis(200));
+    }
+
+    // Over those 10 s the 30 s plant updated once and the 1 s plant about ten times
+    let counts: Vec<usize> = seen.iter().map(HashSet::len).collect();
+    assert_eq!(counts[0], 1, "30 s plant updated {} times", counts[0]);
+    assert!(counts[2] >= 8, "1 s plant updated {} times", counts[2]);
+}
@@ -0,0 +1,189 @@
+#![cfg(unix)]
+
+mod common;
+
+use std::time::{Duration, Instant};
+
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+use common::Simulator;
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// Start a simulator with two offline plants.
+fn start_simulator(name: &str) -> Simulator {
+    let plant = |id: &str, base: u16| json!({
+        "id": id, "name": id, "latitude": 45.07, "longitude": 7.33, "nominal_power_kw": 100.0,
+        "timezone": "Europe/Rome", "modbus_mapping": { "base_address": base }
+    });
+    Simulator::start(name, |http_port, modbus_port| json!({
+        "server":       { "port": http_port, "bind_address": "127.0.0.1" },
+        "modbus":       { "port": modbus_port, "bind_address": "127.0.0.1" },
+        "offline_mode": true,
+        "plants":       [plant("plant_1", 0), plant("plant_2", 100)],
+    }).to_string())
+}
+
+/// Open `path` once the HTTP server is up.
+async fn connect(port: u16, path: &str) -> Socket {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        match connect_async(format!("ws://127.0.0.1:{port}{path}")).await {
+            Ok((socket, _)) => return socket,
+            Err(e) if Instant::now() > deadline => panic!("WebSocket {path} not up: {e}"),
+            Err(_) => tokio::time::sleep(Duration::from_millis(100)).await,
+        }
+    }
+}
+
+/// Next frame of `kind` ("telemetry", "subscribed", "error", "alarm" or
+/// "event"), skipping the others.
+async fn next_frame(socket: &mut Socket, kind: &str) -> Value {
+    let deadline = Instant::now() + Duration::from_secs(10);
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        let msg = tokio::time::timeout(left, socket.next()).await
+            .unwrap_or_else(|_| panic!("no {kind} frame within 10 s"))
+            .expect("socket open")
+            .unwrap();
+        if let Message::Text(text) = msg {
+            let frame: Value = serde_json::from_str(text.as_str()).unwrap();
+            if frame["type"] == kind {
+                return frame;
+            }
+        }
+    }
+}
+
+/// Next telemetry frame carrying `plants` plants, once their first update is in.
+async fn telemetry_of(socket: &mut Socket, plants: usize) -> Value {
+    loop {
+        let frame = next_frame(socket, "telemetry").await;
+        if frame["plants"].as_object().unwrap().len() == plants {
+            return frame;
+        }
+    }
+}
+
+async fn subscribe(socket: &mut Socket, subscription: Value) {
+    let text = json!({ "subscribe": subscription }).to_string();
+    socket.send(Message::Text(text.into())).await.unwrap();
+}
+
+#[tokio::test]
+async fn a_subscription_trims_the_shared_stream_and_survives_errors() {
+    let sim = start_simulator("ws-subscribe");
+    let port = sim.http_port;
+    let mut socket = connect(port, "/ws/telemetry").await;
+    let frame = telemetry_of(&mut socket, 2).await;
+    assert!(frame["plants"]["plant_1"]["frequency_hz"].is_number(), "{frame}");
+
+    subscribe(&mut socket, json!({ "plants": ["plant_1"], "fields": ["power_kw", "status"], "interval_s": 1 })).await;
+    let ack = next_frame(&mut socket, "subscribed").await;
+    assert_eq!(ack["plants"], json!(["plant_1"]));
+    assert_eq!(ack["fields"], json!(["power_kw", "status"]));
+    assert_eq!(ack["interval_s"], 1);
+    let frame = next_frame(&mut socket, "telemetry").await;
+    let plants = frame["plants"].as_object().unwrap();
+    assert_eq!(plants.keys().collect::<Vec<_>>(), ["plant_1"]);
+    let mut fields: Vec<_> = plants["plant_1"].as_object().unwrap().keys().cloned().collect();
+    fields.sort();
+    assert_eq!(fields, ["power_kw", "status"]);
+
+    // Frames now come every second
+    let started = Instant::now();
+    next_frame(&mut socket, "telemetry").await;
+    next_frame(&mut socket, "telemetry").await;
+    assert!(started.elapsed() < Duration::from_millis(2500), "{:?}", started.elapsed());
+
+    // Rejected subscriptions answer with an error and keep the socket open
+    for rejected in [
+        json!({ "plants": ["nope"] }),
+        json!({ "fields": ["power_kw", "warp_factor"] }),
+        json!({ "interval_s": 0 }),
+    ] {
+        subscribe(&mut socket, rejected.clone()).await;
+        let error = next_frame(&mut socket, "error").await;
+        assert!(error["error"].is_string(), "{rejected}: {error}");
+    }
+    socket.send(Message::Text("hello".into())).await.unwrap();
+    next_frame(&mut socket, "error").await;
+    let frame = next_frame(&mut socket, "telemetry").await;
+    assert_eq!(frame["plants"].as_object().unwrap().len(), 1, "previous subscription kept: {frame}");
+
+    // Unset plants and fields mean all of them again
+    subscribe(&mut socket, json!({})).await;
+    let ack = next_frame(&mut socket, "subscribed").await;
+    assert_eq!((&ack["plants"], &ack["fields"], &ack["interval_s"]), (&Value::Null, &Value::Null, &json!(2)));
+    telemetry_of(&mut socket, 2).await;
+}
+
+#[tokio::test]
+async fn the_plant_socket_streams_one_plant_only() {
+    let sim = start_simulator("ws-plant");
+    let port = sim.http_port;
+    let mut socket = connect(port, "/ws/plants/plant_2").await;
+    let frame = telemetry_of(&mut socket, 1).await;
+    assert!(frame["plants"]["plant_2"]["power_kw"].is_number(), "{frame}");
+
+    // Fields narrow it further; another plant is refused
+    subscribe(&mut socket, json!({ "fields": ["power_kw"] })).await;
+    let ack = next_frame(&mut socket, "subscribed").await;
+    assert_eq!(ack["plants"], json!(["plant_2"]));
+    let frame = next_frame(&mut socket, "telemetry").await;
+    assert_eq!(frame["plants"]["plant_2"].as_object().unwrap().len(), 1, "{frame}");
+    subscribe(&mut socket, json!({ "plants": ["plant_1"] })).await;
+    next_frame(&mut socket, "error").await;
+    let frame = next_frame(&mut socket, "telemetry").await;
+    assert_eq!(frame["plants"].as_object().unwrap().keys().collect::<Vec<_>>(), ["plant_2"]);
+
+    // An unknown plant never upgrades
+    assert!(connect_async(format!("ws://127.0.0.1:{port}/ws/plants/nope")).await.is_err());
+}
+
+#[tokio::test]
+async fn an_injected_fault_is_pushed_within_a_second() {
+    let sim = start_simulator("ws-alarms");
+    let port = sim.http_port;
+    let mut socket = connect(port, "/ws/telemetry").await;
+    let mut quiet = connect(port, "/ws/telemetry").await;
+    telemetry_of(&mut socket, 2).await;
+    // The first client only follows plant_2, the second opts out of alarms and events
+    subscribe(&mut quiet, json!({ "alarms": false, "events": false })).await;
+    let ack = next_frame(&mut quiet, "subscribed").await;
+    assert_eq!((&ack["alarms"], &ack["events"]), (&json!(false), &json!(false)));
+    subscribe(&mut socket, json!({ "plants": ["plant_2"] })).await;
+    next_frame(&mut socket, "subscribed").await;
+
+    let inject = |plant: &'static str| async move {
+        let response = reqwest::Client::new()
+            .post(format!("http://127.0.0.1:{port}/api/plants/{plant}/faults"))
+            .json(&json!({ "code": 301, "duration_s": 120 }))
+            .send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        Instant::now()
+    };
+
+    // Nothing about plant_1 reaches a plant_2 subscriber
+    inject("plant_1").await;
+    let injected = inject("plant_2").await;
+    let event = next_frame(&mut socket, "event").await;
+    assert!(injected.elapsed() < Duration::from_secs(1), "{:?}", injected.elapsed());
+    assert_eq!((&event["plant_id"], &event["kind"]), (&json!("plant_2"), &json!("SETTING_CHANGED")));
+
+    // The alarm follows on the next update of the plant
+    let alarm = next_frame(&mut socket, "alarm").await;
+    assert_eq!((&alarm["plant_id"], &alarm["code"], &alarm["active"]), (&json!("plant_2"), &json!(301), &json!(true)));
+
+    // Meanwhile the opted-out client only got telemetry
+    let deadline = Instant::now() + Duration::from_secs(3);
+    while Instant::now() < deadline {
+        let Ok(Some(Ok(Message::Text(text)))) = tokio::time::timeout(Duration::from_secs(3), quiet.next()).await else { continue };
+        let frame: Value = serde_json::from_str(text.as_str()).unwrap();
+        assert_eq!(frame["type"], "telemetry", "{frame}");
+    }
+}